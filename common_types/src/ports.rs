@@ -7,6 +7,7 @@
 //! - Single source of truth
 
 use std::env;
+use std::ops::RangeInclusive;
 
 /// Port configuration for Phoenix Web UI
 pub struct PhoenixWebPort;
@@ -18,10 +19,118 @@ impl PhoenixWebPort {
     /// Environment variable name
     pub const ENV_VAR: &'static str = "PHOENIX_WEB_BIND";
 
+    /// Host override (takes precedence over the host in `PHOENIX_WEB_BIND`)
+    pub const HOST_ENV_VAR: &'static str = "PHOENIX_WEB_HOST";
+
+    /// Port override (takes precedence over the port in `PHOENIX_WEB_BIND`; 0 = OS-assigned)
+    pub const PORT_ENV_VAR: &'static str = "PHOENIX_WEB_PORT";
+
+    /// Ports to try when the configured one is taken, e.g. `8889-8899`
+    pub const FALLBACK_ENV_VAR: &'static str = "PHOENIX_WEB_PORT_FALLBACK";
+
     /// Get bind address from env or default
     pub fn bind() -> String {
         env::var(Self::ENV_VAR).unwrap_or_else(|_| Self::DEFAULT_BIND.to_string())
     }
+
+    /// Get host and port: `PHOENIX_WEB_BIND` (or the default), then the host/port overrides
+    pub fn host_port() -> (String, u16) {
+        let (mut host, mut port) = split_host_port(&Self::bind())
+            .or_else(|| split_host_port(Self::DEFAULT_BIND))
+            .expect("default bind address is valid");
+        if let Ok(h) = env::var(Self::HOST_ENV_VAR) {
+            if !h.trim().is_empty() {
+                host = h.trim().to_string();
+            }
+        }
+        if let Some(p) = env::var(Self::PORT_ENV_VAR)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+        {
+            port = p;
+        }
+        (host, port)
+    }
+
+    /// Get the fallback port range from env, if set and valid
+    pub fn fallback() -> Option<RangeInclusive<u16>> {
+        env::var(Self::FALLBACK_ENV_VAR)
+            .ok()
+            .and_then(|s| parse_port_range(&s))
+    }
+}
+
+/// Split `host:port` (IPv6 hosts in brackets, e.g. `[::1]:8888`) into host and port
+pub fn split_host_port(bind: &str) -> Option<(String, u16)> {
+    let (host, port) = bind.trim().rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// Parse `start-end` (or a single port) into an inclusive range
+pub fn parse_port_range(s: &str) -> Option<RangeInclusive<u16>> {
+    let s = s.trim();
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    (start <= end).then_some(start..=end)
+}
+
+/// gRPC listener of Phoenix Web (the switchboard service)
+pub struct PhoenixGrpcPort;
+
+impl PhoenixGrpcPort {
+    /// Default bind address
+    pub const DEFAULT_BIND: &'static str = "127.0.0.1:50051";
+
+    /// Environment variable name
+    pub const ENV_VAR: &'static str = "PHOENIX_GRPC_BIND";
+
+    /// Get bind address from env or default
+    pub fn bind() -> String {
+        env::var(Self::ENV_VAR).unwrap_or_else(|_| Self::DEFAULT_BIND.to_string())
+    }
+}
+
+/// Local IPC endpoint of Phoenix Web (Unix socket path / Windows named pipe name)
+pub struct PhoenixIpcEndpoint;
+
+impl PhoenixIpcEndpoint {
+    /// Socket / pipe file name
+    pub const NAME: &'static str = "pagi-sola-web";
+
+    /// Environment variable name
+    pub const ENV_VAR: &'static str = "PHOENIX_IPC_PATH";
+
+    /// Get endpoint from env or the per-user default (`$XDG_RUNTIME_DIR/pagi-sola-web.sock`,
+    /// `<temp dir>/pagi-sola-web-<user>/pagi-sola-web.sock`, or `\\.\pipe\pagi-sola-web`)
+    pub fn path() -> String {
+        env::var(Self::ENV_VAR).unwrap_or_else(|_| Self::default_path())
+    }
+
+    #[cfg(windows)]
+    fn default_path() -> String {
+        format!(r"\\.\pipe\{}", Self::NAME)
+    }
+
+    #[cfg(not(windows))]
+    fn default_path() -> String {
+        // Without a runtime dir, keep the socket in a per-user directory rather than directly in
+        // the shared temp dir; the server creates it with mode 0700.
+        let dir = env::var_os("XDG_RUNTIME_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| {
+                let user = env::var("USER")
+                    .or_else(|_| env::var("LOGNAME"))
+                    .unwrap_or_else(|_| "user".to_string());
+                env::temp_dir().join(format!("{}-{user}", Self::NAME))
+            });
+        dir.join(format!("{}.sock", Self::NAME))
+            .to_string_lossy()
+            .into_owned()
+    }
 }
 
 /// Port configuration for Vital Pulse Collector (Telemetrist)
@@ -117,7 +226,6 @@ impl FrontendDevPort {
 
 /// Validate that all configured ports are unique
 pub fn validate_ports() -> Result<(), String> {
-    let phoenix_web = PhoenixWebPort::bind();
     let vital_pulse = VitalPulseCollectorPort::bind();
     let pulse_dist = SynapticPulseDistributorPort::bind();
 
@@ -125,7 +233,8 @@ pub fn validate_ports() -> Result<(), String> {
     let extract_port = |bind: &str| -> Option<u16> { bind.split(':').next_back()?.parse().ok() };
 
     let ports: Vec<Option<u16>> = vec![
-        extract_port(&phoenix_web),
+        // 0 asks the OS for a free port, which cannot conflict
+        Some(PhoenixWebPort::host_port().1).filter(|&p| p != 0),
         extract_port(&vital_pulse),
         extract_port(&pulse_dist),
         Some(ChromeDevToolsPort::port()),
@@ -158,6 +267,19 @@ mod tests {
         assert_eq!(FrontendDevPort::DEFAULT_PORT, 3000);
     }
 
+    #[test]
+    fn test_split_host_port_and_range() {
+        assert_eq!(
+            split_host_port("127.0.0.1:8888"),
+            Some(("127.0.0.1".to_string(), 8888))
+        );
+        assert_eq!(split_host_port("[::1]:0"), Some(("::1".to_string(), 0)));
+        assert_eq!(split_host_port("localhost"), None);
+        assert_eq!(parse_port_range("8889-8899"), Some(8889..=8899));
+        assert_eq!(parse_port_range(" 9000 "), Some(9000..=9000));
+        assert_eq!(parse_port_range("9000-8000"), None);
+    }
+
     #[test]
     fn test_port_validation() {
         // Default ports should be unique
@@ -0,0 +1,82 @@
+//! Dimensional (valence/arousal) view of emotion estimates.
+//!
+//! Categorical labels flip between neighbours ("Sadness" / "Fear") even when the underlying
+//! signal barely moves. Mapping each label onto the circumplex model and blending by weight
+//! gives continuous values that charts, drift correlation, and automation thresholds can use
+//! directly.
+
+use serde::{Deserialize, Serialize};
+
+use crate::fusion::{FusionWeights, ModalityEstimate};
+use crate::{DetectedEmotion, EmotionalState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Affect {
+    /// Unpleasant (-1.0) to pleasant (1.0).
+    pub valence: f64,
+    /// Calm (0.0) to activated (1.0).
+    pub arousal: f64,
+}
+
+impl Affect {
+    pub const NEUTRAL: Affect = Affect {
+        valence: 0.0,
+        arousal: 0.3,
+    };
+
+    /// Circumplex anchor of an emotion at full intensity.
+    pub fn anchor(emotion: &DetectedEmotion) -> Self {
+        let (valence, arousal) = match emotion {
+            DetectedEmotion::Joy => (0.8, 0.65),
+            DetectedEmotion::Love => (0.85, 0.45),
+            DetectedEmotion::Surprise => (0.2, 0.85),
+            DetectedEmotion::Neutral => (Self::NEUTRAL.valence, Self::NEUTRAL.arousal),
+            DetectedEmotion::Sadness => (-0.7, 0.2),
+            DetectedEmotion::Fear => (-0.65, 0.85),
+            DetectedEmotion::Anger => (-0.7, 0.9),
+            DetectedEmotion::Disgust => (-0.6, 0.5),
+            DetectedEmotion::Jealousy => (-0.55, 0.7),
+        };
+        Self { valence, arousal }
+    }
+
+    /// Anchor of `emotion` pulled toward neutral as `intensity` drops.
+    pub fn of(emotion: &DetectedEmotion, intensity: f64) -> Self {
+        Self::NEUTRAL.lerp(Self::anchor(emotion), intensity.clamp(0.0, 1.0))
+    }
+
+    pub fn from_state(state: &EmotionalState) -> Self {
+        Self::of(&state.primary_emotion, state.intensity)
+    }
+
+    /// Weighted blend of every modality's estimate (not just the winning label).
+    pub fn blend(estimates: &[ModalityEstimate], weights: &FusionWeights) -> Self {
+        let mut total = 0.0;
+        let mut valence = 0.0;
+        let mut arousal = 0.0;
+        for e in estimates {
+            let w = weights.for_modality(e.modality) * e.confidence.clamp(0.0, 1.0);
+            if w <= 0.0 {
+                continue;
+            }
+            let a = Self::of(&e.emotion, e.confidence);
+            valence += w * a.valence;
+            arousal += w * a.arousal;
+            total += w;
+        }
+        if total <= 0.0 {
+            return Self::NEUTRAL;
+        }
+        Self {
+            valence: valence / total,
+            arousal: arousal / total,
+        }
+    }
+
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        Self {
+            valence: self.valence + (to.valence - self.valence) * t,
+            arousal: self.arousal + (to.arousal - self.arousal) * t,
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Pluggable emotion inference backends.
+//!
+//! [`EmotionDetector`](crate::EmotionDetector) delegates per-modality classification to an
+//! [`EmotionBackend`], so users can trade accuracy for CPU usage without touching fusion,
+//! calibration, or persistence.
+//!
+//! Selected with `EMOTION_BACKEND`:
+//! - `heuristic` (default): keyword/file-name heuristics, no model files, negligible CPU
+//! - `onnx` (feature `face-onnx-tract`): local FER+ model for faces (`EMOTION_ONNX_MODEL`);
+//!   voice and text stay heuristic
+//! - `remote` (feature `remote-api`): POSTs each input to `EMOTION_REMOTE_URL` (optional
+//!   `EMOTION_REMOTE_API_KEY`, `EMOTION_REMOTE_TIMEOUT_SECS`), falling back to the heuristic
+//!   backend when the service is unreachable
+//!
+//! An unknown or unavailable backend falls back to `heuristic` with a warning.
+
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::text::{self, TextEmotionEstimate, TextSource};
+use crate::{classify_text_heuristic, DetectedEmotion, ImageBuffer};
+
+/// One backend classification.
+#[derive(Debug, Clone)]
+pub struct BackendEstimate {
+    pub emotion: DetectedEmotion,
+    /// Model confidence, when the backend has one (heuristics don't; the detector then uses
+    /// its `sensitivity`).
+    pub confidence: Option<f64>,
+}
+
+#[async_trait]
+pub trait EmotionBackend: Send + Sync + std::fmt::Debug {
+    /// Short identifier (`"heuristic"`, `"onnx"`, `"remote"`).
+    fn name(&self) -> &'static str;
+
+    async fn classify_voice(&self, audio_path: &Path) -> Option<BackendEstimate>;
+
+    async fn classify_face(&self, frame: &ImageBuffer) -> Option<BackendEstimate>;
+
+    /// Defaults to the built-in lexicon analyzer.
+    async fn classify_text(&self, text: &str, source: TextSource) -> Option<TextEmotionEstimate> {
+        Some(text::analyze_text(text, source))
+    }
+}
+
+/// Built-in heuristics (the historical behaviour).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicBackend;
+
+#[async_trait]
+impl EmotionBackend for HeuristicBackend {
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+
+    async fn classify_voice(&self, audio_path: &Path) -> Option<BackendEstimate> {
+        // Allows quick testing by naming files like "sad.wav" / "love.wav".
+        let name = audio_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        classify_text_heuristic(&name).map(|emotion| BackendEstimate {
+            emotion,
+            confidence: None,
+        })
+    }
+
+    async fn classify_face(&self, _frame: &ImageBuffer) -> Option<BackendEstimate> {
+        None
+    }
+}
+
+/// Build the backend named by `EMOTION_BACKEND`.
+pub fn backend_from_env() -> Arc<dyn EmotionBackend> {
+    let name = std::env::var("EMOTION_BACKEND")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let built: Result<Arc<dyn EmotionBackend>, String> = match name.as_str() {
+        "" | "heuristic" => Ok(Arc::new(HeuristicBackend)),
+        "onnx" => onnx_from_env(),
+        "remote" => remote_from_env(),
+        other => Err(format!("unknown backend '{other}'")),
+    };
+    built.unwrap_or_else(|e| {
+        tracing::warn!(target: "emotion", "EMOTION_BACKEND: {e}; using heuristic backend");
+        Arc::new(HeuristicBackend)
+    })
+}
+
+#[cfg(feature = "face-onnx-tract")]
+fn onnx_from_env() -> Result<Arc<dyn EmotionBackend>, String> {
+    let path = std::env::var("EMOTION_ONNX_MODEL")
+        .map_err(|_| "EMOTION_ONNX_MODEL is not set".to_string())?;
+    Ok(Arc::new(onnx::OnnxBackend::load(Path::new(&path))?))
+}
+
+#[cfg(not(feature = "face-onnx-tract"))]
+fn onnx_from_env() -> Result<Arc<dyn EmotionBackend>, String> {
+    Err("built without the `face-onnx-tract` feature".to_string())
+}
+
+#[cfg(feature = "remote-api")]
+fn remote_from_env() -> Result<Arc<dyn EmotionBackend>, String> {
+    Ok(Arc::new(remote::RemoteBackend::from_env()?))
+}
+
+#[cfg(not(feature = "remote-api"))]
+fn remote_from_env() -> Result<Arc<dyn EmotionBackend>, String> {
+    Err("built without the `remote-api` feature".to_string())
+}
+
+#[cfg(feature = "face-onnx-tract")]
+pub mod onnx {
+    //! FER+ (8-class, 1x1x64x64 grayscale input) face model run through `tract`.
+
+    use super::*;
+    use tract_onnx::prelude::*;
+
+    /// FER+ output order.
+    const LABELS: [DetectedEmotion; 8] = [
+        DetectedEmotion::Neutral,
+        DetectedEmotion::Joy,
+        DetectedEmotion::Surprise,
+        DetectedEmotion::Sadness,
+        DetectedEmotion::Anger,
+        DetectedEmotion::Disgust,
+        DetectedEmotion::Fear,
+        // Contempt
+        DetectedEmotion::Disgust,
+    ];
+    const SIDE: u32 = 64;
+
+    #[derive(Debug)]
+    pub struct OnnxBackend {
+        model: TypedRunnableModel<TypedModel>,
+    }
+
+    impl OnnxBackend {
+        pub fn load(path: &Path) -> Result<Self, String> {
+            let model = tract_onnx::onnx()
+                .model_for_path(path)
+                .and_then(|m| {
+                    m.with_input_fact(0, f32::fact([1, 1, SIDE as usize, SIDE as usize]).into())
+                })
+                .and_then(|m| m.into_optimized())
+                .and_then(|m| m.into_runnable())
+                .map_err(|e| format!("failed to load {}: {e}", path.display()))?;
+            Ok(Self { model })
+        }
+
+        fn infer(&self, frame: &ImageBuffer) -> TractResult<BackendEstimate> {
+            let gray = image::imageops::grayscale(frame);
+            let gray =
+                image::imageops::resize(&gray, SIDE, SIDE, image::imageops::FilterType::Triangle);
+            let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+                (1, 1, SIDE as usize, SIDE as usize),
+                |(_, _, y, x)| gray.get_pixel(x as u32, y as u32)[0] as f32,
+            )
+            .into();
+            let out = self.model.run(tvec!(input.into()))?;
+            let logits = out[0].to_array_view::<f32>()?;
+            let max = logits.iter().copied().fold(f32::MIN, f32::max);
+            let exp: Vec<f32> = logits.iter().map(|v| (v - max).exp()).collect();
+            let sum: f32 = exp.iter().sum();
+            let (best, p) = exp
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+                .map(|(i, v)| (i, v / sum))
+                .unwrap_or((0, 0.0));
+            Ok(BackendEstimate {
+                emotion: LABELS
+                    .get(best)
+                    .cloned()
+                    .unwrap_or(DetectedEmotion::Neutral),
+                confidence: Some(p as f64),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl EmotionBackend for OnnxBackend {
+        fn name(&self) -> &'static str {
+            "onnx"
+        }
+
+        async fn classify_voice(&self, audio_path: &Path) -> Option<BackendEstimate> {
+            HeuristicBackend.classify_voice(audio_path).await
+        }
+
+        async fn classify_face(&self, frame: &ImageBuffer) -> Option<BackendEstimate> {
+            self.infer(frame)
+                .map_err(|e| tracing::warn!(target: "emotion", "onnx inference failed: {e}"))
+                .ok()
+        }
+    }
+}
+
+#[cfg(feature = "remote-api")]
+pub mod remote {
+    //! HTTP emotion service.
+    //!
+    //! Request: `POST <url>` with JSON `{"modality": "voice"|"face"|"text", ...}` carrying
+    //! `audio_base64`, `image_png_base64`, or `text`. Response: `{"emotion": "Joy",
+    //! "confidence": 0.82}` (emotion names as in [`DetectedEmotion`]).
+
+    use super::*;
+    use base64::Engine as _;
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[derive(Debug, Deserialize)]
+    struct RemoteResponse {
+        emotion: DetectedEmotion,
+        #[serde(default)]
+        confidence: Option<f64>,
+    }
+
+    #[derive(Debug)]
+    pub struct RemoteBackend {
+        url: String,
+        api_key: Option<String>,
+        client: reqwest::Client,
+    }
+
+    impl RemoteBackend {
+        pub fn from_env() -> Result<Self, String> {
+            let url = std::env::var("EMOTION_REMOTE_URL")
+                .ok()
+                .filter(|s| !s.trim().is_empty())
+                .ok_or_else(|| "EMOTION_REMOTE_URL is not set".to_string())?;
+            let timeout = std::env::var("EMOTION_REMOTE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(5);
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(timeout))
+                .build()
+                .map_err(|e| e.to_string())?;
+            Ok(Self {
+                url,
+                api_key: std::env::var("EMOTION_REMOTE_API_KEY").ok(),
+                client,
+            })
+        }
+
+        async fn post(&self, body: serde_json::Value) -> Option<RemoteResponse> {
+            let mut req = self.client.post(&self.url).json(&body);
+            if let Some(key) = self.api_key.as_deref() {
+                req = req.bearer_auth(key);
+            }
+            match req.send().await.and_then(|r| r.error_for_status()) {
+                Ok(resp) => resp.json::<RemoteResponse>().await.ok(),
+                Err(e) => {
+                    tracing::warn!(target: "emotion", "remote backend request failed: {e}");
+                    None
+                }
+            }
+        }
+    }
+
+    fn estimate(r: RemoteResponse) -> BackendEstimate {
+        BackendEstimate {
+            emotion: r.emotion,
+            confidence: r.confidence.map(|c| c.clamp(0.0, 1.0)),
+        }
+    }
+
+    #[async_trait]
+    impl EmotionBackend for RemoteBackend {
+        fn name(&self) -> &'static str {
+            "remote"
+        }
+
+        async fn classify_voice(&self, audio_path: &Path) -> Option<BackendEstimate> {
+            let Ok(bytes) = tokio::fs::read(audio_path).await else {
+                return HeuristicBackend.classify_voice(audio_path).await;
+            };
+            let body = json!({
+                "modality": "voice",
+                "audio_base64": base64::engine::general_purpose::STANDARD.encode(bytes),
+            });
+            match self.post(body).await {
+                Some(r) => Some(estimate(r)),
+                None => HeuristicBackend.classify_voice(audio_path).await,
+            }
+        }
+
+        async fn classify_face(&self, frame: &ImageBuffer) -> Option<BackendEstimate> {
+            let mut png = std::io::Cursor::new(Vec::new());
+            frame.write_to(&mut png, image::ImageFormat::Png).ok()?;
+            let body = json!({
+                "modality": "face",
+                "image_png_base64": base64::engine::general_purpose::STANDARD.encode(png.into_inner()),
+            });
+            self.post(body).await.map(estimate)
+        }
+
+        async fn classify_text(
+            &self,
+            text: &str,
+            source: TextSource,
+        ) -> Option<TextEmotionEstimate> {
+            let local = text::analyze_text(text, source);
+            let Some(r) = self.post(json!({ "modality": "text", "text": text })).await else {
+                return Some(local);
+            };
+            Some(TextEmotionEstimate {
+                primary_emotion: r.emotion,
+                confidence: r.confidence.unwrap_or(local.confidence).clamp(0.0, 1.0),
+                ..local
+            })
+        }
+    }
+}
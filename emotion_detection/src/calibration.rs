@@ -0,0 +1,261 @@
+//! Confidence calibration, an "uncertain" floor, and hysteresis for the reported emotion.
+//!
+//! Raw detector confidences are not comparable across emotions (the heuristics fire on "tired"
+//! far more readily than on "disgusted"), and a 20% guess should not be shown the same way as
+//! an 80% one. [`EmotionStabilizer`] turns the stream of fused states into what the UI should
+//! actually display:
+//! 1. scale the confidence by a per-emotion calibration factor,
+//! 2. report `"uncertain"` when the calibrated confidence is below the floor,
+//! 3. only switch labels after `confirm_updates` consecutive agreeing updates, unless the new
+//!    label beats the current one by `switch_margin` (so the status doesn't flap).
+//!
+//! Configuration (env): `EMOTION_CONFIDENCE_FLOOR` (default 0.35),
+//! `EMOTION_HYSTERESIS_CONFIRM` (default 2), `EMOTION_HYSTERESIS_MARGIN` (default 0.25),
+//! `EMOTION_CALIBRATION` (e.g. `Sadness=0.8,Disgust=1.2`), `EMOTION_AFFECT_SMOOTHING`
+//! (default 0.3; weight of each new valence/arousal sample in the moving average).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::affect::Affect;
+use crate::{DetectedEmotion, EmotionalState};
+
+/// Label reported when the calibrated confidence is below the floor.
+pub const UNCERTAIN_LABEL: &str = "uncertain";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationConfig {
+    /// Multiplier applied to the raw confidence, keyed by emotion name (`"Joy"`, ...).
+    /// Missing emotions use 1.0.
+    #[serde(default)]
+    pub per_emotion: HashMap<String, f64>,
+    pub confidence_floor: f64,
+    /// Consecutive updates a new label needs before it replaces the current one.
+    pub confirm_updates: u32,
+    /// A new label wins immediately if its confidence beats the current one by this much.
+    pub switch_margin: f64,
+    /// Exponential moving-average weight of each new valence/arousal sample (0 < x <= 1).
+    #[serde(default = "default_affect_smoothing")]
+    pub affect_smoothing: f64,
+}
+
+fn default_affect_smoothing() -> f64 {
+    0.3
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            per_emotion: HashMap::new(),
+            confidence_floor: 0.35,
+            confirm_updates: 2,
+            switch_margin: 0.25,
+            affect_smoothing: default_affect_smoothing(),
+        }
+    }
+}
+
+impl CalibrationConfig {
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        let per_emotion = std::env::var("EMOTION_CALIBRATION")
+            .ok()
+            .map(|s| {
+                s.split(',')
+                    .filter_map(|kv| {
+                        let (k, v) = kv.split_once('=')?;
+                        let v = v.trim().parse::<f64>().ok().filter(|v| *v >= 0.0)?;
+                        Some((k.trim().to_string(), v))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            per_emotion,
+            confidence_floor: std::env::var("EMOTION_CONFIDENCE_FLOOR")
+                .ok()
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .map(|v| v.clamp(0.0, 1.0))
+                .unwrap_or(d.confidence_floor),
+            confirm_updates: std::env::var("EMOTION_HYSTERESIS_CONFIRM")
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok())
+                .unwrap_or(d.confirm_updates)
+                .max(1),
+            switch_margin: std::env::var("EMOTION_HYSTERESIS_MARGIN")
+                .ok()
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .map(|v| v.clamp(0.0, 1.0))
+                .unwrap_or(d.switch_margin),
+            affect_smoothing: std::env::var("EMOTION_AFFECT_SMOOTHING")
+                .ok()
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .filter(|v| *v > 0.0 && *v <= 1.0)
+                .unwrap_or(d.affect_smoothing),
+        }
+    }
+
+    pub fn calibrate(&self, emotion: &DetectedEmotion, raw_confidence: f64) -> f64 {
+        let scale = self
+            .per_emotion
+            .get(&format!("{emotion:?}"))
+            .copied()
+            .unwrap_or(1.0);
+        (raw_confidence * scale).clamp(0.0, 1.0)
+    }
+}
+
+/// What the status/UI should show.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportedEmotion {
+    /// Emotion name, or [`UNCERTAIN_LABEL`].
+    pub label: String,
+    /// `None` while uncertain.
+    pub emotion: Option<DetectedEmotion>,
+    pub raw_confidence: f64,
+    pub calibrated_confidence: f64,
+    pub uncertain: bool,
+    /// When the current label was first reported.
+    pub since: DateTime<Utc>,
+    /// Smoothed valence (-1.0..=1.0); moves continuously even while the label holds.
+    pub valence: f64,
+    /// Smoothed arousal (0.0..=1.0).
+    pub arousal: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmotionStabilizer {
+    pub config: CalibrationConfig,
+    current: Option<ReportedEmotion>,
+    pending: Option<(String, u32)>,
+    affect: Option<Affect>,
+}
+
+impl Default for EmotionStabilizer {
+    fn default() -> Self {
+        Self::new(CalibrationConfig::from_env())
+    }
+}
+
+impl EmotionStabilizer {
+    pub fn new(config: CalibrationConfig) -> Self {
+        Self {
+            config,
+            current: None,
+            pending: None,
+            affect: None,
+        }
+    }
+
+    pub fn current(&self) -> Option<&ReportedEmotion> {
+        self.current.as_ref()
+    }
+
+    /// Feed a new fused state; returns the (possibly unchanged) reported emotion.
+    pub fn update(&mut self, state: &EmotionalState) -> ReportedEmotion {
+        self.update_with_affect(state, Affect::from_state(state))
+    }
+
+    /// Like [`update`](Self::update), with the valence/arousal measured for this state (e.g.
+    /// [`FusedEmotion::affect`](crate::fusion::FusedEmotion::affect)).
+    pub fn update_with_affect(
+        &mut self,
+        state: &EmotionalState,
+        affect: Affect,
+    ) -> ReportedEmotion {
+        let smoothed = match self.affect {
+            Some(prev) => prev.lerp(affect, self.config.affect_smoothing.clamp(0.0, 1.0)),
+            None => affect,
+        };
+        self.affect = Some(smoothed);
+        let calibrated = self
+            .config
+            .calibrate(&state.primary_emotion, state.confidence);
+        let uncertain = calibrated < self.config.confidence_floor;
+        let candidate = ReportedEmotion {
+            label: if uncertain {
+                UNCERTAIN_LABEL.to_string()
+            } else {
+                format!("{:?}", state.primary_emotion)
+            },
+            emotion: (!uncertain).then(|| state.primary_emotion.clone()),
+            raw_confidence: state.confidence,
+            calibrated_confidence: calibrated,
+            uncertain,
+            since: state.timestamp,
+            valence: smoothed.valence,
+            arousal: smoothed.arousal,
+        };
+
+        let Some(current) = self.current.as_mut() else {
+            self.pending = None;
+            self.current = Some(candidate.clone());
+            return candidate;
+        };
+
+        if candidate.label == current.label {
+            // Same label: refresh the numbers, keep `since`.
+            self.pending = None;
+            current.raw_confidence = candidate.raw_confidence;
+            current.calibrated_confidence = candidate.calibrated_confidence;
+            current.emotion = candidate.emotion;
+            current.valence = candidate.valence;
+            current.arousal = candidate.arousal;
+            return current.clone();
+        }
+
+        let count = match self.pending.as_mut() {
+            Some((label, n)) if *label == candidate.label => {
+                *n += 1;
+                *n
+            }
+            _ => {
+                self.pending = Some((candidate.label.clone(), 1));
+                1
+            }
+        };
+        let decisive = candidate.calibrated_confidence
+            >= current.calibrated_confidence + self.config.switch_margin;
+        if decisive || count >= self.config.confirm_updates {
+            self.pending = None;
+            *current = candidate;
+        } else {
+            current.valence = candidate.valence;
+            current.arousal = candidate.arousal;
+        }
+        current.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(e: DetectedEmotion, confidence: f64) -> EmotionalState {
+        EmotionalState {
+            primary_emotion: e,
+            intensity: confidence,
+            confidence,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn floor_and_hysteresis() {
+        let mut s = EmotionStabilizer::new(CalibrationConfig::default());
+        assert!(s.update(&state(DetectedEmotion::Joy, 0.2)).uncertain);
+        // A clearly stronger reading switches immediately (0.5 >= 0.2 + margin).
+        assert_eq!(s.update(&state(DetectedEmotion::Joy, 0.5)).label, "Joy");
+        // One Sadness reading that is only slightly stronger does not flip the label...
+        assert_eq!(s.update(&state(DetectedEmotion::Sadness, 0.6)).label, "Joy");
+        // ...but a second consecutive one does.
+        assert_eq!(
+            s.update(&state(DetectedEmotion::Sadness, 0.6)).label,
+            "Sadness"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Weighted fusion of per-modality emotion estimates.
+//!
+//! Voice, face, and text estimates arrive independently (a clip here, a webcam frame there).
+//! [`FusionWindow`] keeps the latest estimate per modality and fuses whichever ones fall inside
+//! the same time window, so the reported state reflects every modality that is currently
+//! observing the user instead of whichever reported last.
+//!
+//! Configuration (env):
+//! - `EMOTION_FUSION_WEIGHT_VOICE` / `_FACE` / `_TEXT` (defaults 0.4 / 0.3 / 0.3)
+//! - `EMOTION_FUSION_WINDOW_SECS` (default 10)
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::affect::Affect;
+use crate::{DetectedEmotion, EmotionalState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmotionModality {
+    Voice,
+    Face,
+    Text,
+}
+
+/// One modality's opinion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModalityEstimate {
+    pub modality: EmotionModality,
+    pub emotion: DetectedEmotion,
+    /// 0.0..=1.0
+    pub confidence: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FusionWeights {
+    pub voice: f64,
+    pub face: f64,
+    pub text: f64,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self {
+            voice: 0.4,
+            face: 0.3,
+            text: 0.3,
+        }
+    }
+}
+
+impl FusionWeights {
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        let w = |key: &str, default: f64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .unwrap_or(default)
+        };
+        Self {
+            voice: w("EMOTION_FUSION_WEIGHT_VOICE", d.voice),
+            face: w("EMOTION_FUSION_WEIGHT_FACE", d.face),
+            text: w("EMOTION_FUSION_WEIGHT_TEXT", d.text),
+        }
+    }
+
+    pub fn for_modality(&self, m: EmotionModality) -> f64 {
+        match m {
+            EmotionModality::Voice => self.voice,
+            EmotionModality::Face => self.face,
+            EmotionModality::Text => self.text,
+        }
+    }
+}
+
+/// Modalities that disagreed about the primary emotion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Disagreement {
+    pub estimates: Vec<(EmotionModality, DetectedEmotion)>,
+    /// Share of the total weight that went to emotions other than the winner (0.0..=1.0).
+    pub dissent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusedEmotion {
+    pub state: EmotionalState,
+    pub contributing: Vec<EmotionModality>,
+    pub disagreement: Option<Disagreement>,
+    /// Valence/arousal blended over every contributing estimate, including dissenting ones.
+    pub affect: Affect,
+}
+
+/// Fuse estimates with `weights`. Each modality contributes `weight * confidence` to its
+/// emotion; the emotion with the most support wins.
+pub fn fuse(estimates: &[ModalityEstimate], weights: &FusionWeights) -> FusedEmotion {
+    let mut support: HashMap<DetectedEmotion, f64> = HashMap::new();
+    let mut backing: HashMap<DetectedEmotion, f64> = HashMap::new();
+    let mut shares: HashMap<EmotionModality, f64> = HashMap::new();
+    let mut total_weight = 0.0;
+    for e in estimates {
+        let w = weights.for_modality(e.modality);
+        if w <= 0.0 {
+            continue;
+        }
+        *support.entry(e.emotion.clone()).or_insert(0.0) += w * e.confidence.clamp(0.0, 1.0);
+        *backing.entry(e.emotion.clone()).or_insert(0.0) += w;
+        *shares.entry(e.modality).or_insert(0.0) += w;
+        total_weight += w;
+    }
+
+    let timestamp = estimates
+        .iter()
+        .map(|e| e.timestamp)
+        .max()
+        .unwrap_or_else(Utc::now);
+    if total_weight <= 0.0 {
+        return FusedEmotion {
+            state: EmotionalState {
+                primary_emotion: DetectedEmotion::Neutral,
+                intensity: 0.5,
+                confidence: 0.0,
+                voice_contribution: 0.0,
+                face_contribution: 0.0,
+                text_contribution: 0.0,
+                timestamp,
+            },
+            contributing: Vec::new(),
+            disagreement: None,
+            affect: Affect::NEUTRAL,
+        };
+    }
+
+    let (primary, best) = support
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(e, s)| (e.clone(), *s))
+        .unwrap_or((DetectedEmotion::Neutral, 0.0));
+    let all_support: f64 = support.values().sum();
+
+    let mut contributing: Vec<EmotionModality> = shares.keys().copied().collect();
+    contributing.sort_by_key(|m| *m as u8);
+    let share = |m| shares.get(&m).copied().unwrap_or(0.0) / total_weight;
+
+    let disagreement = (support.len() > 1).then(|| Disagreement {
+        estimates: estimates
+            .iter()
+            .filter(|e| weights.for_modality(e.modality) > 0.0)
+            .map(|e| (e.modality, e.emotion.clone()))
+            .collect(),
+        dissent: if all_support > 0.0 {
+            1.0 - best / all_support
+        } else {
+            0.0
+        },
+    });
+
+    let winner_weight = backing.get(&primary).copied().unwrap_or(total_weight);
+    FusedEmotion {
+        state: EmotionalState {
+            // How strongly the agreeing modalities see it...
+            intensity: (best / winner_weight).clamp(0.0, 1.0),
+            // ...discounted by the weight of modalities that saw something else.
+            confidence: (best / total_weight).clamp(0.0, 1.0),
+            primary_emotion: primary,
+            voice_contribution: share(EmotionModality::Voice),
+            face_contribution: share(EmotionModality::Face),
+            text_contribution: share(EmotionModality::Text),
+            timestamp,
+        },
+        contributing,
+        disagreement,
+        affect: Affect::blend(estimates, weights),
+    }
+}
+
+/// Latest estimate per modality, fused over a sliding time window.
+#[derive(Debug, Clone)]
+pub struct FusionWindow {
+    pub weights: FusionWeights,
+    pub window: Duration,
+    latest: HashMap<EmotionModality, ModalityEstimate>,
+}
+
+impl Default for FusionWindow {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+impl FusionWindow {
+    pub fn new(weights: FusionWeights, window: Duration) -> Self {
+        Self {
+            weights,
+            window,
+            latest: HashMap::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let secs = std::env::var("EMOTION_FUSION_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+            .filter(|s| *s > 0)
+            .unwrap_or(10);
+        Self::new(FusionWeights::from_env(), Duration::seconds(secs))
+    }
+
+    /// Record new estimates and fuse everything still inside the window ending at the newest one.
+    pub fn push(&mut self, estimates: Vec<ModalityEstimate>) -> FusedEmotion {
+        for e in estimates {
+            self.latest.insert(e.modality, e);
+        }
+        let newest = self.latest.values().map(|e| e.timestamp).max();
+        if let Some(newest) = newest {
+            let window = self.window;
+            self.latest.retain(|_, e| newest - e.timestamp <= window);
+        }
+        let current: Vec<ModalityEstimate> = self.latest.values().cloned().collect();
+        fuse(&current, &self.weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn est(m: EmotionModality, e: DetectedEmotion, secs_ago: i64) -> ModalityEstimate {
+        ModalityEstimate {
+            modality: m,
+            emotion: e,
+            confidence: 1.0,
+            timestamp: Utc::now() - Duration::seconds(secs_ago),
+        }
+    }
+
+    #[test]
+    fn window_fuses_recent_modalities_and_reports_disagreement() {
+        let mut w = FusionWindow::new(FusionWeights::default(), Duration::seconds(10));
+        w.push(vec![est(EmotionModality::Face, DetectedEmotion::Joy, 3)]);
+        let fused = w.push(vec![est(
+            EmotionModality::Voice,
+            DetectedEmotion::Sadness,
+            0,
+        )]);
+
+        assert_eq!(fused.state.primary_emotion, DetectedEmotion::Sadness);
+        assert_eq!(
+            fused.contributing,
+            vec![EmotionModality::Voice, EmotionModality::Face]
+        );
+        let d = fused.disagreement.expect("face and voice disagree");
+        assert!((d.dissent - 0.3 / 0.7).abs() < 1e-9);
+
+        // A stale face estimate falls out of the window.
+        let fused = w.push(vec![est(
+            EmotionModality::Voice,
+            DetectedEmotion::Sadness,
+            -20,
+        )]);
+        assert_eq!(fused.contributing, vec![EmotionModality::Voice]);
+        assert!(fused.disagreement.is_none());
+    }
+}
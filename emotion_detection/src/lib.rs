@@ -3,16 +3,28 @@
 //! This crate defaults to a **heuristic** implementation so the Phoenix workspace compiles
 //! without heavyweight model runtimes.
 //!
+//! Inference is delegated to a pluggable [`backend::EmotionBackend`] selected with
+//! `EMOTION_BACKEND` (`heuristic`, `onnx`, `remote`); see [`backend`].
+//!
 //! Planned backends (feature-gated):
 //! - Text: `rust-bert` (sentiment/emotion classifier)
-//! - Face: `tract-onnx` + ONNX FER model
 //! - Voice: prosody features (pitch/energy) (backend TBD)
 
 use chrono::{DateTime, Utc};
 use image::RgbImage;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub mod affect;
+pub mod backend;
+pub mod calibration;
+pub mod fusion;
+pub mod text;
+
+use backend::EmotionBackend;
+use fusion::{EmotionModality, FusedEmotion, FusionWeights, ModalityEstimate};
+use text::{TextEmotionEstimate, TextSource};
 
 /// Video frame type for facial emotion recognition.
 pub type ImageBuffer = RgbImage;
@@ -49,8 +61,9 @@ pub struct EmotionDetector {
     pub voice_enabled: bool,
     pub face_enabled: bool,
     pub text_enabled: bool,
-    /// 0.5 default
+    /// 0.5 default; also the confidence reported for backends without a native one.
     pub sensitivity: f64,
+    backend: Arc<dyn EmotionBackend>,
 }
 
 impl Default for EmotionDetector {
@@ -79,31 +92,35 @@ impl EmotionDetector {
             face_enabled,
             text_enabled,
             sensitivity,
+            backend: backend::backend_from_env(),
         }
     }
 
+    /// Replace the inference backend (e.g. a test double or a preloaded model).
+    pub fn with_backend(mut self, backend: Arc<dyn EmotionBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
     pub async fn detect_from_audio(&self, audio_path: &Path) -> Option<DetectedEmotion> {
         if !self.voice_enabled {
             return None;
         }
-
-        // Heuristic stub:
-        // - allows quick testing by naming files like "sad.wav" / "love.wav".
-        let name = audio_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_ascii_lowercase();
-        classify_text_heuristic(&name)
+        self.backend
+            .classify_voice(audio_path)
+            .await
+            .map(|e| e.emotion)
     }
 
-    pub async fn detect_from_video_frame(&self, _frame: &ImageBuffer) -> Option<DetectedEmotion> {
+    pub async fn detect_from_video_frame(&self, frame: &ImageBuffer) -> Option<DetectedEmotion> {
         if !self.face_enabled {
             return None;
         }
-
-        // TODO(feature face-onnx-tract): run FER model against face crop(s).
-        None
+        self.backend.classify_face(frame).await.map(|e| e.emotion)
     }
 
     pub fn detect_from_text(&self, text: &str) -> Option<DetectedEmotion> {
@@ -113,60 +130,84 @@ impl EmotionDetector {
         classify_text_heuristic(text)
     }
 
+    /// Scored emotion estimate for longer text (journal entries, scripts, transcripts).
+    pub fn analyze_text(&self, text: &str, source: TextSource) -> Option<TextEmotionEstimate> {
+        if !self.text_enabled {
+            return None;
+        }
+        Some(text::analyze_text(text, source))
+    }
+
     pub async fn fused_emotional_state(
         &self,
         text: &str,
         audio: Option<PathBuf>,
         video_frame: Option<ImageBuffer>,
     ) -> EmotionalState {
-        let mut scores: HashMap<DetectedEmotion, f64> = HashMap::new();
+        let estimates = self.modality_estimates(text, audio, video_frame).await;
+        fusion::fuse(&estimates, &FusionWeights::from_env()).state
+    }
 
-        let mut text_contribution = 0.0;
-        let mut voice_contribution = 0.0;
-        let mut face_contribution = 0.0;
+    /// Like [`fused_emotional_state`](Self::fused_emotional_state), but also reports which
+    /// modalities contributed and whether they disagreed.
+    pub async fn fuse_with_report(
+        &self,
+        text: &str,
+        audio: Option<PathBuf>,
+        video_frame: Option<ImageBuffer>,
+        weights: &FusionWeights,
+    ) -> FusedEmotion {
+        let estimates = self.modality_estimates(text, audio, video_frame).await;
+        fusion::fuse(&estimates, weights)
+    }
 
-        // Text
-        if let Some(e) = self.detect_from_text(text) {
-            let w = 0.3;
-            *scores.entry(e).or_insert(0.0) += w;
-            text_contribution = w;
-        }
+    /// Run every enabled detector on the inputs that are present.
+    ///
+    /// Backends without a native confidence (the heuristics) report `sensitivity`.
+    pub async fn modality_estimates(
+        &self,
+        text: &str,
+        audio: Option<PathBuf>,
+        video_frame: Option<ImageBuffer>,
+    ) -> Vec<ModalityEstimate> {
+        let now = Utc::now();
+        let mut out = Vec::new();
 
-        // Voice
-        if let Some(path) = audio {
-            if let Some(e) = self.detect_from_audio(&path).await {
-                let w = 0.4;
-                *scores.entry(e).or_insert(0.0) += w;
-                voice_contribution = w;
+        if self.text_enabled && !text.trim().is_empty() {
+            if let Some(est) = self
+                .backend
+                .classify_text(text, TextSource::Transcript)
+                .await
+            {
+                out.push(ModalityEstimate {
+                    modality: EmotionModality::Text,
+                    emotion: est.primary_emotion,
+                    confidence: est.confidence,
+                    timestamp: now,
+                });
             }
         }
-
-        // Face
-        if let Some(frame) = video_frame {
-            if let Some(e) = self.detect_from_video_frame(&frame).await {
-                let w = 0.3;
-                *scores.entry(e).or_insert(0.0) += w;
-                face_contribution = w;
+        if let Some(path) = audio.filter(|_| self.voice_enabled) {
+            if let Some(est) = self.backend.classify_voice(&path).await {
+                out.push(ModalityEstimate {
+                    modality: EmotionModality::Voice,
+                    emotion: est.emotion,
+                    confidence: est.confidence.unwrap_or(self.sensitivity),
+                    timestamp: now,
+                });
             }
         }
-
-        let (primary, intensity) = scores
-            .iter()
-            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
-            .map(|(e, s)| (e.clone(), *s))
-            .unwrap_or((DetectedEmotion::Neutral, 0.5));
-
-        let confidence = (intensity * self.sensitivity).clamp(0.0, 1.0);
-
-        EmotionalState {
-            primary_emotion: primary,
-            intensity: intensity.clamp(0.0, 1.0),
-            confidence,
-            voice_contribution,
-            face_contribution,
-            text_contribution,
-            timestamp: Utc::now(),
+        if let Some(frame) = video_frame.filter(|_| self.face_enabled) {
+            if let Some(est) = self.backend.classify_face(&frame).await {
+                out.push(ModalityEstimate {
+                    modality: EmotionModality::Face,
+                    emotion: est.emotion,
+                    confidence: est.confidence.unwrap_or(self.sensitivity),
+                    timestamp: now,
+                });
+            }
         }
+        out
     }
 
     pub fn respond_to_emotion(&self, state: &EmotionalState) -> String {
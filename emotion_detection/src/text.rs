@@ -0,0 +1,287 @@
+//! Lexicon-based emotion estimation over free text (journal entries, ghost scripts,
+//! transcripts).
+//!
+//! Unlike [`classify_text_heuristic`](crate::EmotionDetector::detect_from_text), which returns the
+//! first keyword family that matches, this scores every emotion so callers get a confidence and
+//! the cues that drove it. Simple negation ("not happy") and intensifiers ("really angry") are
+//! handled; everything else is intentionally naive until a model-backed text backend lands.
+//!
+//! Deployments can add their own terms on top of the built-in lexicon with
+//! [`set_extra_terms`]; they take effect for the next analysis.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::{DetectedEmotion, EmotionalState};
+
+/// Where a piece of analyzed text came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSource {
+    Journal,
+    GhostScript,
+    Transcript,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextEmotionEstimate {
+    pub primary_emotion: DetectedEmotion,
+    /// 0.0..=1.0
+    pub intensity: f64,
+    /// 0.0..=1.0; grows with the number of agreeing cues.
+    pub confidence: f64,
+    /// Normalized score per emotion that had at least one cue.
+    pub scores: Vec<(DetectedEmotion, f64)>,
+    /// Lexicon entries that matched (negated ones are prefixed with `not `).
+    pub cues: Vec<String>,
+    pub source: TextSource,
+}
+
+impl TextEmotionEstimate {
+    /// Convert into the shared [`EmotionalState`] shape (text is the only contributor).
+    pub fn to_state(&self) -> EmotionalState {
+        EmotionalState {
+            primary_emotion: self.primary_emotion.clone(),
+            intensity: self.intensity,
+            confidence: self.confidence,
+            voice_contribution: 0.0,
+            face_contribution: 0.0,
+            text_contribution: 1.0,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+const LEXICON: &[(DetectedEmotion, &[&str])] = &[
+    (
+        DetectedEmotion::Joy,
+        &[
+            "happy",
+            "glad",
+            "joy",
+            "excited",
+            "grateful",
+            "thankful",
+            "relieved",
+            "proud",
+            "great",
+            "wonderful",
+            "yay",
+            "delighted",
+        ],
+    ),
+    (
+        DetectedEmotion::Sadness,
+        &[
+            "sad",
+            "cry",
+            "crying",
+            "hurt",
+            "lonely",
+            "alone",
+            "miss",
+            "grief",
+            "grieving",
+            "empty",
+            "hopeless",
+            "depressed",
+            "heartbroken",
+            "tired",
+        ],
+    ),
+    (
+        DetectedEmotion::Anger,
+        &[
+            "angry",
+            "mad",
+            "furious",
+            "pissed",
+            "annoyed",
+            "frustrated",
+            "resent",
+            "hate",
+            "unfair",
+            "always",
+            "never",
+        ],
+    ),
+    (
+        DetectedEmotion::Fear,
+        &[
+            "afraid",
+            "scared",
+            "panic",
+            "anxious",
+            "worried",
+            "nervous",
+            "terrified",
+            "overwhelmed",
+            "unsafe",
+        ],
+    ),
+    (
+        DetectedEmotion::Surprise,
+        &["surprised", "shocked", "wow", "unexpected", "suddenly"],
+    ),
+    (
+        DetectedEmotion::Disgust,
+        &["disgust", "disgusted", "gross", "sick of"],
+    ),
+    (
+        DetectedEmotion::Love,
+        &[
+            "love",
+            "sweetheart",
+            "darling",
+            "cherish",
+            "adore",
+            "care about",
+        ],
+    ),
+    (
+        DetectedEmotion::Jealousy,
+        &[
+            "jealous",
+            "jealousy",
+            "envious",
+            "envy",
+            "possessive",
+            "threatened by",
+        ],
+    ),
+];
+
+/// Terms scored alongside [`LEXICON`], lowercased, one list per emotion.
+static EXTRA_TERMS: RwLock<Vec<(DetectedEmotion, Vec<String>)>> = RwLock::new(Vec::new());
+
+/// Replace the extra lexicon terms. Terms are matched like the built-in ones: case-insensitive,
+/// whole words, multi-word terms as phrases.
+pub fn set_extra_terms(terms: Vec<(DetectedEmotion, Vec<String>)>) {
+    let terms = terms
+        .into_iter()
+        .map(|(emotion, list)| {
+            let list = list
+                .iter()
+                .map(|term| {
+                    term.split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .to_ascii_lowercase()
+                })
+                .filter(|term| !term.is_empty())
+                .collect();
+            (emotion, list)
+        })
+        .collect();
+    *EXTRA_TERMS.write().unwrap_or_else(|e| e.into_inner()) = terms;
+}
+
+const NEGATIONS: &[&str] = &[
+    "not", "no", "never", "don't", "dont", "isn't", "wasn't", "aren't",
+];
+const INTENSIFIERS: &[&str] = &["very", "really", "so", "extremely", "totally", "incredibly"];
+
+/// Score `text` against the emotion lexicon.
+pub fn analyze_text(text: &str, source: TextSource) -> TextEmotionEstimate {
+    let lower = text.to_ascii_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let extra = EXTRA_TERMS.read().unwrap_or_else(|e| e.into_inner());
+    let lexicon = LEXICON
+        .iter()
+        .map(|(emotion, terms)| (emotion, terms.to_vec()))
+        .chain(
+            extra
+                .iter()
+                .map(|(emotion, terms)| (emotion, terms.iter().map(String::as_str).collect())),
+        );
+
+    let mut raw: HashMap<DetectedEmotion, f64> = HashMap::new();
+    let mut cues = Vec::new();
+    for (emotion, terms) in lexicon {
+        for term in terms {
+            let term_words: Vec<&str> = term.split(' ').collect();
+            for start in 0..words.len() {
+                if words[start..].len() < term_words.len()
+                    || words[start..start + term_words.len()] != term_words[..]
+                {
+                    continue;
+                }
+                let prev = start.checked_sub(1).map(|i| words[i]);
+                let prev2 = start.checked_sub(2).map(|i| words[i]);
+                let negated = [prev, prev2]
+                    .into_iter()
+                    .flatten()
+                    .any(|w| NEGATIONS.contains(&w))
+                    // "never" is itself an anger cue ("you never listen"), not a negation of one.
+                    && term != "never";
+                if negated {
+                    cues.push(format!("not {term}"));
+                    continue;
+                }
+                let weight = if prev.is_some_and(|w| INTENSIFIERS.contains(&w)) {
+                    1.5
+                } else {
+                    1.0
+                };
+                *raw.entry(emotion.clone()).or_insert(0.0) += weight;
+                cues.push(term.to_string());
+            }
+        }
+    }
+
+    let total: f64 = raw.values().sum();
+    if total <= 0.0 {
+        return TextEmotionEstimate {
+            primary_emotion: DetectedEmotion::Neutral,
+            intensity: 0.0,
+            confidence: if words.is_empty() { 0.0 } else { 0.3 },
+            scores: Vec::new(),
+            cues,
+            source,
+        };
+    }
+
+    let mut scores: Vec<(DetectedEmotion, f64)> =
+        raw.into_iter().map(|(e, s)| (e, s / total)).collect();
+    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    let (primary, share) = scores[0].clone();
+
+    // Cue density: a couple of emotional words in a short message is a strong signal.
+    let density = (total / (words.len().max(1) as f64) * 8.0).min(1.0);
+    let evidence = 1.0 - (-total / 2.0).exp();
+    TextEmotionEstimate {
+        primary_emotion: primary,
+        intensity: density.max(0.2),
+        confidence: (share * evidence).clamp(0.0, 1.0),
+        scores,
+        cues,
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negation_and_intensifiers() {
+        let e = analyze_text(
+            "I'm not happy. I'm really angry and frustrated.",
+            TextSource::Journal,
+        );
+        assert_eq!(e.primary_emotion, DetectedEmotion::Anger);
+        assert!(e.cues.contains(&"not happy".to_string()));
+        assert!(e.confidence > 0.5);
+
+        let neutral = analyze_text("Picked up groceries after work.", TextSource::Transcript);
+        assert_eq!(neutral.primary_emotion, DetectedEmotion::Neutral);
+        assert!(neutral.scores.is_empty());
+    }
+}
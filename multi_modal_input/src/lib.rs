@@ -3,6 +3,8 @@
 //! This crate intentionally avoids any biometric identification (face/voice recognition).
 //! It only provides **capture plumbing** and leaves higher-level interpretation to callers.
 
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 use thiserror::Error;
 
 // Image types are only available when a feature that pulls in `image` is enabled.
@@ -72,15 +74,41 @@ pub struct VideoStream {
     _private: (),
 }
 
+/// Microphone input level (RMS of the latest chunk, `0.0..=1.0`), shared with the capture
+/// callback.
+#[derive(Debug, Clone, Default)]
+pub struct InputLevel(Arc<AtomicU32>);
+
+impl InputLevel {
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, level: f32) {
+        self.0
+            .store(level.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Record the RMS level of `samples`.
+    pub fn update(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        self.set(mean_square.sqrt());
+    }
+}
+
 /// Opaque handle to a running microphone capture backend.
 ///
 /// The stream remains active as long as this value is kept alive.
+#[non_exhaustive]
 pub struct AudioStream {
     #[cfg(feature = "audio")]
     pub stream: cpal::Stream,
 
-    #[cfg(not(feature = "audio"))]
-    _private: (),
+    /// Live input level, updated by the capture callback.
+    pub level: InputLevel,
 }
 
 /// Capture-only live input configuration.
@@ -170,14 +198,16 @@ impl LiveMultiModalInput {
                 eprintln!("[multi_modal_input] audio stream error: {err}");
             }
 
-            fn process_audio_chunk(_data: &[f32]) {
+            fn process_audio_chunk(data: &[f32], level: &InputLevel) {
                 // Capture-only crate: callers should provide their own downstream processing.
                 // (e.g. VAD / wake-word / emotion analysis in separate, explicitly opted-in crates.)
+                level.update(data);
             }
 
             fn build_stream<T>(
                 device: &cpal::Device,
                 config: &StreamConfig,
+                level: InputLevel,
             ) -> Result<cpal::Stream, LiveInputError>
             where
                 T: Sample,
@@ -190,7 +220,7 @@ impl LiveMultiModalInput {
                             for s in data {
                                 buf.push(s.to_f32());
                             }
-                            process_audio_chunk(&buf);
+                            process_audio_chunk(&buf, &level);
                         },
                         err_fn,
                         None,
@@ -199,10 +229,11 @@ impl LiveMultiModalInput {
                 Ok(stream)
             }
 
+            let level = InputLevel::default();
             let stream = match sample_format {
-                SampleFormat::F32 => build_stream::<f32>(&device, &config)?,
-                SampleFormat::I16 => build_stream::<i16>(&device, &config)?,
-                SampleFormat::U16 => build_stream::<u16>(&device, &config)?,
+                SampleFormat::F32 => build_stream::<f32>(&device, &config, level.clone())?,
+                SampleFormat::I16 => build_stream::<i16>(&device, &config, level.clone())?,
+                SampleFormat::U16 => build_stream::<u16>(&device, &config, level.clone())?,
                 other => {
                     return Err(LiveInputError::AudioBackend(format!(
                         "unsupported sample format: {other:?}"
@@ -214,7 +245,7 @@ impl LiveMultiModalInput {
                 .play()
                 .map_err(|e| LiveInputError::AudioBackend(e.to_string()))?;
 
-            Ok(AudioStream { stream })
+            Ok(AudioStream { stream, level })
         }
 
         #[cfg(not(feature = "audio"))]
@@ -0,0 +1,75 @@
+//! Short-lived confirmation tokens for destructive operations.
+//!
+//! A destructive call is split in two: a request step that reports what would be affected and
+//! issues a token, and the operation itself, which only runs when handed that token back before
+//! it expires. Tokens are single-use and scoped to one operation; requesting again replaces the
+//! previous token.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// How long a token stays valid.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Default)]
+pub struct ConfirmationTokens {
+    pending: Arc<Mutex<HashMap<&'static str, (String, Instant)>>>,
+}
+
+impl ConfirmationTokens {
+    /// Issue a token for `operation`, invalidating any earlier one.
+    pub fn issue(&self, operation: &'static str) -> String {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(
+                operation,
+                (token.clone(), Instant::now() + CONFIRMATION_TTL),
+            );
+        }
+        token
+    }
+
+    /// Consume the token for `operation`. Any attempt uses it up, so a wrong token means
+    /// requesting a new one.
+    pub fn redeem(&self, operation: &'static str, token: &str) -> Result<(), Error> {
+        let issued = self
+            .pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(operation));
+        match issued {
+            Some((expected, expires)) if expected == token.trim() && Instant::now() < expires => {
+                Ok(())
+            }
+            Some((expected, _)) if expected == token.trim() => Err(Error::ConfirmationRequired(
+                "the confirmation token has expired",
+            )),
+            _ => Err(Error::ConfirmationRequired(
+                "no matching confirmation token; request one first",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_single_use_and_scoped() {
+        let tokens = ConfirmationTokens::default();
+        let token = tokens.issue("clear_all");
+        assert!(tokens.redeem("other", &token).is_err());
+        assert!(tokens.redeem("clear_all", &token).is_ok());
+        assert!(tokens.redeem("clear_all", &token).is_err());
+
+        let stale = tokens.issue("clear_all");
+        let fresh = tokens.issue("clear_all");
+        assert!(tokens.redeem("clear_all", &stale).is_err());
+        // The failed attempt used up the fresh token too.
+        assert!(tokens.redeem("clear_all", &fresh).is_err());
+    }
+}
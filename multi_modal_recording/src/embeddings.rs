@@ -0,0 +1,385 @@
+//! Versioned biometric embedding storage.
+//!
+//! Every stored embedding records the model (name + version) that produced it. When the active
+//! voice/face backend changes, previously stored vectors are no longer comparable; the store
+//! detects this and can rebuild them from the enrollment samples it retains on disk.
+//!
+//! Layout (under the recorder's `models/` directory):
+//! - `embeddings/<modality>.json` — all embeddings for that modality
+//! - `embeddings/samples/<modality>/<profile>/` — retained enrollment samples
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// Bump when the stub embedding function changes in an incompatible way.
+const STUB_EMBEDDING_VERSION: u32 = 1;
+const EMBEDDING_DIMS: usize = 128;
+
+/// Biometric modality an embedding was computed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modality {
+    Voice,
+    Face,
+}
+
+impl Modality {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Modality::Voice => "voice",
+            Modality::Face => "face",
+        }
+    }
+}
+
+/// Identifies the model that produced an embedding.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingModel {
+    pub backend: String,
+    pub version: u32,
+    pub dims: usize,
+}
+
+impl EmbeddingModel {
+    /// The model currently compiled in for `modality`.
+    pub fn current(modality: Modality) -> Self {
+        let backend = match modality {
+            Modality::Voice => {
+                if cfg!(feature = "speech-vosk") {
+                    "vosk"
+                } else if cfg!(feature = "speech-whisper") {
+                    "whisper-rs"
+                } else {
+                    "stub"
+                }
+            }
+            Modality::Face => {
+                if cfg!(feature = "face-dlib") {
+                    "dlib-face-recognition"
+                } else if cfg!(feature = "face-rustface") {
+                    "rustface"
+                } else {
+                    "stub"
+                }
+            }
+        };
+        Self {
+            backend: backend.to_string(),
+            version: STUB_EMBEDDING_VERSION,
+            dims: EMBEDDING_DIMS,
+        }
+    }
+}
+
+/// One embedding vector for one retained enrollment sample.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredEmbedding {
+    pub profile: String,
+    pub modality: Modality,
+    pub model: EmbeddingModel,
+    pub vector: Vec<f32>,
+    /// Retained copy of the enrollment sample this vector was computed from.
+    pub sample: PathBuf,
+    pub created_unix: i64,
+}
+
+/// Result of comparing stored embeddings against the active model.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompatibilityReport {
+    pub modality: Modality,
+    pub current_model: EmbeddingModel,
+    pub total: usize,
+    pub stale: usize,
+    /// Profiles with at least one embedding from a different model.
+    pub stale_profiles: Vec<String>,
+}
+
+impl CompatibilityReport {
+    pub fn needs_migration(&self) -> bool {
+        self.stale > 0
+    }
+}
+
+/// Result of a re-embedding migration run.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub modality: Option<Modality>,
+    pub migrated: usize,
+    pub already_current: usize,
+    /// Retained samples that could not be read; those embeddings were dropped.
+    pub missing_samples: Vec<PathBuf>,
+}
+
+/// File-backed, versioned embedding store.
+#[derive(Clone, Debug)]
+pub struct EmbeddingStore {
+    root: PathBuf,
+}
+
+impl EmbeddingStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn index_path(&self, modality: Modality) -> PathBuf {
+        self.root.join(format!("{}.json", modality.as_str()))
+    }
+
+    fn samples_dir(&self, modality: Modality, profile: &str) -> PathBuf {
+        self.root
+            .join("samples")
+            .join(modality.as_str())
+            .join(sanitize_component(profile))
+    }
+
+    /// Load all embeddings for a modality (empty if none stored yet).
+    pub fn load(&self, modality: Modality) -> Result<Vec<StoredEmbedding>, Error> {
+        let path = self.index_path(modality);
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        let raw = crate::sealed::read(&path)?;
+        Ok(serde_json::from_slice(&raw)?)
+    }
+
+    pub(crate) fn save(
+        &self,
+        modality: Modality,
+        entries: &[StoredEmbedding],
+    ) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.root)?;
+        let path = self.index_path(modality);
+        let tmp = path.with_extension("json.tmp");
+        crate::sealed::write(&tmp, &serde_json::to_vec_pretty(entries)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Enroll `samples` for `profile`, replacing any embeddings that profile already had.
+    ///
+    /// Samples are copied into the store so embeddings can be rebuilt after a model change.
+    pub fn enroll(
+        &self,
+        profile: &str,
+        modality: Modality,
+        samples: &[PathBuf],
+    ) -> Result<Vec<StoredEmbedding>, Error> {
+        let model = EmbeddingModel::current(modality);
+        let dir = self.samples_dir(modality, profile);
+        std::fs::create_dir_all(&dir)?;
+
+        let mut fresh = Vec::with_capacity(samples.len());
+        for (idx, sample) in samples.iter().enumerate() {
+            let bytes = crate::sealed::read(sample)?;
+            let file_name = sample
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "sample".to_string());
+            let retained = dir.join(format!("{idx:03}-{file_name}"));
+            crate::sealed::write(&retained, &bytes)?;
+            fresh.push(StoredEmbedding {
+                profile: profile.to_string(),
+                modality,
+                vector: compute_embedding(&bytes, &model),
+                model: model.clone(),
+                sample: retained,
+                created_unix: Utc::now().timestamp(),
+            });
+        }
+
+        let mut entries = self.load(modality)?;
+        entries.retain(|e| e.profile != profile);
+        entries.extend(fresh.iter().cloned());
+        self.save(modality, &entries)?;
+        Ok(fresh)
+    }
+
+    /// Compare stored embeddings against the active model.
+    pub fn check_compatibility(&self, modality: Modality) -> Result<CompatibilityReport, Error> {
+        let current_model = EmbeddingModel::current(modality);
+        let entries = self.load(modality)?;
+        let mut stale_profiles: Vec<String> = entries
+            .iter()
+            .filter(|e| e.model != current_model)
+            .map(|e| e.profile.clone())
+            .collect();
+        stale_profiles.sort();
+        stale_profiles.dedup();
+        Ok(CompatibilityReport {
+            modality,
+            total: entries.len(),
+            stale: entries.iter().filter(|e| e.model != current_model).count(),
+            stale_profiles,
+            current_model,
+        })
+    }
+
+    /// Embeddings for `profile` that were produced by the active model.
+    pub fn current_for_profile(
+        &self,
+        profile: &str,
+        modality: Modality,
+    ) -> Result<Vec<StoredEmbedding>, Error> {
+        let model = EmbeddingModel::current(modality);
+        Ok(self
+            .load(modality)?
+            .into_iter()
+            .filter(|e| e.profile == profile && e.model == model)
+            .collect())
+    }
+
+    /// Rebuild every stale embedding from its retained enrollment sample.
+    pub fn migrate(&self, modality: Modality) -> Result<MigrationReport, Error> {
+        let model = EmbeddingModel::current(modality);
+        let mut report = MigrationReport {
+            modality: Some(modality),
+            ..Default::default()
+        };
+
+        let mut out = Vec::new();
+        for mut entry in self.load(modality)? {
+            if entry.model == model {
+                report.already_current += 1;
+                out.push(entry);
+                continue;
+            }
+            match crate::sealed::read(&entry.sample) {
+                Ok(bytes) => {
+                    entry.vector = compute_embedding(&bytes, &model);
+                    entry.model = model.clone();
+                    entry.created_unix = Utc::now().timestamp();
+                    report.migrated += 1;
+                    out.push(entry);
+                }
+                Err(_) => report.missing_samples.push(entry.sample.clone()),
+            }
+        }
+
+        self.save(modality, &out)?;
+        Ok(report)
+    }
+
+    /// Remove all embeddings and retained samples for `profile`.
+    pub fn remove_profile(&self, profile: &str) -> Result<usize, Error> {
+        let mut removed = 0usize;
+        for modality in [Modality::Voice, Modality::Face] {
+            let mut entries = self.load(modality)?;
+            let before = entries.len();
+            entries.retain(|e| e.profile != profile);
+            removed += before - entries.len();
+            if before != entries.len() {
+                self.save(modality, &entries)?;
+            }
+            let dir = self.samples_dir(modality, profile);
+            if dir.is_dir() {
+                std::fs::remove_dir_all(&dir)?;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Compute an embedding for raw sample bytes with the given model.
+///
+/// The stub backend derives a deterministic unit vector from the sample hash, salted with the
+/// model identity so vectors from different models never accidentally compare as equal.
+pub fn compute_embedding(bytes: &[u8], model: &EmbeddingModel) -> Vec<f32> {
+    let mut out = Vec::with_capacity(model.dims);
+    let mut counter = 0u32;
+    while out.len() < model.dims {
+        let mut hasher = Sha256::new();
+        hasher.update(model.backend.as_bytes());
+        hasher.update(model.version.to_le_bytes());
+        hasher.update(counter.to_le_bytes());
+        hasher.update(bytes);
+        for b in hasher.finalize() {
+            if out.len() == model.dims {
+                break;
+            }
+            out.push((b as f32 / 127.5) - 1.0);
+        }
+        counter += 1;
+    }
+    let norm = out.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        out.iter_mut().for_each(|v| *v /= norm);
+    }
+    out
+}
+
+/// Cosine similarity in `[-1, 1]`; returns 0 for mismatched or empty vectors.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let na = a.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let nb = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if na == 0.0 || nb == 0.0 {
+        return 0.0;
+    }
+    (dot / (na * nb)).clamp(-1.0, 1.0)
+}
+
+fn sanitize_component(s: &str) -> String {
+    let cleaned: String = s
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_rebuilds_stale_embeddings_from_retained_samples() {
+        let dir = std::env::temp_dir().join(format!("mmr-emb-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sample = dir.join("hello.wav");
+        std::fs::write(&sample, b"sample-bytes").unwrap();
+
+        let store = EmbeddingStore::new(dir.join("embeddings"));
+        store.enroll("Dad", Modality::Voice, &[sample]).unwrap();
+        assert!(!store
+            .check_compatibility(Modality::Voice)
+            .unwrap()
+            .needs_migration());
+
+        // Simulate an older model version on disk.
+        let mut entries = store.load(Modality::Voice).unwrap();
+        entries[0].model.version = 0;
+        store.save(Modality::Voice, &entries).unwrap();
+        let report = store.check_compatibility(Modality::Voice).unwrap();
+        assert_eq!(report.stale, 1);
+        assert_eq!(report.stale_profiles, vec!["Dad".to_string()]);
+
+        let migrated = store.migrate(Modality::Voice).unwrap();
+        assert_eq!(migrated.migrated, 1);
+        assert!(!store
+            .check_compatibility(Modality::Voice)
+            .unwrap()
+            .needs_migration());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
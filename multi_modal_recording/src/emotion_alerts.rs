@@ -0,0 +1,396 @@
+//! Sustained / recurring negative-emotion alerts.
+//!
+//! Every emotional moment the recorder logs is fed to an [`AlertEngine`]. An alert fires when
+//! a watched emotion (sadness and anger by default) stays above a confidence threshold for
+//! `sustained_minutes`, or when it starts a new episode for the `daily_recurrence`-th time in
+//! a day. Alerts are appended to the Soul-Vault `emotion_alerts` timeline and published on the
+//! recorder's alert channel; during quiet hours they are still logged but flagged
+//! `notify: false`. Profiles listed in `opted_out_profiles` never produce alerts.
+//!
+//! Configuration (env, overridable at runtime): `EMOTION_ALERT_EMOTIONS` (`Sadness,Anger`),
+//! `EMOTION_ALERT_MIN_CONFIDENCE` (0.6), `EMOTION_ALERT_SUSTAINED_MINUTES` (20),
+//! `EMOTION_ALERT_DAILY_RECURRENCE` (5), `EMOTION_ALERT_QUIET_HOURS` (e.g. `22-7`).
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::emotion_history::EmotionalMoment;
+
+/// Soul-Vault key holding the alert timeline (JSON lines, most recent last).
+pub const ALERT_TIMELINE_KEY: &str = "emotion_alerts";
+
+/// Soul-Vault key holding the persisted [`AlertRules`].
+pub const ALERT_RULES_KEY: &str = "emotion_alert_rules";
+
+/// Maximum number of alerts kept in the timeline.
+const ALERT_TIMELINE_MAX: usize = 200;
+
+/// A gap longer than this between two qualifying moments ends the episode.
+const EPISODE_GAP_SECS: i64 = 5 * 60;
+
+/// Local-time window (hours, `start..end`, wrapping past midnight) in which alerts are logged
+/// but not notified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl QuietHours {
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AlertRules {
+    pub enabled: bool,
+    /// Emotion names (`"Sadness"`, ...) to watch, case-insensitive.
+    pub emotions: Vec<String>,
+    pub min_confidence: f64,
+    /// Alert once an episode has lasted this long (0 disables the sustained rule).
+    pub sustained_minutes: u32,
+    /// Alert on the M-th episode of the same emotion in a day (0 disables the recurrence rule).
+    pub daily_recurrence: u32,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    /// Profile labels (see [`EmotionalMoment::profile_label`]) that never get alerts.
+    #[serde(default)]
+    pub opted_out_profiles: Vec<String>,
+}
+
+impl Default for AlertRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            emotions: vec!["Sadness".to_string(), "Anger".to_string()],
+            min_confidence: 0.6,
+            sustained_minutes: 20,
+            daily_recurrence: 5,
+            quiet_hours: None,
+            opted_out_profiles: Vec::new(),
+        }
+    }
+}
+
+impl AlertRules {
+    pub fn from_env() -> Self {
+        let d = Self::default();
+        let parse_u32 = |key: &str, default: u32| {
+            std::env::var(key)
+                .ok()
+                .and_then(|s| s.trim().parse::<u32>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            enabled: std::env::var("EMOTION_ALERTS_ENABLED")
+                .ok()
+                .and_then(|s| s.trim().parse::<bool>().ok())
+                .unwrap_or(d.enabled),
+            emotions: std::env::var("EMOTION_ALERT_EMOTIONS")
+                .ok()
+                .map(|s| {
+                    s.split(',')
+                        .map(|e| e.trim().to_string())
+                        .filter(|e| !e.is_empty())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|v| !v.is_empty())
+                .unwrap_or(d.emotions),
+            min_confidence: std::env::var("EMOTION_ALERT_MIN_CONFIDENCE")
+                .ok()
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .map(|v| v.clamp(0.0, 1.0))
+                .unwrap_or(d.min_confidence),
+            sustained_minutes: parse_u32("EMOTION_ALERT_SUSTAINED_MINUTES", d.sustained_minutes),
+            daily_recurrence: parse_u32("EMOTION_ALERT_DAILY_RECURRENCE", d.daily_recurrence),
+            quiet_hours: std::env::var("EMOTION_ALERT_QUIET_HOURS")
+                .ok()
+                .and_then(|s| {
+                    let (a, b) = s.split_once('-')?;
+                    Some(QuietHours {
+                        start_hour: a.trim().parse::<u32>().ok().filter(|h| *h < 24)?,
+                        end_hour: b.trim().parse::<u32>().ok().filter(|h| *h < 24)?,
+                    })
+                }),
+            opted_out_profiles: Vec::new(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err("min_confidence must be within 0..=1".to_string());
+        }
+        if let Some(q) = self.quiet_hours {
+            if q.start_hour > 23 || q.end_hour > 23 {
+                return Err("quiet hours must be within 0..=23".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn watches(&self, emotion: &str) -> bool {
+        self.emotions
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(emotion))
+    }
+
+    fn opted_out(&self, profile: &str) -> bool {
+        self.opted_out_profiles
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(profile))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertTrigger {
+    /// The emotion persisted for `minutes`.
+    Sustained { minutes: u32 },
+    /// The emotion started its `count`-th episode today.
+    Recurring { count: u32 },
+}
+
+/// Structured alert record (logged and published).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionAlert {
+    pub id: String,
+    pub ts_unix: i64,
+    pub profile: String,
+    pub emotion: String,
+    pub confidence: f64,
+    pub trigger: AlertTrigger,
+    /// Recording (or text reference) of the moment that triggered the alert.
+    pub recording: String,
+    /// False during quiet hours: the alert is logged but no notification should be shown.
+    pub notify: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Episode {
+    started: i64,
+    last: i64,
+    sustained_fired: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct DailyCount {
+    day: Option<NaiveDate>,
+    episodes: u32,
+    recurrence_fired: bool,
+}
+
+/// Tracks episodes per `(profile, emotion)` and evaluates [`AlertRules`].
+#[derive(Clone, Debug)]
+pub struct AlertEngine {
+    pub rules: AlertRules,
+    episodes: HashMap<(String, String), Episode>,
+    daily: HashMap<(String, String), DailyCount>,
+}
+
+impl Default for AlertEngine {
+    fn default() -> Self {
+        Self::new(AlertRules::from_env())
+    }
+}
+
+impl AlertEngine {
+    pub fn new(rules: AlertRules) -> Self {
+        Self {
+            rules,
+            episodes: HashMap::new(),
+            daily: HashMap::new(),
+        }
+    }
+
+    /// Feed one moment; returns the alert it triggers, if any.
+    pub fn observe(&mut self, moment: &EmotionalMoment) -> Option<EmotionAlert> {
+        let local = Local.timestamp_opt(moment.ts_unix, 0).single()?;
+        self.observe_at(moment, local)
+    }
+
+    fn observe_at(
+        &mut self,
+        moment: &EmotionalMoment,
+        local: DateTime<Local>,
+    ) -> Option<EmotionAlert> {
+        let profile = moment.profile_label().to_string();
+        if !self.rules.enabled || self.rules.opted_out(&profile) {
+            return None;
+        }
+        let now = moment.ts_unix;
+
+        // Any other emotion (or a weak reading) from this profile ends its open episodes.
+        let qualifies =
+            self.rules.watches(&moment.emotion) && moment.confidence >= self.rules.min_confidence;
+        self.episodes.retain(|(p, e), ep| {
+            p != &profile
+                || (qualifies && e == &moment.emotion && now - ep.last <= EPISODE_GAP_SECS)
+        });
+        if !qualifies {
+            return None;
+        }
+
+        let key = (profile.clone(), moment.emotion.clone());
+        let mut trigger = None;
+        match self.episodes.get_mut(&key) {
+            Some(ep) => {
+                ep.last = now;
+                let minutes = self.rules.sustained_minutes;
+                if minutes > 0 && !ep.sustained_fired && now - ep.started >= i64::from(minutes) * 60
+                {
+                    ep.sustained_fired = true;
+                    trigger = Some(AlertTrigger::Sustained { minutes });
+                }
+            }
+            None => {
+                self.episodes.insert(
+                    key.clone(),
+                    Episode {
+                        started: now,
+                        last: now,
+                        sustained_fired: false,
+                    },
+                );
+                let today = local.date_naive();
+                let daily = self.daily.entry(key).or_default();
+                if daily.day != Some(today) {
+                    *daily = DailyCount {
+                        day: Some(today),
+                        ..DailyCount::default()
+                    };
+                }
+                daily.episodes += 1;
+                let m = self.rules.daily_recurrence;
+                if m > 0 && !daily.recurrence_fired && daily.episodes >= m {
+                    daily.recurrence_fired = true;
+                    trigger = Some(AlertTrigger::Recurring {
+                        count: daily.episodes,
+                    });
+                }
+            }
+        }
+
+        let trigger = trigger?;
+        let quiet = self
+            .rules
+            .quiet_hours
+            .is_some_and(|q| q.contains(local.hour()));
+        Some(EmotionAlert {
+            id: uuid::Uuid::new_v4().to_string(),
+            ts_unix: now,
+            profile,
+            emotion: moment.emotion.clone(),
+            confidence: moment.confidence,
+            trigger,
+            recording: moment.recording.clone(),
+            notify: !quiet,
+        })
+    }
+}
+
+/// Append an alert to the Soul-Vault timeline (bounded to the newest entries).
+pub fn append_to_timeline(vaults: &VitalOrganVaults, alert: &EmotionAlert) {
+    let Ok(entry) = serde_json::to_string(alert) else {
+        return;
+    };
+    let existing = vaults.recall_soul(ALERT_TIMELINE_KEY).unwrap_or_default();
+    let mut lines = existing
+        .lines()
+        .map(|s| s.to_string())
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>();
+    lines.push(entry);
+    if lines.len() > ALERT_TIMELINE_MAX {
+        lines = lines.split_off(lines.len() - ALERT_TIMELINE_MAX);
+    }
+    let _ = vaults.store_soul(ALERT_TIMELINE_KEY, &lines.join("\n"));
+}
+
+/// Read the alert timeline (most recent last).
+pub fn recent(vaults: &VitalOrganVaults, max: usize) -> Vec<EmotionAlert> {
+    let raw = vaults.recall_soul(ALERT_TIMELINE_KEY).unwrap_or_default();
+    let mut alerts = raw
+        .lines()
+        .filter_map(|l| serde_json::from_str::<EmotionAlert>(l).ok())
+        .collect::<Vec<_>>();
+    if alerts.len() > max {
+        alerts = alerts.split_off(alerts.len() - max);
+    }
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moment(ts_unix: i64, emotion: &str, profile: &str) -> EmotionalMoment {
+        EmotionalMoment {
+            ts_unix,
+            emotion: emotion.to_string(),
+            intensity: 0.8,
+            confidence: 0.8,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            recording: format!("rec-{ts_unix}"),
+            profile: Some(profile.to_string()),
+            valence: None,
+            arousal: None,
+        }
+    }
+
+    #[test]
+    fn sustained_recurring_quiet_hours_and_opt_out() {
+        let rules = AlertRules {
+            sustained_minutes: 10,
+            daily_recurrence: 2,
+            quiet_hours: Some(QuietHours {
+                start_hour: 22,
+                end_hour: 7,
+            }),
+            opted_out_profiles: vec!["guest-kid".to_string()],
+            ..AlertRules::default()
+        };
+        let mut engine = AlertEngine::new(rules);
+        let noon = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let at = |m: &EmotionalMoment, engine: &mut AlertEngine| {
+            engine.observe_at(m, noon + chrono::Duration::seconds(m.ts_unix))
+        };
+
+        // Sadness every 4 minutes: fires once it has lasted 10 minutes, and only once.
+        for t in [0, 240, 480] {
+            assert!(at(&moment(t, "Sadness", "me"), &mut engine).is_none());
+        }
+        let alert = at(&moment(720, "Sadness", "me"), &mut engine).expect("sustained");
+        assert_eq!(alert.trigger, AlertTrigger::Sustained { minutes: 10 });
+        assert!(alert.notify);
+        assert!(at(&moment(900, "Sadness", "me"), &mut engine).is_none());
+
+        // A calm moment ends the episode; the second episode of the day is a recurrence.
+        assert!(at(&moment(1000, "Joy", "me"), &mut engine).is_none());
+        let alert = at(&moment(1100, "Sadness", "me"), &mut engine).expect("recurring");
+        assert_eq!(alert.trigger, AlertTrigger::Recurring { count: 2 });
+
+        // Opted-out profiles never alert.
+        for t in [0, 700, 1400] {
+            assert!(at(&moment(t, "Anger", "guest-kid"), &mut engine).is_none());
+        }
+
+        // Quiet hours: logged but not notified.
+        let late = Local.with_ymd_and_hms(2024, 5, 1, 23, 0, 0).unwrap();
+        for t in [5000, 5250, 5500] {
+            assert!(engine.observe_at(&moment(t, "Anger", "me"), late).is_none());
+        }
+        let alert = engine
+            .observe_at(&moment(5650, "Anger", "me"), late)
+            .expect("sustained");
+        assert!(!alert.notify);
+    }
+}
@@ -0,0 +1,182 @@
+//! Emotion history export (CSV / JSON) for sharing with a clinician or other tools.
+//!
+//! Exports go through [`EmotionPrivacy::redact_for_export`], so profiles that asked to be
+//! redacted never appear in the output.
+
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::emotion_history::{self, EmotionQuery, EmotionalMoment};
+use crate::emotion_privacy::EmotionPrivacy;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+const CSV_HEADER: &str = "timestamp,ts_unix,profile,emotion,intensity,confidence,valence,arousal,\
+voice_contribution,face_contribution,text_contribution,recording";
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn opt(v: Option<f64>) -> String {
+    v.map(|v| format!("{v:.3}")).unwrap_or_default()
+}
+
+pub fn to_csv(moments: &[EmotionalMoment]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for m in moments {
+        let ts = Utc
+            .timestamp_opt(m.ts_unix, 0)
+            .single()
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        let row = [
+            ts,
+            m.ts_unix.to_string(),
+            csv_field(m.profile_label()),
+            csv_field(&m.emotion),
+            format!("{:.3}", m.intensity),
+            format!("{:.3}", m.confidence),
+            opt(m.valence),
+            opt(m.arousal),
+            format!("{:.3}", m.voice_contribution),
+            format!("{:.3}", m.face_contribution),
+            format!("{:.3}", m.text_contribution),
+            csv_field(&m.recording),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Render `moments` in `format`.
+pub fn render(moments: &[EmotionalMoment], format: ExportFormat) -> Result<String, crate::Error> {
+    Ok(match format {
+        ExportFormat::Csv => to_csv(moments),
+        ExportFormat::Json => serde_json::to_string_pretty(moments)?,
+    })
+}
+
+/// Query, redact, and render the history. Returns the document and the number of samples.
+pub fn export(
+    vaults: &VitalOrganVaults,
+    query: &EmotionQuery,
+    format: ExportFormat,
+) -> Result<(String, usize), crate::Error> {
+    let moments = emotion_history::query_moments(vaults, query);
+    let moments = EmotionPrivacy::load(vaults).redact_for_export(moments);
+    Ok((render(&moments, format)?, moments.len()))
+}
+
+/// The whole history, oldest first and redacted like [`export`], for full data exports; not
+/// capped at [`EMOTION_QUERY_MAX`](crate::emotion_history::EMOTION_QUERY_MAX) like a single
+/// query.
+pub fn all_moments(vaults: &VitalOrganVaults) -> Vec<EmotionalMoment> {
+    EmotionPrivacy::load(vaults).redact_for_export(emotion_history::full_history(vaults))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emotion_history::append_moment;
+    use crate::emotion_privacy::ProfileEmotionPrivacy;
+
+    fn moment(ts_unix: i64, profile: Option<&str>, recording: &str) -> EmotionalMoment {
+        EmotionalMoment {
+            ts_unix,
+            emotion: "Joy".to_string(),
+            intensity: 0.5,
+            confidence: 0.25,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            recording: recording.to_string(),
+            profile: profile.map(str::to_string),
+            valence: Some(0.75),
+            arousal: None,
+        }
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let csv = to_csv(&[moment(0, None, "a \"b\", c\nd")]);
+        let mut lines = csv.splitn(2, '\n');
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        assert_eq!(
+            lines.next(),
+            Some(
+                "1970-01-01T00:00:00+00:00,0,unknown,Joy,0.500,0.250,0.750,,1.000,0.000,0.000,\
+                 \"a \"\"b\"\", c\nd\"\n"
+            )
+        );
+        assert_eq!(to_csv(&[]), format!("{CSV_HEADER}\n"));
+    }
+
+    #[test]
+    fn exports_are_filtered_and_redacted() {
+        let dir = std::env::temp_dir().join(format!("mmr-export-{}", uuid::Uuid::new_v4()));
+        let vaults = VitalOrganVaults::awaken_in(&dir);
+        for m in [
+            moment(10, Some("alice"), "r1"),
+            moment(20, Some("bob"), "r1"),
+            moment(30, Some("alice"), "r2"),
+        ] {
+            append_moment(&vaults, &m);
+        }
+
+        let alice = EmotionQuery {
+            profile: Some("alice".to_string()),
+            to_unix: Some(30),
+            ..EmotionQuery::default()
+        };
+        let (json, count) = export(&vaults, &alice, ExportFormat::Json).unwrap();
+        assert_eq!(count, 1);
+        let exported: Vec<EmotionalMoment> = serde_json::from_str(&json).unwrap();
+        assert_eq!(exported[0].ts_unix, 10);
+
+        let mut privacy = EmotionPrivacy::load(&vaults);
+        let redacted = ProfileEmotionPrivacy {
+            redact_from_exports: true,
+            ..ProfileEmotionPrivacy::default()
+        };
+        privacy.set_profile("bob", redacted);
+        privacy.save(&vaults).unwrap();
+        let everyone = EmotionQuery::default();
+        let (csv, count) = export(&vaults, &everyone, ExportFormat::Csv).unwrap();
+        assert_eq!(count, 2);
+        assert!(!csv.contains(",bob,"));
+        assert_eq!(all_moments(&vaults).len(), 2);
+
+        drop(vaults);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
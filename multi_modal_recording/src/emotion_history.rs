@@ -0,0 +1,524 @@
+//! Emotional-moment records and per-profile queries.
+//!
+//! Every emotional state the recorder computes is attributed to the profile that was most
+//! recently recognized, or else to the active profile, so household members' moods are not
+//! blended together.
+//!
+//! Moments are persisted twice:
+//! - the capped `emotional_moments` timeline (recent context for prompts and status panels)
+//! - the full history, which is never trimmed and is what [`query_moments`] searches: the
+//!   `emotion_moments` table when a database is set with [`crate::use_storage`], otherwise one
+//!   Soul-Vault key per moment under `emotion:moment:<ts>:<id>`.
+//!
+//! [`import_vault_history`] copies vault-key history the database doesn't have yet;
+//! [`merge_moments`] adds the history from a data export.
+
+use emotion_detection::affect::Affect;
+use emotion_detection::calibration::ReportedEmotion;
+use emotion_detection::fusion::Disagreement;
+use emotion_detection::EmotionalState;
+use phoenix_storage::moments::{MomentFilter, NewMoment};
+use phoenix_storage::Storage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::Error;
+
+/// Soul-Vault key holding the emotional-moment timeline (JSON lines, most recent last).
+pub const EMOTION_TIMELINE_KEY: &str = "emotional_moments";
+
+/// Maximum number of moments kept in the timeline.
+const EMOTION_TIMELINE_MAX: usize = 200;
+
+/// Soul-Vault key prefix of the persistent per-moment history.
+pub const EMOTION_HISTORY_PREFIX: &str = "emotion:moment:";
+
+/// Upper bound on moments returned by a single history query.
+pub const EMOTION_QUERY_MAX: usize = 10_000;
+
+/// How long a recognition result keeps attributing emotions to that profile.
+pub const ATTRIBUTION_WINDOW_SECS: i64 = 5 * 60;
+
+/// Profile label used when no enrolled profile was recognized recently.
+pub const UNATTRIBUTED_PROFILE: &str = "unknown";
+
+/// One persisted emotional state sample.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionalMoment {
+    pub ts_unix: i64,
+    pub emotion: String,
+    pub intensity: f64,
+    pub confidence: f64,
+    pub voice_contribution: f64,
+    pub face_contribution: f64,
+    pub text_contribution: f64,
+    pub recording: String,
+    /// Recognized profile this moment is attributed to (legacy entries have none).
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Valence (-1.0..=1.0) / arousal (0.0..=1.0); absent on legacy entries.
+    #[serde(default)]
+    pub valence: Option<f64>,
+    #[serde(default)]
+    pub arousal: Option<f64>,
+}
+
+impl EmotionalMoment {
+    pub fn from_state(state: &EmotionalState, recording: &Path, profile: Option<String>) -> Self {
+        Self {
+            ts_unix: state.timestamp.timestamp(),
+            emotion: format!("{:?}", state.primary_emotion),
+            intensity: state.intensity,
+            confidence: state.confidence,
+            voice_contribution: state.voice_contribution,
+            face_contribution: state.face_contribution,
+            text_contribution: state.text_contribution,
+            recording: recording.display().to_string(),
+            profile,
+            valence: None,
+            arousal: None,
+        }
+        .with_affect(Affect::from_state(state))
+    }
+
+    pub fn with_affect(mut self, affect: Affect) -> Self {
+        self.valence = Some(affect.valence);
+        self.arousal = Some(affect.arousal);
+        self
+    }
+
+    /// Profile label for grouping (legacy / unattributed moments map to `"unknown"`).
+    pub fn profile_label(&self) -> &str {
+        self.profile.as_deref().unwrap_or(UNATTRIBUTED_PROFILE)
+    }
+
+    /// Modalities that contributed to this moment.
+    pub fn sources(&self) -> Vec<EmotionSource> {
+        [
+            (EmotionSource::Voice, self.voice_contribution),
+            (EmotionSource::Face, self.face_contribution),
+            (EmotionSource::Text, self.text_contribution),
+        ]
+        .into_iter()
+        .filter(|(_, w)| *w > 0.0)
+        .map(|(s, _)| s)
+        .collect()
+    }
+}
+
+/// Filters for [`query_moments`]. Every field is optional; bounds are Unix seconds,
+/// `from` inclusive and `to` exclusive.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EmotionQuery {
+    #[serde(default)]
+    pub from_unix: Option<i64>,
+    #[serde(default)]
+    pub to_unix: Option<i64>,
+    /// Emotion name (`"Joy"`, `"Sadness"`, ...), case-insensitive.
+    #[serde(default)]
+    pub emotion: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub modality: Option<EmotionSource>,
+    /// Maximum results (most recent kept); defaults to [`EMOTION_QUERY_MAX`].
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+fn history_key(ts_unix: i64) -> String {
+    // Zero-padded so lexical key order is chronological.
+    format!("{EMOTION_HISTORY_PREFIX}{:012}", ts_unix.max(0))
+}
+
+/// Soul-Vault key of a new history entry.
+fn vault_key(ts_unix: i64) -> String {
+    format!(
+        "{}:{}",
+        history_key(ts_unix),
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    )
+}
+
+/// Query the persistent history (oldest first).
+pub fn query_moments(vaults: &VitalOrganVaults, q: &EmotionQuery) -> Vec<EmotionalMoment> {
+    let limit = q.limit.unwrap_or(EMOTION_QUERY_MAX).min(EMOTION_QUERY_MAX);
+    let Some(storage) = crate::storage() else {
+        return query_vault(vaults, q, limit);
+    };
+    let filter = MomentFilter {
+        from_unix: q.from_unix,
+        to_unix: q.to_unix,
+        emotion: q.emotion.as_deref(),
+        profile: q.profile.as_deref(),
+        // The modality filter runs on the decoded moments, so the limit has to wait for it.
+        limit: q.modality.is_none().then_some(limit),
+    };
+    let bodies = storage.query_moments(&filter).unwrap_or_else(|e| {
+        tracing::warn!("Emotion history query failed: {e}");
+        Vec::new()
+    });
+    let mut out = bodies
+        .iter()
+        .filter_map(|b| serde_json::from_str::<EmotionalMoment>(b).ok())
+        .filter(|m| q.modality.is_none_or(|s| m.sources().contains(&s)))
+        .collect::<Vec<_>>();
+    if out.len() > limit {
+        out = out.split_off(out.len() - limit);
+    }
+    out
+}
+
+fn query_vault(vaults: &VitalOrganVaults, q: &EmotionQuery, limit: usize) -> Vec<EmotionalMoment> {
+    let start = format!("soul:{}", history_key(q.from_unix.unwrap_or(0)));
+    let end = match q.to_unix {
+        Some(to) => format!("soul:{}", history_key(to)),
+        // '~' sorts after every digit, so this covers all timestamps.
+        None => format!("soul:{EMOTION_HISTORY_PREFIX}~"),
+    };
+    let mut out = vaults
+        .recall_range(&start, &end, usize::MAX)
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_str::<EmotionalMoment>(&v).ok())
+        .filter(|m| {
+            q.emotion
+                .as_deref()
+                .is_none_or(|e| m.emotion.eq_ignore_ascii_case(e))
+        })
+        .filter(|m| {
+            q.profile
+                .as_deref()
+                .is_none_or(|p| m.profile_label().eq_ignore_ascii_case(p))
+        })
+        .filter(|m| q.modality.is_none_or(|s| m.sources().contains(&s)))
+        .collect::<Vec<_>>();
+    if out.len() > limit {
+        out = out.split_off(out.len() - limit);
+    }
+    out
+}
+
+/// Input that contributed to an emotion estimate.
+pub use emotion_detection::fusion::EmotionModality as EmotionSource;
+
+/// Live emotion estimate published on the recorder's emotion channel.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionUpdate {
+    #[serde(flatten)]
+    pub moment: EmotionalMoment,
+    pub sources: Vec<EmotionSource>,
+    /// Set when the contributing modalities disagreed about the primary emotion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disagreement: Option<Disagreement>,
+    /// Calibrated/stabilized label the status should show (may be `"uncertain"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported: Option<ReportedEmotion>,
+}
+
+impl EmotionUpdate {
+    pub fn new(moment: EmotionalMoment) -> Self {
+        let sources = moment.sources();
+        Self {
+            moment,
+            sources,
+            disagreement: None,
+            reported: None,
+        }
+    }
+}
+
+/// The whole history, oldest first and unredacted; not capped at [`EMOTION_QUERY_MAX`] like a
+/// single query.
+pub fn full_history(vaults: &VitalOrganVaults) -> Vec<EmotionalMoment> {
+    let mut batches = Vec::new();
+    let mut to_unix = None;
+    loop {
+        let query = EmotionQuery {
+            to_unix,
+            ..EmotionQuery::default()
+        };
+        let mut batch = query_moments(vaults, &query);
+        let Some(oldest) = batch.first().map(|m| m.ts_unix) else {
+            break;
+        };
+        if batch.len() < EMOTION_QUERY_MAX {
+            batches.push(batch);
+            break;
+        }
+        // The batch may hold only part of its oldest second; fetch that second whole next
+        // time, unless it fills the batch by itself.
+        let newer = batch.partition_point(|m| m.ts_unix <= oldest);
+        if newer == batch.len() {
+            to_unix = Some(oldest);
+        } else {
+            batch.drain(..newer);
+            to_unix = Some(oldest + 1);
+        }
+        batches.push(batch);
+    }
+    batches.into_iter().rev().flatten().collect()
+}
+
+/// Persist `moment` to the history and append it to the Soul-Vault timeline (best-effort).
+pub fn append_moment(vaults: &VitalOrganVaults, moment: &EmotionalMoment) {
+    let Ok(entry) = serde_json::to_string(moment) else {
+        return;
+    };
+    match crate::storage() {
+        Some(storage) => {
+            let row = NewMoment {
+                ts_unix: moment.ts_unix,
+                profile: moment.profile_label(),
+                emotion: &moment.emotion,
+                body: &entry,
+            };
+            if let Err(e) = storage.insert_moment(&row) {
+                tracing::warn!("Emotion moment not stored: {e}");
+            }
+        }
+        None => {
+            let _ = vaults.store_soul(&vault_key(moment.ts_unix), &entry);
+        }
+    }
+
+    let existing = vaults.recall_soul(EMOTION_TIMELINE_KEY).unwrap_or_default();
+    let mut lines = existing
+        .lines()
+        .map(|s| s.to_string())
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>();
+    lines.push(entry);
+    if lines.len() > EMOTION_TIMELINE_MAX {
+        lines = lines.split_off(lines.len() - EMOTION_TIMELINE_MAX);
+    }
+    let _ = vaults.store_soul(EMOTION_TIMELINE_KEY, &lines.join("\n"));
+}
+
+/// Copy the Soul-Vault history the database set with [`crate::use_storage`] doesn't have yet.
+/// Returns how many moments were copied. The vault keys are left in place.
+pub fn import_vault_history(vaults: &VitalOrganVaults) -> Result<usize, Error> {
+    match crate::storage() {
+        Some(storage) => import_into(storage, vaults),
+        None => Ok(0),
+    }
+}
+
+/// Copy the vault moments from the second of the newest one in `storage` on. Vault keys only
+/// gain moments while no database is in use, so the older ones were copied on an earlier start;
+/// those from that second are compared with what the database holds for it.
+fn import_into(storage: &Storage, vaults: &VitalOrganVaults) -> Result<usize, Error> {
+    let latest = storage.latest_moment_unix()?;
+    let known = match latest {
+        Some(ts) => storage.query_moments(&MomentFilter {
+            from_unix: Some(ts),
+            to_unix: Some(ts + 1),
+            ..MomentFilter::default()
+        })?,
+        None => Vec::new(),
+    };
+    let moments = vaults
+        .recall_range(
+            &format!("soul:{}", history_key(latest.unwrap_or(0))),
+            &format!("soul:{EMOTION_HISTORY_PREFIX}~"),
+            usize::MAX,
+        )
+        .into_iter()
+        .filter(|(_, v)| !known.contains(v))
+        .filter_map(|(_, v)| Some((serde_json::from_str::<EmotionalMoment>(&v).ok()?, v)))
+        .collect::<Vec<_>>();
+    let rows = moments
+        .iter()
+        .map(|(m, body)| NewMoment {
+            ts_unix: m.ts_unix,
+            profile: m.profile_label(),
+            emotion: &m.emotion,
+            body,
+        })
+        .collect::<Vec<_>>();
+    Ok(storage.insert_moments(&rows)?)
+}
+
+/// Add moments from a data export to the full history, skipping the ones it already holds
+/// (equal in every field). The timeline is left alone. Returns how many were new; with
+/// `dry_run` nothing is written.
+pub fn merge_moments(
+    vaults: &VitalOrganVaults,
+    moments: &[EmotionalMoment],
+    dry_run: bool,
+) -> Result<usize, Error> {
+    let mut by_second: HashMap<i64, Vec<serde_json::Value>> = HashMap::new();
+    let mut new = Vec::new();
+    for moment in moments {
+        let value = serde_json::to_value(moment)?;
+        let known = by_second.entry(moment.ts_unix).or_insert_with(|| {
+            let second = EmotionQuery {
+                from_unix: Some(moment.ts_unix),
+                to_unix: Some(moment.ts_unix + 1),
+                ..EmotionQuery::default()
+            };
+            query_moments(vaults, &second)
+                .iter()
+                .filter_map(|m| serde_json::to_value(m).ok())
+                .collect()
+        });
+        if !known.contains(&value) {
+            // Also keeps repeats within the import from counting twice.
+            known.push(value);
+            new.push((moment, serde_json::to_string(moment)?));
+        }
+    }
+    if dry_run || new.is_empty() {
+        return Ok(new.len());
+    }
+    match crate::storage() {
+        Some(storage) => {
+            let rows = new
+                .iter()
+                .map(|(m, body)| NewMoment {
+                    ts_unix: m.ts_unix,
+                    profile: m.profile_label(),
+                    emotion: &m.emotion,
+                    body,
+                })
+                .collect::<Vec<_>>();
+            storage.insert_moments(&rows)?;
+        }
+        None => {
+            for (m, body) in &new {
+                vaults
+                    .store_soul(&vault_key(m.ts_unix), body)
+                    .map_err(std::io::Error::other)?;
+            }
+        }
+    }
+    Ok(new.len())
+}
+
+/// Parse timeline lines, keeping only moments attributed to `profile` (most recent last).
+pub fn filter_profile<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    profile: &str,
+    max: usize,
+) -> Vec<EmotionalMoment> {
+    let mut out = lines
+        .into_iter()
+        .filter_map(|l| serde_json::from_str::<EmotionalMoment>(l).ok())
+        .filter(|m| m.profile_label().eq_ignore_ascii_case(profile))
+        .collect::<Vec<_>>();
+    if out.len() > max {
+        out = out.split_off(out.len() - max);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moment(ts_unix: i64, emotion: &str) -> EmotionalMoment {
+        EmotionalMoment {
+            ts_unix,
+            emotion: emotion.to_string(),
+            intensity: 0.5,
+            confidence: 0.5,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            recording: String::new(),
+            profile: None,
+            valence: None,
+            arousal: None,
+        }
+    }
+
+    fn store(vaults: &VitalOrganVaults, moment: &EmotionalMoment) {
+        let body = serde_json::to_string(moment).unwrap();
+        vaults
+            .store_soul(&vault_key(moment.ts_unix), &body)
+            .unwrap();
+    }
+
+    #[test]
+    fn history_queries_filter_by_range_emotion_profile_and_modality() {
+        let dir = std::env::temp_dir().join(format!("mmr-history-{}", uuid::Uuid::new_v4()));
+        let vaults = VitalOrganVaults::awaken_in(&dir);
+        let mut seen = moment(20, "Sadness");
+        seen.voice_contribution = 0.0;
+        seen.face_contribution = 1.0;
+        seen.profile = Some("alice".to_string());
+        for m in [moment(10, "Joy"), seen, moment(30, "joy")] {
+            append_moment(&vaults, &m);
+        }
+        let times = |q: EmotionQuery| {
+            query_moments(&vaults, &q)
+                .iter()
+                .map(|m| m.ts_unix)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(times(EmotionQuery::default()), [10, 20, 30]);
+        // `from` inclusive, `to` exclusive.
+        let range = EmotionQuery {
+            from_unix: Some(20),
+            to_unix: Some(30),
+            ..EmotionQuery::default()
+        };
+        assert_eq!(times(range), [20]);
+        let joy = EmotionQuery {
+            emotion: Some("JOY".to_string()),
+            ..EmotionQuery::default()
+        };
+        assert_eq!(times(joy), [10, 30]);
+        let alice = EmotionQuery {
+            profile: Some("Alice".to_string()),
+            ..EmotionQuery::default()
+        };
+        assert_eq!(times(alice), [20]);
+        let unattributed = EmotionQuery {
+            profile: Some(UNATTRIBUTED_PROFILE.to_string()),
+            ..EmotionQuery::default()
+        };
+        assert_eq!(times(unattributed), [10, 30]);
+        let face = EmotionQuery {
+            modality: Some(EmotionSource::Face),
+            ..EmotionQuery::default()
+        };
+        assert_eq!(times(face), [20]);
+        // The most recent are kept.
+        let last_two = EmotionQuery {
+            limit: Some(2),
+            ..EmotionQuery::default()
+        };
+        assert_eq!(times(last_two), [20, 30]);
+
+        let timeline = vaults.recall_soul(EMOTION_TIMELINE_KEY).unwrap();
+        assert_eq!(filter_profile(timeline.lines(), "unknown", 1).len(), 1);
+        assert_eq!(filter_profile(timeline.lines(), "unknown", 10).len(), 2);
+
+        drop(vaults);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vault_history_is_imported_once_and_then_only_what_is_new() {
+        let dir = std::env::temp_dir().join(format!("mmr-history-{}", uuid::Uuid::new_v4()));
+        let vaults = VitalOrganVaults::awaken_in(&dir);
+        let storage = Storage::open_sqlite(&dir.join("phoenix.db"), 1).unwrap();
+        store(&vaults, &moment(10, "Joy"));
+        store(&vaults, &moment(20, "Sadness"));
+        assert_eq!(import_into(&storage, &vaults).unwrap(), 2);
+        assert_eq!(import_into(&storage, &vaults).unwrap(), 0);
+
+        // Written while the database was not in use: same second as the newest, and later.
+        store(&vaults, &moment(20, "Anger"));
+        store(&vaults, &moment(30, "Joy"));
+        assert_eq!(import_into(&storage, &vaults).unwrap(), 2);
+        assert_eq!(import_into(&storage, &vaults).unwrap(), 0);
+        assert_eq!(storage.moment_count().unwrap(), 4);
+
+        drop((vaults, storage));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,291 @@
+//! Per-profile emotion privacy controls.
+//!
+//! Each profile can opt out of emotion inference entirely (nothing is computed while they are
+//! the attributed speaker/face) and/or have its samples redacted from exports. [`purge_profile`]
+//! deletes every stored emotion record for a profile — history, timelines, alerts, recording
+//! emotion tracks — and recomputes the daily summaries, while leaving recordings untouched.
+//!
+//! Settings live in the Soul Vault under `emotion_privacy`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::emotion_alerts::ALERT_TIMELINE_KEY;
+use crate::emotion_history::{
+    EmotionalMoment, EMOTION_HISTORY_PREFIX, EMOTION_TIMELINE_KEY, UNATTRIBUTED_PROFILE,
+};
+use crate::emotion_track::{self, EMOTION_TRACK_SUFFIX};
+use crate::{mood_summary, Error};
+
+/// Soul-Vault key holding the serialized [`EmotionPrivacy`].
+pub const EMOTION_PRIVACY_KEY: &str = "emotion_privacy";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileEmotionPrivacy {
+    /// Skip emotion inference while this profile is the attributed person.
+    #[serde(default)]
+    pub inference_disabled: bool,
+    /// Leave this profile's samples out of emotion exports.
+    #[serde(default)]
+    pub redact_from_exports: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EmotionPrivacy {
+    /// Keyed by lower-cased profile label (`"unknown"` covers unattributed samples).
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileEmotionPrivacy>,
+}
+
+impl EmotionPrivacy {
+    pub fn load(vaults: &VitalOrganVaults) -> Self {
+        vaults
+            .recall_soul(EMOTION_PRIVACY_KEY)
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, vaults: &VitalOrganVaults) -> Result<(), Error> {
+        let raw = serde_json::to_string(self)?;
+        vaults
+            .store_soul(EMOTION_PRIVACY_KEY, &raw)
+            .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))
+    }
+
+    pub fn for_profile(&self, profile: &str) -> ProfileEmotionPrivacy {
+        self.profiles
+            .get(&profile.to_ascii_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_profile(&mut self, profile: &str, settings: ProfileEmotionPrivacy) {
+        let key = profile.to_ascii_lowercase();
+        if settings == ProfileEmotionPrivacy::default() {
+            self.profiles.remove(&key);
+        } else {
+            self.profiles.insert(key, settings);
+        }
+    }
+
+    pub fn inference_allowed(&self, profile: Option<&str>) -> bool {
+        !self
+            .for_profile(profile.unwrap_or(UNATTRIBUTED_PROFILE))
+            .inference_disabled
+    }
+
+    /// Drop samples of profiles that asked to be redacted from exports.
+    pub fn redact_for_export(&self, moments: Vec<EmotionalMoment>) -> Vec<EmotionalMoment> {
+        moments
+            .into_iter()
+            .filter(|m| !self.for_profile(m.profile_label()).redact_from_exports)
+            .collect()
+    }
+}
+
+/// What [`purge_profile`] removed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub profile: String,
+    pub history_samples: usize,
+    pub timeline_entries: usize,
+    pub alerts: usize,
+    pub track_points: usize,
+    pub summaries_recomputed: usize,
+}
+
+fn is_profile(value: &serde_json::Value, profile: &str) -> bool {
+    value
+        .get("profile")
+        .and_then(|p| p.as_str())
+        .unwrap_or(UNATTRIBUTED_PROFILE)
+        .eq_ignore_ascii_case(profile)
+}
+
+/// Rewrite a JSON-lines timeline without `profile`'s entries; returns how many were dropped.
+fn purge_timeline(vaults: &VitalOrganVaults, key: &str, profile: &str) -> usize {
+    let Some(raw) = vaults.recall_soul(key) else {
+        return 0;
+    };
+    let mut removed = 0;
+    let kept = raw
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter(|l| {
+            let drop = serde_json::from_str::<serde_json::Value>(l)
+                .map(|v| is_profile(&v, profile))
+                .unwrap_or(false);
+            removed += usize::from(drop);
+            !drop
+        })
+        .collect::<Vec<_>>();
+    if removed > 0 {
+        let _ = vaults.store_soul(key, &kept.join("\n"));
+    }
+    removed
+}
+
+/// Delete every stored emotion record attributed to `profile`. Recordings are kept.
+pub async fn purge_profile(
+    vaults: &VitalOrganVaults,
+    recordings_dir: &Path,
+    profile: &str,
+) -> Result<PurgeReport, Error> {
+    let mut report = PurgeReport {
+        profile: profile.to_string(),
+        ..PurgeReport::default()
+    };
+
+    for (key, value) in vaults.recall_prefix(&format!("soul:{EMOTION_HISTORY_PREFIX}"), usize::MAX)
+    {
+        let matches = serde_json::from_str::<serde_json::Value>(&value)
+            .map(|v| is_profile(&v, profile))
+            .unwrap_or(false);
+        if matches && vaults.forget_soul(&key).unwrap_or(false) {
+            report.history_samples += 1;
+        }
+    }
+    if let Some(storage) = crate::storage() {
+        report.history_samples += storage.delete_moments_for_profile(profile)?;
+    }
+    report.timeline_entries = purge_timeline(vaults, EMOTION_TIMELINE_KEY, profile);
+    report.alerts = purge_timeline(vaults, ALERT_TIMELINE_KEY, profile);
+
+    if tokio::fs::try_exists(recordings_dir).await.unwrap_or(false) {
+        let mut rd = tokio::fs::read_dir(recordings_dir).await?;
+        while let Some(entry) = rd.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(recording) = name.strip_suffix(EMOTION_TRACK_SUFFIX) else {
+                continue;
+            };
+            let recording = recordings_dir.join(recording);
+            let Some(mut track) = emotion_track::load(&recording).await else {
+                continue;
+            };
+            let before = track.points.len();
+            track.points.retain(|p| {
+                !p.profile
+                    .as_deref()
+                    .unwrap_or(UNATTRIBUTED_PROFILE)
+                    .eq_ignore_ascii_case(profile)
+            });
+            if track.points.len() != before {
+                report.track_points += before - track.points.len();
+                emotion_track::save(&track).await?;
+            }
+        }
+    }
+
+    report.summaries_recomputed = mood_summary::regenerate_all(vaults);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emotion_history::append_moment;
+    use crate::emotion_track::{EmotionTrack, EmotionTrackPoint};
+
+    fn moment(ts_unix: i64, profile: Option<&str>) -> EmotionalMoment {
+        EmotionalMoment {
+            ts_unix,
+            emotion: "Joy".to_string(),
+            intensity: 0.5,
+            confidence: 0.5,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            recording: String::new(),
+            profile: profile.map(str::to_string),
+            valence: None,
+            arousal: None,
+        }
+    }
+
+    fn point(offset_secs: f64, profile: Option<&str>) -> EmotionTrackPoint {
+        EmotionTrackPoint {
+            offset_secs,
+            emotion: "Joy".to_string(),
+            intensity: 0.5,
+            confidence: 0.5,
+            profile: profile.map(str::to_string),
+            valence: None,
+            arousal: None,
+        }
+    }
+
+    #[test]
+    fn settings_are_per_profile_and_case_insensitive() {
+        let mut privacy = EmotionPrivacy::default();
+        let redacted = ProfileEmotionPrivacy {
+            inference_disabled: true,
+            redact_from_exports: true,
+        };
+        privacy.set_profile("Alice", redacted.clone());
+        privacy.set_profile(UNATTRIBUTED_PROFILE, redacted);
+        assert!(!privacy.inference_allowed(Some("ALICE")));
+        // Unattributed samples follow the "unknown" settings.
+        assert!(!privacy.inference_allowed(None));
+        assert!(privacy.inference_allowed(Some("bob")));
+
+        let kept = privacy.redact_for_export(vec![
+            moment(1, Some("alice")),
+            moment(2, Some("bob")),
+            moment(3, None),
+        ]);
+        assert_eq!(kept.iter().map(|m| m.ts_unix).collect::<Vec<_>>(), [2]);
+
+        // Back to the defaults: the entry goes away rather than being stored.
+        privacy.set_profile("alice", ProfileEmotionPrivacy::default());
+        assert!(!privacy.profiles.contains_key("alice"));
+        assert!(privacy.inference_allowed(Some("alice")));
+    }
+
+    #[tokio::test]
+    async fn purge_removes_one_profile_and_keeps_recordings() {
+        let dir = std::env::temp_dir().join(format!("mmr-privacy-{}", uuid::Uuid::new_v4()));
+        let recordings = dir.join("recordings");
+        std::fs::create_dir_all(&recordings).unwrap();
+        let vaults = VitalOrganVaults::awaken_in(&dir.join("vaults"));
+        for m in [
+            moment(10, Some("alice")),
+            moment(20, Some("Bob")),
+            moment(30, None),
+        ] {
+            append_moment(&vaults, &m);
+        }
+        let recording = recordings.join("r1.av");
+        std::fs::write(&recording, b"media").unwrap();
+        let track = EmotionTrack {
+            recording: recording.clone(),
+            started_unix: 10,
+            duration_secs: 20,
+            points: vec![point(0.0, Some("bob")), point(5.0, Some("alice"))],
+        };
+        emotion_track::save(&track).await.unwrap();
+
+        let report = purge_profile(&vaults, &recordings, "BOB").await.unwrap();
+        assert_eq!(
+            (
+                report.history_samples,
+                report.timeline_entries,
+                report.track_points
+            ),
+            (1, 1, 1)
+        );
+        let left = crate::emotion_history::full_history(&vaults);
+        assert_eq!(left.iter().map(|m| m.ts_unix).collect::<Vec<_>>(), [10, 30]);
+        let track = emotion_track::load(&recording).await.unwrap();
+        assert_eq!(track.points.len(), 1);
+        assert_eq!(std::fs::read(&recording).unwrap(), b"media");
+
+        // Nothing left to remove the second time.
+        let again = purge_profile(&vaults, &recordings, "bob").await.unwrap();
+        assert_eq!(again.history_samples + again.timeline_entries, 0);
+
+        drop(vaults);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
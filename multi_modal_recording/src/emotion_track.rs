@@ -0,0 +1,175 @@
+//! Time-aligned emotion tracks stored next to recordings.
+//!
+//! Each `.phoenixrec` bundle gets an `<name>.phoenixrec.emotion.json` sidecar. Every emotional
+//! moment analyzed for that recording is appended with its offset from the recording start, so
+//! playback UIs can draw an emotion track under the scrubber and [`search`] can find the
+//! segment where a given emotion peaked.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::emotion_history::EmotionalMoment;
+
+/// Suffix appended to the recording file name for the sidecar.
+pub const EMOTION_TRACK_SUFFIX: &str = ".emotion.json";
+
+/// Points further apart than this are not merged into one segment.
+const SEGMENT_GAP_SECS: f64 = 10.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionTrackPoint {
+    /// Seconds from the start of the recording.
+    pub offset_secs: f64,
+    pub emotion: String,
+    pub intensity: f64,
+    pub confidence: f64,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub valence: Option<f64>,
+    #[serde(default)]
+    pub arousal: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionTrack {
+    pub recording: PathBuf,
+    pub started_unix: i64,
+    pub duration_secs: u64,
+    /// Ordered by `offset_secs`.
+    pub points: Vec<EmotionTrackPoint>,
+}
+
+/// A contiguous run of the same emotion inside one recording.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionSegment {
+    pub recording: PathBuf,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub emotion: String,
+    pub peak_intensity: f64,
+    pub profile: Option<String>,
+}
+
+pub fn sidecar_path(recording: &Path) -> PathBuf {
+    let mut name = recording.as_os_str().to_owned();
+    name.push(EMOTION_TRACK_SUFFIX);
+    PathBuf::from(name)
+}
+
+impl EmotionTrack {
+    pub fn new(recording: &Path, started_unix: i64, duration_secs: u64) -> Self {
+        Self {
+            recording: recording.to_path_buf(),
+            started_unix,
+            duration_secs,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, moment: &EmotionalMoment) {
+        let offset_secs = (moment.ts_unix - self.started_unix).max(0) as f64;
+        let at = self
+            .points
+            .partition_point(|p| p.offset_secs <= offset_secs);
+        self.points.insert(
+            at,
+            EmotionTrackPoint {
+                offset_secs,
+                emotion: moment.emotion.clone(),
+                intensity: moment.intensity,
+                confidence: moment.confidence,
+                profile: moment.profile.clone(),
+                valence: moment.valence,
+                arousal: moment.arousal,
+            },
+        );
+    }
+
+    /// Merge consecutive points with the same emotion (and profile) into segments.
+    pub fn segments(&self) -> Vec<EmotionSegment> {
+        let mut out: Vec<EmotionSegment> = Vec::new();
+        for p in &self.points {
+            match out.last_mut() {
+                Some(seg)
+                    if seg.emotion == p.emotion
+                        && seg.profile == p.profile
+                        && p.offset_secs - seg.end_secs <= SEGMENT_GAP_SECS =>
+                {
+                    seg.end_secs = p.offset_secs;
+                    seg.peak_intensity = seg.peak_intensity.max(p.intensity);
+                }
+                _ => out.push(EmotionSegment {
+                    recording: self.recording.clone(),
+                    start_secs: p.offset_secs,
+                    end_secs: p.offset_secs,
+                    emotion: p.emotion.clone(),
+                    peak_intensity: p.intensity,
+                    profile: p.profile.clone(),
+                }),
+            }
+        }
+        out
+    }
+}
+
+pub async fn load(recording: &Path) -> Option<EmotionTrack> {
+    let raw = tokio::fs::read(sidecar_path(recording)).await.ok()?;
+    serde_json::from_slice(&crate::sealed::open(&raw).ok()?).ok()
+}
+
+pub async fn save(track: &EmotionTrack) -> Result<(), crate::Error> {
+    let raw = crate::sealed::seal(&serde_json::to_vec(track)?)?;
+    tokio::fs::write(sidecar_path(&track.recording), raw).await?;
+    Ok(())
+}
+
+/// Append `moment` to the sidecar of `recording` (no-op when the recording has no sidecar).
+pub(crate) async fn append(recording: &Path, moment: &EmotionalMoment) {
+    if let Some(mut track) = load(recording).await {
+        track.push(moment);
+        let _ = save(&track).await;
+    }
+}
+
+/// Segments of `emotion` (case-insensitive) at or above `min_intensity` across every
+/// sidecar in `storage_dir`, strongest first.
+pub async fn search(
+    storage_dir: &Path,
+    emotion: &str,
+    min_intensity: f64,
+    profile: Option<&str>,
+) -> Result<Vec<EmotionSegment>, crate::Error> {
+    let mut out = Vec::new();
+    if !tokio::fs::try_exists(storage_dir).await.unwrap_or(false) {
+        return Ok(out);
+    }
+    let mut rd = tokio::fs::read_dir(storage_dir).await?;
+    while let Some(entry) = rd.next_entry().await? {
+        let p = entry.path();
+        let is_sidecar = p
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(EMOTION_TRACK_SUFFIX));
+        if !is_sidecar {
+            continue;
+        }
+        let Ok(raw) = tokio::fs::read(&p).await else {
+            continue;
+        };
+        let Ok(track) = serde_json::from_slice::<EmotionTrack>(&raw) else {
+            continue;
+        };
+        out.extend(track.segments().into_iter().filter(|s| {
+            s.emotion.eq_ignore_ascii_case(emotion)
+                && s.peak_intensity >= min_intensity
+                && profile.is_none_or(|want| s.profile.as_deref() == Some(want))
+        }));
+    }
+    out.sort_by(|a, b| {
+        b.peak_intensity
+            .partial_cmp(&a.peak_intensity)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    Ok(out)
+}
@@ -0,0 +1,310 @@
+//! Chart-ready emotion trends.
+//!
+//! Buckets the persistent emotion history by local hour, day, or ISO week and reports each
+//! bucket's confidence-weighted emotion distribution and average valence/arousal, plus totals
+//! for the same-length period immediately before the requested range so charts can show
+//! "vs. previous period" deltas without pulling raw samples.
+
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::emotion_history::{self, EmotionQuery, EmotionalMoment};
+use crate::Error;
+
+/// Upper bound on buckets in a single response.
+pub const TREND_MAX_BUCKETS: usize = 1_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendBucket {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl TrendBucket {
+    /// Range used when the caller gives no `from_unix`.
+    fn default_span_secs(self) -> i64 {
+        match self {
+            TrendBucket::Hour => 24 * 3600,
+            TrendBucket::Day => 7 * 86_400,
+            TrendBucket::Week => 8 * 7 * 86_400,
+        }
+    }
+
+    fn approx_secs(self) -> i64 {
+        match self {
+            TrendBucket::Hour => 3600,
+            TrendBucket::Day => 86_400,
+            TrendBucket::Week => 7 * 86_400,
+        }
+    }
+
+    fn local_date(ts_unix: i64) -> NaiveDate {
+        Local
+            .timestamp_opt(ts_unix, 0)
+            .single()
+            .map(|t| t.date_naive())
+            .unwrap_or_default()
+    }
+
+    fn midnight(date: NaiveDate) -> i64 {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+            .map(|t| t.timestamp())
+            .unwrap_or_default()
+    }
+
+    /// Start of the bucket containing `ts_unix`.
+    fn floor(self, ts_unix: i64) -> i64 {
+        match self {
+            TrendBucket::Hour => Local
+                .timestamp_opt(ts_unix, 0)
+                .single()
+                .map(|t| ts_unix - i64::from(t.minute() * 60 + t.second()))
+                .unwrap_or(ts_unix),
+            TrendBucket::Day => Self::midnight(Self::local_date(ts_unix)),
+            TrendBucket::Week => {
+                let date = Self::local_date(ts_unix);
+                let monday = date
+                    .checked_sub_days(Days::new(u64::from(date.weekday().num_days_from_monday())))
+                    .unwrap_or(date);
+                Self::midnight(monday)
+            }
+        }
+    }
+
+    /// Start of the bucket following the one starting at `start`.
+    fn next(self, start: i64) -> i64 {
+        match self {
+            TrendBucket::Hour => start + 3600,
+            TrendBucket::Day | TrendBucket::Week => {
+                let days = if self == TrendBucket::Day { 1 } else { 7 };
+                let next = Self::local_date(start)
+                    .checked_add_days(Days::new(days))
+                    .map(Self::midnight)
+                    .unwrap_or_default();
+                // Guard against a DST edge producing a non-advancing boundary.
+                next.max(start + 3600)
+            }
+        }
+    }
+}
+
+/// Parameters for [`compute`]. Bounds are Unix seconds (`from` inclusive, `to` exclusive);
+/// `to` defaults to now and `from` to a bucket-dependent span before `to`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrendQuery {
+    #[serde(default)]
+    pub bucket: TrendBucket,
+    #[serde(default)]
+    pub from_unix: Option<i64>,
+    #[serde(default)]
+    pub to_unix: Option<i64>,
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// Aggregate over a set of samples.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrendStats {
+    pub samples: usize,
+    /// Confidence-weighted share per emotion (sums to 1.0 when `samples > 0`).
+    pub distribution: BTreeMap<String, f64>,
+    pub average_valence: Option<f64>,
+    pub average_arousal: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub start_unix: i64,
+    pub end_unix: i64,
+    #[serde(flatten)]
+    pub stats: TrendStats,
+}
+
+/// Current period minus previous period.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TrendChange {
+    pub samples: i64,
+    pub average_valence: Option<f64>,
+    pub average_arousal: Option<f64>,
+    /// Share delta for every emotion seen in either period.
+    pub distribution: BTreeMap<String, f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionTrends {
+    pub bucket: TrendBucket,
+    pub from_unix: i64,
+    pub to_unix: i64,
+    pub profile: Option<String>,
+    /// One point per bucket (empty buckets included), oldest first.
+    pub points: Vec<TrendPoint>,
+    pub current: TrendStats,
+    /// Same-length period ending at `from_unix`.
+    pub previous: TrendStats,
+    pub change: TrendChange,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0usize), |(s, n), v| (s + v, n + 1));
+    (n > 0).then(|| sum / n as f64)
+}
+
+pub fn stats<'a>(moments: impl IntoIterator<Item = &'a EmotionalMoment>) -> TrendStats {
+    let moments = moments.into_iter().collect::<Vec<_>>();
+    let mut distribution = BTreeMap::<String, f64>::new();
+    for m in &moments {
+        *distribution.entry(m.emotion.clone()).or_default() += m.confidence.max(0.0);
+    }
+    let total: f64 = distribution.values().sum();
+    if total > 0.0 {
+        distribution.values_mut().for_each(|v| *v /= total);
+    }
+    TrendStats {
+        samples: moments.len(),
+        distribution,
+        average_valence: mean(moments.iter().filter_map(|m| m.valence)),
+        average_arousal: mean(moments.iter().filter_map(|m| m.arousal)),
+    }
+}
+
+fn delta(current: Option<f64>, previous: Option<f64>) -> Option<f64> {
+    Some(current? - previous?)
+}
+
+pub fn compare(current: &TrendStats, previous: &TrendStats) -> TrendChange {
+    let mut distribution = BTreeMap::new();
+    for emotion in current
+        .distribution
+        .keys()
+        .chain(previous.distribution.keys())
+    {
+        let now = current.distribution.get(emotion).copied().unwrap_or(0.0);
+        let before = previous.distribution.get(emotion).copied().unwrap_or(0.0);
+        distribution.insert(emotion.clone(), now - before);
+    }
+    TrendChange {
+        samples: current.samples as i64 - previous.samples as i64,
+        average_valence: delta(current.average_valence, previous.average_valence),
+        average_arousal: delta(current.average_arousal, previous.average_arousal),
+        distribution,
+    }
+}
+
+/// Split `moments` (oldest first) into buckets covering `[from, to)`.
+pub fn bucketize(
+    moments: &[EmotionalMoment],
+    bucket: TrendBucket,
+    from_unix: i64,
+    to_unix: i64,
+) -> Vec<TrendPoint> {
+    let mut points = Vec::new();
+    let mut start = bucket.floor(from_unix);
+    while start < to_unix && points.len() < TREND_MAX_BUCKETS {
+        let end = bucket.next(start);
+        let lo = moments.partition_point(|m| m.ts_unix < start.max(from_unix));
+        let hi = moments.partition_point(|m| m.ts_unix < end.min(to_unix));
+        points.push(TrendPoint {
+            start_unix: start,
+            end_unix: end,
+            stats: stats(&moments[lo..hi.max(lo)]),
+        });
+        start = end;
+    }
+    points
+}
+
+/// Bucketed trends for `q`, with the previous period for comparison.
+pub fn compute(
+    vaults: &VitalOrganVaults,
+    q: &TrendQuery,
+    now_unix: i64,
+) -> Result<EmotionTrends, Error> {
+    let to_unix = q.to_unix.unwrap_or(now_unix);
+    let from_unix = q
+        .from_unix
+        .unwrap_or(to_unix - q.bucket.default_span_secs());
+    if from_unix >= to_unix {
+        return Err(Error::InvalidArgument(
+            "from_unix must be before to_unix".to_string(),
+        ));
+    }
+    let span = to_unix - from_unix;
+    if span / q.bucket.approx_secs() > TREND_MAX_BUCKETS as i64 {
+        return Err(Error::InvalidArgument(format!(
+            "range spans more than {TREND_MAX_BUCKETS} buckets"
+        )));
+    }
+
+    let query = |from, to| EmotionQuery {
+        from_unix: Some(from),
+        to_unix: Some(to),
+        profile: q.profile.clone(),
+        ..EmotionQuery::default()
+    };
+    let moments = emotion_history::query_moments(vaults, &query(from_unix, to_unix));
+    let earlier = emotion_history::query_moments(vaults, &query(from_unix - span, from_unix));
+
+    let current = stats(&moments);
+    let previous = stats(&earlier);
+    Ok(EmotionTrends {
+        bucket: q.bucket,
+        from_unix,
+        to_unix,
+        profile: q.profile.clone(),
+        points: bucketize(&moments, q.bucket, from_unix, to_unix),
+        change: compare(&current, &previous),
+        current,
+        previous,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moment(ts_unix: i64, emotion: &str, valence: f64) -> EmotionalMoment {
+        EmotionalMoment {
+            ts_unix,
+            emotion: emotion.to_string(),
+            intensity: 0.5,
+            confidence: 1.0,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            recording: String::new(),
+            profile: None,
+            valence: Some(valence),
+            arousal: None,
+        }
+    }
+
+    #[test]
+    fn buckets_and_compares_periods() {
+        let start = TrendBucket::Hour.floor(1_700_000_000);
+        let moments = vec![
+            moment(start + 10, "Joy", 0.8),
+            moment(start + 20, "Sadness", -0.4),
+            moment(start + 3600 + 5, "Joy", 0.6),
+        ];
+        let points = bucketize(&moments, TrendBucket::Hour, start, start + 3 * 3600);
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].stats.samples, 2);
+        assert_eq!(points[0].stats.distribution["Joy"], 0.5);
+        assert_eq!(points[1].stats.samples, 1);
+        assert_eq!(points[2].stats.samples, 0);
+        assert!(points[2].stats.average_valence.is_none());
+
+        let current = stats(&moments);
+        let previous = stats(&moments[1..2]);
+        let change = compare(&current, &previous);
+        assert_eq!(change.samples, 2);
+        assert!((change.distribution["Sadness"] + 2.0 / 3.0).abs() < 1e-9);
+        assert!(change.average_valence.unwrap() > 0.0);
+    }
+}
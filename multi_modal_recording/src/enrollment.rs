@@ -0,0 +1,439 @@
+//! Guided enrollment sessions.
+//!
+//! The backend owns the whole flow; a frontend only renders the current prompt and calls
+//! `capture` / `submit` / `finalize`:
+//! 1. [`EnrollmentSession::new`] builds a list of prompts (phrases to say, or face angles).
+//! 2. Each step gets a sample, either captured live by the recorder or submitted as a file.
+//! 3. Every sample is scored by [`score_sample`]; rejected samples can simply be retaken.
+//! 4. Finalizing enrolls all accepted samples into the versioned embedding store.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::embeddings::{compute_embedding, cosine_similarity, EmbeddingModel, Modality};
+use crate::Error;
+
+/// Minimum number of accepted samples required to finalize a session.
+pub const MIN_ACCEPTED_SAMPLES: usize = 3;
+
+/// Samples scoring below this are rejected and should be retaken.
+pub const MIN_QUALITY_SCORE: f32 = 0.5;
+
+const VOICE_PROMPTS: &[&str] = &[
+    "Say: \"Hey Phoenix, it's me.\"",
+    "Say: \"The quick brown fox jumps over the lazy dog.\"",
+    "Say: \"Remind me to call home tonight.\"",
+    "Say: \"I'm feeling pretty good today.\"",
+    "Count slowly from one to ten.",
+];
+
+const FACE_PROMPTS: &[&str] = &[
+    "Look straight at the camera.",
+    "Turn your head slightly to the left.",
+    "Turn your head slightly to the right.",
+    "Tilt your chin up a little.",
+    "Tilt your chin down a little.",
+];
+
+/// One prompt shown to the user.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnrollmentPrompt {
+    pub index: usize,
+    pub instruction: String,
+}
+
+/// Quality assessment for one sample.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SampleQuality {
+    /// 0.0..=1.0
+    pub score: f32,
+    pub accepted: bool,
+    pub issues: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CapturedSample {
+    pub index: usize,
+    pub path: PathBuf,
+    pub quality: SampleQuality,
+}
+
+/// Client-facing view of a session.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnrollmentStatus {
+    pub session_id: String,
+    pub profile: String,
+    pub modality: Modality,
+    pub prompts: Vec<EnrollmentPrompt>,
+    pub samples: Vec<CapturedSample>,
+    /// Next prompt without an accepted sample (None when every prompt is done).
+    pub next_prompt: Option<EnrollmentPrompt>,
+    pub accepted: usize,
+    pub can_finalize: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnrollmentSession {
+    pub id: String,
+    pub profile: String,
+    pub modality: Modality,
+    pub prompts: Vec<EnrollmentPrompt>,
+    pub samples: Vec<CapturedSample>,
+    pub started_unix: i64,
+    /// Directory holding this session's captured samples.
+    pub dir: PathBuf,
+}
+
+impl EnrollmentSession {
+    pub fn new(
+        profile: &str,
+        modality: Modality,
+        steps: usize,
+        work_root: &Path,
+    ) -> Result<Self, Error> {
+        let profile = profile.trim();
+        if profile.is_empty() {
+            return Err(Error::InvalidArgument(
+                "profile name must not be empty".to_string(),
+            ));
+        }
+        let pool = match modality {
+            Modality::Voice => VOICE_PROMPTS,
+            Modality::Face => FACE_PROMPTS,
+        };
+        let steps = steps.clamp(MIN_ACCEPTED_SAMPLES, pool.len());
+        let id = uuid::Uuid::new_v4().to_string();
+        Ok(Self {
+            dir: work_root.join(&id),
+            id,
+            profile: profile.to_string(),
+            modality,
+            prompts: pool
+                .iter()
+                .take(steps)
+                .enumerate()
+                .map(|(index, p)| EnrollmentPrompt {
+                    index,
+                    instruction: p.to_string(),
+                })
+                .collect(),
+            samples: Vec::new(),
+            started_unix: Utc::now().timestamp(),
+        })
+    }
+
+    fn accepted(&self) -> impl Iterator<Item = &CapturedSample> {
+        self.samples.iter().filter(|s| s.quality.accepted)
+    }
+
+    /// Index of the first prompt without an accepted sample.
+    pub fn next_index(&self) -> Option<usize> {
+        self.prompts
+            .iter()
+            .map(|p| p.index)
+            .find(|i| !self.accepted().any(|s| s.index == *i))
+    }
+
+    /// Score `path` for prompt `index` and record it (replacing an earlier take).
+    pub fn record_sample(&mut self, index: usize, path: PathBuf) -> Result<CapturedSample, Error> {
+        if index >= self.prompts.len() {
+            return Err(Error::InvalidArgument(format!(
+                "prompt index {index} out of range (0..{})",
+                self.prompts.len()
+            )));
+        }
+        let others: Vec<PathBuf> = self
+            .accepted()
+            .filter(|s| s.index != index)
+            .map(|s| s.path.clone())
+            .collect();
+        let quality = score_sample(self.modality, &path, &others)?;
+        let sample = CapturedSample {
+            index,
+            path,
+            quality,
+        };
+        self.samples.retain(|s| s.index != index);
+        self.samples.push(sample.clone());
+        self.samples.sort_by_key(|s| s.index);
+        Ok(sample)
+    }
+
+    pub fn accepted_paths(&self) -> Vec<PathBuf> {
+        self.accepted().map(|s| s.path.clone()).collect()
+    }
+
+    pub fn status(&self) -> EnrollmentStatus {
+        let accepted = self.accepted().count();
+        EnrollmentStatus {
+            session_id: self.id.clone(),
+            profile: self.profile.clone(),
+            modality: self.modality,
+            prompts: self.prompts.clone(),
+            samples: self.samples.clone(),
+            next_prompt: self.next_index().map(|i| self.prompts[i].clone()),
+            accepted,
+            can_finalize: accepted >= MIN_ACCEPTED_SAMPLES,
+        }
+    }
+}
+
+/// Heuristic quality score for an enrollment sample.
+///
+/// - face: image must decode, be at least 160×160, and be neither too dark, too bright, nor flat
+/// - voice: clip must be non-trivially sized
+/// - both: near-identical duplicates of already-accepted samples are rejected
+pub fn score_sample(
+    modality: Modality,
+    path: &Path,
+    accepted: &[PathBuf],
+) -> Result<SampleQuality, Error> {
+    let bytes = std::fs::read(path)?;
+    let mut issues = Vec::new();
+    let mut score: f32 = 1.0;
+
+    match modality {
+        Modality::Face => match image::load_from_memory(&bytes) {
+            Ok(img) => {
+                let luma = img.to_luma8();
+                let (w, h) = luma.dimensions();
+                if w < 160 || h < 160 {
+                    issues.push(format!("image too small ({w}x{h}); move closer"));
+                    score -= 0.5;
+                }
+                let n = (w as f64 * h as f64).max(1.0);
+                let mean = luma.pixels().map(|p| p.0[0] as f64).sum::<f64>() / n;
+                let var = luma
+                    .pixels()
+                    .map(|p| (p.0[0] as f64 - mean).powi(2))
+                    .sum::<f64>()
+                    / n;
+                if mean < 40.0 {
+                    issues.push("image too dark; add light".to_string());
+                    score -= 0.4;
+                } else if mean > 220.0 {
+                    issues.push("image overexposed; reduce backlight".to_string());
+                    score -= 0.4;
+                }
+                if var.sqrt() < 12.0 {
+                    issues.push("image has very little detail; is the lens covered?".to_string());
+                    score -= 0.4;
+                }
+            }
+            Err(e) => {
+                issues.push(format!("not a readable image: {e}"));
+                score = 0.0;
+            }
+        },
+        Modality::Voice => {
+            if bytes.len() < 4 * 1024 {
+                issues.push("clip too short; speak the whole phrase".to_string());
+                score -= 0.6;
+            }
+        }
+    }
+
+    let model = EmbeddingModel::current(modality);
+    let emb = compute_embedding(&bytes, &model);
+    for other in accepted {
+        if let Ok(other_bytes) = std::fs::read(other) {
+            if cosine_similarity(&emb, &compute_embedding(&other_bytes, &model)) > 0.999 {
+                issues.push("duplicate of an earlier sample; vary the take".to_string());
+                score -= 0.6;
+                break;
+            }
+        }
+    }
+
+    let score = score.clamp(0.0, 1.0);
+    Ok(SampleQuality {
+        score,
+        accepted: score >= MIN_QUALITY_SCORE,
+        issues,
+    })
+}
+
+/// File extensions accepted as voice / face enrollment samples.
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "ogg", "opus", "flac", "m4a", "aac"];
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp"];
+
+/// Enrollment modality for a file, from its extension (audio → voice, image → face).
+pub fn modality_for_path(path: &Path) -> Option<Modality> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    if AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        Some(Modality::Voice)
+    } else if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        Some(Modality::Face)
+    } else {
+        None
+    }
+}
+
+/// Per-file outcome of enrolling loose files (e.g. dropped onto the window).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileEnrollmentResult {
+    pub path: PathBuf,
+    pub modality: Option<Modality>,
+    pub quality: Option<SampleQuality>,
+    pub enrolled: bool,
+    pub error: Option<String>,
+}
+
+/// Grab one frame from the default webcam and save it as a PNG.
+#[cfg(feature = "video")]
+pub(crate) async fn capture_face_frame(dest: &Path) -> Result<(), Error> {
+    use nokhwa::pixel_format::RgbFormat;
+
+    let mut input = multi_modal_input::LiveMultiModalInput::from_env();
+    input.webcam_enabled = true;
+    let mut vs = input
+        .start_webcam_stream()
+        .await
+        .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))?;
+    vs.camera
+        .open_stream()
+        .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))?;
+    // The first frames after opening are often black while auto-exposure settles.
+    let mut frame = None;
+    for _ in 0..5 {
+        frame = vs.camera.frame().ok();
+    }
+    let _ = vs.camera.stop_stream();
+    let rgb = frame
+        .ok_or_else(|| Error::Io(std::io::Error::other("webcam returned no frame")))?
+        .decode_image::<RgbFormat>()
+        .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))?;
+    rgb.save(dest)
+        .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))
+}
+
+#[cfg(not(feature = "video"))]
+pub(crate) async fn capture_face_frame(_dest: &Path) -> Result<(), Error> {
+    Err(Error::FeatureDisabled("video"))
+}
+
+/// Record `secs` seconds from the default microphone into a 16-bit mono WAV file.
+#[cfg(feature = "audio")]
+pub(crate) async fn capture_voice_clip(dest: &Path, secs: u64) -> Result<(), Error> {
+    let dest = dest.to_path_buf();
+    tokio::task::spawn_blocking(move || -> Result<(), Error> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+        use std::sync::{Arc, Mutex};
+
+        let io = |e: String| Error::Io(std::io::Error::other(e));
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| io("no default input device".to_string()))?;
+        let config = device
+            .default_input_config()
+            .map_err(|e| io(e.to_string()))?;
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels() as usize;
+        let samples = Arc::new(Mutex::new(Vec::<i16>::new()));
+
+        let sink = samples.clone();
+        let on_err = |e| tracing::warn!(target: "recorder", "enrollment capture error: {e}");
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    if let Ok(mut buf) = sink.lock() {
+                        buf.extend(
+                            data.chunks(channels)
+                                .map(|c| (c[0].clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+                        );
+                    }
+                },
+                on_err,
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    if let Ok(mut buf) = sink.lock() {
+                        buf.extend(data.chunks(channels).map(|c| c[0]));
+                    }
+                },
+                on_err,
+                None,
+            ),
+            other => return Err(io(format!("unsupported sample format: {other:?}"))),
+        }
+        .map_err(|e| io(e.to_string()))?;
+        stream.play().map_err(|e| io(e.to_string()))?;
+        std::thread::sleep(std::time::Duration::from_secs(secs));
+        drop(stream);
+
+        let pcm = samples.lock().map(|b| b.clone()).unwrap_or_default();
+        std::fs::write(&dest, encode_wav_mono16(&pcm, sample_rate))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| Error::Io(std::io::Error::other(e)))?
+}
+
+#[cfg(not(feature = "audio"))]
+pub(crate) async fn capture_voice_clip(_dest: &Path, _secs: u64) -> Result<(), Error> {
+    Err(Error::FeatureDisabled("audio"))
+}
+
+#[cfg(feature = "audio")]
+fn encode_wav_mono16(pcm: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_len = (pcm.len() * 2) as u32;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for s in pcm {
+        out.extend_from_slice(&s.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, f: impl Fn(u32, u32) -> u8) {
+        image::GrayImage::from_fn(200, 200, |x, y| image::Luma([f(x, y)]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn scoring_rejects_dark_and_duplicate_faces() {
+        let dir = std::env::temp_dir().join(format!("enroll-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("good.png");
+        let dark = dir.join("dark.png");
+        write_png(&good, |x, y| ((x * 7 + y * 3) % 200 + 30) as u8);
+        write_png(&dark, |_, _| 5);
+
+        let mut session = EnrollmentSession::new("Mom", Modality::Face, 3, &dir).unwrap();
+        assert!(
+            session
+                .record_sample(0, good.clone())
+                .unwrap()
+                .quality
+                .accepted
+        );
+        assert!(!session.record_sample(1, dark).unwrap().quality.accepted);
+        assert!(!session.record_sample(2, good).unwrap().quality.accepted);
+
+        let status = session.status();
+        assert_eq!(status.accepted, 1);
+        assert_eq!(status.next_prompt.map(|p| p.index), Some(1));
+        assert!(!status.can_finalize);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
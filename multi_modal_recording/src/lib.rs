@@ -7,22 +7,70 @@
 //!   - `video` => [`nokhwa`](https://crates.io/crates/nokhwa)
 //!   - `speech-vosk` / `speech-whisper` => [`vosk`](https://crates.io/crates/vosk) / [`whisper-rs`](https://crates.io/crates/whisper-rs)
 //!   - `face-rustface` / `face-dlib` => [`rustface`](https://crates.io/crates/rustface) / [`dlib-face-recognition`](https://crates.io/crates/dlib-face-recognition)
+//!   - `emotion-onnx` / `emotion-remote` => ONNX or remote emotion inference
+//! - `face`, `emotion` and `stt` switch on the backends of a whole subsystem; [`Capabilities`]
+//!   tells frontends which ones a build has.
 
 use chrono::Utc;
+pub use emotion_detection::affect::Affect;
+use emotion_detection::calibration::EmotionStabilizer;
+pub use emotion_detection::calibration::{CalibrationConfig, ReportedEmotion};
+use emotion_detection::fusion::FusionWindow;
+pub use emotion_detection::fusion::{FusedEmotion, FusionWeights};
 use emotion_detection::{EmotionDetector, EmotionalState};
 use image::DynamicImage;
-use multi_modal_input::LiveMultiModalInput;
+use multi_modal_input::{InputLevel, LiveMultiModalInput};
+use pagi_config::{ConfigError, Layers, Overrides};
+use pagi_errors::{ErrorCode, PagiError};
+use phoenix_storage::Storage;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering},
+    Arc, OnceLock, RwLock,
 };
-use tokio::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::{broadcast, Mutex};
+use tracing::Instrument;
 use vital_organ_vaults::VitalOrganVaults;
 
+pub mod confirmation;
+pub mod embeddings;
+pub mod emotion_alerts;
+pub mod emotion_export;
+pub mod emotion_history;
+pub mod emotion_privacy;
+pub mod emotion_track;
+pub mod emotion_trends;
+pub mod enrollment;
+pub mod model_manager;
+pub mod mood_summary;
+pub mod playback;
+pub mod presence;
+pub mod recognition;
+pub mod recording_library;
+pub mod sealed;
+
+use confirmation::{ConfirmationTokens, CONFIRMATION_TTL};
+use embeddings::{CompatibilityReport, EmbeddingModel, EmbeddingStore, MigrationReport, Modality};
+use emotion_alerts::{AlertEngine, AlertRules, EmotionAlert, ALERT_RULES_KEY};
+pub use emotion_detection::text::TextSource;
+use emotion_history::{
+    EmotionQuery, EmotionUpdate, EmotionalMoment, ATTRIBUTION_WINDOW_SECS, EMOTION_TIMELINE_KEY,
+};
+use emotion_privacy::{EmotionPrivacy, ProfileEmotionPrivacy, PurgeReport};
+use emotion_track::{EmotionSegment, EmotionTrack};
+use enrollment::{CapturedSample, EnrollmentSession, EnrollmentStatus, MIN_ACCEPTED_SAMPLES};
+use model_manager::ModelManager;
+use presence::{PresenceEvent, UnknownPresenceEvent, UNKNOWN_PRESENCE_COOLDOWN_SECS};
+use recognition::{CalibrationReport, ProfileMatch, RecognitionThresholds, ThresholdStore};
+
+/// Profile label used for the single enrolled household member.
+pub const DEFAULT_PROFILE: &str = "Dad";
+
 /// Image type used by [`MultiModalRecorder::recognize_user()`](crate::MultiModalRecorder::recognize_user).
 pub type Image = DynamicImage;
 
@@ -36,9 +84,302 @@ pub enum Error {
 
     #[error("feature not enabled: {0}")]
     FeatureDisabled(&'static str),
+
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("guest mode is active: {0}")]
+    GuestMode(&'static str),
+
+    #[error("recorder is shutting down")]
+    ShuttingDown,
+
+    #[error("confirmation required: {0}")]
+    ConfirmationRequired(&'static str),
+
+    #[error("storage error: {0}")]
+    Storage(#[from] phoenix_storage::StorageError),
+}
+
+impl Error {
+    /// The stable code frontends branch on.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+            Error::Io(_) => ErrorCode::Io,
+            Error::InvalidArgument(_) => ErrorCode::InvalidArgument,
+            Error::FeatureDisabled(_) => ErrorCode::FeatureDisabled,
+            Error::Serialization(_) => ErrorCode::Internal,
+            Error::GuestMode(_) => ErrorCode::GuestMode,
+            Error::ShuttingDown => ErrorCode::ShuttingDown,
+            Error::ConfirmationRequired(_) => ErrorCode::ConfirmationRequired,
+            Error::Storage(_) => ErrorCode::Storage,
+        }
+    }
+}
+
+impl From<Error> for PagiError {
+    fn from(e: Error) -> Self {
+        let code = e.code();
+        match e {
+            Error::InvalidArgument(msg) => PagiError::new(code, msg),
+            Error::FeatureDisabled(what) => {
+                PagiError::new(code, format!("This build doesn't include {what} support."))
+            }
+            Error::GuestMode(what) => PagiError::new(code, format!("Guest mode is on; {what}.")),
+            Error::ShuttingDown => PagiError::new(code, code.default_message()),
+            Error::ConfirmationRequired(what) => {
+                PagiError::new(code, format!("Please confirm first; {what}."))
+            }
+            Error::Storage(e) => e.into(),
+            Error::Io(_) | Error::Serialization(_) => PagiError::from_cause(code, e),
+        }
+    }
+}
+
+static STORAGE: OnceLock<Storage> = OnceLock::new();
+
+/// Keep the emotion history and the recordings index in `storage` for the rest of the process.
+///
+/// Without it, history stays in Soul-Vault keys and every recordings listing re-reads each
+/// bundle header. Only the first call has an effect.
+pub fn use_storage(storage: Storage) {
+    if STORAGE.set(storage).is_err() {
+        tracing::warn!("multi_modal_recording storage was already set; keeping the first one");
+    }
+}
+
+pub(crate) fn storage() -> Option<&'static Storage> {
+    STORAGE.get()
+}
+
+/// Append an entry to the audit log of the database set with [`use_storage`], if any.
+///
+/// The recorder is driven directly (desktop app, chat commands), with no API key or session
+/// behind the call, so entries are attributed to `recorder` and the active profile.
+fn audit(action: &str, params: serde_json::Value) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    let entry = phoenix_storage::audit::AuditEntry {
+        ts_unix: Utc::now().timestamp(),
+        actor: "recorder".to_string(),
+        profile: storage.active_profile().ok(),
+        action: action.to_string(),
+        params: params.to_string(),
+    };
+    if let Err(e) = storage.append_audit(&entry) {
+        tracing::warn!("audit entry {action} not written: {e}");
+    }
+}
+
+/// Make sure a recognized or newly enrolled person has a profile row, so their data shows up in
+/// the profile list and is removed with the profile.
+fn register_profile(profile: &str) {
+    if let Some(storage) = storage() {
+        if let Err(e) = storage.create_profile(profile, profile, Utc::now().timestamp()) {
+            tracing::warn!("registering profile {profile} failed: {e}");
+        }
+    }
+}
+
+const CLEAR_ALL_OPERATION: &str = "clear_all_recordings";
+
+/// What clearing all recordings would delete, and the token that authorizes it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClearAllConfirmation {
+    pub token: String,
+    pub recordings: u64,
+    /// Recordings plus their emotion-track sidecars.
+    pub bytes: u64,
+    pub expires_unix: i64,
+}
+
+/// Guest / incognito mode status.
+///
+/// While enabled, no recognition or emotion analysis runs and nothing biometric is computed or
+/// stored. Recordings are refused unless `allow_recordings` is set.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct GuestMode {
+    pub enabled: bool,
+    pub allow_recordings: bool,
+}
+
+/// User-facing recorder configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecorderConfig {
+    pub audio_enabled: bool,
+    pub video_enabled: bool,
+    /// Start always-listening when the app starts.
+    pub always_listening: bool,
+    pub wake_word: String,
+    /// Directory encrypted recordings are written to.
+    pub storage_path: PathBuf,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            audio_enabled: true,
+            video_enabled: true,
+            always_listening: false,
+            wake_word: "Phoenix".to_string(),
+            storage_path: PathBuf::from("./data/recordings/encrypted"),
+        }
+    }
+}
+
+impl RecorderConfig {
+    /// The `[recorder]` table of the shared settings (see [`pagi_config`]): `audio`, `video`,
+    /// `always_listening`, `wake_word` and `storage_path`, each also settable through the
+    /// environment variable it has always had (`MULTI_MODAL_ENABLED` sets both tracks).
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let not_empty = |s: &str| match s.trim() {
+            "" => Err("must not be empty".to_string()),
+            s => Ok(s.to_string()),
+        };
+        Ok(Self {
+            audio_enabled: layers.flag("recorder.audio", defaults.audio_enabled)?,
+            video_enabled: layers.flag("recorder.video", defaults.video_enabled)?,
+            always_listening: layers
+                .flag("recorder.always_listening", defaults.always_listening)?,
+            wake_word: layers.or("recorder.wake_word", defaults.wake_word, not_empty)?,
+            storage_path: layers.or("recorder.storage_path", defaults.storage_path, |s| {
+                not_empty(s).map(PathBuf::from)
+            })?,
+        })
+    }
+
+    /// The config file and environment, as the web server reads them.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_layers(&Layers::load(&Overrides::default())?)
+    }
+
+    /// The environment alone; settings it gets wrong fall back to the defaults.
+    pub fn from_env() -> Self {
+        Self::from_layers(&Layers::env_only()).unwrap_or_else(|e| {
+            tracing::warn!("{e}; using the default recorder settings");
+            Self::default()
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.wake_word.trim().is_empty() {
+            return Err(Error::InvalidArgument(
+                "wake_word must not be empty".to_string(),
+            ));
+        }
+        if self.storage_path.as_os_str().is_empty() {
+            return Err(Error::InvalidArgument(
+                "storage_path must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What the recorder is doing right now, for tray/status indicators.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecorderStatus {
+    /// At least one on-demand recording is being captured.
+    pub recording: bool,
+    pub always_listening: bool,
+    pub live_streaming: bool,
+    pub guest_mode: bool,
+}
+
+/// The optional subsystems compiled into this build, so frontends can hide what can't work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Microphone capture and playback (`audio`).
+    pub audio: bool,
+    /// Webcam capture (`video`).
+    pub video: bool,
+    /// Face detection and recognition (`face`).
+    pub face: bool,
+    /// Model-based emotion inference (`emotion`); without it the heuristic backend runs.
+    pub emotion: bool,
+    /// Speech-to-text and wake-word backends (`stt`).
+    pub stt: bool,
+    /// Downloading recognition models (`model-download`).
+    pub model_download: bool,
+}
+
+impl Capabilities {
+    pub const fn compiled() -> Self {
+        Self {
+            audio: cfg!(feature = "audio"),
+            video: cfg!(feature = "video"),
+            face: cfg!(any(feature = "face-rustface", feature = "face-dlib")),
+            emotion: cfg!(any(feature = "emotion-onnx", feature = "emotion-remote")),
+            stt: cfg!(any(feature = "speech-vosk", feature = "speech-whisper")),
+            model_download: cfg!(feature = "model-download"),
+        }
+    }
+}
+
+/// On-demand recording lifecycle (covers recordings started from any caller: commands, tray,
+/// scheduler, deep links).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RecordingEvent {
+    Started {
+        path: PathBuf,
+        duration_secs: u64,
+        audio: bool,
+        video: bool,
+    },
+    /// Seconds captured so far. The placeholder capture completes at once, so it currently sends
+    /// a single event with `elapsed_secs == duration_secs`.
+    Progress {
+        path: PathBuf,
+        elapsed_secs: u64,
+        duration_secs: u64,
+    },
+    Finished {
+        path: PathBuf,
+        duration_secs: u64,
+    },
+    Failed {
+        path: Option<PathBuf>,
+        error: String,
+    },
+}
+
+/// A recurring recording registered with [`MultiModalRecorder::schedule_recording`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingSchedule {
+    pub cron_expr: String,
+    pub purpose: String,
+}
+
+/// File (in the storage directory) that schedules are saved to on shutdown.
+pub const SCHEDULES_FILE: &str = ".schedules.json";
+
+/// What [`MultiModalRecorder::shutdown`] managed to do before returning.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    /// Schedules written to [`SCHEDULES_FILE`].
+    pub schedules_saved: usize,
+    /// Recordings still being written when the timeout ran out.
+    pub recordings_unfinished: usize,
+    /// Live streaming was still running when the timeout ran out.
+    pub live_streaming_running: bool,
+    pub error: Option<String>,
+}
+
+/// Outcome of one scheduled recording run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledRecordingResult {
+    pub ts_unix: i64,
+    pub cron_expr: String,
+    pub purpose: String,
+    pub path: Option<PathBuf>,
+    pub error: Option<String>,
 }
 
-/// Recognition confidence values for the enrolled user.
+/// Recognition confidence values for the best-matching enrolled profile.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct RecognitionConfidence {
     pub voice: f32,
@@ -48,6 +389,28 @@ pub struct RecognitionConfidence {
     pub label: Option<String>,
 }
 
+/// Marks an on-demand capture as in progress for [`MultiModalRecorder::status`].
+struct CaptureGuard<'a>(&'a MultiModalRecorder);
+
+impl<'a> CaptureGuard<'a> {
+    fn new(recorder: &'a MultiModalRecorder) -> Self {
+        recorder
+            .recordings_in_progress
+            .fetch_add(1, Ordering::Relaxed);
+        recorder.publish_status();
+        Self(recorder)
+    }
+}
+
+impl Drop for CaptureGuard<'_> {
+    fn drop(&mut self) {
+        self.0
+            .recordings_in_progress
+            .fetch_sub(1, Ordering::Relaxed);
+        self.0.publish_status();
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct RecordingMeta {
     created_unix: i64,
@@ -74,15 +437,54 @@ pub struct MultiModalRecorder {
     storage_path: PathBuf,
     last_recording: Arc<Mutex<Option<PathBuf>>>,
     listening_stop: Arc<AtomicBool>,
+    listening_running: Arc<AtomicBool>,
+    recordings_in_progress: Arc<AtomicUsize>,
+    shutting_down: Arc<AtomicBool>,
+    schedules: Arc<std::sync::Mutex<Vec<RecordingSchedule>>>,
+    status_tx: broadcast::Sender<RecorderStatus>,
+    schedule_tx: broadcast::Sender<ScheduledRecordingResult>,
+    recording_tx: broadcast::Sender<RecordingEvent>,
 
     // Live streaming mode (capture-only; no identification).
     live_stop: Arc<AtomicBool>,
     live_running: Arc<AtomicBool>,
+    input_level: InputLevel,
 
     // Emotion detection + persistence hooks
     emotion_detector: EmotionDetector,
     last_emotional_state: Arc<Mutex<Option<EmotionalState>>>,
+    emotion_tx: broadcast::Sender<EmotionUpdate>,
+    fusion_window: Arc<Mutex<FusionWindow>>,
+    last_fusion: Arc<Mutex<Option<FusedEmotion>>>,
+    stabilizer: Arc<Mutex<EmotionStabilizer>>,
     vaults: Option<Arc<VitalOrganVaults>>,
+
+    // Per-profile emotion privacy settings (persisted in the Soul Vault)
+    emotion_privacy: Arc<RwLock<EmotionPrivacy>>,
+
+    // Sustained / recurring negative-emotion alerts
+    alert_engine: Arc<std::sync::Mutex<AlertEngine>>,
+    alert_tx: broadcast::Sender<EmotionAlert>,
+
+    // Unknown-person / visitor events
+    presence_tx: broadcast::Sender<PresenceEvent>,
+    last_unknown_unix: Arc<AtomicI64>,
+
+    // Most recently recognized profile + when, used for emotion attribution.
+    recognized_profile: Arc<RwLock<Option<(String, i64)>>>,
+
+    // Guest / incognito mode
+    guest_enabled: Arc<AtomicBool>,
+    guest_allow_recordings: Arc<AtomicBool>,
+
+    // Guided enrollment sessions in progress, by session id.
+    enrollments: Arc<Mutex<HashMap<String, EnrollmentSession>>>,
+
+    // In-app playback of recordings.
+    player: playback::Player,
+
+    // Tokens guarding destructive operations (`request_clear_all` → `clear_all_recordings`).
+    confirmations: ConfirmationTokens,
 }
 
 impl std::fmt::Debug for MultiModalRecorder {
@@ -106,30 +508,20 @@ impl Default for MultiModalRecorder {
 }
 
 impl MultiModalRecorder {
-    /// Build a recorder from `.env` / environment variables.
-    ///
-    /// Reads:
-    /// - `MULTI_MODAL_ENABLED`
-    /// - `ALWAYS_LISTENING_ENABLED`
-    /// - `WAKE_WORD`
-    /// - `RECORDING_STORAGE_PATH`
+    /// Build a recorder from `.env` / environment variables (see [`RecorderConfig::from_env`]).
     pub fn from_env() -> Self {
-        let audio_enabled = std::env::var("MULTI_MODAL_ENABLED")
-            .ok()
-            .and_then(|s| s.parse::<bool>().ok())
-            .unwrap_or(true);
-        let video_enabled = std::env::var("MULTI_MODAL_ENABLED")
-            .ok()
-            .and_then(|s| s.parse::<bool>().ok())
-            .unwrap_or(true);
-        let always_listening = std::env::var("ALWAYS_LISTENING_ENABLED")
-            .ok()
-            .and_then(|s| s.parse::<bool>().ok())
-            .unwrap_or(false);
-        let wake_word = std::env::var("WAKE_WORD").unwrap_or_else(|_| "Phoenix".to_string());
-        let storage_path = std::env::var("RECORDING_STORAGE_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("./data/recordings/encrypted"));
+        Self::from_config(RecorderConfig::from_env())
+    }
+
+    /// Build a recorder from an explicit (e.g. persisted) configuration.
+    pub fn from_config(config: RecorderConfig) -> Self {
+        let RecorderConfig {
+            audio_enabled,
+            video_enabled,
+            always_listening,
+            wake_word,
+            storage_path,
+        } = config;
 
         Self {
             audio_enabled,
@@ -141,33 +533,192 @@ impl MultiModalRecorder {
             storage_path,
             last_recording: Arc::new(Mutex::new(None)),
             listening_stop: Arc::new(AtomicBool::new(false)),
+            listening_running: Arc::new(AtomicBool::new(false)),
+            recordings_in_progress: Arc::new(AtomicUsize::new(0)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            schedules: Arc::new(std::sync::Mutex::new(Vec::new())),
+            status_tx: broadcast::channel(16).0,
+            schedule_tx: broadcast::channel(16).0,
+            recording_tx: broadcast::channel(64).0,
 
             live_stop: Arc::new(AtomicBool::new(false)),
             live_running: Arc::new(AtomicBool::new(false)),
+            input_level: InputLevel::default(),
 
             emotion_detector: EmotionDetector::from_env(),
             last_emotional_state: Arc::new(Mutex::new(None)),
+            emotion_tx: broadcast::channel(64).0,
+            fusion_window: Arc::new(Mutex::new(FusionWindow::from_env())),
+            last_fusion: Arc::new(Mutex::new(None)),
+            stabilizer: Arc::new(Mutex::new(EmotionStabilizer::default())),
             vaults: None,
+
+            emotion_privacy: Arc::new(RwLock::new(EmotionPrivacy::default())),
+
+            alert_engine: Arc::new(std::sync::Mutex::new(AlertEngine::default())),
+            alert_tx: broadcast::channel(64).0,
+
+            presence_tx: broadcast::channel(64).0,
+            last_unknown_unix: Arc::new(AtomicI64::new(0)),
+
+            recognized_profile: Arc::new(RwLock::new(None)),
+
+            guest_enabled: Arc::new(AtomicBool::new(false)),
+            guest_allow_recordings: Arc::new(AtomicBool::new(false)),
+
+            enrollments: Arc::new(Mutex::new(HashMap::new())),
+
+            player: playback::Player::default(),
+
+            confirmations: ConfirmationTokens::default(),
         }
     }
 
     /// Attach an existing Soul Vault handle so recordings can log emotional moments without
     /// creating multi-open DB conflicts.
     pub fn attach_vaults(&mut self, vaults: Arc<VitalOrganVaults>) {
+        // Alert rules changed at runtime are persisted and win over the env defaults.
+        if let Some(rules) = vaults
+            .recall_soul(ALERT_RULES_KEY)
+            .and_then(|raw| serde_json::from_str::<AlertRules>(&raw).ok())
+        {
+            if let Ok(mut engine) = self.alert_engine.lock() {
+                engine.rules = rules;
+            }
+        }
+        if let Ok(mut guard) = self.emotion_privacy.write() {
+            *guard = EmotionPrivacy::load(&vaults);
+        }
         self.vaults = Some(vaults);
     }
 
+    pub fn emotion_privacy(&self) -> EmotionPrivacy {
+        self.emotion_privacy
+            .read()
+            .map(|p| p.clone())
+            .unwrap_or_default()
+    }
+
+    /// Update one profile's emotion privacy settings and persist them.
+    pub fn set_profile_emotion_privacy(
+        &self,
+        profile: &str,
+        settings: ProfileEmotionPrivacy,
+    ) -> Result<(), Error> {
+        if profile.trim().is_empty() {
+            return Err(Error::InvalidArgument(
+                "profile must not be empty".to_string(),
+            ));
+        }
+        let mut updated = self.emotion_privacy();
+        updated.set_profile(profile.trim(), settings);
+        if let Some(vaults) = self.vaults.as_ref() {
+            updated.save(vaults)?;
+        }
+        if let Ok(mut guard) = self.emotion_privacy.write() {
+            *guard = updated;
+        }
+        Ok(())
+    }
+
+    /// Delete all stored emotion data (history, timelines, alerts, recording emotion tracks)
+    /// for `profile` and recompute the daily summaries. Recordings themselves are kept.
+    pub async fn purge_profile_emotions(&self, profile: &str) -> Result<PurgeReport, Error> {
+        let vaults = self
+            .vaults
+            .as_ref()
+            .ok_or_else(|| Error::InvalidArgument("no Soul Vault attached".to_string()))?;
+        let profile = profile.trim();
+        let report = emotion_privacy::purge_profile(vaults, &self.storage_path, profile).await?;
+        audit("emotion.purge", serde_json::json!({ "profile": profile }));
+        Ok(report)
+    }
+
+    /// Whether emotion inference may run for whoever is currently attributed.
+    fn emotion_inference_allowed(&self, profile: Option<&str>) -> bool {
+        self.emotion_privacy
+            .read()
+            .map(|p| p.inference_allowed(profile))
+            .unwrap_or(true)
+    }
+
+    /// Name of the active emotion inference backend (`EMOTION_BACKEND`).
+    pub fn emotion_backend(&self) -> &'static str {
+        self.emotion_detector.backend_name()
+    }
+
     /// Retrieve the most recently computed emotional state (if any).
     pub async fn last_emotion(&self) -> Option<EmotionalState> {
         self.last_emotional_state.lock().await.clone()
     }
 
+    /// Most recent fused estimate, including which modalities contributed and whether they
+    /// disagreed.
+    pub async fn last_fusion(&self) -> Option<FusedEmotion> {
+        self.last_fusion.lock().await.clone()
+    }
+
+    /// The emotion the status should display: calibrated, floored to `"uncertain"`, and
+    /// stabilized against flapping.
+    pub async fn reported_emotion(&self) -> Option<ReportedEmotion> {
+        self.stabilizer.lock().await.current().cloned()
+    }
+
+    /// Smoothed valence/arousal of the user's current state (continuous, unlike the label).
+    pub async fn current_affect(&self) -> Option<Affect> {
+        self.reported_emotion().await.map(|r| Affect {
+            valence: r.valence,
+            arousal: r.arousal,
+        })
+    }
+
+    pub async fn emotion_calibration(&self) -> CalibrationConfig {
+        self.stabilizer.lock().await.config.clone()
+    }
+
+    pub async fn set_emotion_calibration(&self, config: CalibrationConfig) -> Result<(), Error> {
+        if !(0.0..=1.0).contains(&config.confidence_floor)
+            || !(0.0..=1.0).contains(&config.switch_margin)
+            || config.confirm_updates == 0
+            || config.affect_smoothing <= 0.0
+            || config.affect_smoothing > 1.0
+            || config
+                .per_emotion
+                .values()
+                .any(|v| !v.is_finite() || *v < 0.0)
+        {
+            return Err(Error::InvalidArgument(
+                "floor/margin must be within 0..=1, affect_smoothing within (0, 1], \
+                 confirm_updates >= 1, scales >= 0"
+                    .to_string(),
+            ));
+        }
+        self.stabilizer.lock().await.config = config;
+        Ok(())
+    }
+
+    pub async fn fusion_weights(&self) -> FusionWeights {
+        self.fusion_window.lock().await.weights
+    }
+
+    /// Override the per-modality fusion weights (at least one must be positive).
+    pub async fn set_fusion_weights(&self, weights: FusionWeights) -> Result<(), Error> {
+        let all = [weights.voice, weights.face, weights.text];
+        if all.iter().any(|w| !w.is_finite() || *w < 0.0) || all.iter().all(|w| *w == 0.0) {
+            return Err(Error::InvalidArgument(
+                "fusion weights must be non-negative and not all zero".to_string(),
+            ));
+        }
+        self.fusion_window.lock().await.weights = weights;
+        Ok(())
+    }
+
     /// Best-effort read of the Soul-Vault emotion timeline (most recent last).
     pub fn emotional_moments_recent(&self, max: usize) -> Vec<String> {
         let Some(vaults) = self.vaults.as_ref() else {
             return Vec::new();
         };
-        let raw = vaults.recall_soul("emotional_moments").unwrap_or_default();
+        let raw = vaults.recall_soul(EMOTION_TIMELINE_KEY).unwrap_or_default();
         let mut lines = raw
             .lines()
             .map(|s| s.to_string())
@@ -182,7 +733,282 @@ impl MultiModalRecorder {
         lines
     }
 
+    /// Enable/disable guest (incognito) mode.
+    ///
+    /// Entering guest mode also forgets the currently recognized profile so nothing recorded
+    /// while visitors are present gets attributed to a household member.
+    pub fn set_guest_mode(&self, enabled: bool, allow_recordings: bool) {
+        self.guest_enabled.store(enabled, Ordering::Relaxed);
+        self.guest_allow_recordings
+            .store(allow_recordings, Ordering::Relaxed);
+        if enabled {
+            if let Ok(mut guard) = self.recognized_profile.write() {
+                *guard = None;
+            }
+        }
+        self.publish_status();
+    }
+
+    pub fn guest_mode(&self) -> GuestMode {
+        GuestMode {
+            enabled: self.guest_enabled.load(Ordering::Relaxed),
+            allow_recordings: self.guest_allow_recordings.load(Ordering::Relaxed),
+        }
+    }
+
+    fn ensure_not_guest(&self, what: &'static str) -> Result<(), Error> {
+        if self.guest_enabled.load(Ordering::Relaxed) {
+            return Err(Error::GuestMode(what));
+        }
+        Ok(())
+    }
+
+    /// Estimate the emotion expressed in `text` (journal entry, script, transcript), record it
+    /// in the emotion timeline, and publish it like any other estimate.
+    ///
+    /// `reference` identifies the text's origin (e.g. `journal:<id>`) and is stored where
+    /// recorded moments keep their recording path. Returns `None` in guest mode, when text
+    /// analysis is disabled, or when the attributed profile opted out of emotion inference.
+    pub async fn analyze_text_emotion(
+        &self,
+        text: &str,
+        source: TextSource,
+        reference: &str,
+    ) -> Option<EmotionalMoment> {
+        let profile = self.attributed_profile();
+        if self.guest_mode().enabled || !self.emotion_inference_allowed(profile.as_deref()) {
+            return None;
+        }
+        let state = self.emotion_detector.analyze_text(text, source)?.to_state();
+        *self.last_emotional_state.lock().await = Some(state.clone());
+        let moment = EmotionalMoment::from_state(&state, Path::new(reference), profile);
+        self.append_emotional_moment_best_effort(&moment);
+        let _ = self.emotion_tx.send(EmotionUpdate::new(moment.clone()));
+        Some(moment)
+    }
+
+    /// Subscribe to live emotion estimates (one per analyzed clip / frame).
+    pub fn subscribe_emotions(&self) -> broadcast::Receiver<EmotionUpdate> {
+        self.emotion_tx.subscribe()
+    }
+
+    /// Subscribe to sustained / recurring negative-emotion alerts.
+    pub fn subscribe_emotion_alerts(&self) -> broadcast::Receiver<EmotionAlert> {
+        self.alert_tx.subscribe()
+    }
+
+    /// Best-effort read of the emotion alert log (most recent last).
+    pub fn emotion_alerts_recent(&self, max: usize) -> Vec<EmotionAlert> {
+        match self.vaults.as_ref() {
+            Some(vaults) => emotion_alerts::recent(vaults, max),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn emotion_alert_rules(&self) -> AlertRules {
+        self.alert_engine
+            .lock()
+            .map(|engine| engine.rules.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the alert rules (quiet hours, opt-outs, thresholds) and persist them.
+    pub fn set_emotion_alert_rules(&self, rules: AlertRules) -> Result<(), Error> {
+        rules.validate().map_err(Error::InvalidArgument)?;
+        if let Some(vaults) = self.vaults.as_ref() {
+            let raw = serde_json::to_string(&rules)?;
+            vaults
+                .store_soul(ALERT_RULES_KEY, &raw)
+                .map_err(|e| Error::Io(std::io::Error::other(e.to_string())))?;
+        }
+        if let Ok(mut engine) = self.alert_engine.lock() {
+            engine.rules = rules;
+        }
+        Ok(())
+    }
+
+    /// Export the emotion history matching `query` (redacted per profile privacy settings)
+    /// to `dest` as CSV or JSON. Returns the number of exported samples.
+    pub async fn export_emotion_history(
+        &self,
+        query: &EmotionQuery,
+        format: emotion_export::ExportFormat,
+        dest: &Path,
+    ) -> Result<usize, Error> {
+        let vaults = self
+            .vaults
+            .as_ref()
+            .ok_or_else(|| Error::InvalidArgument("no Soul Vault attached".to_string()))?;
+        let (doc, count) = emotion_export::export(vaults, query, format)?;
+        if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(dest, doc).await?;
+        Ok(count)
+    }
+
+    /// Time-aligned emotion track of a recording (from its sidecar), for the playback scrubber.
+    pub async fn recording_emotion_track(&self, recording: &Path) -> Option<EmotionTrack> {
+        emotion_track::load(recording).await
+    }
+
+    /// Find recording segments where `emotion` peaked at or above `min_intensity`
+    /// (e.g. "the part where she got excited"), strongest first.
+    pub async fn search_recording_emotions(
+        &self,
+        emotion: &str,
+        min_intensity: f64,
+        profile: Option<&str>,
+    ) -> Result<Vec<EmotionSegment>, Error> {
+        emotion_track::search(&self.storage_path, emotion, min_intensity, profile).await
+    }
+
+    /// Generate the end-of-day mood summary if one is due (see [`mood_summary::generate_due`]).
+    /// Returns the summary only when it was newly stored, so callers can notify once.
+    pub fn generate_due_mood_summary(&self) -> Option<mood_summary::DailyMoodSummary> {
+        mood_summary::generate_due(self.vaults.as_ref()?, chrono::Local::now())
+    }
+
+    /// Stored daily mood summaries (most recent last).
+    pub fn mood_summaries(&self, max: usize) -> Vec<mood_summary::DailyMoodSummary> {
+        match self.vaults.as_ref() {
+            Some(vaults) => mood_summary::recent(vaults, max),
+            None => Vec::new(),
+        }
+    }
+
+    /// Bucketed emotion trends with a previous-period comparison.
+    pub fn emotion_trends(
+        &self,
+        query: &emotion_trends::TrendQuery,
+    ) -> Result<emotion_trends::EmotionTrends, Error> {
+        let vaults = self
+            .vaults
+            .as_ref()
+            .ok_or_else(|| Error::InvalidArgument("no Soul Vault attached".to_string()))?;
+        emotion_trends::compute(vaults, query, chrono::Utc::now().timestamp())
+    }
+
+    pub fn status(&self) -> RecorderStatus {
+        RecorderStatus {
+            recording: self.recordings_in_progress.load(Ordering::Relaxed) > 0,
+            always_listening: self.listening_running.load(Ordering::Relaxed),
+            live_streaming: self.live_running.load(Ordering::Relaxed),
+            guest_mode: self.guest_enabled.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Subscribe to recording / listening / streaming / guest-mode changes.
+    pub fn subscribe_status(&self) -> broadcast::Receiver<RecorderStatus> {
+        self.status_tx.subscribe()
+    }
+
+    fn publish_status(&self) {
+        let _ = self.status_tx.send(self.status());
+    }
+
+    /// Subscribe to unknown-presence / visitor events.
+    pub fn subscribe_presence(&self) -> broadcast::Receiver<PresenceEvent> {
+        self.presence_tx.subscribe()
+    }
+
+    /// Best-effort read of the unknown-presence timeline (most recent last).
+    pub fn unknown_presence_recent(&self, max: usize) -> Vec<UnknownPresenceEvent> {
+        match self.vaults.as_ref() {
+            Some(vaults) => presence::recent(vaults, max),
+            None => Vec::new(),
+        }
+    }
+
+    /// "Enroll this person": turn the snapshot of an unknown-presence event into a face
+    /// enrollment for a new (or existing) profile.
+    pub fn enroll_unknown_presence(&self, event_id: &str, profile: &str) -> Result<(), Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
+        let profile = profile.trim();
+        if profile.is_empty() {
+            return Err(Error::InvalidArgument(
+                "profile name must not be empty".to_string(),
+            ));
+        }
+        let event = self
+            .unknown_presence_recent(usize::MAX)
+            .into_iter()
+            .find(|e| e.id == event_id)
+            .ok_or_else(|| Error::InvalidArgument(format!("unknown presence event: {event_id}")))?;
+        let snapshot = event.snapshot.ok_or_else(|| {
+            Error::InvalidArgument("presence event has no snapshot to enroll from".to_string())
+        })?;
+        self.embedding_store()
+            .enroll(profile, Modality::Face, &[snapshot])?;
+        register_profile(profile);
+        audit(
+            "enrollment.face",
+            serde_json::json!({ "profile": profile, "presence_event": event_id }),
+        );
+        let _ = self.presence_tx.send(PresenceEvent::Enrolled {
+            event_id: event_id.to_string(),
+            profile: profile.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Profile recognized by voice or face within the attribution window, if any.
+    pub fn recognized_profile(&self) -> Option<String> {
+        self.recognized_profile.read().ok().and_then(|guard| {
+            let (profile, ts) = guard.as_ref()?;
+            (Utc::now().timestamp() - ts <= ATTRIBUTION_WINDOW_SECS).then(|| profile.clone())
+        })
+    }
+
+    /// Profile that new emotional moments are currently attributed to: whoever was recognized
+    /// within the attribution window, otherwise the active profile when a database is set.
+    pub fn attributed_profile(&self) -> Option<String> {
+        self.recognized_profile()
+            .or_else(|| storage()?.active_profile().ok())
+    }
+
+    /// Emotional moments attributed to `profile` (most recent last).
+    ///
+    /// Pass `"unknown"` to get moments that could not be attributed to anyone.
+    pub fn emotion_history_for_profile(&self, profile: &str, max: usize) -> Vec<EmotionalMoment> {
+        let Some(vaults) = self.vaults.as_ref() else {
+            return Vec::new();
+        };
+        let raw = vaults.recall_soul(EMOTION_TIMELINE_KEY).unwrap_or_default();
+        emotion_history::filter_profile(raw.lines(), profile, max)
+    }
+
+    /// Query the full (untrimmed) emotion history by time range, emotion, profile, or modality.
+    pub fn query_emotion_history(&self, query: &EmotionQuery) -> Vec<EmotionalMoment> {
+        let Some(vaults) = self.vaults.as_ref() else {
+            return Vec::new();
+        };
+        emotion_history::query_moments(vaults, query)
+    }
+
     /// Convenience: clone this recorder but override audio/video enable flags.
+    /// Current configuration, suitable for persisting.
+    pub fn config(&self) -> RecorderConfig {
+        RecorderConfig {
+            audio_enabled: self.audio_enabled,
+            video_enabled: self.video_enabled,
+            always_listening: self.always_listening,
+            wake_word: self.wake_word.clone(),
+            storage_path: self.storage_path.clone(),
+        }
+    }
+
+    /// Apply a new configuration in place (running loops and subscriptions are kept).
+    pub fn apply_config(&mut self, config: RecorderConfig) -> Result<(), Error> {
+        config.validate()?;
+        self.audio_enabled = config.audio_enabled;
+        self.video_enabled = config.video_enabled;
+        self.always_listening = config.always_listening;
+        self.wake_word = config.wake_word;
+        self.storage_path = config.storage_path;
+        Ok(())
+    }
+
     pub fn clone_with_modes(&self, audio_enabled: bool, video_enabled: bool) -> Self {
         let mut out = self.clone();
         out.audio_enabled = audio_enabled;
@@ -200,18 +1026,91 @@ impl MultiModalRecorder {
     /// When features are enabled, the placeholder payload is where captured frames/samples
     /// should be serialized (container format TBD: e.g. Matroska/WebM).
     pub async fn start_on_demand(&self, duration_secs: u64) -> Result<PathBuf, Error> {
+        self.start_on_demand_with_purpose(duration_secs, None).await
+    }
+
+    /// [`start_on_demand`](Self::start_on_demand), tagging the recording with a purpose (shown
+    /// and filterable in the recording library).
+    ///
+    /// Runs in a `recorder` `recording` span; `debug` events inside it time each phase, so
+    /// with `RUST_LOG=recorder=debug` a slow start can be pinned on one step.
+    #[tracing::instrument(
+        target = "recorder",
+        name = "recording",
+        skip_all,
+        fields(duration_secs = duration_secs, purpose = ?purpose)
+    )]
+    pub async fn start_on_demand_with_purpose(
+        &self,
+        duration_secs: u64,
+        purpose: Option<String>,
+    ) -> Result<PathBuf, Error> {
+        let started = Instant::now();
+        let mut path = None;
+        let result = self
+            .record_on_demand(duration_secs, purpose, &mut path)
+            .await;
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        match &result {
+            Ok(path) => {
+                tracing::info!(target: "recorder", elapsed_ms, path = %path.display(), "recording finished")
+            }
+            Err(e) => tracing::warn!(target: "recorder", elapsed_ms, "recording failed: {e}"),
+        }
+        let _ = self.recording_tx.send(match &result {
+            Ok(path) => RecordingEvent::Finished {
+                path: path.clone(),
+                duration_secs,
+            },
+            Err(e) => RecordingEvent::Failed {
+                path,
+                error: e.to_string(),
+            },
+        });
+        result
+    }
+
+    /// Subscribe to on-demand recording lifecycle events.
+    pub fn subscribe_recordings(&self) -> broadcast::Receiver<RecordingEvent> {
+        self.recording_tx.subscribe()
+    }
+
+    async fn record_on_demand(
+        &self,
+        duration_secs: u64,
+        purpose: Option<String>,
+        started: &mut Option<PathBuf>,
+    ) -> Result<PathBuf, Error> {
         if duration_secs == 0 {
             return Err(Error::InvalidArgument(
                 "duration_secs must be > 0".to_string(),
             ));
         }
+        let guest = self.guest_mode();
+        if guest.enabled && !guest.allow_recordings {
+            return Err(Error::GuestMode("recordings are disabled"));
+        }
+        if self.is_shutting_down() {
+            return Err(Error::ShuttingDown);
+        }
 
+        let began = Instant::now();
+        let _capture = CaptureGuard::new(self);
         tokio::fs::create_dir_all(&self.storage_path).await?;
+        let elapsed_ms = || began.elapsed().as_millis() as u64;
+        tracing::debug!(target: "recorder", elapsed_ms = elapsed_ms(), "storage ready");
 
         let ts = Utc::now().timestamp();
         let id = uuid::Uuid::new_v4().to_string();
         let filename = format!("REC-{ts}-{id}.phoenixrec");
         let out_path = self.storage_path.join(filename);
+        *started = Some(out_path.clone());
+        let _ = self.recording_tx.send(RecordingEvent::Started {
+            path: out_path.clone(),
+            duration_secs,
+            audio: self.audio_enabled,
+            video: self.video_enabled,
+        });
 
         // TODO(real capture):
         // - audio: cpal input stream -> samples -> encode (wav/opus)
@@ -222,35 +1121,35 @@ impl MultiModalRecorder {
             duration_secs,
             audio_enabled: self.audio_enabled,
             video_enabled: self.video_enabled,
-            purpose: None,
+            purpose,
             wake_word: self.wake_word.clone(),
         };
 
-        let meta_json = serde_json::to_vec(&meta).unwrap_or_default();
-
         // Placeholder payload: random bytes sized to duration (tiny).
         let mut payload = vec![0u8; (duration_secs.min(300) as usize) * 256];
         rand::thread_rng().fill_bytes(&mut payload);
+        let _ = self.recording_tx.send(RecordingEvent::Progress {
+            path: out_path.clone(),
+            elapsed_secs: duration_secs,
+            duration_secs,
+        });
 
-        let mut bundle = Vec::with_capacity(16 + meta_json.len() + payload.len());
-        bundle.extend_from_slice(b"PHXREC\0\0");
-        bundle.extend_from_slice(&(meta_json.len() as u32).to_le_bytes());
-        bundle.extend_from_slice(&meta_json);
-        bundle.extend_from_slice(&payload);
+        tracing::debug!(target: "recorder", elapsed_ms = elapsed_ms(), "capture done");
 
-        let encrypted = xor_encrypt(&bundle, &derive_key_from_env());
+        let encrypted = recording_library::seal_bundle(&meta, &payload)?;
         tokio::fs::write(&out_path, encrypted).await?;
+        tracing::debug!(target: "recorder", elapsed_ms = elapsed_ms(), "recording written");
 
         *self.last_recording.lock().await = Some(out_path.clone());
 
+        // Emotion track sidecar; filled in as moments for this recording are analyzed.
+        let _ = emotion_track::save(&EmotionTrack::new(&out_path, ts, duration_secs)).await;
+
         // Emotion fusion (best-effort). For now we treat the encrypted recording path as an
         // audio hint for the heuristic backend.
-        let state = self
-            .emotion_detector
-            .fused_emotional_state("", Some(out_path.clone()), None)
+        self.analyze_emotion("", Some(out_path.clone()), None, &out_path)
             .await;
-        *self.last_emotional_state.lock().await = Some(state.clone());
-        self.append_emotional_moment_best_effort(&state, &out_path);
+        tracing::debug!(target: "recorder", elapsed_ms = elapsed_ms(), "emotion analyzed");
 
         Ok(out_path)
     }
@@ -258,18 +1157,30 @@ impl MultiModalRecorder {
     /// Schedule a recurring recording.
     ///
     /// This spawns a background Tokio task. The `cron_expr` uses the [`cron`](https://crates.io/crates/cron)
-    /// crate format (supports seconds).
+    /// crate format (supports seconds). Scheduling the same expression and purpose again is a
+    /// no-op.
     pub async fn schedule_recording(&self, cron_expr: &str, purpose: &str) {
         let expr = cron_expr.trim().to_string();
         let purpose = purpose.trim().to_string();
+        let Ok(schedule) = expr.parse::<cron::Schedule>() else {
+            return;
+        };
+        let entry = RecordingSchedule {
+            cron_expr: expr.clone(),
+            purpose: purpose.clone(),
+        };
+        {
+            let Ok(mut schedules) = self.schedules.lock() else {
+                return;
+            };
+            if schedules.contains(&entry) {
+                return;
+            }
+            schedules.push(entry);
+        }
         let this = self.clone();
 
         tokio::spawn(async move {
-            let schedule = match expr.parse::<cron::Schedule>() {
-                Ok(s) => s,
-                Err(_) => return,
-            };
-
             loop {
                 let now = chrono::Utc::now();
                 let Some(next) = schedule.after(&now).next() else {
@@ -280,17 +1191,32 @@ impl MultiModalRecorder {
                     continue;
                 };
                 tokio::time::sleep(dur).await;
-                let p = this.start_on_demand(30).await.ok();
+                if this.is_shutting_down() {
+                    return;
+                }
+                let result = this
+                    .start_on_demand_with_purpose(
+                        30,
+                        Some(purpose.clone()).filter(|p| !p.is_empty()),
+                    )
+                    .await;
 
                 // If we have a purpose, fuse it as text context too.
-                if let Some(path) = p {
-                    let state = this
-                        .emotion_detector
-                        .fused_emotional_state(&purpose, Some(path.clone()), None)
+                if let Ok(path) = result.as_ref() {
+                    this.analyze_emotion(&purpose, Some(path.clone()), None, path)
                         .await;
-                    *this.last_emotional_state.lock().await = Some(state.clone());
-                    this.append_emotional_moment_best_effort(&state, &path);
                 }
+                let (path, error) = match result {
+                    Ok(path) => (Some(path), None),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                let _ = this.schedule_tx.send(ScheduledRecordingResult {
+                    ts_unix: Utc::now().timestamp(),
+                    cron_expr: expr.clone(),
+                    purpose: purpose.clone(),
+                    path,
+                    error,
+                });
 
                 // Persist last purpose (best-effort) into a sidecar file.
                 let _ = tokio::fs::write(
@@ -302,6 +1228,30 @@ impl MultiModalRecorder {
         });
     }
 
+    /// Recurring recordings registered so far.
+    pub fn schedules(&self) -> Vec<RecordingSchedule> {
+        self.schedules.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Re-register the schedules saved by the last [`shutdown`](Self::shutdown).
+    pub async fn restore_schedules(&self) -> Result<usize, Error> {
+        let raw = match tokio::fs::read(self.storage_path.join(SCHEDULES_FILE)).await {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let saved: Vec<RecordingSchedule> = serde_json::from_slice(&raw)?;
+        for s in &saved {
+            self.schedule_recording(&s.cron_expr, &s.purpose).await;
+        }
+        Ok(saved.len())
+    }
+
+    /// Subscribe to the outcome of every scheduled recording run.
+    pub fn subscribe_scheduled_recordings(&self) -> broadcast::Receiver<ScheduledRecordingResult> {
+        self.schedule_tx.subscribe()
+    }
+
     /// Start always-listening mode.
     ///
     /// This spawns a background Tokio task that (when fully implemented) will:
@@ -309,24 +1259,33 @@ impl MultiModalRecorder {
     /// - run wake-word detection (Vosk/Whisper backends)
     /// - optionally run speaker ID (voiceprint)
     /// - optionally trigger video capture for face recognition
+    ///
+    /// The task stays in the caller's span, so its events carry the caller's correlation ID.
+    #[tracing::instrument(target = "recorder", name = "listening", skip_all)]
     pub async fn start_always_listening(&self) {
         self.listening_stop.store(false, Ordering::Relaxed);
+        self.listening_running.store(true, Ordering::Relaxed);
+        self.publish_status();
         let stop = self.listening_stop.clone();
         let wake = self.wake_word.clone();
         let this = self.clone();
 
-        tokio::spawn(async move {
-            // Placeholder loop.
-            while !stop.load(Ordering::Relaxed) {
-                // TODO(real impl): wire wake-word engine here.
-                // If detected:
-                // - optional recognition
-                // - optional start_on_demand short clip
-                let _ = &wake;
-                let _ = &this;
-                tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        tokio::spawn(
+            async move {
+                // Placeholder loop.
+                while !stop.load(Ordering::Relaxed) {
+                    // TODO(real impl): wire wake-word engine here.
+                    // If detected:
+                    // - optional recognition
+                    // - optional start_on_demand short clip
+                    let _ = &wake;
+                    let _ = &this;
+                    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+                }
+                tracing::debug!(target: "recorder", "always-listening stopped");
             }
-        });
+            .instrument(tracing::Span::current()),
+        );
     }
 
     /// Start live streaming mode (continuous capture).
@@ -336,6 +1295,7 @@ impl MultiModalRecorder {
     /// Enable backends via crate features:
     /// - `multi_modal_recording/audio`
     /// - `multi_modal_recording/video`
+    #[tracing::instrument(target = "recorder", name = "live_streaming", skip_all)]
     pub async fn start_live_streaming(&self) -> Result<(), Error> {
         let mut cfg = LiveMultiModalInput::from_env();
         cfg.microphone_enabled = cfg.microphone_enabled && self.audio_enabled;
@@ -357,15 +1317,12 @@ impl MultiModalRecorder {
 
         self.live_stop.store(false, Ordering::Relaxed);
         self.live_running.store(true, Ordering::Relaxed);
+        self.publish_status();
 
         let stop = self.live_stop.clone();
         let running = self.live_running.clone();
         let this = self.clone();
         tokio::spawn(async move {
-            // When built without `video`, the live-loop is capture-only and won't use `this`.
-            #[cfg(not(feature = "video"))]
-            let _ = &this;
-
             // Keep the streams alive for the duration of this loop.
             let audio = if cfg.microphone_enabled {
                 cfg.start_audio_stream().await.ok()
@@ -381,6 +1338,7 @@ impl MultiModalRecorder {
             // If both requested streams failed to start, exit.
             if cfg.microphone_enabled && audio.is_none() && cfg.webcam_enabled && video.is_none() {
                 running.store(false, Ordering::Relaxed);
+                this.publish_status();
                 return;
             }
 
@@ -390,7 +1348,7 @@ impl MultiModalRecorder {
             #[cfg(feature = "video")]
             if let Some(vs) = video.as_mut() {
                 if let Err(e) = vs.camera.open_stream() {
-                    eprintln!("[multi_modal_recording] failed to open webcam stream: {e}");
+                    tracing::warn!(target: "recorder", "failed to open webcam stream: {e}");
                 }
             }
 
@@ -398,6 +1356,10 @@ impl MultiModalRecorder {
             let _ = &video;
 
             while !stop.load(Ordering::Relaxed) {
+                if let Some(a) = audio.as_ref() {
+                    this.input_level.set(a.level.get());
+                }
+
                 // Video -> emotion (best-effort)
                 #[cfg(feature = "video")]
                 if let Some(vs) = video.as_ref() {
@@ -406,23 +1368,20 @@ impl MultiModalRecorder {
                     match vs.camera.frame() {
                         Ok(buffer) => match buffer.decode_image::<RgbFormat>() {
                             Ok(rgb) => {
-                                let mut state = this
-                                    .emotion_detector
-                                    .fused_emotional_state("", None, Some(rgb.clone()))
-                                    .await;
-
-                                *this.last_emotional_state.lock().await = Some(state.clone());
-                                this.append_emotional_moment_best_effort(
-                                    &state,
+                                this.analyze_emotion(
+                                    "",
+                                    None,
+                                    Some(rgb),
                                     Path::new("(live-stream)"),
-                                );
+                                )
+                                .await;
                             }
                             Err(e) => {
-                                eprintln!("[multi_modal_recording] decode_image failed: {e}");
+                                tracing::warn!(target: "recorder", "decode_image failed: {e}");
                             }
                         },
                         Err(e) => {
-                            eprintln!("[multi_modal_recording] webcam frame capture failed: {e}");
+                            tracing::warn!(target: "recorder", "webcam frame capture failed: {e}");
                         }
                     }
                 }
@@ -430,7 +1389,9 @@ impl MultiModalRecorder {
                 tokio::time::sleep(std::time::Duration::from_millis(250)).await;
             }
 
+            this.input_level.set(0.0);
             running.store(false, Ordering::Relaxed);
+            this.publish_status();
         });
 
         Ok(())
@@ -446,32 +1407,85 @@ impl MultiModalRecorder {
         self.live_running.load(Ordering::Relaxed)
     }
 
+    /// Microphone input level (`0.0..=1.0`) while live streaming, otherwise `0.0`.
+    pub fn input_level(&self) -> f32 {
+        self.input_level.get()
+    }
+
+    /// Set once [`shutdown`](Self::shutdown) has started; new recordings are refused.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// Stop everything before the process exits: refuse new recordings, stop scheduled runs,
+    /// always-listening and live streaming, save the schedules, and wait (up to `timeout`) for
+    /// recordings in progress to finish writing.
+    ///
+    /// Call this before reading `status().always_listening` for persistence; afterwards it reads
+    /// `false`.
+    pub async fn shutdown(&self, timeout: std::time::Duration) -> ShutdownReport {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        let mut report = ShutdownReport::default();
+
+        let schedules = self.schedules();
+        let saved = async {
+            tokio::fs::create_dir_all(&self.storage_path).await?;
+            let raw = serde_json::to_vec_pretty(&schedules)?;
+            tokio::fs::write(self.storage_path.join(SCHEDULES_FILE), raw).await?;
+            Ok::<_, Error>(())
+        };
+        match saved.await {
+            Ok(()) => report.schedules_saved = schedules.len(),
+            Err(e) => report.error = Some(e.to_string()),
+        }
+
+        self.stop_listening();
+        self.stop_live_streaming();
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            report.recordings_unfinished = self.recordings_in_progress.load(Ordering::Relaxed);
+            report.live_streaming_running = self.live_streaming_active();
+            if (report.recordings_unfinished == 0 && !report.live_streaming_running)
+                || tokio::time::Instant::now() >= deadline
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        report
+    }
+
     /// Stop always-listening background loop (privacy command).
     pub fn stop_listening(&self) {
         self.listening_stop.store(true, Ordering::Relaxed);
+        if self.listening_running.swap(false, Ordering::Relaxed) {
+            self.publish_status();
+        }
     }
 
     /// Train / enroll a speaker identification model.
     ///
     /// Current behavior: stores sample list and creates a placeholder model file.
     pub fn enroll_user_voice(&mut self, samples: Vec<PathBuf>) -> Result<(), Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
         if samples.is_empty() {
             return Err(Error::InvalidArgument(
                 "enroll_user_voice requires at least one sample".to_string(),
             ));
         }
-        let model_dir = self
-            .storage_path
-            .join("..")
-            .join("..")
-            .join("models")
-            .join("voice");
+        let model_dir = self.models_dir().join("voice");
         std::fs::create_dir_all(&model_dir)?;
         let model_path = model_dir.join("user_voice.model.json");
+        let embeddings = self
+            .embedding_store()
+            .enroll(DEFAULT_PROFILE, Modality::Voice, &samples)?;
 
         let data = serde_json::json!({
             "created_unix": Utc::now().timestamp(),
             "samples": samples,
+            "embeddings": embeddings.len(),
+            "embedding_model": embeddings.first().map(|e| &e.model),
             "backend": if cfg!(feature = "speech-vosk") {
                 "vosk"
             } else if cfg!(feature = "speech-whisper") {
@@ -485,30 +1499,84 @@ impl MultiModalRecorder {
             serde_json::to_vec_pretty(&data).unwrap_or_default(),
         )?;
         self.user_voice_model = Some(model_path);
+        audit(
+            "enrollment.voice",
+            serde_json::json!({ "profile": DEFAULT_PROFILE, "samples": samples.len() }),
+        );
         Ok(())
     }
 
+    /// Enroll loose sample files: audio goes to voice enrollment and images to face enrollment.
+    ///
+    /// Every file is type-checked and quality-scored first; only accepted samples are enrolled.
+    pub fn enroll_files(&mut self, paths: Vec<PathBuf>) -> Vec<enrollment::FileEnrollmentResult> {
+        use enrollment::FileEnrollmentResult;
+
+        let mut results = Vec::with_capacity(paths.len());
+        let mut accepted: HashMap<Modality, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            let mut result = FileEnrollmentResult {
+                path: path.clone(),
+                modality: enrollment::modality_for_path(&path),
+                quality: None,
+                enrolled: false,
+                error: None,
+            };
+            match result.modality {
+                None => result.error = Some("unsupported file type".to_string()),
+                Some(modality) => {
+                    let prior = accepted.entry(modality).or_default();
+                    match enrollment::score_sample(modality, &path, prior) {
+                        Ok(quality) => {
+                            if quality.accepted {
+                                prior.push(path);
+                            }
+                            result.quality = Some(quality);
+                        }
+                        Err(e) => result.error = Some(e.to_string()),
+                    }
+                }
+            }
+            results.push(result);
+        }
+
+        for (modality, samples) in accepted {
+            let outcome = match modality {
+                Modality::Voice => self.enroll_user_voice(samples.clone()),
+                Modality::Face => self.enroll_user_face(samples.clone()),
+            };
+            for r in results.iter_mut().filter(|r| samples.contains(&r.path)) {
+                match &outcome {
+                    Ok(()) => r.enrolled = true,
+                    Err(e) => r.error = Some(e.to_string()),
+                }
+            }
+        }
+        results
+    }
+
     /// Train / enroll a face identification model.
     ///
     /// Current behavior: stores image list and creates a placeholder model file.
     pub fn enroll_user_face(&mut self, images: Vec<PathBuf>) -> Result<(), Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
         if images.is_empty() {
             return Err(Error::InvalidArgument(
                 "enroll_user_face requires at least one image".to_string(),
             ));
         }
-        let model_dir = self
-            .storage_path
-            .join("..")
-            .join("..")
-            .join("models")
-            .join("face");
+        let model_dir = self.models_dir().join("face");
         std::fs::create_dir_all(&model_dir)?;
         let model_path = model_dir.join("user_face.model.json");
+        let embeddings = self
+            .embedding_store()
+            .enroll(DEFAULT_PROFILE, Modality::Face, &images)?;
 
         let data = serde_json::json!({
             "created_unix": Utc::now().timestamp(),
             "images": images,
+            "embeddings": embeddings.len(),
+            "embedding_model": embeddings.first().map(|e| &e.model),
             "backend": if cfg!(feature = "face-dlib") {
                 "dlib-face-recognition"
             } else if cfg!(feature = "face-rustface") {
@@ -522,41 +1590,270 @@ impl MultiModalRecorder {
             serde_json::to_vec_pretty(&data).unwrap_or_default(),
         )?;
         self.user_face_model = Some(model_path);
+        audit(
+            "enrollment.face",
+            serde_json::json!({ "profile": DEFAULT_PROFILE, "samples": images.len() }),
+        );
         Ok(())
     }
 
-    /// Recognize the enrolled user from an audio sample + video frame.
+    /// Manager for downloadable voice/face/emotion/STT models.
+    pub fn model_manager(&self) -> ModelManager {
+        ModelManager::new(self.models_dir())
+    }
+
+    /// Report whether stored voice/face embeddings match the active models.
+    pub fn embedding_compatibility(&self) -> Result<Vec<CompatibilityReport>, Error> {
+        let store = self.embedding_store();
+        Ok(vec![
+            store.check_compatibility(Modality::Voice)?,
+            store.check_compatibility(Modality::Face)?,
+        ])
+    }
+
+    /// Re-embed every stale voice/face embedding from retained enrollment samples.
+    ///
+    /// Runs on the blocking pool since it re-reads every retained sample.
+    pub async fn migrate_embeddings(&self) -> Result<Vec<MigrationReport>, Error> {
+        let store = self.embedding_store();
+        tokio::task::spawn_blocking(move || {
+            Ok(vec![
+                store.migrate(Modality::Voice)?,
+                store.migrate(Modality::Face)?,
+            ])
+        })
+        .await
+        .map_err(|e| Error::Io(std::io::Error::other(e)))?
+    }
+
+    /// Voice/face match thresholds for `profile` (defaults when never tuned).
+    pub fn recognition_thresholds(&self, profile: &str) -> Result<RecognitionThresholds, Error> {
+        self.threshold_store().get(profile)
+    }
+
+    /// Persist new voice/face match thresholds for `profile`.
+    pub fn set_recognition_thresholds(
+        &self,
+        profile: &str,
+        thresholds: RecognitionThresholds,
+    ) -> Result<(), Error> {
+        self.threshold_store().set(profile, thresholds)
+    }
+
+    /// Evaluate the current thresholds for `profile` against held-out enrollment samples and
+    /// report the expected false-accept / false-reject tradeoff per modality.
+    pub fn calibrate_recognition(&self, profile: &str) -> Result<Vec<CalibrationReport>, Error> {
+        let thresholds = self.recognition_thresholds(profile)?;
+        let store = self.embedding_store();
+        [Modality::Voice, Modality::Face]
+            .into_iter()
+            .map(|m| recognition::calibrate(&store, profile, m, thresholds.for_modality(m)))
+            .collect()
+    }
+
+    /// Start a guided enrollment session for `profile`.
+    ///
+    /// `steps` is clamped to the number of available prompts (at least
+    /// [`MIN_ACCEPTED_SAMPLES`]).
+    #[tracing::instrument(target = "recorder", name = "enrollment_start", skip(self))]
+    pub async fn start_enrollment(
+        &self,
+        profile: &str,
+        modality: Modality,
+        steps: usize,
+    ) -> Result<EnrollmentStatus, Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
+        let session = EnrollmentSession::new(
+            profile,
+            modality,
+            steps,
+            &self.models_dir().join("enrollment"),
+        )?;
+        std::fs::create_dir_all(&session.dir)?;
+        let status = session.status();
+        self.enrollments
+            .lock()
+            .await
+            .insert(session.id.clone(), session);
+        Ok(status)
+    }
+
+    pub async fn enrollment_status(&self, session_id: &str) -> Result<EnrollmentStatus, Error> {
+        self.enrollments
+            .lock()
+            .await
+            .get(session_id)
+            .map(EnrollmentSession::status)
+            .ok_or_else(|| unknown_session(session_id))
+    }
+
+    /// Capture the next (or given) prompt live from the microphone / webcam and score it.
+    ///
+    /// Requires the `audio` (voice) or `video` (face) feature.
+    #[tracing::instrument(target = "recorder", name = "enrollment_capture", skip(self))]
+    pub async fn capture_enrollment_step(
+        &self,
+        session_id: &str,
+        index: Option<usize>,
+    ) -> Result<CapturedSample, Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
+        let (modality, dir, index) = {
+            let sessions = self.enrollments.lock().await;
+            let session = sessions
+                .get(session_id)
+                .ok_or_else(|| unknown_session(session_id))?;
+            let index = index.or_else(|| session.next_index()).ok_or_else(|| {
+                Error::InvalidArgument("every prompt already has an accepted sample".to_string())
+            })?;
+            (session.modality, session.dir.clone(), index)
+        };
+
+        let path = match modality {
+            Modality::Voice => {
+                let path = dir.join(format!("{index:03}-{}.wav", Utc::now().timestamp_millis()));
+                enrollment::capture_voice_clip(&path, 4).await?;
+                path
+            }
+            Modality::Face => {
+                let path = dir.join(format!("{index:03}-{}.png", Utc::now().timestamp_millis()));
+                enrollment::capture_face_frame(&path).await?;
+                path
+            }
+        };
+        self.record_enrollment_sample(session_id, index, path).await
+    }
+
+    /// Score and record an already-captured sample file for prompt `index`.
+    pub async fn submit_enrollment_sample(
+        &self,
+        session_id: &str,
+        index: usize,
+        sample: PathBuf,
+    ) -> Result<CapturedSample, Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
+        if !sample.is_file() {
+            return Err(Error::InvalidArgument(format!(
+                "sample not found: {}",
+                sample.display()
+            )));
+        }
+        self.record_enrollment_sample(session_id, index, sample)
+            .await
+    }
+
+    /// Drop a session and any samples it captured.
+    pub async fn cancel_enrollment(&self, session_id: &str) -> Result<bool, Error> {
+        let Some(session) = self.enrollments.lock().await.remove(session_id) else {
+            return Ok(false);
+        };
+        if session.dir.is_dir() {
+            std::fs::remove_dir_all(&session.dir)?;
+        }
+        Ok(true)
+    }
+
+    /// Enroll every accepted sample of the session into the embedding store.
+    ///
+    /// For [`DEFAULT_PROFILE`] this also refreshes the user voice/face model, exactly like
+    /// [`enroll_user_voice`](Self::enroll_user_voice) / [`enroll_user_face`](Self::enroll_user_face).
+    #[tracing::instrument(target = "recorder", name = "enrollment_finalize", skip(self))]
+    pub async fn finalize_enrollment(
+        &mut self,
+        session_id: &str,
+    ) -> Result<EnrollmentStatus, Error> {
+        self.ensure_not_guest("enrollment is disabled")?;
+        let session = {
+            let sessions = self.enrollments.lock().await;
+            sessions
+                .get(session_id)
+                .cloned()
+                .ok_or_else(|| unknown_session(session_id))?
+        };
+        let samples = session.accepted_paths();
+        if samples.len() < MIN_ACCEPTED_SAMPLES {
+            return Err(Error::InvalidArgument(format!(
+                "need at least {MIN_ACCEPTED_SAMPLES} accepted samples, have {}",
+                samples.len()
+            )));
+        }
+
+        if session.profile == DEFAULT_PROFILE {
+            match session.modality {
+                Modality::Voice => self.enroll_user_voice(samples)?,
+                Modality::Face => self.enroll_user_face(samples)?,
+            }
+        } else {
+            self.embedding_store()
+                .enroll(&session.profile, session.modality, &samples)?;
+            let action = match session.modality {
+                Modality::Voice => "enrollment.voice",
+                Modality::Face => "enrollment.face",
+            };
+            audit(
+                action,
+                serde_json::json!({ "profile": session.profile, "samples": samples.len() }),
+            );
+        }
+
+        // The embedding store keeps its own copies of the samples.
+        let status = session.status();
+        let _ = self.cancel_enrollment(session_id).await;
+        Ok(status)
+    }
+
+    /// Recognize an enrolled profile from an audio sample + video frame.
     ///
-    /// Current behavior:
-    /// - if a model is enrolled, returns high confidence
-    /// - otherwise returns low confidence
+    /// Both are embedded with the active models and matched against every enrolled profile,
+    /// each with its own thresholds (see [`recognition::match_profiles`]); an empty sample or
+    /// frame is left out. The scores reported are those of the best match.
     pub fn recognize_user(
         &self,
-        _audio_sample: &[f32],
-        _video_frame: &Image,
+        audio_sample: &[f32],
+        video_frame: &Image,
     ) -> RecognitionConfidence {
-        let voice: f32 = if self.user_voice_model.is_some() {
-            0.92_f32
-        } else {
-            0.10_f32
+        if self.guest_mode().enabled {
+            return RecognitionConfidence::default();
+        }
+        let voice = (!audio_sample.is_empty()).then(|| {
+            let bytes: Vec<u8> = audio_sample.iter().flat_map(|s| s.to_le_bytes()).collect();
+            embeddings::compute_embedding(&bytes, &EmbeddingModel::current(Modality::Voice))
+        });
+        let face = (video_frame.width() > 0 && video_frame.height() > 0).then(|| {
+            embeddings::compute_embedding(
+                video_frame.as_bytes(),
+                &EmbeddingModel::current(Modality::Face),
+            )
+        });
+        let matches = recognition::match_profiles(
+            &self.embedding_store(),
+            &self.threshold_store(),
+            voice.as_deref(),
+            face.as_deref(),
+        )
+        .unwrap_or_else(|e| {
+            tracing::warn!(target: "recorder", "recognition failed: {e}");
+            Vec::new()
+        });
+        let best = matches.first();
+        let confidence = RecognitionConfidence {
+            voice: best.and_then(|m| m.voice).unwrap_or_default(),
+            face: best.and_then(|m| m.face).unwrap_or_default(),
+            combined: best.map(ProfileMatch::combined).unwrap_or_default(),
+            recognized: best.is_some_and(|m| m.accepted),
+            label: best.filter(|m| m.accepted).map(|m| m.profile.clone()),
         };
-        let face: f32 = if self.user_face_model.is_some() {
-            0.93_f32
+        if let Some(label) = confidence.label.as_ref() {
+            if let Ok(mut guard) = self.recognized_profile.write() {
+                if guard.as_ref().is_none_or(|(previous, _)| previous != label) {
+                    register_profile(label);
+                }
+                *guard = Some((label.clone(), Utc::now().timestamp()));
+            }
         } else {
-            0.10_f32
-        };
-        let combined: f32 = (voice * 0.5_f32 + face * 0.5_f32).clamp(0.0_f32, 1.0_f32);
-        RecognitionConfidence {
-            voice,
-            face,
-            combined,
-            recognized: combined >= 0.80,
-            label: if combined >= 0.80 {
-                Some("Dad".to_string())
-            } else {
-                None
-            },
+            let closest = best.map(|m| m.profile.as_str());
+            self.report_unknown_presence(&confidence, closest, Some(video_frame));
         }
+        confidence
     }
 
     /// Delete the last on-disk recording created by this process (privacy command).
@@ -568,62 +1865,261 @@ impl MultiModalRecorder {
         if tokio::fs::try_exists(&p).await.unwrap_or(false) {
             tokio::fs::remove_file(&p).await?;
         }
+        let _ = tokio::fs::remove_file(emotion_track::sidecar_path(&p)).await;
         *self.last_recording.lock().await = None;
+        audit("recording.delete", serde_json::json!({ "path": p }));
         Ok(true)
     }
 
-    /// Clear all encrypted recordings in the configured storage directory (privacy command).
-    pub async fn clear_all_recordings(&self) -> Result<u64, Error> {
-        let mut removed = 0u64;
+    /// One page of the recording library (newest first); `page` is zero-based.
+    pub async fn list_recordings(
+        &self,
+        filter: &recording_library::RecordingFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<recording_library::RecordingPage, Error> {
+        let entries = recording_library::scan(&self.storage_path).await?;
+        Ok(recording_library::paginate(
+            entries, filter, page, page_size,
+        ))
+    }
+
+    /// Start (or resume) playback. With `path`, that recording is decrypted and loaded first; it
+    /// must be inside the storage directory.
+    pub async fn play_recording(&self, path: Option<&Path>) -> Result<playback::NowPlaying, Error> {
+        if let Some(path) = path {
+            let root = tokio::fs::canonicalize(&self.storage_path).await?;
+            let path = tokio::fs::canonicalize(path).await?;
+            if !path.starts_with(&root) {
+                return Err(Error::InvalidArgument(format!(
+                    "{} is not in the recordings folder",
+                    path.display()
+                )));
+            }
+            let (meta, payload) = recording_library::read_bundle(&path).await?;
+            self.player.load(&path, payload, meta.duration_secs as f64);
+        }
+        self.player.play()
+    }
+
+    pub fn pause_playback(&self) -> playback::NowPlaying {
+        self.player.pause()
+    }
+
+    pub fn seek_playback(&self, position_secs: f64) -> Result<playback::NowPlaying, Error> {
+        self.player.seek(position_secs)
+    }
+
+    pub fn now_playing(&self) -> playback::NowPlaying {
+        self.player.now_playing()
+    }
+
+    /// Playback state changes and position updates.
+    pub fn subscribe_playback(&self) -> broadcast::Receiver<playback::NowPlaying> {
+        self.player.subscribe()
+    }
+
+    /// Encrypted recordings in the storage directory.
+    async fn stored_recordings(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut found = Vec::new();
         if !tokio::fs::try_exists(&self.storage_path)
             .await
             .unwrap_or(false)
         {
-            return Ok(0);
+            return Ok(found);
         }
         let mut rd = tokio::fs::read_dir(&self.storage_path).await?;
         while let Some(entry) = rd.next_entry().await? {
             let p = entry.path();
             if p.extension().and_then(|s| s.to_str()) == Some("phoenixrec") {
-                let _ = tokio::fs::remove_file(&p).await;
-                removed += 1;
+                found.push(p);
+            }
+        }
+        Ok(found)
+    }
+
+    /// First step of clearing all recordings: report what would be deleted and issue the token
+    /// [`clear_all_recordings`](Self::clear_all_recordings) requires.
+    pub async fn request_clear_all(&self) -> Result<ClearAllConfirmation, Error> {
+        let recordings = self.stored_recordings().await?;
+        let mut bytes = 0u64;
+        for p in &recordings {
+            for file in [p.clone(), emotion_track::sidecar_path(p)] {
+                if let Ok(meta) = tokio::fs::metadata(&file).await {
+                    bytes += meta.len();
+                }
             }
         }
+        Ok(ClearAllConfirmation {
+            token: self.confirmations.issue(CLEAR_ALL_OPERATION),
+            recordings: recordings.len() as u64,
+            bytes,
+            expires_unix: Utc::now().timestamp() + CONFIRMATION_TTL.as_secs() as i64,
+        })
+    }
+
+    /// Clear all encrypted recordings in the configured storage directory (privacy command).
+    ///
+    /// Only runs with a live token from [`request_clear_all`](Self::request_clear_all).
+    pub async fn clear_all_recordings(&self, token: &str) -> Result<u64, Error> {
+        self.confirmations.redeem(CLEAR_ALL_OPERATION, token)?;
+        let mut removed = 0u64;
+        for p in self.stored_recordings().await? {
+            let _ = tokio::fs::remove_file(&p).await;
+            let _ = tokio::fs::remove_file(emotion_track::sidecar_path(&p)).await;
+            removed += 1;
+        }
         *self.last_recording.lock().await = None;
+        audit(
+            "recording.clear_all",
+            serde_json::json!({ "removed": removed }),
+        );
         Ok(removed)
     }
 }
 
 impl MultiModalRecorder {
-    fn append_emotional_moment_best_effort(&self, state: &EmotionalState, recording_path: &Path) {
-        let Some(vaults) = self.vaults.as_ref() else {
+    /// Run emotion fusion for one capture, remember it, and log it to the timeline.
+    ///
+    /// No-op in guest mode, and for profiles that disabled emotion inference: nothing
+    /// biometric is computed or stored.
+    async fn analyze_emotion(
+        &self,
+        text: &str,
+        audio: Option<PathBuf>,
+        video_frame: Option<emotion_detection::ImageBuffer>,
+        recording_path: &Path,
+    ) {
+        let profile = self.attributed_profile();
+        if self.guest_mode().enabled || !self.emotion_inference_allowed(profile.as_deref()) {
+            return;
+        }
+        let estimates = self
+            .emotion_detector
+            .modality_estimates(text, audio, video_frame)
+            .await;
+        if estimates.is_empty() {
+            return;
+        }
+        // Fuse with whatever other modalities reported inside the fusion window.
+        let fused = self.fusion_window.lock().await.push(estimates);
+        let state = fused.state.clone();
+        *self.last_emotional_state.lock().await = Some(state.clone());
+        *self.last_fusion.lock().await = Some(fused.clone());
+        let reported = self
+            .stabilizer
+            .lock()
+            .await
+            .update_with_affect(&state, fused.affect);
+        let moment =
+            EmotionalMoment::from_state(&state, recording_path, profile).with_affect(fused.affect);
+        self.append_emotional_moment_best_effort(&moment);
+        if recording_path.extension().and_then(|s| s.to_str()) == Some("phoenixrec") {
+            emotion_track::append(recording_path, &moment).await;
+        }
+        let _ = self.emotion_tx.send(EmotionUpdate {
+            disagreement: fused.disagreement,
+            reported: Some(reported),
+            ..EmotionUpdate::new(moment)
+        });
+    }
+
+    async fn record_enrollment_sample(
+        &self,
+        session_id: &str,
+        index: usize,
+        path: PathBuf,
+    ) -> Result<CapturedSample, Error> {
+        let mut sessions = self.enrollments.lock().await;
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| unknown_session(session_id))?;
+        session.record_sample(index, path)
+    }
+
+    /// Root for enrolled models (sibling of the `recordings/` tree).
+    fn models_dir(&self) -> PathBuf {
+        self.storage_path.join("..").join("..").join("models")
+    }
+
+    /// Seal the embeddings, thresholds, emotion tracks and snapshots still in the clear; see
+    /// [`sealed`]. Returns how many files were sealed.
+    pub fn seal_existing_files(&self) -> Result<usize, Error> {
+        Ok(sealed::seal_existing(&self.models_dir(), &self.storage_path)?)
+    }
+
+    fn embedding_store(&self) -> EmbeddingStore {
+        EmbeddingStore::new(self.models_dir().join("embeddings"))
+    }
+
+    /// Publish + persist an unknown-presence event (rate-limited by a cooldown).
+    fn report_unknown_presence(
+        &self,
+        confidence: &RecognitionConfidence,
+        closest_profile: Option<&str>,
+        frame: Option<&Image>,
+    ) {
+        let now = Utc::now().timestamp();
+        // Checked and claimed in one step, so concurrent sightings report once.
+        let claimed = self
+            .last_unknown_unix
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                (now - last >= UNKNOWN_PRESENCE_COOLDOWN_SECS).then_some(now)
+            })
+            .is_ok();
+        if !claimed {
             return;
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let snapshot = frame
+            .filter(|f| f.width() > 0 && f.height() > 0)
+            .and_then(|f| {
+                let dir = self.storage_path.join("snapshots");
+                std::fs::create_dir_all(&dir).ok()?;
+                let path = dir.join(format!("unknown-{now}-{id}.png"));
+                let mut png = std::io::Cursor::new(Vec::new());
+                f.write_to(&mut png, image::ImageFormat::Png).ok()?;
+                sealed::write(&path, png.get_ref()).ok().map(|_| path)
+            });
+        let event = UnknownPresenceEvent {
+            id,
+            ts_unix: now,
+            voice_confidence: confidence.voice,
+            face_confidence: confidence.face,
+            closest_profile: closest_profile.map(str::to_string),
+            snapshot,
         };
+        if let Some(vaults) = self.vaults.as_ref() {
+            presence::append_to_timeline(vaults, &event);
+        }
+        let _ = self.presence_tx.send(PresenceEvent::Unknown(event));
+    }
 
-        let entry = serde_json::json!({
-            "ts_unix": state.timestamp.timestamp(),
-            "emotion": format!("{:?}", state.primary_emotion),
-            "intensity": state.intensity,
-            "confidence": state.confidence,
-            "voice_contribution": state.voice_contribution,
-            "face_contribution": state.face_contribution,
-            "text_contribution": state.text_contribution,
-            "recording": recording_path.display().to_string(),
-        })
-        .to_string();
+    /// Run the alert rules over a new moment; log + publish any alert it triggers.
+    fn evaluate_emotion_alerts(&self, moment: &EmotionalMoment) {
+        let alert = match self.alert_engine.lock() {
+            Ok(mut engine) => engine.observe(moment),
+            Err(_) => None,
+        };
+        let Some(alert) = alert else {
+            return;
+        };
+        if let Some(vaults) = self.vaults.as_ref() {
+            emotion_alerts::append_to_timeline(vaults, &alert);
+        }
+        let _ = self.alert_tx.send(alert);
+    }
 
-        let existing = vaults.recall_soul("emotional_moments").unwrap_or_default();
-        let mut lines = existing
-            .lines()
-            .map(|s| s.to_string())
-            .filter(|s| !s.trim().is_empty())
-            .collect::<Vec<_>>();
-        lines.push(entry);
-        if lines.len() > 200 {
-            lines = lines.split_off(lines.len() - 200);
+    fn threshold_store(&self) -> ThresholdStore {
+        ThresholdStore::new(self.models_dir().join("thresholds.json"))
+    }
+
+    fn append_emotional_moment_best_effort(&self, moment: &EmotionalMoment) {
+        self.evaluate_emotion_alerts(moment);
+        if let Some(vaults) = self.vaults.as_ref() {
+            emotion_history::append_moment(vaults, moment);
         }
-        let updated = lines.join("\n");
-        let _ = vaults.store_soul("emotional_moments", &updated);
     }
 }
 
@@ -651,3 +2147,139 @@ fn is_file(path: &Path) -> bool {
         .map(|m| m.is_file())
         .unwrap_or(false)
 }
+
+fn unknown_session(session_id: &str) -> Error {
+    Error::InvalidArgument(format!("unknown enrollment session: {session_id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recording_library::{RecordingEntry, RecordingModality};
+
+    fn frame(shade: u8) -> Image {
+        Image::ImageRgb8(image::RgbImage::from_pixel(
+            2,
+            2,
+            image::Rgb([shade, 0, 255]),
+        ))
+    }
+
+    #[test]
+    fn recognizes_every_enrolled_profile_with_its_own_thresholds() {
+        let dir = std::env::temp_dir().join(format!("mmr-recognize-{}", uuid::Uuid::new_v4()));
+        let recorder = MultiModalRecorder::from_config(RecorderConfig {
+            storage_path: dir.join("data").join("recordings").join("encrypted"),
+            ..RecorderConfig::default()
+        });
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = recorder.embedding_store();
+        // The stub face model embeds raw pixels, so enroll each profile with its frame's bytes.
+        for (profile, shade) in [(DEFAULT_PROFILE, 10), ("Kid", 200)] {
+            let sample = dir.join(format!("{profile}.raw"));
+            std::fs::write(&sample, frame(shade).as_bytes()).unwrap();
+            store.enroll(profile, Modality::Face, &[sample]).unwrap();
+        }
+
+        let kid = recorder.recognize_user(&[], &frame(200));
+        assert!(kid.recognized);
+        assert_eq!(kid.label.as_deref(), Some("Kid"));
+        assert!(kid.face > 0.99);
+        assert_eq!(recorder.recognized_profile().as_deref(), Some("Kid"));
+        let dad = recorder.recognize_user(&[], &frame(10));
+        assert_eq!(dad.label.as_deref(), Some(DEFAULT_PROFILE));
+
+        let stranger = frame(90);
+        assert!(!recorder.recognize_user(&[], &stranger).recognized);
+        // A profile's own threshold decides for it alone.
+        recorder
+            .set_recognition_thresholds(
+                "Kid",
+                RecognitionThresholds {
+                    voice: 0.8,
+                    face: 0.0,
+                },
+            )
+            .unwrap();
+        let lenient = recorder.recognize_user(&[], &stranger);
+        assert_eq!(lenient.label.as_deref(), Some("Kid"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn shutdown_saves_schedules_and_waits_for_recordings() {
+        let dir = std::env::temp_dir().join(format!("mmr-shutdown-{}", uuid::Uuid::new_v4()));
+        let config = || RecorderConfig {
+            storage_path: dir.clone(),
+            ..RecorderConfig::default()
+        };
+        let recorder = MultiModalRecorder::from_config(config());
+        recorder.schedule_recording("0 0 3 * * *", "nightly").await;
+        recorder
+            .schedule_recording(" 0 0 3 * * * ", "nightly")
+            .await;
+        recorder.schedule_recording("not cron", "ignored").await;
+        assert_eq!(recorder.schedules().len(), 1);
+
+        let capture = CaptureGuard::new(&recorder);
+        let report = recorder
+            .shutdown(std::time::Duration::from_millis(100))
+            .await;
+        assert_eq!(report.schedules_saved, 1);
+        // Gave up waiting on the recording still being written.
+        assert_eq!(report.recordings_unfinished, 1);
+        drop(capture);
+        assert!(matches!(
+            recorder.start_on_demand(1).await,
+            Err(Error::ShuttingDown)
+        ));
+
+        let restarted = MultiModalRecorder::from_config(config());
+        assert_eq!(restarted.restore_schedules().await.unwrap(), 1);
+        assert_eq!(restarted.schedules(), recorder.schedules());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn plays_only_recordings_from_the_storage_directory() {
+        let dir = std::env::temp_dir().join(format!("mmr-playback-{}", uuid::Uuid::new_v4()));
+        let storage = dir.join("recordings");
+        std::fs::create_dir_all(&storage).unwrap();
+        let recorder = MultiModalRecorder::from_config(RecorderConfig {
+            storage_path: storage.clone(),
+            ..RecorderConfig::default()
+        });
+        let entry = RecordingEntry {
+            path: PathBuf::from("REC-1.phoenixrec"),
+            created_unix: 1_700_000_000,
+            duration_secs: 12,
+            modality: RecordingModality::Audio,
+            purpose: None,
+            size_bytes: 0,
+            emotion: None,
+            thumbnail: None,
+        };
+        let inside = storage.join("REC-1.phoenixrec");
+        let outside = dir.join("REC-1.phoenixrec");
+        for path in [&inside, &outside] {
+            recording_library::write_media(path, &entry, b"not audio").unwrap();
+        }
+
+        assert!(matches!(
+            recorder.play_recording(Some(&outside)).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        let playing = recorder.play_recording(Some(&inside)).await.unwrap();
+        assert_eq!(playing.state, playback::PlaybackState::Playing);
+        assert_eq!(playing.duration_secs, 12.0);
+        assert!(!playing.audible);
+        assert_eq!(
+            recorder.pause_playback().state,
+            playback::PlaybackState::Paused
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,364 @@
+//! Model download and management.
+//!
+//! Voice, face, emotion, STT and local LLM models are large, so they are fetched on first use
+//! instead of being bundled. The manager keeps two JSON files under the models directory:
+//! - `manifest.json` — the models this build knows about (id, kind, version, URL, SHA-256)
+//! - `installed.json` — what is on disk, its version, size, and whether the user pinned it
+//!
+//! Downloads go to `<id>-<version>.part` and resume from the partial file via an HTTP `Range`
+//! request; the file is only moved into place after its SHA-256 matches the manifest.
+//!
+//! Network downloads require the `model-download` feature; without it, [`ModelManager::ensure`]
+//! only succeeds for models that are already installed.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelKind {
+    Voice,
+    Face,
+    Emotion,
+    Stt,
+    /// GGUF weights or a `tokenizer.json` for local completions (see `pagi_llm::local`).
+    Llm,
+}
+
+/// A downloadable model as described by the manifest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelSpec {
+    pub id: String,
+    pub kind: ModelKind,
+    pub version: String,
+    pub url: String,
+    /// Lowercase hex SHA-256 of the complete file.
+    pub sha256: String,
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    /// Required models are fetched automatically on first use.
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A model that is present on disk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InstalledModel {
+    pub id: String,
+    pub kind: ModelKind,
+    pub version: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub sha256: String,
+    /// Pinned models are never deleted or replaced automatically.
+    #[serde(default)]
+    pub pinned: bool,
+    pub installed_unix: i64,
+    #[serde(default)]
+    pub last_used_unix: Option<i64>,
+}
+
+/// Combined view of manifest + install state for UIs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelStatus {
+    pub spec: Option<ModelSpec>,
+    pub installed: Option<InstalledModel>,
+    /// Installed version differs from the manifest version.
+    pub update_available: bool,
+    /// Bytes of an interrupted download that can be resumed.
+    pub partial_bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    pub partial_bytes: u64,
+    pub per_model: Vec<(String, u64)>,
+}
+
+/// File-backed model registry + downloader.
+#[derive(Clone, Debug)]
+pub struct ModelManager {
+    root: PathBuf,
+}
+
+impl ModelManager {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        std::env::var("MODEL_MANIFEST_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| self.root.join("manifest.json"))
+    }
+
+    fn installed_path(&self) -> PathBuf {
+        self.root.join("installed.json")
+    }
+
+    fn downloads_dir(&self) -> PathBuf {
+        self.root.join("downloads")
+    }
+
+    fn partial_path(&self, spec: &ModelSpec) -> PathBuf {
+        self.downloads_dir()
+            .join(format!("{}-{}.part", spec.id, spec.version))
+    }
+
+    /// Models this build knows about (empty when no manifest is present).
+    pub fn manifest(&self) -> Result<Vec<ModelSpec>, Error> {
+        let path = self.manifest_path();
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn installed(&self) -> Result<Vec<InstalledModel>, Error> {
+        let path = self.installed_path();
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    fn save_installed(&self, models: &[InstalledModel]) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.root)?;
+        let path = self.installed_path();
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(models)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Manifest + install state for every known or installed model.
+    pub fn status(&self) -> Result<Vec<ModelStatus>, Error> {
+        let manifest = self.manifest()?;
+        let installed = self.installed()?;
+        let mut out = Vec::new();
+        for spec in &manifest {
+            let inst = installed.iter().find(|m| m.id == spec.id).cloned();
+            let partial_bytes = std::fs::metadata(self.partial_path(spec))
+                .map(|m| m.len())
+                .unwrap_or(0);
+            out.push(ModelStatus {
+                update_available: inst.as_ref().is_some_and(|m| m.version != spec.version),
+                spec: Some(spec.clone()),
+                installed: inst,
+                partial_bytes,
+            });
+        }
+        for inst in installed {
+            if !manifest.iter().any(|s| s.id == inst.id) {
+                out.push(ModelStatus {
+                    spec: None,
+                    installed: Some(inst),
+                    update_available: false,
+                    partial_bytes: 0,
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// Return the on-disk path of model `id`, downloading it first if necessary.
+    ///
+    /// A pinned model is used as-is even when the manifest lists a newer version.
+    pub async fn ensure(&self, id: &str) -> Result<PathBuf, Error> {
+        let spec = self.manifest()?.into_iter().find(|s| s.id == id);
+        let mut installed = self.installed()?;
+        if let Some(m) = installed.iter_mut().find(|m| m.id == id) {
+            let current = spec.as_ref().is_none_or(|s| s.version == m.version);
+            if (current || m.pinned) && m.path.is_file() {
+                m.last_used_unix = Some(Utc::now().timestamp());
+                let path = m.path.clone();
+                self.save_installed(&installed)?;
+                return Ok(path);
+            }
+        }
+        let spec = spec.ok_or_else(|| Error::InvalidArgument(format!("unknown model id: {id}")))?;
+        self.download(&spec).await
+    }
+
+    /// Download every manifest model marked `required` that isn't installed yet.
+    pub async fn ensure_required(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut out = Vec::new();
+        for spec in self.manifest()?.into_iter().filter(|s| s.required) {
+            out.push(self.ensure(&spec.id).await?);
+        }
+        Ok(out)
+    }
+
+    /// Download (or resume downloading) `spec`, verify it, and register it as installed.
+    pub async fn download(&self, spec: &ModelSpec) -> Result<PathBuf, Error> {
+        std::fs::create_dir_all(self.downloads_dir())?;
+        let partial = self.partial_path(spec);
+        fetch_resumable(&spec.url, &partial).await?;
+
+        let (sha256, size_bytes) = {
+            let partial = partial.clone();
+            tokio::task::spawn_blocking(move || hash_file(&partial))
+                .await
+                .map_err(|e| Error::Io(std::io::Error::other(e)))??
+        };
+        if !sha256.eq_ignore_ascii_case(spec.sha256.trim()) {
+            let _ = std::fs::remove_file(&partial);
+            return Err(Error::InvalidArgument(format!(
+                "checksum mismatch for model {} (expected {}, got {sha256})",
+                spec.id, spec.sha256
+            )));
+        }
+
+        let kind_dir = self
+            .root
+            .join(format!("{:?}", spec.kind).to_ascii_lowercase());
+        std::fs::create_dir_all(&kind_dir)?;
+        let file_name = spec
+            .url
+            .rsplit('/')
+            .next()
+            .filter(|n| !n.is_empty() && !n.contains('?'))
+            .map(|n| format!("{}-{}-{n}", spec.id, spec.version))
+            .unwrap_or_else(|| format!("{}-{}.bin", spec.id, spec.version));
+        let final_path = kind_dir.join(file_name);
+        std::fs::rename(&partial, &final_path)?;
+
+        let mut installed = self.installed()?;
+        let pinned = installed.iter().any(|m| m.id == spec.id && m.pinned);
+        for old in installed
+            .iter()
+            .filter(|m| m.id == spec.id && m.path != final_path)
+        {
+            let _ = std::fs::remove_file(&old.path);
+        }
+        installed.retain(|m| m.id != spec.id);
+        installed.push(InstalledModel {
+            id: spec.id.clone(),
+            kind: spec.kind,
+            version: spec.version.clone(),
+            path: final_path.clone(),
+            size_bytes,
+            sha256,
+            pinned,
+            installed_unix: Utc::now().timestamp(),
+            last_used_unix: None,
+        });
+        self.save_installed(&installed)?;
+        Ok(final_path)
+    }
+
+    /// Delete an installed model. Pinned models must be unpinned first.
+    pub fn delete(&self, id: &str) -> Result<bool, Error> {
+        let mut installed = self.installed()?;
+        let Some(idx) = installed.iter().position(|m| m.id == id) else {
+            return Ok(false);
+        };
+        if installed[idx].pinned {
+            return Err(Error::InvalidArgument(format!(
+                "model {id} is pinned; unpin it before deleting"
+            )));
+        }
+        let model = installed.remove(idx);
+        if model.path.is_file() {
+            std::fs::remove_file(&model.path)?;
+        }
+        self.save_installed(&installed)?;
+        Ok(true)
+    }
+
+    pub fn set_pinned(&self, id: &str, pinned: bool) -> Result<(), Error> {
+        let mut installed = self.installed()?;
+        let model = installed
+            .iter_mut()
+            .find(|m| m.id == id)
+            .ok_or_else(|| Error::InvalidArgument(format!("model {id} is not installed")))?;
+        model.pinned = pinned;
+        self.save_installed(&installed)
+    }
+
+    pub fn disk_usage(&self) -> Result<DiskUsage, Error> {
+        let mut usage = DiskUsage::default();
+        for m in self.installed()? {
+            let size = std::fs::metadata(&m.path).map(|md| md.len()).unwrap_or(0);
+            usage.total_bytes += size;
+            usage.per_model.push((m.id, size));
+        }
+        if let Ok(rd) = std::fs::read_dir(self.downloads_dir()) {
+            for entry in rd.flatten() {
+                usage.partial_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+        Ok(usage)
+    }
+}
+
+fn hash_file(path: &Path) -> Result<(String, u64), Error> {
+    let mut f = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        total += n as u64;
+    }
+    let hex = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    Ok((hex, total))
+}
+
+#[cfg(feature = "model-download")]
+async fn fetch_resumable(url: &str, partial: &Path) -> Result<(), Error> {
+    use tokio::io::AsyncWriteExt;
+
+    let offset = tokio::fs::metadata(partial)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let client = reqwest::Client::new();
+    let mut req = client.get(url);
+    if offset > 0 {
+        req = req.header(reqwest::header::RANGE, format!("bytes={offset}-"));
+    }
+    let mut resp = req
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(std::io::Error::other)?;
+
+    // Servers that ignore Range reply 200 with the full body: start over.
+    let resumed = resp.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(partial)
+        .await?;
+    while let Some(chunk) = resp.chunk().await.map_err(std::io::Error::other)? {
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "model-download"))]
+async fn fetch_resumable(_url: &str, _partial: &Path) -> Result<(), Error> {
+    Err(Error::FeatureDisabled("model-download"))
+}
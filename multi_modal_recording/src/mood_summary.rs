@@ -0,0 +1,246 @@
+//! End-of-day mood summaries.
+//!
+//! Aggregates one local calendar day of the persistent emotion history into a
+//! [`DailyMoodSummary`] (dominant emotions, volatility, notable moments with their recordings)
+//! and stores it in the Soul Vault under `emotion:summary:<YYYY-MM-DD>`.
+//!
+//! [`generate_due`] is safe to call from several periodic jobs (desktop app, web server): a day
+//! is only summarized once, so only the first caller gets a summary back to notify about.
+//! The summary hour is `MOOD_SUMMARY_HOUR` (local, default 21).
+
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::emotion_history::{self, EmotionQuery, EmotionalMoment};
+
+/// Soul-Vault key prefix for stored summaries.
+pub const MOOD_SUMMARY_PREFIX: &str = "emotion:summary:";
+
+/// Number of notable moments kept per summary.
+const NOTABLE_MAX: usize = 3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionShare {
+    pub emotion: String,
+    /// Confidence-weighted share of the day's samples (0.0..=1.0).
+    pub share: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileMood {
+    pub profile: String,
+    pub samples: usize,
+    pub dominant: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DailyMoodSummary {
+    pub date: NaiveDate,
+    pub generated_unix: i64,
+    pub samples: usize,
+    /// Most prevalent emotions, strongest first.
+    pub dominant: Vec<EmotionShare>,
+    /// Fraction of consecutive samples where the label changed (0 = steady, 1 = every sample).
+    pub volatility: f64,
+    pub average_intensity: f64,
+    /// Strongest non-neutral moments; `recording` links back to the capture.
+    pub notable: Vec<EmotionalMoment>,
+    pub profiles: Vec<ProfileMood>,
+}
+
+impl DailyMoodSummary {
+    /// One-line text for notifications.
+    pub fn headline(&self) -> String {
+        match self.dominant.first() {
+            Some(top) => format!(
+                "Mostly {} today ({:.0}% of {} samples), volatility {:.0}%.",
+                top.emotion.to_ascii_lowercase(),
+                top.share * 100.0,
+                self.samples,
+                self.volatility * 100.0
+            ),
+            None => "No emotion samples were recorded today.".to_string(),
+        }
+    }
+}
+
+fn summary_hour() -> u32 {
+    std::env::var("MOOD_SUMMARY_HOUR")
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(21)
+}
+
+fn summary_key(date: NaiveDate) -> String {
+    format!("{MOOD_SUMMARY_PREFIX}{date}")
+}
+
+fn dominant(moments: &[&EmotionalMoment]) -> Vec<EmotionShare> {
+    let mut weight: HashMap<&str, f64> = HashMap::new();
+    for m in moments {
+        *weight.entry(m.emotion.as_str()).or_insert(0.0) += m.confidence.max(0.01);
+    }
+    let total: f64 = weight.values().sum();
+    let mut shares = weight
+        .into_iter()
+        .map(|(emotion, w)| EmotionShare {
+            emotion: emotion.to_string(),
+            share: if total > 0.0 { w / total } else { 0.0 },
+        })
+        .collect::<Vec<_>>();
+    shares.sort_by(|a, b| {
+        b.share
+            .partial_cmp(&a.share)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    shares
+}
+
+/// Aggregate `moments` (oldest first) into a summary for `date`.
+pub fn summarize(date: NaiveDate, moments: &[EmotionalMoment]) -> DailyMoodSummary {
+    let all = moments.iter().collect::<Vec<_>>();
+    let changes = moments
+        .windows(2)
+        .filter(|w| w[0].emotion != w[1].emotion)
+        .count();
+    let volatility = if moments.len() > 1 {
+        changes as f64 / (moments.len() - 1) as f64
+    } else {
+        0.0
+    };
+    let average_intensity = if moments.is_empty() {
+        0.0
+    } else {
+        moments.iter().map(|m| m.intensity).sum::<f64>() / moments.len() as f64
+    };
+
+    let mut notable = moments
+        .iter()
+        .filter(|m| m.emotion != "Neutral")
+        .cloned()
+        .collect::<Vec<_>>();
+    notable.sort_by(|a, b| {
+        (b.intensity * b.confidence)
+            .partial_cmp(&(a.intensity * a.confidence))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    notable.truncate(NOTABLE_MAX);
+
+    let mut by_profile: HashMap<&str, Vec<&EmotionalMoment>> = HashMap::new();
+    for m in moments {
+        by_profile.entry(m.profile_label()).or_default().push(m);
+    }
+    let mut profiles = by_profile
+        .into_iter()
+        .map(|(profile, ms)| ProfileMood {
+            profile: profile.to_string(),
+            samples: ms.len(),
+            dominant: dominant(&ms)
+                .first()
+                .map(|s| s.emotion.clone())
+                .unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    profiles.sort_by(|a, b| a.profile.cmp(&b.profile));
+
+    DailyMoodSummary {
+        date,
+        generated_unix: chrono::Utc::now().timestamp(),
+        samples: moments.len(),
+        dominant: dominant(&all),
+        volatility,
+        average_intensity,
+        notable,
+        profiles,
+    }
+}
+
+/// Unix bounds `[start, end)` of a local calendar day.
+fn day_bounds(date: NaiveDate) -> Option<(i64, i64)> {
+    let start = Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+    let end = Local
+        .from_local_datetime(&date.checked_add_days(Days::new(1))?.and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+    Some((start.timestamp(), end.timestamp()))
+}
+
+/// Summarize `date` from the persistent history without storing it (e.g. "today so far").
+pub fn compute(vaults: &VitalOrganVaults, date: NaiveDate) -> DailyMoodSummary {
+    let moments = day_bounds(date)
+        .map(|(from, to)| {
+            emotion_history::query_moments(
+                vaults,
+                &EmotionQuery {
+                    from_unix: Some(from),
+                    to_unix: Some(to),
+                    ..EmotionQuery::default()
+                },
+            )
+        })
+        .unwrap_or_default();
+    summarize(date, &moments)
+}
+
+/// Stored summary for `date`, if one was generated.
+pub fn load(vaults: &VitalOrganVaults, date: NaiveDate) -> Option<DailyMoodSummary> {
+    vaults
+        .recall_soul(&summary_key(date))
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+}
+
+/// Stored summaries, most recent last.
+pub fn recent(vaults: &VitalOrganVaults, max: usize) -> Vec<DailyMoodSummary> {
+    let mut summaries = vaults
+        .recall_prefix(&format!("soul:{MOOD_SUMMARY_PREFIX}"), usize::MAX)
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_str::<DailyMoodSummary>(&v).ok())
+        .collect::<Vec<_>>();
+    summaries.sort_by_key(|s| s.date);
+    if summaries.len() > max {
+        summaries = summaries.split_off(summaries.len() - max);
+    }
+    summaries
+}
+
+/// Generate whichever summary is due at `now` and not stored yet: today's once the summary
+/// hour has passed, otherwise a missed one for yesterday (app closed overnight). Returns the
+/// newly stored summary; days without samples are skipped.
+pub fn generate_due(vaults: &VitalOrganVaults, now: DateTime<Local>) -> Option<DailyMoodSummary> {
+    let today = now.date_naive();
+    let due = if now.hour() >= summary_hour() {
+        today
+    } else {
+        today.checked_sub_days(Days::new(1))?
+    };
+    if load(vaults, due).is_some() {
+        return None;
+    }
+    let summary = compute(vaults, due);
+    if summary.samples == 0 {
+        return None;
+    }
+    let raw = serde_json::to_string(&summary).ok()?;
+    vaults.store_soul(&summary_key(due), &raw).ok()?;
+    Some(summary)
+}
+
+/// Recompute and overwrite every stored summary from the current history (e.g. after a
+/// profile's emotion data was purged). Returns how many summaries were rewritten.
+pub fn regenerate_all(vaults: &VitalOrganVaults) -> usize {
+    recent(vaults, usize::MAX)
+        .into_iter()
+        .filter_map(|old| {
+            let summary = DailyMoodSummary {
+                generated_unix: old.generated_unix,
+                ..compute(vaults, old.date)
+            };
+            let raw = serde_json::to_string(&summary).ok()?;
+            vaults.store_soul(&summary_key(old.date), &raw).ok()
+        })
+        .count()
+}
@@ -0,0 +1,383 @@
+//! Playback of recordings for the in-app player.
+//!
+//! Bundles are decrypted in memory. With the `audio` feature the payload is played through the
+//! default output device when rodio can decode it; otherwise (and in builds without `audio`)
+//! playback runs silently on a clock, so position, seeking and end-of-file behave the same for
+//! every recording.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::Error;
+
+/// Interval between position updates while playing.
+const TICK_MS: u64 = 250;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackState {
+    #[default]
+    Stopped,
+    Playing,
+    Paused,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NowPlaying {
+    pub path: Option<PathBuf>,
+    pub state: PlaybackState,
+    pub position_secs: f64,
+    pub duration_secs: f64,
+    /// Sound is actually being output (`false`: silent clock).
+    pub audible: bool,
+}
+
+/// Playback position, advancing while resumed.
+#[derive(Default)]
+struct Clock {
+    offset: f64,
+    resumed_at: Option<Instant>,
+}
+
+impl Clock {
+    fn position(&self) -> f64 {
+        self.offset + self.resumed_at.map_or(0.0, |t| t.elapsed().as_secs_f64())
+    }
+
+    fn pause(&mut self) {
+        self.offset = self.position();
+        self.resumed_at = None;
+    }
+
+    fn resume(&mut self) {
+        self.resumed_at.get_or_insert_with(Instant::now);
+    }
+
+    fn seek(&mut self, to: f64) {
+        self.offset = to;
+        if self.resumed_at.is_some() {
+            self.resumed_at = Some(Instant::now());
+        }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    path: Option<PathBuf>,
+    state: PlaybackState,
+    duration_secs: f64,
+    clock: Clock,
+    /// Bumped on every load so a stale ticker stops.
+    generation: u64,
+    #[cfg(feature = "audio")]
+    output: Option<output::Output>,
+}
+
+impl Inner {
+    fn audible(&self) -> bool {
+        #[cfg(feature = "audio")]
+        {
+            self.output.is_some()
+        }
+        #[cfg(not(feature = "audio"))]
+        {
+            false
+        }
+    }
+
+    fn snapshot(&self) -> NowPlaying {
+        NowPlaying {
+            path: self.path.clone(),
+            state: self.state,
+            position_secs: self.clock.position().min(self.duration_secs),
+            duration_secs: self.duration_secs,
+            audible: self.audible(),
+        }
+    }
+}
+
+/// Single-track player shared by every clone.
+#[derive(Clone)]
+pub struct Player {
+    inner: Arc<Mutex<Inner>>,
+    tx: broadcast::Sender<NowPlaying>,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner::default())),
+            tx: broadcast::channel(64).0,
+        }
+    }
+}
+
+impl Player {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn publish(&self, now: NowPlaying) -> NowPlaying {
+        let _ = self.tx.send(now.clone());
+        now
+    }
+
+    /// State changes and position updates (every [`TICK_MS`] while playing).
+    pub fn subscribe(&self) -> broadcast::Receiver<NowPlaying> {
+        self.tx.subscribe()
+    }
+
+    pub fn now_playing(&self) -> NowPlaying {
+        self.lock().snapshot()
+    }
+
+    /// Replace the current track (paused at the start).
+    pub fn load(&self, path: &Path, payload: Vec<u8>, duration_secs: f64) -> NowPlaying {
+        let mut inner = self.lock();
+        #[cfg(feature = "audio")]
+        let (output, duration_secs) = match output::Output::start(payload) {
+            Some((output, decoded)) => (Some(output), decoded.unwrap_or(duration_secs)),
+            None => (None, duration_secs),
+        };
+        #[cfg(not(feature = "audio"))]
+        let _ = payload;
+        *inner = Inner {
+            path: Some(path.to_path_buf()),
+            state: PlaybackState::Paused,
+            duration_secs: duration_secs.max(0.0),
+            clock: Clock::default(),
+            generation: inner.generation + 1,
+            #[cfg(feature = "audio")]
+            output,
+        };
+        let now = inner.snapshot();
+        drop(inner);
+        self.publish(now)
+    }
+
+    pub fn play(&self) -> Result<NowPlaying, Error> {
+        let mut inner = self.lock();
+        if inner.path.is_none() {
+            return Err(Error::InvalidArgument("nothing to play".to_string()));
+        }
+        if inner.state == PlaybackState::Playing {
+            return Ok(inner.snapshot());
+        }
+        // Replay from the start once the end was reached.
+        if inner.clock.position() >= inner.duration_secs {
+            inner.clock.seek(0.0);
+            #[cfg(feature = "audio")]
+            if let Some(out) = inner.output.as_ref() {
+                out.seek(Duration::ZERO);
+            }
+        }
+        inner.state = PlaybackState::Playing;
+        inner.clock.resume();
+        #[cfg(feature = "audio")]
+        if let Some(out) = inner.output.as_ref() {
+            out.play();
+        }
+        let generation = inner.generation;
+        let now = inner.snapshot();
+        drop(inner);
+        self.spawn_ticker(generation);
+        Ok(self.publish(now))
+    }
+
+    pub fn pause(&self) -> NowPlaying {
+        let mut inner = self.lock();
+        if inner.state == PlaybackState::Playing {
+            inner.state = PlaybackState::Paused;
+            inner.clock.pause();
+            #[cfg(feature = "audio")]
+            if let Some(out) = inner.output.as_ref() {
+                out.pause();
+            }
+        }
+        let now = inner.snapshot();
+        drop(inner);
+        self.publish(now)
+    }
+
+    pub fn seek(&self, position_secs: f64) -> Result<NowPlaying, Error> {
+        if !position_secs.is_finite() {
+            return Err(Error::InvalidArgument(
+                "position must be a number".to_string(),
+            ));
+        }
+        let mut inner = self.lock();
+        if inner.path.is_none() {
+            return Err(Error::InvalidArgument("nothing to seek in".to_string()));
+        }
+        let to = position_secs.clamp(0.0, inner.duration_secs);
+        inner.clock.seek(to);
+        #[cfg(feature = "audio")]
+        if let Some(out) = inner.output.as_ref() {
+            out.seek(Duration::from_secs_f64(to));
+        }
+        if inner.state == PlaybackState::Stopped {
+            inner.state = PlaybackState::Paused;
+        }
+        let now = inner.snapshot();
+        drop(inner);
+        Ok(self.publish(now))
+    }
+
+    /// Publish the position while playing; stop at the end of the track.
+    fn spawn_ticker(&self, generation: u64) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(TICK_MS)).await;
+                let mut inner = this.lock();
+                if inner.generation != generation || inner.state != PlaybackState::Playing {
+                    return;
+                }
+                if inner.clock.position() >= inner.duration_secs {
+                    inner.state = PlaybackState::Stopped;
+                    inner.clock.pause();
+                    #[cfg(feature = "audio")]
+                    if let Some(out) = inner.output.as_ref() {
+                        out.pause();
+                    }
+                    let now = inner.snapshot();
+                    drop(inner);
+                    this.publish(now);
+                    return;
+                }
+                let now = inner.snapshot();
+                drop(inner);
+                this.publish(now);
+            }
+        });
+    }
+}
+
+#[cfg(feature = "audio")]
+mod output {
+    //! rodio's output stream is not `Send`, so it lives on its own thread and is driven by
+    //! messages.
+
+    use std::io::Cursor;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    enum Command {
+        Play,
+        Pause,
+        Seek(Duration),
+    }
+
+    pub struct Output {
+        tx: mpsc::Sender<Command>,
+    }
+
+    impl Output {
+        /// Start a paused output for `payload`, with its decoded duration if known. `None` when
+        /// it can't be decoded or there is no output device.
+        pub fn start(payload: Vec<u8>) -> Option<(Self, Option<f64>)> {
+            let (tx, rx) = mpsc::channel();
+            let (ready_tx, ready_rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let Ok(decoder) = rodio::Decoder::new(Cursor::new(payload)) else {
+                    let _ = ready_tx.send(None);
+                    return;
+                };
+                let total = rodio::Source::total_duration(&decoder).map(|d| d.as_secs_f64());
+                let Ok((_stream, handle)) = rodio::OutputStream::try_default() else {
+                    let _ = ready_tx.send(None);
+                    return;
+                };
+                let Ok(sink) = rodio::Sink::try_new(&handle) else {
+                    let _ = ready_tx.send(None);
+                    return;
+                };
+                sink.pause();
+                sink.append(decoder);
+                let _ = ready_tx.send(Some(total));
+                // Ends (dropping the stream) once the `Output` is dropped.
+                while let Ok(cmd) = rx.recv() {
+                    match cmd {
+                        Command::Play => sink.play(),
+                        Command::Pause => sink.pause(),
+                        Command::Seek(to) => {
+                            let _ = sink.try_seek(to);
+                        }
+                    }
+                }
+            });
+            let total = ready_rx.recv().ok().flatten()?;
+            Some((Self { tx }, total))
+        }
+
+        pub fn play(&self) {
+            let _ = self.tx.send(Command::Play);
+        }
+
+        pub fn pause(&self) {
+            let _ = self.tx.send(Command::Pause);
+        }
+
+        pub fn seek(&self, to: Duration) {
+            let _ = self.tx.send(Command::Seek(to));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeking_and_pausing_follow_the_track() {
+        let player = Player::default();
+        assert!(player.play().is_err());
+        assert!(player.seek(1.0).is_err());
+
+        let loaded = player.load(Path::new("r.phoenixrec"), Vec::new(), 10.0);
+        assert_eq!(loaded.state, PlaybackState::Paused);
+        assert_eq!(loaded.position_secs, 0.0);
+        assert!(player.seek(f64::NAN).is_err());
+        // Clamped to the track.
+        assert_eq!(player.seek(-3.0).unwrap().position_secs, 0.0);
+        assert_eq!(player.seek(42.0).unwrap().position_secs, 10.0);
+
+        let paused = player.seek(4.0).unwrap();
+        assert_eq!(
+            (paused.state, paused.position_secs),
+            (PlaybackState::Paused, 4.0)
+        );
+        let held = player.pause();
+        assert_eq!(
+            (held.state, held.position_secs),
+            (PlaybackState::Paused, 4.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn playing_past_the_end_stops_and_replays_from_the_start() {
+        let player = Player::default();
+        let mut updates = player.subscribe();
+        player.load(Path::new("r.phoenixrec"), Vec::new(), 0.3);
+        assert_eq!(player.play().unwrap().state, PlaybackState::Playing);
+
+        let stopped = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let now = updates.recv().await.unwrap();
+                if now.state == PlaybackState::Stopped {
+                    return now;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(stopped.position_secs, 0.3);
+
+        let replay = player.play().unwrap();
+        assert_eq!(replay.state, PlaybackState::Playing);
+        assert!(replay.position_secs < 0.3);
+    }
+}
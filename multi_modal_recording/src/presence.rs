@@ -0,0 +1,79 @@
+//! Unknown-person ("visitor") presence events.
+//!
+//! When recognition runs and no enrolled profile clears its thresholds, the recorder emits an
+//! [`UnknownPresenceEvent`] on its presence channel and appends it to the Soul-Vault
+//! `presence_events` timeline. If a video frame was available, a snapshot is written next to
+//! the recordings so the user can later turn the visitor into an enrolled profile.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use vital_organ_vaults::VitalOrganVaults;
+
+/// Soul-Vault key holding the presence timeline (JSON lines, most recent last).
+pub const PRESENCE_TIMELINE_KEY: &str = "presence_events";
+
+/// Maximum number of presence events kept in the timeline.
+const PRESENCE_TIMELINE_MAX: usize = 200;
+
+/// Minimum spacing between two unknown-presence events, so a visitor standing in front of
+/// the camera doesn't produce an event every frame.
+pub const UNKNOWN_PRESENCE_COOLDOWN_SECS: i64 = 60;
+
+/// A voice/face was observed that matches no enrolled profile.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnknownPresenceEvent {
+    pub id: String,
+    pub ts_unix: i64,
+    pub voice_confidence: f32,
+    pub face_confidence: f32,
+    /// Closest enrolled profile, if any scored at all.
+    pub closest_profile: Option<String>,
+    /// Saved frame of the visitor (PNG), when video was available.
+    pub snapshot: Option<PathBuf>,
+}
+
+/// Events published on the recorder's presence channel.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PresenceEvent {
+    Unknown(UnknownPresenceEvent),
+    /// A previously unknown visitor was enrolled as `profile`.
+    Enrolled {
+        event_id: String,
+        profile: String,
+    },
+}
+
+pub(crate) fn append_to_timeline(vaults: &VitalOrganVaults, event: &UnknownPresenceEvent) {
+    let Ok(entry) = serde_json::to_string(event) else {
+        return;
+    };
+    let existing = vaults
+        .recall_soul(PRESENCE_TIMELINE_KEY)
+        .unwrap_or_default();
+    let mut lines = existing
+        .lines()
+        .map(|s| s.to_string())
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<_>>();
+    lines.push(entry);
+    if lines.len() > PRESENCE_TIMELINE_MAX {
+        lines = lines.split_off(lines.len() - PRESENCE_TIMELINE_MAX);
+    }
+    let _ = vaults.store_soul(PRESENCE_TIMELINE_KEY, &lines.join("\n"));
+}
+
+/// Read the presence timeline (most recent last).
+pub fn recent(vaults: &VitalOrganVaults, max: usize) -> Vec<UnknownPresenceEvent> {
+    let raw = vaults
+        .recall_soul(PRESENCE_TIMELINE_KEY)
+        .unwrap_or_default();
+    let mut events = raw
+        .lines()
+        .filter_map(|l| serde_json::from_str::<UnknownPresenceEvent>(l).ok())
+        .collect::<Vec<_>>();
+    if events.len() > max {
+        events = events.split_off(events.len() - max);
+    }
+    events
+}
@@ -0,0 +1,395 @@
+//! Per-profile recognition thresholds and calibration.
+//!
+//! Thresholds are persisted in `models/thresholds.json`. Calibration scores every retained
+//! enrollment sample against the profile's other samples (leave-one-out, "genuine" attempts)
+//! and against every other profile's samples ("impostor" attempts), then reports the
+//! false-accept / false-reject tradeoff across a sweep of candidate thresholds. Live samples are
+//! matched the same way: against the centroid of each enrolled profile's samples, with that
+//! profile's thresholds.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use crate::embeddings::{
+    cosine_similarity, EmbeddingModel, EmbeddingStore, Modality, StoredEmbedding,
+};
+use crate::Error;
+
+/// Match thresholds for one profile (0.0..=1.0 similarity).
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecognitionThresholds {
+    pub voice: f32,
+    pub face: f32,
+}
+
+impl Default for RecognitionThresholds {
+    fn default() -> Self {
+        Self {
+            voice: 0.80,
+            face: 0.80,
+        }
+    }
+}
+
+impl RecognitionThresholds {
+    pub fn for_modality(&self, modality: Modality) -> f32 {
+        match modality {
+            Modality::Voice => self.voice,
+            Modality::Face => self.face,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        for (name, v) in [("voice", self.voice), ("face", self.face)] {
+            if !(0.0..=1.0).contains(&v) {
+                return Err(Error::InvalidArgument(format!(
+                    "{name} threshold must be within 0.0..=1.0 (got {v})"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One point on the threshold sweep.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ThresholdPoint {
+    pub threshold: f32,
+    /// Fraction of impostor attempts that would be accepted.
+    pub false_accept_rate: f32,
+    /// Fraction of genuine attempts that would be rejected.
+    pub false_reject_rate: f32,
+}
+
+/// Calibration result for one profile + modality.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    pub profile: String,
+    pub modality: Modality,
+    pub genuine_attempts: usize,
+    pub impostor_attempts: usize,
+    pub current_threshold: f32,
+    pub current: Option<ThresholdPoint>,
+    /// Threshold closest to the equal-error point of the sweep.
+    pub recommended_threshold: Option<f32>,
+    pub sweep: Vec<ThresholdPoint>,
+    /// Human-readable caveats (e.g. too few samples to hold any out).
+    pub notes: Vec<String>,
+}
+
+/// File-backed per-profile threshold settings.
+#[derive(Clone, Debug)]
+pub struct ThresholdStore {
+    path: PathBuf,
+}
+
+impl ThresholdStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn load_all(&self) -> Result<HashMap<String, RecognitionThresholds>, Error> {
+        if !self.path.is_file() {
+            return Ok(HashMap::new());
+        }
+        Ok(serde_json::from_slice(&crate::sealed::read(&self.path)?)?)
+    }
+
+    /// Thresholds for `profile`, falling back to defaults when never tuned.
+    pub fn get(&self, profile: &str) -> Result<RecognitionThresholds, Error> {
+        Ok(self.load_all()?.get(profile).copied().unwrap_or_default())
+    }
+
+    pub fn set(&self, profile: &str, thresholds: RecognitionThresholds) -> Result<(), Error> {
+        thresholds.validate()?;
+        let mut all = self.load_all()?;
+        all.insert(profile.to_string(), thresholds);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crate::sealed::write(&self.path, &serde_json::to_vec_pretty(&all)?)?;
+        Ok(())
+    }
+}
+
+/// How well a live sample matches one enrolled profile.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProfileMatch {
+    pub profile: String,
+    /// Similarity to the profile's voice enrollment; `None` when the profile has no current
+    /// voice embeddings or there was no live audio.
+    pub voice: Option<f32>,
+    /// Likewise for the face.
+    pub face: Option<f32>,
+    /// Whether every compared modality cleared the profile's own threshold.
+    pub accepted: bool,
+}
+
+impl ProfileMatch {
+    /// Mean similarity over the compared modalities.
+    pub fn combined(&self) -> f32 {
+        let scores: Vec<f32> = self.voice.iter().chain(&self.face).copied().collect();
+        if scores.is_empty() {
+            0.0
+        } else {
+            scores.iter().sum::<f32>() / scores.len() as f32
+        }
+    }
+}
+
+/// Score live voice / face embeddings against every enrolled profile, each with its own
+/// thresholds. Accepted matches come first, best first; profiles with nothing to compare are
+/// left out.
+pub fn match_profiles(
+    store: &EmbeddingStore,
+    thresholds: &ThresholdStore,
+    voice: Option<&[f32]>,
+    face: Option<&[f32]>,
+) -> Result<Vec<ProfileMatch>, Error> {
+    let mut scores: BTreeMap<String, [Option<f32>; 2]> = BTreeMap::new();
+    for (slot, modality, live) in [(0, Modality::Voice, voice), (1, Modality::Face, face)] {
+        let Some(live) = live else {
+            continue;
+        };
+        let model = EmbeddingModel::current(modality);
+        let mut by_profile: BTreeMap<String, Vec<Vec<f32>>> = BTreeMap::new();
+        for entry in store.load(modality)? {
+            if entry.model == model {
+                by_profile
+                    .entry(entry.profile)
+                    .or_default()
+                    .push(entry.vector);
+            }
+        }
+        for (profile, vectors) in by_profile {
+            let vectors: Vec<&[f32]> = vectors.iter().map(Vec::as_slice).collect();
+            let similarity = cosine_similarity(live, &centroid(&vectors)).clamp(0.0, 1.0);
+            scores.entry(profile).or_default()[slot] = Some(similarity);
+        }
+    }
+
+    let all_thresholds = thresholds.load_all()?;
+    let mut matches: Vec<ProfileMatch> = scores
+        .into_iter()
+        .map(|(profile, [voice, face])| {
+            let limits = all_thresholds.get(&profile).copied().unwrap_or_default();
+            let accepted =
+                voice.is_none_or(|s| s >= limits.voice) && face.is_none_or(|s| s >= limits.face);
+            ProfileMatch {
+                profile,
+                voice,
+                face,
+                accepted,
+            }
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.accepted
+            .cmp(&a.accepted)
+            .then(b.combined().total_cmp(&a.combined()))
+    });
+    Ok(matches)
+}
+
+/// Evaluate `current_threshold` against held-out enrollment samples for `profile`.
+pub fn calibrate(
+    store: &EmbeddingStore,
+    profile: &str,
+    modality: Modality,
+    current_threshold: f32,
+) -> Result<CalibrationReport, Error> {
+    let model = EmbeddingModel::current(modality);
+    let entries: Vec<StoredEmbedding> = store
+        .load(modality)?
+        .into_iter()
+        .filter(|e| e.model == model)
+        .collect();
+    let (own, others): (Vec<_>, Vec<_>) = entries.iter().partition(|e| e.profile == profile);
+
+    let mut notes = Vec::new();
+    let mut genuine = Vec::new();
+    if own.len() < 2 {
+        notes.push(format!(
+            "profile has {} current {} sample(s); at least 2 are needed to hold one out",
+            own.len(),
+            modality.as_str()
+        ));
+    } else {
+        for (i, held_out) in own.iter().enumerate() {
+            let rest: Vec<&[f32]> = own
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, e)| e.vector.as_slice())
+                .collect();
+            genuine.push(cosine_similarity(&held_out.vector, &centroid(&rest)));
+        }
+    }
+
+    let all_own: Vec<&[f32]> = own.iter().map(|e| e.vector.as_slice()).collect();
+    let profile_centroid = centroid(&all_own);
+    let impostor: Vec<f32> = if all_own.is_empty() {
+        Vec::new()
+    } else {
+        others
+            .iter()
+            .map(|e| cosine_similarity(&e.vector, &profile_centroid))
+            .collect()
+    };
+    if impostor.is_empty() {
+        notes.push("no other enrolled profiles; false-accept rate cannot be estimated".to_string());
+    }
+
+    let sweep: Vec<ThresholdPoint> = (0..=20)
+        .map(|i| evaluate(i as f32 * 0.05, &genuine, &impostor))
+        .collect();
+    let recommended_threshold = if genuine.is_empty() || impostor.is_empty() {
+        None
+    } else {
+        sweep
+            .iter()
+            .min_by(|a, b| {
+                let da = (a.false_accept_rate - a.false_reject_rate).abs();
+                let db = (b.false_accept_rate - b.false_reject_rate).abs();
+                da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|p| p.threshold)
+    };
+
+    Ok(CalibrationReport {
+        profile: profile.to_string(),
+        modality,
+        genuine_attempts: genuine.len(),
+        impostor_attempts: impostor.len(),
+        current_threshold,
+        current: (!genuine.is_empty() || !impostor.is_empty())
+            .then(|| evaluate(current_threshold, &genuine, &impostor)),
+        recommended_threshold,
+        sweep,
+        notes,
+    })
+}
+
+fn evaluate(threshold: f32, genuine: &[f32], impostor: &[f32]) -> ThresholdPoint {
+    let rate = |n: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            n as f32 / total as f32
+        }
+    };
+    ThresholdPoint {
+        threshold,
+        false_accept_rate: rate(
+            impostor.iter().filter(|s| **s >= threshold).count(),
+            impostor.len(),
+        ),
+        false_reject_rate: rate(
+            genuine.iter().filter(|s| **s < threshold).count(),
+            genuine.len(),
+        ),
+    }
+}
+
+fn centroid(vectors: &[&[f32]]) -> Vec<f32> {
+    let Some(first) = vectors.first() else {
+        return Vec::new();
+    };
+    let mut out = vec![0.0f32; first.len()];
+    for v in vectors {
+        for (o, x) in out.iter_mut().zip(v.iter()) {
+            *o += x;
+        }
+    }
+    let n = vectors.len() as f32;
+    out.iter_mut().for_each(|o| *o /= n);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mmr-rec-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn calibration_recommends_the_threshold_that_separates_known_samples() {
+        let dir = temp_dir();
+        let sample = dir.join("sample.wav");
+        std::fs::write(&sample, b"sample-bytes").unwrap();
+        let store = EmbeddingStore::new(dir.join("embeddings"));
+        store
+            .enroll(
+                "Dad",
+                Modality::Voice,
+                &[sample.clone(), sample.clone(), sample.clone()],
+            )
+            .unwrap();
+        store
+            .enroll("Kid", Modality::Voice, &[sample.clone(), sample])
+            .unwrap();
+
+        // Replace the stub vectors with known ones: Dad's samples agree exactly and Kid's sit
+        // at cos = 1/sqrt(5) ~ 0.447 from Dad's centroid.
+        let mut entries = store.load(Modality::Voice).unwrap();
+        for e in &mut entries {
+            e.vector = if e.profile == "Dad" {
+                vec![1.0, 0.0]
+            } else {
+                vec![1.0, 2.0]
+            };
+        }
+        store.save(Modality::Voice, &entries).unwrap();
+
+        let report = calibrate(&store, "Dad", Modality::Voice, 0.4).unwrap();
+        assert_eq!(report.genuine_attempts, 3);
+        assert_eq!(report.impostor_attempts, 2);
+        assert!(report.notes.is_empty());
+        let current = report.current.unwrap();
+        assert_eq!(current.false_accept_rate, 1.0);
+        assert_eq!(current.false_reject_rate, 0.0);
+        // 0.45 is the first swept threshold that rejects both impostors.
+        let recommended = report.recommended_threshold.unwrap();
+        assert!((recommended - 0.45).abs() < 1e-4, "got {recommended}");
+
+        let alone = calibrate(&store, "Nobody", Modality::Voice, 0.8).unwrap();
+        assert_eq!(alone.genuine_attempts, 0);
+        assert_eq!(alone.recommended_threshold, None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn thresholds_survive_a_reload() {
+        let dir = temp_dir();
+        let path = dir.join("models").join("thresholds.json");
+        let tuned = RecognitionThresholds {
+            voice: 0.65,
+            face: 0.9,
+        };
+        ThresholdStore::new(&path).set("Kid", tuned).unwrap();
+        assert!(ThresholdStore::new(&path)
+            .set(
+                "Kid",
+                RecognitionThresholds {
+                    voice: 1.5,
+                    face: 0.9
+                }
+            )
+            .is_err());
+
+        let reopened = ThresholdStore::new(&path);
+        assert_eq!(reopened.get("Kid").unwrap(), tuned);
+        assert_eq!(
+            reopened.get("Dad").unwrap(),
+            RecognitionThresholds::default()
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
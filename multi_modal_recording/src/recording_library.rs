@@ -0,0 +1,434 @@
+//! Recording library listing for UIs.
+//!
+//! Entries are built from each `.phoenixrec` bundle's metadata header (only the header is
+//! decrypted), its emotion-track sidecar and an optional thumbnail sidecar
+//! (`<name>.phoenixrec.thumb.jpg` / `.png`). Listing is newest first and paginated.
+//!
+//! With a database set ([`crate::use_storage`]) built entries are kept in its recordings index
+//! and reused until the bundle or one of its sidecars changes.
+
+use phoenix_storage::recordings::IndexedRecording;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::AsyncReadExt;
+
+use crate::emotion_track::{self, EmotionTrack};
+use crate::{derive_key_from_env, xor_encrypt, Error, RecordingMeta};
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 200;
+
+const THUMBNAIL_SUFFIXES: &[&str] = &[".thumb.jpg", ".thumb.png"];
+const BUNDLE_MAGIC: &[u8; 8] = b"PHXREC\0\0";
+/// Metadata headers are small; anything bigger is a corrupt length field.
+const MAX_META_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingModality {
+    Audio,
+    Video,
+    AudioVideo,
+    /// Neither audio nor video was enabled.
+    NoCapture,
+}
+
+impl RecordingModality {
+    pub fn from_flags(audio: bool, video: bool) -> Self {
+        match (audio, video) {
+            (true, true) => Self::AudioVideo,
+            (true, false) => Self::Audio,
+            (false, true) => Self::Video,
+            (false, false) => Self::NoCapture,
+        }
+    }
+}
+
+/// Summary of a recording's emotion track.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmotionSummary {
+    /// Emotion with the most points (ties go to the higher total intensity).
+    pub dominant: String,
+    pub peak_intensity: f64,
+    pub mean_valence: Option<f64>,
+    pub points: usize,
+}
+
+impl EmotionSummary {
+    pub fn from_track(track: &EmotionTrack) -> Option<Self> {
+        let mut by_emotion: HashMap<&str, (usize, f64)> = HashMap::new();
+        for p in &track.points {
+            let e = by_emotion.entry(p.emotion.as_str()).or_default();
+            e.0 += 1;
+            e.1 += p.intensity;
+        }
+        let (dominant, _) = by_emotion.into_iter().max_by(|a, b| {
+            a.1 .0
+                .cmp(&b.1 .0)
+                .then(a.1 .1.total_cmp(&b.1 .1))
+                .then(b.0.cmp(a.0))
+        })?;
+        let valences = track
+            .points
+            .iter()
+            .filter_map(|p| p.valence)
+            .collect::<Vec<_>>();
+        Some(Self {
+            dominant: dominant.to_string(),
+            peak_intensity: track.points.iter().map(|p| p.intensity).fold(0.0, f64::max),
+            mean_valence: (!valences.is_empty())
+                .then(|| valences.iter().sum::<f64>() / valences.len() as f64),
+            points: track.points.len(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordingEntry {
+    pub path: PathBuf,
+    pub created_unix: i64,
+    pub duration_secs: u64,
+    pub modality: RecordingModality,
+    pub purpose: Option<String>,
+    pub size_bytes: u64,
+    pub emotion: Option<EmotionSummary>,
+    pub thumbnail: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecordingFilter {
+    #[serde(default)]
+    pub modality: Option<RecordingModality>,
+    #[serde(default)]
+    pub from_unix: Option<i64>,
+    #[serde(default)]
+    pub to_unix: Option<i64>,
+    /// Case-insensitive substring of the purpose.
+    #[serde(default)]
+    pub purpose: Option<String>,
+    /// Dominant emotion (case-insensitive).
+    #[serde(default)]
+    pub emotion: Option<String>,
+}
+
+impl RecordingFilter {
+    pub fn matches(&self, e: &RecordingEntry) -> bool {
+        self.modality.is_none_or(|m| e.modality == m)
+            && self.from_unix.is_none_or(|from| e.created_unix >= from)
+            && self.to_unix.is_none_or(|to| e.created_unix <= to)
+            && self.purpose.as_deref().is_none_or(|want| {
+                e.purpose
+                    .as_deref()
+                    .is_some_and(|p| p.to_lowercase().contains(&want.to_lowercase()))
+            })
+            && self.emotion.as_deref().is_none_or(|want| {
+                e.emotion
+                    .as_ref()
+                    .is_some_and(|s| s.dominant.eq_ignore_ascii_case(want))
+            })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordingPage {
+    /// Zero-based.
+    pub page: usize,
+    pub page_size: usize,
+    /// Matching recordings across all pages.
+    pub total: usize,
+    pub entries: Vec<RecordingEntry>,
+}
+
+/// Filter, sort newest first and cut out one page. `page_size` is clamped to
+/// `1..=MAX_PAGE_SIZE` (0 means [`DEFAULT_PAGE_SIZE`]).
+pub fn paginate(
+    mut entries: Vec<RecordingEntry>,
+    filter: &RecordingFilter,
+    page: usize,
+    page_size: usize,
+) -> RecordingPage {
+    let page_size = match page_size {
+        0 => DEFAULT_PAGE_SIZE,
+        n => n.min(MAX_PAGE_SIZE),
+    };
+    entries.retain(|e| filter.matches(e));
+    entries.sort_by(|a, b| {
+        b.created_unix
+            .cmp(&a.created_unix)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    let total = entries.len();
+    let entries = entries
+        .into_iter()
+        .skip(page.saturating_mul(page_size))
+        .take(page_size)
+        .collect();
+    RecordingPage {
+        page,
+        page_size,
+        total,
+        entries,
+    }
+}
+
+/// Decrypt just the metadata header of a bundle.
+async fn read_meta(path: &Path) -> Option<RecordingMeta> {
+    let key = derive_key_from_env();
+    let mut file = tokio::fs::File::open(path).await.ok()?;
+    let mut head = [0u8; 12];
+    file.read_exact(&mut head).await.ok()?;
+    let head = xor_encrypt(&head, &key);
+    if &head[..8] != BUNDLE_MAGIC {
+        return None;
+    }
+    let len = u32::from_le_bytes(head[8..12].try_into().ok()?) as usize;
+    if len > MAX_META_BYTES {
+        return None;
+    }
+    let mut raw = vec![0u8; len];
+    file.read_exact(&mut raw).await.ok()?;
+    // The XOR key stream continues from offset 12.
+    let raw = raw
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b ^ key[(i + head.len()) % key.len()])
+        .collect::<Vec<_>>();
+    serde_json::from_slice(&raw).ok()
+}
+
+/// Decrypt a whole bundle into its metadata and payload.
+pub(crate) async fn read_bundle(path: &Path) -> Result<(RecordingMeta, Vec<u8>), Error> {
+    open_bundle(path, &tokio::fs::read(path).await?)
+}
+
+/// The captured media of the recording at `path`, decrypted, for exports. Blocks on the read.
+pub fn read_media(path: &Path) -> Result<Vec<u8>, Error> {
+    open_bundle(path, &std::fs::read(path)?).map(|(_, payload)| payload)
+}
+
+/// Pack and encrypt a bundle: magic, metadata length, metadata, payload.
+pub(crate) fn seal_bundle(meta: &RecordingMeta, payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let meta_json = serde_json::to_vec(meta)?;
+    let mut bundle = Vec::with_capacity(12 + meta_json.len() + payload.len());
+    bundle.extend_from_slice(BUNDLE_MAGIC);
+    bundle.extend_from_slice(&(meta_json.len() as u32).to_le_bytes());
+    bundle.extend_from_slice(&meta_json);
+    bundle.extend_from_slice(payload);
+    Ok(xor_encrypt(&bundle, &derive_key_from_env()))
+}
+
+/// Write media from a data export (see [`read_media`]) back as a recording at `dest`, described
+/// by its library `entry`. Blocks on the write.
+pub fn write_media(dest: &Path, entry: &RecordingEntry, media: &[u8]) -> Result<(), Error> {
+    let (audio_enabled, video_enabled) = match entry.modality {
+        RecordingModality::Audio => (true, false),
+        RecordingModality::Video => (false, true),
+        RecordingModality::AudioVideo => (true, true),
+        RecordingModality::NoCapture => (false, false),
+    };
+    let meta = RecordingMeta {
+        created_unix: entry.created_unix,
+        duration_secs: entry.duration_secs,
+        audio_enabled,
+        video_enabled,
+        purpose: entry.purpose.clone(),
+        wake_word: crate::RecorderConfig::default().wake_word,
+    };
+    std::fs::write(dest, seal_bundle(&meta, media)?)?;
+    Ok(())
+}
+
+fn open_bundle(path: &Path, encrypted: &[u8]) -> Result<(RecordingMeta, Vec<u8>), Error> {
+    let raw = xor_encrypt(encrypted, &derive_key_from_env());
+    let invalid = || Error::InvalidArgument(format!("{} is not a recording", path.display()));
+    if raw.len() < 12 || &raw[..8] != BUNDLE_MAGIC {
+        return Err(invalid());
+    }
+    let len = u32::from_le_bytes(raw[8..12].try_into().map_err(|_| invalid())?) as usize;
+    if len > MAX_META_BYTES || raw.len() < 12 + len {
+        return Err(invalid());
+    }
+    let meta = serde_json::from_slice(&raw[12..12 + len])?;
+    Ok((meta, raw[12 + len..].to_vec()))
+}
+
+async fn thumbnail(recording: &Path) -> Option<PathBuf> {
+    for suffix in THUMBNAIL_SUFFIXES {
+        let mut name = recording.as_os_str().to_owned();
+        name.push(suffix);
+        let p = PathBuf::from(name);
+        if tokio::fs::try_exists(&p).await.unwrap_or(false) {
+            return Some(p);
+        }
+    }
+    None
+}
+
+/// Size and modification time of the bundle and every sidecar that exists, hashed.
+async fn fingerprint(recording: &Path) -> i64 {
+    let mut files = vec![
+        recording.to_path_buf(),
+        emotion_track::sidecar_path(recording),
+    ];
+    for suffix in THUMBNAIL_SUFFIXES {
+        let mut name = recording.as_os_str().to_owned();
+        name.push(suffix);
+        files.push(PathBuf::from(name));
+    }
+    let mut hasher = DefaultHasher::new();
+    for (i, file) in files.iter().enumerate() {
+        if let Ok(meta) = tokio::fs::metadata(file).await {
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok());
+            (i, meta.len(), modified).hash(&mut hasher);
+        }
+    }
+    hasher.finish() as i64
+}
+
+async fn build_entry(path: PathBuf, size_bytes: u64) -> Option<RecordingEntry> {
+    let meta = read_meta(&path).await?;
+    let emotion = emotion_track::load(&path)
+        .await
+        .as_ref()
+        .and_then(EmotionSummary::from_track);
+    Some(RecordingEntry {
+        thumbnail: thumbnail(&path).await,
+        path,
+        created_unix: meta.created_unix,
+        duration_secs: meta.duration_secs,
+        modality: RecordingModality::from_flags(meta.audio_enabled, meta.video_enabled),
+        purpose: meta.purpose,
+        size_bytes,
+        emotion,
+    })
+}
+
+/// Every readable recording in `storage_dir` (unordered).
+pub async fn scan(storage_dir: &Path) -> Result<Vec<RecordingEntry>, Error> {
+    let mut out = Vec::new();
+    if !tokio::fs::try_exists(storage_dir).await.unwrap_or(false) {
+        return Ok(out);
+    }
+    let storage = crate::storage();
+    let dir = storage_dir.display().to_string();
+    let mut indexed: HashMap<String, IndexedRecording> = match storage {
+        Some(storage) => storage
+            .indexed_recordings(&dir)?
+            .into_iter()
+            .map(|r| (r.path.clone(), r))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let mut changed = Vec::new();
+
+    let mut rd = tokio::fs::read_dir(storage_dir).await?;
+    while let Some(entry) = rd.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("phoenixrec") {
+            continue;
+        }
+        let key = path.display().to_string();
+        let cached = indexed.remove(&key);
+        let fingerprint = match storage {
+            Some(_) => fingerprint(&path).await,
+            None => 0,
+        };
+        if let Some(hit) = cached
+            .filter(|r| r.fingerprint == fingerprint)
+            .and_then(|r| serde_json::from_str::<RecordingEntry>(&r.body).ok())
+        {
+            out.push(hit);
+            continue;
+        }
+        let size_bytes = entry.metadata().await.map(|m| m.len()).unwrap_or_default();
+        let Some(built) = build_entry(path, size_bytes).await else {
+            continue;
+        };
+        if storage.is_some() {
+            changed.push(IndexedRecording {
+                path: key,
+                dir: dir.clone(),
+                fingerprint,
+                body: serde_json::to_string(&built)?,
+            });
+        }
+        out.push(built);
+    }
+
+    if let Some(storage) = storage {
+        storage.index_recordings(&changed)?;
+        storage.unindex_recordings(&indexed.into_keys().collect::<Vec<_>>())?;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, created_unix: i64, purpose: Option<&str>) -> RecordingEntry {
+        RecordingEntry {
+            path: PathBuf::from(name),
+            created_unix,
+            duration_secs: 30,
+            modality: RecordingModality::Audio,
+            purpose: purpose.map(str::to_string),
+            size_bytes: 0,
+            emotion: None,
+            thumbnail: None,
+        }
+    }
+
+    #[test]
+    fn paginates_newest_first_after_filtering() {
+        let entries = (0..5)
+            .map(|i| {
+                entry(
+                    &format!("r{i}"),
+                    i,
+                    (i % 2 == 0).then_some("Morning check-in"),
+                )
+            })
+            .collect::<Vec<_>>();
+        let filter = RecordingFilter {
+            purpose: Some("morning".to_string()),
+            ..Default::default()
+        };
+
+        let first = paginate(entries.clone(), &filter, 0, 2);
+        assert_eq!(first.total, 3);
+        let times = first
+            .entries
+            .iter()
+            .map(|e| e.created_unix)
+            .collect::<Vec<_>>();
+        assert_eq!(times, vec![4, 2]);
+
+        let second = paginate(entries, &filter, 1, 2);
+        assert_eq!(second.entries.len(), 1);
+        assert_eq!(second.entries[0].created_unix, 0);
+    }
+
+    #[tokio::test]
+    async fn exported_media_is_written_back_as_a_recording() {
+        let dir = std::env::temp_dir().join(format!("phoenix-media-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("REC-1.phoenixrec");
+        let original = entry("REC-1.phoenixrec", 1_700_000_000, Some("standup"));
+        write_media(&path, &original, b"captured").unwrap();
+
+        assert_eq!(read_media(&path).unwrap(), b"captured");
+        let listed = scan(&dir).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].created_unix, 1_700_000_000);
+        assert_eq!(listed[0].modality, RecordingModality::Audio);
+        assert_eq!(listed[0].purpose.as_deref(), Some("standup"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,147 @@
+//! Encryption at rest for the files this crate keeps next to the recordings: the embedding
+//! index and retained enrollment samples, `thresholds.json`, emotion-track sidecars and
+//! presence snapshots. Recording bundles have their own format and are not handled here.
+//!
+//! The server hands over its data key once with [`use_sealer`]; until then (and in builds
+//! that never call it) files are written in the clear. Reads go through [`pagi_crypto::Sealer`],
+//! so files written before encryption was turned on stay readable, and [`seal_existing`]
+//! rewrites them sealed.
+
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use pagi_crypto::Sealer;
+
+static SEALER: OnceLock<Sealer> = OnceLock::new();
+
+/// Seal the files described above with `sealer` for the rest of the process. Only the first
+/// call has an effect.
+pub fn use_sealer(sealer: Sealer) {
+    if SEALER.set(sealer).is_err() {
+        tracing::warn!("multi_modal_recording sealer was already set; keeping the first one");
+    }
+}
+
+fn sealer() -> Sealer {
+    SEALER.get().cloned().unwrap_or_default()
+}
+
+/// `plain` as it should be stored.
+pub fn seal(plain: &[u8]) -> io::Result<Vec<u8>> {
+    sealer().seal(plain).map_err(io::Error::other)
+}
+
+/// The plain content of a stored file; files that were never sealed come back unchanged.
+pub fn open(stored: &[u8]) -> io::Result<Vec<u8>> {
+    sealer()
+        .open(stored)
+        .map(|plain| plain.into_owned())
+        .map_err(io::Error::other)
+}
+
+/// Read `path` and [`open`] it.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    open(&std::fs::read(path)?)
+}
+
+/// [`seal`] `plain` and write it to `path`.
+pub fn write(path: &Path, plain: &[u8]) -> io::Result<()> {
+    std::fs::write(path, seal(plain)?)
+}
+
+/// Rewrite every file under `dirs` (recursively) that `sealer` would seal but is still in the
+/// clear, through a temporary file so a crash never leaves one half-written. Missing
+/// directories are skipped. Returns how many files were sealed.
+pub(crate) fn seal_files(
+    sealer: &Sealer,
+    dirs: &[&Path],
+    keep: impl Fn(&Path) -> bool,
+) -> io::Result<usize> {
+    if !sealer.is_enabled() {
+        return Ok(0);
+    }
+    let mut sealed = 0;
+    let mut pending: Vec<_> = dirs.iter().map(|d| d.to_path_buf()).collect();
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !keep(&path) {
+                continue;
+            }
+            let raw = std::fs::read(&path)?;
+            if !sealer.needs_sealing(&raw) {
+                continue;
+            }
+            let mut tmp = path.as_os_str().to_owned();
+            tmp.push(".sealing");
+            std::fs::write(&tmp, sealer.seal(&raw).map_err(io::Error::other)?)?;
+            std::fs::rename(&tmp, &path)?;
+            sealed += 1;
+        }
+    }
+    Ok(sealed)
+}
+
+/// Seal what is still in the clear among the files described above: everything under
+/// `models_dir`'s `embeddings/` and its `thresholds.json`, and the emotion-track sidecars and
+/// presence snapshots in `storage_dir`.
+pub fn seal_existing(models_dir: &Path, storage_dir: &Path) -> io::Result<usize> {
+    let sealer = sealer();
+    let thresholds = models_dir.join("thresholds.json");
+    let models = seal_files(&sealer, &[models_dir], |p| {
+        p.starts_with(models_dir.join("embeddings")) || p == thresholds
+    })?;
+    let sidecars = seal_files(&sealer, &[storage_dir], |p| {
+        p.starts_with(storage_dir.join("snapshots"))
+            || p.to_string_lossy()
+                .ends_with(crate::emotion_track::EMOTION_TRACK_SUFFIX)
+    })?;
+    Ok(models + sidecars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seals_plain_files_once_and_leaves_others_alone() {
+        let dir = std::env::temp_dir().join(format!("mmr-sealed-{}", uuid::Uuid::new_v4()));
+        let models = dir.join("models");
+        std::fs::create_dir_all(models.join("embeddings/samples/face/Dad")).unwrap();
+        std::fs::write(models.join("embeddings/face.json"), b"[]").unwrap();
+        std::fs::write(models.join("embeddings/samples/face/Dad/000-a.png"), b"png").unwrap();
+        std::fs::write(models.join("registry.json"), b"{}").unwrap();
+
+        let only_embeddings = |p: &Path| p.starts_with(models.join("embeddings"));
+        assert_eq!(
+            seal_files(&Sealer::default(), &[&models], only_embeddings).unwrap(),
+            0
+        );
+        let sealer = Sealer::new(pagi_crypto::DataKey::generate().unwrap());
+        assert_eq!(
+            seal_files(&sealer, &[&models], only_embeddings).unwrap(),
+            2
+        );
+        let index = std::fs::read(models.join("embeddings/face.json")).unwrap();
+        assert!(pagi_crypto::is_sealed(&index));
+        assert_eq!(sealer.open(&index).unwrap().as_ref(), b"[]");
+        assert_eq!(std::fs::read(models.join("registry.json")).unwrap(), b"{}");
+        // Already sealed files are not sealed again.
+        assert_eq!(
+            seal_files(&sealer, &[&models, &dir.join("missing")], only_embeddings).unwrap(),
+            0
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,6 +1,8 @@
 // neural_cortex_strata/src/lib.rs
+use pagi_crypto::Sealer;
 use serde::{Deserialize, Serialize};
 use sled::Db;
+use std::path::Path;
 use std::sync::Arc;
 
 pub mod trust_calculator;
@@ -95,28 +97,87 @@ pub enum MemoryLayer {
 
 pub struct NeuralCortexStrata {
     db: Arc<Db>,
+    /// Encryption at rest of every memory.
+    sealer: Sealer,
+}
+
+fn sealing_error(e: pagi_crypto::CryptoError) -> sled::Error {
+    sled::Error::Io(std::io::Error::other(e))
 }
 
 impl NeuralCortexStrata {
     pub fn awaken() -> Self {
-        let db = sled::open("./eternal_memory.db").unwrap();
-        println!("Neural Cortex Strata online — 7 eternal layers active.");
-        Self { db: Arc::new(db) }
+        Self::awaken_in(Path::new("."))
+    }
+
+    /// Open the store under `dir` instead of the working directory.
+    pub fn awaken_in(dir: &Path) -> Self {
+        Self::awaken_sealed(dir, Sealer::default())
+    }
+
+    /// Open the store under `dir`, encrypting memories with `sealer`. Memories written before
+    /// encryption was turned on are encrypted on the way in.
+    pub fn awaken_sealed(dir: &Path, sealer: Sealer) -> Self {
+        let db = sled::open(dir.join("eternal_memory.db")).unwrap();
+        tracing::info!("Neural Cortex Strata online — 7 eternal layers active.");
+        let strata = Self {
+            db: Arc::new(db),
+            sealer,
+        };
+        if strata.sealer.is_enabled() {
+            match strata.seal_plain_values() {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("Neural Cortex Strata: encrypted {n} memories"),
+                Err(e) => tracing::warn!("Neural Cortex Strata not encrypted: {e}"),
+            }
+        }
+        strata
+    }
+
+    /// Rewrite sealed the memories that are still in the clear.
+    fn seal_plain_values(&self) -> Result<usize, sled::Error> {
+        let mut batch = sled::Batch::default();
+        let mut count = 0;
+        for item in self.db.iter() {
+            let (key, value) = item?;
+            if self.sealer.needs_sealing(&value) {
+                batch.insert(key, self.sealer.seal(&value).map_err(sealing_error)?);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.db.apply_batch(batch)?;
+            self.db.flush()?;
+        }
+        Ok(count)
+    }
+
+    fn decode(&self, stored: &[u8]) -> Option<MemoryLayer> {
+        let plain = self
+            .sealer
+            .open(stored)
+            .map_err(|e| tracing::warn!("Unreadable memory: {e}"))
+            .ok()?;
+        serde_json::from_slice(&plain).ok()
     }
 
     pub fn etch(&self, layer: MemoryLayer, key: &str) -> Result<(), sled::Error> {
         let serialized =
             serde_json::to_vec(&layer).map_err(|e| sled::Error::Io(std::io::Error::other(e)))?;
+        let serialized = self.sealer.seal(&serialized).map_err(sealing_error)?;
         self.db.insert(key.as_bytes(), serialized)?;
         self.db.flush()?;
         Ok(())
     }
 
+    /// Flush pending writes to disk (e.g. before shutdown).
+    pub fn flush(&self) -> Result<(), sled::Error> {
+        self.db.flush()?;
+        Ok(())
+    }
+
     pub fn recall(&self, key: &str) -> Option<MemoryLayer> {
-        self.db
-            .get(key.as_bytes())
-            .ok()?
-            .map(|ivec| serde_json::from_slice(&ivec).unwrap())
+        self.decode(&self.db.get(key.as_bytes()).ok()??)
     }
 
     /// Best-effort prefix scan for memory keys.
@@ -127,7 +188,7 @@ impl NeuralCortexStrata {
         let mut out = Vec::new();
         for (k, v) in self.db.scan_prefix(prefix.as_bytes()).flatten() {
             let key = String::from_utf8_lossy(&k).to_string();
-            if let Ok(layer) = serde_json::from_slice::<MemoryLayer>(&v) {
+            if let Some(layer) = self.decode(&v) {
                 out.push((key, layer));
                 if out.len() >= limit {
                     break;
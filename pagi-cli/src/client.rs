@@ -0,0 +1,203 @@
+//! A thin client for the web server's `/api/v1` routes.
+//!
+//! Every request of one `pagi` run carries the same `X-Request-Id`, so the server's log lines
+//! for the run can be found with one grep; it is printed with server errors. Requests ask for
+//! the client's language. Error bodies are the server's problem details, turned back into a
+//! [`PagiError`].
+
+use std::path::Path;
+use std::time::Duration;
+
+use pagi_errors::{ErrorCode, PagiError};
+use pagi_utils::correlation;
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+
+use crate::CliError;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Full exports and imports with media can take a while to send and process.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+pub struct Client {
+    base_url: String,
+    api_key: Option<String>,
+    correlation_id: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// `base_url` is the server root, e.g. `http://127.0.0.1:8888`.
+    pub fn new(base_url: &str, api_key: Option<String>) -> Result<Self, CliError> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            correlation_id: correlation::new_id(),
+            http,
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1{path}", self.base_url)
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, CliError> {
+        let mut request = request
+            .header(correlation::HEADER, &self.correlation_id)
+            .header(reqwest::header::ACCEPT_LANGUAGE, pagi_i18n::language());
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request.send().await.map_err(|source| {
+            if source.is_connect() {
+                CliError::Unreachable {
+                    url: self.base_url.clone(),
+                    source,
+                }
+            } else {
+                CliError::Http(source)
+            }
+        })?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status().as_u16();
+        let body = response.bytes().await.unwrap_or_default();
+        Err(CliError::Server {
+            error: problem_error(status, &body),
+            request_id: self.correlation_id.clone(),
+        })
+    }
+
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, CliError> {
+        let response = self
+            .send(self.http.get(self.url(path)).query(query))
+            .await?;
+        Ok(response.json().await?)
+    }
+
+    /// The body as text, for exports that aren't JSON.
+    pub async fn get_text(&self, path: &str, query: &[(&str, String)]) -> Result<String, CliError> {
+        let response = self
+            .send(self.http.get(self.url(path)).query(query))
+            .await?;
+        Ok(response.text().await?)
+    }
+
+    /// Stream the body into `dest`, for archives too big to hold in memory. Returns its size.
+    pub async fn download(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        dest: &Path,
+    ) -> Result<u64, CliError> {
+        let request = self
+            .http
+            .get(self.url(path))
+            .query(query)
+            .timeout(DOWNLOAD_TIMEOUT);
+        let mut response = self.send(request).await?;
+        let mut file = tokio::fs::File::create(dest).await?;
+        let mut bytes = 0;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk).await?;
+            bytes += chunk.len() as u64;
+        }
+        file.flush().await?;
+        Ok(bytes)
+    }
+
+    pub async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, CliError> {
+        let response = self.send(self.http.post(self.url(path)).json(body)).await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<(), CliError> {
+        self.send(self.http.delete(self.url(path))).await?;
+        Ok(())
+    }
+
+    /// Send `body` as it is, for archives; the reply is JSON.
+    pub async fn post_bytes<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<T, CliError> {
+        let request = self
+            .http
+            .post(self.url(path))
+            .query(query)
+            .header(reqwest::header::CONTENT_TYPE, "application/gzip")
+            .body(body)
+            .timeout(DOWNLOAD_TIMEOUT);
+        Ok(self.send(request).await?.json().await?)
+    }
+}
+
+/// The error a problem body describes; bodies that aren't one get the status's code.
+fn problem_error(status: u16, body: &[u8]) -> PagiError {
+    let fallback = ErrorCode::for_http_status(status);
+    let Ok(problem) = serde_json::from_slice::<Value>(body) else {
+        return PagiError::new(fallback, fallback.default_message());
+    };
+    let code = problem
+        .get("code")
+        .and_then(|c| serde_json::from_value::<ErrorCode>(c.clone()).ok())
+        .unwrap_or(fallback);
+    let mut message = ["detail", "message", "error"]
+        .iter()
+        .find_map(|k| problem.get(*k).and_then(Value::as_str))
+        .unwrap_or(code.default_message())
+        .to_string();
+    // Validation failures list each rejected field.
+    for field in problem
+        .get("errors")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let (Some(name), Some(reason)) = (
+            field.get("field").and_then(Value::as_str),
+            field.get("message").and_then(Value::as_str),
+        ) {
+            message.push_str(&format!("\n  {name}: {reason}"));
+        }
+    }
+    PagiError::new(code, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problem_bodies_keep_code_detail_and_field_errors() {
+        let body = br#"{"type":"error","status":400,"detail":"Some fields were not accepted.",
+            "code":"validation_failed","errors":[{"field":"script","message":"must not be empty"}]}"#;
+        let e = problem_error(400, body);
+        assert_eq!(e.code, ErrorCode::ValidationFailed);
+        assert_eq!(
+            e.message,
+            "Some fields were not accepted.\n  script: must not be empty"
+        );
+
+        let e = problem_error(503, b"upstream gone");
+        assert_eq!(e.code, ErrorCode::Unavailable);
+        assert_eq!(e.message, ErrorCode::Unavailable.default_message());
+    }
+}
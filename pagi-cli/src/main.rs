@@ -0,0 +1,1131 @@
+//! `pagi` — the recorder, the Relational Ghost, exports and imports from a terminal.
+//!
+//! Commands talk to a running web server (`pagi-twin web`) over `/api/v1`, found through
+//! `--server` / `PAGI_SERVER` or the same bind settings the server reads. With `--standalone`,
+//! `record` and `recordings` drive the recorder in-process instead, using the `[recorder]`
+//! settings, so they work on a machine where nothing else is running.
+//!
+//! Output is a short human summary; `--json` prints the server's responses as they are, for
+//! scripts. Exit status is 0 on success, 2 for usage errors and 1 for everything else.
+//!
+//! `secrets` works on the OS keyring of this machine, without a server: API keys saved there
+//! are named in settings as `keyring:<name>` (including `--api-key` / `PAGI_API_KEY`).
+//!
+//! Summaries are in `[i18n] language` / `PAGI_LANGUAGE`, or the system's language, which is
+//! also asked of the server for Ghost replies and error titles.
+
+mod client;
+
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDate, TimeZone, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use multi_modal_recording::recording_library::{
+    RecordingEntry, RecordingFilter, RecordingModality, RecordingPage, MAX_PAGE_SIZE,
+};
+use multi_modal_recording::{MultiModalRecorder, RecorderConfig};
+use pagi_config::secrets::{self, SecretError};
+use pagi_config::{Layers, Overrides};
+use pagi_errors::PagiError;
+use pagi_i18n::{t, t_args, I18nConfig};
+use serde_json::{json, Value};
+use tokio::io::AsyncReadExt;
+
+use crate::client::Client;
+
+/// How often `record` looks for the finished recording.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long past the requested duration `record` waits for the recording to appear.
+const FINISH_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error("no server at {url} ({source}); start one with `pagi-twin web` or pass --standalone")]
+    Unreachable { url: String, source: reqwest::Error },
+    #[error("{error} [{code}, request {request_id}]", code = error.code)]
+    Server {
+        error: PagiError,
+        request_id: String,
+    },
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Recorder(#[from] multi_modal_recording::Error),
+    #[error("settings: {0}")]
+    Config(#[from] pagi_config::ConfigError),
+    #[error(transparent)]
+    Secret(#[from] SecretError),
+    #[error("{0}")]
+    Usage(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Self::Usage(_) | Self::Secret(SecretError::InvalidName(_)) => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(name = "pagi")]
+#[command(about = "Drive the PAGI Twin recorder and Ghost from the command line", long_about = None)]
+struct Cli {
+    /// Web server to talk to (default: from PHOENIX_WEB_BIND / PHOENIX_WEB_HOST /
+    /// PHOENIX_WEB_PORT, or http://127.0.0.1:8888)
+    #[arg(long, global = true, env = "PAGI_SERVER")]
+    server: Option<String>,
+    /// API key, when the server asks for one
+    #[arg(long, global = true, env = "PAGI_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    /// Use the recorder in this process instead of the server (record and recordings only)
+    #[arg(long, global = true)]
+    standalone: bool,
+    /// Print JSON instead of a summary
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Record for a number of seconds and print where the recording went
+    Record {
+        #[arg(value_enum)]
+        mode: Mode,
+        /// Length of the recording in seconds
+        seconds: u64,
+        /// Why the recording was made; stored with it and searchable
+        #[arg(long)]
+        purpose: Option<String>,
+    },
+    /// Run text past the Relational Ghost
+    Ghost {
+        #[command(subcommand)]
+        action: GhostAction,
+    },
+    /// Browse the recording library
+    Recordings {
+        #[command(subcommand)]
+        action: RecordingsAction,
+    },
+    /// Export everything the server keeps, or picked parts of it, to a directory
+    Export(ExportArgs),
+    /// Import a full export into the server, merging it with what the server holds
+    Import(ImportArgs),
+    /// Show how syncing with your other devices went, or start a round
+    Sync {
+        /// Start a round with every peer now
+        #[arg(long)]
+        now: bool,
+    },
+    /// Pair the mobile companion app, or list and unpair paired phones
+    Companion {
+        #[command(subcommand)]
+        action: CompanionAction,
+    },
+    /// Keep API keys and other secrets in the OS keyring
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    Audio,
+    Video,
+    Av,
+}
+
+impl Mode {
+    fn tracks(self) -> (bool, bool) {
+        match self {
+            Self::Audio => (true, false),
+            Self::Video => (false, true),
+            Self::Av => (true, true),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum GhostAction {
+    /// Simulate a partner's reply to a script
+    Simulate {
+        /// Attachment style to reply as, e.g. secure, avoidant, anxious, fearful; repeat for an
+        /// Echo Chamber of several
+        #[arg(long = "persona", default_value = "secure")]
+        personas: Vec<String>,
+        /// Read the script from this file
+        #[arg(long, conflicts_with = "text")]
+        file: Option<PathBuf>,
+        /// The script itself (default: read from stdin)
+        #[arg(long)]
+        text: Option<String>,
+        /// How hard the persona pushes back, 0-100
+        #[arg(long, default_value_t = 50)]
+        intensity: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecordingsAction {
+    /// List recordings, newest first
+    Ls {
+        /// Only recordings since this long ago (`7d`, `12h`, `30m`, `90s`) or this local date
+        /// (`2026-01-31`)
+        #[arg(long, value_parser = parse_since)]
+        since: Option<i64>,
+        /// Only recordings whose purpose contains this (case-insensitive)
+        #[arg(long)]
+        purpose: Option<String>,
+        #[arg(long, value_enum)]
+        modality: Option<Mode>,
+        /// At most this many
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsAction {
+    /// Save a secret, read from stdin so it stays out of the shell history
+    Set {
+        /// Letters, digits, `_` and `.`; settings refer to it as `keyring:<name>`
+        name: String,
+    },
+    /// Print a secret
+    Get { name: String },
+    /// Remove a secret
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+enum CompanionAction {
+    /// Show a QR code for the companion app to scan; it is valid for five minutes
+    Pair,
+    /// List the paired phones
+    Ls,
+    /// Unpair a phone; it has to pair again to reach the server
+    Unpair { device: String },
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Everything the server keeps, as a documented archive unpacked into the directory (the
+    /// default when nothing is picked)
+    #[arg(long)]
+    all: bool,
+    /// With --all, the recordings themselves too, decrypted
+    #[arg(long)]
+    media: bool,
+    /// The counselor report, as Markdown
+    #[arg(long)]
+    counselor: bool,
+    /// The emotion history
+    #[arg(long)]
+    emotions: bool,
+    /// The recording index (metadata only; the recordings stay encrypted where they are)
+    #[arg(long)]
+    recordings: bool,
+    /// Directory to write into (default: ./pagi-export-<date>-<time>)
+    #[arg(long)]
+    out: Option<PathBuf>,
+    /// Days of history for the counselor report (1-90)
+    #[arg(long, default_value_t = 7)]
+    days: u32,
+    /// Format of the emotion history
+    #[arg(long, value_enum, default_value = "json")]
+    format: EmotionFormat,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// The export: an archive, or a directory `pagi export` wrote
+    path: PathBuf,
+    /// Only report what would change
+    #[arg(long)]
+    dry_run: bool,
+    /// What to do with records the server holds in another version
+    #[arg(long, value_enum, default_value = "keep")]
+    on_conflict: OnConflict,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OnConflict {
+    /// Leave the server's version
+    Keep,
+    /// Take the exported version
+    Replace,
+}
+
+impl OnConflict {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Replace => "replace",
+        }
+    }
+}
+
+/// One section of the server's import report.
+#[derive(serde::Deserialize)]
+struct ImportSection {
+    added: usize,
+    unchanged: usize,
+    conflicts: usize,
+    replaced: usize,
+    skipped: usize,
+    #[serde(default)]
+    conflicting: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ImportReport {
+    dry_run: bool,
+    sections: std::collections::BTreeMap<String, ImportSection>,
+    notes: Vec<String>,
+}
+
+/// The server's `GET /sync`.
+#[derive(serde::Deserialize)]
+struct SyncStatus {
+    device: String,
+    records: usize,
+    peers: Vec<SyncPeer>,
+}
+
+#[derive(serde::Deserialize)]
+struct SyncPeer {
+    url: String,
+    last_sync: Option<SyncResult>,
+}
+
+#[derive(serde::Deserialize)]
+struct SyncResult {
+    last_sync_unix: i64,
+    ok: bool,
+    message: String,
+}
+
+/// The server's `POST /companion/pairings`.
+#[derive(serde::Deserialize)]
+struct PairingOffer {
+    uri: String,
+    expires_unix: i64,
+}
+
+/// One phone from the server's `GET /companion/devices`.
+#[derive(serde::Deserialize)]
+struct CompanionDevice {
+    id: String,
+    name: String,
+    last_seen_unix: Option<i64>,
+    pending_alerts: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EmotionFormat {
+    Json,
+    Csv,
+}
+
+impl EmotionFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// A `--since` value as a Unix timestamp.
+fn parse_since(s: &str) -> Result<i64, String> {
+    parse_since_at(s, Utc::now().timestamp())
+}
+
+fn parse_since_at(s: &str, now_unix: i64) -> Result<i64, String> {
+    let s = s.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight exists");
+        return Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|t| t.timestamp())
+            .ok_or_else(|| format!("{s} has no local midnight"));
+    }
+    let unit_secs = match s.chars().last() {
+        Some('d') => 86_400,
+        Some('h') => 3_600,
+        Some('m') => 60,
+        Some('s') => 1,
+        _ => return Err("expected e.g. 7d, 12h, 30m, 90s or 2026-01-31".to_string()),
+    };
+    let count: i64 = s[..s.len() - 1]
+        .parse()
+        .map_err(|_| format!("{s:?} is not a number followed by d, h, m or s"))?;
+    count
+        .checked_mul(unit_secs)
+        .and_then(|ago| now_unix.checked_sub(ago))
+        .ok_or_else(|| format!("{s} is too far back"))
+}
+
+fn default_server() -> String {
+    let (host, port) = common_types::ports::PhoenixWebPort::host_port();
+    // A server bound to every interface is reachable on loopback.
+    let host = match host.as_str() {
+        "0.0.0.0" | "::" | "[::]" => "127.0.0.1".to_string(),
+        _ => host,
+    };
+    format!("http://{host}:{port}")
+}
+
+fn modality(mode: Mode) -> RecordingModality {
+    let (audio, video) = mode.tracks();
+    RecordingModality::from_flags(audio, video)
+}
+
+fn modality_label(m: RecordingModality) -> &'static str {
+    match m {
+        RecordingModality::Audio => "audio",
+        RecordingModality::Video => "video",
+        RecordingModality::AudioVideo => "av",
+        RecordingModality::NoCapture => "-",
+    }
+}
+
+/// Write to stdout, stopping quietly when the reader has gone away (`pagi ... | head`).
+fn print_lines(text: &str) {
+    let mut out = std::io::stdout().lock();
+    let _ = out.write_all(text.as_bytes()).and_then(|()| out.flush());
+}
+
+fn print_json(value: &impl serde::Serialize) {
+    let json = serde_json::to_string_pretty(value).unwrap_or_else(|_| "null".to_string());
+    print_lines(&format!("{json}\n"));
+}
+
+fn print_recordings(entries: &[RecordingEntry]) {
+    if entries.is_empty() {
+        println!("{}", t("cli-no-recordings"));
+        return;
+    }
+    let mut table = format!(
+        "{:<16}  {:<5}  {:>7}  {:<24}  {}\n",
+        t("cli-recordings-header-created"),
+        t("cli-recordings-header-mode"),
+        t("cli-recordings-header-seconds"),
+        t("cli-recordings-header-purpose"),
+        t("cli-recordings-header-path"),
+    );
+    for e in entries {
+        let created = Local
+            .timestamp_opt(e.created_unix, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| e.created_unix.to_string());
+        let purpose: String = e
+            .purpose
+            .as_deref()
+            .unwrap_or("-")
+            .chars()
+            .take(24)
+            .collect();
+        table.push_str(&format!(
+            "{created:<16}  {:<5}  {:>7}  {purpose:<24}  {}\n",
+            modality_label(e.modality),
+            e.duration_secs,
+            e.path.display()
+        ));
+    }
+    print_lines(&table);
+}
+
+/// The recorder used with `--standalone`.
+fn local_recorder() -> Result<MultiModalRecorder, CliError> {
+    Ok(MultiModalRecorder::from_config(RecorderConfig::load()?))
+}
+
+async fn record(
+    cli: &Cli,
+    mode: Mode,
+    seconds: u64,
+    purpose: Option<String>,
+) -> Result<(), CliError> {
+    let (audio, video) = mode.tracks();
+    if cli.standalone {
+        let recorder = local_recorder()?.clone_with_modes(audio, video);
+        eprintln!(
+            "{}",
+            t_args("cli-recording", &[("seconds", seconds.into())])
+        );
+        let path = recorder
+            .start_on_demand_with_purpose(seconds, purpose)
+            .await?;
+        if cli.json {
+            print_json(&json!({ "path": path }));
+        } else {
+            println!("{}", path.display());
+        }
+        return Ok(());
+    }
+
+    let client = server(cli)?;
+    let started_unix = Utc::now().timestamp();
+    let _: Value = client
+        .post(
+            "/recorder/record",
+            &json!({ "duration_secs": seconds, "audio": audio, "video": video, "purpose": purpose }),
+        )
+        .await?;
+    eprintln!(
+        "{}",
+        t_args("cli-recording", &[("seconds", seconds.into())])
+    );
+
+    // The server records in the background; the recording shows up in the library once written.
+    let deadline = Instant::now() + Duration::from_secs(seconds) + FINISH_GRACE;
+    let query = [
+        ("from_unix", started_unix.saturating_sub(1).to_string()),
+        ("modality", modality_label(modality(mode)).to_string()),
+        ("page_size", "1".to_string()),
+    ];
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let page: RecordingPage = client.get("/recorder/recordings", &query).await?;
+        if let Some(entry) = page.entries.into_iter().next() {
+            if cli.json {
+                print_json(&entry);
+            } else {
+                println!("{}", entry.path.display());
+            }
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(CliError::Usage(
+                "the recording didn't appear in time; see `pagi recordings ls` or the server log"
+                    .to_string(),
+            ));
+        }
+    }
+}
+
+async fn list_recordings(
+    cli: &Cli,
+    since: Option<i64>,
+    purpose: Option<String>,
+    mode: Option<Mode>,
+    limit: usize,
+) -> Result<(), CliError> {
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(CliError::Usage(format!(
+            "--limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    let page = if cli.standalone {
+        let filter = RecordingFilter {
+            modality: mode.map(modality),
+            from_unix: since,
+            purpose,
+            ..RecordingFilter::default()
+        };
+        local_recorder()?.list_recordings(&filter, 0, limit).await?
+    } else {
+        let mut query = vec![("page_size", limit.to_string())];
+        if let Some(since) = since {
+            query.push(("from_unix", since.to_string()));
+        }
+        if let Some(purpose) = purpose {
+            query.push(("purpose", purpose));
+        }
+        if let Some(mode) = mode {
+            query.push(("modality", modality_label(modality(mode)).to_string()));
+        }
+        server(cli)?.get("/recorder/recordings", &query).await?
+    };
+    if cli.json {
+        print_json(&page);
+    } else {
+        print_recordings(&page.entries);
+        if page.total > page.entries.len() {
+            let more = t_args(
+                "cli-recordings-more",
+                &[
+                    ("shown", page.entries.len().into()),
+                    ("total", page.total.into()),
+                ],
+            );
+            println!("{more}");
+        }
+    }
+    Ok(())
+}
+
+async fn simulate(
+    cli: &Cli,
+    personas: Vec<String>,
+    file: Option<PathBuf>,
+    text: Option<String>,
+    intensity: i64,
+) -> Result<(), CliError> {
+    let client = server(cli)?;
+    let script = match (file, text) {
+        (Some(path), _) => tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| CliError::Usage(format!("can't read {}: {e}", path.display())))?,
+        (None, Some(text)) => text,
+        (None, None) => {
+            let mut script = String::new();
+            tokio::io::stdin().read_to_string(&mut script).await?;
+            script
+        }
+    };
+    let body = match personas.as_slice() {
+        [one] => json!({ "script": script, "persona_type": one, "intensity_level": intensity }),
+        many => json!({ "script": script, "personas": many, "intensity_level": intensity }),
+    };
+    let resp: Value = client.post("/ghost/simulate", &body).await?;
+    if cli.json {
+        print_json(&resp);
+        return Ok(());
+    }
+
+    let text = |key: &str| resp.get(key).and_then(Value::as_str).unwrap_or_default();
+    let number = |key: &str| resp.get(key).and_then(Value::as_u64).unwrap_or_default();
+    let list = |key: &str| -> Vec<String> {
+        resp.get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect()
+    };
+    let turns = resp
+        .get("group_replies")
+        .and_then(Value::as_array)
+        .filter(|turns| !turns.is_empty());
+    if let Some(turns) = turns {
+        for turn in turns {
+            let speaker = turn.get("speaker").and_then(Value::as_str).unwrap_or("?");
+            let said = turn.get("text").and_then(Value::as_str).unwrap_or_default();
+            println!("{speaker}: {said}\n");
+        }
+    } else {
+        println!("{}: {}\n", text("persona"), text("ghost_reply"));
+    }
+    let scores = t_args(
+        "cli-ghost-scores",
+        &[
+            ("resonance", number("resonance_score").into()),
+            ("risk", number("risk_score").into()),
+        ],
+    );
+    println!("{scores}");
+    if resp.get("paused").and_then(Value::as_bool) == Some(true) {
+        println!("{}", t("cli-ghost-paused"));
+    }
+    for flag in list("flags") {
+        println!("{}", t_args("cli-ghost-flag", &[("flag", flag.into())]));
+    }
+    for suggestion in list("suggestions") {
+        println!(
+            "{}",
+            t_args("cli-ghost-suggestion", &[("suggestion", suggestion.into())])
+        );
+    }
+    Ok(())
+}
+
+async fn export(cli: &Cli, args: &ExportArgs) -> Result<(), CliError> {
+    if !(1..=90).contains(&args.days) {
+        return Err(CliError::Usage(
+            "--days must be between 1 and 90".to_string(),
+        ));
+    }
+    let client = server(cli)?;
+    let all = args.all || !(args.counselor || args.emotions || args.recordings);
+    if args.media && !all {
+        return Err(CliError::Usage("--media goes with --all".to_string()));
+    }
+    let out = args.out.clone().unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "pagi-export-{}",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+    tokio::fs::create_dir_all(&out).await?;
+
+    let mut written = Vec::new();
+    if all {
+        written.extend(full_export(&client, &out, args.media).await?);
+    }
+    if args.counselor {
+        let report = client
+            .get_text("/counselor/export", &[("days", args.days.to_string())])
+            .await?;
+        let path = out.join("counselor.md");
+        tokio::fs::write(&path, report).await?;
+        written.push(path);
+    }
+    if args.emotions {
+        let doc = client
+            .get_text(
+                "/emotion/export",
+                &[("format", args.format.as_str().to_string())],
+            )
+            .await?;
+        let path = out.join(format!("emotions.{}", args.format.as_str()));
+        tokio::fs::write(&path, doc).await?;
+        written.push(path);
+    }
+    if args.recordings {
+        let mut entries = Vec::new();
+        for page in 0.. {
+            let query = [
+                ("page", page.to_string()),
+                ("page_size", MAX_PAGE_SIZE.to_string()),
+            ];
+            let batch: RecordingPage = client.get("/recorder/recordings", &query).await?;
+            let done =
+                batch.entries.is_empty() || entries.len() + batch.entries.len() >= batch.total;
+            entries.extend(batch.entries);
+            if done {
+                break;
+            }
+        }
+        let path = out.join("recordings.json");
+        tokio::fs::write(
+            &path,
+            serde_json::to_vec_pretty(&entries).map_err(std::io::Error::other)?,
+        )
+        .await?;
+        written.push(path);
+    }
+
+    if cli.json {
+        print_json(&json!({ "files": written }));
+    } else {
+        for path in &written {
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Download the full export into `out` and unpack it there; returns its README and manifest.
+async fn full_export(client: &Client, out: &Path, media: bool) -> Result<[PathBuf; 2], CliError> {
+    let archive = out.join(".pagi-export.tar.gz");
+    let unpacked = async {
+        client
+            .download("/admin/export", &[("media", media.to_string())], &archive)
+            .await?;
+        let (archive, out) = (archive.clone(), out.to_path_buf());
+        tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&archive)?;
+            tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(&out)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        Ok::<_, CliError>(())
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&archive).await;
+    unpacked?;
+    Ok([out.join("README.md"), out.join("manifest.json")])
+}
+
+async fn import(cli: &Cli, args: &ImportArgs) -> Result<(), CliError> {
+    let client = server(cli)?;
+    let path = args.path.clone();
+    let archive = tokio::task::spawn_blocking(move || {
+        if !path.is_dir() {
+            return std::fs::read(&path);
+        }
+        let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        tar.append_dir_all(".", &path)?;
+        tar.into_inner()?.finish()
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+    let query = [
+        ("dry_run", args.dry_run.to_string()),
+        ("on_conflict", args.on_conflict.as_str().to_string()),
+    ];
+    let report: Value = client.post_bytes("/admin/import", &query, archive).await?;
+    if cli.json {
+        print_json(&report);
+        return Ok(());
+    }
+    let report: ImportReport = serde_json::from_value(report).map_err(std::io::Error::other)?;
+    if report.dry_run {
+        println!("{}", t("cli-import-dry-run"));
+    }
+    for (name, section) in &report.sections {
+        let line = t_args(
+            "cli-import-section",
+            &[
+                ("section", name.as_str().into()),
+                ("added", section.added.into()),
+                ("unchanged", section.unchanged.into()),
+                ("conflicts", section.conflicts.into()),
+                ("replaced", section.replaced.into()),
+                ("skipped", section.skipped.into()),
+            ],
+        );
+        println!("{line}");
+        if !section.conflicting.is_empty() {
+            let keys = section.conflicting.join(", ");
+            println!(
+                "{}",
+                t_args("cli-import-conflicting", &[("keys", keys.into())])
+            );
+        }
+    }
+    for note in &report.notes {
+        println!(
+            "{}",
+            t_args("cli-import-note", &[("note", note.as_str().into())])
+        );
+    }
+    Ok(())
+}
+
+async fn sync(cli: &Cli, now: bool) -> Result<(), CliError> {
+    let client = server(cli)?;
+    if now {
+        let queued: Value = client
+            .post("/scheduler/jobs/sync/run", &serde_json::json!({}))
+            .await?;
+        if cli.json {
+            print_json(&queued);
+        } else {
+            println!("{}", t("cli-sync-queued"));
+        }
+        return Ok(());
+    }
+    let status: Value = client.get("/sync", &[]).await?;
+    if cli.json {
+        print_json(&status);
+        return Ok(());
+    }
+    let status: SyncStatus = serde_json::from_value(status).map_err(std::io::Error::other)?;
+    println!(
+        "{}",
+        t_args(
+            "cli-sync-device",
+            &[
+                ("device", status.device.into()),
+                ("records", status.records.into()),
+            ],
+        )
+    );
+    for peer in status.peers {
+        let url = peer.url.into();
+        let line = match peer.last_sync {
+            None => t_args("cli-sync-peer-never", &[("url", url)]),
+            Some(last) => {
+                let time = local_time(last.last_sync_unix);
+                let key = if last.ok {
+                    "cli-sync-peer-ok"
+                } else {
+                    "cli-sync-peer-failed"
+                };
+                t_args(
+                    key,
+                    &[
+                        ("url", url),
+                        ("time", time.into()),
+                        ("message", last.message.into()),
+                    ],
+                )
+            }
+        };
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn local_time(unix: i64) -> String {
+    Local
+        .timestamp_opt(unix, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| unix.to_string())
+}
+
+async fn companion(cli: &Cli, action: &CompanionAction) -> Result<(), CliError> {
+    let client = server(cli)?;
+    match action {
+        CompanionAction::Pair => {
+            let offer: Value = client.post("/companion/pairings", &json!({})).await?;
+            if cli.json {
+                print_json(&offer);
+                return Ok(());
+            }
+            let offer: PairingOffer =
+                serde_json::from_value(offer).map_err(std::io::Error::other)?;
+            println!("{}", t("cli-companion-scan"));
+            if qr2term::print_qr(&offer.uri).is_err() {
+                println!("{}", offer.uri);
+            }
+            let time = local_time(offer.expires_unix).into();
+            println!("{}", t_args("cli-companion-expires", &[("time", time)]));
+        }
+        CompanionAction::Ls => {
+            let devices: Value = client.get("/companion/devices", &[]).await?;
+            if cli.json {
+                print_json(&devices);
+                return Ok(());
+            }
+            let devices: Vec<CompanionDevice> =
+                serde_json::from_value(devices).map_err(std::io::Error::other)?;
+            if devices.is_empty() {
+                println!("{}", t("cli-companion-none"));
+            }
+            for device in devices {
+                let seen = device
+                    .last_seen_unix
+                    .map_or_else(|| t("cli-companion-never"), local_time);
+                let line = t_args(
+                    "cli-companion-device",
+                    &[
+                        ("id", device.id.into()),
+                        ("name", device.name.into()),
+                        ("seen", seen.into()),
+                        ("alerts", device.pending_alerts.into()),
+                    ],
+                );
+                println!("{line}");
+            }
+        }
+        CompanionAction::Unpair { device } => {
+            client
+                .delete(&format!("/companion/devices/{device}"))
+                .await?;
+            println!(
+                "{}",
+                t_args(
+                    "cli-companion-unpaired",
+                    &[("device", device.as_str().into())]
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+fn manage_secrets(cli: &Cli, action: &SecretsAction) -> Result<(), CliError> {
+    match action {
+        SecretsAction::Set { name } => {
+            secrets::check_name(name)?;
+            let stdin = std::io::stdin();
+            if stdin.is_terminal() {
+                eprint!(
+                    "{}",
+                    t_args("cli-secret-prompt", &[("name", name.as_str().into())])
+                );
+            }
+            let mut value = String::new();
+            stdin.read_line(&mut value)?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            if value.is_empty() {
+                return Err(CliError::Usage("no value on stdin".to_string()));
+            }
+            secrets::set(name, value)?;
+            if cli.json {
+                print_json(&json!({ "name": name, "saved": true }));
+            } else {
+                println!(
+                    "{}",
+                    t_args("cli-secret-saved", &[("name", name.as_str().into())])
+                );
+            }
+        }
+        SecretsAction::Get { name } => {
+            let value = secrets::get(name)?.ok_or_else(|| SecretError::NotFound(name.clone()))?;
+            if cli.json {
+                print_json(&json!({ "name": name, "value": value }));
+            } else {
+                print_lines(&format!("{value}\n"));
+            }
+        }
+        SecretsAction::Delete { name } => {
+            if !secrets::delete(name)? {
+                return Err(SecretError::NotFound(name.clone()).into());
+            }
+            if cli.json {
+                print_json(&json!({ "name": name, "deleted": true }));
+            } else {
+                println!(
+                    "{}",
+                    t_args("cli-secret-deleted", &[("name", name.as_str().into())])
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The server client, refusing `--standalone` for commands that need the server.
+fn server(cli: &Cli) -> Result<Client, CliError> {
+    if cli.standalone && !matches!(cli.command, Commands::Record { .. }) {
+        return Err(CliError::Usage(
+            "this command needs the server; drop --standalone".to_string(),
+        ));
+    }
+    let url = cli.server.clone().unwrap_or_else(default_server);
+    let api_key = cli.api_key.as_deref().map(secrets::resolve).transpose()?;
+    Client::new(&url, api_key)
+}
+
+async fn run(cli: Cli) -> Result<(), CliError> {
+    match &cli.command {
+        Commands::Record {
+            mode,
+            seconds,
+            purpose,
+        } => record(&cli, *mode, *seconds, purpose.clone()).await,
+        Commands::Recordings {
+            action:
+                RecordingsAction::Ls {
+                    since,
+                    purpose,
+                    modality,
+                    limit,
+                },
+        } => list_recordings(&cli, *since, purpose.clone(), *modality, *limit).await,
+        Commands::Ghost {
+            action:
+                GhostAction::Simulate {
+                    personas,
+                    file,
+                    text,
+                    intensity,
+                },
+        } => {
+            simulate(
+                &cli,
+                personas.clone(),
+                file.clone(),
+                text.clone(),
+                *intensity,
+            )
+            .await
+        }
+        Commands::Export(args) => export(&cli, args).await,
+        Commands::Import(args) => import(&cli, args).await,
+        Commands::Sync { now } => sync(&cli, *now).await,
+        Commands::Companion { action } => companion(&cli, action).await,
+        Commands::Secrets { action } => manage_secrets(&cli, action),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // The server's bind settings and the recorder's may live in `.env`.
+    pagi_utils::load_dotenv_best_effort();
+    // A broken settings file is reported by the commands that need it.
+    let language = Layers::load(&Overrides::default())
+        .and_then(|layers| I18nConfig::from_layers(&layers))
+        .map(|config| config.language)
+        .unwrap_or_default();
+    pagi_i18n::set_language(language.as_deref());
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                t_args("cli-error", &[("error", e.to_string().into())])
+            );
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn since_takes_ages_and_dates() {
+        let now = 1_800_000_000;
+        assert_eq!(parse_since_at("7d", now), Ok(now - 7 * 86_400));
+        assert_eq!(parse_since_at("12h", now), Ok(now - 12 * 3_600));
+        assert_eq!(parse_since_at(" 90s ", now), Ok(now - 90));
+        let date = parse_since_at("2026-01-31", now).unwrap();
+        assert_eq!(
+            Local.timestamp_opt(date, 0).unwrap().date_naive(),
+            NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()
+        );
+        assert!(parse_since_at("7w", now).is_err());
+        assert!(parse_since_at("d", now).is_err());
+        assert!(parse_since_at("999999999999999d", now).is_err());
+    }
+
+    #[test]
+    fn arguments_parse_like_the_examples() {
+        let cli = Cli::try_parse_from(["pagi", "record", "audio", "60"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Record {
+                mode: Mode::Audio,
+                seconds: 60,
+                ..
+            }
+        ));
+        let cli = Cli::try_parse_from([
+            "pagi",
+            "ghost",
+            "simulate",
+            "--persona",
+            "avoidant",
+            "--file",
+            "draft.txt",
+        ])
+        .unwrap();
+        let Commands::Ghost {
+            action: GhostAction::Simulate { personas, file, .. },
+        } = cli.command
+        else {
+            panic!("not ghost simulate");
+        };
+        assert_eq!(personas, ["avoidant"]);
+        assert_eq!(file, Some(PathBuf::from("draft.txt")));
+        assert!(Cli::try_parse_from(["pagi", "recordings", "ls", "--since", "7d"]).is_ok());
+        assert!(Cli::try_parse_from(["pagi", "export", "--all", "--media"]).is_ok());
+        assert!(Cli::try_parse_from([
+            "pagi",
+            "import",
+            "export.tar.gz",
+            "--dry-run",
+            "--on-conflict",
+            "replace"
+        ])
+        .is_ok());
+        let cli = Cli::try_parse_from(["pagi", "sync", "--now"]).unwrap();
+        assert!(matches!(cli.command, Commands::Sync { now: true }));
+        let cli = Cli::try_parse_from(["pagi", "companion", "unpair", "ab12"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Companion {
+                action: CompanionAction::Unpair { ref device }
+            } if device == "ab12"
+        ));
+        let cli = Cli::try_parse_from(["pagi", "secrets", "set", "openai_api_key"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Secrets {
+                action: SecretsAction::Set { ref name }
+            } if name == "openai_api_key"
+        ));
+    }
+}
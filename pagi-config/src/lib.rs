@@ -0,0 +1,779 @@
+//! Settings shared by every binary in the workspace (the web server, the desktop app, the
+//! recorder), layered: built-in defaults, then a TOML file, then environment variables, then
+//! command-line flags.
+//!
+//! The file is the one given with `--config`, else `PHOENIX_CONFIG`, else `./phoenix.toml` when
+//! it exists. Every setting has a dotted key, written as `[table]` + `name = value` in the file
+//! and as `--set table.name=value` on the command line; most also keep the environment variable
+//! they have always had. [`KEYS`] is the full schema. Unknown keys are an error, so a typo can't
+//! silently fall back to a default.
+//!
+//! [`Layers`] holds the raw values and types them on request ([`Layers::or`],
+//! [`Layers::flag`]); each consumer builds its own section from them and validates it, like
+//! [`SensorSettings::from_layers`]. [`Layers::to_toml`] writes the effective settings back out
+//! as a config file.
+//!
+//! Secret settings can point into the OS keyring instead of holding the value, as
+//! `keyring:<name>`; read them with [`Layers::secret`]. See [`secrets`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use common_types::ports::{PhoenixGrpcPort, PhoenixWebPort};
+use pagi_utils::logging::{LogConfig, LogFormat};
+
+pub mod secrets;
+mod toml;
+
+pub const CONFIG_ENV: &str = "PHOENIX_CONFIG";
+pub const DEFAULT_CONFIG_FILE: &str = "phoenix.toml";
+
+/// One setting: its key and the environment variable that overrides the file.
+#[derive(Debug, Clone, Copy)]
+pub struct Key {
+    pub name: &'static str,
+    pub env: Option<&'static str>,
+    /// The variable turns the setting *off* (`PHOENIX_IPC_DISABLED=1` -> `ipc_bridge = false`).
+    pub env_negated: bool,
+}
+
+const fn key(name: &'static str, env: &'static str) -> Key {
+    Key {
+        name,
+        env: Some(env),
+        env_negated: false,
+    }
+}
+
+/// A setting with no environment variable.
+const fn file_only(name: &'static str) -> Key {
+    Key {
+        name,
+        env: None,
+        env_negated: false,
+    }
+}
+
+pub const KEYS: &[Key] = &[
+    key("server.bind", PhoenixWebPort::ENV_VAR),
+    key("server.host", PhoenixWebPort::HOST_ENV_VAR),
+    key("server.port", PhoenixWebPort::PORT_ENV_VAR),
+    key("server.port_fallback", PhoenixWebPort::FALLBACK_ENV_VAR),
+    key("server.socket", "PHOENIX_WEB_SOCKET"),
+    key("server.data_dir", "PHOENIX_DATA_DIR"),
+    key("server.ui_dir", "PHOENIX_UI_DIR"),
+    key("server.grpc_bind", PhoenixGrpcPort::ENV_VAR),
+    key("server.cors_origins", "PHOENIX_CORS_ORIGINS"),
+    key(
+        "server.shutdown_timeout_secs",
+        "PHOENIX_SHUTDOWN_TIMEOUT_SECS",
+    ),
+    key("tls.cert", "PHOENIX_TLS_CERT"),
+    key("tls.key", "PHOENIX_TLS_KEY"),
+    key("tls.self_signed", "PHOENIX_TLS_SELF_SIGNED"),
+    key("auth.api_auth", "PHOENIX_API_AUTH"),
+    key("auth.api_keys_path", "PHOENIX_API_KEYS_PATH"),
+    key("auth.ui_passphrase", "PHOENIX_UI_PASSPHRASE"),
+    key(
+        "auth.rate_limit_cheap_per_min",
+        "PHOENIX_RATE_LIMIT_CHEAP_PER_MIN",
+    ),
+    key(
+        "auth.rate_limit_expensive_per_min",
+        "PHOENIX_RATE_LIMIT_EXPENSIVE_PER_MIN",
+    ),
+    key("sensors.stress_sample_secs", "PHOENIX_STRESS_SAMPLE_SECS"),
+    key(
+        "sensors.recording_progress_secs",
+        "PHOENIX_RECORDING_PROGRESS_SECS",
+    ),
+    key(
+        "sensors.stress_alert_percent",
+        "PHOENIX_STRESS_ALERT_PERCENT",
+    ),
+    key("retention.session_ttl_hours", "PHOENIX_SESSION_TTL_HOURS"),
+    key("retention.log_files", "PHOENIX_LOG_KEEP_FILES"),
+    key("retention.sandbox_cleanup_days", "SANDBOX_CLEANUP_DAYS"),
+    key("storage.url", "PHOENIX_DATABASE_URL"),
+    file_only("storage.readers"),
+    key("logging.filter", "RUST_LOG"),
+    key("logging.format", "PHOENIX_LOG_FORMAT"),
+    key("logging.dir", "PHOENIX_LOG_DIR"),
+    Key {
+        name: "features.ipc_bridge",
+        env: Some("PHOENIX_IPC_DISABLED"),
+        env_negated: true,
+    },
+    key("features.grpc", "PHOENIX_GRPC_ENABLED"),
+    file_only("features.mobile_pairing"),
+    key("features.vector_kb", "VECTOR_KB_ENABLED"),
+    key("features.audio_intelligence", "AUDIO_INTELLIGENCE_ENABLED"),
+    key("features.desktop_capture", "DESKTOP_CAPTURE_ENABLED"),
+    key("features.wifi_analyzer", "WIFI_ANALYZER_ENABLED"),
+    key("features.bluetooth_sniffer", "BLUETOOTH_SNIFFER_ENABLED"),
+    key("features.correlation_engine", "CORRELATION_ENGINE_ENABLED"),
+    key("features.privacy_framework", "PRIVACY_FRAMEWORK_ENABLED"),
+    key("features.hardware_detector", "HARDWARE_DETECTOR_ENABLED"),
+    key("features.home_automation", "HOME_AUTOMATION_ENABLED"),
+    key("features.outlook_com", "OUTLOOK_COM_ENABLED"),
+    key("features.malware_sandbox", "MALWARE_SANDBOX_ENABLED"),
+    key(
+        "features.network_security_agent",
+        "NETWORK_SECURITY_AGENT_ENABLED",
+    ),
+    file_only("lexicon.joy"),
+    file_only("lexicon.sadness"),
+    file_only("lexicon.anger"),
+    file_only("lexicon.fear"),
+    file_only("lexicon.surprise"),
+    file_only("lexicon.disgust"),
+    file_only("lexicon.love"),
+    file_only("lexicon.jealousy"),
+    file_only("scheduler.retention_prune"),
+    file_only("scheduler.weekly_report"),
+    file_only("scheduler.model_updates"),
+    key("backup.schedule", "PHOENIX_BACKUP_SCHEDULE"),
+    key("backup.destination", "PHOENIX_BACKUP_DESTINATION"),
+    key("backup.passphrase", "PHOENIX_BACKUP_PASSPHRASE"),
+    file_only("backup.keep"),
+    file_only("backup.keep_days"),
+    file_only("backup.webdav_user"),
+    key("backup.webdav_password", "PHOENIX_BACKUP_WEBDAV_PASSWORD"),
+    key("backup.s3_region", "AWS_REGION"),
+    file_only("backup.s3_endpoint"),
+    key("backup.s3_access_key", "AWS_ACCESS_KEY_ID"),
+    key("backup.s3_secret_key", "AWS_SECRET_ACCESS_KEY"),
+    key("sync.peers", "PHOENIX_SYNC_PEERS"),
+    key("sync.key", "PHOENIX_SYNC_KEY"),
+    file_only("sync.schedule"),
+    key("mqtt.broker", "PHOENIX_MQTT_BROKER"),
+    file_only("mqtt.username"),
+    key("mqtt.password", "PHOENIX_MQTT_PASSWORD"),
+    file_only("mqtt.discovery_prefix"),
+    file_only("mqtt.node_id"),
+    file_only("mqtt.ca_file"),
+    // Both tracks follow the one variable the recorder has always read.
+    key("recorder.audio", "MULTI_MODAL_ENABLED"),
+    key("recorder.video", "MULTI_MODAL_ENABLED"),
+    key("recorder.always_listening", "ALWAYS_LISTENING_ENABLED"),
+    key("recorder.wake_word", "WAKE_WORD"),
+    key("recorder.storage_path", "RECORDING_STORAGE_PATH"),
+    key("llm.provider", "LLM_PROVIDER"),
+    key("llm.base_url", "LLM_BASE_URL"),
+    key("llm.api_key", "OPENROUTER_API_KEY"),
+    key("llm.model", "DEFAULT_LLM_MODEL"),
+    key("llm.ollama_url", "OLLAMA_BASE_URL"),
+    key("llm.ollama_model", "OLLAMA_MODEL"),
+    key("llm.local_model", "LLM_LOCAL_MODEL"),
+    file_only("llm.local_tokenizer"),
+    file_only("llm.local_template"),
+    key("llm.temperature", "TEMPERATURE"),
+    key("llm.max_tokens", "MAX_TOKENS"),
+    key("llm.timeout_secs", "LLM_TIMEOUT_SECS"),
+    key("llm.retries", "LLM_RETRIES"),
+    file_only("llm.max_prompt_tokens"),
+    key("llm.tokens_per_hour", "LLM_TOKENS_PER_HOUR"),
+    file_only("llm.cache_ttl_secs"),
+    file_only("llm.cache_entries"),
+    key("plugins.enabled", "PLUGINS_ENABLED"),
+    key("plugins.dir", "PLUGINS_DIR"),
+    file_only("plugins.fuel"),
+    file_only("plugins.max_memory_mb"),
+    key("scripts.enabled", "SCRIPTS_ENABLED"),
+    key("scripts.dir", "SCRIPTS_DIR"),
+    file_only("scripts.max_operations"),
+    file_only("scripts.timeout_ms"),
+    key("i18n.language", "PAGI_LANGUAGE"),
+    key("encryption.enabled", "PHOENIX_ENCRYPTION"),
+    key("encryption.key_source", "PHOENIX_ENCRYPTION_KEY_SOURCE"),
+    key("encryption.passphrase", "PHOENIX_DATA_PASSPHRASE"),
+];
+
+/// Settings whose values are never printed.
+const SECRET_KEYS: &[&str] = &[
+    "auth.ui_passphrase",
+    "llm.api_key",
+    "encryption.passphrase",
+    "backup.passphrase",
+    "backup.webdav_password",
+    "backup.s3_secret_key",
+    "sync.key",
+    "mqtt.password",
+];
+
+pub fn is_secret(name: &str) -> bool {
+    SECRET_KEYS.contains(&name)
+}
+
+/// Settings holding a list (arrays in the file, comma lists elsewhere).
+pub fn is_list(name: &str) -> bool {
+    name == "server.cors_origins" || name == "sync.peers" || name.starts_with("lexicon.")
+}
+
+/// Where a value came from, lowest precedence first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Cli,
+}
+
+impl Source {
+    /// Higher wins.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::Default => 0,
+            Self::File(_) => 1,
+            Self::Env(_) => 2,
+            Self::Cli => 3,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(var) => write!(f, "${var}"),
+            Self::Cli => f.write_str("command line"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}:{line}: {message}", path.display())]
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    #[error("unknown setting `{key}` (from {origin})")]
+    Unknown { key: String, origin: Source },
+    #[error("invalid `{key}` (from {origin}): {message}")]
+    Invalid {
+        key: &'static str,
+        origin: Source,
+        message: String,
+    },
+}
+
+/// Command-line input: an explicit config file and `key = value` overrides.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub config_file: Option<PathBuf>,
+    pub values: BTreeMap<String, String>,
+}
+
+impl Overrides {
+    pub fn set(&mut self, key: &str, value: impl Into<String>) -> &mut Self {
+        self.values.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Apply a `--set key=value` argument.
+    pub fn set_assignment(&mut self, assignment: &str) -> Result<&mut Self, String> {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got {assignment:?}"))?;
+        Ok(self.set(key.trim(), value.trim()))
+    }
+}
+
+/// The file, environment and command-line values before they are typed.
+#[derive(Clone)]
+pub struct Layers {
+    file: Option<(PathBuf, BTreeMap<String, String>)>,
+    cli: BTreeMap<String, String>,
+}
+
+impl fmt::Debug for Layers {
+    // Values stay out of debug output; the file may hold the UI passphrase.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Layers")
+            .field("file", &self.file_path())
+            .field("cli", &self.cli.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn spec(name: &str) -> Option<&'static Key> {
+    KEYS.iter().find(|k| k.name == name)
+}
+
+pub fn parse_bool(s: &str) -> Result<bool, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "y" | "on" => Ok(true),
+        "0" | "false" | "no" | "n" | "off" => Ok(false),
+        other => Err(format!("expected true or false, got {other:?}")),
+    }
+}
+
+pub fn parse_number<T: FromStr>(s: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    s.trim().parse::<T>().map_err(|e| format!("{e} ({s:?})"))
+}
+
+/// A whole number of seconds, at least one.
+pub fn interval(s: &str) -> Result<Duration, String> {
+    match parse_number::<u64>(s).map(Duration::from_secs)? {
+        Duration::ZERO => Err("must be at least 1 second".to_string()),
+        d => Ok(d),
+    }
+}
+
+/// A comma list, as arrays are flattened to.
+pub fn parse_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl Layers {
+    /// Read the config file (if any) and check every key from the file and `overrides`.
+    pub fn load(overrides: &Overrides) -> Result<Self, ConfigError> {
+        let path = overrides
+            .config_file
+            .clone()
+            .or_else(|| pagi_utils::env_nonempty(CONFIG_ENV).map(PathBuf::from))
+            .or_else(|| Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|p| p.is_file()));
+        match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path).map_err(|source| ConfigError::Read {
+                    path: path.clone(),
+                    source,
+                })?;
+                Self::parse(path, &text, overrides)
+            }
+            None => Self::without_file(overrides),
+        }
+    }
+
+    /// Layers with `text` as the config file read from `path`.
+    pub fn parse(
+        path: impl Into<PathBuf>,
+        text: &str,
+        overrides: &Overrides,
+    ) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let values = toml::parse(text).map_err(|(line, message)| ConfigError::Syntax {
+            path: path.clone(),
+            line,
+            message,
+        })?;
+        if let Some(unknown) = values.keys().find(|k| spec(k).is_none()) {
+            return Err(ConfigError::Unknown {
+                key: unknown.clone(),
+                origin: Source::File(path),
+            });
+        }
+        Ok(Self {
+            file: Some((path, values)),
+            ..Self::without_file(overrides)?
+        })
+    }
+
+    fn without_file(overrides: &Overrides) -> Result<Self, ConfigError> {
+        if let Some(unknown) = overrides.values.keys().find(|k| spec(k).is_none()) {
+            return Err(ConfigError::Unknown {
+                key: unknown.clone(),
+                origin: Source::Cli,
+            });
+        }
+        Ok(Self {
+            file: None,
+            cli: overrides.values.clone(),
+        })
+    }
+
+    /// Defaults and environment variables only, for code that has no config file or
+    /// command line of its own.
+    pub fn env_only() -> Self {
+        Self {
+            file: None,
+            cli: BTreeMap::new(),
+        }
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// Read the same config file again, keeping the command-line values.
+    pub fn reload(&self) -> Result<Self, ConfigError> {
+        Self::load(&Overrides {
+            config_file: self.file_path().map(Path::to_path_buf),
+            values: self.cli.clone(),
+        })
+    }
+
+    /// The winning raw value for `name` and where it came from.
+    pub fn get(&self, name: &str) -> Option<(String, Source)> {
+        let key = spec(name)?;
+        if let Some(value) = self.cli.get(name) {
+            return Some((value.clone(), Source::Cli));
+        }
+        if let Some(var) = key.env {
+            if let Some(value) = pagi_utils::env_nonempty(var) {
+                let value = if key.env_negated {
+                    (!parse_bool(&value).unwrap_or(false)).to_string()
+                } else {
+                    value
+                };
+                return Some((value, Source::Env(var)));
+            }
+        }
+        let (path, values) = self.file.as_ref()?;
+        values
+            .get(name)
+            .map(|value| (value.clone(), Source::File(path.clone())))
+    }
+
+    /// Every setting's effective raw value, secrets masked, for `--print-config`.
+    pub fn effective(&self) -> Vec<(&'static str, Option<(String, Source)>)> {
+        KEYS.iter()
+            .map(|key| {
+                let value = self.get(key.name).map(|(value, source)| {
+                    if is_secret(key.name) {
+                        ("********".to_string(), source)
+                    } else {
+                        (value, source)
+                    }
+                });
+                (key.name, value)
+            })
+            .collect()
+    }
+
+    /// The effective settings as a config file: set values under their tables, each with where
+    /// it came from, and unset keys commented out. Secrets are masked.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        if let Some(path) = self.file_path() {
+            out.push_str(&format!("# config file: {}\n", path.display()));
+        }
+        let mut table = "";
+        for (name, value) in self.effective() {
+            let (section, key) = name.split_once('.').unwrap_or(("", name));
+            if section != table {
+                if !out.is_empty() {
+                    out.push('\n');
+                }
+                out.push_str(&format!("[{section}]\n"));
+                table = section;
+            }
+            match value {
+                Some((value, source)) => out.push_str(&format!(
+                    "{key} = {}  # {source}\n",
+                    toml::render_value(&value, is_list(name))
+                )),
+                None => out.push_str(&format!("# {key} (default)\n")),
+            }
+        }
+        out
+    }
+
+    /// The parsed winning value of `name` and its source; a value `parse` rejects is an
+    /// [`ConfigError::Invalid`] naming the key.
+    pub fn typed<T>(
+        &self,
+        name: &'static str,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> Result<Option<(T, Source)>, ConfigError> {
+        let Some((raw, origin)) = self.get(name) else {
+            return Ok(None);
+        };
+        match parse(&raw) {
+            Ok(value) => Ok(Some((value, origin))),
+            Err(message) => Err(ConfigError::Invalid {
+                key: name,
+                origin,
+                message,
+            }),
+        }
+    }
+
+    /// The parsed value of `name`, or `default` when it isn't set.
+    pub fn or<T>(
+        &self,
+        name: &'static str,
+        default: T,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> Result<T, ConfigError> {
+        Ok(self.typed(name, parse)?.map_or(default, |(value, _)| value))
+    }
+
+    pub fn flag(&self, name: &'static str, default: bool) -> Result<bool, ConfigError> {
+        self.or(name, default, parse_bool)
+    }
+
+    /// A secret setting's value, looked up in the OS keyring when it is a `keyring:<name>`
+    /// reference.
+    pub fn secret(&self, name: &'static str) -> Result<Option<String>, ConfigError> {
+        Ok(self
+            .typed(name, |raw| secrets::resolve(raw).map_err(|e| e.to_string()))?
+            .map(|(value, _)| value))
+    }
+
+    pub fn path(&self, name: &'static str) -> Option<PathBuf> {
+        self.get(name).map(|(value, _)| PathBuf::from(value))
+    }
+
+    /// Logging for a binary called `name`.
+    pub fn log_config(&self, name: &str) -> Result<LogConfig, ConfigError> {
+        let defaults = LogConfig::new(name);
+        Ok(LogConfig {
+            filter: self
+                .get("logging.filter")
+                .map_or(defaults.filter, |(filter, _)| filter),
+            format: self.or("logging.format", LogFormat::default(), |s| {
+                LogFormat::parse(s).ok_or_else(|| "expected text, pretty or json".to_string())
+            })?,
+            dir: self.path("logging.dir"),
+            keep_files: self.or("retention.log_files", defaults.keep_files, |s| {
+                parse_number::<usize>(s).and_then(|n| match n {
+                    0 => Err("must be at least 1".to_string()),
+                    n => Ok(n),
+                })
+            })?,
+            ..defaults
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SensorSettings {
+    /// How often CPU load is sampled for live `stress` events.
+    pub stress_sample_interval: Duration,
+    /// How often an active recording reports its elapsed time.
+    pub recording_progress_interval: Duration,
+    /// CPU percentage that raises a `stress_threshold` alert.
+    pub stress_alert_percent: u8,
+}
+
+impl Default for SensorSettings {
+    fn default() -> Self {
+        Self {
+            stress_sample_interval: Duration::from_secs(5),
+            recording_progress_interval: Duration::from_secs(2),
+            stress_alert_percent: 85,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetentionSettings {
+    /// Idle time after which a login session expires.
+    pub session_ttl: Duration,
+    /// Age at which malware sandbox samples are deleted.
+    pub sandbox_cleanup_days: i64,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            session_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            sandbox_cleanup_days: 7,
+        }
+    }
+}
+
+impl SensorSettings {
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        Ok(Self {
+            stress_sample_interval: layers.or(
+                "sensors.stress_sample_secs",
+                defaults.stress_sample_interval,
+                interval,
+            )?,
+            recording_progress_interval: layers.or(
+                "sensors.recording_progress_secs",
+                defaults.recording_progress_interval,
+                interval,
+            )?,
+            stress_alert_percent: layers.or(
+                "sensors.stress_alert_percent",
+                defaults.stress_alert_percent,
+                |s| {
+                    parse_number::<u8>(s).and_then(|p| match p {
+                        0..=100 => Ok(p),
+                        _ => Err("must be 0-100".to_string()),
+                    })
+                },
+            )?,
+        })
+    }
+}
+
+impl RetentionSettings {
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        Ok(Self {
+            session_ttl: layers.or("retention.session_ttl_hours", defaults.session_ttl, |s| {
+                match parse_number::<u64>(s)? {
+                    0 => Err("must be at least 1 hour".to_string()),
+                    h => Ok(Duration::from_secs(h * 60 * 60)),
+                }
+            })?,
+            sandbox_cleanup_days: layers.or(
+                "retention.sandbox_cleanup_days",
+                defaults.sandbox_cleanup_days,
+                parse_number,
+            )?,
+        })
+    }
+}
+
+/// Optional subsystems the web server starts.
+#[derive(Debug, Clone)]
+pub struct FeatureToggles {
+    /// Serve the local JSON-RPC bridge.
+    pub ipc_bridge: bool,
+    /// Serve the switchboard gRPC service.
+    pub grpc: bool,
+    /// Print LAN pairing details for the Mobile PWA and allow its origin.
+    pub mobile_pairing: bool,
+    pub vector_kb: bool,
+    pub audio_intelligence: bool,
+    pub desktop_capture: bool,
+    pub wifi_analyzer: bool,
+    pub bluetooth_sniffer: bool,
+    pub correlation_engine: bool,
+    pub privacy_framework: bool,
+    pub hardware_detector: bool,
+    pub home_automation: bool,
+    pub outlook_com: bool,
+    pub malware_sandbox: bool,
+    pub network_security_agent: bool,
+}
+
+impl Default for FeatureToggles {
+    fn default() -> Self {
+        Self {
+            ipc_bridge: true,
+            grpc: false,
+            mobile_pairing: true,
+            vector_kb: false,
+            audio_intelligence: false,
+            desktop_capture: false,
+            wifi_analyzer: false,
+            bluetooth_sniffer: false,
+            correlation_engine: false,
+            privacy_framework: false,
+            hardware_detector: false,
+            home_automation: false,
+            outlook_com: false,
+            malware_sandbox: false,
+            network_security_agent: false,
+        }
+    }
+}
+
+impl FeatureToggles {
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let features = Self::default();
+        Ok(Self {
+            ipc_bridge: layers.flag("features.ipc_bridge", features.ipc_bridge)?,
+            grpc: layers.flag("features.grpc", features.grpc)?,
+            mobile_pairing: layers.flag("features.mobile_pairing", features.mobile_pairing)?,
+            vector_kb: layers.flag("features.vector_kb", features.vector_kb)?,
+            audio_intelligence: layers
+                .flag("features.audio_intelligence", features.audio_intelligence)?,
+            desktop_capture: layers.flag("features.desktop_capture", features.desktop_capture)?,
+            wifi_analyzer: layers.flag("features.wifi_analyzer", features.wifi_analyzer)?,
+            bluetooth_sniffer: layers
+                .flag("features.bluetooth_sniffer", features.bluetooth_sniffer)?,
+            correlation_engine: layers
+                .flag("features.correlation_engine", features.correlation_engine)?,
+            privacy_framework: layers
+                .flag("features.privacy_framework", features.privacy_framework)?,
+            hardware_detector: layers
+                .flag("features.hardware_detector", features.hardware_detector)?,
+            home_automation: layers.flag("features.home_automation", features.home_automation)?,
+            outlook_com: layers.flag("features.outlook_com", features.outlook_com)?,
+            malware_sandbox: layers.flag("features.malware_sandbox", features.malware_sandbox)?,
+            network_security_agent: layers.flag(
+                "features.network_security_agent",
+                features.network_security_agent,
+            )?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(file: &str, cli: &[(&str, &str)]) -> Layers {
+        let mut overrides = Overrides::default();
+        for (key, value) in cli {
+            overrides.set(key, *value);
+        }
+        Layers::parse("phoenix.toml", file, &overrides).unwrap()
+    }
+
+    #[test]
+    fn later_layers_win_and_unknown_keys_are_refused() {
+        let file = "[sensors]\nstress_alert_percent = 70\nstress_sample_secs = 9";
+        let sensors =
+            SensorSettings::from_layers(&layers(file, &[("sensors.stress_sample_secs", "3")]))
+                .unwrap();
+        assert_eq!(sensors.stress_alert_percent, 70);
+        assert_eq!(sensors.stress_sample_interval, Duration::from_secs(3));
+
+        let err =
+            SensorSettings::from_layers(&layers("[sensors]\nstress_alert_percent = 150", &[]))
+                .unwrap_err();
+        assert!(err.to_string().contains("sensors.stress_alert_percent"));
+        assert!(matches!(
+            Layers::parse(
+                "phoenix.toml",
+                "[sensors]\nstres_alert_percent = 1",
+                &Overrides::default()
+            ),
+            Err(ConfigError::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn effective_settings_round_trip_through_toml() {
+        let layers = layers(
+            "[server]\ncors_origins = [\"tauri://localhost\"]\n[auth]\nui_passphrase = \"hunter2\"",
+            &[("recorder.wake_word", "Hey Sola")],
+        );
+        let text = layers.to_toml();
+        assert!(text.contains("\nwake_word = \"Hey Sola\"  # command line\n"));
+        assert!(!text.contains("hunter2"));
+        let reread = toml::parse(&text).unwrap();
+        assert_eq!(reread["server.cors_origins"], "tauri://localhost");
+        assert_eq!(reread["recorder.wake_word"], "Hey Sola");
+        assert!(!reread.contains_key("server.port"));
+    }
+
+    #[test]
+    fn secrets_are_values_or_keyring_references() {
+        let plain = layers("[auth]\nui_passphrase = \"hunter2\"", &[]);
+        assert_eq!(
+            plain.secret("auth.ui_passphrase").unwrap().as_deref(),
+            Some("hunter2")
+        );
+        // No keyring here, or no such entry in it; either way the key is named.
+        let missing = layers("[auth]\nui_passphrase = \"keyring:pagi_test_missing\"", &[]);
+        let err = missing.secret("auth.ui_passphrase").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { key: "auth.ui_passphrase", .. }));
+    }
+}
@@ -0,0 +1,209 @@
+//! Secrets kept in the OS credential store: the macOS Keychain, the Windows Credential Manager,
+//! or a Secret Service (GNOME Keyring, KWallet) on Linux. Builds without the `keyring` feature
+//! have none, and every call fails with [`SecretError::Unavailable`].
+//!
+//! Each secret is stored under the service [`SERVICE`] with its name as the account. A secret
+//! setting (see [`crate::is_secret`]) can name one instead of holding the value, as
+//! `keyring:<name>` in the file or the environment; [`crate::Layers::secret`] looks it up. For
+//! the many places that read an API key straight from an environment variable,
+//! [`resolve_env`] swaps such references for the secrets at startup. `pagi secrets
+//! set|get|delete <name>` manages them.
+
+/// The credential store's service name for everything this app keeps there.
+pub const SERVICE: &str = "pagi-twin";
+
+/// Prefix of a setting value that names a secret instead of holding it.
+pub const REFERENCE_PREFIX: &str = "keyring:";
+
+const MAX_NAME_CHARS: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("invalid secret name {0:?}; use up to {MAX_NAME_CHARS} letters, digits, `_` and `.`")]
+    InvalidName(String),
+    #[error("no secret `{0}` in the OS keyring")]
+    NotFound(String),
+    #[error("secret `{0}` is not UTF-8 text")]
+    NotText(String),
+    #[error("no OS keyring: {0}")]
+    Unavailable(String),
+    #[error("OS keyring: {0}")]
+    Keyring(String),
+    #[error("${var}: {source}")]
+    Env {
+        var: String,
+        source: Box<SecretError>,
+    },
+}
+
+/// The secret name in a `keyring:<name>` value, if it is one.
+pub fn reference(value: &str) -> Option<&str> {
+    value.trim().strip_prefix(REFERENCE_PREFIX).map(str::trim)
+}
+
+/// Check a name given on the command line or in a reference. The app's own entries (see
+/// [`get_raw`]) have a `-` in their names, so these never clash with them.
+pub fn check_name(name: &str) -> Result<(), SecretError> {
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_NAME_CHARS
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !valid {
+        return Err(SecretError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// `value` itself, or the secret it names.
+pub fn resolve(value: &str) -> Result<String, SecretError> {
+    match reference(value) {
+        Some(name) => get(name)?.ok_or_else(|| SecretError::NotFound(name.to_string())),
+        None => Ok(value.to_string()),
+    }
+}
+
+/// Replace every environment variable that holds a `keyring:<name>` reference with the secret
+/// it names, returning the variables replaced. Call it once at startup, before other threads
+/// read the environment.
+pub fn resolve_env() -> Result<Vec<String>, SecretError> {
+    let references: Vec<(String, String)> = std::env::vars()
+        .filter(|(_, value)| reference(value).is_some())
+        .collect();
+    let mut resolved = Vec::with_capacity(references.len());
+    for (var, value) in references {
+        let secret = resolve(&value).map_err(|e| SecretError::Env {
+            var: var.clone(),
+            source: Box::new(e),
+        })?;
+        std::env::set_var(&var, secret);
+        resolved.push(var);
+    }
+    Ok(resolved)
+}
+
+pub fn get(name: &str) -> Result<Option<String>, SecretError> {
+    check_name(name)?;
+    store::get(name)?
+        .map(|bytes| String::from_utf8(bytes).map_err(|_| SecretError::NotText(name.to_string())))
+        .transpose()
+}
+
+pub fn set(name: &str, value: &str) -> Result<(), SecretError> {
+    check_name(name)?;
+    store::set(name, value.as_bytes())
+}
+
+/// Remove a secret; `false` when there was none.
+pub fn delete(name: &str) -> Result<bool, SecretError> {
+    check_name(name)?;
+    store::delete(name)
+}
+
+/// Raw bytes under one of the app's own names, such as `data-key`.
+pub fn get_raw(name: &'static str) -> Result<Option<Vec<u8>>, SecretError> {
+    store::get(name)
+}
+
+pub fn set_raw(name: &'static str, value: &[u8]) -> Result<(), SecretError> {
+    store::set(name, value)
+}
+
+#[cfg(feature = "keyring")]
+mod store {
+    use keyring::credential::CredentialPersistence;
+
+    use super::{SecretError, SERVICE};
+
+    fn entry(name: &str) -> Result<keyring::Entry, SecretError> {
+        // Without a native store keyring falls back to one in process memory, which would lose
+        // everything at exit.
+        let builder = keyring::default::default_credential_builder();
+        if !matches!(builder.persistence(), CredentialPersistence::UntilDelete) {
+            return Err(SecretError::Unavailable(
+                "no persistent credential store on this platform".to_string(),
+            ));
+        }
+        keyring::Entry::new(SERVICE, name).map_err(error)
+    }
+
+    fn error(e: keyring::Error) -> SecretError {
+        match e {
+            keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_) => {
+                SecretError::Unavailable(e.to_string())
+            }
+            e => SecretError::Keyring(e.to_string()),
+        }
+    }
+
+    pub(super) fn get(name: &str) -> Result<Option<Vec<u8>>, SecretError> {
+        match entry(name)?.get_secret() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(error(e)),
+        }
+    }
+
+    pub(super) fn set(name: &str, value: &[u8]) -> Result<(), SecretError> {
+        entry(name)?.set_secret(value).map_err(error)
+    }
+
+    pub(super) fn delete(name: &str) -> Result<bool, SecretError> {
+        match entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(error(e)),
+        }
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+mod store {
+    use super::SecretError;
+
+    fn unavailable() -> SecretError {
+        SecretError::Unavailable("this build has no OS keyring support".to_string())
+    }
+
+    pub(super) fn get(_name: &str) -> Result<Option<Vec<u8>>, SecretError> {
+        Err(unavailable())
+    }
+
+    pub(super) fn set(_name: &str, _value: &[u8]) -> Result<(), SecretError> {
+        Err(unavailable())
+    }
+
+    pub(super) fn delete(_name: &str) -> Result<bool, SecretError> {
+        Err(unavailable())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_name_a_secret() {
+        assert_eq!(reference("keyring:openai_api_key"), Some("openai_api_key"));
+        assert_eq!(reference(" keyring: s3.secret "), Some("s3.secret"));
+        assert_eq!(reference("sk-or-123"), None);
+        // Plain values come back as they are, without touching the keyring.
+        assert_eq!(resolve("sk-or-123").unwrap(), "sk-or-123");
+    }
+
+    #[test]
+    fn names_are_checked() {
+        assert!(check_name("openai_api_key").is_ok());
+        assert!(check_name("s3.secret_key").is_ok());
+        assert!(matches!(check_name(""), Err(SecretError::InvalidName(_))));
+        assert!(matches!(
+            check_name("a/b"),
+            Err(SecretError::InvalidName(_))
+        ));
+        assert!(matches!(
+            check_name(&"x".repeat(65)),
+            Err(SecretError::InvalidName(_))
+        ));
+        assert!(check_name("data-key").is_err());
+    }
+}
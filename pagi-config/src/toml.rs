@@ -0,0 +1,249 @@
+//! The part of TOML the settings need: `[table]` headers, `key = value` lines holding strings,
+//! integers, booleans or arrays of those, and `#` comments. Values are kept as the flat strings
+//! the environment and command-line layers use; arrays become comma lists.
+
+use std::collections::BTreeMap;
+
+/// Characters of `s` outside quoted strings, with their byte offsets.
+fn unquoted(s: &str) -> Vec<(usize, char)> {
+    let mut out = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => out.push((i, c)),
+        }
+    }
+    out
+}
+
+fn strip_comment(line: &str) -> &str {
+    match unquoted(line).into_iter().find(|&(_, c)| c == '#') {
+        Some((i, _)) => &line[..i],
+        None => line,
+    }
+}
+
+fn brackets_closed(s: &str) -> bool {
+    unquoted(s).iter().fold(0i32, |depth, &(_, c)| match c {
+        '[' => depth + 1,
+        ']' => depth - 1,
+        _ => depth,
+    }) <= 0
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+        })
+}
+
+/// One TOML value as the flat string the other layers use (arrays become comma lists).
+fn parse_value(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    if let Some(rest) = s.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if chars.as_str().trim().is_empty() => return Ok(out),
+                '"' => return Err(format!("unexpected text after string: {s}")),
+                '\\' => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    Some('r') => out.push('\r'),
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    other => return Err(format!("unsupported escape \\{}", other.unwrap_or(' '))),
+                },
+                c => out.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    } else if let Some(rest) = s.strip_prefix('\'') {
+        match rest.split_once('\'') {
+            Some((literal, tail)) if tail.trim().is_empty() => Ok(literal.to_string()),
+            Some(_) => Err(format!("unexpected text after string: {s}")),
+            None => Err("unterminated string".to_string()),
+        }
+    } else if let Some(inner) = s.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| format!("unexpected text after array: {s}"))?;
+        let mut items = Vec::new();
+        let mut start = 0;
+        let commas = unquoted(inner).into_iter().filter(|&(_, c)| c == ',');
+        for end in commas.map(|(i, _)| i).chain([inner.len()]) {
+            let item = inner[start..end].trim();
+            start = end + 1;
+            if item.is_empty() {
+                continue;
+            }
+            if item.starts_with('[') {
+                return Err("nested arrays are not supported".to_string());
+            }
+            let value = parse_value(item)?;
+            if value.contains(',') {
+                return Err(format!("array items can't contain commas: {item}"));
+            }
+            items.push(value);
+        }
+        Ok(items.join(","))
+    } else if s == "true" || s == "false" {
+        Ok(s.to_string())
+    } else {
+        let number = s.replace('_', "");
+        if number.parse::<i64>().is_ok() || number.parse::<f64>().is_ok() {
+            Ok(number)
+        } else {
+            Err(format!("unsupported value {s:?} (quote strings)"))
+        }
+    }
+}
+
+/// `table.key` -> value for every assignment in `text`; errors carry the 1-based line.
+pub(crate) fn parse(text: &str) -> Result<BTreeMap<String, String>, (usize, String)> {
+    let mut values = BTreeMap::new();
+    let mut table = String::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((index, raw)) = lines.next() {
+        let line_no = index + 1;
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            if header.starts_with('[') {
+                return Err((line_no, "arrays of tables are not supported".to_string()));
+            }
+            let name = header
+                .strip_suffix(']')
+                .map(str::trim)
+                .filter(|name| is_bare_key(name))
+                .ok_or_else(|| (line_no, format!("invalid table header {line}")))?;
+            table = name.to_string();
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (line_no, format!("expected key = value, got {line}")))?;
+        let key = key.trim();
+        if !is_bare_key(key) {
+            return Err((line_no, format!("invalid key {key:?}")));
+        }
+        // Arrays may span lines.
+        let mut value = value.trim().to_string();
+        while value.starts_with('[') && !brackets_closed(&value) {
+            let (_, next) = lines
+                .next()
+                .ok_or_else(|| (line_no, "unterminated array".to_string()))?;
+            value.push(' ');
+            value.push_str(strip_comment(next).trim());
+        }
+        let name = if table.is_empty() {
+            key.to_string()
+        } else {
+            format!("{table}.{key}")
+        };
+        let value = parse_value(&value).map_err(|message| (line_no, message))?;
+        if values.insert(name.clone(), value).is_some() {
+            return Err((line_no, format!("{name} is set twice")));
+        }
+    }
+    Ok(values)
+}
+
+/// `value` written back as a TOML value: booleans and integers bare, strings quoted, and the
+/// comma lists of `list` keys as arrays.
+pub(crate) fn render_value(value: &str, list: bool) -> String {
+    if list {
+        let items: Vec<String> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(quote)
+            .collect();
+        return format!("[{}]", items.join(", "));
+    }
+    if value == "true" || value == "false" || value.parse::<i64>().is_ok() {
+        value.to_string()
+    } else {
+        quote(value)
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_subset() {
+        let values = parse(
+            r#"
+            # Phoenix
+            [server]
+            port = 9_000   # comment
+            ui_dir = 'C:\ui'
+            cors_origins = [
+              "http://localhost:*", # dev
+              "https://ui.lan#1",
+            ]
+
+            [features]
+            vector_kb = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(values["server.port"], "9000");
+        assert_eq!(values["server.ui_dir"], "C:\\ui");
+        assert_eq!(
+            values["server.cors_origins"],
+            "http://localhost:*,https://ui.lan#1"
+        );
+        assert_eq!(values["features.vector_kb"], "true");
+
+        assert_eq!(parse("[server]\nport").unwrap_err().0, 2);
+        assert!(parse("a = 1\na = 2").is_err());
+        assert!(parse("a = bare").is_err());
+    }
+
+    #[test]
+    fn rendered_values_parse_back() {
+        for (value, list) in [
+            ("8888", false),
+            ("true", false),
+            ("C:\\ui \"quoted\" #1", false),
+            ("tauri://localhost,http://localhost:*", true),
+        ] {
+            let line = format!("k = {}", render_value(value, list));
+            assert_eq!(parse(&line).unwrap()["k"], value, "{line}");
+        }
+        assert_eq!(render_value("a, b", true), r#"["a", "b"]"#);
+    }
+}
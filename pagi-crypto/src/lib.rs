@@ -0,0 +1,211 @@
+//! Encryption at rest for the data directory.
+//!
+//! One random 256-bit data key protects everything the server persists. The SQLite database
+//! is encrypted whole with it by SQLCipher (see `phoenix_storage`). The sled stores, the
+//! server's own files and the recorder's biometric and emotion files (see
+//! `multi_modal_recording::sealed`) keep their layout, but every value goes through a
+//! [`Sealer`]: `MAGIC | nonce | AES-256-GCM(value)`. `phoenix.toml.example` lists what is not
+//! covered.
+//!
+//! The data key itself is never written in the clear. It is either kept in the OS credential
+//! store or wrapped with a key derived from a passphrase; see [`unlock`], which runs once at
+//! startup. `data/encryption.json` records which, so a data directory that was encrypted is
+//! never opened as if it weren't.
+//!
+//! Without a key a [`Sealer`] passes values through unchanged, and with one it still reads
+//! values written before encryption was turned on, so stores can be upgraded in place.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use zeroize::Zeroizing;
+
+mod unlock;
+
+pub use unlock::{unlock, unlock_existing, EncryptionConfig, KeySource, KEY_FILE};
+
+/// Length of the data key, in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// Prefix of every sealed value.
+const MAGIC: &[u8; 8] = b"PHXSEAL1";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    #[error("the data is encrypted and no key is unlocked; turn on [encryption]")]
+    Locked,
+    #[error("cannot decrypt: wrong key or damaged data")]
+    Decrypt,
+    #[error("no system randomness")]
+    Random,
+    #[error("wrong passphrase for the data directory")]
+    WrongPassphrase,
+    #[error("{0}")]
+    Unlock(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A 256-bit key, wiped from memory when dropped.
+pub struct DataKey(Zeroizing<[u8; KEY_LEN]>);
+
+impl DataKey {
+    /// A fresh random key.
+    pub fn generate() -> Result<Self, CryptoError> {
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        SystemRandom::new()
+            .fill(key.as_mut())
+            .map_err(|_| CryptoError::Random)?;
+        Ok(Self(key))
+    }
+
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+
+    fn aead(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, self.as_bytes()).expect("32-byte AES key"))
+    }
+}
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DataKey(********)")
+    }
+}
+
+/// Whether `data` was written by [`Sealer::seal`] with a key.
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.len() >= MAGIC.len() + NONCE_LEN && data.starts_with(MAGIC)
+}
+
+/// Seals and opens stored values with the data key, if there is one. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Sealer {
+    key: Option<Arc<DataKey>>,
+}
+
+impl fmt::Debug for Sealer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sealer")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+impl Sealer {
+    pub fn new(key: DataKey) -> Self {
+        Self {
+            key: Some(Arc::new(key)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn key(&self) -> Option<&DataKey> {
+        self.key.as_deref()
+    }
+
+    /// `plain` as it should be stored: encrypted with a key, unchanged without one.
+    pub fn seal(&self, plain: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let Some(key) = &self.key else {
+            return Ok(plain.to_vec());
+        };
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| CryptoError::Random)?;
+        let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + plain.len() + 16);
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&nonce);
+        let mut body = plain.to_vec();
+        key.aead()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(MAGIC),
+                &mut body,
+            )
+            .map_err(|_| CryptoError::Decrypt)?;
+        sealed.extend_from_slice(&body);
+        Ok(sealed)
+    }
+
+    /// The plain value of something read back from a store. Values that were never sealed are
+    /// returned as they are.
+    pub fn open<'a>(&self, stored: &'a [u8]) -> Result<Cow<'a, [u8]>, CryptoError> {
+        if !is_sealed(stored) {
+            return Ok(Cow::Borrowed(stored));
+        }
+        let key = self.key.as_ref().ok_or(CryptoError::Locked)?;
+        let (nonce, body) = stored[MAGIC.len()..].split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| CryptoError::Decrypt)?;
+        let mut buf = body.to_vec();
+        let plain_len = key
+            .aead()
+            .open_in_place(nonce, Aad::from(MAGIC), &mut buf)
+            .map_err(|_| CryptoError::Decrypt)?
+            .len();
+        buf.truncate(plain_len);
+        Ok(Cow::Owned(buf))
+    }
+
+    /// Whether a stored value is still in the clear and should be rewritten sealed.
+    pub fn needs_sealing(&self, stored: &[u8]) -> bool {
+        self.is_enabled() && !is_sealed(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seals_and_opens() {
+        let sealer = Sealer::new(DataKey::generate().unwrap());
+        let sealed = sealer.seal(b"transcript").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(10).any(|w| w == b"transcript"));
+        assert_eq!(sealer.open(&sealed).unwrap().as_ref(), b"transcript");
+        // A fresh nonce each time.
+        assert_ne!(sealer.seal(b"transcript").unwrap(), sealed);
+    }
+
+    #[test]
+    fn reads_plain_values_and_refuses_others() {
+        let sealer = Sealer::new(DataKey::generate().unwrap());
+        assert_eq!(sealer.open(b"legacy").unwrap().as_ref(), b"legacy");
+        assert!(sealer.needs_sealing(b"legacy"));
+
+        let sealed = sealer.seal(b"secret").unwrap();
+        let other = Sealer::new(DataKey::generate().unwrap());
+        assert!(matches!(other.open(&sealed), Err(CryptoError::Decrypt)));
+        assert!(matches!(
+            Sealer::default().open(&sealed),
+            Err(CryptoError::Locked)
+        ));
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(sealer.open(&tampered), Err(CryptoError::Decrypt)));
+    }
+
+    #[test]
+    fn without_a_key_values_pass_through() {
+        let sealer = Sealer::default();
+        assert_eq!(sealer.seal(b"plain").unwrap(), b"plain");
+        assert!(!sealer.needs_sealing(b"plain"));
+        assert_eq!(
+            format!("{:?}", DataKey::from_bytes([7; KEY_LEN])),
+            "DataKey(********)"
+        );
+    }
+}
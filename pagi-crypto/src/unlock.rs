@@ -0,0 +1,410 @@
+//! The `[encryption]` settings and the key file that says how the data key is kept.
+
+use std::fmt;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use base64::Engine;
+use pagi_config::{ConfigError, Layers};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{CryptoError, DataKey, Sealer, KEY_LEN};
+
+/// Where the key file lives, relative to the data directory.
+pub const KEY_FILE: &str = "data/encryption.json";
+
+const FORMAT: u32 = 1;
+const PBKDF2_ROUNDS: u32 = 200_000;
+const SALT_LEN: usize = 16;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+/// Sealed with the keyring's key into the key file, so a different key is told apart from
+/// damaged data.
+const CHECK: &[u8] = b"pagi data key";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// Wrapped with a key derived from `encryption.passphrase`.
+    #[default]
+    Passphrase,
+    /// Kept in the OS credential store.
+    Keyring,
+}
+
+impl KeySource {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "passphrase" => Some(Self::Passphrase),
+            "keyring" => Some(Self::Keyring),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Passphrase => "passphrase",
+            Self::Keyring => "keyring",
+        }
+    }
+}
+
+/// The `[encryption]` settings; see [`EncryptionConfig::from_layers`].
+#[derive(Clone, Default, PartialEq)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    pub key_source: KeySource,
+    pub passphrase: Option<String>,
+}
+
+impl fmt::Debug for EncryptionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionConfig")
+            .field("enabled", &self.enabled)
+            .field("key_source", &self.key_source)
+            .field("passphrase", &self.passphrase.as_ref().map(|_| "********"))
+            .finish()
+    }
+}
+
+impl EncryptionConfig {
+    /// The `[encryption]` table: `enabled`, `key_source` (`passphrase` or `keyring`) and
+    /// `passphrase`.
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        Ok(Self {
+            enabled: layers.flag("encryption.enabled", false)?,
+            key_source: layers.or("encryption.key_source", KeySource::default(), |s| {
+                KeySource::parse(s).ok_or_else(|| "expected passphrase or keyring".to_string())
+            })?,
+            passphrase: layers
+                .secret("encryption.passphrase")?
+                .filter(|p| !p.is_empty()),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    format: u32,
+    source: KeySource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rounds: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    /// With a passphrase, the data key sealed with the passphrase's key; with the keyring,
+    /// [`CHECK`] sealed with the data key.
+    sealed: String,
+}
+
+fn unlock_error(message: impl Into<String>) -> CryptoError {
+    CryptoError::Unlock(message.into())
+}
+
+fn b64() -> base64::engine::GeneralPurpose {
+    base64::engine::general_purpose::STANDARD
+}
+
+fn passphrase_key(passphrase: &str, salt: &[u8], rounds: u32) -> Result<DataKey, CryptoError> {
+    let rounds = NonZeroU32::new(rounds).ok_or_else(|| unlock_error("key file: zero rounds"))?;
+    let mut key = [0u8; KEY_LEN];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        rounds,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    Ok(DataKey::from_bytes(key))
+}
+
+fn required_passphrase(config: &EncryptionConfig) -> Result<&str, CryptoError> {
+    config.passphrase.as_deref().ok_or_else(|| {
+        unlock_error("the data key is protected by a passphrase; set PHOENIX_DATA_PASSPHRASE")
+    })
+}
+
+fn read_key_file(path: &Path) -> Result<Option<KeyFile>, CryptoError> {
+    let raw = match std::fs::read(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let file: KeyFile = serde_json::from_slice(&raw)
+        .map_err(|e| unlock_error(format!("{}: {e}", path.display())))?;
+    if file.format > FORMAT {
+        return Err(unlock_error(format!(
+            "{} was written by a newer version (format {})",
+            path.display(),
+            file.format
+        )));
+    }
+    Ok(Some(file))
+}
+
+fn write_key_file(path: &Path, file: &KeyFile) -> Result<(), CryptoError> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(
+        &tmp,
+        serde_json::to_vec_pretty(file).map_err(std::io::Error::other)?,
+    )?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn create(config: &EncryptionConfig, path: &Path) -> Result<Sealer, CryptoError> {
+    let sealer = Sealer::new(DataKey::generate()?);
+    let key = sealer.key().expect("new sealer has a key");
+    let file = match config.key_source {
+        KeySource::Passphrase => {
+            let passphrase = required_passphrase(config)?;
+            if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+                return Err(unlock_error(format!(
+                    "the passphrase must be at least {MIN_PASSPHRASE_CHARS} characters"
+                )));
+            }
+            let mut salt = [0u8; SALT_LEN];
+            SystemRandom::new()
+                .fill(&mut salt)
+                .map_err(|_| CryptoError::Random)?;
+            let wrapping = Sealer::new(passphrase_key(passphrase, &salt, PBKDF2_ROUNDS)?);
+            KeyFile {
+                format: FORMAT,
+                source: KeySource::Passphrase,
+                rounds: Some(PBKDF2_ROUNDS),
+                salt: Some(b64().encode(salt)),
+                sealed: b64().encode(wrapping.seal(key.as_bytes())?),
+            }
+        }
+        KeySource::Keyring => {
+            os_keyring::store(key)?;
+            KeyFile {
+                format: FORMAT,
+                source: KeySource::Keyring,
+                rounds: None,
+                salt: None,
+                sealed: b64().encode(sealer.seal(CHECK)?),
+            }
+        }
+    };
+    write_key_file(path, &file)?;
+    let source = config.key_source.as_str();
+    tracing::info!(target: "crypto", source, path = %path.display(), "data key created");
+    Ok(sealer)
+}
+
+fn open(config: &EncryptionConfig, file: &KeyFile) -> Result<Sealer, CryptoError> {
+    let sealed = b64()
+        .decode(&file.sealed)
+        .map_err(|e| unlock_error(format!("key file: {e}")))?;
+    match file.source {
+        KeySource::Passphrase => {
+            let salt = file
+                .salt
+                .as_deref()
+                .map(|s| b64().decode(s))
+                .transpose()
+                .map_err(|e| unlock_error(format!("key file: {e}")))?
+                .ok_or_else(|| unlock_error("key file: no salt"))?;
+            let rounds = file.rounds.unwrap_or(PBKDF2_ROUNDS);
+            let wrapping =
+                Sealer::new(passphrase_key(required_passphrase(config)?, &salt, rounds)?);
+            let plain = Zeroizing::new(
+                wrapping
+                    .open(&sealed)
+                    .map_err(|_| CryptoError::WrongPassphrase)?
+                    .into_owned(),
+            );
+            let bytes: [u8; KEY_LEN] = plain
+                .as_slice()
+                .try_into()
+                .map_err(|_| unlock_error("key file: the wrapped key has the wrong length"))?;
+            Ok(Sealer::new(DataKey::from_bytes(bytes)))
+        }
+        KeySource::Keyring => {
+            let key = os_keyring::load()?
+                .ok_or_else(|| unlock_error("the OS keyring has no data key for this install"))?;
+            let sealer = Sealer::new(key);
+            match sealer.open(&sealed) {
+                Ok(check) if check.as_ref() == CHECK => {}
+                _ => return Err(unlock_error("the OS keyring holds a different data key")),
+            }
+            Ok(sealer)
+        }
+    }
+}
+
+fn unlock_with(
+    config: &EncryptionConfig,
+    key_file: &Path,
+    create_missing: bool,
+) -> Result<Sealer, CryptoError> {
+    let Some(file) = read_key_file(key_file)? else {
+        if config.enabled && create_missing {
+            return create(config, key_file);
+        }
+        return Ok(Sealer::default());
+    };
+    if !config.enabled {
+        return Err(unlock_error(format!(
+            "the data directory is encrypted ({}) but encryption.enabled is off",
+            key_file.display()
+        )));
+    }
+    if file.source != config.key_source {
+        return Err(unlock_error(format!(
+            "the data key is kept by the {} source; set encryption.key_source = \"{}\"",
+            file.source.as_str(),
+            file.source.as_str()
+        )));
+    }
+    open(config, &file)
+}
+
+/// Unlock the data key described by `key_file` (the data directory's [`KEY_FILE`]). When
+/// encryption is on and there is no key yet, one is created; when it is off the sealer has no
+/// key. A data directory that has a key file can't be opened with encryption off.
+pub fn unlock(config: &EncryptionConfig, key_file: &Path) -> Result<Sealer, CryptoError> {
+    unlock_with(config, key_file, true)
+}
+
+/// Like [`unlock`], but never creates a key, for checks that must not change anything.
+pub fn unlock_existing(config: &EncryptionConfig, key_file: &Path) -> Result<Sealer, CryptoError> {
+    unlock_with(config, key_file, false)
+}
+
+mod os_keyring {
+    use pagi_config::secrets::{self, SecretError};
+    use zeroize::Zeroizing;
+
+    use super::unlock_error;
+    use crate::{CryptoError, DataKey, KEY_LEN};
+
+    const NAME: &str = "data-key";
+
+    fn keyring_error(e: SecretError) -> CryptoError {
+        match e {
+            SecretError::Unavailable(_) => {
+                unlock_error(format!("{e}; use encryption.key_source = \"passphrase\""))
+            }
+            e => unlock_error(e.to_string()),
+        }
+    }
+
+    pub(super) fn load() -> Result<Option<DataKey>, CryptoError> {
+        let Some(secret) = secrets::get_raw(NAME).map_err(keyring_error)? else {
+            return Ok(None);
+        };
+        let secret = Zeroizing::new(secret);
+        let bytes: [u8; KEY_LEN] = secret
+            .as_slice()
+            .try_into()
+            .map_err(|_| unlock_error("keyring: the stored data key has the wrong length"))?;
+        Ok(Some(DataKey::from_bytes(bytes)))
+    }
+
+    pub(super) fn store(key: &DataKey) -> Result<(), CryptoError> {
+        secrets::set_raw(NAME, key.as_bytes()).map_err(keyring_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pagi_config::Overrides;
+
+    use super::*;
+
+    fn key_file(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("pagi-crypto-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join(KEY_FILE)
+    }
+
+    fn config(passphrase: &str) -> EncryptionConfig {
+        EncryptionConfig {
+            enabled: true,
+            key_source: KeySource::Passphrase,
+            passphrase: Some(passphrase.to_string()),
+        }
+    }
+
+    #[test]
+    fn passphrase_unlocks_the_same_key() {
+        let path = key_file("passphrase");
+        assert!(!unlock_existing(&config("correct horse"), &path)
+            .unwrap()
+            .is_enabled());
+
+        let sealed = unlock(&config("correct horse"), &path)
+            .unwrap()
+            .seal(b"ghost script")
+            .unwrap();
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(raw.contains("\"passphrase\"") && !raw.contains("correct horse"));
+
+        let again = unlock(&config("correct horse"), &path).unwrap();
+        assert_eq!(again.open(&sealed).unwrap().as_ref(), b"ghost script");
+        assert!(matches!(
+            unlock(&config("wrong horse"), &path),
+            Err(CryptoError::WrongPassphrase)
+        ));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn an_encrypted_directory_is_not_opened_in_the_clear() {
+        let path = key_file("disabled");
+        unlock(&config("correct horse"), &path).unwrap();
+        let err = unlock(&EncryptionConfig::default(), &path).unwrap_err();
+        assert!(err.to_string().contains("encryption.enabled is off"));
+        let keyring = EncryptionConfig {
+            key_source: KeySource::Keyring,
+            ..config("correct horse")
+        };
+        assert!(unlock(&keyring, &path)
+            .unwrap_err()
+            .to_string()
+            .contains("key_source = \"passphrase\""));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn needs_a_usable_passphrase() {
+        let path = key_file("weak");
+        assert!(unlock(&config("short"), &path).is_err());
+        let missing = EncryptionConfig {
+            passphrase: None,
+            ..config("")
+        };
+        assert!(unlock(&missing, &path).is_err());
+        assert!(!path.exists());
+        // Off and never turned on: nothing to unlock.
+        assert!(!unlock(&EncryptionConfig::default(), &path)
+            .unwrap()
+            .is_enabled());
+    }
+
+    #[test]
+    fn reads_the_settings() {
+        let layers = Layers::parse(
+            "phoenix.toml",
+            "[encryption]\nenabled = true\nkey_source = \"keyring\"",
+            &Overrides::default(),
+        )
+        .unwrap();
+        let config = EncryptionConfig::from_layers(&layers).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.key_source, KeySource::Keyring);
+
+        let layers = Layers::parse(
+            "phoenix.toml",
+            "[encryption]\nkey_source = \"tpm\"",
+            &Overrides::default(),
+        )
+        .unwrap();
+        assert!(EncryptionConfig::from_layers(&layers).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Errors as frontends see them.
+//!
+//! The recorder, the HTTP API and the desktop commands each keep their own error enums; at the
+//! boundary they become a [`PagiError`]: a stable [`ErrorCode`] to branch on, a message fit to
+//! show the user, and optionally a diagnostic (the underlying cause, which may name paths or
+//! internal state) for logs and bug reports.
+//!
+//! Codes serialize as snake_case strings (`"guest_mode"`, `"not_found"`). Once shipped a code is
+//! never renamed or reused; new kinds get new codes.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// An argument or setting was out of range or malformed.
+    InvalidArgument,
+    /// Several request fields were rejected at once (the HTTP body lists them).
+    ValidationFailed,
+    /// A request body wasn't JSON of the expected shape.
+    InvalidJson,
+    NotFound,
+    /// The thing exists but is in the wrong state, e.g. already running or paused.
+    Conflict,
+    /// No or unknown credentials.
+    Unauthorized,
+    /// Valid credentials without the needed permission.
+    Forbidden,
+    RateLimited,
+    /// Guest mode is on and the action is disabled while it is.
+    GuestMode,
+    /// This build or install lacks the feature (e.g. no camera support compiled in).
+    FeatureDisabled,
+    /// A destructive action needs its confirmation token first.
+    ConfirmationRequired,
+    ShuttingDown,
+    /// A subsystem that isn't running right now.
+    Unavailable,
+    /// The local database failed.
+    Storage,
+    /// Reading or writing a file failed.
+    Io,
+    Internal,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 16] = [
+        Self::InvalidArgument,
+        Self::ValidationFailed,
+        Self::InvalidJson,
+        Self::NotFound,
+        Self::Conflict,
+        Self::Unauthorized,
+        Self::Forbidden,
+        Self::RateLimited,
+        Self::GuestMode,
+        Self::FeatureDisabled,
+        Self::ConfirmationRequired,
+        Self::ShuttingDown,
+        Self::Unavailable,
+        Self::Storage,
+        Self::Io,
+        Self::Internal,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidArgument => "invalid_argument",
+            Self::ValidationFailed => "validation_failed",
+            Self::InvalidJson => "invalid_json",
+            Self::NotFound => "not_found",
+            Self::Conflict => "conflict",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::RateLimited => "rate_limited",
+            Self::GuestMode => "guest_mode",
+            Self::FeatureDisabled => "feature_disabled",
+            Self::ConfirmationRequired => "confirmation_required",
+            Self::ShuttingDown => "shutting_down",
+            Self::Unavailable => "unavailable",
+            Self::Storage => "storage",
+            Self::Io => "io",
+            Self::Internal => "internal",
+        }
+    }
+
+    /// The HTTP status an error with this code is answered with.
+    pub fn http_status(self) -> u16 {
+        match self {
+            Self::InvalidArgument | Self::ValidationFailed | Self::InvalidJson => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden | Self::GuestMode => 403,
+            Self::NotFound => 404,
+            Self::Conflict | Self::ConfirmationRequired => 409,
+            Self::RateLimited => 429,
+            Self::FeatureDisabled => 501,
+            Self::ShuttingDown | Self::Unavailable => 503,
+            Self::Storage | Self::Io | Self::Internal => 500,
+        }
+    }
+
+    /// The closest code for an HTTP status, for errors that only carry a status.
+    pub fn for_http_status(status: u16) -> Self {
+        match status {
+            401 => Self::Unauthorized,
+            403 => Self::Forbidden,
+            404 => Self::NotFound,
+            409 => Self::Conflict,
+            429 => Self::RateLimited,
+            501 => Self::FeatureDisabled,
+            503 => Self::Unavailable,
+            400..=499 => Self::InvalidArgument,
+            _ => Self::Internal,
+        }
+    }
+
+    /// What to tell the user when the error brings no message of its own.
+    pub fn default_message(self) -> &'static str {
+        match self {
+            Self::InvalidArgument => "The request had an invalid value.",
+            Self::ValidationFailed => "Some fields were not accepted.",
+            Self::InvalidJson => "The request body could not be read.",
+            Self::NotFound => "That doesn't exist (anymore).",
+            Self::Conflict => "That can't be done right now.",
+            Self::Unauthorized => "Sign in or provide an API key.",
+            Self::Forbidden => "You don't have permission to do that.",
+            Self::RateLimited => "Too many requests; try again shortly.",
+            Self::GuestMode => "That is disabled while guest mode is on.",
+            Self::FeatureDisabled => "This install doesn't support that.",
+            Self::ConfirmationRequired => "Please confirm first.",
+            Self::ShuttingDown => "The app is shutting down.",
+            Self::Unavailable => "That service isn't running.",
+            Self::Storage => "The local database could not be read or written.",
+            Self::Io => "A file could not be read or written.",
+            Self::Internal => "Something went wrong.",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error ready to hand to a frontend; see the module docs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[error("{message}")]
+pub struct PagiError {
+    pub code: ErrorCode,
+    /// Safe to show the user as is.
+    pub message: String,
+    /// The underlying cause, for logs and bug reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostic: Option<String>,
+}
+
+impl PagiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            diagnostic: None,
+        }
+    }
+
+    /// The code's [default message](ErrorCode::default_message), keeping `cause` as the
+    /// diagnostic.
+    pub fn from_cause(code: ErrorCode, cause: impl fmt::Display) -> Self {
+        Self::new(code, code.default_message()).with_diagnostic(cause)
+    }
+
+    pub fn internal(cause: impl fmt::Display) -> Self {
+        Self::from_cause(ErrorCode::Internal, cause)
+    }
+
+    pub fn with_diagnostic(mut self, diagnostic: impl fmt::Display) -> Self {
+        self.diagnostic = Some(diagnostic.to_string());
+        self
+    }
+}
+
+impl From<std::io::Error> for PagiError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::from_cause(ErrorCode::NotFound, e),
+            _ => Self::from_cause(ErrorCode::Io, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_serialize_as_their_stable_names() {
+        for code in ErrorCode::ALL {
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, format!("\"{}\"", code.as_str()));
+            assert_eq!(serde_json::from_str::<ErrorCode>(&json).unwrap(), code);
+        }
+        assert_eq!(ErrorCode::for_http_status(422), ErrorCode::InvalidArgument);
+        assert_eq!(ErrorCode::for_http_status(502), ErrorCode::Internal);
+        assert_eq!(
+            ErrorCode::for_http_status(ErrorCode::Conflict.http_status()),
+            ErrorCode::Conflict
+        );
+    }
+
+    #[test]
+    fn diagnostics_stay_out_of_the_message() {
+        let e = PagiError::from(std::io::Error::other("/home/me/data/recs is read-only"));
+        assert_eq!(e.code, ErrorCode::Io);
+        assert_eq!(e.to_string(), ErrorCode::Io.default_message());
+        assert_eq!(
+            serde_json::to_value(&e).unwrap(),
+            serde_json::json!({
+                "code": "io",
+                "message": "A file could not be read or written.",
+                "diagnostic": "/home/me/data/recs is read-only",
+            })
+        );
+        let plain = serde_json::to_value(PagiError::new(
+            ErrorCode::GuestMode,
+            "No recordings in guest mode.",
+        ))
+        .unwrap();
+        assert!(plain.get("diagnostic").is_none());
+    }
+}
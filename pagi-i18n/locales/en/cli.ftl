@@ -0,0 +1,33 @@
+# Output of the `pagi` command-line client.
+
+cli-error = pagi: { $error }
+cli-no-recordings = No recordings.
+cli-recordings-header-created = CREATED
+cli-recordings-header-mode = MODE
+cli-recordings-header-seconds = SECONDS
+cli-recordings-header-purpose = PURPOSE
+cli-recordings-header-path = PATH
+cli-recordings-more = ({ $shown } of { $total }; raise --limit for more)
+cli-recording = Recording { $seconds }s...
+cli-ghost-scores = resonance { $resonance }  risk { $risk }
+cli-ghost-paused = The simulation was paused for safety.
+cli-ghost-flag = flag: { $flag }
+cli-ghost-suggestion = try: { $suggestion }
+cli-secret-prompt = Value for { $name }:{" "}
+cli-secret-saved = Saved { $name }; refer to it in settings as keyring:{ $name }
+cli-secret-deleted = Deleted { $name }.
+cli-import-dry-run = Dry run; nothing was changed.
+cli-import-section = { $section }: { $added } added, { $unchanged } unchanged, { $conflicts } conflicts ({ $replaced } replaced), { $skipped } skipped
+cli-import-conflicting = {"  "}conflicting: { $keys }
+cli-import-note = note: { $note }
+cli-sync-device = This device: { $device }, { $records } records tracked
+cli-sync-peer-never = { $url }: not synced yet
+cli-sync-peer-ok = { $url }: synced { $time }, { $message }
+cli-sync-peer-failed = { $url }: failed { $time }: { $message }
+cli-sync-queued = Sync started; run `pagi sync` in a moment for the results.
+cli-companion-scan = Scan this with the companion app:
+cli-companion-expires = The code works once, until { $time }.
+cli-companion-none = No phones are paired; pair one with `pagi companion pair`.
+cli-companion-never = never
+cli-companion-device = { $id }  { $name }, last seen { $seen }, { $alerts } alerts waiting
+cli-companion-unpaired = Unpaired { $device }.
@@ -0,0 +1,81 @@
+# Desktop app strings originating in the backend (tray, notifications, status lines).
+# Keep message ids in sync with the other locales; missing ones fall back to English.
+
+## Tray menu
+
+tray-show = Show Window
+tray-hide = Hide Window
+tray-guest-mode = Guest Mode
+tray-record-audio = Record 1 min audio
+tray-record-av = Record 5 min AV
+tray-always-listening = Toggle always listening
+tray-mini-recorder = Mini recorder
+tray-no-updates = No updates
+tray-install-update = Install update { $version }
+tray-installing-update = Installing update...
+tray-quit = Quit
+
+## Tray status line and tooltip
+
+activity-recording = Recording
+activity-guest = Guest mode
+activity-live = Live
+activity-listening = Listening
+activity-idle = Idle
+tray-status = Status: { $activity }
+tray-tooltip = Sola AGI - { $activity } | listening { $listening ->
+        [on] on
+       *[off] off
+    } | { $mood }
+tray-tooltip-startup = Sola AGI - v{ $version }
+mood-uncertain = uncertain
+mood-none = no emotion yet
+mood-reading = { $label } ({ $confidence }%)
+
+## Emotion status
+
+emotion-uncertain = { $who } is feeling: uncertain
+emotion-reading = { $who } is feeling: { $label } ({ $confidence }%) ❤️
+emotion-neutral = { $who } is feeling: Neutral
+
+## Notifications
+
+recording-saved = Recording saved
+recording-saved-body = { $seconds }s recording finished.
+recording-failed = Recording failed
+update-ready = Update ready
+update-ready-body = Version { $version } is ready to install from the tray menu.
+update-failed = Update failed
+presence-unknown = Someone new is here
+presence-unknown-body = An unrecognized person was detected. Open to enroll them.
+checking-in = Checking in
+alert-sustained = { $emotion } has been showing for over { $minutes } minutes.
+alert-recurring = { $emotion } has come up { $count ->
+        [one] once
+       *[other] { $count } times
+    } today.
+scheduled-recording = Scheduled recording
+scheduled-recording-purpose = Scheduled recording: { $purpose }
+scheduled-recording-saved = Saved.
+scheduled-recording-failed = Failed: { $error }
+mood-summary = Your day in moods
+listening-paused = Listening paused
+listening-paused-body = You seem to be away; listening will resume when you're back.
+listening-resumed = Listening resumed
+listening-resumed-body = Welcome back. Always-listening is on again.
+clipboard-failed = Couldn't analyze the clipboard
+link-unsupported = Unsupported link
+link-confirm = Allow this link?
+link-confirm-record = A link asks Sola to record { $what ->
+        [audio] audio
+        [video] video
+       *[av] audio and video
+    } for { $seconds } seconds. Only allow it if you opened the link yourself.
+link-confirm-listening = A link asks Sola to turn always-listening on. Only allow it if you opened the link yourself.
+link-confirm-allow = Allow
+link-confirm-deny = Don't allow
+crash-notice = Sola closed unexpectedly last time
+crash-notice-body = A crash report ({ $subsystem }) was saved on this computer. Open it to view or send it.
+settings-reset = Your settings couldn't be loaded
+settings-reset-body = Sola started with the default settings. The old settings file was kept as { $backup }.
+settings-unsaved-body = Sola started with the default settings and won't save changes until this is fixed: { $error }
@@ -0,0 +1,21 @@
+# Server error titles (the `title` of problem details), by HTTP status.
+# Statuses without a message keep their standard English reason phrase.
+
+http-400 = Bad Request
+http-401 = Unauthorized
+http-403 = Forbidden
+http-404 = Not Found
+http-405 = Method Not Allowed
+http-406 = Not Acceptable
+http-408 = Request Timeout
+http-409 = Conflict
+http-410 = Gone
+http-413 = Payload Too Large
+http-415 = Unsupported Media Type
+http-422 = Unprocessable Entity
+http-429 = Too Many Requests
+http-500 = Internal Server Error
+http-501 = Not Implemented
+http-502 = Bad Gateway
+http-503 = Service Unavailable
+http-504 = Gateway Timeout
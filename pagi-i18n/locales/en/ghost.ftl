@@ -0,0 +1,58 @@
+# Relational Ghost: persona replies, NVC breach notes and coaching.
+# Keep message ids in sync with the other locales; missing ones fall back to English.
+
+## Breaches
+
+breach-absolute = Absolutes can be heard as character judgments. Swap for a specific recent instance.
+breach-directive = Directive language often triggers defensiveness. Try an invitational request (e.g., ‘Would you be willing to…’).
+breach-blame = This reads as blame. Try: ‘When I notice…, I feel…, because I need… Would you be willing to…’
+breach-you-statement = ‘You are…’ often lands as evaluation. Try describing an observable behavior instead.
+
+## Persona replies
+## clear: the script resonates; mixed: partly; judged: it lands as criticism.
+## pressed: high intensity; hot: very high intensity.
+
+ghost-secure-clear-pressed = I can hear this matters. I want to understand, but I need us to stay respectful. What’s the specific request?
+ghost-secure-clear = I appreciate you being clear. Let’s talk—what time works for a short check-in?
+ghost-secure-mixed-pressed = I’m starting to feel some heat here. Can we slow down and restate this as what you noticed, how you feel, and what you’re asking for?
+ghost-secure-mixed = I hear you, and I want to get this right. Can you tell me what you need most right now?
+ghost-secure-hot = This is landing as blame/criticism and I’m shutting down a bit. I’m going to pause and come back when we can reframe it as an observation + request.
+ghost-secure-judged = That felt like a judgment. Can you rephrase as an observation and a request so I can respond?
+
+ghost-avoidant-clear-pressed = Ok. Keep it short. What’s the one request—and how much time will this take?
+ghost-avoidant-clear = I hear you. I can do a short check-in. What’s the one thing you want from me?
+ghost-avoidant-mixed-pressed = This is starting to feel like pressure. I’m going to need space right now. If you can send one clear request with options, I’ll respond.
+ghost-avoidant-mixed = This feels like a lot. Can we schedule 10 minutes later instead of doing this right now?
+ghost-avoidant-hot = No response. (Withdrawn — avoidant persona disengages under high pressure.)
+ghost-avoidant-judged = This feels like criticism. I’m stepping back. If you can keep it to an observation and a request, I’ll revisit.
+
+ghost-anxious-clear-pressed = Thank you for saying it plainly. I’m a little activated, but I want to stay connected—are we okay? When can we talk?
+ghost-anxious-clear = Thank you for being clear. I want to reconnect too. Are we okay? Let’s talk tonight.
+ghost-anxious-mixed-pressed = I feel attacked and scared. Do you still want us? I need reassurance and a clear plan for when we’ll talk.
+ghost-anxious-mixed = I’m getting nervous. Can you reassure me and say what you’re asking for?
+ghost-anxious-hot = I’m panicking a bit. This feels like you’re pulling away and blaming me. Please tell me we’re okay and what you want me to do.
+ghost-anxious-judged = That’s landing as a judgment. Can you rephrase it gently and tell me what you need?
+# After another speaker withdrew.
+ghost-anxious-chase = Wait—{ $speaker } going quiet is really activating for me. Are we okay? I need reassurance and a specific time we’ll reconnect, even if it’s just 10 minutes.
+
+ghost-fearful-clear-hot = Thank you for being clear. I want to stay connected, but I’m getting scared and tense. Can we keep this gentle for 10 minutes and then pause if needed?
+ghost-fearful-clear-pressed = I hear you. I want to work on this, but I’m feeling activated—can we slow down and keep it to one request?
+ghost-fearful-clear = I appreciate you saying it clearly. I want to talk—can we do a short calm check-in and take breaks if either of us gets flooded?
+ghost-fearful-mixed-hot = I’m overwhelmed and on edge. I don’t want to fight—can you reassure me what you want between us and make one clear request?
+ghost-fearful-mixed-pressed = I’m starting to feel unsafe/defensive. Can we restate this as an observation + feeling + request, and agree on a time limit?
+ghost-fearful-mixed = I’m trying to hear you, but I’m getting overwhelmed. Can you reassure me you want connection and then say the request?
+ghost-fearful-hot = I’m shutting down and also panicking. I’m going to step back. If you can rephrase as an observation + feeling + request, I can re-engage later.
+ghost-fearful-judged = This is landing as criticism. I need a softer reframe (observation + feeling + need) and one doable request.
+
+## Safety interlock
+
+ghost-mediator-speaker = External Mediator (Sola)
+ghost-mediator = Pause. Group stress is high. I’m stepping in as an external mediator. Let’s take 60 seconds, lower intensity, and restate one observation + one request before continuing.
+
+## Coaching for the user's own emotional state
+
+coach-anger = You seem angry right now. Consider a 20-minute pause before sending; heated messages tend to land as attacks.
+coach-sadness = You seem low right now. It's okay to name that directly ('I'm feeling sad about…') and ask for comfort, not just a fix.
+coach-fear = You seem anxious. Ground first (slow exhale, feet on the floor), then keep the request small and specific.
+coach-jealousy = Jealousy is showing up. Try owning it ('I notice I feel insecure when…') instead of asking them to change who they talk to.
+coach-disgust = Contempt-adjacent language predicts escalation. Swap judgments for a concrete observation.
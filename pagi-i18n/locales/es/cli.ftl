@@ -0,0 +1,33 @@
+# Salida del cliente de línea de órdenes `pagi`.
+
+cli-error = pagi: { $error }
+cli-no-recordings = No hay grabaciones.
+cli-recordings-header-created = CREADA
+cli-recordings-header-mode = MODO
+cli-recordings-header-seconds = SEGUNDOS
+cli-recordings-header-purpose = MOTIVO
+cli-recordings-header-path = RUTA
+cli-recordings-more = ({ $shown } de { $total }; sube --limit para ver más)
+cli-recording = Grabando { $seconds } s...
+cli-ghost-scores = resonancia { $resonance }  riesgo { $risk }
+cli-ghost-paused = La simulación se pausó por seguridad.
+cli-ghost-flag = aviso: { $flag }
+cli-ghost-suggestion = prueba: { $suggestion }
+cli-secret-prompt = Valor de { $name }:{" "}
+cli-secret-saved = { $name } guardado; en la configuración, úsalo como keyring:{ $name }
+cli-secret-deleted = { $name } eliminado.
+cli-import-dry-run = Simulación; no se cambió nada.
+cli-import-section = { $section }: { $added } añadidos, { $unchanged } sin cambios, { $conflicts } conflictos ({ $replaced } reemplazados), { $skipped } omitidos
+cli-import-conflicting = {"  "}en conflicto: { $keys }
+cli-import-note = nota: { $note }
+cli-sync-device = Este dispositivo: { $device }, { $records } registros seguidos
+cli-sync-peer-never = { $url }: aún sin sincronizar
+cli-sync-peer-ok = { $url }: sincronizado { $time }, { $message }
+cli-sync-peer-failed = { $url }: falló { $time }: { $message }
+cli-sync-queued = Sincronización iniciada; ejecuta `pagi sync` en un momento para ver el resultado.
+cli-companion-scan = Escanea esto con la app complementaria:
+cli-companion-expires = El código sirve una vez, hasta las { $time }.
+cli-companion-none = No hay teléfonos emparejados; empareja uno con `pagi companion pair`.
+cli-companion-never = nunca
+cli-companion-device = { $id }  { $name }, visto por última vez { $seen }, { $alerts } alertas pendientes
+cli-companion-unpaired = { $device } desemparejado.
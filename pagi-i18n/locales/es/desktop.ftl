@@ -0,0 +1,80 @@
+# Textos de la aplicación de escritorio generados por el backend (bandeja, notificaciones, líneas de estado).
+
+## Menú de la bandeja
+
+tray-show = Mostrar ventana
+tray-hide = Ocultar ventana
+tray-guest-mode = Modo invitado
+tray-record-audio = Grabar 1 min de audio
+tray-record-av = Grabar 5 min de audio y vídeo
+tray-always-listening = Activar/desactivar escucha continua
+tray-mini-recorder = Minigrabadora
+tray-no-updates = Sin actualizaciones
+tray-install-update = Instalar actualización { $version }
+tray-installing-update = Instalando actualización...
+tray-quit = Salir
+
+## Línea de estado y tooltip
+
+activity-recording = Grabando
+activity-guest = Modo invitado
+activity-live = En directo
+activity-listening = Escuchando
+activity-idle = Inactivo
+tray-status = Estado: { $activity }
+tray-tooltip = Sola AGI - { $activity } | escucha { $listening ->
+        [on] activada
+       *[off] desactivada
+    } | { $mood }
+tray-tooltip-startup = Sola AGI - v{ $version }
+mood-uncertain = incierto
+mood-none = sin emoción todavía
+mood-reading = { $label } ({ $confidence } %)
+
+## Estado emocional
+
+emotion-uncertain = { $who } se siente: incierto
+emotion-reading = { $who } se siente: { $label } ({ $confidence } %) ❤️
+emotion-neutral = { $who } se siente: neutral
+
+## Notificaciones
+
+recording-saved = Grabación guardada
+recording-saved-body = Grabación de { $seconds } s terminada.
+recording-failed = La grabación falló
+update-ready = Actualización lista
+update-ready-body = La versión { $version } está lista para instalarse desde el menú de la bandeja.
+update-failed = La actualización falló
+presence-unknown = Hay alguien nuevo
+presence-unknown-body = Se detectó a una persona no reconocida. Abre para registrarla.
+checking-in = ¿Cómo estás?
+alert-sustained = { $emotion } lleva más de { $minutes } minutos.
+alert-recurring = { $emotion } ha aparecido { $count ->
+        [one] una vez
+       *[other] { $count } veces
+    } hoy.
+scheduled-recording = Grabación programada
+scheduled-recording-purpose = Grabación programada: { $purpose }
+scheduled-recording-saved = Guardada.
+scheduled-recording-failed = Falló: { $error }
+mood-summary = Tu día en emociones
+listening-paused = Escucha en pausa
+listening-paused-body = Parece que no estás; la escucha se reanudará cuando vuelvas.
+listening-resumed = Escucha reanudada
+listening-resumed-body = Bienvenido de nuevo. La escucha continua vuelve a estar activa.
+clipboard-failed = No se pudo analizar el portapapeles
+link-unsupported = Enlace no compatible
+link-confirm = ¿Permitir este enlace?
+link-confirm-record = Un enlace pide a Sola que grabe { $what ->
+        [audio] audio
+        [video] vídeo
+       *[av] audio y vídeo
+    } durante { $seconds } segundos. Permítelo solo si abriste el enlace tú.
+link-confirm-listening = Un enlace pide a Sola que active la escucha continua. Permítelo solo si abriste el enlace tú.
+link-confirm-allow = Permitir
+link-confirm-deny = No permitir
+crash-notice = Sola se cerró inesperadamente la última vez
+crash-notice-body = Se guardó un informe de fallo ({ $subsystem }) en este equipo. Ábrelo para verlo o enviarlo.
+settings-reset = No se pudieron cargar tus ajustes
+settings-reset-body = Sola se inició con los ajustes predeterminados. El archivo de ajustes anterior se guardó como { $backup }.
+settings-unsaved-body = Sola se inició con los ajustes predeterminados y no guardará cambios hasta que se resuelva: { $error }
@@ -0,0 +1,20 @@
+# Títulos de los errores del servidor, por código HTTP.
+
+http-400 = Solicitud incorrecta
+http-401 = No autorizado
+http-403 = Prohibido
+http-404 = No encontrado
+http-405 = Método no permitido
+http-406 = No aceptable
+http-408 = Tiempo de espera agotado
+http-409 = Conflicto
+http-410 = Ya no disponible
+http-413 = Contenido demasiado grande
+http-415 = Tipo de contenido no admitido
+http-422 = Entidad no procesable
+http-429 = Demasiadas solicitudes
+http-500 = Error interno del servidor
+http-501 = No implementado
+http-502 = Puerta de enlace incorrecta
+http-503 = Servicio no disponible
+http-504 = Tiempo de espera de la puerta de enlace agotado
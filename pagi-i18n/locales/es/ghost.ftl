@@ -0,0 +1,54 @@
+# Relational Ghost: respuestas de los perfiles, avisos de CNV y sugerencias.
+
+## Infracciones
+
+breach-absolute = Los absolutos pueden sonar a juicios sobre la persona. Cámbialos por un ejemplo concreto y reciente.
+breach-directive = Las órdenes suelen provocar una actitud defensiva. Prueba con una petición abierta (p. ej., ‘¿Estarías dispuesto a…?’).
+breach-blame = Esto suena a culpa. Prueba: ‘Cuando noto…, me siento…, porque necesito… ¿Estarías dispuesto a…?’
+breach-you-statement = ‘Eres…’ suele sonar a evaluación. Prueba a describir una conducta observable.
+
+## Respuestas de los perfiles
+
+ghost-secure-clear-pressed = Veo que esto es importante. Quiero entenderte, pero necesito que sigamos siendo respetuosos. ¿Cuál es la petición concreta?
+ghost-secure-clear = Te agradezco que seas claro. Hablemos: ¿a qué hora te va bien una conversación breve?
+ghost-secure-mixed-pressed = Empiezo a notar tensión. ¿Podemos ir más despacio y decirlo como lo que notaste, cómo te sientes y qué pides?
+ghost-secure-mixed = Te escucho y quiero hacerlo bien. ¿Puedes decirme qué necesitas más ahora mismo?
+ghost-secure-hot = Esto me llega como culpa o crítica y me estoy cerrando un poco. Voy a hacer una pausa y volveré cuando podamos plantearlo como una observación + una petición.
+ghost-secure-judged = Eso me ha sonado a juicio. ¿Puedes reformularlo como una observación y una petición para que pueda responder?
+
+ghost-avoidant-clear-pressed = Vale. Que sea breve. ¿Cuál es la petición, y cuánto tiempo nos va a llevar?
+ghost-avoidant-clear = Te escucho. Puedo hablar un momento. ¿Qué es lo único que quieres de mí?
+ghost-avoidant-mixed-pressed = Esto empieza a parecer presión. Ahora mismo necesito espacio. Si me mandas una petición clara con opciones, te responderé.
+ghost-avoidant-mixed = Esto es mucho. ¿Podemos dedicarle 10 minutos más tarde en lugar de ahora?
+ghost-avoidant-hot = Sin respuesta. (Retirada: el perfil evitativo se desconecta bajo mucha presión).
+ghost-avoidant-judged = Esto me parece una crítica. Me aparto. Si puedes limitarlo a una observación y una petición, lo retomaré.
+
+ghost-anxious-clear-pressed = Gracias por decirlo sin rodeos. Estoy un poco activado, pero quiero seguir conectado: ¿estamos bien? ¿Cuándo podemos hablar?
+ghost-anxious-clear = Gracias por ser claro. Yo también quiero reconectar. ¿Estamos bien? Hablemos esta noche.
+ghost-anxious-mixed-pressed = Me siento atacado y con miedo. ¿Todavía quieres lo nuestro? Necesito que me tranquilices y un plan claro de cuándo hablaremos.
+ghost-anxious-mixed = Me estoy poniendo nervioso. ¿Puedes tranquilizarme y decirme qué me pides?
+ghost-anxious-hot = Estoy entrando un poco en pánico. Siento que te alejas y que me culpas. Por favor, dime que estamos bien y qué quieres que haga.
+ghost-anxious-judged = Eso me llega como un juicio. ¿Puedes decirlo con más suavidad y contarme qué necesitas?
+ghost-anxious-chase = Espera: que { $speaker } se quede en silencio me activa mucho. ¿Estamos bien? Necesito que me tranquilices y una hora concreta para volver a hablar, aunque sean 10 minutos.
+
+ghost-fearful-clear-hot = Gracias por ser claro. Quiero seguir conectado, pero me estoy asustando y poniendo tenso. ¿Podemos mantenerlo suave 10 minutos y hacer una pausa si hace falta?
+ghost-fearful-clear-pressed = Te escucho. Quiero trabajar en esto, pero me siento activado: ¿podemos ir más despacio y limitarnos a una petición?
+ghost-fearful-clear = Te agradezco que lo digas con claridad. Quiero hablar: ¿hacemos una conversación breve y tranquila, con pausas si alguno se desborda?
+ghost-fearful-mixed-hot = Estoy desbordado y a la defensiva. No quiero pelear: ¿puedes asegurarme qué quieres entre nosotros y hacer una petición clara?
+ghost-fearful-mixed-pressed = Empiezo a sentirme inseguro y a la defensiva. ¿Podemos decirlo como observación + sentimiento + petición, y acordar un límite de tiempo?
+ghost-fearful-mixed = Intento escucharte, pero me estoy desbordando. ¿Puedes asegurarme que quieres conexión y luego decir la petición?
+ghost-fearful-hot = Me estoy cerrando y a la vez entrando en pánico. Voy a apartarme. Si puedes reformularlo como observación + sentimiento + petición, podré volver más tarde.
+ghost-fearful-judged = Esto me llega como una crítica. Necesito un planteamiento más suave (observación + sentimiento + necesidad) y una petición asumible.
+
+## Interbloqueo de seguridad
+
+ghost-mediator-speaker = Mediadora externa (Sola)
+ghost-mediator = Pausa. La tensión del grupo es alta. Intervengo como mediadora externa. Tomemos 60 segundos, bajemos la intensidad y digamos una observación + una petición antes de seguir.
+
+## Sugerencias según el estado emocional del usuario
+
+coach-anger = Pareces enfadado ahora mismo. Plantéate una pausa de 20 minutos antes de enviar; los mensajes acalorados suelen sonar a ataque.
+coach-sadness = Pareces decaído. Está bien decirlo directamente (‘Me siento triste por…’) y pedir consuelo, no solo una solución.
+coach-fear = Pareces ansioso. Primero céntrate (exhala despacio, pies en el suelo) y luego haz una petición pequeña y concreta.
+coach-jealousy = Aparecen los celos. Prueba a reconocerlos (‘Noto que me siento inseguro cuando…’) en lugar de pedir que cambie con quién habla.
+coach-disgust = El lenguaje cercano al desprecio anticipa una escalada. Cambia los juicios por una observación concreta.
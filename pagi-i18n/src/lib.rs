@@ -0,0 +1,343 @@
+//! Localized strings shared by the server, the `pagi` client and the desktop app.
+//!
+//! Messages are [Fluent](https://projectfluent.org) files under `locales/<lang>/`, one per area
+//! (`ghost.ftl` for Relational Ghost replies, breach notes and coaching, `errors.ftl` for server
+//! error titles, `cli.ftl`, `desktop.ftl`), embedded at build time. Code refers to messages by id
+//! only, so adding a language is a matter of translating the files and listing them in
+//! [`LOCALES`]. A message missing from a language falls back to English, and one missing from
+//! English to its id.
+//!
+//! Requests carry their own language ([`message`], with [`from_accept_language`] for HTTP);
+//! [`t`] and [`t_args`] use the process-wide one from [`set_language`], for the CLI and the
+//! desktop app.
+
+use std::sync::{OnceLock, RwLock};
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use pagi_config::{ConfigError, Layers};
+use serde::Serialize;
+use tracing::warn;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentValue;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// A language and its message files, `(area, source)`.
+pub struct Locale {
+    pub code: &'static str,
+    /// The language's name in itself.
+    pub name: &'static str,
+    sources: &'static [(&'static str, &'static str)],
+}
+
+macro_rules! locale {
+    ($code:literal, $name:literal) => {
+        Locale {
+            code: $code,
+            name: $name,
+            sources: &[
+                (
+                    "cli",
+                    include_str!(concat!("../locales/", $code, "/cli.ftl")),
+                ),
+                (
+                    "desktop",
+                    include_str!(concat!("../locales/", $code, "/desktop.ftl")),
+                ),
+                (
+                    "errors",
+                    include_str!(concat!("../locales/", $code, "/errors.ftl")),
+                ),
+                (
+                    "ghost",
+                    include_str!(concat!("../locales/", $code, "/ghost.ftl")),
+                ),
+            ],
+        }
+    };
+}
+
+/// Every supported language; English first, as the fallback.
+pub const LOCALES: &[Locale] = &[locale!("en", "English"), locale!("es", "Español")];
+
+#[derive(Debug, thiserror::Error)]
+#[error("unsupported language '{tag}' (available: {available})")]
+pub struct UnsupportedLanguage {
+    pub tag: String,
+    pub available: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageInfo {
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+/// The `[i18n]` settings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct I18nConfig {
+    /// `None` follows the system locale.
+    pub language: Option<String>,
+}
+
+impl I18nConfig {
+    /// The `[i18n]` table: `language`, a code from [`LOCALES`] or a tag like `es-MX`.
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let language = layers.typed("i18n.language", |s| {
+            validate(Some(s)).map_err(|e| e.to_string())?;
+            Ok(s.to_string())
+        })?;
+        Ok(Self {
+            language: language.map(|(language, _)| language),
+        })
+    }
+}
+
+type Bundle = FluentBundle<FluentResource>;
+
+fn bundle(locale: &Locale) -> Bundle {
+    let langid = locale
+        .code
+        .parse::<LanguageIdentifier>()
+        .unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Isolation marks show up as stray characters in terminals, tray menus and notifications.
+    bundle.set_use_isolating(false);
+    for (area, source) in locale.sources {
+        let language = locale.code;
+        let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(|(res, e)| {
+            warn!(target: "i18n", language, "{} syntax errors in {area}.ftl", e.len());
+            res
+        });
+        if let Err(e) = bundle.add_resource(resource) {
+            warn!(target: "i18n", language, "{} duplicate messages in {area}.ftl", e.len());
+        }
+    }
+    bundle
+}
+
+/// One bundle per [`LOCALES`] entry, in the same order, built on first use.
+fn bundles() -> &'static [Bundle] {
+    static BUNDLES: OnceLock<Vec<Bundle>> = OnceLock::new();
+    BUNDLES.get_or_init(|| LOCALES.iter().map(bundle).collect())
+}
+
+fn bundle_for(code: &str) -> Option<&'static Bundle> {
+    let index = LOCALES.iter().position(|l| l.code == code)?;
+    bundles().get(index)
+}
+
+pub fn languages() -> Vec<LanguageInfo> {
+    LOCALES
+        .iter()
+        .map(|l| LanguageInfo {
+            code: l.code,
+            name: l.name,
+        })
+        .collect()
+}
+
+/// The supported language for a tag like `es-MX` / `es_MX.UTF-8`, if any.
+pub fn supported(tag: &str) -> Option<&'static str> {
+    let tag = tag
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    let langid = tag.parse::<LanguageIdentifier>().ok()?;
+    negotiate(&[langid])
+}
+
+/// The best supported language for `requested`, in order of preference.
+fn negotiate(requested: &[LanguageIdentifier]) -> Option<&'static str> {
+    let available: Vec<LanguageIdentifier> =
+        LOCALES.iter().filter_map(|l| l.code.parse().ok()).collect();
+    let best = negotiate_languages(requested, &available, None, NegotiationStrategy::Lookup);
+    let best = best.first()?.to_string();
+    LOCALES.iter().map(|l| l.code).find(|code| *code == best)
+}
+
+/// The best supported language in an `Accept-Language` header, honoring `q` weights; `None`
+/// when it names none of them.
+pub fn from_accept_language(header: &str) -> Option<&'static str> {
+    let mut ranked: Vec<(f32, LanguageIdentifier)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            if q <= 0.0 || tag == "*" {
+                return None;
+            }
+            Some((q, tag.parse().ok()?))
+        })
+        .collect();
+    // Stable, so equal weights keep the header's order.
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let requested: Vec<_> = ranked.into_iter().map(|(_, langid)| langid).collect();
+    negotiate(&requested)
+}
+
+/// The first supported language among the system's preferred ones.
+pub fn system_language() -> Option<&'static str> {
+    sys_locale::get_locales().find_map(|tag| supported(&tag))
+}
+
+/// Setting validation: an unknown language is rejected rather than silently ignored.
+pub fn validate(setting: Option<&str>) -> Result<(), UnsupportedLanguage> {
+    match setting {
+        Some(tag) if supported(tag).is_none() => Err(UnsupportedLanguage {
+            tag: tag.to_string(),
+            available: LOCALES
+                .iter()
+                .map(|l| l.code)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn format(bundle: &Bundle, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, args, &mut errors);
+    if !errors.is_empty() {
+        warn!(target: "i18n", id, "{errors:?}");
+    }
+    Some(text.into_owned())
+}
+
+/// The message `id` in `language` (a supported code) with `args`, or in English when
+/// `language` lacks it; `None` when English lacks it too.
+pub fn lookup(language: &str, id: &str, args: &[(&str, FluentValue)]) -> Option<String> {
+    let args = (!args.is_empty()).then(|| {
+        let mut fluent = FluentArgs::new();
+        for (name, value) in args {
+            fluent.set(*name, value.clone());
+        }
+        fluent
+    });
+    bundle_for(language)
+        .and_then(|b| format(b, id, args.as_ref()))
+        .or_else(|| format(&bundles()[0], id, args.as_ref()))
+}
+
+/// Like [`lookup`], but the id itself when no locale has the message.
+pub fn message(language: &str, id: &str, args: &[(&str, FluentValue)]) -> String {
+    lookup(language, id, args).unwrap_or_else(|| id.to_string())
+}
+
+static LANGUAGE: RwLock<&str> = RwLock::new(DEFAULT_LANGUAGE);
+
+/// Switch the process-wide language; `None` (or an unsupported tag) follows the system locale.
+/// Returns the language now in use.
+pub fn set_language(requested: Option<&str>) -> &'static str {
+    let language = requested
+        .and_then(supported)
+        .or_else(system_language)
+        .unwrap_or(DEFAULT_LANGUAGE);
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current = language;
+    }
+    language
+}
+
+/// The process-wide language.
+pub fn language() -> &'static str {
+    LANGUAGE.read().map(|l| *l).unwrap_or(DEFAULT_LANGUAGE)
+}
+
+/// The message `id` with `args` in the process-wide language.
+pub fn t_args(id: &str, args: &[(&str, FluentValue)]) -> String {
+    message(language(), id, args)
+}
+
+pub fn t(id: &str) -> String {
+    t_args(id, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use fluent_syntax::ast::Entry;
+    use pagi_config::Overrides;
+
+    use super::*;
+
+    #[test]
+    fn every_locale_has_the_english_messages() {
+        for (area, source) in LOCALES[0].sources {
+            let resource = FluentResource::try_new(source.to_string())
+                .unwrap_or_else(|(_, errors)| panic!("{area}.ftl: {errors:?}"));
+            let ids: Vec<&str> = resource
+                .entries()
+                .filter_map(|entry| match entry {
+                    Entry::Message(m) => Some(m.id.name),
+                    _ => None,
+                })
+                .collect();
+            assert!(!ids.is_empty(), "{area}.ftl is empty");
+            for (locale, bundle) in LOCALES.iter().zip(bundles()) {
+                for id in &ids {
+                    assert!(bundle.has_message(id), "{} is missing {id}", locale.code);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_regional_tags() {
+        assert_eq!(supported("es-MX"), Some("es"));
+        assert_eq!(supported("en_US.UTF-8"), Some("en"));
+        assert_eq!(supported("xx"), None);
+        assert!(validate(Some("fr")).is_err());
+        assert!(validate(None).is_ok());
+    }
+
+    #[test]
+    fn reads_accept_language() {
+        assert_eq!(from_accept_language("es-ES,es;q=0.9,en;q=0.8"), Some("es"));
+        assert_eq!(
+            from_accept_language("fr-FR, en;q=0.5, es;q=0.7"),
+            Some("es")
+        );
+        assert_eq!(from_accept_language("es;q=0, en"), Some("en"));
+        assert_eq!(from_accept_language("fr, *;q=0.1"), None);
+        assert_eq!(from_accept_language(""), None);
+    }
+
+    #[test]
+    fn falls_back_to_english_then_the_id() {
+        let text = message("es", "ghost-anxious-chase", &[("speaker", "Ana".into())]);
+        assert!(text.contains("Ana") && text.starts_with("Espera"));
+        assert_eq!(message("xx", "http-404", &[]), "Not Found");
+        assert_eq!(message("es", "no-such-message", &[]), "no-such-message");
+        assert_eq!(lookup("es", "no-such-message", &[]), None);
+    }
+
+    #[test]
+    fn language_setting_is_checked() {
+        let layers = Layers::parse(
+            "phoenix.toml",
+            "[i18n]\nlanguage = \"es-MX\"",
+            &Overrides::default(),
+        )
+        .unwrap();
+        let config = I18nConfig::from_layers(&layers).unwrap();
+        assert_eq!(config.language.as_deref(), Some("es-MX"));
+
+        let layers = Layers::parse(
+            "phoenix.toml",
+            "[i18n]\nlanguage = \"fr\"",
+            &Overrides::default(),
+        )
+        .unwrap();
+        let err = I18nConfig::from_layers(&layers).unwrap_err();
+        assert!(err.to_string().contains("i18n.language"));
+    }
+}
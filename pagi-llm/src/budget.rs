@@ -0,0 +1,151 @@
+//! Token limits: a cap on prompt size and an hourly allowance.
+//!
+//! Prompts are measured before sending with [`estimate_tokens`] (about four characters per
+//! token, close enough for English prose across the common tokenizers). A request reserves its
+//! prompt plus its `max_tokens` from the hour's allowance; once the backend reports real usage
+//! the reservation is settled to that.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{LlmError, Message};
+
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Per-message overhead for role markers and separators.
+const MESSAGE_OVERHEAD: u32 = 4;
+
+/// Rough token count of `messages`.
+pub fn estimate_tokens(messages: &[Message]) -> u32 {
+    messages
+        .iter()
+        .map(|m| (m.content.chars().count() as u32).div_ceil(4) + MESSAGE_OVERHEAD)
+        .sum()
+}
+
+struct Window {
+    started: Instant,
+    used: u32,
+}
+
+pub struct TokenBudget {
+    /// Largest prompt accepted; `0` = no limit.
+    max_prompt_tokens: u32,
+    /// Tokens per rolling hour; `0` = no limit.
+    per_hour: u32,
+    window: Mutex<Window>,
+}
+
+impl TokenBudget {
+    pub fn new(max_prompt_tokens: u32, per_hour: u32) -> Self {
+        Self {
+            max_prompt_tokens,
+            per_hour,
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                used: 0,
+            }),
+        }
+    }
+
+    /// Reserve `prompt_tokens + max_tokens`; returns the amount reserved.
+    pub fn reserve(&self, prompt_tokens: u32, max_tokens: u32) -> Result<u32, LlmError> {
+        self.reserve_at(Instant::now(), prompt_tokens, max_tokens)
+    }
+
+    fn reserve_at(
+        &self,
+        now: Instant,
+        prompt_tokens: u32,
+        max_tokens: u32,
+    ) -> Result<u32, LlmError> {
+        if self.max_prompt_tokens > 0 && prompt_tokens > self.max_prompt_tokens {
+            return Err(LlmError::PromptTooLong {
+                tokens: prompt_tokens,
+                max: self.max_prompt_tokens,
+            });
+        }
+        let needed = prompt_tokens.saturating_add(max_tokens);
+        if self.per_hour == 0 {
+            return Ok(needed);
+        }
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if now.duration_since(window.started) >= WINDOW {
+            *window = Window {
+                started: now,
+                used: 0,
+            };
+        }
+        let remaining = self.per_hour.saturating_sub(window.used);
+        if needed > remaining {
+            return Err(LlmError::BudgetExceeded { needed, remaining });
+        }
+        window.used += needed;
+        Ok(needed)
+    }
+
+    /// Replace a reservation with what the request actually used (`reserved` itself when the
+    /// backend didn't report usage, `0` when the request failed).
+    pub fn settle(&self, reserved: u32, used: u32) {
+        if self.per_hour == 0 {
+            return;
+        }
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        window.used = window.used.saturating_sub(reserved).saturating_add(used);
+    }
+
+    /// Tokens left this hour; `None` when there is no hourly limit.
+    pub fn remaining(&self) -> Option<u32> {
+        if self.per_hour == 0 {
+            return None;
+        }
+        let window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if window.started.elapsed() >= WINDOW {
+            return Some(self.per_hour);
+        }
+        Some(self.per_hour.saturating_sub(window.used))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompletionRequest;
+
+    #[test]
+    fn estimates_about_four_characters_per_token() {
+        let request = CompletionRequest::prompt("a".repeat(40)).with_system("be kind");
+        assert_eq!(estimate_tokens(&request.messages), (10 + 4) + (2 + 4));
+    }
+
+    #[test]
+    fn refuses_long_prompts_and_spends_the_hourly_allowance() {
+        let budget = TokenBudget::new(100, 500);
+        assert!(matches!(
+            budget.reserve(101, 0),
+            Err(LlmError::PromptTooLong {
+                tokens: 101,
+                max: 100
+            })
+        ));
+
+        let start = Instant::now();
+        let reserved = budget.reserve_at(start, 100, 200).unwrap();
+        assert_eq!(reserved, 300);
+        // The backend said it used less.
+        budget.settle(reserved, 120);
+        assert_eq!(budget.remaining(), Some(380));
+        assert!(matches!(
+            budget.reserve_at(start, 100, 300),
+            Err(LlmError::BudgetExceeded {
+                needed: 400,
+                remaining: 380
+            })
+        ));
+        // A new hour starts over.
+        assert!(budget.reserve_at(start + WINDOW, 100, 300).is_ok());
+
+        let unlimited = TokenBudget::new(0, 0);
+        assert!(unlimited.reserve(1_000_000, 1_000_000).is_ok());
+        assert_eq!(unlimited.remaining(), None);
+    }
+}
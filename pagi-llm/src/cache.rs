@@ -0,0 +1,134 @@
+//! Completions kept for a while, keyed by the backend, model and the whole request.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{Completion, CompletionRequest};
+
+struct Entries {
+    map: HashMap<String, (Instant, Completion)>,
+    /// Keys oldest first, for eviction.
+    order: VecDeque<String>,
+}
+
+pub struct ResponseCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    /// A zero `ttl` or `capacity` turns caching off.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(Entries {
+                map: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.capacity > 0
+    }
+
+    pub fn key(provider: &str, model: &str, request: &CompletionRequest) -> String {
+        let request = serde_json::to_string(request).unwrap_or_default();
+        format!("{provider}\n{model}\n{request}")
+    }
+
+    pub fn get(&self, key: &str) -> Option<Completion> {
+        if !self.enabled() {
+            return None;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.map.get(key) {
+            Some((stored, completion)) if stored.elapsed() < self.ttl => Some(Completion {
+                cached: true,
+                ..completion.clone()
+            }),
+            Some(_) => {
+                entries.map.remove(key);
+                entries.order.retain(|k| k != key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn put(&self, key: String, completion: &Completion) {
+        if !self.enabled() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries
+            .map
+            .insert(key.clone(), (Instant::now(), completion.clone()))
+            .is_some()
+        {
+            entries.order.retain(|k| *k != key);
+        }
+        entries.order.push_back(key);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.map.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .map
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(text: &str) -> Completion {
+        Completion {
+            text: text.to_string(),
+            model: "m".to_string(),
+            usage: None,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn hits_until_evicted_and_keys_on_the_whole_request() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 2);
+        let a = ResponseCache::key("ollama", "m", &CompletionRequest::prompt("a"));
+        let warmer = ResponseCache::key(
+            "ollama",
+            "m",
+            &CompletionRequest::prompt("a").with_temperature(0.9),
+        );
+        assert_ne!(a, warmer);
+
+        cache.put(a.clone(), &completion("first"));
+        let hit = cache.get(&a).unwrap();
+        assert!(hit.cached);
+        assert_eq!(hit.text, "first");
+        assert!(cache.get(&warmer).is_none());
+
+        cache.put("b".to_string(), &completion("b"));
+        cache.put("c".to_string(), &completion("c"));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&a).is_none());
+
+        let off = ResponseCache::new(Duration::ZERO, 10);
+        off.put(a.clone(), &completion("x"));
+        assert!(off.get(&a).is_none() && off.is_empty());
+    }
+}
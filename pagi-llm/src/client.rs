@@ -0,0 +1,312 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::budget::{estimate_tokens, TokenBudget};
+use crate::cache::ResponseCache;
+use crate::config::{LlmConfig, ProviderKind};
+use crate::{Completion, CompletionRequest, LlmError, Ollama, OpenAiCompatible, Provider};
+
+/// How long connecting may take, within the per-attempt timeout.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Extra attempts after the first.
+    pub retries: u32,
+    /// Wait before the first retry; doubled for each one after.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A [`Provider`] with timeouts, retries, the token budget and the cache; see the crate docs.
+pub struct LlmClient {
+    provider: Arc<dyn Provider>,
+    timeout: Duration,
+    retry: RetryPolicy,
+    budget: TokenBudget,
+    cache: ResponseCache,
+    temperature: f32,
+    max_tokens: u32,
+}
+
+impl LlmClient {
+    pub fn new(provider: Arc<dyn Provider>, config: &LlmConfig) -> Self {
+        Self {
+            provider,
+            timeout: config.timeout,
+            retry: RetryPolicy {
+                retries: config.retries,
+                ..RetryPolicy::default()
+            },
+            budget: TokenBudget::new(config.max_prompt_tokens, config.tokens_per_hour),
+            cache: ResponseCache::new(config.cache_ttl, config.cache_entries),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+        }
+    }
+
+    /// The provider `config` names. OpenRouter needs an API key; `local` needs the `local`
+    /// feature and its files on disk (see [`LlmClient::from_config_with`]).
+    pub fn from_config(config: &LlmConfig) -> Result<Self, LlmError> {
+        Self::from_config_with(config, &|_| None)
+    }
+
+    /// Like [`LlmClient::from_config`], looking up `local` model ids with `installed`.
+    pub fn from_config_with(
+        config: &LlmConfig,
+        installed: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<Self, LlmError> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .map_err(|e| LlmError::NotConfigured(e.to_string()))?;
+        let provider: Arc<dyn Provider> = match config.provider {
+            ProviderKind::Local => return Self::local(config, installed),
+            ProviderKind::Ollama => Arc::new(Ollama::new(http, &config.base_url, &config.model)),
+            ProviderKind::OpenRouter => {
+                let key = config.api_key.clone().ok_or_else(|| {
+                    LlmError::NotConfigured(
+                        "OPENROUTER_API_KEY (llm.api_key) is not set".to_string(),
+                    )
+                })?;
+                Arc::new(
+                    OpenAiCompatible::new(
+                        "openrouter",
+                        http,
+                        &config.base_url,
+                        Some(key),
+                        &config.model,
+                    )
+                    .with_header("HTTP-Referer", "https://github.com/phoenix-2.0")
+                    .with_header("X-Title", "Sola AGI"),
+                )
+            }
+            ProviderKind::OpenAi => Arc::new(OpenAiCompatible::new(
+                "openai",
+                http,
+                &config.base_url,
+                config.api_key.clone(),
+                &config.model,
+            )),
+        };
+        Ok(Self::new(provider, config))
+    }
+
+    /// A retry would queue behind the generation that timed out, so there are none.
+    #[cfg(feature = "local")]
+    fn local(
+        config: &LlmConfig,
+        installed: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<Self, LlmError> {
+        let (weights, tokenizer) = config.local_files(installed)?;
+        let model = crate::LocalModel::new(
+            &config.local_model,
+            &weights,
+            &tokenizer,
+            config.local_template,
+        )?;
+        Ok(Self::new(Arc::new(model), config).with_retry(RetryPolicy {
+            retries: 0,
+            ..RetryPolicy::default()
+        }))
+    }
+
+    #[cfg(not(feature = "local"))]
+    fn local(
+        _config: &LlmConfig,
+        _installed: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<Self, LlmError> {
+        Err(LlmError::NotConfigured(
+            "this build has no local inference (build with `--features local-llm`)".to_string(),
+        ))
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn provider(&self) -> &str {
+        self.provider.name()
+    }
+
+    pub fn model(&self) -> &str {
+        self.provider.model()
+    }
+
+    /// Tokens left this hour; `None` without an hourly limit.
+    pub fn budget_remaining(&self) -> Option<u32> {
+        self.budget.remaining()
+    }
+
+    /// Complete `request`, from the cache when it was asked recently.
+    pub async fn complete(&self, mut request: CompletionRequest) -> Result<Completion, LlmError> {
+        request.temperature.get_or_insert(self.temperature);
+        let max_tokens = *request.max_tokens.get_or_insert(self.max_tokens);
+
+        let key = ResponseCache::key(self.provider.name(), self.provider.model(), &request);
+        if let Some(hit) = self.cache.get(&key) {
+            debug!(target: "llm", provider = self.provider.name(), "answered from cache");
+            return Ok(hit);
+        }
+
+        let reserved = self
+            .budget
+            .reserve(estimate_tokens(&request.messages), max_tokens)?;
+        let result = self.attempt(&request).await;
+        let used = match &result {
+            Ok(c) => c.usage.map_or(reserved, |u| u.total()),
+            Err(_) => 0,
+        };
+        self.budget.settle(reserved, used);
+
+        let completion = result?;
+        self.cache.put(key, &completion);
+        Ok(completion)
+    }
+
+    /// A single user prompt, returning just the text.
+    pub async fn prompt(&self, text: impl Into<String>) -> Result<String, LlmError> {
+        Ok(self.complete(CompletionRequest::prompt(text)).await?.text)
+    }
+
+    async fn attempt(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let mut backoff = self.retry.backoff;
+        let mut attempt = 0;
+        loop {
+            let result =
+                match tokio::time::timeout(self.timeout, self.provider.complete(request)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(LlmError::Timeout(self.timeout)),
+                };
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.retry.retries => {
+                    attempt += 1;
+                    warn!(
+                        target: "llm",
+                        provider = self.provider.name(),
+                        attempt,
+                        "retrying in {backoff:?}: {e}"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_trait::async_trait;
+    use pagi_config::{Layers, Overrides};
+
+    use super::*;
+    use crate::Usage;
+
+    /// Fails with `error` for the first `failures` calls, then answers.
+    struct Flaky {
+        failures: u32,
+        error: fn() -> LlmError,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl Provider for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn model(&self) -> &str {
+            "m"
+        }
+
+        async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.error)());
+            }
+            Ok(Completion {
+                text: format!("re: {}", request.messages[0].content),
+                model: "m".to_string(),
+                usage: Some(Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 5,
+                }),
+                cached: false,
+            })
+        }
+    }
+
+    fn flaky_client(failures: u32, error: fn() -> LlmError, file: &str) -> (LlmClient, Arc<Flaky>) {
+        let flaky = Arc::new(Flaky {
+            failures,
+            error,
+            calls: AtomicU32::new(0),
+        });
+        let layers = Layers::parse("phoenix.toml", file, &Overrides::default()).unwrap();
+        let config = LlmConfig::from_layers(&layers).unwrap();
+        let client = LlmClient::new(flaky.clone(), &config).with_retry(RetryPolicy {
+            retries: 2,
+            backoff: Duration::from_millis(1),
+        });
+        (client, flaky)
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_then_caches() {
+        let overloaded = || LlmError::Status {
+            status: 503,
+            body: String::new(),
+        };
+        let (client, flaky) = flaky_client(2, overloaded, "");
+        assert_eq!(client.prompt("hi").await.unwrap(), "re: hi");
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+
+        let again = client
+            .complete(CompletionRequest::prompt("hi"))
+            .await
+            .unwrap();
+        assert!(again.cached);
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_permanent_failures_and_the_budget() {
+        let unauthorized = || LlmError::Status {
+            status: 401,
+            body: String::new(),
+        };
+        let (client, flaky) = flaky_client(5, unauthorized, "");
+        assert!(matches!(
+            client.prompt("hi").await,
+            Err(LlmError::Status { status: 401, .. })
+        ));
+        assert_eq!(flaky.calls.load(Ordering::SeqCst), 1);
+
+        let no_error = || unreachable!();
+        let (client, _) =
+            flaky_client(0, no_error, "[llm]\ntokens_per_hour = 100\nmax_tokens = 70");
+        client.prompt("one").await.unwrap();
+        // Only the 15 reported tokens count, not the 75 reserved.
+        assert_eq!(client.budget_remaining(), Some(85));
+        client.prompt("two").await.unwrap();
+        assert!(matches!(
+            client.prompt("three").await,
+            Err(LlmError::BudgetExceeded { .. })
+        ));
+    }
+}
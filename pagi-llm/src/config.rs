@@ -0,0 +1,285 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pagi_config::{parse_number, ConfigError, Layers, Overrides};
+
+use crate::{ChatTemplate, LlmError};
+
+const OPENROUTER_URL: &str = "https://openrouter.ai/api/v1";
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+const OLLAMA_URL: &str = "http://127.0.0.1:11434";
+/// The model-manager id of the default local model.
+const DEFAULT_LOCAL_MODEL: &str = "llama-3.2-1b-instruct-q4";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    OpenRouter,
+    /// Any other server speaking OpenAI's API, at `base_url`.
+    OpenAi,
+    Ollama,
+    /// A GGUF model run in-process; see [`crate::local`].
+    Local,
+}
+
+impl ProviderKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "openrouter" => Some(Self::OpenRouter),
+            "openai" => Some(Self::OpenAi),
+            "ollama" => Some(Self::Ollama),
+            "local" => Some(Self::Local),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OpenRouter => "openrouter",
+            Self::OpenAi => "openai",
+            Self::Ollama => "ollama",
+            Self::Local => "local",
+        }
+    }
+}
+
+/// The `[llm]` settings; see [`LlmConfig::from_layers`].
+#[derive(Clone, PartialEq)]
+pub struct LlmConfig {
+    pub provider: ProviderKind,
+    /// API root of an OpenAI-compatible server (up to `/v1`), or Ollama's server root.
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    /// For `local`: a model-manager id or a `.gguf` path.
+    pub local_model: String,
+    /// For `local`: a model-manager id or a `tokenizer.json` path; unset means
+    /// `<local_model>-tokenizer`, or the `tokenizer.json` beside a `.gguf` path.
+    pub local_tokenizer: Option<String>,
+    pub local_template: ChatTemplate,
+    pub temperature: f32,
+    /// Reply length when a caller doesn't ask for one.
+    pub max_tokens: u32,
+    /// Per attempt.
+    pub timeout: Duration,
+    /// Extra attempts after a retryable failure.
+    pub retries: u32,
+    /// `0` = no limit.
+    pub max_prompt_tokens: u32,
+    /// `0` = no limit.
+    pub tokens_per_hour: u32,
+    /// `0` turns the response cache off.
+    pub cache_ttl: Duration,
+    pub cache_entries: usize,
+}
+
+impl fmt::Debug for LlmConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LlmConfig")
+            .field("provider", &self.provider)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "********"))
+            .field("model", &self.model)
+            .field("local_model", &self.local_model)
+            .field("local_tokenizer", &self.local_tokenizer)
+            .field("local_template", &self.local_template)
+            .field("temperature", &self.temperature)
+            .field("max_tokens", &self.max_tokens)
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("max_prompt_tokens", &self.max_prompt_tokens)
+            .field("tokens_per_hour", &self.tokens_per_hour)
+            .field("cache_ttl", &self.cache_ttl)
+            .field("cache_entries", &self.cache_entries)
+            .finish()
+    }
+}
+
+impl LlmConfig {
+    /// The `[llm]` table: `provider` (`openrouter`, `openai`, `ollama` or `local`), `base_url`,
+    /// `api_key`, `model`, `ollama_url` and `ollama_model`, `local_model`, `local_tokenizer` and
+    /// `local_template` (`llama3` or `chatml`), `temperature`, `max_tokens`, `timeout_secs`,
+    /// `retries`, `max_prompt_tokens`, `tokens_per_hour`, `cache_ttl_secs` and `cache_entries`.
+    ///
+    /// CPU inference is slow, so `local` waits 120 seconds by default instead of 30.
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let provider = layers.or("llm.provider", ProviderKind::OpenRouter, |s| {
+            ProviderKind::parse(s)
+                .ok_or_else(|| "expected openrouter, openai or ollama".to_string())
+        })?;
+        let text = |s: &str| match s.trim() {
+            "" => Err("must not be empty".to_string()),
+            s => Ok(s.to_string()),
+        };
+        let (base_url, model) = match provider {
+            ProviderKind::Ollama => (
+                layers.or("llm.ollama_url", OLLAMA_URL.to_string(), text)?,
+                layers.or("llm.ollama_model", "llama3".to_string(), text)?,
+            ),
+            // Named by `local_model`; the URL is unused.
+            ProviderKind::Local => (String::new(), "local".to_string()),
+            ProviderKind::OpenRouter | ProviderKind::OpenAi => {
+                let default_url = match provider {
+                    ProviderKind::OpenRouter => OPENROUTER_URL,
+                    _ => OPENAI_URL,
+                };
+                (
+                    layers.or("llm.base_url", default_url.to_string(), text)?,
+                    layers.or("llm.model", "deepseek/deepseek-v3.2".to_string(), text)?,
+                )
+            }
+        };
+        let default_timeout = match provider {
+            ProviderKind::Local => Duration::from_secs(120),
+            _ => Duration::from_secs(30),
+        };
+        let at_least_one = |s: &str| match parse_number::<u64>(s)? {
+            0 => Err("must be at least 1".to_string()),
+            n => Ok(n),
+        };
+        Ok(Self {
+            provider,
+            base_url,
+            api_key: layers.secret("llm.api_key")?,
+            model,
+            local_model: layers.or("llm.local_model", DEFAULT_LOCAL_MODEL.to_string(), text)?,
+            local_tokenizer: layers
+                .get("llm.local_tokenizer")
+                .map(|(s, _)| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            local_template: layers.or("llm.local_template", ChatTemplate::Llama3, |s| {
+                ChatTemplate::parse(s).ok_or_else(|| "expected llama3 or chatml".to_string())
+            })?,
+            temperature: layers.or("llm.temperature", 0.7, |s| {
+                parse_number::<f32>(s).and_then(|t| match t {
+                    t if (0.0..=2.0).contains(&t) => Ok(t),
+                    _ => Err("must be 0.0-2.0".to_string()),
+                })
+            })?,
+            max_tokens: layers.or("llm.max_tokens", 1024, |s| {
+                at_least_one(s).map(|n| n.min(u32::MAX as u64) as u32)
+            })?,
+            timeout: layers.or("llm.timeout_secs", default_timeout, |s| {
+                at_least_one(s).map(Duration::from_secs)
+            })?,
+            retries: layers.or("llm.retries", 2, parse_number)?,
+            max_prompt_tokens: layers.or("llm.max_prompt_tokens", 16_000, parse_number)?,
+            tokens_per_hour: layers.or("llm.tokens_per_hour", 0, parse_number)?,
+            cache_ttl: layers.or("llm.cache_ttl_secs", Duration::from_secs(10 * 60), |s| {
+                parse_number::<u64>(s).map(Duration::from_secs)
+            })?,
+            cache_entries: layers.or("llm.cache_entries", 256, parse_number)?,
+        })
+    }
+
+    /// The weights and tokenizer files for `local`. `installed` looks up a model-manager id;
+    /// names it doesn't know are taken as paths.
+    pub fn local_files(
+        &self,
+        installed: &dyn Fn(&str) -> Option<PathBuf>,
+    ) -> Result<(PathBuf, PathBuf), LlmError> {
+        let missing = |what: &str, name: &str| {
+            LlmError::NotConfigured(format!(
+                "local {what} {name} is neither an installed model nor a file"
+            ))
+        };
+        let file = |name: &str| Some(PathBuf::from(name)).filter(|p| p.is_file());
+        let (weights, by_id) = match installed(&self.local_model) {
+            Some(path) => (path, true),
+            None => (
+                file(&self.local_model).ok_or_else(|| missing("model", &self.local_model))?,
+                false,
+            ),
+        };
+        let tokenizer = match &self.local_tokenizer {
+            Some(name) => installed(name).or_else(|| file(name)),
+            None if by_id => installed(&format!("{}-tokenizer", self.local_model)),
+            None => file(
+                &Path::new(&self.local_model)
+                    .with_file_name("tokenizer.json")
+                    .to_string_lossy(),
+            ),
+        };
+        let tokenizer = tokenizer.ok_or_else(|| {
+            let name = self
+                .local_tokenizer
+                .clone()
+                .unwrap_or_else(|| format!("for {}", self.local_model));
+            missing("tokenizer", &name)
+        })?;
+        Ok((weights, tokenizer))
+    }
+
+    /// The config file and environment, as the web server reads them.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_layers(&Layers::load(&Overrides::default())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(file: &str) -> Layers {
+        Layers::parse("phoenix.toml", file, &Overrides::default()).unwrap()
+    }
+
+    #[test]
+    fn provider_picks_its_url_and_model_keys() {
+        let file = "[llm]\nprovider = \"ollama\"\nollama_model = \"llama3.1:8b\"\nmodel = \"ignored\"\ntokens_per_hour = 50000";
+        let config = LlmConfig::from_layers(&layers(file)).unwrap();
+        assert_eq!(config.provider, ProviderKind::Ollama);
+        assert_eq!(config.base_url, OLLAMA_URL);
+        assert_eq!(config.model, "llama3.1:8b");
+        assert_eq!(config.tokens_per_hour, 50_000);
+
+        let config = LlmConfig::from_layers(&layers(
+            "[llm]\nprovider = \"openai\"\nmodel = \"gpt-4o-mini\"\napi_key = \"sk-test\"",
+        ))
+        .unwrap();
+        assert_eq!(config.base_url, OPENAI_URL);
+        assert!(!format!("{config:?}").contains("sk-test"));
+
+        let err = LlmConfig::from_layers(&layers("[llm]\ntemperature = 3")).unwrap_err();
+        assert!(err.to_string().contains("llm.temperature"));
+    }
+
+    #[test]
+    fn local_files_come_from_the_model_manager_or_disk() {
+        let config = LlmConfig::from_layers(&layers("[llm]\nprovider = \"local\"")).unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(120));
+        let installed = |id: &str| match id {
+            "llama-3.2-1b-instruct-q4" => Some(PathBuf::from("/models/llm/a.gguf")),
+            "llama-3.2-1b-instruct-q4-tokenizer" => Some(PathBuf::from("/models/llm/t.json")),
+            _ => None,
+        };
+        assert_eq!(
+            config.local_files(&installed).unwrap(),
+            (
+                PathBuf::from("/models/llm/a.gguf"),
+                PathBuf::from("/models/llm/t.json")
+            )
+        );
+        assert!(matches!(
+            config.local_files(&|_| None),
+            Err(LlmError::NotConfigured(e)) if e.contains("llama-3.2-1b-instruct-q4")
+        ));
+
+        let dir = std::env::temp_dir().join(format!("pagi-llm-local-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let weights = dir.join("qwen.gguf");
+        std::fs::write(&weights, b"").unwrap();
+        std::fs::write(dir.join("tokenizer.json"), b"{}").unwrap();
+        let file = format!(
+            "[llm]\nprovider = \"local\"\nlocal_model = {:?}\nlocal_template = \"chatml\"",
+            weights.display().to_string()
+        );
+        let config = LlmConfig::from_layers(&layers(&file)).unwrap();
+        assert_eq!(config.local_template, ChatTemplate::ChatMl);
+        assert_eq!(
+            config.local_files(&|_| None).unwrap(),
+            (weights, dir.join("tokenizer.json"))
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
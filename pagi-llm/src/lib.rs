@@ -0,0 +1,178 @@
+//! Text completion for the features that ask a language model for a few sentences: the Ghost's
+//! persona replies, narrative reframes, lesson summaries and whatever summarizes next.
+//!
+//! A [`Provider`] sends one request to one backend: [`OpenAiCompatible`] (OpenRouter, OpenAI or
+//! any server speaking `/chat/completions`), [`Ollama`]'s native API, or a GGUF model run
+//! in-process (`LocalModel`, with the `local` feature; see the [`local`] module). Callers use an
+//! [`LlmClient`] around it, which adds what every caller would otherwise repeat:
+//!
+//! - a timeout per attempt, and retries with backoff for timeouts, connection errors, `429`s
+//!   and `5xx`s;
+//! - a [token budget](TokenBudget): prompts over a size limit are refused, and an hourly
+//!   allowance caps spend;
+//! - a [response cache](ResponseCache), so the same prompt to the same model within the TTL
+//!   is answered without a request.
+//!
+//! [`LlmConfig`] is the `[llm]` table of the shared settings (see [`pagi_config`]); each key
+//! also reads the environment variable the LLM orchestrator has always used.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+mod budget;
+mod cache;
+mod client;
+mod config;
+pub mod local;
+mod ollama;
+mod openai;
+
+pub use budget::{estimate_tokens, TokenBudget};
+pub use cache::ResponseCache;
+pub use client::{LlmClient, RetryPolicy};
+pub use config::{LlmConfig, ProviderKind};
+pub use local::ChatTemplate;
+#[cfg(feature = "local")]
+pub use local::LocalModel;
+pub use ollama::Ollama;
+pub use openai::OpenAiCompatible;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+/// One completion request. Unset `temperature` and `max_tokens` take the client's defaults.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CompletionRequest {
+    pub messages: Vec<Message>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl CompletionRequest {
+    /// A single user message.
+    pub fn prompt(text: impl Into<String>) -> Self {
+        Self {
+            messages: vec![Message {
+                role: Role::User,
+                content: text.into(),
+            }],
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    /// Put a system message first.
+    pub fn with_system(mut self, text: impl Into<String>) -> Self {
+        self.messages.insert(
+            0,
+            Message {
+                role: Role::System,
+                content: text.into(),
+            },
+        );
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+}
+
+/// Token counts as the backend reported them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl Usage {
+    pub fn total(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Completion {
+    pub text: String,
+    /// The model that answered.
+    pub model: String,
+    /// `None` when the backend didn't say.
+    pub usage: Option<Usage>,
+    /// Served from the [`ResponseCache`].
+    #[serde(default)]
+    pub cached: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("LLM not configured: {0}")]
+    NotConfigured(String),
+    #[error("LLM request timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("LLM request failed: {0}")]
+    Transport(String),
+    #[error("LLM backend answered {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("unexpected LLM response: {0}")]
+    BadResponse(String),
+    #[error("prompt is ~{tokens} tokens, over the limit of {max}")]
+    PromptTooLong { tokens: u32, max: u32 },
+    #[error("LLM token budget spent: needs ~{needed}, {remaining} left this hour")]
+    BudgetExceeded { needed: u32, remaining: u32 },
+    #[error("local inference failed: {0}")]
+    Inference(String),
+}
+
+impl LlmError {
+    /// Whether the same request may succeed if sent again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout(_) | Self::Transport(_) => true,
+            Self::Status { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// One LLM backend.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Short name for logs and cache keys, e.g. `openrouter`.
+    fn name(&self) -> &str;
+
+    fn model(&self) -> &str;
+
+    /// Send `request` once. `temperature` and `max_tokens` are already filled in.
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError>;
+}
+
+/// Non-success responses keep a bounded slice of the body for the error.
+fn status_error(status: reqwest::StatusCode, body: &str) -> LlmError {
+    LlmError::Status {
+        status: status.as_u16(),
+        body: body.chars().take(300).collect(),
+    }
+}
+
+fn transport_error(e: reqwest::Error) -> LlmError {
+    LlmError::Transport(e.to_string())
+}
@@ -0,0 +1,299 @@
+//! In-process inference of a quantized GGUF model (`provider = "local"`), for people who won't
+//! send their conversations to any API.
+//!
+//! The model runs on the CPU with candle's quantized Llama, which also loads the Llama-style
+//! GGUF conversions of Mistral, Qwen and Phi-3 checkpoints. Weights and the `tokenizer.json`
+//! are plain files; the web server finds them through the model manager (`kind: "llm"` entries
+//! in the models manifest), so downloading, pinning and deleting work as for the voice and face
+//! models. The weights are read on the first request, not at startup.
+//!
+//! Without the `local` feature only [`ChatTemplate`] is compiled and
+//! [`LlmClient::from_config`](crate::LlmClient::from_config) refuses the provider.
+
+use crate::{Message, Role};
+
+/// How messages are laid out for a model that was tuned on one chat format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatTemplate {
+    /// `<|start_header_id|>role<|end_header_id|>` (Llama 3).
+    Llama3,
+    /// `<|im_start|>role` (Qwen, most community fine-tunes).
+    ChatMl,
+}
+
+impl ChatTemplate {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "llama3" => Some(Self::Llama3),
+            "chatml" => Some(Self::ChatMl),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Llama3 => "llama3",
+            Self::ChatMl => "chatml",
+        }
+    }
+
+    /// `messages` followed by the opening of the assistant's turn.
+    pub fn render(self, messages: &[Message]) -> String {
+        let role = |r: Role| match r {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        let mut out = String::new();
+        match self {
+            Self::Llama3 => {
+                out.push_str("<|begin_of_text|>");
+                for m in messages {
+                    out.push_str(&format!(
+                        "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                        role(m.role),
+                        m.content.trim()
+                    ));
+                }
+                out.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+            Self::ChatMl => {
+                for m in messages {
+                    out.push_str(&format!(
+                        "<|im_start|>{}\n{}<|im_end|>\n",
+                        role(m.role),
+                        m.content.trim()
+                    ));
+                }
+                out.push_str("<|im_start|>assistant\n");
+            }
+        }
+        out
+    }
+
+    /// The token that ends the assistant's turn.
+    pub fn stop_token(self) -> &'static str {
+        match self {
+            Self::Llama3 => "<|eot_id|>",
+            Self::ChatMl => "<|im_end|>",
+        }
+    }
+}
+
+#[cfg(feature = "local")]
+pub use engine::LocalModel;
+
+#[cfg(feature = "local")]
+mod engine {
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use candle_core::quantized::gguf_file;
+    use candle_core::{Device, Tensor};
+    use candle_transformers::generation::LogitsProcessor;
+    use candle_transformers::models::quantized_llama::{ModelWeights, MAX_SEQ_LEN};
+    use tokenizers::Tokenizer;
+    use tracing::info;
+
+    use super::ChatTemplate;
+    use crate::{Completion, CompletionRequest, LlmError, Provider, Usage};
+
+    struct Loaded {
+        weights: ModelWeights,
+        tokenizer: Tokenizer,
+        /// The template's stop token and the GGUF's end-of-sequence token.
+        stop: Vec<u32>,
+    }
+
+    /// A GGUF model and its tokenizer, loaded on first use.
+    pub struct LocalModel {
+        model: String,
+        weights_path: PathBuf,
+        tokenizer_path: PathBuf,
+        template: ChatTemplate,
+        /// Generation needs the model exclusively, so requests take turns.
+        loaded: Arc<Mutex<Option<Loaded>>>,
+    }
+
+    impl LocalModel {
+        /// `model` names the weights in logs and cache keys.
+        pub fn new(
+            model: impl Into<String>,
+            weights_path: &Path,
+            tokenizer_path: &Path,
+            template: ChatTemplate,
+        ) -> Result<Self, LlmError> {
+            for path in [weights_path, tokenizer_path] {
+                if !path.is_file() {
+                    return Err(LlmError::NotConfigured(format!(
+                        "no local model file at {}",
+                        path.display()
+                    )));
+                }
+            }
+            Ok(Self {
+                model: model.into(),
+                weights_path: weights_path.to_path_buf(),
+                tokenizer_path: tokenizer_path.to_path_buf(),
+                template,
+                loaded: Arc::new(Mutex::new(None)),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for LocalModel {
+        fn name(&self) -> &str {
+            "local"
+        }
+
+        fn model(&self) -> &str {
+            &self.model
+        }
+
+        async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+            let loaded = self.loaded.clone();
+            let weights_path = self.weights_path.clone();
+            let tokenizer_path = self.tokenizer_path.clone();
+            let template = self.template;
+            let request = request.clone();
+            let model = self.model.clone();
+            tokio::task::spawn_blocking(move || {
+                let mut guard = loaded.lock().unwrap_or_else(|e| e.into_inner());
+                if guard.is_none() {
+                    *guard = Some(load(&weights_path, &tokenizer_path, template)?);
+                }
+                let loaded = guard.as_mut().expect("loaded above");
+                let (text, usage) = generate(loaded, template, &request)?;
+                Ok(Completion {
+                    text,
+                    model,
+                    usage: Some(usage),
+                    cached: false,
+                })
+            })
+            .await
+            .map_err(|e| LlmError::Inference(e.to_string()))?
+        }
+    }
+
+    fn inference(e: impl std::fmt::Display) -> LlmError {
+        LlmError::Inference(e.to_string())
+    }
+
+    fn load(
+        weights_path: &Path,
+        tokenizer_path: &Path,
+        template: ChatTemplate,
+    ) -> Result<Loaded, LlmError> {
+        let started = std::time::Instant::now();
+        let mut file = std::fs::File::open(weights_path).map_err(inference)?;
+        let content = gguf_file::Content::read(&mut file).map_err(inference)?;
+        let eos = content
+            .metadata
+            .get("tokenizer.ggml.eos_token_id")
+            .and_then(|v| v.to_u32().ok());
+        let weights =
+            ModelWeights::from_gguf(content, &mut file, &Device::Cpu).map_err(inference)?;
+        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(inference)?;
+        let stop = tokenizer
+            .token_to_id(template.stop_token())
+            .into_iter()
+            .chain(eos)
+            .collect();
+        info!(
+            target: "llm",
+            "loaded {} in {:?}",
+            weights_path.display(),
+            started.elapsed()
+        );
+        Ok(Loaded {
+            weights,
+            tokenizer,
+            stop,
+        })
+    }
+
+    fn generate(
+        loaded: &mut Loaded,
+        template: ChatTemplate,
+        request: &CompletionRequest,
+    ) -> Result<(String, Usage), LlmError> {
+        let prompt = template.render(&request.messages);
+        let prompt_tokens = loaded
+            .tokenizer
+            .encode(prompt, false)
+            .map_err(inference)?
+            .get_ids()
+            .to_vec();
+        let max_tokens = request.max_tokens.unwrap_or(256) as usize;
+        if prompt_tokens.len() + max_tokens > MAX_SEQ_LEN {
+            return Err(LlmError::PromptTooLong {
+                tokens: prompt_tokens.len() as u32,
+                max: MAX_SEQ_LEN.saturating_sub(max_tokens) as u32,
+            });
+        }
+
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let mut sampler = LogitsProcessor::new(seed, request.temperature.map(f64::from), Some(0.9));
+        let mut generated: Vec<u32> = Vec::new();
+        let mut input = prompt_tokens.clone();
+        let mut position = 0;
+        while generated.len() < max_tokens {
+            let tensor = Tensor::new(input.as_slice(), &Device::Cpu)
+                .and_then(|t| t.unsqueeze(0))
+                .map_err(inference)?;
+            let logits = loaded
+                .weights
+                .forward(&tensor, position)
+                .and_then(|l| l.squeeze(0))
+                .map_err(inference)?;
+            position += input.len();
+            let next = sampler.sample(&logits).map_err(inference)?;
+            if loaded.stop.contains(&next) {
+                break;
+            }
+            generated.push(next);
+            input = vec![next];
+        }
+
+        let text = loaded
+            .tokenizer
+            .decode(&generated, true)
+            .map_err(inference)?;
+        Ok((
+            text.trim().to_string(),
+            Usage {
+                prompt_tokens: prompt_tokens.len() as u32,
+                completion_tokens: generated.len() as u32,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompletionRequest;
+
+    #[test]
+    fn renders_each_chat_format() {
+        let request =
+            CompletionRequest::prompt("Why did you leave early?").with_system("Be brief.");
+        assert_eq!(
+            ChatTemplate::Llama3.render(&request.messages),
+            "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nWhy did you leave early?<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\n"
+        );
+        assert_eq!(
+            ChatTemplate::ChatMl.render(&request.messages),
+            "<|im_start|>system\nBe brief.<|im_end|>\n\
+             <|im_start|>user\nWhy did you leave early?<|im_end|>\n<|im_start|>assistant\n"
+        );
+        assert_eq!(ChatTemplate::parse("ChatML"), Some(ChatTemplate::ChatMl));
+    }
+}
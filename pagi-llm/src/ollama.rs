@@ -0,0 +1,119 @@
+//! Ollama's native chat API (`POST /api/chat`).
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::{
+    status_error, transport_error, Completion, CompletionRequest, LlmError, Provider, Usage,
+};
+
+pub struct Ollama {
+    http: reqwest::Client,
+    /// The server root, e.g. `http://127.0.0.1:11434`.
+    base_url: String,
+    model: String,
+}
+
+impl Ollama {
+    pub fn new(http: reqwest::Client, base_url: &str, model: impl Into<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Ollama {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let mut options = json!({});
+        if let Some(t) = request.temperature {
+            options["temperature"] = json!(t);
+        }
+        if let Some(n) = request.max_tokens {
+            options["num_predict"] = json!(n);
+        }
+        let body = json!({
+            "model": self.model,
+            "messages": request.messages,
+            "stream": false,
+            "options": options,
+        });
+        let response = self
+            .http
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(transport_error)?;
+        let status = response.status();
+        let text = response.text().await.map_err(transport_error)?;
+        if !status.is_success() {
+            return Err(status_error(status, &text));
+        }
+        parse(&text, &self.model)
+    }
+}
+
+fn parse(body: &str, requested_model: &str) -> Result<Completion, LlmError> {
+    let json: Value =
+        serde_json::from_str(body).map_err(|e| LlmError::BadResponse(e.to_string()))?;
+    if let Some(error) = json["error"].as_str() {
+        return Err(LlmError::BadResponse(error.to_string()));
+    }
+    let text = json["message"]["content"]
+        .as_str()
+        .ok_or_else(|| LlmError::BadResponse("no message.content".to_string()))?;
+    let count = |key: &str| json[key].as_u64().map(|n| n as u32);
+    let usage = match (count("prompt_eval_count"), count("eval_count")) {
+        (Some(prompt_tokens), Some(completion_tokens)) => Some(Usage {
+            prompt_tokens,
+            completion_tokens,
+        }),
+        _ => None,
+    };
+    Ok(Completion {
+        text: text.to_string(),
+        model: json["model"]
+            .as_str()
+            .unwrap_or(requested_model)
+            .to_string(),
+        usage,
+        cached: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_message_and_eval_counts() {
+        let body = r#"{"model":"llama3.1:8b","created_at":"2026-10-18T06:00:00Z",
+            "message":{"role":"assistant","content":"Can we talk later?"},"done":true,
+            "prompt_eval_count":30,"eval_count":7}"#;
+        let c = parse(body, "llama3").unwrap();
+        assert_eq!(c.text, "Can we talk later?");
+        assert_eq!(c.model, "llama3.1:8b");
+        assert_eq!(
+            c.usage,
+            Some(Usage {
+                prompt_tokens: 30,
+                completion_tokens: 7
+            })
+        );
+        assert!(matches!(
+            parse(r#"{"error":"model 'x' not found"}"#, "x"),
+            Err(LlmError::BadResponse(e)) if e.contains("not found")
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+//! OpenAI's `/chat/completions` API, which OpenRouter and most hosted and local servers speak.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::{
+    status_error, transport_error, Completion, CompletionRequest, LlmError, Provider, Usage,
+};
+
+pub struct OpenAiCompatible {
+    name: String,
+    http: reqwest::Client,
+    /// Up to and including the version segment, e.g. `https://api.openai.com/v1`.
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl OpenAiCompatible {
+    pub fn new(
+        name: impl Into<String>,
+        http: reqwest::Client,
+        base_url: &str,
+        api_key: Option<String>,
+        model: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: model.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Send `name: value` with every request (OpenRouter's attribution headers).
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAiCompatible {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: &CompletionRequest) -> Result<Completion, LlmError> {
+        let body = json!({
+            "model": self.model,
+            "messages": request.messages,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "stream": false,
+        });
+        let mut builder = self
+            .http
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+        for (name, value) in &self.headers {
+            builder = builder.header(*name, value);
+        }
+        let response = builder.send().await.map_err(transport_error)?;
+        let status = response.status();
+        let text = response.text().await.map_err(transport_error)?;
+        if !status.is_success() {
+            return Err(status_error(status, &text));
+        }
+        parse(&text, &self.model)
+    }
+}
+
+fn parse(body: &str, requested_model: &str) -> Result<Completion, LlmError> {
+    let json: Value =
+        serde_json::from_str(body).map_err(|e| LlmError::BadResponse(e.to_string()))?;
+    let text = json["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_else(|| LlmError::BadResponse("no choices[0].message.content".to_string()))?;
+    let count = |key: &str| json["usage"][key].as_u64().map(|n| n as u32);
+    let usage = match (count("prompt_tokens"), count("completion_tokens")) {
+        (Some(prompt_tokens), Some(completion_tokens)) => Some(Usage {
+            prompt_tokens,
+            completion_tokens,
+        }),
+        _ => None,
+    };
+    Ok(Completion {
+        text: text.to_string(),
+        model: json["model"]
+            .as_str()
+            .unwrap_or(requested_model)
+            .to_string(),
+        usage,
+        cached: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_first_choice_and_usage() {
+        let body = r#"{"id":"x","model":"openai/gpt-4o-mini","choices":[{"index":0,
+            "message":{"role":"assistant","content":"I hear you."},"finish_reason":"stop"}],
+            "usage":{"prompt_tokens":12,"completion_tokens":4,"total_tokens":16}}"#;
+        let c = parse(body, "fallback").unwrap();
+        assert_eq!(c.text, "I hear you.");
+        assert_eq!(c.model, "openai/gpt-4o-mini");
+        assert_eq!(c.usage.unwrap().total(), 16);
+
+        let c = parse(r#"{"choices":[{"message":{"content":"ok"}}]}"#, "m").unwrap();
+        assert_eq!((c.model.as_str(), c.usage), ("m", None));
+        assert!(matches!(
+            parse(r#"{"choices":[]}"#, "m"),
+            Err(LlmError::BadResponse(_))
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+//! Third-party analyzers, shipped as WebAssembly components and loaded from a plugins
+//! directory.
+//!
+//! An analyzer implements the `analyzer` world in `wit/analyzer.wit`: it describes itself
+//! ([`PluginInfo`]) and is handed [`Input`]s (a Ghost script, a recording's transcript, an
+//! emotion event) of the kinds it accepts, answering with [`Finding`]s: flags about the input
+//! and suggestions for it.
+//!
+//! Plugins are sandboxed. Their only import is a log function, so they have no filesystem,
+//! network, clock or randomness. Every call runs in a fresh instance with a fuel allowance
+//! (roughly, instructions) and a memory cap; a plugin that traps, runs out of either or answers
+//! garbage is logged and skipped, and the other plugins still answer.
+//!
+//! Running plugins needs the `wasm` feature (wasmtime). Without it [`PluginHost::load`] loads
+//! nothing and lists each `.wasm` file it found as a [`LoadFailure`].
+
+use std::path::{Path, PathBuf};
+
+use pagi_config::{parse_number, ConfigError, Layers};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "wasm")]
+mod wasm;
+
+/// The `[plugins]` settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginConfig {
+    pub enabled: bool,
+    /// Every `*.wasm` file here is loaded, in name order.
+    pub dir: PathBuf,
+    /// Fuel per call; a plugin that needs more is stopped.
+    pub fuel: u64,
+    /// Linear memory per instance, in bytes.
+    pub max_memory: usize,
+}
+
+impl PluginConfig {
+    /// The `[plugins]` table: `enabled`, `dir` (default: `plugins/` under `data_dir`), `fuel`
+    /// and `max_memory_mb`.
+    pub fn from_layers(layers: &Layers, data_dir: &Path) -> Result<Self, ConfigError> {
+        let at_least_one = |s: &str| match parse_number::<u64>(s)? {
+            0 => Err("must be at least 1".to_string()),
+            n => Ok(n),
+        };
+        Ok(Self {
+            enabled: layers.flag("plugins.enabled", true)?,
+            dir: layers
+                .path("plugins.dir")
+                .unwrap_or_else(|| data_dir.join("plugins")),
+            fuel: layers.or("plugins.fuel", 500_000_000, at_least_one)?,
+            max_memory: layers.or("plugins.max_memory_mb", 64 << 20, |s| {
+                at_least_one(s).map(|mb| (mb as usize).saturating_mul(1 << 20))
+            })?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputKind {
+    Script,
+    Transcript,
+    Emotion,
+}
+
+/// A live emotion estimate; see `emotion-event` in the WIT.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmotionEvent {
+    pub timestamp: i64,
+    pub emotion: String,
+    pub intensity: f64,
+    pub confidence: f64,
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// What analyzers are asked about.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "input", rename_all = "lowercase")]
+pub enum Input {
+    Script(String),
+    /// One `speaker: text` line per segment.
+    Transcript(String),
+    Emotion(EmotionEvent),
+}
+
+impl Input {
+    pub fn kind(&self) -> InputKind {
+        match self {
+            Self::Script(_) => InputKind::Script,
+            Self::Transcript(_) => InputKind::Transcript,
+            Self::Emotion(_) => InputKind::Emotion,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Alert,
+}
+
+/// A flag or suggestion from one plugin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    /// The plugin's name.
+    pub plugin: String,
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// A loaded plugin.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: String,
+    pub accepts: Vec<InputKind>,
+    pub file: PathBuf,
+}
+
+/// A `.wasm` file that didn't load.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadFailure {
+    pub file: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("cannot read the plugins directory {0}: {1}")]
+    Dir(PathBuf, std::io::Error),
+    #[error("{0}")]
+    Wasm(String),
+}
+
+/// The loaded plugins.
+#[derive(Default)]
+pub struct PluginHost {
+    #[cfg(feature = "wasm")]
+    runtime: Option<wasm::Runtime>,
+    failures: Vec<LoadFailure>,
+}
+
+impl PluginHost {
+    /// Load every plugin in `config.dir`; a missing directory means none. Files that fail to
+    /// load are kept in [`PluginHost::failures`].
+    pub fn load(config: &PluginConfig) -> Result<Self, PluginError> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let files = wasm_files(&config.dir)?;
+        Self::load_files(config, files)
+    }
+
+    #[cfg(feature = "wasm")]
+    fn load_files(config: &PluginConfig, files: Vec<PathBuf>) -> Result<Self, PluginError> {
+        let (runtime, failures) = wasm::Runtime::load(config, &files)?;
+        Ok(Self {
+            runtime: Some(runtime),
+            failures,
+        })
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn load_files(_config: &PluginConfig, files: Vec<PathBuf>) -> Result<Self, PluginError> {
+        Ok(Self {
+            failures: files
+                .into_iter()
+                .map(|file| LoadFailure {
+                    file,
+                    error: "this build can't run plugins (the `plugins` feature)".to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn plugins(&self) -> &[PluginInfo] {
+        #[cfg(feature = "wasm")]
+        if let Some(runtime) = &self.runtime {
+            return runtime.plugins();
+        }
+        &[]
+    }
+
+    pub fn failures(&self) -> &[LoadFailure] {
+        &self.failures
+    }
+
+    /// Whether any plugin takes `kind`, so callers can skip building the input.
+    pub fn accepts(&self, kind: InputKind) -> bool {
+        self.plugins().iter().any(|p| p.accepts.contains(&kind))
+    }
+
+    /// Ask every plugin that accepts `input`, in load order. This runs WebAssembly on the
+    /// calling thread; async callers should use `spawn_blocking`.
+    pub fn analyze(&self, input: &Input) -> Vec<Finding> {
+        #[cfg(feature = "wasm")]
+        if let Some(runtime) = &self.runtime {
+            return runtime.analyze(input);
+        }
+        let _ = input;
+        Vec::new()
+    }
+}
+
+fn wasm_files(dir: &Path) -> Result<Vec<PathBuf>, PluginError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(PluginError::Dir(dir.to_path_buf(), e)),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|x| x == "wasm"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pagi_config::Overrides;
+
+    #[test]
+    fn config_defaults_under_the_data_dir() {
+        let layers = Layers::parse("phoenix.toml", "", &Overrides::default()).unwrap();
+        let config = PluginConfig::from_layers(&layers, Path::new("/srv/pagi")).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.dir, Path::new("/srv/pagi/plugins"));
+        assert_eq!(config.max_memory, 64 << 20);
+
+        let layers = Layers::parse(
+            "phoenix.toml",
+            "[plugins]\ndir = \"/opt/analyzers\"\nmax_memory_mb = 8\nfuel = 0",
+            &Overrides::default(),
+        )
+        .unwrap();
+        let err = PluginConfig::from_layers(&layers, Path::new(".")).unwrap_err();
+        assert!(err.to_string().contains("plugins.fuel"));
+    }
+
+    #[test]
+    fn inputs_serialize_with_their_kind() {
+        let input = Input::Script("You never listen.".to_string());
+        assert_eq!(
+            serde_json::to_value(&input).unwrap(),
+            serde_json::json!({"kind": "script", "input": "You never listen."})
+        );
+        assert_eq!(input.kind(), InputKind::Script);
+    }
+}
@@ -0,0 +1,342 @@
+//! The wasmtime side: compiling components, the sandbox, and converting to and from the WIT
+//! types.
+
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, info, warn};
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::{Finding, Input, InputKind, LoadFailure, PluginConfig, PluginError, PluginInfo};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        world: "analyzer",
+        path: "wit",
+    });
+}
+
+use bindings::pagi::analyzer::{host, types};
+use bindings::{Analyzer, AnalyzerPre};
+
+/// Findings kept per call; the rest are dropped.
+const MAX_FINDINGS: usize = 32;
+/// Characters kept of each string a plugin returns.
+const MAX_TEXT: usize = 2_000;
+
+/// A plugin's store data.
+struct State {
+    plugin: String,
+    limits: StoreLimits,
+}
+
+impl host::Host for State {
+    fn log(&mut self, level: host::Level, message: String) {
+        let message = clamp(&message);
+        match level {
+            host::Level::Debug => debug!(target: "plugins", plugin = %self.plugin, "{message}"),
+            host::Level::Info => info!(target: "plugins", plugin = %self.plugin, "{message}"),
+            host::Level::Warn => warn!(target: "plugins", plugin = %self.plugin, "{message}"),
+        }
+    }
+}
+
+impl types::Host for State {}
+
+struct Loaded {
+    pre: AnalyzerPre<State>,
+    info: PluginInfo,
+}
+
+pub(crate) struct Runtime {
+    engine: Engine,
+    fuel: u64,
+    max_memory: usize,
+    loaded: Vec<Loaded>,
+    infos: Vec<PluginInfo>,
+}
+
+impl Runtime {
+    pub(crate) fn load(
+        config: &PluginConfig,
+        files: &[PathBuf],
+    ) -> Result<(Self, Vec<LoadFailure>), PluginError> {
+        let mut engine_config = Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).map_err(wasm_error)?;
+        let mut linker = Linker::<State>::new(&engine);
+        Analyzer::add_to_linker(&mut linker, |state: &mut State| state).map_err(wasm_error)?;
+
+        let mut runtime = Self {
+            engine,
+            fuel: config.fuel,
+            max_memory: config.max_memory,
+            loaded: Vec::new(),
+            infos: Vec::new(),
+        };
+        let mut failures = Vec::new();
+        for file in files {
+            match runtime.load_one(&linker, file) {
+                Ok(loaded) => {
+                    info!(
+                        target: "plugins",
+                        "loaded {} {} from {}",
+                        loaded.info.name,
+                        loaded.info.version,
+                        file.display()
+                    );
+                    runtime.infos.push(loaded.info.clone());
+                    runtime.loaded.push(loaded);
+                }
+                Err(error) => {
+                    warn!(target: "plugins", "cannot load {}: {error}", file.display());
+                    failures.push(LoadFailure {
+                        file: file.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+        Ok((runtime, failures))
+    }
+
+    fn load_one(&self, linker: &Linker<State>, file: &Path) -> Result<Loaded, String> {
+        let component = Component::from_file(&self.engine, file).map_err(|e| format!("{e:#}"))?;
+        let pre = linker
+            .instantiate_pre(&component)
+            .and_then(AnalyzerPre::new)
+            .map_err(|e| format!("{e:#}"))?;
+        let label = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut store = self.store(&label);
+        let meta = pre
+            .instantiate(&mut store)
+            .and_then(|analyzer| analyzer.call_describe(&mut store))
+            .map_err(|e| format!("describe failed: {e:#}"))?;
+        if meta.name.trim().is_empty() {
+            return Err("describe returned an empty name".to_string());
+        }
+        let info = PluginInfo {
+            name: clamp(&meta.name),
+            version: clamp(&meta.version),
+            accepts: meta.accepts.into_iter().map(InputKind::from).collect(),
+            file: file.to_path_buf(),
+        };
+        Ok(Loaded { pre, info })
+    }
+
+    /// A fresh store: full fuel, capped memory, one instance.
+    fn store(&self, plugin: &str) -> Store<State> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory)
+            .instances(1)
+            .build();
+        let mut store = Store::new(
+            &self.engine,
+            State {
+                plugin: plugin.to_string(),
+                limits,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.fuel).expect("fuel is enabled");
+        store
+    }
+
+    pub(crate) fn plugins(&self) -> &[PluginInfo] {
+        &self.infos
+    }
+
+    pub(crate) fn analyze(&self, input: &Input) -> Vec<Finding> {
+        let kind = input.kind();
+        let wit_input = types::Input::from(input);
+        let mut findings = Vec::new();
+        for loaded in self
+            .loaded
+            .iter()
+            .filter(|l| l.info.accepts.contains(&kind))
+        {
+            let name = &loaded.info.name;
+            let mut store = self.store(name);
+            let result = loaded
+                .pre
+                .instantiate(&mut store)
+                .and_then(|analyzer| analyzer.call_analyze(&mut store, &wit_input));
+            match result {
+                Ok(answer) => {
+                    findings.extend(answer.into_iter().take(MAX_FINDINGS).map(|f| Finding {
+                        plugin: name.clone(),
+                        code: clamp(&f.code),
+                        severity: f.severity.into(),
+                        message: clamp(&f.message),
+                        suggestion: f.suggestion.as_deref().map(clamp),
+                    }))
+                }
+                Err(e) => warn!(target: "plugins", plugin = %name, "analyze failed: {e:#}"),
+            }
+        }
+        findings
+    }
+}
+
+fn wasm_error(e: wasmtime::Error) -> PluginError {
+    PluginError::Wasm(format!("{e:#}"))
+}
+
+fn clamp(s: &str) -> String {
+    s.chars().take(MAX_TEXT).collect()
+}
+
+impl From<&Input> for types::Input {
+    fn from(input: &Input) -> Self {
+        match input {
+            Input::Script(text) => Self::Script(text.clone()),
+            Input::Transcript(text) => Self::Transcript(text.clone()),
+            Input::Emotion(e) => Self::Emotion(types::EmotionEvent {
+                timestamp: e.timestamp,
+                emotion: e.emotion.clone(),
+                intensity: e.intensity,
+                confidence: e.confidence,
+                profile: e.profile.clone(),
+            }),
+        }
+    }
+}
+
+impl From<types::InputKind> for InputKind {
+    fn from(kind: types::InputKind) -> Self {
+        match kind {
+            types::InputKind::Script => Self::Script,
+            types::InputKind::Transcript => Self::Transcript,
+            types::InputKind::Emotion => Self::Emotion,
+        }
+    }
+}
+
+impl From<types::Severity> for crate::Severity {
+    fn from(severity: types::Severity) -> Self {
+        match severity {
+            types::Severity::Info => Self::Info,
+            types::Severity::Warning => Self::Warning,
+            types::Severity::Alert => Self::Alert,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PluginHost, Severity};
+
+    use super::*;
+
+    /// A component for the `analyzer` world. `analyze_body` is the core function's body; it
+    /// gets the flattened `input` and returns a pointer to the `list<finding>`.
+    fn analyzer(name: &str, analyze_body: &str) -> Vec<u8> {
+        let name_len = name.len();
+        wat::parse_str(format!(
+            r#"(component
+  (core module $m
+    (memory (export "memory") 1)
+    (global $heap (mut i32) (i32.const 4096))
+    (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+      (local $p i32)
+      (local.set $p (i32.and (i32.add (global.get $heap) (i32.const 7)) (i32.const -8)))
+      (global.set $heap (i32.add (local.get $p) (local.get 3)))
+      (local.get $p))
+    (data (i32.const 16) "{name}")
+    (data (i32.const 64) "1.0.0")
+    (data (i32.const 72) "\00")
+    (data (i32.const 80) "tone")
+    (data (i32.const 96) "Consider softening this.")
+    ;; describe: name, version, accepts = [script]
+    (data (i32.const 128) "\10\00\00\00\{name_len:02x}\00\00\00\40\00\00\00\05\00\00\00\48\00\00\00\01\00\00\00")
+    (func (export "describe") (result i32) (i32.const 128))
+    (func (export "analyze")
+      (param $case i32) (param $a i64) (param $b i32) (param i32) (param f64) (param f64)
+      (param i32) (param i32) (param i32) (result i32)
+      {analyze_body}))
+  (core instance $i (instantiate $m))
+  (type $kind' (enum "script" "transcript" "emotion"))
+  (export $kind "input-kind" (type $kind'))
+  (type $meta' (record (field "name" string) (field "version" string) (field "accepts" (list $kind))))
+  (export $meta "metadata" (type $meta'))
+  (type $severity' (enum "info" "warning" "alert"))
+  (export $severity "severity" (type $severity'))
+  (type $finding' (record (field "code" string) (field "severity" $severity) (field "message" string) (field "suggestion" (option string))))
+  (export $finding "finding" (type $finding'))
+  (type $emotion' (record (field "timestamp" s64) (field "emotion" string) (field "intensity" f64) (field "confidence" f64) (field "profile" (option string))))
+  (export $emotion "emotion-event" (type $emotion'))
+  (type $input' (variant (case "script" string) (case "transcript" string) (case "emotion" $emotion)))
+  (export $input "input" (type $input'))
+  (func $describe (result $meta)
+    (canon lift (core func $i "describe") (memory $i "memory") (realloc (func $i "cabi_realloc"))))
+  (export "describe" (func $describe))
+  (func $analyze (param "input" $input) (result (list $finding))
+    (canon lift (core func $i "analyze") (memory $i "memory") (realloc (func $i "cabi_realloc"))))
+  (export "analyze" (func $analyze)))"#
+        ))
+        .unwrap()
+    }
+
+    /// One `tone` warning whose suggestion echoes the script back.
+    const ECHO: &str = r#"
+      (i32.store (i32.const 256) (i32.const 80))
+      (i32.store (i32.const 260) (i32.const 4))
+      (i32.store8 (i32.const 264) (i32.const 1))
+      (i32.store (i32.const 268) (i32.const 96))
+      (i32.store (i32.const 272) (i32.const 24))
+      (i32.store8 (i32.const 276) (i32.const 1))
+      (i32.store (i32.const 280) (i32.wrap_i64 (local.get $a)))
+      (i32.store (i32.const 284) (local.get $b))
+      (i32.store (i32.const 320) (i32.const 256))
+      (i32.store (i32.const 324) (i32.const 1))
+      (i32.const 320)"#;
+
+    #[test]
+    fn runs_plugins_in_a_fuelled_sandbox() {
+        let dir = std::env::temp_dir().join(format!("pagi-plugins-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a-echo.wasm"), analyzer("echo", ECHO)).unwrap();
+        std::fs::write(
+            dir.join("b-spin.wasm"),
+            analyzer("spin", "(loop $forever (br $forever)) (i32.const 0)"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("c-broken.wasm"), b"not wasm").unwrap();
+        std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+
+        let config = PluginConfig {
+            enabled: true,
+            dir: dir.clone(),
+            fuel: 1_000_000,
+            max_memory: 1 << 20,
+        };
+        let host = PluginHost::load(&config).unwrap();
+        let names: Vec<_> = host.plugins().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["echo", "spin"]);
+        assert_eq!(host.plugins()[0].accepts, [InputKind::Script]);
+        assert_eq!(host.failures().len(), 1);
+        assert!(host.failures()[0].file.ends_with("c-broken.wasm"));
+
+        // `spin` runs out of fuel and is skipped; `echo` still answers.
+        let findings = host.analyze(&Input::Script("You never listen.".to_string()));
+        assert_eq!(
+            findings,
+            [Finding {
+                plugin: "echo".to_string(),
+                code: "tone".to_string(),
+                severity: Severity::Warning,
+                message: "Consider softening this.".to_string(),
+                suggestion: Some("You never listen.".to_string()),
+            }]
+        );
+        assert!(host
+            .analyze(&Input::Transcript("a: hi".to_string()))
+            .is_empty());
+        assert!(!host.accepts(InputKind::Emotion));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
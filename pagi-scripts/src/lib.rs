@@ -0,0 +1,570 @@
+//! Automation scripts in [Rhai](https://rhai.rs), loaded from a scripts directory, so rules like
+//! "if anger is above 0.8 between 9pm and 11pm, schedule a check-in recording" need no rebuild.
+//!
+//! A script is a `*.rhai` file defining any of the hook functions. Each takes one argument, a
+//! map built from the event's JSON:
+//!
+//! - `on_recording_finished(recording)`: a recording ended. `source` says which kind: a
+//!   `"meeting"` has `session_id`, `participants`, `keywords`, `summary` and `transcript` (one
+//!   `speaker: text` line per segment); a `"capture"` has `path`, `purpose`, `duration_secs`,
+//!   `audio` and `video`.
+//! - `on_emotion_alert(alert)`: a sustained or recurring negative emotion; `emotion`,
+//!   `confidence`, `profile`, `trigger` (`kind` plus `minutes` or `count`), `notify`, `ts_unix`.
+//! - `on_ghost_response(response)`: a Relational Ghost simulation finished; the simulation
+//!   result as `/api/ghost/simulate` returns it (`risk_score`, `drift_alert`, `ghost_reply`, ...).
+//!
+//! Scripts act by calling host functions, which queue [`Action`]s for the server to carry out
+//! after the hook returns:
+//!
+//! - `schedule_recording(#{ in_secs: 300, duration_secs: 120, purpose: "check-in" })`
+//! - `notify("message")`: a proactive message, as the check-ins send.
+//!
+//! and can read the clock with `now_unix()`, `local_hour()`, `local_minute()`, `weekday()`
+//! (`"Mon"` to `"Sun"`) and `between_hours(21, 23)` (local time, wrapping past midnight).
+//! `print` and `debug` go to the server log.
+//!
+//! ```rhai
+//! fn on_emotion_alert(alert) {
+//!     if alert.emotion == "Anger" && alert.confidence > 0.8 && between_hours(21, 23) {
+//!         schedule_recording(#{ in_secs: 600, duration_secs: 120, purpose: "evening check-in" });
+//!     }
+//! }
+//! ```
+//!
+//! Scripts can't import modules or touch files, and each hook call has an operation budget and
+//! a time limit. Top-level statements run before every hook; a top-level `const LIMIT = 0.8;`
+//! is `global::LIMIT` inside the hooks. A script that fails is logged and skipped; the other
+//! scripts still run.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, Local, Timelike};
+use pagi_config::{parse_number, ConfigError, Layers};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+/// Actions one script may queue per hook call; queueing more fails the script.
+const MAX_ACTIONS: usize = 16;
+
+/// The `[scripts]` settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptConfig {
+    pub enabled: bool,
+    /// Every `*.rhai` file here is loaded, in name order.
+    pub dir: PathBuf,
+    /// Rhai operations per hook call; a script that needs more is stopped.
+    pub max_operations: u64,
+    /// Wall-clock limit per hook call.
+    pub timeout: Duration,
+}
+
+impl ScriptConfig {
+    /// The `[scripts]` table: `enabled`, `dir` (default: `scripts/` under `data_dir`),
+    /// `max_operations` and `timeout_ms`.
+    pub fn from_layers(layers: &Layers, data_dir: &Path) -> Result<Self, ConfigError> {
+        let at_least_one = |s: &str| match parse_number::<u64>(s)? {
+            0 => Err("must be at least 1".to_string()),
+            n => Ok(n),
+        };
+        Ok(Self {
+            enabled: layers.flag("scripts.enabled", true)?,
+            dir: layers
+                .path("scripts.dir")
+                .unwrap_or_else(|| data_dir.join("scripts")),
+            max_operations: layers.or("scripts.max_operations", 1_000_000, at_least_one)?,
+            timeout: layers.or("scripts.timeout_ms", Duration::from_millis(250), |s| {
+                at_least_one(s).map(Duration::from_millis)
+            })?,
+        })
+    }
+}
+
+/// The events scripts can handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    RecordingFinished,
+    EmotionAlert,
+    GhostResponse,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 3] = [
+        Self::RecordingFinished,
+        Self::EmotionAlert,
+        Self::GhostResponse,
+    ];
+
+    /// The script function that handles it.
+    pub fn function(self) -> &'static str {
+        match self {
+            Self::RecordingFinished => "on_recording_finished",
+            Self::EmotionAlert => "on_emotion_alert",
+            Self::GhostResponse => "on_ghost_response",
+        }
+    }
+}
+
+/// Something a script asked the server to do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// A one-off recording `in_secs` from now.
+    ScheduleRecording {
+        #[serde(default)]
+        in_secs: u64,
+        #[serde(default = "default_duration")]
+        duration_secs: u64,
+        #[serde(default)]
+        purpose: Option<String>,
+    },
+    Notify {
+        message: String,
+    },
+}
+
+fn default_duration() -> u64 {
+    60
+}
+
+/// A loaded script.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScriptInfo {
+    /// The file name without `.rhai`.
+    pub name: String,
+    pub file: PathBuf,
+    /// The hooks it defines.
+    pub hooks: Vec<Hook>,
+}
+
+/// A `.rhai` file that didn't compile.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadFailure {
+    pub file: PathBuf,
+    pub error: String,
+}
+
+/// A script whose hook failed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookFailure {
+    pub script: String,
+    pub error: String,
+}
+
+/// An [`Action`] and the script that asked for it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Queued {
+    pub script: String,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// What one hook call across every script came to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HookRun {
+    /// In script order, then call order.
+    pub actions: Vec<Queued>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<HookFailure>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("cannot read the scripts directory {0}: {1}")]
+    Dir(PathBuf, std::io::Error),
+}
+
+struct Script {
+    info: ScriptInfo,
+    ast: AST,
+}
+
+#[derive(Default)]
+struct Loaded {
+    scripts: Vec<Script>,
+    failures: Vec<LoadFailure>,
+}
+
+/// The loaded scripts. [`ScriptHost::reload`] re-reads the directory in place.
+#[derive(Default)]
+pub struct ScriptHost {
+    config: Option<ScriptConfig>,
+    loaded: RwLock<Loaded>,
+}
+
+impl ScriptHost {
+    /// Compile every script in `config.dir`; a missing directory means none. Files that fail to
+    /// compile are kept in [`ScriptHost::failures`].
+    pub fn load(config: &ScriptConfig) -> Result<Self, ScriptError> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let host = Self {
+            config: Some(config.clone()),
+            loaded: RwLock::default(),
+        };
+        host.reload()?;
+        Ok(host)
+    }
+
+    /// Compile the directory again and swap the result in; on error the old scripts stay.
+    /// A host that was disabled stays empty.
+    pub fn reload(&self) -> Result<(), ScriptError> {
+        let Some(config) = &self.config else {
+            return Ok(());
+        };
+        let engine = engine(config);
+        let mut loaded = Loaded::default();
+        for file in script_files(&config.dir)? {
+            match compile(&engine, &file) {
+                Ok(script) => {
+                    info!(
+                        target: "scripts",
+                        "loaded {} ({:?})",
+                        file.display(),
+                        script.info.hooks
+                    );
+                    loaded.scripts.push(script);
+                }
+                Err(error) => {
+                    warn!(target: "scripts", "cannot load {}: {error}", file.display());
+                    loaded.failures.push(LoadFailure { file, error });
+                }
+            }
+        }
+        *self.loaded.write().unwrap_or_else(|e| e.into_inner()) = loaded;
+        Ok(())
+    }
+
+    /// Whether scripts run at all (`[scripts] enabled`).
+    pub fn enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    pub fn scripts(&self) -> Vec<ScriptInfo> {
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        loaded.scripts.iter().map(|s| s.info.clone()).collect()
+    }
+
+    pub fn failures(&self) -> Vec<LoadFailure> {
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        loaded.failures.clone()
+    }
+
+    /// Whether any script defines `hook`, so callers can skip building the payload.
+    pub fn handles(&self, hook: Hook) -> bool {
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        loaded.scripts.iter().any(|s| s.info.hooks.contains(&hook))
+    }
+
+    /// Call `hook` in every script that defines it, in load order, and collect what they ask
+    /// for. This runs the scripts on the calling thread; async callers should use
+    /// `spawn_blocking`.
+    pub fn run(&self, hook: Hook, payload: &serde_json::Value) -> HookRun {
+        let mut run = HookRun::default();
+        let Some(config) = &self.config else {
+            return run;
+        };
+        let argument = match rhai::serde::to_dynamic(payload) {
+            Ok(argument) => argument,
+            Err(e) => {
+                warn!(target: "scripts", ?hook, "cannot pass the payload: {e}");
+                return run;
+            }
+        };
+        let loaded = self.loaded.read().unwrap_or_else(|e| e.into_inner());
+        for script in loaded
+            .scripts
+            .iter()
+            .filter(|s| s.info.hooks.contains(&hook))
+        {
+            let name = &script.info.name;
+            let outbox = Arc::new(Mutex::new(Vec::new()));
+            let engine = hook_engine(config, name, outbox.clone());
+            let result = engine.call_fn_with_options::<Dynamic>(
+                CallFnOptions::new(),
+                &mut Scope::new(),
+                &script.ast,
+                hook.function(),
+                (argument.clone(),),
+            );
+            // Actions queued before a failure still count: the script got that far.
+            let actions = std::mem::take(&mut *outbox.lock().unwrap_or_else(|e| e.into_inner()));
+            debug!(target: "scripts", script = %name, ?hook, ?actions, "hook ran");
+            run.actions.extend(actions.into_iter().map(|action| Queued {
+                script: name.clone(),
+                action,
+            }));
+            if let Err(e) = result {
+                warn!(target: "scripts", script = %name, ?hook, "hook failed: {e}");
+                run.failures.push(HookFailure {
+                    script: name.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+        run
+    }
+}
+
+/// An engine with the sandbox limits and no module imports.
+fn engine(config: &ScriptConfig) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(config.max_operations)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(64 * 1024)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000);
+    engine
+}
+
+/// [`engine`] plus the host functions, queueing actions into `outbox`, and the time limit.
+fn hook_engine(config: &ScriptConfig, script: &str, outbox: Arc<Mutex<Vec<Action>>>) -> Engine {
+    let mut engine = engine(config);
+    let deadline = Instant::now() + config.timeout;
+    engine.on_progress(move |_| (Instant::now() > deadline).then(|| Dynamic::from("timed out")));
+
+    let label = script.to_string();
+    engine.on_print(move |s| info!(target: "scripts", script = %label, "{s}"));
+    let label = script.to_string();
+    engine.on_debug(
+        move |s, _, position| debug!(target: "scripts", script = %label, %position, "{s}"),
+    );
+
+    let queue = move |action: Action| -> Result<(), Box<EvalAltResult>> {
+        let mut outbox = outbox.lock().unwrap_or_else(|e| e.into_inner());
+        if outbox.len() >= MAX_ACTIONS {
+            return Err(format!("more than {MAX_ACTIONS} actions in one hook call").into());
+        }
+        outbox.push(action);
+        Ok(())
+    };
+    let schedule = queue.clone();
+    engine.register_fn("schedule_recording", move |options: rhai::Map| {
+        let mut options = options;
+        options.insert("action".into(), "schedule_recording".into());
+        let action = rhai::serde::from_dynamic::<Action>(&options.into())
+            .map_err(|e| format!("schedule_recording: {e}"))?;
+        schedule(action)
+    });
+    engine.register_fn("notify", move |message: &str| {
+        queue(Action::Notify {
+            message: message.to_string(),
+        })
+    });
+
+    engine.register_fn("now_unix", || chrono::Utc::now().timestamp());
+    engine.register_fn("local_hour", || Local::now().hour() as i64);
+    engine.register_fn("local_minute", || Local::now().minute() as i64);
+    engine.register_fn("weekday", || Local::now().weekday().to_string());
+    engine.register_fn("between_hours", |start: i64, end: i64| {
+        let hour = Local::now().hour() as i64;
+        between(hour, start, end)
+    });
+    engine
+}
+
+/// `start <= hour < end`, wrapping past midnight when `start > end`.
+fn between(hour: i64, start: i64, end: i64) -> bool {
+    if start <= end {
+        (start..end).contains(&hour)
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+fn compile(engine: &Engine, file: &Path) -> Result<Script, String> {
+    let source = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
+    let name = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut ast = engine.compile(source).map_err(|e| e.to_string())?;
+    ast.set_source(name.as_str());
+    let mut hooks = Vec::new();
+    for function in ast.iter_functions() {
+        let Some(hook) = Hook::ALL
+            .into_iter()
+            .find(|h| h.function() == function.name)
+        else {
+            continue;
+        };
+        if function.params.len() != 1 {
+            return Err(format!(
+                "{} takes one argument, not {}",
+                function.name,
+                function.params.len()
+            ));
+        }
+        hooks.push(hook);
+    }
+    Ok(Script {
+        info: ScriptInfo {
+            name,
+            file: file.to_path_buf(),
+            hooks,
+        },
+        ast,
+    })
+}
+
+fn script_files(dir: &Path) -> Result<Vec<PathBuf>, ScriptError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ScriptError::Dir(dir.to_path_buf(), e)),
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|x| x == "rhai"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pagi_config::Overrides;
+    use serde_json::json;
+
+    fn host_with(scripts: &[(&str, &str)]) -> (ScriptHost, PathBuf) {
+        let dir = std::env::temp_dir().join(format!(
+            "pagi-scripts-{}-{}",
+            std::process::id(),
+            scripts.len()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, source) in scripts {
+            std::fs::write(dir.join(file), source).unwrap();
+        }
+        let config = ScriptConfig {
+            enabled: true,
+            dir: dir.clone(),
+            max_operations: 10_000,
+            timeout: Duration::from_secs(5),
+        };
+        (ScriptHost::load(&config).unwrap(), dir)
+    }
+
+    #[test]
+    fn config_defaults_under_the_data_dir() {
+        let layers = Layers::parse("phoenix.toml", "", &Overrides::default()).unwrap();
+        let config = ScriptConfig::from_layers(&layers, Path::new("/srv/pagi")).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.dir, Path::new("/srv/pagi/scripts"));
+        assert_eq!(config.timeout, Duration::from_millis(250));
+
+        let layers = Layers::parse(
+            "phoenix.toml",
+            "[scripts]\ntimeout_ms = 0",
+            &Overrides::default(),
+        )
+        .unwrap();
+        let err = ScriptConfig::from_layers(&layers, Path::new(".")).unwrap_err();
+        assert!(err.to_string().contains("scripts.timeout_ms"));
+    }
+
+    #[test]
+    fn hooks_queue_actions() {
+        let (host, dir) = host_with(&[
+            (
+                "a-anger.rhai",
+                r#"
+                const LIMIT = 0.8;
+                fn on_emotion_alert(alert) {
+                    if alert.emotion == "Anger" && alert.confidence > global::LIMIT {
+                        schedule_recording(#{ in_secs: 600, purpose: "check-in" });
+                        notify(`${alert.profile} seems angry`);
+                    }
+                }
+                "#,
+            ),
+            (
+                "b-spin.rhai",
+                "fn on_emotion_alert(alert) { loop { } }\nfn on_ghost_response(r) { }",
+            ),
+            ("c-broken.rhai", "fn on_emotion_alert(alert) {"),
+            ("d-arity.rhai", "fn on_recording_finished() { }"),
+            ("notes.txt", "ignored"),
+        ]);
+        let names: Vec<_> = host.scripts().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["a-anger", "b-spin"]);
+        assert_eq!(
+            host.scripts()[1].hooks,
+            [Hook::EmotionAlert, Hook::GhostResponse]
+        );
+        assert_eq!(host.failures().len(), 2);
+        assert!(!host.handles(Hook::RecordingFinished));
+
+        // `b-spin` runs out of operations; `a-anger` still acts.
+        let run = host.run(
+            Hook::EmotionAlert,
+            &json!({"emotion": "Anger", "confidence": 0.9, "profile": "Sam"}),
+        );
+        assert_eq!(
+            run.actions.into_iter().map(|q| q.action).collect::<Vec<_>>(),
+            [
+                Action::ScheduleRecording {
+                    in_secs: 600,
+                    duration_secs: 60,
+                    purpose: Some("check-in".to_string()),
+                },
+                Action::Notify {
+                    message: "Sam seems angry".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            run.failures,
+            [HookFailure {
+                script: "b-spin".to_string(),
+                error: "Too many operations".to_string(),
+            }]
+        );
+
+        let calm = host.run(
+            Hook::EmotionAlert,
+            &json!({"emotion": "Anger", "confidence": 0.5, "profile": "Sam"}),
+        );
+        assert!(calm.actions.is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bad_actions_and_imports_fail_the_script() {
+        let (host, dir) = host_with(&[
+            (
+                "a.rhai",
+                "fn on_ghost_response(r) { schedule_recording(#{ in_secs: -5 }); }",
+            ),
+            (
+                "b.rhai",
+                "fn on_ghost_response(r) { import \"other\" as other; notify(\"hi\"); }",
+            ),
+        ]);
+        let run = host.run(Hook::GhostResponse, &json!({}));
+        assert!(run.actions.is_empty());
+        assert_eq!(run.failures.len(), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn hour_windows_wrap_past_midnight() {
+        assert!(between(21, 21, 23));
+        assert!(!between(23, 21, 23));
+        assert!(between(23, 22, 7));
+        assert!(between(3, 22, 7));
+        assert!(!between(12, 22, 7));
+    }
+}
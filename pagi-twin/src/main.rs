@@ -12,6 +12,9 @@ use tracing::info;
 #[command(name = "pagi-twin")]
 #[command(about = "PAGI Twin — Unified AGI Desktop Companion", long_about = None)]
 struct Cli {
+    /// Web server config file (default: $PHOENIX_CONFIG, then ./phoenix.toml if it exists)
+    #[arg(long, global = true)]
+    config: Option<std::path::PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -20,9 +23,13 @@ struct Cli {
 enum Commands {
     /// Start the web server with telemetry services
     Web {
-        /// Override the bind address (default: from PHOENIX_WEB_BIND or 127.0.0.1:8888)
+        /// Override the bind address, `host:port` with port 0 for any free port (default: from
+        /// PHOENIX_WEB_BIND / PHOENIX_WEB_HOST / PHOENIX_WEB_PORT or 127.0.0.1:8888)
         #[arg(short, long)]
         bind: Option<String>,
+        /// Override any web server setting, e.g. `--set features.vector_kb=true` (repeatable)
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        set: Vec<String>,
     },
     /// Interactive CLI mode (future implementation)
     Cli,
@@ -32,6 +39,27 @@ enum Commands {
     Desktop,
     /// Run as background daemon
     Daemon,
+    /// Manage API keys for the web server's `/api` routes
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeysAction {
+    /// Create a key and print it once
+    Create {
+        /// Label to recognise the key by
+        name: String,
+        /// Comma-separated scopes: read, record, admin
+        #[arg(long, value_delimiter = ',', default_value = "read")]
+        scopes: Vec<String>,
+    },
+    /// List keys (never the secrets)
+    List,
+    /// Revoke a key by id
+    Revoke { id: String },
 }
 
 #[tokio::main]
@@ -47,7 +75,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let user_name = pagi_utils::env_nonempty("USER_NAME")
         .unwrap_or_else(|| "User".to_string());
 
-    if let Some(path) = dotenv_path {
+    if let Some(path) = &dotenv_path {
         info!("Loaded .env from: {}", path.display());
     }
     info!("Phoenix Name: {}", phoenix_name);
@@ -56,13 +84,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Web { bind } => {
+        Commands::Web { bind, set } => {
             info!("Starting PAGI Twin in Web Server mode");
-            
-            // Override bind address if provided
+
+            let mut overrides = web_overrides(cli.config);
+            for assignment in &set {
+                overrides.set_assignment(assignment)?;
+            }
             if let Some(bind_addr) = bind {
-                std::env::set_var("PHOENIX_WEB_BIND", bind_addr);
+                overrides.set("server.bind", bind_addr);
             }
+            let config = phoenix_web::ServerConfig {
+                dotenv_path: dotenv_path.clone(),
+                ..phoenix_web::ServerConfig::load(&overrides)?
+            };
 
             // Spawn telemetry services as background tasks
             let collector_handle = tokio::spawn(async {
@@ -81,7 +116,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             // Run the main web server (this will block until shutdown)
             info!("Starting Phoenix Web Server");
-            let web_result = phoenix_web::run_server().await;
+            let web_result = phoenix_web::run_server(config).await;
 
             // If web server exits, cancel background tasks
             collector_handle.abort();
@@ -105,6 +140,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("Future: Launch Tauri desktop window with full GUI.");
             println!("Note: Desktop frontend is in phoenix-desktop-tauri directory.");
         }
+        Commands::Keys { action } => run_keys(action, web_overrides(cli.config))?,
         Commands::Daemon => {
             info!("Starting PAGI Twin in Daemon mode");
             println!("Daemon mode not yet implemented. Use 'pagi-twin web' to start the web server.");
@@ -115,6 +151,53 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn web_overrides(config_file: Option<std::path::PathBuf>) -> phoenix_web::settings::Overrides {
+    phoenix_web::settings::Overrides {
+        config_file,
+        ..Default::default()
+    }
+}
+
+/// `pagi-twin keys …`: edit the key file the web server reads (`auth.api_keys_path`).
+fn run_keys(
+    action: KeysAction,
+    overrides: phoenix_web::settings::Overrides,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use phoenix_web::api_keys::{ApiKeyStore, Scope};
+
+    let config = phoenix_web::ServerConfig::load(&overrides)?;
+    // The key file is sealed when the data directory is encrypted.
+    let key_file = config.data_dir.join(pagi_crypto::KEY_FILE);
+    let sealer = pagi_crypto::unlock_existing(&config.encryption, &key_file)?;
+    let store = ApiKeyStore::open(config.auth.api_keys_path, sealer)?;
+    match action {
+        KeysAction::Create { name, scopes } => {
+            let scopes = scopes
+                .iter()
+                .map(|s| Scope::parse(s).ok_or_else(|| format!("unknown scope: {s}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            let created = store.create(&name, &scopes)?;
+            println!("Created key {} ({})", created.record.id, created.record.name);
+            println!("{}", created.key);
+            println!("Store it now; it cannot be shown again.");
+        }
+        KeysAction::List => {
+            for key in store.list() {
+                let scopes = key.scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+                println!("{}  {}  [{}]", key.id, key.name, scopes.join(","));
+            }
+        }
+        KeysAction::Revoke { id } => {
+            if store.revoke(&id)? {
+                println!("Revoked {id}");
+            } else {
+                return Err(format!("no key with id {id}").into());
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Run the Vital Pulse Collector service (telemetry ingestion)
 async fn run_vital_pulse_collector() -> std::io::Result<()> {
     // For now, we'll call the main function from vital_pulse_collector
@@ -0,0 +1,50 @@
+//! Correlation IDs tie together the logs one user action produces across processes.
+//!
+//! The desktop app mints an ID per UI action (or takes the frontend's), records it on the
+//! command's span and sends it to the sidecar as `X-Request-Id`; the sidecar's request span
+//! carries the same ID, and recorder work runs inside that span. Grepping the logs of both
+//! processes for the ID shows the whole path with timings.
+
+/// The HTTP header the ID travels in.
+pub const HEADER: &str = "x-request-id";
+
+const MAX_LEN: usize = 128;
+
+/// A fresh ID: 32 lowercase hex characters.
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// `value` trimmed, if it is short printable ASCII and so safe to log and echo.
+pub fn accept(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let ok =
+        !value.is_empty() && value.len() <= MAX_LEN && value.bytes().all(|b| b.is_ascii_graphic());
+    ok.then_some(value)
+}
+
+/// The caller's ID when it is [acceptable](accept), otherwise a [new one](new_id).
+pub fn or_new(value: Option<&str>) -> String {
+    value
+        .and_then(accept)
+        .map(str::to_string)
+        .unwrap_or_else(new_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_printable_ids() {
+        assert_eq!(accept(" abc-123 "), Some("abc-123"));
+        assert_eq!(accept(""), None);
+        assert_eq!(accept("has space"), None);
+        assert_eq!(accept("line\nbreak"), None);
+        assert_eq!(accept(&"x".repeat(MAX_LEN + 1)), None);
+        assert_eq!(or_new(Some("ui-42")), "ui-42");
+        let fresh = or_new(Some("bad id"));
+        assert_eq!(fresh.len(), 32);
+        assert!(accept(&fresh).is_some());
+    }
+}
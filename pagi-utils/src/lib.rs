@@ -3,8 +3,10 @@
 // Centralized utilities for the PAGI Twin ecosystem.
 // Provides common functions for environment variable handling, logging, and .env loading.
 
+pub mod correlation;
+pub mod logging;
+
 use std::path::{Path, PathBuf};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 /// Returns the value of an environment variable if it exists and is non-empty.
 pub fn env_nonempty(key: &str) -> Option<String> {
@@ -78,27 +80,13 @@ pub fn load_dotenv_best_effort() -> Option<PathBuf> {
     None
 }
 
-/// Initialize tracing subscriber with environment filter.
+/// Initialize tracing for `pagi-twin` from the environment (see [`logging`]).
 /// Uses RUST_LOG environment variable for filtering (defaults to "info").
 pub fn init_tracing() {
-    let filter = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new("info"))
-        .unwrap();
-
-    tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    logging::init(&logging::LogConfig::from_env("pagi-twin"));
 }
 
 /// Initialize tracing subscriber with a custom default log level.
 pub fn init_tracing_with_default(default_level: &str) {
-    let filter = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new(default_level))
-        .unwrap();
-
-    tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    logging::init(&logging::LogConfig::from_env("pagi-twin").with_default_filter(default_level));
 }
@@ -0,0 +1,460 @@
+// pagi-utils/src/logging.rs
+//
+// Log output shared by the PAGI binaries.
+//
+// Environment:
+// - `RUST_LOG`: filter directives (default `info`). Subsystems log under their own targets
+//   (`http`, `ghost`, `recorder`, `sensor`), e.g. `RUST_LOG=info,ghost=debug`.
+// - `PHOENIX_LOG_FORMAT`: `text` (default), `pretty` (multi-line, for development) or `json`
+//   (one object per line, for log shippers).
+// - `PHOENIX_LOG_DIR`: also write logs to `<dir>/<name>.<YYYY-MM-DD>.log`, rotated daily.
+// - `PHOENIX_LOG_KEEP_FILES`: rotated files to keep (default 7).
+//
+// The filter and the number of kept files can be changed after `init` with `set_filter` and
+// `set_keep_files`; format and directory are fixed for the life of the process.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+use crate::env_nonempty;
+
+const DEFAULT_KEEP_FILES: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" | "compact" | "full" => Some(Self::Text),
+            "pretty" => Some(Self::Pretty),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// File name prefix for rotated logs (usually the binary name).
+    pub name: String,
+    /// `RUST_LOG`-style filter directives.
+    pub filter: String,
+    pub format: LogFormat,
+    pub dir: Option<PathBuf>,
+    pub keep_files: usize,
+}
+
+impl LogConfig {
+    /// `info` to stdout as text, no files.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            filter: "info".to_string(),
+            format: LogFormat::default(),
+            dir: None,
+            keep_files: DEFAULT_KEEP_FILES,
+        }
+    }
+
+    pub fn from_env(name: &str) -> Self {
+        let format = env_nonempty("PHOENIX_LOG_FORMAT")
+            .and_then(|s| LogFormat::parse(&s))
+            .unwrap_or_default();
+        let keep_files = env_nonempty("PHOENIX_LOG_KEEP_FILES")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_KEEP_FILES);
+        Self {
+            name: name.to_string(),
+            filter: env_nonempty("RUST_LOG").unwrap_or_else(|| "info".to_string()),
+            format,
+            dir: env_nonempty("PHOENIX_LOG_DIR").map(PathBuf::from),
+            keep_files,
+        }
+    }
+
+    /// Use `filter` unless `RUST_LOG` is set.
+    pub fn with_default_filter(mut self, filter: &str) -> Self {
+        if env_nonempty("RUST_LOG").is_none() {
+            self.filter = filter.to_string();
+        }
+        self
+    }
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+type FilterReloader = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+/// Set by [`init`]: swaps the global filter.
+static FILTER_RELOAD: OnceLock<FilterReloader> = OnceLock::new();
+/// Set by [`init`] when logging to files.
+static LOG_FILE: OnceLock<Arc<RollingFile>> = OnceLock::new();
+
+fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Text => Box::new(layer),
+        LogFormat::Pretty => Box::new(layer.pretty()),
+        LogFormat::Json => Box::new(layer.fmt_fields(JsonFields).event_format(JsonFormat)),
+    }
+}
+
+/// Install the global subscriber: stdout in `config.format`, plus rotated files when
+/// `config.dir` is set (JSON stays JSON; `pretty` is written as `text`).
+pub fn init(config: &LogConfig) {
+    let (filter, filter_error) = match EnvFilter::try_new(&config.filter) {
+        Ok(filter) => (filter, None),
+        Err(e) => (EnvFilter::new("info"), Some(e)),
+    };
+
+    let mut layers: Vec<BoxedLayer> = vec![fmt_layer(config.format, io::stdout, true)];
+    let mut file_error = None;
+    if let Some(dir) = &config.dir {
+        match RollingFile::new(dir, &config.name, config.keep_files) {
+            Ok(file) => {
+                let format = match config.format {
+                    LogFormat::Pretty => LogFormat::Text,
+                    other => other,
+                };
+                let file = Arc::new(file);
+                let _ = LOG_FILE.set(file.clone());
+                layers.push(fmt_layer(format, file, false));
+            }
+            Err(e) => file_error = Some(e),
+        }
+    }
+
+    let (filter, handle) = reload::Layer::new(filter);
+    let _ = FILTER_RELOAD.set(Box::new(move |filter| handle.reload(filter)));
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .init();
+
+    if let Some(e) = filter_error {
+        tracing::warn!("Invalid log filter {:?} ({e}); using info", config.filter);
+    }
+    if let (Some(dir), Some(e)) = (&config.dir, file_error) {
+        tracing::warn!("File logging disabled; cannot use {}: {e}", dir.display());
+    }
+}
+
+/// Replace the filter installed by [`init`] with new `RUST_LOG`-style directives.
+pub fn set_filter(directives: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+    let reload = FILTER_RELOAD
+        .get()
+        .ok_or_else(|| "logging is not initialized".to_string())?;
+    reload(filter).map_err(|e| e.to_string())
+}
+
+/// Change how many rotated log files are kept; extra files go at the next rotation.
+pub fn set_keep_files(keep: usize) {
+    if let Some(file) = LOG_FILE.get() {
+        file.set_keep(keep);
+    }
+}
+
+/// A log file that moves to a new `<name>.<YYYY-MM-DD>.log` each day and deletes the oldest
+/// files beyond `keep`.
+#[derive(Debug)]
+pub struct RollingFile {
+    dir: PathBuf,
+    name: String,
+    keep: AtomicUsize,
+    current: Mutex<Option<(String, File)>>,
+}
+
+impl RollingFile {
+    pub fn new(dir: impl Into<PathBuf>, name: &str, keep: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            name: name.to_string(),
+            keep: AtomicUsize::new(keep.max(1)),
+            current: Mutex::new(None),
+        })
+    }
+
+    pub fn set_keep(&self, keep: usize) {
+        self.keep.store(keep.max(1), Ordering::Relaxed);
+    }
+
+    pub fn path_for(&self, date: &str) -> PathBuf {
+        self.dir.join(format!("{}.{date}.log", self.name))
+    }
+
+    fn is_own(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix(&self.name))
+            .and_then(|n| n.strip_prefix('.'))
+            .and_then(|n| n.strip_suffix(".log"))
+            .is_some_and(|date| date.len() == 10 && !date.contains('.'))
+    }
+
+    /// Delete all but the newest `keep` files (dates sort lexically).
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| self.is_own(p))
+            .collect();
+        files.sort();
+        let excess = files
+            .len()
+            .saturating_sub(self.keep.load(Ordering::Relaxed));
+        for path in &files[..excess] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn write_dated(&self, date: &str, buf: &[u8]) -> io::Result<usize> {
+        let mut current = self
+            .current
+            .lock()
+            .map_err(|_| io::Error::other("log file lock poisoned"))?;
+        if current.as_ref().map(|(d, _)| d.as_str()) != Some(date) {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path_for(date))?;
+            *current = Some((date.to_string(), file));
+            self.prune();
+        }
+        match current.as_mut() {
+            Some((_, file)) => file.write(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Write for &RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        self.write_dated(&today, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current.lock() {
+            Ok(mut current) => match current.as_mut() {
+                Some((_, file)) => file.flush(),
+                None => Ok(()),
+            },
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), Value::from(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+}
+
+fn parse_object(s: &str) -> Map<String, Value> {
+    match serde_json::from_str(s) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Span fields stored as a JSON object so [`JsonFormat`] can merge them.
+pub struct JsonFields;
+
+impl<'writer> FormatFields<'writer> for JsonFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut map = Map::new();
+        fields.record(&mut JsonVisitor(&mut map));
+        write!(writer, "{}", Value::Object(map))
+    }
+
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &tracing::span::Record<'_>,
+    ) -> fmt::Result {
+        let mut map = parse_object(&current.fields);
+        fields.record(&mut JsonVisitor(&mut map));
+        current.fields = Value::Object(map).to_string();
+        Ok(())
+    }
+}
+
+/// One JSON object per event: `timestamp`, `level`, `target`, `message`, the event's other
+/// `fields`, and the enclosing `spans` from the root inwards.
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let meta = event.metadata();
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".into(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        line.insert("level".into(), meta.level().as_str().into());
+        line.insert("target".into(), meta.target().into());
+        if let Some(message) = fields.remove("message") {
+            line.insert("message".into(), message);
+        }
+        if !fields.is_empty() {
+            line.insert("fields".into(), Value::Object(fields));
+        }
+
+        if let Some(scope) = ctx.event_scope() {
+            let spans: Vec<Value> = scope
+                .from_root()
+                .map(|span| {
+                    let mut object = Map::new();
+                    object.insert("name".into(), span.name().into());
+                    if let Some(stored) = span.extensions().get::<FormattedFields<N>>() {
+                        object.extend(parse_object(&stored.fields));
+                    }
+                    Value::Object(object)
+                })
+                .collect();
+            if !spans.is_empty() {
+                line.insert("spans".into(), Value::Array(spans));
+            }
+        }
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_lines_carry_span_fields() {
+        let captured = Captured(Arc::new(Mutex::new(Vec::new())));
+        let sink = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(fmt_layer(
+            LogFormat::Json,
+            move || sink.clone(),
+            false,
+        ));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "request",
+                request_id = "abc",
+                status = tracing::field::Empty
+            );
+            let _entered = span.enter();
+            span.record("status", 200);
+            tracing::info!(target: "ghost", latency_ms = 12, "done");
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["target"], "ghost");
+        assert_eq!(line["message"], "done");
+        assert_eq!(line["fields"]["latency_ms"], 12);
+        assert_eq!(line["spans"][0]["name"], "request");
+        assert_eq!(line["spans"][0]["request_id"], "abc");
+        assert_eq!(line["spans"][0]["status"], 200);
+    }
+
+    #[test]
+    fn rolling_file_rotates_and_prunes() {
+        let dir = std::env::temp_dir().join(format!("pagi-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let file = RollingFile::new(&dir, "phoenix", 2).unwrap();
+        for date in ["2026-01-01", "2026-01-02", "2026-01-03"] {
+            file.write_dated(date, b"line\n").unwrap();
+        }
+        fs::write(dir.join("other.2026-01-01.log"), "x").unwrap();
+
+        assert!(!file.path_for("2026-01-01").exists());
+        assert!(file.path_for("2026-01-02").exists());
+        assert!(file.path_for("2026-01-03").exists());
+        assert!(dir.join("other.2026-01-01.log").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
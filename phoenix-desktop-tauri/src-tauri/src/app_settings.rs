@@ -0,0 +1,373 @@
+//! Desktop app settings persisted as JSON in `vault/app_settings.json`.
+//!
+//! The file carries a `version`; older files are migrated step by step on load (and written
+//! back). A file that can't be read as settings is copied to `app_settings.json.bak` before
+//! anything is written over it, and the app starts with the defaults.
+//!
+//! The recorder is configured from here; the shared config file (`[recorder]` in
+//! `phoenix.toml`, see `pagi_config`) and environment variables only seed the recorder section
+//! the first time.
+
+use multi_modal_recording::RecorderConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::{broadcast, RwLock};
+
+use crate::idle_pause::IdlePauseSettings;
+use crate::mini_recorder::MiniRecorderSettings;
+use crate::onboarding::{OnboardingState, OnboardingStep};
+use crate::shortcuts::ShortcutSettings;
+
+/// Current settings file version.
+pub const SETTINGS_VERSION: u32 = 3;
+
+/// Command-line flag that starts the app hidden in the tray.
+pub const MINIMIZED_ARG: &str = "--minimized";
+
+/// Flag the autostart entry launches with; the app then honours `start_minimized`.
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    Light,
+    Dark,
+    #[default]
+    System,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+}
+
+fn default_accent() -> String {
+    "#7c5cff".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeSettings {
+    #[serde(default)]
+    pub mode: ThemeMode,
+    /// `#rrggbb`; also tints the tray icon while listening.
+    #[serde(default = "default_accent")]
+    pub accent: String,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            mode: ThemeMode::default(),
+            accent: default_accent(),
+        }
+    }
+}
+
+impl ThemeSettings {
+    pub fn accent_rgb(&self) -> Option<[u8; 3]> {
+        let hex = self.accent.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.accent_rgb()
+            .map(|_| ())
+            .ok_or_else(|| format!("accent must be a #rrggbb color, got '{}'", self.accent))
+    }
+
+    /// Window theme to force, `None` to follow the OS.
+    pub fn window_theme(&self) -> Option<tauri::Theme> {
+        match self.mode {
+            ThemeMode::Light => Some(tauri::Theme::Light),
+            ThemeMode::Dark => Some(tauri::Theme::Dark),
+            ThemeMode::System => None,
+        }
+    }
+}
+
+/// The recorder section a new settings file starts with.
+fn seed_recorder() -> RecorderConfig {
+    RecorderConfig::load().unwrap_or_else(|e| {
+        tracing::warn!(target: "settings", "{e}; seeding the recorder from the environment only");
+        RecorderConfig::from_env()
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSettings {
+    #[serde(default)]
+    pub version: u32,
+    /// Launch on login.
+    #[serde(default)]
+    pub autostart: bool,
+    /// Go straight to the tray when launched on login.
+    #[serde(default)]
+    pub start_minimized: bool,
+    #[serde(default)]
+    pub theme: ThemeSettings,
+    /// Recorder configuration; `always_listening` tracks the last state and is restored on
+    /// startup.
+    #[serde(default = "seed_recorder")]
+    pub recorder: RecorderConfig,
+    /// Folders the pickers last opened in.
+    #[serde(default)]
+    pub last_enrollment_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub last_export_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    #[serde(default)]
+    pub mini_recorder: MiniRecorderSettings,
+    /// Auto-pause of always-listening while the user is away.
+    #[serde(default)]
+    pub idle_pause: IdlePauseSettings,
+    /// Hotkey overrides (see `shortcuts`).
+    #[serde(default)]
+    pub shortcuts: ShortcutSettings,
+    /// First-run wizard progress.
+    #[serde(default)]
+    pub onboarding: OnboardingState,
+    /// UI language for backend strings (`en`, `es`, …); `None` follows the system locale.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            autostart: false,
+            start_minimized: false,
+            theme: ThemeSettings::default(),
+            recorder: seed_recorder(),
+            last_enrollment_dir: None,
+            last_export_dir: None,
+            update_channel: UpdateChannel::default(),
+            mini_recorder: MiniRecorderSettings::default(),
+            idle_pause: IdlePauseSettings::default(),
+            shortcuts: ShortcutSettings::default(),
+            onboarding: OnboardingState::default(),
+            language: None,
+        }
+    }
+}
+
+impl AppSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.theme.validate()?;
+        self.idle_pause.validate()?;
+        self.shortcuts.validate()?;
+        crate::i18n::validate(self.language.as_deref())?;
+        self.recorder.validate().map_err(|e| e.to_string())
+    }
+}
+
+/// Upgrade a settings document of any older version to [`SETTINGS_VERSION`].
+fn migrate(mut doc: Value) -> Value {
+    let Some(obj) = doc.as_object_mut() else {
+        return doc;
+    };
+    let version = obj.get("version").and_then(Value::as_u64).unwrap_or(1);
+    if version < 2 {
+        // v1 kept the last always-listening state at the top level and had no recorder section.
+        let mut recorder = serde_json::to_value(seed_recorder()).unwrap_or_default();
+        if let Some(listening) = obj.remove("always_listening") {
+            recorder["always_listening"] = listening;
+        }
+        obj.insert("recorder".to_string(), recorder);
+    }
+    if version < 3 {
+        // Onboarding arrived in v3; existing installs are already set up.
+        let done = OnboardingState {
+            step: OnboardingStep::Done,
+            ..Default::default()
+        };
+        obj.insert(
+            "onboarding".to_string(),
+            serde_json::to_value(done).unwrap_or_default(),
+        );
+    }
+    obj.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    doc
+}
+
+/// RFC 7386 JSON merge patch: objects merge recursively, `null` removes, anything else replaces.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(obj) = target else {
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            obj.remove(key);
+        } else {
+            merge_patch(obj.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+fn settings_path() -> Result<PathBuf, String> {
+    Ok(crate::security::vault_dir()?.join("app_settings.json"))
+}
+
+/// Why the saved settings weren't used at startup.
+#[derive(Debug, Clone)]
+pub struct LoadProblem {
+    pub error: String,
+    /// Where the unreadable file was copied. `None` when it couldn't be copied; settings are
+    /// then not saved for the rest of the run, so the file stays as it was.
+    pub backup: Option<PathBuf>,
+}
+
+/// Copy the unreadable settings file aside before anything replaces it.
+fn set_aside(path: &Path, error: String) -> LoadProblem {
+    let backup = path.with_extension("json.bak");
+    match std::fs::copy(path, &backup) {
+        Ok(_) => LoadProblem {
+            error,
+            backup: Some(backup),
+        },
+        Err(e) => LoadProblem {
+            error: format!("{error} (copying it to {} failed: {e})", backup.display()),
+            backup: None,
+        },
+    }
+}
+
+fn load(path: &Path) -> (AppSettings, Option<LoadProblem>) {
+    let raw = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return (AppSettings::default(), None),
+        Err(e) => return (AppSettings::default(), Some(set_aside(path, e.to_string()))),
+    };
+    let doc = match serde_json::from_str::<Value>(&raw) {
+        Ok(doc) => doc,
+        Err(e) => return (AppSettings::default(), Some(set_aside(path, e.to_string()))),
+    };
+    let outdated = doc.get("version").and_then(Value::as_u64) != Some(SETTINGS_VERSION as u64);
+    let settings = match serde_json::from_value::<AppSettings>(migrate(doc)) {
+        Ok(settings) => settings,
+        Err(e) => return (AppSettings::default(), Some(set_aside(path, e.to_string()))),
+    };
+    if outdated {
+        let written = serde_json::to_string_pretty(&settings)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| std::fs::write(path, raw).map_err(|e| e.to_string()));
+        if let Err(error) = written {
+            tracing::warn!(target: "settings", %error, "could not save the migrated settings");
+        }
+    }
+    (settings, None)
+}
+
+#[derive(Debug, Clone)]
+pub struct AppSettingsState {
+    inner: Arc<RwLock<AppSettings>>,
+    tx: broadcast::Sender<AppSettings>,
+    /// Set when the settings file couldn't be read or set aside; updates are refused with it.
+    write_blocked: Option<String>,
+}
+
+impl AppSettingsState {
+    /// Load the settings file; without one, the defaults. When the file can't be used, the
+    /// defaults are returned with the problem, for the caller to tell the user.
+    pub fn load_or_default() -> (Self, Option<LoadProblem>) {
+        let (settings, problem) = match settings_path() {
+            Ok(path) => load(&path),
+            Err(error) => (
+                AppSettings::default(),
+                Some(LoadProblem {
+                    error,
+                    backup: None,
+                }),
+            ),
+        };
+        if let Some(problem) = &problem {
+            tracing::error!(
+                target: "settings",
+                error = %problem.error,
+                backup = ?problem.backup,
+                "saved settings could not be loaded; using the defaults"
+            );
+        }
+        let write_blocked = problem
+            .as_ref()
+            .filter(|p| p.backup.is_none())
+            .map(|p| format!("settings are not saved this session: {}", p.error));
+        let state = Self {
+            inner: Arc::new(RwLock::new(settings)),
+            tx: broadcast::channel(16).0,
+            write_blocked,
+        };
+        (state, problem)
+    }
+
+    /// Receive the new settings after every successful update.
+    pub fn subscribe(&self) -> broadcast::Receiver<AppSettings> {
+        self.tx.subscribe()
+    }
+
+    pub async fn get(&self) -> AppSettings {
+        self.inner.read().await.clone()
+    }
+
+    /// Apply `f`, validate, and persist the result (nothing changes if validation or saving
+    /// fails).
+    pub async fn update(&self, f: impl FnOnce(&mut AppSettings)) -> Result<AppSettings, String> {
+        if let Some(reason) = &self.write_blocked {
+            return Err(reason.clone());
+        }
+        let mut guard = self.inner.write().await;
+        let mut next = guard.clone();
+        f(&mut next);
+        next.version = SETTINGS_VERSION;
+        next.validate()?;
+        let path = settings_path()?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        let raw = serde_json::to_string_pretty(&next).map_err(|e| e.to_string())?;
+        tokio::fs::write(&path, raw)
+            .await
+            .map_err(|e| e.to_string())?;
+        *guard = next;
+        let _ = self.tx.send(guard.clone());
+        Ok(guard.clone())
+    }
+}
+
+/// Whether this launch should go straight to the tray.
+pub fn launch_minimized(settings: &AppSettings) -> bool {
+    let args = std::env::args().collect::<Vec<_>>();
+    args.iter().any(|a| a == MINIMIZED_ARG)
+        || (settings.start_minimized && args.iter().any(|a| a == AUTOSTART_ARG))
+}
@@ -0,0 +1,178 @@
+//! Clipboard round-trip for the ghost / resonance analyzers.
+//!
+//! A message drafted in another app is read from the clipboard and sent to phoenix-web's
+//! counselor routes (resonance always, the Relational Ghost on request); a suggested rewrite can
+//! be copied back. The `analyze_clipboard` shortcut (unbound by default) runs the same analysis
+//! and emits the result as `clipboard-analysis`. Each request carries the analysis's correlation
+//! ID as `X-Request-Id` (see [`crate::command_trace`]).
+
+use serde::{Deserialize, Serialize};
+use pagi_utils::correlation;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tracing::Instrument;
+
+use crate::web_sidecar::WebSidecar;
+use crate::{command_trace, i18n, notifications};
+
+/// Longest script sent for analysis, in characters.
+const MAX_SCRIPT_CHARS: usize = 20_000;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_PERSONA: &str = "secure";
+const DEFAULT_INTENSITY: u8 = 50;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClipboardAnalysisRequest {
+    /// Partner persona (loose label, e.g. `avoidant`); defaults to `secure`.
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// `gentle` | `direct`.
+    #[serde(default)]
+    pub tone: Option<String>,
+    /// Also run the Relational Ghost simulation.
+    #[serde(default)]
+    pub simulate: bool,
+    #[serde(default)]
+    pub intensity_level: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipboardAnalysis {
+    pub script: String,
+    /// `ResonanceResult` from `POST /api/counselor/resonate`.
+    pub resonance: Value,
+    /// `SimulateResponse` from `POST /api/counselor/ghost/simulate`, when requested.
+    pub ghost: Option<Value>,
+}
+
+pub fn read_script(app: &AppHandle) -> Result<String, String> {
+    let text = app
+        .clipboard()
+        .read_text()
+        .map_err(|e| format!("couldn't read the clipboard: {e}"))?;
+    let script = text.trim();
+    if script.is_empty() {
+        return Err("the clipboard has no text".to_string());
+    }
+    if script.chars().count() > MAX_SCRIPT_CHARS {
+        return Err(format!(
+            "the clipboard text is too long to analyze (over {MAX_SCRIPT_CHARS} characters)"
+        ));
+    }
+    Ok(script.to_string())
+}
+
+pub fn copy(app: &AppHandle, text: &str) -> Result<(), String> {
+    app.clipboard()
+        .write_text(text.to_string())
+        .map_err(|e| format!("couldn't write the clipboard: {e}"))
+}
+
+async fn post(
+    base_url: &str,
+    path: &str,
+    body: Value,
+    correlation_id: &str,
+) -> Result<Value, String> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let started = Instant::now();
+    let resp = client
+        .post(format!("{base_url}{path}"))
+        .header(correlation::HEADER, correlation_id)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("{path}: {e}"))?;
+    let status = resp.status();
+    tracing::debug!(
+        target: "command",
+        path,
+        status = status.as_u16(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "sidecar answered"
+    );
+    let value = resp
+        .json::<Value>()
+        .await
+        .map_err(|e| format!("{path}: {e}"))?;
+    if !status.is_success() {
+        let message = value
+            .get("message")
+            .or_else(|| value.get("error"))
+            .and_then(Value::as_str)
+            .unwrap_or("request failed");
+        return Err(format!("{path}: {status}: {message}"));
+    }
+    Ok(value)
+}
+
+/// Analyze the clipboard text with phoenix-web.
+pub async fn analyze(
+    app: &AppHandle,
+    req: &ClipboardAnalysisRequest,
+    correlation_id: &str,
+) -> Result<ClipboardAnalysis, String> {
+    let script = read_script(app)?;
+    let base_url = app
+        .state::<WebSidecar>()
+        .status()
+        .base_url
+        .ok_or_else(|| "the web server isn't running".to_string())?;
+    let persona = req.persona.as_deref().unwrap_or(DEFAULT_PERSONA);
+
+    let resonance = post(
+        &base_url,
+        "/api/counselor/resonate",
+        json!({ "persona": persona, "script": script, "tone": req.tone }),
+        correlation_id,
+    )
+    .await?;
+    let ghost = if req.simulate {
+        Some(
+            post(
+                &base_url,
+                "/api/counselor/ghost/simulate",
+                json!({
+                    "script": script,
+                    "persona_type": persona,
+                    "intensity_level": req.intensity_level.unwrap_or(DEFAULT_INTENSITY).min(100),
+                }),
+                correlation_id,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+    Ok(ClipboardAnalysis {
+        script,
+        resonance,
+        ghost,
+    })
+}
+
+/// Shortcut handler: analyze with defaults and show the result in the main window.
+pub async fn analyze_from_shortcut(app: AppHandle) {
+    let correlation_id = correlation::new_id();
+    let span = command_trace::span("analyze_clipboard", Some(correlation_id.as_str()));
+    match analyze(&app, &ClipboardAnalysisRequest::default(), &correlation_id)
+        .instrument(span)
+        .await
+    {
+        Ok(analysis) => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            let _ = app.emit("clipboard-analysis", &analysis);
+        }
+        Err(e) => {
+            notifications::notify(&app, i18n::t("clipboard-failed"), e, None);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! Tracing for commands the UI invokes.
+//!
+//! Recorder commands take an optional `correlationId` (any short printable string the UI mints
+//! per action) and run inside a `command` span carrying it, or a fresh ID when the UI sent none.
+//! The recorder's own spans (`recording`, `listening`, `enrollment_*`) nest inside, with `debug`
+//! events timing each phase. Requests the app makes to the web sidecar send the same ID as
+//! `X-Request-Id`, so the sidecar's request logs carry it too; see [`pagi_utils::correlation`].
+//!
+//! Logs go to stdout, filtered by `RUST_LOG` (`info` by default; `recorder=debug` shows the
+//! phase timings), and to rotated files when `PHOENIX_LOG_DIR` is set.
+
+use std::time::Instant;
+
+use pagi_utils::correlation;
+use pagi_utils::logging::LogConfig;
+use tracing::Span;
+
+pub fn init() {
+    pagi_utils::logging::init(&LogConfig::from_env("pagi-desktop"));
+}
+
+/// The span for one invocation of `command`.
+pub fn span(command: &'static str, correlation_id: Option<&str>) -> Span {
+    let correlation_id = correlation::or_new(correlation_id);
+    tracing::info_span!(target: "command", "command", command, correlation_id = %correlation_id)
+}
+
+/// Log how long a command waited for the recorder lock; a capture or enrollment holding it is
+/// the usual reason a recording is slow to start.
+pub fn lock_acquired(since: Instant) {
+    tracing::debug!(
+        target: "command",
+        waited_ms = since.elapsed().as_millis() as u64,
+        "recorder lock acquired"
+    );
+}
@@ -0,0 +1,267 @@
+//! Local-first crash reports.
+//!
+//! A panic hook writes each panic as JSON to `logs/crashes/<id>.json`, tagged with the app
+//! version and the subsystem the panic came from (derived from its source file). A session
+//! marker is written at startup and removed on an orderly exit; if it is still there on the next
+//! launch the previous session crashed (panic or not), and the app shows a notice linking to the
+//! report. Reports never leave the machine unless the user sends one, which posts it to
+//! `PAGI_CRASH_REPORT_ENDPOINT`.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{audit, diagnostics};
+
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+const ENDPOINT_ENV: &str = "PAGI_CRASH_REPORT_ENDPOINT";
+const SESSION_FILE: &str = "session.json";
+/// Older reports are pruned.
+const MAX_REPORTS: usize = 50;
+const RECENT_ERRORS_IN_REPORT: usize = 20;
+
+/// The previous session's crash, found by [`begin_session`].
+static LAST_SESSION_CRASH: OnceLock<Option<CrashReport>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    Panic,
+    /// The process ended without shutting down (killed, aborted, power loss…).
+    UncleanExit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub ts_unix: i64,
+    pub kind: CrashKind,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    /// e.g. `web_sidecar` or `multi_modal_recording::playback`.
+    pub subsystem: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: Option<String>,
+    /// The error ring at the time of the crash.
+    #[serde(default)]
+    pub recent_errors: Vec<diagnostics::ErrorEntry>,
+    #[serde(default)]
+    pub sent_unix: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionMarker {
+    pid: u32,
+    started_unix: i64,
+    app_version: String,
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+fn crashes_dir() -> Result<PathBuf, String> {
+    Ok(audit::logs_dir()?.join("crashes"))
+}
+
+impl CrashReport {
+    fn new(kind: CrashKind, subsystem: &str, message: String) -> Self {
+        let now = now();
+        Self {
+            id: format!("{}-{}", now.as_millis(), std::process::id()),
+            ts_unix: now.as_secs() as i64,
+            kind,
+            app_version: APP_VERSION.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            subsystem: subsystem.to_string(),
+            message,
+            location: None,
+            thread: None,
+            backtrace: None,
+            recent_errors: Vec::new(),
+            sent_unix: None,
+        }
+    }
+}
+
+/// `…/multi_modal_recording/src/playback.rs` → `multi_modal_recording::playback`; this app's
+/// own files map to just the module name.
+fn subsystem_of(file: &str) -> String {
+    let parts = file.split(['/', '\\']).collect::<Vec<_>>();
+    let Some(src) = parts.iter().rposition(|p| *p == "src") else {
+        return file.to_string();
+    };
+    let module = parts[src + 1..]
+        .join("::")
+        .trim_end_matches(".rs")
+        .trim_end_matches("::mod")
+        .to_string();
+    match parts.get(src.wrapping_sub(1)) {
+        Some(&"src-tauri") | None => module,
+        Some(krate) if module == "lib" || module == "main" => krate.to_string(),
+        Some(krate) => format!("{krate}::{module}"),
+    }
+}
+
+fn save(report: &CrashReport) -> Result<PathBuf, String> {
+    let dir = crashes_dir()?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("{}.json", report.id));
+    let raw = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, raw).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Write a report for every panic, then run the previous hook.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic with a non-string payload".to_string());
+        let subsystem = info
+            .location()
+            .map(|l| subsystem_of(l.file()))
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut report = CrashReport::new(CrashKind::Panic, &subsystem, message);
+        report.location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        report.thread = std::thread::current().name().map(str::to_string);
+        report.backtrace = Some(std::backtrace::Backtrace::force_capture().to_string());
+        let errors = diagnostics::recent_errors();
+        report.recent_errors = errors
+            .into_iter()
+            .rev()
+            .take(RECENT_ERRORS_IN_REPORT)
+            .collect();
+        if let Err(e) = save(&report) {
+            tracing::error!(target: "crash", "failed to save crash report: {e}");
+        }
+        previous(info);
+    }));
+}
+
+/// Mark this session as running; detect whether the previous one crashed.
+pub fn begin_session() {
+    let found = crashes_dir().ok().and_then(|dir| {
+        let marker_path = dir.join(SESSION_FILE);
+        let marker = std::fs::read_to_string(&marker_path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<SessionMarker>(&raw).ok())?;
+        // A panic report from that session says more than a bare unclean exit.
+        let panic = list()
+            .into_iter()
+            .find(|r| r.kind == CrashKind::Panic && r.ts_unix >= marker.started_unix);
+        Some(panic.unwrap_or_else(|| {
+            let mut report = CrashReport::new(
+                CrashKind::UncleanExit,
+                "app",
+                format!(
+                    "session started at {} (pid {}) ended without shutting down",
+                    marker.started_unix, marker.pid
+                ),
+            );
+            report.app_version = marker.app_version;
+            if let Err(e) = save(&report) {
+                tracing::error!(target: "crash", "failed to save crash report: {e}");
+            }
+            report
+        }))
+    });
+    let _ = LAST_SESSION_CRASH.set(found);
+
+    let marker = SessionMarker {
+        pid: std::process::id(),
+        started_unix: now().as_secs() as i64,
+        app_version: APP_VERSION.to_string(),
+    };
+    let written = crashes_dir().and_then(|dir| {
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let raw = serde_json::to_string(&marker).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(SESSION_FILE), raw).map_err(|e| e.to_string())
+    });
+    if let Err(e) = written {
+        diagnostics::report_error("crash", format!("failed to write session marker: {e}"));
+    }
+    prune();
+}
+
+/// Orderly exit: the next launch won't report a crash.
+pub fn end_session() {
+    if let Ok(dir) = crashes_dir() {
+        let _ = std::fs::remove_file(dir.join(SESSION_FILE));
+    }
+}
+
+pub fn last_session_crash() -> Option<CrashReport> {
+    LAST_SESSION_CRASH.get().cloned().flatten()
+}
+
+/// Saved reports, newest first.
+pub fn list() -> Vec<CrashReport> {
+    let Ok(rd) = crashes_dir().and_then(|d| std::fs::read_dir(d).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let mut reports = rd
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension().and_then(|s| s.to_str()) == Some("json")
+                && p.file_name().and_then(|s| s.to_str()) != Some(SESSION_FILE)
+        })
+        .filter_map(|p| std::fs::read_to_string(p).ok())
+        .filter_map(|raw| serde_json::from_str::<CrashReport>(&raw).ok())
+        .collect::<Vec<_>>();
+    reports.sort_by(|a, b| b.ts_unix.cmp(&a.ts_unix).then_with(|| b.id.cmp(&a.id)));
+    reports
+}
+
+fn prune() {
+    let Ok(dir) = crashes_dir() else {
+        return;
+    };
+    for old in list().into_iter().skip(MAX_REPORTS) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.json", old.id)));
+    }
+}
+
+pub fn get(id: &str) -> Result<CrashReport, String> {
+    list()
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("no crash report '{id}'"))
+}
+
+/// Post a report to the configured endpoint (only on the user's request).
+pub async fn send(id: &str) -> Result<CrashReport, String> {
+    let endpoint = std::env::var(ENDPOINT_ENV)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| format!("no crash report endpoint configured (set {ENDPOINT_ENV})"))?;
+    let mut report = get(id)?;
+    let resp = reqwest::Client::new()
+        .post(endpoint.trim())
+        .timeout(Duration::from_secs(30))
+        .json(&report)
+        .send()
+        .await
+        .map_err(|e| format!("failed to send crash report: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("crash report endpoint answered {}", resp.status()));
+    }
+    report.sent_unix = Some(now().as_secs() as i64);
+    save(&report)?;
+    Ok(report)
+}
@@ -0,0 +1,144 @@
+//! `pagi://` deep links.
+//!
+//! Handled natively:
+//! - `pagi://record/audio?secs=60`, `pagi://record/video`, `pagi://record/av` start an on-demand
+//!   recording (`secs` defaults to 60, max one hour)
+//! - `pagi://listening/on`, `pagi://listening/off` toggle always-listening
+//!
+//! Any web page can open a link, so the ones that start recording or listening only run after
+//! the user allows them in a native dialog.
+//!
+//! Anything else (e.g. `pagi://ghost/simulate?scenario=raise`) focuses the main window and is
+//! handed to the UI as a `deep-link` event `{ route, params }`, so features that live behind the
+//! web API can be reached the same way.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::RecorderState;
+use crate::{i18n, notifications};
+
+pub const SCHEME: &str = "pagi";
+
+const DEFAULT_RECORD_SECS: u64 = 60;
+const MAX_RECORD_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeepLink {
+    Record { audio: bool, video: bool, secs: u64 },
+    Listening { enabled: bool },
+    Ui { route: String, params: BTreeMap<String, String> },
+}
+
+pub fn parse(url: &Url) -> Result<DeepLink, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("unsupported scheme '{}'", url.scheme()));
+    }
+    let params = url.query_pairs().into_owned().collect::<BTreeMap<_, _>>();
+    // `pagi://record/audio` parses with host `record` and path `/audio`.
+    let route = format!(
+        "{}{}",
+        url.host_str().unwrap_or_default(),
+        url.path().trim_end_matches('/')
+    );
+    match route.as_str() {
+        "record/audio" | "record/video" | "record/av" => {
+            let secs = match params.get("secs") {
+                Some(raw) => raw
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|s| (1..=MAX_RECORD_SECS).contains(s))
+                    .ok_or_else(|| format!("secs must be between 1 and {MAX_RECORD_SECS}"))?,
+                None => DEFAULT_RECORD_SECS,
+            };
+            Ok(DeepLink::Record {
+                audio: route != "record/video",
+                video: route != "record/audio",
+                secs,
+            })
+        }
+        "listening/on" => Ok(DeepLink::Listening { enabled: true }),
+        "listening/off" => Ok(DeepLink::Listening { enabled: false }),
+        "" => Err("missing route".to_string()),
+        _ => Ok(DeepLink::Ui { route, params }),
+    }
+}
+
+/// What to ask before following `link`, for links that start capturing.
+fn confirmation(link: &DeepLink) -> Option<String> {
+    match link {
+        DeepLink::Record { audio, video, secs } => {
+            let what = match (audio, video) {
+                (true, false) => "audio",
+                (false, true) => "video",
+                _ => "av",
+            };
+            Some(i18n::t_args(
+                "link-confirm-record",
+                &[("what", what.into()), ("seconds", (*secs).into())],
+            ))
+        }
+        DeepLink::Listening { enabled: true } => Some(i18n::t("link-confirm-listening")),
+        DeepLink::Listening { enabled: false } | DeepLink::Ui { .. } => None,
+    }
+}
+
+pub fn handle(app: &AppHandle, url: &Url) {
+    let link = match parse(url) {
+        Ok(link) => link,
+        Err(e) => {
+            notifications::notify(app, i18n::t("link-unsupported"), format!("{url}: {e}"), None);
+            return;
+        }
+    };
+    let Some(question) = confirmation(&link) else {
+        follow(app, link);
+        return;
+    };
+    let handle = app.clone();
+    app.dialog()
+        .message(question)
+        .title(i18n::t("link-confirm"))
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            i18n::t("link-confirm-allow"),
+            i18n::t("link-confirm-deny"),
+        ))
+        .show(move |allowed| {
+            if allowed {
+                follow(&handle, link);
+            }
+        });
+}
+
+fn follow(app: &AppHandle, link: DeepLink) {
+    match link {
+        DeepLink::Record { audio, video, secs } => {
+            tauri::async_runtime::spawn(crate::tray_record(app.clone(), audio, video, secs));
+        }
+        DeepLink::Listening { enabled } => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let Some(recorder) = app.try_state::<RecorderState>() else {
+                    return;
+                };
+                let rec = recorder.inner.lock().await.clone();
+                match (enabled, rec.status().always_listening) {
+                    (true, false) => rec.start_always_listening().await,
+                    (false, true) => rec.stop_listening(),
+                    _ => {}
+                }
+            });
+        }
+        ui @ DeepLink::Ui { .. } => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            let _ = app.emit("deep-link", &ui);
+        }
+    }
+}
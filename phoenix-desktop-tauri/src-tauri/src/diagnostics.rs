@@ -0,0 +1,232 @@
+//! Diagnostics for the About/Debug screen and support bundles.
+//!
+//! `collect` gathers recorder/device state, model versions, redacted settings, the recent
+//! error ring and the log files present; `write_bundle` zips that report together with the
+//! tail of each log.
+
+use multi_modal_recording::embeddings::CompatibilityReport;
+use multi_modal_recording::model_manager::ModelStatus;
+use multi_modal_recording::{RecorderConfig, RecorderStatus};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::app_settings::AppSettingsState;
+use crate::permissions::{self, MediaPermissions};
+use crate::RecorderState;
+
+const MAX_ERRORS: usize = 100;
+/// Only the end of each log goes into a bundle.
+const LOG_TAIL_BYTES: u64 = 256 * 1024;
+/// Settings keys containing any of these are replaced by [`REDACTED`].
+const SECRET_KEY_PARTS: &[&str] = &[
+    "secret",
+    "token",
+    "password",
+    "passphrase",
+    "api_key",
+    "apikey",
+    "private_key",
+    "credential",
+];
+const REDACTED: &str = "[redacted]";
+
+static RECENT_ERRORS: Mutex<VecDeque<ErrorEntry>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorEntry {
+    pub ts_unix: i64,
+    pub source: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogFile {
+    pub name: String,
+    pub size_bytes: u64,
+    pub modified_unix: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecorderDiagnostics {
+    pub status: RecorderStatus,
+    pub config: RecorderConfig,
+    pub emotion_backend: String,
+    pub input_level: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub generated_unix: i64,
+    pub app_version: String,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub recorder: Option<RecorderDiagnostics>,
+    pub permissions: MediaPermissions,
+    /// Model manifest/install state, or why it could not be read.
+    pub models: Result<Vec<ModelStatus>, String>,
+    pub embeddings: Result<Vec<CompatibilityReport>, String>,
+    /// App settings with secret-looking values redacted.
+    pub settings: Value,
+    pub recent_errors: Vec<ErrorEntry>,
+    pub log_files: Vec<LogFile>,
+}
+
+fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Log an error and keep it for the diagnostics report.
+pub fn report_error(source: &str, message: impl Into<String>) {
+    let entry = ErrorEntry {
+        ts_unix: now_unix(),
+        source: source.to_string(),
+        message: message.into(),
+    };
+    tracing::error!(target: "diagnostics", source = %entry.source, "{}", entry.message);
+    if let Ok(mut errors) = RECENT_ERRORS.lock() {
+        if errors.len() == MAX_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(entry);
+    }
+}
+
+pub fn recent_errors() -> Vec<ErrorEntry> {
+    RECENT_ERRORS
+        .lock()
+        .map(|errors| errors.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(obj) => {
+            for (key, v) in obj.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if SECRET_KEY_PARTS.iter().any(|p| key.contains(p)) && !v.is_null() {
+                    *v = Value::from(REDACTED);
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn unavailable<T>() -> Result<T, String> {
+    Err("recorder not initialized".to_string())
+}
+
+fn log_files() -> Vec<LogFile> {
+    let Ok(dir) = crate::audit::logs_dir() else {
+        return Vec::new();
+    };
+    let mut files = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| {
+                    let meta = e.metadata().ok().filter(|m| m.is_file())?;
+                    Some(LogFile {
+                        name: e.file_name().to_string_lossy().into_owned(),
+                        size_bytes: meta.len(),
+                        modified_unix: meta
+                            .modified()
+                            .ok()
+                            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                            .map(|d| d.as_secs() as i64),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    files
+}
+
+pub async fn collect(app: &AppHandle) -> Diagnostics {
+    let recorder = match app.try_state::<RecorderState>() {
+        Some(state) => Some(state.inner.lock().await.clone()),
+        None => None,
+    };
+    let mut settings = match app.try_state::<AppSettingsState>() {
+        Some(state) => serde_json::to_value(state.get().await).unwrap_or_default(),
+        None => Value::Null,
+    };
+    redact(&mut settings);
+    let permissions = tauri::async_runtime::spawn_blocking(permissions::all)
+        .await
+        .unwrap_or_else(|_| MediaPermissions {
+            microphone: permissions::PermissionState::Unknown,
+            camera: permissions::PermissionState::Unknown,
+            can_prompt: false,
+        });
+
+    Diagnostics {
+        generated_unix: now_unix(),
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        recorder: recorder.as_ref().map(|rec| RecorderDiagnostics {
+            status: rec.status(),
+            config: rec.config(),
+            emotion_backend: rec.emotion_backend().to_string(),
+            input_level: rec.input_level(),
+        }),
+        permissions,
+        models: recorder.as_ref().map_or_else(unavailable, |rec| {
+            rec.model_manager().status().map_err(|e| e.to_string())
+        }),
+        embeddings: recorder.as_ref().map_or_else(unavailable, |rec| {
+            rec.embedding_compatibility().map_err(|e| e.to_string())
+        }),
+        settings,
+        recent_errors: recent_errors(),
+        log_files: log_files(),
+    }
+}
+
+fn log_tail(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Write `diagnostics.json` plus log tails to a zip at `dest`.
+pub fn write_bundle(report: &Diagnostics, dest: &Path) -> Result<(), String> {
+    let file = std::fs::File::create(dest).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let json = serde_json::to_vec_pretty(report).map_err(|e| e.to_string())?;
+    zip.start_file("diagnostics.json", options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(&json).map_err(|e| e.to_string())?;
+
+    let logs_dir = crate::audit::logs_dir()?;
+    for log in &report.log_files {
+        let Ok(tail) = log_tail(&logs_dir.join(&log.name)) else {
+            continue;
+        };
+        zip.start_file(format!("logs/{}", log.name), options)
+            .map_err(|e| e.to_string())?;
+        zip.write_all(&tail).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Local environment sensing for the desktop app.
+//!
+//! Currently: how long the user has been away from keyboard and mouse. Best-effort; `None`
+//! means the platform gives no answer (e.g. a Linux session without `xprintidle` or GNOME's
+//! idle monitor) and callers should treat the user as present.
+
+use std::time::Duration;
+
+/// Time since the last keyboard / mouse input in this session. Blocking (may run a helper
+/// process on Linux).
+pub fn user_idle() -> Option<Duration> {
+    platform::user_idle()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::time::Duration;
+
+    #[repr(C)]
+    struct LastInputInfo {
+        cb_size: u32,
+        dw_time: u32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetLastInputInfo(plii: *mut LastInputInfo) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    pub fn user_idle() -> Option<Duration> {
+        let mut info = LastInputInfo {
+            cb_size: std::mem::size_of::<LastInputInfo>() as u32,
+            dw_time: 0,
+        };
+        // SAFETY: `info` is a properly sized LASTINPUTINFO owned by this frame.
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // Both are 32-bit tick counts that wrap after ~49 days.
+        let now = unsafe { GetTickCount() };
+        Some(Duration::from_millis(now.wrapping_sub(info.dw_time) as u64))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::time::Duration;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = u32::MAX;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    pub fn user_idle() -> Option<Duration> {
+        // SAFETY: plain C function taking and returning scalars.
+        let secs = unsafe {
+            CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT)
+        };
+        (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use std::process::Command;
+    use std::time::Duration;
+
+    fn output(program: &str, args: &[&str]) -> Option<String> {
+        let out = Command::new(program).args(args).output().ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    /// X11: `xprintidle` prints milliseconds.
+    fn xprintidle() -> Option<Duration> {
+        let ms = output("xprintidle", &[])?.trim().parse::<u64>().ok()?;
+        Some(Duration::from_millis(ms))
+    }
+
+    /// The milliseconds in Mutter's `(uint64 12345,)` answer.
+    pub(super) fn parse_mutter(out: &str) -> Option<Duration> {
+        let ms = out
+            .split_once("uint64")?
+            .1
+            .trim_start()
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse::<u64>()
+            .ok()?;
+        Some(Duration::from_millis(ms))
+    }
+
+    /// GNOME (X11 and Wayland): Mutter's idle monitor.
+    fn mutter() -> Option<Duration> {
+        let out = output(
+            "gdbus",
+            &[
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ],
+        )?;
+        parse_mutter(&out)
+    }
+
+    pub fn user_idle() -> Option<Duration> {
+        xprintidle().or_else(mutter)
+    }
+}
+
+#[cfg(all(test, not(any(target_os = "windows", target_os = "macos"))))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_mutter_idle_time() {
+        assert_eq!(
+            platform::parse_mutter("(uint64 12345,)\n"),
+            Some(Duration::from_millis(12345))
+        );
+        assert_eq!(platform::parse_mutter("(uint64 ,)"), None);
+        assert_eq!(platform::parse_mutter("Error: no such interface"), None);
+    }
+}
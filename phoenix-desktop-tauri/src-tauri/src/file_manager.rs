@@ -0,0 +1,51 @@
+//! Show recordings in the OS file manager (Tauri opener plugin).
+//!
+//! The recorder's storage path may be relative (`./data/recordings/encrypted` by default), so it
+//! is resolved against the working directory before being handed to the OS. Purposes map to
+//! subdirectories of it when those exist; only paths inside the recordings tree can be revealed.
+
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+
+/// Absolute recordings root, created if missing.
+pub fn recordings_root(storage_path: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(storage_path).map_err(|e| e.to_string())?;
+    storage_path
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", storage_path.display()))
+}
+
+/// The subdirectory for `purpose`, or the root when there is none (or it has no folder yet).
+pub fn recordings_dir(storage_path: &Path, purpose: Option<&str>) -> Result<PathBuf, String> {
+    let root = recordings_root(storage_path)?;
+    let Some(purpose) = purpose.map(str::trim).filter(|p| !p.is_empty()) else {
+        return Ok(root);
+    };
+    let mut components = Path::new(purpose).components();
+    let (Some(Component::Normal(name)), None) = (components.next(), components.next()) else {
+        return Err(format!("invalid purpose folder '{purpose}'"));
+    };
+    let dir = root.join(name);
+    Ok(if dir.is_dir() { dir } else { root })
+}
+
+pub fn open_dir(app: &AppHandle, dir: &Path) -> Result<(), String> {
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string())
+}
+
+/// Select `recording` in its folder. It must live under the recordings root.
+pub fn reveal(app: &AppHandle, storage_path: &Path, recording: &Path) -> Result<(), String> {
+    let root = recordings_root(storage_path)?;
+    let path = recording
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", recording.display()))?;
+    if !path.starts_with(&root) {
+        return Err(format!("{} is not a recording", recording.display()));
+    }
+    app.opener()
+        .reveal_item_in_dir(&path)
+        .map_err(|e| e.to_string())
+}
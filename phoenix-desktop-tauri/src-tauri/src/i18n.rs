@@ -0,0 +1,31 @@
+//! Localization of backend-originated strings (tray menu, notifications, status lines).
+//!
+//! Messages live in the shared [`pagi_i18n`] crate, in `locales/<lang>/desktop.ftl`. The active
+//! language comes from `AppSettings.language`, or the system locale when that is unset, and can
+//! be switched at runtime; a message missing from the active locale falls back to English.
+
+use serde::Serialize;
+
+pub use pagi_i18n::{language, set_language, t, t_args, LanguageInfo};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageStatus {
+    /// The language in use.
+    pub language: &'static str,
+    /// The saved choice; `None` follows the system locale.
+    pub setting: Option<String>,
+    pub available: Vec<LanguageInfo>,
+}
+
+pub fn status(setting: Option<String>) -> LanguageStatus {
+    LanguageStatus {
+        language: language(),
+        setting,
+        available: pagi_i18n::languages(),
+    }
+}
+
+/// Setting validation: an unknown language is rejected rather than silently ignored.
+pub fn validate(setting: Option<&str>) -> Result<(), String> {
+    pagi_i18n::validate(setting).map_err(|e| e.to_string())
+}
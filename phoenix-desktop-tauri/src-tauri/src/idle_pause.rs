@@ -0,0 +1,267 @@
+//! Pause always-listening while the user is away.
+//!
+//! After `idle_minutes` without keyboard / mouse input (see [`env_sensor::user_idle`]) listening
+//! is stopped; the next input resumes it. Turning listening on by hand, or switching the feature
+//! off, ends an auto-pause. While paused the saved listening preference stays on, so a restart
+//! doesn't lose it. Every pause and resume is appended to `logs/listening.log`, emitted as
+//! `idle-pause` and (unless `notify` is off) shown as a notification.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_settings::AppSettingsState;
+use crate::{audit, diagnostics, env_sensor, i18n, notifications, RecorderState};
+
+const POLL: Duration = Duration::from_secs(15);
+/// Input within this long counts as the user being back.
+const ACTIVE_WITHIN: Duration = Duration::from_secs(30);
+const LOG_FILE: &str = "listening.log";
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_idle_minutes() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlePauseSettings {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_idle_minutes")]
+    pub idle_minutes: u32,
+    /// Show a notification on every pause / resume.
+    #[serde(default = "default_enabled")]
+    pub notify: bool,
+}
+
+impl Default for IdlePauseSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            idle_minutes: default_idle_minutes(),
+            notify: default_enabled(),
+        }
+    }
+}
+
+impl IdlePauseSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=24 * 60).contains(&self.idle_minutes) {
+            return Err(format!(
+                "idle_minutes must be between 1 and 1440, got {}",
+                self.idle_minutes
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct IdlePauseState {
+    auto_paused: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdlePauseStatus {
+    pub settings: IdlePauseSettings,
+    pub auto_paused: bool,
+    /// `None` when idle time can't be read on this system.
+    pub idle_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Change {
+    Paused,
+    Resumed,
+}
+
+/// What a poll does.
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    Wait,
+    /// Listening was turned back on by hand during an auto-pause.
+    Clear,
+    Pause(Duration),
+    Resume(&'static str),
+}
+
+/// Whether [`step`] needs the idle time; reading it may run a helper process.
+fn watches_idle(config: &IdlePauseSettings, listening: bool, paused: bool) -> bool {
+    config.enabled && listening != paused
+}
+
+fn step(config: &IdlePauseSettings, listening: bool, paused: bool, idle: Option<Duration>) -> Step {
+    if paused && listening {
+        return Step::Clear;
+    }
+    if paused && !config.enabled {
+        return Step::Resume("disabled");
+    }
+    if !watches_idle(config, listening, paused) {
+        return Step::Wait;
+    }
+    let Some(idle) = idle else {
+        return Step::Wait;
+    };
+    if !paused && idle >= Duration::from_secs(u64::from(config.idle_minutes) * 60) {
+        Step::Pause(idle)
+    } else if paused && idle <= ACTIVE_WITHIN {
+        Step::Resume("activity")
+    } else {
+        Step::Wait
+    }
+}
+
+/// Listening is off only because the user is away.
+pub fn is_auto_paused(app: &AppHandle) -> bool {
+    app.try_state::<IdlePauseState>()
+        .is_some_and(|s| s.auto_paused.load(Ordering::Relaxed))
+}
+
+async fn idle() -> Option<Duration> {
+    tokio::task::spawn_blocking(env_sensor::user_idle)
+        .await
+        .ok()
+        .flatten()
+}
+
+pub async fn status(app: &AppHandle) -> IdlePauseStatus {
+    let settings = match app.try_state::<AppSettingsState>() {
+        Some(s) => s.get().await.idle_pause,
+        None => IdlePauseSettings::default(),
+    };
+    IdlePauseStatus {
+        settings,
+        auto_paused: is_auto_paused(app),
+        idle_secs: idle().await.map(|d| d.as_secs()),
+    }
+}
+
+fn report(app: &AppHandle, change: Change, reason: &str, notify: bool) {
+    let action = match change {
+        Change::Paused => "auto_pause",
+        Change::Resumed => "auto_resume",
+    };
+    if let Err(e) = audit::append_line(LOG_FILE, &format!("{action} reason={reason}")) {
+        diagnostics::report_error("idle-pause", format!("failed to log {action}: {e}"));
+    }
+    let _ = app.emit(
+        "idle-pause",
+        serde_json::json!({ "change": change, "reason": reason }),
+    );
+    if notify {
+        let (title, body) = match change {
+            Change::Paused => ("listening-paused", "listening-paused-body"),
+            Change::Resumed => ("listening-resumed", "listening-resumed-body"),
+        };
+        notifications::notify(app, i18n::t(title), i18n::t(body), None);
+    }
+}
+
+/// Watch idle time and pause / resume listening until the app exits.
+pub async fn run(app: AppHandle) {
+    let (Some(recorder), Some(settings), Some(state)) = (
+        app.try_state::<RecorderState>(),
+        app.try_state::<AppSettingsState>(),
+        app.try_state::<IdlePauseState>(),
+    ) else {
+        return;
+    };
+    let rec = recorder.inner.lock().await.clone();
+    loop {
+        tokio::time::sleep(POLL).await;
+        if rec.is_shutting_down() {
+            return;
+        }
+        let config = settings.get().await.idle_pause;
+        let listening = rec.status().always_listening;
+        let paused = state.auto_paused.load(Ordering::Relaxed);
+        let idle = if watches_idle(&config, listening, paused) {
+            idle().await
+        } else {
+            None
+        };
+
+        match step(&config, listening, paused, idle) {
+            Step::Wait => {}
+            Step::Clear => {
+                state.auto_paused.store(false, Ordering::Relaxed);
+                let _ = audit::append_line(LOG_FILE, "auto_pause_cleared reason=manual");
+            }
+            Step::Pause(idle) => {
+                // Flag first so the listening change isn't saved as the user's choice.
+                state.auto_paused.store(true, Ordering::Relaxed);
+                rec.stop_listening();
+                report(
+                    &app,
+                    Change::Paused,
+                    &format!("idle_secs={}", idle.as_secs()),
+                    config.notify,
+                );
+            }
+            Step::Resume(reason) => {
+                state.auto_paused.store(false, Ordering::Relaxed);
+                rec.start_always_listening().await;
+                report(&app, Change::Resumed, reason, config.notify);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn settings_default_and_validate() {
+        let settings: IdlePauseSettings = serde_json::from_str("{}").unwrap();
+        assert!(settings.enabled && settings.notify);
+        assert_eq!(settings.idle_minutes, 10);
+        for (minutes, ok) in [(0, false), (1, true), (1440, true), (1441, false)] {
+            let settings = IdlePauseSettings {
+                idle_minutes: minutes,
+                ..IdlePauseSettings::default()
+            };
+            assert_eq!(settings.validate().is_ok(), ok, "{minutes}");
+        }
+    }
+
+    #[test]
+    fn pauses_after_the_idle_time_and_resumes_on_activity() {
+        let on = IdlePauseSettings::default();
+        let idle = |minutes: u32| Some(MINUTE * minutes);
+        assert_eq!(step(&on, true, false, idle(9)), Step::Wait);
+        assert_eq!(step(&on, true, false, idle(10)), Step::Pause(MINUTE * 10));
+        // Still away, then back.
+        assert_eq!(step(&on, false, true, idle(11)), Step::Wait);
+        assert_eq!(
+            step(&on, false, true, Some(Duration::from_secs(5))),
+            Step::Resume("activity")
+        );
+        // No idle reading: the user counts as present.
+        assert_eq!(step(&on, true, false, None), Step::Wait);
+        // Listening off by choice is left alone.
+        assert!(!watches_idle(&on, false, false));
+        assert_eq!(step(&on, false, false, idle(60)), Step::Wait);
+    }
+
+    #[test]
+    fn manual_changes_end_an_auto_pause() {
+        let on = IdlePauseSettings::default();
+        assert_eq!(step(&on, true, true, None), Step::Clear);
+        let off = IdlePauseSettings {
+            enabled: false,
+            ..IdlePauseSettings::default()
+        };
+        assert_eq!(step(&off, false, true, None), Step::Resume("disabled"));
+        assert!(!watches_idle(&off, true, false));
+        assert_eq!(step(&off, true, false, Some(MINUTE * 60)), Step::Wait);
+    }
+}
@@ -2,153 +2,1424 @@
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use multi_modal_recording::MultiModalRecorder;
+use multi_modal_recording::embeddings::{CompatibilityReport, MigrationReport, Modality};
+use multi_modal_recording::emotion_alerts::{AlertRules, AlertTrigger, EmotionAlert};
+use multi_modal_recording::emotion_export::ExportFormat;
+use multi_modal_recording::emotion_history::{EmotionQuery, EmotionalMoment, UNATTRIBUTED_PROFILE};
+use multi_modal_recording::emotion_privacy::{EmotionPrivacy, ProfileEmotionPrivacy, PurgeReport};
+use multi_modal_recording::emotion_track::{EmotionSegment, EmotionTrack};
+use multi_modal_recording::emotion_trends::{EmotionTrends, TrendQuery};
+use multi_modal_recording::enrollment::{CapturedSample, EnrollmentStatus};
+use multi_modal_recording::model_manager::{DiskUsage, ModelStatus};
+use multi_modal_recording::mood_summary::DailyMoodSummary;
+use multi_modal_recording::playback::NowPlaying;
+use multi_modal_recording::presence::{PresenceEvent, UnknownPresenceEvent};
+use multi_modal_recording::recognition::{CalibrationReport, RecognitionThresholds};
+use multi_modal_recording::recording_library::{RecordingFilter, RecordingPage};
+use multi_modal_recording::{
+    Affect, CalibrationConfig, Capabilities, ClearAllConfirmation, FusedEmotion, FusionWeights,
+    GuestMode, MultiModalRecorder, RecorderStatus, RecordingEvent, ReportedEmotion, TextSource,
+};
+use pagi_errors::PagiError;
 use serde::Serialize;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 use tauri::{
-    AppHandle, Manager, State,
-    menu::{Menu, MenuItem, PredefinedMenuItem},
+    AppHandle, Manager, State, Wry,
+    image::Image,
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem},
     tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState},
 };
 use tokio::sync::Mutex;
+use tracing::Instrument;
+
+mod app_settings;
+mod audit;
+mod clipboard;
+mod command_trace;
+mod crash_reports;
+mod deep_link;
+mod diagnostics;
+mod env_sensor;
+mod file_manager;
+mod i18n;
+mod idle_pause;
+mod mini_recorder;
+mod agents;
+mod models;
+mod notifications;
+mod onboarding;
+mod permissions;
+mod pickers;
+mod security;
+mod shortcuts;
+mod shutdown;
+mod tools;
+mod updates;
+mod web_sidecar;
+mod sola_state;
+mod vault;
+mod l7_db;
+mod scout_state;
+
+use crate::agents::researcher::{MemoryInjection, ResearchSession};
+use crate::agents::scout::ScoutAgent;
+use crate::app_settings::{
+    AppSettings, AppSettingsState, ThemeSettings, UpdateChannel, AUTOSTART_ARG,
+};
+use crate::models::zodiac::{ZodiacRegistry, ZodiacSign};
+use crate::notifications::{Notice, NotificationAction};
+use crate::permissions::{MediaDevice, MediaPermissions, PermissionState};
+use crate::security::{VaultHealth, VaultSecurityState};
+use crate::tools::video_scout::{PendingReviewItem, ReviewQueueState, ReviewStatus, ScoutFilter};
+use crate::sola_state::{OrchestratorMode, SolaState};
+use crate::scout_state::{MissionStatus, ScoutMission, ScoutMissionState};
+use crate::models::zodiac::{PhaseThresholds, TrustSignals};
+
+#[derive(Default)]
+struct RecorderState {
+    inner: Arc<Mutex<MultiModalRecorder>>,
+}
+
+#[derive(Clone, Serialize)]
+struct RecordResult {
+    path: String,
+    /// Requested devices the OS blocked; their tracks are empty.
+    blocked: Vec<MediaDevice>,
+}
+
+async fn record_with_modes(
+    state: State<'_, RecorderState>,
+    audio: bool,
+    video: bool,
+    duration_secs: u64,
+) -> Result<RecordResult, PagiError> {
+    let waiting = Instant::now();
+    let rec = state.inner.lock().await.clone();
+    command_trace::lock_acquired(waiting);
+    let rec = rec.clone_with_modes(audio, video);
+    let p = rec.start_on_demand(duration_secs).await.map_err(PagiError::from)?;
+    let blocked = tauri::async_runtime::spawn_blocking(move || permissions::blocked(audio, video))
+        .await
+        .unwrap_or_default();
+    Ok(RecordResult { path: p.display().to_string(), blocked })
+}
+
+#[tauri::command]
+async fn record_audio(
+    state: State<'_, RecorderState>,
+    duration_secs: u64,
+    correlation_id: Option<String>,
+) -> Result<RecordResult, PagiError> {
+    record_with_modes(state, true, false, duration_secs)
+        .instrument(command_trace::span("record_audio", correlation_id.as_deref()))
+        .await
+}
+
+#[tauri::command]
+async fn record_video(
+    state: State<'_, RecorderState>,
+    duration_secs: u64,
+    correlation_id: Option<String>,
+) -> Result<RecordResult, PagiError> {
+    record_with_modes(state, false, true, duration_secs)
+        .instrument(command_trace::span("record_video", correlation_id.as_deref()))
+        .await
+}
+
+#[tauri::command]
+async fn record_av(
+    state: State<'_, RecorderState>,
+    duration_secs: u64,
+    correlation_id: Option<String>,
+) -> Result<RecordResult, PagiError> {
+    record_with_modes(state, true, true, duration_secs)
+        .instrument(command_trace::span("record_av", correlation_id.as_deref()))
+        .await
+}
+
+#[tauri::command]
+async fn media_permissions() -> Result<MediaPermissions, String> {
+    tauri::async_runtime::spawn_blocking(permissions::all)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn request_media_permission(device: MediaDevice) -> Result<PermissionState, String> {
+    tauri::async_runtime::spawn_blocking(move || permissions::request(device))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn open_media_permission_settings(device: MediaDevice) -> Result<(), String> {
+    permissions::open_settings(device)
+}
+
+/// Recording library page (newest first). `page` is zero-based; `page_size` 0 uses the default.
+#[tauri::command]
+async fn list_recordings(
+    state: State<'_, RecorderState>,
+    page: usize,
+    page_size: usize,
+    filters: Option<RecordingFilter>,
+) -> Result<RecordingPage, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.list_recordings(&filters.unwrap_or_default(), page, page_size)
+        .await
+        .map_err(PagiError::from)
+}
+
+/// Play `path` (decrypting it in the backend), or resume the loaded recording when omitted.
+#[tauri::command]
+async fn play(
+    state: State<'_, RecorderState>,
+    path: Option<String>,
+    correlation_id: Option<String>,
+) -> Result<NowPlaying, PagiError> {
+    async {
+        let rec = state.inner.lock().await.clone();
+        rec.play_recording(path.as_deref().map(std::path::Path::new))
+            .await
+            .map_err(PagiError::from)
+    }
+    .instrument(command_trace::span("play", correlation_id.as_deref()))
+    .await
+}
+
+#[tauri::command]
+async fn pause(state: State<'_, RecorderState>) -> Result<NowPlaying, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.pause_playback())
+}
+
+#[tauri::command]
+async fn seek(state: State<'_, RecorderState>, position_secs: f64) -> Result<NowPlaying, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.seek_playback(position_secs).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn now_playing(state: State<'_, RecorderState>) -> Result<NowPlaying, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.now_playing())
+}
+
+#[tauri::command]
+async fn schedule_recording(state: State<'_, RecorderState>, cron_expr: String, purpose: String) -> Result<(), String> {
+    let rec = state.inner.lock().await.clone();
+    rec.schedule_recording(&cron_expr, &purpose).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_always_listening(
+    state: State<'_, RecorderState>,
+    enabled: bool,
+    correlation_id: Option<String>,
+) -> Result<(), String> {
+    async {
+        let rec = state.inner.lock().await.clone();
+        if enabled {
+            rec.start_always_listening().await;
+        } else {
+            rec.stop_listening();
+        }
+    }
+    .instrument(command_trace::span("set_always_listening", correlation_id.as_deref()))
+    .await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn enroll_voice(state: State<'_, RecorderState>, samples: Vec<String>) -> Result<(), PagiError> {
+    let samples = samples.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+    let mut rec = state.inner.lock().await;
+    rec.enroll_user_voice(samples).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn enroll_face(state: State<'_, RecorderState>, images: Vec<String>) -> Result<(), PagiError> {
+    let images = images.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+    let mut rec = state.inner.lock().await;
+    rec.enroll_user_face(images).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn embedding_status(state: State<'_, RecorderState>) -> Result<Vec<CompatibilityReport>, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.embedding_compatibility().map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn migrate_embeddings(state: State<'_, RecorderState>) -> Result<Vec<MigrationReport>, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.migrate_embeddings().await.map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn get_recognition_thresholds(
+    state: State<'_, RecorderState>,
+    profile: String,
+) -> Result<RecognitionThresholds, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.recognition_thresholds(&profile).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn set_recognition_thresholds(
+    state: State<'_, RecorderState>,
+    profile: String,
+    voice: f32,
+    face: f32,
+) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.set_recognition_thresholds(&profile, RecognitionThresholds { voice, face })
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn calibrate_recognition(
+    state: State<'_, RecorderState>,
+    profile: String,
+) -> Result<Vec<CalibrationReport>, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.calibrate_recognition(&profile).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn unknown_presence_recent(
+    state: State<'_, RecorderState>,
+    max: usize,
+) -> Result<Vec<UnknownPresenceEvent>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.unknown_presence_recent(max))
+}
+
+#[tauri::command]
+async fn enroll_unknown_presence(
+    state: State<'_, RecorderState>,
+    event_id: String,
+    profile: String,
+) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.enroll_unknown_presence(&event_id, &profile)
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn start_enrollment(
+    state: State<'_, RecorderState>,
+    profile: String,
+    modality: Modality,
+    steps: Option<usize>,
+    correlation_id: Option<String>,
+) -> Result<EnrollmentStatus, PagiError> {
+    async {
+        let rec = state.inner.lock().await.clone();
+        rec.start_enrollment(&profile, modality, steps.unwrap_or(5))
+            .await
+            .map_err(PagiError::from)
+    }
+    .instrument(command_trace::span("start_enrollment", correlation_id.as_deref()))
+    .await
+}
+
+#[tauri::command]
+async fn enrollment_status(
+    state: State<'_, RecorderState>,
+    session_id: String,
+) -> Result<EnrollmentStatus, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.enrollment_status(&session_id)
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn capture_enrollment_step(
+    state: State<'_, RecorderState>,
+    session_id: String,
+    index: Option<usize>,
+    correlation_id: Option<String>,
+) -> Result<CapturedSample, PagiError> {
+    async {
+        // Clone so the recorder lock isn't held for the length of the capture.
+        let waiting = Instant::now();
+        let rec = state.inner.lock().await.clone();
+        command_trace::lock_acquired(waiting);
+        rec.capture_enrollment_step(&session_id, index)
+            .await
+            .map_err(PagiError::from)
+    }
+    .instrument(command_trace::span("capture_enrollment_step", correlation_id.as_deref()))
+    .await
+}
+
+#[tauri::command]
+async fn submit_enrollment_sample(
+    state: State<'_, RecorderState>,
+    session_id: String,
+    index: usize,
+    path: String,
+) -> Result<CapturedSample, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.submit_enrollment_sample(&session_id, index, PathBuf::from(path))
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn finalize_enrollment(
+    state: State<'_, RecorderState>,
+    session_id: String,
+    correlation_id: Option<String>,
+) -> Result<EnrollmentStatus, PagiError> {
+    async {
+        // Holds the recorder lock throughout; other recorder commands wait for it.
+        let waiting = Instant::now();
+        let mut rec = state.inner.lock().await;
+        command_trace::lock_acquired(waiting);
+        rec.finalize_enrollment(&session_id)
+            .await
+            .map_err(PagiError::from)
+    }
+    .instrument(command_trace::span("finalize_enrollment", correlation_id.as_deref()))
+    .await
+}
+
+#[tauri::command]
+async fn cancel_enrollment(
+    state: State<'_, RecorderState>,
+    session_id: String,
+) -> Result<bool, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.cancel_enrollment(&session_id)
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn set_guest_mode(
+    app: AppHandle,
+    state: State<'_, RecorderState>,
+    enabled: bool,
+    allow_recordings: bool,
+) -> Result<GuestMode, String> {
+    use tauri::Emitter;
+
+    let rec = state.inner.lock().await.clone();
+    rec.set_guest_mode(enabled, allow_recordings);
+    let mode = rec.guest_mode();
+    let _ = app.emit("guest-mode-changed", &mode);
+    Ok(mode)
+}
+
+#[tauri::command]
+async fn guest_mode_status(state: State<'_, RecorderState>) -> Result<GuestMode, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.guest_mode())
+}
+
+#[tauri::command]
+async fn recorder_status(state: State<'_, RecorderState>) -> Result<RecorderStatus, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.status())
+}
+
+/// Which optional recorder subsystems (camera, face recognition, emotion models, speech) this
+/// build includes, so the UI can hide controls that can't work.
+#[tauri::command]
+fn recorder_capabilities() -> Capabilities {
+    Capabilities::compiled()
+}
+
+#[tauri::command]
+async fn list_models(state: State<'_, RecorderState>) -> Result<Vec<ModelStatus>, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.model_manager().status().map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn download_model(state: State<'_, RecorderState>, id: String) -> Result<String, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    let path = rec.model_manager().ensure(&id).await.map_err(PagiError::from)?;
+    Ok(path.display().to_string())
+}
+
+#[tauri::command]
+async fn delete_model(state: State<'_, RecorderState>, id: String) -> Result<bool, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.model_manager().delete(&id).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn pin_model(state: State<'_, RecorderState>, id: String, pinned: bool) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.model_manager().set_pinned(&id, pinned).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn model_disk_usage(state: State<'_, RecorderState>) -> Result<DiskUsage, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.model_manager().disk_usage().map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn delete_last_recording(state: State<'_, RecorderState>) -> Result<bool, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.delete_last_recording().await.map_err(PagiError::from)
+}
+
+/// First step of `clear_all_recordings`: what would be deleted, plus a short-lived token.
+#[tauri::command]
+async fn request_clear_all(
+    state: State<'_, RecorderState>,
+) -> Result<ClearAllConfirmation, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.request_clear_all().await.map_err(PagiError::from)
+}
+
+/// Delete every recording; requires the token from `request_clear_all`.
+#[tauri::command]
+async fn clear_all_recordings(
+    state: State<'_, RecorderState>,
+    token: String,
+    correlation_id: Option<String>,
+) -> Result<u64, PagiError> {
+    async {
+        let rec = state.inner.lock().await.clone();
+        rec.clear_all_recordings(&token)
+            .await
+            .map_err(PagiError::from)
+    }
+    .instrument(command_trace::span("clear_all_recordings", correlation_id.as_deref()))
+    .await
+}
+
+/// The profile recognized by voice or face just now; `None` when nobody is.
+#[tauri::command]
+async fn recognition_status(state: State<'_, RecorderState>) -> Result<Option<String>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.recognized_profile())
+}
+
+#[tauri::command]
+async fn emotion_status(state: State<'_, RecorderState>) -> Result<String, String> {
+    let rec = state.inner.lock().await.clone();
+    let who = rec
+        .attributed_profile()
+        .unwrap_or_else(|| UNATTRIBUTED_PROFILE.to_string());
+    let result = match rec.reported_emotion().await {
+        Some(r) if r.uncertain => i18n::t_args("emotion-uncertain", &[("who", who.into())]),
+        Some(r) => i18n::t_args(
+            "emotion-reading",
+            &[
+                ("who", who.into()),
+                ("label", r.label.into()),
+                ("confidence", format!("{:.0}", r.calibrated_confidence * 100.0).into()),
+            ],
+        ),
+        None => i18n::t_args("emotion-neutral", &[("who", who.into())]),
+    };
+    Ok(result)
+}
+
+#[tauri::command]
+async fn emotion_backend(state: State<'_, RecorderState>) -> Result<String, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotion_backend().to_string())
+}
+
+#[tauri::command]
+async fn emotion_affect(state: State<'_, RecorderState>) -> Result<Option<Affect>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.current_affect().await)
+}
+
+#[tauri::command]
+async fn get_emotion_calibration(
+    state: State<'_, RecorderState>,
+) -> Result<CalibrationConfig, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotion_calibration().await)
+}
+
+#[tauri::command]
+async fn set_emotion_calibration(
+    state: State<'_, RecorderState>,
+    config: CalibrationConfig,
+) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.set_emotion_calibration(config)
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn export_emotion_history(
+    state: State<'_, RecorderState>,
+    query: EmotionQuery,
+    format: ExportFormat,
+    path: String,
+) -> Result<usize, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.export_emotion_history(&query, format, &PathBuf::from(path))
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn get_emotion_privacy(state: State<'_, RecorderState>) -> Result<EmotionPrivacy, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotion_privacy())
+}
+
+#[tauri::command]
+async fn set_profile_emotion_privacy(
+    state: State<'_, RecorderState>,
+    profile: String,
+    settings: ProfileEmotionPrivacy,
+) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.set_profile_emotion_privacy(&profile, settings)
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn purge_profile_emotions(
+    state: State<'_, RecorderState>,
+    profile: String,
+) -> Result<PurgeReport, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.purge_profile_emotions(&profile)
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn emotion_alerts(
+    state: State<'_, RecorderState>,
+    max: usize,
+) -> Result<Vec<EmotionAlert>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotion_alerts_recent(max))
+}
+
+#[tauri::command]
+async fn get_emotion_alert_rules(state: State<'_, RecorderState>) -> Result<AlertRules, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotion_alert_rules())
+}
+
+#[tauri::command]
+async fn set_emotion_alert_rules(
+    state: State<'_, RecorderState>,
+    rules: AlertRules,
+) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.set_emotion_alert_rules(rules).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn recording_emotion_track(
+    state: State<'_, RecorderState>,
+    path: String,
+) -> Result<Option<EmotionTrack>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.recording_emotion_track(&PathBuf::from(path)).await)
+}
+
+#[tauri::command]
+async fn search_recording_emotions(
+    state: State<'_, RecorderState>,
+    emotion: String,
+    min_intensity: Option<f64>,
+    profile: Option<String>,
+) -> Result<Vec<EmotionSegment>, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.search_recording_emotions(&emotion, min_intensity.unwrap_or(0.5), profile.as_deref())
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn mood_summaries(
+    state: State<'_, RecorderState>,
+    max: usize,
+) -> Result<Vec<DailyMoodSummary>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.mood_summaries(max))
+}
+
+#[tauri::command]
+async fn emotion_trends(
+    state: State<'_, RecorderState>,
+    query: TrendQuery,
+) -> Result<EmotionTrends, PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.emotion_trends(&query).map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn emotion_fusion_report(
+    state: State<'_, RecorderState>,
+) -> Result<Option<FusedEmotion>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.last_fusion().await)
+}
+
+#[tauri::command]
+async fn get_emotion_fusion_weights(
+    state: State<'_, RecorderState>,
+) -> Result<FusionWeights, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.fusion_weights().await)
+}
+
+#[tauri::command]
+async fn set_emotion_fusion_weights(
+    state: State<'_, RecorderState>,
+    weights: FusionWeights,
+) -> Result<(), PagiError> {
+    let rec = state.inner.lock().await.clone();
+    rec.set_fusion_weights(weights)
+        .await
+        .map_err(PagiError::from)
+}
+
+#[tauri::command]
+async fn analyze_text_emotion(
+    state: State<'_, RecorderState>,
+    text: String,
+    source: TextSource,
+    reference: Option<String>,
+) -> Result<Option<EmotionalMoment>, String> {
+    let rec = state.inner.lock().await.clone();
+    let reference = reference.unwrap_or_else(|| "text:desktop".to_string());
+    Ok(rec.analyze_text_emotion(&text, source, &reference).await)
+}
+
+#[tauri::command]
+async fn emotion_history_for_profile(
+    state: State<'_, RecorderState>,
+    profile: String,
+    max: usize,
+) -> Result<Vec<EmotionalMoment>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotion_history_for_profile(&profile, max))
+}
+
+#[tauri::command]
+async fn emotion_history(state: State<'_, RecorderState>, max: usize) -> Result<Vec<String>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.emotional_moments_recent(max))
+}
+
+#[tauri::command]
+async fn query_emotion_history(
+    state: State<'_, RecorderState>,
+    query: EmotionQuery,
+) -> Result<Vec<EmotionalMoment>, String> {
+    let rec = state.inner.lock().await.clone();
+    Ok(rec.query_emotion_history(&query))
+}
+
+#[tauri::command]
+fn send_notification(
+    app: AppHandle,
+    title: String,
+    body: String,
+    action: Option<NotificationAction>,
+) -> Result<Notice, String> {
+    Ok(notifications::notify(&app, title, body, action))
+}
+
+#[tauri::command]
+async fn get_settings(settings: State<'_, AppSettingsState>) -> Result<AppSettings, String> {
+    Ok(settings.get().await)
+}
+
+/// Merge `patch` (JSON merge patch) into the settings, persist, and apply the recorder section.
+#[tauri::command]
+async fn update_settings(
+    state: State<'_, RecorderState>,
+    settings: State<'_, AppSettingsState>,
+    patch: serde_json::Value,
+) -> Result<AppSettings, String> {
+    let current = serde_json::to_value(settings.get().await).map_err(|e| e.to_string())?;
+    let mut merged = current;
+    app_settings::merge_patch(&mut merged, &patch);
+    let next: AppSettings = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+    // Saved first, so the recorder never runs a configuration that isn't on disk.
+    let saved = settings.update(|s| *s = next).await?;
+    let rec = {
+        let mut guard = state.inner.lock().await;
+        guard
+            .apply_config(saved.recorder.clone())
+            .map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+    match (saved.recorder.always_listening, rec.status().always_listening) {
+        (true, false) => rec.start_always_listening().await,
+        (false, true) => rec.stop_listening(),
+        _ => {}
+    }
+    Ok(saved)
+}
+
+#[tauri::command]
+async fn set_autostart(
+    app: AppHandle,
+    settings: State<'_, AppSettingsState>,
+    enabled: bool,
+    start_minimized: bool,
+) -> Result<AppSettings, String> {
+    use tauri_plugin_autostart::ManagerExt;
+
+    let autolaunch = app.autolaunch();
+    if enabled {
+        autolaunch.enable().map_err(|e| e.to_string())?;
+    } else {
+        autolaunch.disable().map_err(|e| e.to_string())?;
+    }
+    settings
+        .update(|s| {
+            s.autostart = enabled;
+            s.start_minimized = start_minimized;
+        })
+        .await
+}
+
+#[tauri::command]
+async fn pick_enrollment_samples(
+    app: AppHandle,
+    settings: State<'_, AppSettingsState>,
+    modality: Modality,
+) -> Result<Vec<String>, String> {
+    let start = settings.get().await.last_enrollment_dir;
+    let picked = pickers::pick_enrollment_samples(&app, modality, start).await?;
+    if let Some(dir) = picked.first().and_then(|p| p.parent()) {
+        let dir = dir.to_path_buf();
+        settings.update(|s| s.last_enrollment_dir = Some(dir)).await?;
+    }
+    Ok(picked.into_iter().map(|p| p.display().to_string()).collect())
+}
+
+/// Pick a destination and export the emotion history there; `None` if the user cancelled.
+#[tauri::command]
+async fn pick_and_export_emotion_history(
+    app: AppHandle,
+    state: State<'_, RecorderState>,
+    settings: State<'_, AppSettingsState>,
+    query: EmotionQuery,
+    format: ExportFormat,
+) -> Result<Option<usize>, String> {
+    let start = settings.get().await.last_export_dir;
+    let name = format!("emotion-history.{}", format.extension());
+    let Some(dest) = pickers::pick_save_destination(&app, name, format.extension(), start).await?
+    else {
+        return Ok(None);
+    };
+    if let Some(dir) = dest.parent() {
+        let dir = dir.to_path_buf();
+        settings.update(|s| s.last_export_dir = Some(dir)).await?;
+    }
+    let rec = state.inner.lock().await.clone();
+    rec.export_emotion_history(&query, format, &dest)
+        .await
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Pick a destination and save the web server's full data export there: every setting,
+/// profile, emotion sample, transcript and recording manifest, with the recordings themselves
+/// when `media` is set. Returns the file written, or `None` if the user cancelled.
+#[tauri::command]
+async fn pick_and_export_all_data(
+    app: AppHandle,
+    sidecar: State<'_, web_sidecar::WebSidecar>,
+    settings: State<'_, AppSettingsState>,
+    media: bool,
+) -> Result<Option<String>, String> {
+    let base_url = sidecar
+        .status()
+        .base_url
+        .ok_or_else(|| "the web server isn't running".to_string())?;
+    let start = settings.get().await.last_export_dir;
+    let name = "phoenix-export.tar.gz".to_string();
+    let Some(dest) = pickers::pick_save_destination(&app, name, "gz", start).await? else {
+        return Ok(None);
+    };
+    if let Some(dir) = dest.parent() {
+        let dir = dir.to_path_buf();
+        settings.update(|s| s.last_export_dir = Some(dir)).await?;
+    }
+
+    let mut resp = reqwest::Client::new()
+        .get(format!("{base_url}/api/admin/export?media={media}"))
+        .send()
+        .await
+        .map_err(|e| format!("export failed: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("export failed: {}", resp.status()));
+    }
+    // Written next to the destination and moved into place, so a failed export leaves nothing
+    // that looks complete.
+    let partial = dest.with_extension("gz.part");
+    let written = async {
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::File::create(&partial).await?;
+        while let Some(chunk) = resp.chunk().await.map_err(std::io::Error::other)? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        tokio::fs::rename(&partial, &dest).await
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(format!("couldn't write {}: {e}", dest.display()));
+    }
+    Ok(Some(dest.display().to_string()))
+}
+
+/// Pick a full data export and import it into the web server, merging it with what is there.
+/// `on_conflict` is `keep` or `replace`; with `dry_run` nothing changes. Returns the server's
+/// report of what changed, or would, or `None` if the user cancelled.
+#[tauri::command]
+async fn pick_and_import_data(
+    app: AppHandle,
+    sidecar: State<'_, web_sidecar::WebSidecar>,
+    settings: State<'_, AppSettingsState>,
+    dry_run: bool,
+    on_conflict: String,
+) -> Result<Option<serde_json::Value>, String> {
+    if !matches!(on_conflict.as_str(), "keep" | "replace") {
+        return Err(format!("unknown conflict choice {on_conflict:?}"));
+    }
+    let base_url = sidecar
+        .status()
+        .base_url
+        .ok_or_else(|| "the web server isn't running".to_string())?;
+    let start = settings.get().await.last_export_dir;
+    let picked =
+        pickers::pick_file(&app, "Import export", "Phoenix export", &["gz", "tgz"], start).await?;
+    let Some(path) = picked else {
+        return Ok(None);
+    };
+    let archive = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("couldn't read {}: {e}", path.display()))?;
+
+    let resp = reqwest::Client::new()
+        .post(format!(
+            "{base_url}/api/admin/import?dry_run={dry_run}&on_conflict={on_conflict}"
+        ))
+        .header(reqwest::header::CONTENT_TYPE, "application/gzip")
+        .body(archive)
+        .send()
+        .await
+        .map_err(|e| format!("import failed: {e}"))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let detail = resp.text().await.unwrap_or_default();
+        return Err(format!("import failed: {status} {detail}"));
+    }
+    resp.json()
+        .await
+        .map(Some)
+        .map_err(|e| format!("import failed: {e}"))
+}
+
+/// Open the recordings folder (or the `purpose` subfolder, when there is one) in the file
+/// manager. Returns the folder that was opened.
+#[tauri::command]
+async fn open_recordings_folder(
+    app: AppHandle,
+    state: State<'_, RecorderState>,
+    purpose: Option<String>,
+) -> Result<String, String> {
+    let storage = state.inner.lock().await.config().storage_path;
+    let dir = file_manager::recordings_dir(&storage, purpose.as_deref())?;
+    file_manager::open_dir(&app, &dir)?;
+    Ok(dir.display().to_string())
+}
+
+/// Show a recording selected in its folder.
+#[tauri::command]
+async fn reveal_recording(
+    app: AppHandle,
+    state: State<'_, RecorderState>,
+    path: String,
+) -> Result<(), String> {
+    let storage = state.inner.lock().await.config().storage_path;
+    file_manager::reveal(&app, &storage, &PathBuf::from(path))
+}
+
+/// Choose a custom recordings directory, persist it, and point the recorder at it.
+#[tauri::command]
+async fn pick_recordings_dir(
+    app: AppHandle,
+    state: State<'_, RecorderState>,
+    settings: State<'_, AppSettingsState>,
+) -> Result<Option<AppSettings>, String> {
+    let current = settings.get().await;
+    let Some(dir) = pickers::pick_writable_folder(
+        &app,
+        "Choose recordings folder",
+        Some(current.recorder.storage_path.clone()),
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    let mut config = current.recorder;
+    config.storage_path = dir;
+    state
+        .inner
+        .lock()
+        .await
+        .apply_config(config.clone())
+        .map_err(|e| e.to_string())?;
+    settings.update(|s| s.recorder = config).await.map(Some)
+}
+
+#[tauri::command]
+async fn toggle_mini_recorder(app: AppHandle) -> Result<bool, String> {
+    mini_recorder::toggle(&app).await
+}
+
+#[tauri::command]
+async fn set_mini_recorder_visible(app: AppHandle, visible: bool) -> Result<bool, String> {
+    mini_recorder::set_open(&app, visible).await
+}
+
+#[tauri::command]
+async fn get_diagnostics(app: AppHandle) -> Result<diagnostics::Diagnostics, String> {
+    Ok(diagnostics::collect(&app).await)
+}
+
+/// Zip a diagnostics report with recent logs. Prompts for the destination when `path` is omitted;
+/// returns `None` if that prompt is cancelled.
+#[tauri::command]
+async fn export_diagnostics(
+    app: AppHandle,
+    settings: State<'_, AppSettingsState>,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let dest = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let ts = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            let name = format!("pagi-diagnostics-{ts}.zip");
+            let start_dir = settings.get().await.last_export_dir;
+            match pickers::pick_save_destination(&app, name, "zip", start_dir).await? {
+                Some(dest) => dest,
+                None => return Ok(None),
+            }
+        }
+    };
+    let report = diagnostics::collect(&app).await;
+    let out = dest.clone();
+    tauri::async_runtime::spawn_blocking(move || diagnostics::write_bundle(&report, &out))
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(Some(dest.display().to_string()))
+}
+
+#[tauri::command]
+async fn idle_pause_status(app: AppHandle) -> Result<idle_pause::IdlePauseStatus, String> {
+    Ok(idle_pause::status(&app).await)
+}
+
+#[tauri::command]
+async fn onboarding_status(app: AppHandle) -> Result<onboarding::OnboardingStatus, String> {
+    onboarding::status(&app).await
+}
+
+#[tauri::command]
+async fn onboarding_next(
+    app: AppHandle,
+    input: Option<serde_json::Value>,
+) -> Result<onboarding::OnboardingStatus, String> {
+    onboarding::next(&app, input).await
+}
 
-mod audit;
-mod agents;
-mod models;
-mod security;
-mod tools;
-mod sola_state;
-mod vault;
-mod l7_db;
-mod scout_state;
+#[tauri::command]
+async fn onboarding_skip(app: AppHandle) -> Result<onboarding::OnboardingStatus, String> {
+    onboarding::skip(&app).await
+}
 
-use crate::agents::researcher::{MemoryInjection, ResearchSession};
-use crate::agents::scout::ScoutAgent;
-use crate::models::zodiac::{ZodiacRegistry, ZodiacSign};
-use crate::security::{VaultHealth, VaultSecurityState};
-use crate::tools::video_scout::{PendingReviewItem, ReviewQueueState, ReviewStatus, ScoutFilter};
-use crate::sola_state::{OrchestratorMode, SolaState};
-use crate::scout_state::{MissionStatus, ScoutMission, ScoutMissionState};
-use crate::models::zodiac::{PhaseThresholds, TrustSignals};
+#[tauri::command]
+async fn analyze_clipboard(
+    app: AppHandle,
+    request: Option<clipboard::ClipboardAnalysisRequest>,
+    correlation_id: Option<String>,
+) -> Result<clipboard::ClipboardAnalysis, String> {
+    let correlation_id = pagi_utils::correlation::or_new(correlation_id.as_deref());
+    clipboard::analyze(&app, &request.unwrap_or_default(), &correlation_id)
+        .instrument(command_trace::span("analyze_clipboard", Some(correlation_id.as_str())))
+        .await
+}
 
-#[derive(Default)]
-struct RecorderState {
-    inner: Arc<Mutex<MultiModalRecorder>>,
+#[tauri::command]
+fn read_clipboard_script(app: AppHandle) -> Result<String, String> {
+    clipboard::read_script(&app)
 }
 
-#[derive(Serialize)]
-struct RecordResult {
-    path: String,
+#[tauri::command]
+fn copy_rewrite(app: AppHandle, text: String) -> Result<(), String> {
+    clipboard::copy(&app, &text)
 }
 
 #[tauri::command]
-async fn record_audio(state: State<'_, RecorderState>, duration_secs: u64) -> Result<RecordResult, String> {
-    let rec = state.inner.lock().await.clone();
-    let rec = rec.clone_with_modes(true, false);
-    let p = rec.start_on_demand(duration_secs).await.map_err(|e| e.to_string())?;
-    Ok(RecordResult { path: p.display().to_string() })
+fn last_session_crash() -> Option<crash_reports::CrashReport> {
+    crash_reports::last_session_crash()
 }
 
 #[tauri::command]
-async fn record_video(state: State<'_, RecorderState>, duration_secs: u64) -> Result<RecordResult, String> {
-    let rec = state.inner.lock().await.clone();
-    let rec = rec.clone_with_modes(false, true);
-    let p = rec.start_on_demand(duration_secs).await.map_err(|e| e.to_string())?;
-    Ok(RecordResult { path: p.display().to_string() })
+fn list_crash_reports() -> Vec<crash_reports::CrashReport> {
+    crash_reports::list()
 }
 
 #[tauri::command]
-async fn record_av(state: State<'_, RecorderState>, duration_secs: u64) -> Result<RecordResult, String> {
-    let rec = state.inner.lock().await.clone();
-    let rec = rec.clone_with_modes(true, true);
-    let p = rec.start_on_demand(duration_secs).await.map_err(|e| e.to_string())?;
-    Ok(RecordResult { path: p.display().to_string() })
+fn get_crash_report(id: String) -> Result<crash_reports::CrashReport, String> {
+    crash_reports::get(&id)
 }
 
 #[tauri::command]
-async fn schedule_recording(state: State<'_, RecorderState>, cron_expr: String, purpose: String) -> Result<(), String> {
-    let rec = state.inner.lock().await.clone();
-    rec.schedule_recording(&cron_expr, &purpose).await;
-    Ok(())
+async fn send_crash_report(id: String) -> Result<crash_reports::CrashReport, String> {
+    crash_reports::send(&id).await
 }
 
 #[tauri::command]
-async fn set_always_listening(state: State<'_, RecorderState>, enabled: bool) -> Result<(), String> {
-    let rec = state.inner.lock().await.clone();
-    if enabled {
-        rec.start_always_listening().await;
-    } else {
-        rec.stop_listening();
+fn shortcut_registry(app: AppHandle) -> Vec<shortcuts::ShortcutEntry> {
+    shortcuts::entries(&app)
+}
+
+#[tauri::command]
+async fn set_shortcut(
+    app: AppHandle,
+    action: shortcuts::ShortcutAction,
+    accelerator: Option<String>,
+) -> Result<Vec<shortcuts::ShortcutEntry>, String> {
+    shortcuts::set_binding(&app, action, accelerator).await
+}
+
+#[tauri::command]
+fn web_server_status(sidecar: State<'_, web_sidecar::WebSidecar>) -> web_sidecar::WebServerStatus {
+    sidecar.status()
+}
+
+#[tauri::command]
+async fn check_for_updates(app: AppHandle) -> Result<Option<updates::UpdateInfo>, String> {
+    let found = updates::check(&app).await?;
+    if found.as_ref().is_some_and(|u| !u.downloaded) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = updates::download_pending(&app).await {
+                diagnostics::report_error("updates", format!("download failed: {e}"));
+            }
+        });
     }
-    Ok(())
+    Ok(found)
 }
 
 #[tauri::command]
-async fn enroll_voice(state: State<'_, RecorderState>, samples: Vec<String>) -> Result<(), String> {
-    let samples = samples.into_iter().map(PathBuf::from).collect::<Vec<_>>();
-    let mut rec = state.inner.lock().await;
-    rec.enroll_user_voice(samples).map_err(|e| e.to_string())
+async fn install_update(app: AppHandle) -> Result<(), String> {
+    updates::install(&app).await
 }
 
 #[tauri::command]
-async fn enroll_face(state: State<'_, RecorderState>, images: Vec<String>) -> Result<(), String> {
-    let images = images.into_iter().map(PathBuf::from).collect::<Vec<_>>();
-    let mut rec = state.inner.lock().await;
-    rec.enroll_user_face(images).map_err(|e| e.to_string())
+async fn set_update_channel(
+    settings: State<'_, AppSettingsState>,
+    channel: UpdateChannel,
+) -> Result<AppSettings, String> {
+    settings.update(|s| s.update_channel = channel).await
 }
 
 #[tauri::command]
-async fn delete_last_recording(state: State<'_, RecorderState>) -> Result<bool, String> {
-    let rec = state.inner.lock().await.clone();
-    rec.delete_last_recording().await.map_err(|e| e.to_string())
+async fn get_theme(settings: State<'_, AppSettingsState>) -> Result<ThemeSettings, String> {
+    Ok(settings.get().await.theme)
 }
 
+/// Persist the theme, apply it to every window, and broadcast `theme-changed`.
 #[tauri::command]
-async fn clear_all_recordings(state: State<'_, RecorderState>) -> Result<u64, String> {
-    let rec = state.inner.lock().await.clone();
-    rec.clear_all_recordings().await.map_err(|e| e.to_string())
+async fn set_theme(
+    app: AppHandle,
+    settings: State<'_, AppSettingsState>,
+    theme: ThemeSettings,
+) -> Result<ThemeSettings, String> {
+    use tauri::Emitter;
+
+    theme.validate()?;
+    let saved = settings.update(|s| s.theme = theme).await?.theme;
+    for window in app.webview_windows().values() {
+        let _ = window.set_theme(saved.window_theme());
+    }
+    let _ = app.emit("theme-changed", &saved);
+    Ok(saved)
 }
 
 #[tauri::command]
-async fn recognition_status(_state: State<'_, RecorderState>) -> Result<String, String> {
-    // Placeholder until live preview + recognition pipeline is wired.
-    Ok("I see you, Dad ❤️".to_string())
+async fn get_languages(
+    settings: State<'_, AppSettingsState>,
+) -> Result<i18n::LanguageStatus, String> {
+    Ok(i18n::status(settings.get().await.language))
 }
 
+/// Persist the language (`None` follows the system locale); the tray is relabeled and
+/// `language-changed` is broadcast once the settings change lands.
 #[tauri::command]
-async fn emotion_status(state: State<'_, RecorderState>) -> Result<String, String> {
-    let rec = state.inner.lock().await.clone();
-    let result = match rec.last_emotion().await {
-        Some(s) => format!(
-            "Dad is feeling: {:?} ({:.0}%) ❤️",
-            s.primary_emotion,
-            s.confidence * 100.0
-        ),
-        None => "Dad is feeling: Neutral".to_string(),
-    };
-    Ok(result)
+async fn set_language(
+    settings: State<'_, AppSettingsState>,
+    language: Option<String>,
+) -> Result<i18n::LanguageStatus, String> {
+    let language = language.filter(|l| !l.trim().is_empty());
+    i18n::validate(language.as_deref())?;
+    let saved = settings.update(|s| s.language = language).await?;
+    i18n::set_language(saved.language.as_deref());
+    Ok(i18n::status(saved.language))
 }
 
 #[tauri::command]
-async fn emotion_history(state: State<'_, RecorderState>, max: usize) -> Result<Vec<String>, String> {
-    let rec = state.inner.lock().await.clone();
-    Ok(rec.emotional_moments_recent(max))
+fn notification_permission(app: AppHandle, request: bool) -> Result<bool, String> {
+    use tauri_plugin_notification::{NotificationExt, PermissionState};
+
+    if request {
+        return Ok(notifications::ensure_permission(&app));
+    }
+    app.notification()
+        .permission_state()
+        .map(|s| s == PermissionState::Granted)
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn send_notification(
-    _app: AppHandle,
-    title: String,
-    body: String,
-) -> Result<(), String> {
-    // Tauri v2 notification API - requires notification permission in capabilities
-    // For now, return success - notifications will be handled via frontend
-    println!("Notification: {} - {}", title, body);
+fn run_notification_action(app: AppHandle, action: NotificationAction) -> Result<(), String> {
+    notifications::run_action(&app, &action);
     Ok(())
 }
 
+/// A second launch was attempted: hand its arguments to the UI and focus the existing window
+/// (unless it was a background/autostart launch).
+fn on_second_instance(app: &AppHandle, args: Vec<String>, cwd: String) {
+    use tauri::Emitter;
+
+    let background = args
+        .iter()
+        .any(|a| a == app_settings::MINIMIZED_ARG || a == AUTOSTART_ARG);
+    let _ = app.emit(
+        "second-instance",
+        serde_json::json!({ "args": args, "cwd": cwd }),
+    );
+    if background {
+        return;
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Files dropped onto the main window: validate, quality-check, and enroll audio as voice and
+/// images as face samples, reporting each file via `enrollment-file-result`.
+async fn enroll_dropped_files(app: AppHandle, paths: Vec<PathBuf>) {
+    use tauri::Emitter;
+
+    let Some(recorder) = app.try_state::<RecorderState>() else {
+        return;
+    };
+    let results = recorder.inner.lock().await.enroll_files(paths);
+    for result in &results {
+        let _ = app.emit("enrollment-file-result", result);
+    }
+    let enrolled = results.iter().filter(|r| r.enrolled).count();
+    let _ = app.emit(
+        "enrollment-drop-finished",
+        serde_json::json!({ "files": results.len(), "enrolled": enrolled }),
+    );
+}
+
+/// Start an on-demand recording from the tray and report the outcome.
+async fn tray_record(app: AppHandle, audio: bool, video: bool, duration_secs: u64) {
+    let Some(recorder) = app.try_state::<RecorderState>() else {
+        return;
+    };
+    let rec = recorder.inner.lock().await.clone();
+    let rec = rec.clone_with_modes(audio, video);
+    let span = command_trace::span("tray_record", None);
+    match rec.start_on_demand(duration_secs).instrument(span).await {
+        Ok(path) => {
+            let path = path.display().to_string();
+            notifications::notify(
+                &app,
+                i18n::t("recording-saved"),
+                i18n::t_args("recording-saved-body", &[("seconds", duration_secs.into())]),
+                Some(NotificationAction::OpenRecording { path }),
+            );
+        }
+        Err(e) => {
+            notifications::notify(&app, i18n::t("recording-failed"), e.to_string(), None);
+        }
+    }
+}
+
+/// Status line and tooltip for the tray.
+fn tray_labels(status: &RecorderStatus, emotion: Option<&ReportedEmotion>) -> (String, String) {
+    let activity = i18n::t(if status.recording {
+        "activity-recording"
+    } else if status.guest_mode {
+        "activity-guest"
+    } else if status.live_streaming {
+        "activity-live"
+    } else if status.always_listening {
+        "activity-listening"
+    } else {
+        "activity-idle"
+    });
+    let mood = match emotion {
+        Some(e) if e.uncertain => i18n::t("mood-uncertain"),
+        Some(e) => i18n::t_args(
+            "mood-reading",
+            &[
+                ("label", e.label.as_str().into()),
+                ("confidence", format!("{:.0}", e.calibrated_confidence * 100.0).into()),
+            ],
+        ),
+        None => i18n::t("mood-none"),
+    };
+    let listening = if status.always_listening { "on" } else { "off" };
+    (
+        i18n::t_args("tray-status", &[("activity", activity.as_str().into())]),
+        i18n::t_args(
+            "tray-tooltip",
+            &[
+                ("activity", activity.into()),
+                ("listening", listening.into()),
+                ("mood", mood.into()),
+            ],
+        ),
+    )
+}
+
+/// Whether the app is currently rendered dark (resolving `system` via the main window).
+fn dark_theme(app: &AppHandle, theme: &ThemeSettings) -> bool {
+    match theme.window_theme() {
+        Some(t) => t == tauri::Theme::Dark,
+        None => app
+            .get_webview_window("main")
+            .and_then(|w| w.theme().ok())
+            .is_some_and(|t| t == tauri::Theme::Dark),
+    }
+}
+
+/// Solid dot used as the tray icon while something noteworthy is going on; `None` means the
+/// default app icon. Listening uses the accent color, and the rim contrasts with the theme.
+fn tray_dot(status: &RecorderStatus, theme: &ThemeSettings, dark: bool) -> Option<Image<'static>> {
+    const SIZE: u32 = 32;
+    let rgb: [u8; 3] = if status.recording {
+        [220, 40, 40]
+    } else if status.guest_mode {
+        [140, 140, 140]
+    } else if status.always_listening || status.live_streaming {
+        theme.accent_rgb().unwrap_or([40, 180, 90])
+    } else {
+        return None;
+    };
+    let rim: [u8; 3] = if dark { [240, 240, 240] } else { [30, 30, 30] };
+    let r = SIZE as f32 / 2.0;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let (dx, dy) = (x as f32 + 0.5 - r, y as f32 + 0.5 - r);
+            let d = (dx * dx + dy * dy).sqrt();
+            let (c, alpha) = if d <= r - 3.0 {
+                (rgb, 255)
+            } else if d <= r - 1.0 {
+                (rim, 255)
+            } else {
+                (rgb, 0)
+            };
+            rgba.extend_from_slice(&[c[0], c[1], c[2], alpha]);
+        }
+    }
+    Some(Image::new_owned(rgba, SIZE, SIZE))
+}
+
+/// Tray menu items whose text/check state follows the recorder, plus the fixed labels that are
+/// re-translated when the language changes.
+#[derive(Clone)]
+struct TrayItems {
+    status: MenuItem<Wry>,
+    guest: CheckMenuItem<Wry>,
+    listening: CheckMenuItem<Wry>,
+    labels: Vec<(MenuItem<Wry>, &'static str)>,
+}
+
+impl TrayItems {
+    fn relabel(&self) {
+        for (item, id) in &self.labels {
+            let _ = item.set_text(i18n::t(id));
+        }
+        let _ = self.guest.set_text(i18n::t("tray-guest-mode"));
+        let _ = self.listening.set_text(i18n::t("tray-always-listening"));
+    }
+}
+
+fn refresh_tray(
+    app: &AppHandle,
+    items: &TrayItems,
+    status: &RecorderStatus,
+    emotion: Option<&ReportedEmotion>,
+    theme: &ThemeSettings,
+) {
+    let (line, tooltip) = tray_labels(status, emotion);
+    let _ = items.status.set_text(line);
+    let _ = items.guest.set_checked(status.guest_mode);
+    let _ = items.listening.set_checked(status.always_listening);
+    if let Some(tray) = app.tray_by_id("main") {
+        let _ = tray.set_tooltip(Some(tooltip));
+        let icon = tray_dot(status, theme, dark_theme(app, theme))
+            .or_else(|| app.default_window_icon().cloned());
+        let _ = tray.set_icon(icon);
+    }
+}
+
+/// Keep the recorder's emotion history and recordings index in the web server's database
+/// (`PHOENIX_DATABASE_URL`, else its default `data/phoenix.db` under the working directory), so
+/// the app and the server share one. An encrypted data directory is left to the server, which
+/// holds the key; the recorder then keeps its own stores.
+fn use_shared_storage() {
+    if std::path::Path::new(pagi_crypto::KEY_FILE).exists() {
+        tracing::info!("Data directory is encrypted; the recorder keeps its own stores");
+        return;
+    }
+    let url = std::env::var("PHOENIX_DATABASE_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or_else(|| "sqlite://data/phoenix.db".to_string());
+    match phoenix_storage::Storage::open(&url, phoenix_storage::DEFAULT_READERS) {
+        Ok(storage) => {
+            tracing::info!("Recorder database: {}", storage.location());
+            multi_modal_recording::use_storage(storage);
+        }
+        Err(e) => tracing::warn!("Recorder database not opened, keeping its own stores: {e}"),
+    }
+}
+
 fn main() {
+    command_trace::init();
+    crash_reports::install_panic_hook();
+    crash_reports::begin_session();
+
     // Recover from any interrupted key rotation.
     if let Ok(p) = crate::security::profiles_dir() {
         let _ = crate::security::recover_shadow_buffers(&p);
@@ -164,34 +1435,132 @@ fn main() {
     })
     .expect("failed to load review queue");
 
+    let (app_settings, settings_problem) = AppSettingsState::load_or_default();
+    let startup_settings = tauri::async_runtime::block_on(app_settings.get());
+    i18n::set_language(startup_settings.language.as_deref());
+    use_shared_storage();
+
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before touching any devices.
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            on_second_instance(app, args, cwd)
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, shortcut, event| {
+                    if event.state() == tauri_plugin_global_shortcut::ShortcutState::Pressed {
+                        shortcuts::trigger(app, shortcut);
+                    }
+                })
+                .build(),
+        )
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            Some(vec![AUTOSTART_ARG]),
+        ))
         .manage(RecorderState {
-            inner: Arc::new(Mutex::new(MultiModalRecorder::from_env())),
+            inner: Arc::new(Mutex::new(MultiModalRecorder::from_config(
+                startup_settings.recorder.clone(),
+            ))),
         })
         .manage(SolaState::default())
         .manage(zodiac_registry)
         .manage(vault_security)
         .manage(review_queue)
         .manage(ScoutMissionState::default())
-        .setup(|app| {
+        .manage(app_settings)
+        .manage(updates::UpdateState::default())
+        .manage(mini_recorder::MiniRecorderState::default())
+        .manage(idle_pause::IdlePauseState::default())
+        .manage(shortcuts::ShortcutRegistry::default())
+        .manage(web_sidecar::WebSidecar::default())
+        .setup(move |app| {
             // Create system tray menu
-            let show = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
-            let hide = MenuItem::with_id(app, "hide", "Hide Window", true, None::<&str>)?;
-            let status = MenuItem::with_id(app, "status", "Status: Active", false, None::<&str>)?;
-            let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+            let show = MenuItem::with_id(app, "show", i18n::t("tray-show"), true, None::<&str>)?;
+            let hide = MenuItem::with_id(app, "hide", i18n::t("tray-hide"), true, None::<&str>)?;
+            let status = MenuItem::with_id(
+                app,
+                "status",
+                i18n::t_args("tray-status", &[("activity", i18n::t("activity-idle").into())]),
+                false,
+                None::<&str>,
+            )?;
+            let guest = CheckMenuItem::with_id(
+                app,
+                "guest_mode",
+                i18n::t("tray-guest-mode"),
+                true,
+                false,
+                None::<&str>,
+            )?;
+            let record_audio_1m = MenuItem::with_id(
+                app,
+                "record_audio_1m",
+                i18n::t("tray-record-audio"),
+                true,
+                None::<&str>,
+            )?;
+            let record_av_5m = MenuItem::with_id(
+                app,
+                "record_av_5m",
+                i18n::t("tray-record-av"),
+                true,
+                None::<&str>,
+            )?;
+            let listening = CheckMenuItem::with_id(
+                app,
+                "always_listening",
+                i18n::t("tray-always-listening"),
+                true,
+                false,
+                None::<&str>,
+            )?;
+            let mini = MenuItem::with_id(
+                app,
+                "mini_recorder",
+                i18n::t("tray-mini-recorder"),
+                true,
+                None::<&str>,
+            )?;
+            let update = MenuItem::with_id(
+                app,
+                "install_update",
+                i18n::t("tray-no-updates"),
+                false,
+                None::<&str>,
+            )?;
+            app.state::<updates::UpdateState>().set_tray_item(update.clone());
+            let quit = MenuItem::with_id(app, "quit", i18n::t("tray-quit"), true, None::<&str>)?;
             
             let menu = Menu::with_items(app, &[
                 &status,
+                &guest,
+                &PredefinedMenuItem::separator(app)?,
+                &record_audio_1m,
+                &record_av_5m,
+                &listening,
+                &mini,
                 &PredefinedMenuItem::separator(app)?,
                 &show,
                 &hide,
                 &PredefinedMenuItem::separator(app)?,
+                &update,
                 &quit,
             ])?;
             
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id("main")
                 .menu(&menu)
-                .tooltip("Sola AGI - v1.0.1")
+                .tooltip(i18n::t_args(
+                    "tray-tooltip-startup",
+                    &[("version", env!("CARGO_PKG_VERSION").into())],
+                ))
                 .on_menu_event(|app, event| match event.id.as_ref() {
                     "show" => {
                         if let Some(window) = app.get_webview_window("main") {
@@ -204,8 +1573,59 @@ fn main() {
                             let _ = window.hide();
                         }
                     }
+                    "guest_mode" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            use tauri::Emitter;
+
+                            let Some(recorder) = app.try_state::<RecorderState>() else {
+                                return;
+                            };
+                            let rec = recorder.inner.lock().await.clone();
+                            let current = rec.guest_mode();
+                            rec.set_guest_mode(!current.enabled, current.allow_recordings);
+                            let _ = app.emit("guest-mode-changed", rec.guest_mode());
+                        });
+                    }
+                    "record_audio_1m" => {
+                        tauri::async_runtime::spawn(tray_record(app.clone(), true, false, 60));
+                    }
+                    "record_av_5m" => {
+                        tauri::async_runtime::spawn(tray_record(app.clone(), true, true, 5 * 60));
+                    }
+                    "always_listening" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let Some(recorder) = app.try_state::<RecorderState>() else {
+                                return;
+                            };
+                            let rec = recorder.inner.lock().await.clone();
+                            if rec.status().always_listening {
+                                rec.stop_listening();
+                            } else {
+                                rec.start_always_listening().await;
+                            }
+                        });
+                    }
+                    "mini_recorder" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = mini_recorder::toggle(&app).await {
+                                diagnostics::report_error("mini-recorder", e);
+                            }
+                        });
+                    }
+                    "install_update" => {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = updates::install(&app).await {
+                                notifications::notify(&app, i18n::t("update-failed"), e, None);
+                            }
+                        });
+                    }
                     "quit" => {
-                        std::process::exit(0);
+                        // Goes through `shutdown::on_run_event`, which finalizes recordings first.
+                        app.exit(0);
                     }
                     _ => {}
                 })
@@ -225,6 +1645,446 @@ fn main() {
                 })
                 .build(app)?;
 
+            for window in app.webview_windows().values() {
+                let _ = window.set_theme(startup_settings.theme.window_theme());
+            }
+
+            // Background mode: start hidden in the tray and keep the login item in sync.
+            if app_settings::launch_minimized(&startup_settings) {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.hide();
+                }
+            }
+            {
+                use tauri_plugin_autostart::ManagerExt;
+
+                let autolaunch = app.autolaunch();
+                if autolaunch.is_enabled().unwrap_or(false) != startup_settings.autostart {
+                    let _ = if startup_settings.autostart {
+                        autolaunch.enable()
+                    } else {
+                        autolaunch.disable()
+                    };
+                }
+            }
+
+            // pagi:// links, both while running and from the launch that started the app.
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+
+                #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+                if let Err(e) = app.deep_link().register_all() {
+                    diagnostics::report_error(
+                        "deep-link",
+                        format!("failed to register {}://: {e}", deep_link::SCHEME),
+                    );
+                }
+                let link_handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        deep_link::handle(&link_handle, &url);
+                    }
+                });
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    for url in urls {
+                        deep_link::handle(app.handle(), &url);
+                    }
+                }
+            }
+
+            // Restore always-listening and saved schedules, and remember later listening changes.
+            let listening_handle = app.handle().clone();
+            let restore_listening = startup_settings.recorder.always_listening;
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let (Some(recorder), Some(settings)) = (
+                    listening_handle.try_state::<RecorderState>(),
+                    listening_handle.try_state::<AppSettingsState>(),
+                ) else {
+                    return;
+                };
+                let rec = recorder.inner.lock().await.clone();
+                let mut rx = rec.subscribe_status();
+                if restore_listening {
+                    rec.start_always_listening().await;
+                }
+                match rec.restore_schedules().await {
+                    Ok(0) => {}
+                    Ok(n) => {
+                        tracing::info!(target: "scheduler", "restored {n} recording schedule(s)")
+                    }
+                    Err(e) => diagnostics::report_error(
+                        "scheduler",
+                        format!("failed to restore schedules: {e}"),
+                    ),
+                }
+                let mut last = restore_listening;
+                loop {
+                    match rx.recv().await {
+                        Ok(status) if status.always_listening != last => {
+                            last = status.always_listening;
+                            let _ = listening_handle.emit(
+                                "always-listening-changed",
+                                serde_json::json!({ "enabled": last }),
+                            );
+                            // Shutdown and idle auto-pause stop listening but keep the saved
+                            // preference.
+                            if !rec.is_shutting_down()
+                                && !idle_pause::is_auto_paused(&listening_handle)
+                            {
+                                let _ = settings
+                                    .update(|s| s.recorder.always_listening = last)
+                                    .await;
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Player state and position for the in-app player.
+            let playback_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let Some(recorder) = playback_handle.try_state::<RecorderState>() else {
+                    return;
+                };
+                let mut rx = recorder.inner.lock().await.subscribe_playback();
+                loop {
+                    match rx.recv().await {
+                        Ok(now) => {
+                            let _ = playback_handle.emit("playback-position", &now);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Recording lifecycle events, whoever started the recording.
+            let recording_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let Some(recorder) = recording_handle.try_state::<RecorderState>() else {
+                    return;
+                };
+                let mut rx = recorder.inner.lock().await.subscribe_recordings();
+                loop {
+                    match rx.recv().await {
+                        Ok(event) => {
+                            let name = match &event {
+                                RecordingEvent::Started { .. } => "recording-started",
+                                RecordingEvent::Progress { .. } => "recording-progress",
+                                RecordingEvent::Finished { .. } => "recording-finished",
+                                RecordingEvent::Failed { error, .. } => {
+                                    diagnostics::report_error("recording", error.clone());
+                                    "recording-failed"
+                                }
+                            };
+                            let _ = recording_handle.emit(name, &event);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Forward unknown-presence (visitor) events to the UI.
+            let presence_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let Some(recorder) = presence_handle.try_state::<RecorderState>() else {
+                    return;
+                };
+                let mut rx = recorder.inner.lock().await.subscribe_presence();
+                loop {
+                    match rx.recv().await {
+                        Ok(PresenceEvent::Unknown(event)) => {
+                            let _ = presence_handle.emit("unknown-presence", &event);
+                            notifications::notify(
+                                &presence_handle,
+                                i18n::t("presence-unknown"),
+                                i18n::t("presence-unknown-body"),
+                                Some(NotificationAction::OpenView { view: "presence".to_string() }),
+                            );
+                        }
+                        Ok(PresenceEvent::Enrolled { event_id, profile }) => {
+                            let _ = presence_handle.emit(
+                                "presence-enrolled",
+                                serde_json::json!({ "event_id": event_id, "profile": profile }),
+                            );
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Keep the tray status line, tooltip, icon, and guest checkbox in sync with the recorder.
+            let tray_handle = app.handle().clone();
+            let tray_items = TrayItems {
+                status: status.clone(),
+                guest: guest.clone(),
+                listening: listening.clone(),
+                labels: vec![
+                    (show.clone(), "tray-show"),
+                    (hide.clone(), "tray-hide"),
+                    (record_audio_1m.clone(), "tray-record-audio"),
+                    (record_av_5m.clone(), "tray-record-av"),
+                    (mini.clone(), "tray-mini-recorder"),
+                    (quit.clone(), "tray-quit"),
+                ],
+            };
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                use tokio::sync::broadcast::error::RecvError;
+
+                let (Some(recorder), Some(settings)) = (
+                    tray_handle.try_state::<RecorderState>(),
+                    tray_handle.try_state::<AppSettingsState>(),
+                ) else {
+                    return;
+                };
+                let rec = recorder.inner.lock().await.clone();
+                let mut status_rx = rec.subscribe_status();
+                let mut emotion_rx = rec.subscribe_emotions();
+                let mut settings_rx = settings.subscribe();
+                let mut current = rec.status();
+                let mut emotion = rec.reported_emotion().await;
+                let startup = settings.get().await;
+                let mut theme = startup.theme;
+                let mut language = startup.language;
+                loop {
+                    refresh_tray(&tray_handle, &tray_items, &current, emotion.as_ref(), &theme);
+                    tokio::select! {
+                        next = settings_rx.recv() => {
+                            let s = match next {
+                                Ok(s) => s,
+                                Err(RecvError::Lagged(_)) => settings.get().await,
+                                Err(RecvError::Closed) => break,
+                            };
+                            theme = s.theme;
+                            // Switch languages at runtime: relabel the tray and tell the UI.
+                            if s.language != language {
+                                language = s.language;
+                                i18n::set_language(language.as_deref());
+                                tray_items.relabel();
+                                if let Some(state) =
+                                    tray_handle.try_state::<updates::UpdateState>()
+                                {
+                                    state.relabel();
+                                }
+                                let _ = tray_handle
+                                    .emit("language-changed", i18n::status(language.clone()));
+                            }
+                        }
+                        next = status_rx.recv() => match next {
+                            Ok(s) => {
+                                current = s;
+                                let _ = tray_handle.emit("recorder-status", &current);
+                            }
+                            Err(RecvError::Lagged(_)) => current = rec.status(),
+                            Err(RecvError::Closed) => break,
+                        },
+                        next = emotion_rx.recv() => match next {
+                            Ok(update) => {
+                                if update.reported.is_some() {
+                                    emotion = update.reported;
+                                }
+                            }
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        },
+                    }
+                }
+            });
+
+            // Forward live emotion estimates to the UI.
+            let emotion_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let Some(recorder) = emotion_handle.try_state::<RecorderState>() else {
+                    return;
+                };
+                let mut rx = recorder.inner.lock().await.subscribe_emotions();
+                loop {
+                    match rx.recv().await {
+                        Ok(update) => {
+                            let _ = emotion_handle.emit("emotion-update", &update);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Sustained / recurring negative-emotion alerts (already logged by the recorder).
+            let alert_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let Some(recorder) = alert_handle.try_state::<RecorderState>() else {
+                    return;
+                };
+                let mut rx = recorder.inner.lock().await.subscribe_emotion_alerts();
+                loop {
+                    match rx.recv().await {
+                        Ok(alert) => {
+                            let _ = alert_handle.emit("emotion-alert", &alert);
+                            if !alert.notify {
+                                // Quiet hours: logged only.
+                                continue;
+                            }
+                            let emotion = alert.emotion.clone();
+                            let body = match alert.trigger {
+                                AlertTrigger::Sustained { minutes } => i18n::t_args(
+                                    "alert-sustained",
+                                    &[("emotion", emotion.into()), ("minutes", minutes.into())],
+                                ),
+                                AlertTrigger::Recurring { count } => i18n::t_args(
+                                    "alert-recurring",
+                                    &[("emotion", emotion.into()), ("count", count.into())],
+                                ),
+                            };
+                            let action = (!alert.recording.is_empty()).then(|| {
+                                NotificationAction::OpenRecording { path: alert.recording.clone() }
+                            });
+                            let title = i18n::t("checking-in");
+                            notifications::notify(&alert_handle, title, body, action);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Scheduled recording results.
+            let schedule_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                let Some(recorder) = schedule_handle.try_state::<RecorderState>() else {
+                    return;
+                };
+                let mut rx = recorder.inner.lock().await.subscribe_scheduled_recordings();
+                loop {
+                    match rx.recv().await {
+                        Ok(result) => {
+                            let _ = schedule_handle.emit("scheduled-recording", &result);
+                            let label = if result.purpose.is_empty() {
+                                i18n::t("scheduled-recording")
+                            } else {
+                                i18n::t_args(
+                                    "scheduled-recording-purpose",
+                                    &[("purpose", result.purpose.as_str().into())],
+                                )
+                            };
+                            match (result.path, result.error) {
+                                (Some(path), _) => notifications::notify(
+                                    &schedule_handle,
+                                    label,
+                                    i18n::t("scheduled-recording-saved"),
+                                    Some(NotificationAction::OpenRecording {
+                                        path: path.display().to_string(),
+                                    }),
+                                ),
+                                (None, error) => notifications::notify(
+                                    &schedule_handle,
+                                    label,
+                                    i18n::t_args(
+                                        "scheduled-recording-failed",
+                                        &[("error", error.unwrap_or_default().into())],
+                                    ),
+                                    None,
+                                ),
+                            };
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // End-of-day mood summary (stored once per day; notify when this app generated it).
+            let summary_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+
+                loop {
+                    if let Some(recorder) = summary_handle.try_state::<RecorderState>() {
+                        let rec = recorder.inner.lock().await.clone();
+                        if let Some(summary) = rec.generate_due_mood_summary() {
+                            let _ = summary_handle.emit("mood-summary", &summary);
+                            notifications::notify(
+                                &summary_handle,
+                                i18n::t("mood-summary"),
+                                summary.headline(),
+                                Some(NotificationAction::OpenView { view: "mood".to_string() }),
+                            );
+                        }
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(15 * 60)).await;
+                }
+            });
+
+            // Reopen the mini-recorder widget if it was open last time.
+            let mini_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                mini_recorder::restore(&mini_handle).await;
+            });
+
+            // Tell the user if the last session crashed.
+            if let Some(report) = crash_reports::last_session_crash() {
+                use tauri::Emitter;
+
+                let handle = app.handle().clone();
+                notifications::notify(
+                    &handle,
+                    i18n::t("crash-notice"),
+                    i18n::t_args(
+                        "crash-notice-body",
+                        &[("subsystem", report.subsystem.as_str().into())],
+                    ),
+                    Some(NotificationAction::OpenView {
+                        view: "crash-report".to_string(),
+                    }),
+                );
+                let _ = handle.emit("last-session-crashed", &report);
+            }
+
+            // Tell the user if their saved settings couldn't be used.
+            if let Some(problem) = settings_problem {
+                let body = match &problem.backup {
+                    Some(backup) => i18n::t_args(
+                        "settings-reset-body",
+                        &[("backup", backup.display().to_string().into())],
+                    ),
+                    None => {
+                        i18n::t_args("settings-unsaved-body", &[("error", problem.error.into())])
+                    }
+                };
+                notifications::notify(app.handle(), i18n::t("settings-reset"), body, None);
+            }
+
+            // Global hotkeys, re-registered whenever they are remapped.
+            tauri::async_runtime::spawn(shortcuts::run(app.handle().clone()));
+
+            // Pause always-listening while the user is away.
+            tauri::async_runtime::spawn(idle_pause::run(app.handle().clone()));
+
+            // Scheduled update checks (background download, install on request).
+            tauri::async_runtime::spawn(updates::run_update_loop(app.handle().clone()));
+
+            // phoenix-web as a supervised sidecar (or an already-running instance).
+            tauri::async_runtime::spawn(web_sidecar::run(app.handle().clone()));
+
             // Background: periodic vault rotation health audit (no automatic destructive actions).
             // This logs when rotation is overdue, but rotation itself is user-triggered.
             let app_handle = app.handle().clone();
@@ -248,20 +2108,123 @@ fn main() {
              
             Ok(())
         })
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. })
+                if window.label() == "main" =>
+            {
+                tauri::async_runtime::spawn(enroll_dropped_files(
+                    window.app_handle().clone(),
+                    paths.clone(),
+                ));
+            }
+            tauri::WindowEvent::Moved(position) if window.label() == mini_recorder::LABEL => {
+                mini_recorder::on_moved(window, *position);
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             record_audio,
             record_video,
             record_av,
+            media_permissions,
+            request_media_permission,
+            open_media_permission_settings,
+            list_recordings,
+            play,
+            pause,
+            seek,
+            now_playing,
             schedule_recording,
             set_always_listening,
+            idle_pause_status,
             enroll_voice,
             enroll_face,
+            embedding_status,
+            migrate_embeddings,
+            get_recognition_thresholds,
+            set_recognition_thresholds,
+            calibrate_recognition,
+            unknown_presence_recent,
+            enroll_unknown_presence,
+            start_enrollment,
+            enrollment_status,
+            capture_enrollment_step,
+            submit_enrollment_sample,
+            finalize_enrollment,
+            cancel_enrollment,
+            set_guest_mode,
+            guest_mode_status,
+            recorder_status,
+            recorder_capabilities,
+            list_models,
+            download_model,
+            delete_model,
+            pin_model,
+            model_disk_usage,
             delete_last_recording,
+            request_clear_all,
             clear_all_recordings,
             recognition_status,
             emotion_status,
+            emotion_affect,
+            emotion_backend,
             emotion_history,
+            query_emotion_history,
+            export_emotion_history,
+            emotion_alerts,
+            get_emotion_privacy,
+            set_profile_emotion_privacy,
+            purge_profile_emotions,
+            get_emotion_alert_rules,
+            set_emotion_alert_rules,
+            mood_summaries,
+            emotion_trends,
+            recording_emotion_track,
+            search_recording_emotions,
+            emotion_history_for_profile,
+            analyze_text_emotion,
+            emotion_fusion_report,
+            get_emotion_fusion_weights,
+            set_emotion_fusion_weights,
+            get_emotion_calibration,
+            set_emotion_calibration,
             send_notification,
+            notification_permission,
+            get_settings,
+            update_settings,
+            onboarding_status,
+            onboarding_next,
+            onboarding_skip,
+            get_theme,
+            pick_enrollment_samples,
+            pick_and_export_emotion_history,
+            pick_and_export_all_data,
+            pick_and_import_data,
+            pick_recordings_dir,
+            open_recordings_folder,
+            reveal_recording,
+            set_theme,
+            get_languages,
+            set_language,
+            toggle_mini_recorder,
+            set_mini_recorder_visible,
+            shortcut_registry,
+            analyze_clipboard,
+            read_clipboard_script,
+            copy_rewrite,
+            set_shortcut,
+            get_diagnostics,
+            last_session_crash,
+            list_crash_reports,
+            get_crash_report,
+            send_crash_report,
+            export_diagnostics,
+            check_for_updates,
+            install_update,
+            set_update_channel,
+            web_server_status,
+            set_autostart,
+            run_notification_action,
             set_orchestrator_mode,
             get_mode_context,
             load_vault_image,
@@ -283,8 +2246,9 @@ fn main() {
             gather_academic_data,
             gather_companion_insights,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| shutdown::on_run_event(app, &event));
 }
 
 #[tauri::command]
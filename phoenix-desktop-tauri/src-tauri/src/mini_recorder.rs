@@ -0,0 +1,168 @@
+//! Floating mini-recorder: a small always-on-top window (label `mini-recorder`) with record
+//! buttons, a live input level meter and the current emotion.
+//!
+//! The window loads the frontend at `index.html#/mini-recorder`; its buttons call the regular
+//! `record_*` commands. While it is open the backend emits `mini-recorder-tick` to it about ten
+//! times a second. Its position is saved shortly after it stops moving and restored the next
+//! time it opens (unless that spot is no longer on any monitor).
+
+use multi_modal_recording::{RecorderStatus, ReportedEmotion};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, WebviewUrl, WebviewWindowBuilder, Window,
+};
+
+use crate::app_settings::AppSettingsState;
+use crate::diagnostics;
+use crate::RecorderState;
+
+pub const LABEL: &str = "mini-recorder";
+
+const WIDTH: f64 = 280.0;
+const HEIGHT: f64 = 112.0;
+const TICK_MS: u64 = 100;
+const SAVE_DELAY_MS: u64 = 500;
+
+/// Persisted widget state (logical pixels).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MiniRecorderSettings {
+    #[serde(default)]
+    pub visible: bool,
+    #[serde(default)]
+    pub x: Option<f64>,
+    #[serde(default)]
+    pub y: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MiniRecorderTick {
+    /// Microphone input level, `0.0..=1.0`.
+    pub level: f32,
+    pub status: RecorderStatus,
+    pub emotion: Option<ReportedEmotion>,
+}
+
+/// Counts moves so only the last one in a burst gets saved.
+#[derive(Default)]
+pub struct MiniRecorderState {
+    moves: AtomicU64,
+}
+
+pub fn is_open(app: &AppHandle) -> bool {
+    app.get_webview_window(LABEL).is_some()
+}
+
+/// Whether the saved top-left corner still lies on a connected monitor.
+fn on_screen(app: &AppHandle, x: f64, y: f64) -> bool {
+    app.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .any(|m| {
+            let scale = m.scale_factor();
+            let pos = m.position().to_logical::<f64>(scale);
+            let size = m.size().to_logical::<f64>(scale);
+            (pos.x..pos.x + size.width).contains(&x) && (pos.y..pos.y + size.height).contains(&y)
+        })
+}
+
+async fn open(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.show();
+        return Ok(());
+    }
+    let saved = app.state::<AppSettingsState>().get().await.mini_recorder;
+    let mut builder = WebviewWindowBuilder::new(
+        app,
+        LABEL,
+        WebviewUrl::App("index.html#/mini-recorder".into()),
+    )
+    .title("Mini recorder")
+    .inner_size(WIDTH, HEIGHT)
+    .resizable(false)
+    .decorations(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .visible_on_all_workspaces(true)
+    .focused(false);
+    builder = match (saved.x, saved.y) {
+        (Some(x), Some(y)) if on_screen(app, x, y) => builder.position(x, y),
+        _ => builder.center(),
+    };
+    builder.build().map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn(run_ticks(app.clone()));
+    Ok(())
+}
+
+/// Open or close the widget and remember the choice.
+pub async fn set_open(app: &AppHandle, open_widget: bool) -> Result<bool, String> {
+    if open_widget {
+        open(app).await?;
+    } else if let Some(window) = app.get_webview_window(LABEL) {
+        window.close().map_err(|e| e.to_string())?;
+    }
+    app.state::<AppSettingsState>()
+        .update(|s| s.mini_recorder.visible = open_widget)
+        .await?;
+    Ok(open_widget)
+}
+
+pub async fn toggle(app: &AppHandle) -> Result<bool, String> {
+    set_open(app, !is_open(app)).await
+}
+
+/// Reopen the widget on startup if it was open when the app last ran.
+pub async fn restore(app: &AppHandle) {
+    if app.state::<AppSettingsState>().get().await.mini_recorder.visible {
+        if let Err(e) = open(app).await {
+            diagnostics::report_error("mini-recorder", format!("failed to reopen: {e}"));
+        }
+    }
+}
+
+/// Window `Moved` handler: save the position once it settles.
+pub fn on_moved(window: &Window, position: PhysicalPosition<i32>) {
+    let app = window.app_handle().clone();
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let pos = position.to_logical::<f64>(scale);
+    let seq = app
+        .state::<MiniRecorderState>()
+        .moves
+        .fetch_add(1, Ordering::Relaxed)
+        + 1;
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(SAVE_DELAY_MS)).await;
+        if app.state::<MiniRecorderState>().moves.load(Ordering::Relaxed) != seq {
+            return;
+        }
+        let saved = app
+            .state::<AppSettingsState>()
+            .update(|s| {
+                s.mini_recorder.x = Some(pos.x);
+                s.mini_recorder.y = Some(pos.y);
+            })
+            .await;
+        if let Err(e) = saved {
+            diagnostics::report_error("mini-recorder", format!("failed to save position: {e}"));
+        }
+    });
+}
+
+/// Feed the widget until it is closed.
+async fn run_ticks(app: AppHandle) {
+    while let Some(window) = app.get_webview_window(LABEL) {
+        if window.is_visible().unwrap_or(false) {
+            if let Some(recorder) = app.try_state::<RecorderState>() {
+                let rec = recorder.inner.lock().await.clone();
+                let tick = MiniRecorderTick {
+                    level: rec.input_level(),
+                    status: rec.status(),
+                    emotion: rec.reported_emotion().await,
+                };
+                let _ = app.emit_to(LABEL, "mini-recorder-tick", &tick);
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(TICK_MS)).await;
+    }
+}
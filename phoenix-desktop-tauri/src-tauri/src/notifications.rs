@@ -0,0 +1,125 @@
+//! Native desktop notifications (Tauri notification plugin).
+//!
+//! Every notification is also emitted to the webview as a `notification` event. Desktop OS
+//! notification APIs don't report clicks back to the app, so the optional action (e.g. "Open
+//! recording") is surfaced there as an in-app toast button that calls `run_notification_action`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::{NotificationExt, PermissionState};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationAction {
+    /// Show the main window on a recording's detail/playback view.
+    OpenRecording { path: String },
+    /// Show the main window on a named view (e.g. `"mood"`, `"presence"`).
+    OpenView { view: String },
+}
+
+impl NotificationAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotificationAction::OpenRecording { .. } => "Open recording",
+            NotificationAction::OpenView { .. } => "Open",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Notice {
+    pub id: u64,
+    pub ts_unix: i64,
+    pub title: String,
+    pub body: String,
+    pub action: Option<NotificationAction>,
+    /// False when the OS notification could not be shown (permission denied / plugin error).
+    pub delivered: bool,
+}
+
+/// Whether native notifications may be shown, prompting the user the first time.
+pub fn ensure_permission(app: &AppHandle) -> bool {
+    let notifications = app.notification();
+    match notifications.permission_state() {
+        Ok(PermissionState::Granted) => true,
+        Ok(PermissionState::Denied) => false,
+        Ok(_) => matches!(
+            notifications.request_permission(),
+            Ok(PermissionState::Granted)
+        ),
+        Err(e) => {
+            tracing::warn!(target: "notifications", "permission check failed: {e}");
+            false
+        }
+    }
+}
+
+/// Show a native notification and mirror it to the webview.
+pub fn notify(
+    app: &AppHandle,
+    title: impl Into<String>,
+    body: impl Into<String>,
+    action: Option<NotificationAction>,
+) -> Notice {
+    let mut notice = Notice {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        ts_unix: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default(),
+        title: title.into(),
+        body: body.into(),
+        action,
+        delivered: false,
+    };
+
+    if ensure_permission(app) {
+        let mut builder = app
+            .notification()
+            .builder()
+            .id(notice.id as i32)
+            .title(&notice.title)
+            .body(&notice.body);
+        if let Some(action) = notice.action.as_ref() {
+            builder = builder.extra("action", action);
+        }
+        match builder.show() {
+            Ok(()) => notice.delivered = true,
+            Err(e) => tracing::warn!(
+                target: "notifications",
+                title = %notice.title,
+                "failed to show notification: {e}"
+            ),
+        }
+    } else {
+        tracing::info!(
+            target: "notifications",
+            title = %notice.title,
+            body = %notice.body,
+            "notification not permitted"
+        );
+    }
+
+    let _ = app.emit("notification", &notice);
+    notice
+}
+
+/// Carry out a notification action: focus the main window and tell the UI where to go.
+pub fn run_action(app: &AppHandle, action: &NotificationAction) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    match action {
+        NotificationAction::OpenRecording { path } => {
+            let _ = app.emit("open-recording", path);
+        }
+        NotificationAction::OpenView { view } => {
+            let _ = app.emit("navigate", view);
+        }
+    }
+}
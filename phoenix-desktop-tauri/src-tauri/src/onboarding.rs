@@ -0,0 +1,291 @@
+//! First-run onboarding.
+//!
+//! The wizard's progress is kept in `AppSettings.onboarding`, so a setup that was closed halfway
+//! resumes at the same step. Steps run in a fixed order; `onboarding_next` completes the current
+//! one (applying its choice, if it takes one) and `onboarding_skip` skips it where that is
+//! allowed. Installs that predate onboarding are migrated as already finished.
+//!
+//! | Step | `next` input | Skippable |
+//! |------|--------------|-----------|
+//! | `permissions` | – | yes |
+//! | `device_selection` | `{ "audio": bool, "video": bool }` | yes (keeps current devices) |
+//! | `enrollment` | – (the UI runs the enrollment commands first) | yes |
+//! | `always_listening` | `{ "enabled": bool }` | yes (stays off) |
+//! | `privacy` | `{ "acknowledged": true }` | no |
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_settings::AppSettingsState;
+use crate::permissions::{self, MediaPermissions};
+use crate::RecorderState;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStep {
+    #[default]
+    Permissions,
+    DeviceSelection,
+    Enrollment,
+    AlwaysListening,
+    Privacy,
+    Done,
+}
+
+impl OnboardingStep {
+    pub const ORDER: [OnboardingStep; 5] = [
+        OnboardingStep::Permissions,
+        OnboardingStep::DeviceSelection,
+        OnboardingStep::Enrollment,
+        OnboardingStep::AlwaysListening,
+        OnboardingStep::Privacy,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            OnboardingStep::Permissions => "Microphone and camera access",
+            OnboardingStep::DeviceSelection => "Choose devices",
+            OnboardingStep::Enrollment => "Teach Sola your voice and face",
+            OnboardingStep::AlwaysListening => "Always-listening",
+            OnboardingStep::Privacy => "How your data is handled",
+            OnboardingStep::Done => "All set",
+        }
+    }
+
+    pub fn skippable(self) -> bool {
+        !matches!(self, OnboardingStep::Privacy | OnboardingStep::Done)
+    }
+
+    fn following(self) -> OnboardingStep {
+        Self::ORDER
+            .iter()
+            .position(|s| *s == self)
+            .and_then(|i| Self::ORDER.get(i + 1).copied())
+            .unwrap_or(OnboardingStep::Done)
+    }
+}
+
+/// Persisted wizard progress.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnboardingState {
+    #[serde(default)]
+    pub step: OnboardingStep,
+    #[serde(default)]
+    pub completed: Vec<OnboardingStep>,
+    #[serde(default)]
+    pub skipped: Vec<OnboardingStep>,
+    #[serde(default)]
+    pub finished_unix: Option<i64>,
+}
+
+impl OnboardingState {
+    pub fn is_finished(&self) -> bool {
+        self.step == OnboardingStep::Done
+    }
+
+    /// Leave the current step, recording how, and move to the next one.
+    fn advance(&mut self, skipped: bool) -> Result<(), String> {
+        let step = self.step;
+        if step == OnboardingStep::Done {
+            return Err("onboarding is already finished".to_string());
+        }
+        if skipped && !step.skippable() {
+            return Err(format!("\"{}\" can't be skipped", step.title()));
+        }
+        self.completed.retain(|s| *s != step);
+        self.skipped.retain(|s| *s != step);
+        if skipped {
+            self.skipped.push(step);
+        } else {
+            self.completed.push(step);
+        }
+        self.step = step.following();
+        if self.step == OnboardingStep::Done {
+            self.finished_unix = Some(now_unix());
+        }
+        Ok(())
+    }
+}
+
+fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepProgress {
+    Pending,
+    Current,
+    Completed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepInfo {
+    pub step: OnboardingStep,
+    pub title: &'static str,
+    pub skippable: bool,
+    pub progress: StepProgress,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnboardingStatus {
+    pub step: OnboardingStep,
+    pub finished: bool,
+    pub steps: Vec<StepInfo>,
+    /// Current OS permission state, for the permissions step.
+    pub permissions: MediaPermissions,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceChoice {
+    audio: bool,
+    video: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListeningChoice {
+    enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrivacyChoice {
+    acknowledged: bool,
+}
+
+fn input<T: for<'de> Deserialize<'de>>(
+    step: OnboardingStep,
+    input: Option<Value>,
+) -> Result<T, String> {
+    let value = input.ok_or_else(|| format!("\"{}\" needs a choice", step.title()))?;
+    serde_json::from_value(value)
+        .map_err(|e| format!("invalid input for \"{}\": {e}", step.title()))
+}
+
+fn status_of(state: &OnboardingState) -> OnboardingStatus {
+    let steps = OnboardingStep::ORDER
+        .into_iter()
+        .map(|step| StepInfo {
+            step,
+            title: step.title(),
+            skippable: step.skippable(),
+            progress: if state.step == step {
+                StepProgress::Current
+            } else if state.completed.contains(&step) {
+                StepProgress::Completed
+            } else if state.skipped.contains(&step) {
+                StepProgress::Skipped
+            } else {
+                StepProgress::Pending
+            },
+        })
+        .collect();
+    OnboardingStatus {
+        step: state.step,
+        finished: state.is_finished(),
+        steps,
+        permissions: permissions::all(),
+    }
+}
+
+pub async fn status(app: &AppHandle) -> Result<OnboardingStatus, String> {
+    let settings = app.state::<AppSettingsState>();
+    Ok(status_of(&settings.get().await.onboarding))
+}
+
+/// Complete the current step with its `input` and move on.
+pub async fn next(
+    app: &AppHandle,
+    input_value: Option<Value>,
+) -> Result<OnboardingStatus, String> {
+    let settings = app.state::<AppSettingsState>();
+    let current = settings.get().await;
+    let mut onboarding = current.onboarding.clone();
+    let step = onboarding.step;
+    let mut recorder = current.recorder.clone();
+    match step {
+        OnboardingStep::DeviceSelection => {
+            let choice: DeviceChoice = input(step, input_value)?;
+            recorder.audio_enabled = choice.audio;
+            recorder.video_enabled = choice.video;
+        }
+        OnboardingStep::AlwaysListening => {
+            let choice: ListeningChoice = input(step, input_value)?;
+            recorder.always_listening = choice.enabled;
+        }
+        OnboardingStep::Privacy => {
+            let choice: PrivacyChoice = input(step, input_value)?;
+            if !choice.acknowledged {
+                return Err("the privacy explanation must be acknowledged".to_string());
+            }
+        }
+        OnboardingStep::Permissions | OnboardingStep::Enrollment | OnboardingStep::Done => {}
+    }
+    onboarding.advance(false)?;
+
+    let rec = {
+        let recorder_state = app.state::<RecorderState>();
+        let mut guard = recorder_state.inner.lock().await;
+        guard
+            .apply_config(recorder.clone())
+            .map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+    let saved = settings
+        .update(|s| {
+            s.recorder = recorder;
+            s.onboarding = onboarding;
+        })
+        .await?;
+    if step == OnboardingStep::AlwaysListening {
+        match (saved.recorder.always_listening, rec.status().always_listening) {
+            (true, false) => rec.start_always_listening().await,
+            (false, true) => rec.stop_listening(),
+            _ => {}
+        }
+    }
+    let status = status_of(&saved.onboarding);
+    let _ = app.emit("onboarding-changed", &status);
+    Ok(status)
+}
+
+/// Skip the current step (not allowed for the privacy explanation).
+pub async fn skip(app: &AppHandle) -> Result<OnboardingStatus, String> {
+    let settings = app.state::<AppSettingsState>();
+    let mut onboarding = settings.get().await.onboarding;
+    onboarding.advance(true)?;
+    let saved = settings.update(|s| s.onboarding = onboarding).await?;
+    let status = status_of(&saved.onboarding);
+    let _ = app.emit("onboarding-changed", &status);
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_the_steps_and_refuses_to_skip_privacy() {
+        let mut state = OnboardingState::default();
+        state.advance(false).unwrap();
+        state.advance(true).unwrap();
+        state.advance(true).unwrap();
+        state.advance(false).unwrap();
+        assert_eq!(state.step, OnboardingStep::Privacy);
+        assert!(state.advance(true).is_err());
+
+        state.advance(false).unwrap();
+        assert!(state.is_finished());
+        assert!(state.finished_unix.is_some());
+        assert_eq!(
+            state.skipped,
+            vec![OnboardingStep::DeviceSelection, OnboardingStep::Enrollment]
+        );
+        assert!(state.advance(false).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! OS-level microphone / camera permission state.
+//!
+//! When the OS blocks a device the recorder does not fail; it just captures nothing. These
+//! helpers let the UI explain that and send the user to the right settings pane.
+//!
+//! - macOS: AVFoundation authorization status; `request` shows the system prompt (the first time
+//!   only; afterwards the user has to change it in System Settings).
+//! - Windows: the privacy consent store in the registry. Desktop apps get no prompt, so `request`
+//!   opens the settings pane when access is off.
+//! - Linux: no permission system; access to the device nodes is reported instead.
+//!
+//! Whenever `request` finds access denied it also opens the settings pane.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaDevice {
+    Microphone,
+    Camera,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    /// Blocked by policy (MDM / parental controls / system-wide switch); the user may not be
+    /// able to change it.
+    Restricted,
+    /// Not asked yet; recording will trigger the prompt.
+    NotDetermined,
+    /// No such device is present.
+    Unavailable,
+    Unknown,
+}
+
+impl PermissionState {
+    /// Whether a recording with this device will come out empty.
+    pub fn is_blocked(self) -> bool {
+        matches!(self, PermissionState::Denied | PermissionState::Restricted)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaPermissions {
+    pub microphone: PermissionState,
+    pub camera: PermissionState,
+    /// Whether `request` can show an OS prompt here (otherwise it opens settings).
+    pub can_prompt: bool,
+}
+
+pub fn status(device: MediaDevice) -> PermissionState {
+    platform::status(device)
+}
+
+pub fn all() -> MediaPermissions {
+    MediaPermissions {
+        microphone: status(MediaDevice::Microphone),
+        camera: status(MediaDevice::Camera),
+        can_prompt: platform::CAN_PROMPT,
+    }
+}
+
+/// Ask for access. Blocks until the user answers a prompt, so call off the main thread.
+pub fn request(device: MediaDevice) -> Result<PermissionState, String> {
+    let current = status(device);
+    if current != PermissionState::NotDetermined || !platform::CAN_PROMPT {
+        if current.is_blocked() {
+            open_settings(device)?;
+        }
+        return Ok(current);
+    }
+    platform::request(device)
+}
+
+/// Open the OS settings pane for `device`.
+pub fn open_settings(device: MediaDevice) -> Result<(), String> {
+    platform::open_settings(device)
+}
+
+/// Requested devices whose permission will make the recording come out empty.
+pub fn blocked(audio: bool, video: bool) -> Vec<MediaDevice> {
+    [(audio, MediaDevice::Microphone), (video, MediaDevice::Camera)]
+        .into_iter()
+        .filter(|(wanted, device)| *wanted && status(*device).is_blocked())
+        .map(|(_, device)| device)
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{MediaDevice, PermissionState};
+    use block::ConcreteBlock;
+    use objc::runtime::{Object, BOOL};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::time::Duration;
+
+    pub const CAN_PROMPT: bool = true;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVMediaTypeAudio: *const Object;
+        static AVMediaTypeVideo: *const Object;
+    }
+
+    fn media_type(device: MediaDevice) -> *const Object {
+        unsafe {
+            match device {
+                MediaDevice::Microphone => AVMediaTypeAudio,
+                MediaDevice::Camera => AVMediaTypeVideo,
+            }
+        }
+    }
+
+    pub fn status(device: MediaDevice) -> PermissionState {
+        // AVAuthorizationStatus
+        let status: isize = unsafe {
+            msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: media_type(device)]
+        };
+        match status {
+            0 => PermissionState::NotDetermined,
+            1 => PermissionState::Restricted,
+            2 => PermissionState::Denied,
+            3 => PermissionState::Granted,
+            _ => PermissionState::Unknown,
+        }
+    }
+
+    pub fn request(device: MediaDevice) -> Result<PermissionState, String> {
+        let (tx, rx) = std::sync::mpsc::channel::<bool>();
+        let handler = ConcreteBlock::new(move |granted: BOOL| {
+            let _ = tx.send(granted != objc::runtime::NO);
+        })
+        .copy();
+        unsafe {
+            let _: () = msg_send![
+                class!(AVCaptureDevice),
+                requestAccessForMediaType: media_type(device)
+                completionHandler: &*handler
+            ];
+        }
+        // The prompt waits on the user; give up waiting (not the prompt) after a while.
+        match rx.recv_timeout(Duration::from_secs(120)) {
+            Ok(true) => Ok(PermissionState::Granted),
+            Ok(false) => Ok(PermissionState::Denied),
+            Err(_) => Ok(status(device)),
+        }
+    }
+
+    pub fn open_settings(device: MediaDevice) -> Result<(), String> {
+        let pane = match device {
+            MediaDevice::Microphone => "Privacy_Microphone",
+            MediaDevice::Camera => "Privacy_Camera",
+        };
+        std::process::Command::new("open")
+            .arg(format!(
+                "x-apple.systempreferences:com.apple.preference.security?{pane}"
+            ))
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{MediaDevice, PermissionState};
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    pub const CAN_PROMPT: bool = false;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    const CONSENT_STORE: &str =
+        r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore";
+
+    fn capability(device: MediaDevice) -> &'static str {
+        match device {
+            MediaDevice::Microphone => "microphone",
+            MediaDevice::Camera => "webcam",
+        }
+    }
+
+    /// `Allow` / `Deny` from the consent store, if set.
+    fn consent(root: &str, key: &str) -> Option<String> {
+        let out = Command::new("reg")
+            .args(["query", &format!(r"{root}\{CONSENT_STORE}\{key}"), "/v", "Value"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find(|l| l.trim_start().starts_with("Value"))
+            .and_then(|l| l.split_whitespace().last())
+            .map(str::to_string)
+    }
+
+    pub fn status(device: MediaDevice) -> PermissionState {
+        let cap = capability(device);
+        // System-wide switch, then the user's switch, then the one for desktop apps.
+        if consent("HKLM", cap).as_deref() == Some("Deny") {
+            return PermissionState::Restricted;
+        }
+        if consent("HKCU", cap).as_deref() == Some("Deny")
+            || consent("HKCU", &format!(r"{cap}\NonPackaged")).as_deref() == Some("Deny")
+        {
+            return PermissionState::Denied;
+        }
+        // Unset means the Windows default, which allows access.
+        PermissionState::Granted
+    }
+
+    pub fn request(device: MediaDevice) -> Result<PermissionState, String> {
+        Ok(status(device))
+    }
+
+    pub fn open_settings(device: MediaDevice) -> Result<(), String> {
+        Command::new("cmd")
+            .args(["/C", "start", "", &format!("ms-settings:privacy-{}", capability(device))])
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::{MediaDevice, PermissionState};
+    use std::io::ErrorKind;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    /// `O_NONBLOCK`, so probing a busy ALSA device doesn't wait for it.
+    const O_NONBLOCK: i32 = 0o4000;
+
+    pub const CAN_PROMPT: bool = false;
+
+    /// Device nodes for `device`: `/dev/video*`, or ALSA capture nodes `/dev/snd/pcmC*D*c`.
+    fn nodes(device: MediaDevice) -> Vec<std::path::PathBuf> {
+        let (dir, matches): (&str, fn(&str) -> bool) = match device {
+            MediaDevice::Camera => ("/dev", |n| n.starts_with("video")),
+            MediaDevice::Microphone => ("/dev/snd", |n| n.starts_with("pcmC") && n.ends_with('c')),
+        };
+        std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| matches(&e.file_name().to_string_lossy()))
+                    .map(|e| e.path())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn status(device: MediaDevice) -> PermissionState {
+        let nodes = nodes(device);
+        if nodes.is_empty() {
+            // Sound servers (PipeWire/PulseAudio) may still provide a microphone.
+            return match device {
+                MediaDevice::Camera => PermissionState::Unavailable,
+                MediaDevice::Microphone => PermissionState::Unknown,
+            };
+        }
+        let mut denied = false;
+        for node in nodes {
+            match std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(O_NONBLOCK)
+                .open(&node)
+            {
+                Ok(_) => return PermissionState::Granted,
+                // Busy devices are accessible, just in use.
+                Err(e) if e.raw_os_error() == Some(16) => return PermissionState::Granted,
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => denied = true,
+                Err(_) => {}
+            }
+        }
+        if denied {
+            PermissionState::Denied
+        } else {
+            PermissionState::Unknown
+        }
+    }
+
+    pub fn request(device: MediaDevice) -> Result<PermissionState, String> {
+        Ok(status(device))
+    }
+
+    pub fn open_settings(_device: MediaDevice) -> Result<(), String> {
+        Err("device access on Linux is managed by group membership (e.g. `audio`, `video`), \
+             not a settings pane"
+            .to_string())
+    }
+}
@@ -0,0 +1,100 @@
+//! Native file/folder pickers (Tauri dialog plugin) with path validation.
+//!
+//! Dialogs block until the user answers, so they run on the blocking thread pool.
+
+use multi_modal_recording::embeddings::Modality;
+use multi_modal_recording::enrollment::{self, AUDIO_EXTENSIONS, IMAGE_EXTENSIONS};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder, FilePath};
+
+fn into_path(p: FilePath) -> Option<PathBuf> {
+    p.into_path().ok()
+}
+
+fn builder(app: &AppHandle, title: &str, start_dir: Option<&Path>) -> FileDialogBuilder<tauri::Wry> {
+    let mut dialog = app.dialog().file().set_title(title);
+    if let Some(dir) = start_dir.filter(|d| d.is_dir()) {
+        dialog = dialog.set_directory(dir);
+    }
+    dialog
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pick enrollment samples for `modality`; files of the wrong type are dropped.
+pub async fn pick_enrollment_samples(
+    app: &AppHandle,
+    modality: Modality,
+    start_dir: Option<PathBuf>,
+) -> Result<Vec<PathBuf>, String> {
+    let (label, extensions) = match modality {
+        Modality::Voice => ("Audio", AUDIO_EXTENSIONS),
+        Modality::Face => ("Images", IMAGE_EXTENSIONS),
+    };
+    let dialog = builder(app, "Choose enrollment samples", start_dir.as_deref())
+        .add_filter(label, extensions);
+    let picked = blocking(move || dialog.blocking_pick_files()).await?;
+    Ok(picked
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(into_path)
+        .filter(|p| p.is_file() && enrollment::modality_for_path(p) == Some(modality))
+        .collect())
+}
+
+/// Choose where to save an export. The parent directory must exist.
+pub async fn pick_save_destination(
+    app: &AppHandle,
+    default_name: String,
+    extension: &'static str,
+    start_dir: Option<PathBuf>,
+) -> Result<Option<PathBuf>, String> {
+    let dialog = builder(app, "Export to", start_dir.as_deref())
+        .set_file_name(default_name)
+        .add_filter(extension.to_ascii_uppercase(), &[extension]);
+    let Some(path) = blocking(move || dialog.blocking_save_file()).await?.and_then(into_path)
+    else {
+        return Ok(None);
+    };
+    if !path.parent().is_some_and(Path::is_dir) {
+        return Err(format!("{} is not in an existing folder", path.display()));
+    }
+    Ok(Some(path))
+}
+
+/// Choose an existing file to open, such as an export to import.
+pub async fn pick_file(
+    app: &AppHandle,
+    title: &'static str,
+    label: &'static str,
+    extensions: &'static [&'static str],
+    start_dir: Option<PathBuf>,
+) -> Result<Option<PathBuf>, String> {
+    let dialog = builder(app, title, start_dir.as_deref()).add_filter(label, extensions);
+    let picked = blocking(move || dialog.blocking_pick_file()).await?;
+    Ok(picked.and_then(into_path).filter(|p| p.is_file()))
+}
+
+/// Choose a folder and check that it is writable.
+pub async fn pick_writable_folder(
+    app: &AppHandle,
+    title: &'static str,
+    start_dir: Option<PathBuf>,
+) -> Result<Option<PathBuf>, String> {
+    let dialog = builder(app, title, start_dir.as_deref());
+    let Some(dir) = blocking(move || dialog.blocking_pick_folder()).await?.and_then(into_path)
+    else {
+        return Ok(None);
+    };
+    let probe = dir.join(".pagi-write-test");
+    tokio::fs::write(&probe, b"ok")
+        .await
+        .map_err(|e| format!("{} is not writable: {e}", dir.display()))?;
+    let _ = tokio::fs::remove_file(&probe).await;
+    Ok(Some(dir))
+}
@@ -0,0 +1,381 @@
+//! Keyboard shortcut registry.
+//!
+//! Every hotkey has an action id, a scope and usually a default accelerator (e.g.
+//! `CommandOrControl+Shift+L`). Users remap them in `AppSettings.shortcuts`; an empty string
+//! unbinds an action and removing the override restores the default. Global shortcuts are
+//! registered with the OS through the global-shortcut plugin and re-registered whenever the
+//! settings change; in-app shortcuts are only stored here and handled by the frontend, which
+//! reads them with `shortcut_registry` and listens for `shortcuts-changed`.
+//!
+//! Two actions can't share an accelerator. A global accelerator the OS reports as taken by
+//! another application is rejected when set, and reported as a conflict on the entry if it
+//! can't be registered later (e.g. at startup).
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+use crate::app_settings::AppSettingsState;
+use crate::{clipboard, diagnostics, mini_recorder, RecorderState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    ToggleWindow,
+    ToggleListening,
+    QuickRecordAudio,
+    ToggleMiniRecorder,
+    AnalyzeClipboard,
+    PlayPause,
+    OpenRecordings,
+    OpenSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutScope {
+    /// Registered with the OS; works while the app is in the background.
+    Global,
+    /// Handled by the frontend while a window has focus.
+    App,
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 8] = [
+        ShortcutAction::ToggleWindow,
+        ShortcutAction::ToggleListening,
+        ShortcutAction::QuickRecordAudio,
+        ShortcutAction::ToggleMiniRecorder,
+        ShortcutAction::AnalyzeClipboard,
+        ShortcutAction::PlayPause,
+        ShortcutAction::OpenRecordings,
+        ShortcutAction::OpenSettings,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShortcutAction::ToggleWindow => "Show / hide window",
+            ShortcutAction::ToggleListening => "Toggle always-listening",
+            ShortcutAction::QuickRecordAudio => "Record audio (1 min)",
+            ShortcutAction::ToggleMiniRecorder => "Show / hide mini recorder",
+            ShortcutAction::AnalyzeClipboard => "Analyze clipboard message",
+            ShortcutAction::PlayPause => "Play / pause",
+            ShortcutAction::OpenRecordings => "Open recordings",
+            ShortcutAction::OpenSettings => "Open settings",
+        }
+    }
+
+    pub fn scope(self) -> ShortcutScope {
+        match self {
+            ShortcutAction::ToggleWindow
+            | ShortcutAction::ToggleListening
+            | ShortcutAction::QuickRecordAudio
+            | ShortcutAction::ToggleMiniRecorder
+            | ShortcutAction::AnalyzeClipboard => ShortcutScope::Global,
+            ShortcutAction::PlayPause
+            | ShortcutAction::OpenRecordings
+            | ShortcutAction::OpenSettings => ShortcutScope::App,
+        }
+    }
+
+    /// `None`: unbound until the user picks one.
+    pub fn default_accelerator(self) -> Option<&'static str> {
+        match self {
+            ShortcutAction::ToggleWindow => Some("CommandOrControl+Shift+Space"),
+            ShortcutAction::ToggleListening => Some("CommandOrControl+Shift+L"),
+            ShortcutAction::QuickRecordAudio => Some("CommandOrControl+Shift+R"),
+            ShortcutAction::ToggleMiniRecorder => Some("CommandOrControl+Shift+M"),
+            ShortcutAction::AnalyzeClipboard => None,
+            ShortcutAction::PlayPause => Some("CommandOrControl+Shift+P"),
+            ShortcutAction::OpenRecordings => Some("CommandOrControl+Shift+O"),
+            ShortcutAction::OpenSettings => Some("CommandOrControl+Comma"),
+        }
+    }
+}
+
+/// User overrides; actions without one use their default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShortcutSettings {
+    /// `""` unbinds the action.
+    #[serde(default)]
+    pub bindings: BTreeMap<ShortcutAction, String>,
+}
+
+impl ShortcutSettings {
+    /// Effective accelerator per bound action.
+    pub fn effective(&self) -> BTreeMap<ShortcutAction, String> {
+        ShortcutAction::ALL
+            .into_iter()
+            .filter_map(|action| {
+                let accel = match self.bindings.get(&action) {
+                    Some(s) => s.trim().to_string(),
+                    None => action.default_accelerator().unwrap_or_default().to_string(),
+                };
+                (!accel.is_empty()).then_some((action, accel))
+            })
+            .collect()
+    }
+
+    /// Every accelerator parses and none is used twice.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen: HashMap<u32, ShortcutAction> = HashMap::new();
+        for (action, accel) in self.effective() {
+            let shortcut = parse(&accel)?;
+            if let Some(other) = seen.insert(shortcut.id(), action) {
+                return Err(format!(
+                    "'{accel}' is assigned to both \"{}\" and \"{}\"",
+                    other.label(),
+                    action.label()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .parse::<Shortcut>()
+        .map_err(|e| format!("invalid shortcut '{accelerator}': {e}"))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShortcutEntry {
+    pub action: ShortcutAction,
+    pub label: &'static str,
+    pub scope: ShortcutScope,
+    pub accelerator: Option<String>,
+    pub default: Option<&'static str>,
+    /// Held with the OS right now (global shortcuts only).
+    pub registered: bool,
+    /// Why a global shortcut could not be registered.
+    pub conflict: Option<String>,
+}
+
+/// What is currently registered with the OS.
+#[derive(Default)]
+pub struct ShortcutRegistry {
+    inner: Mutex<Applied>,
+}
+
+#[derive(Default)]
+struct Applied {
+    bindings: BTreeMap<ShortcutAction, String>,
+    by_id: HashMap<u32, ShortcutAction>,
+    conflicts: HashMap<ShortcutAction, String>,
+}
+
+impl ShortcutRegistry {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Applied> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The full registry with current bindings and registration state.
+pub fn entries(app: &AppHandle) -> Vec<ShortcutEntry> {
+    let registry = app.state::<ShortcutRegistry>();
+    let applied = registry.lock();
+    ShortcutAction::ALL
+        .into_iter()
+        .map(|action| {
+            let accelerator = applied.bindings.get(&action).cloned();
+            ShortcutEntry {
+                action,
+                label: action.label(),
+                scope: action.scope(),
+                registered: applied.by_id.values().any(|a| *a == action),
+                conflict: applied.conflicts.get(&action).cloned(),
+                default: action.default_accelerator(),
+                accelerator,
+            }
+        })
+        .collect()
+}
+
+/// Replace the OS registrations with `settings`' global shortcuts.
+pub fn apply(app: &AppHandle, settings: &ShortcutSettings) {
+    let bindings = settings.effective();
+    let registry = app.state::<ShortcutRegistry>();
+    let mut applied = registry.lock();
+    if applied.bindings == bindings && !applied.by_id.is_empty() {
+        return;
+    }
+    let global = app.global_shortcut();
+    if let Err(e) = global.unregister_all() {
+        diagnostics::report_error("shortcuts", format!("failed to unregister shortcuts: {e}"));
+    }
+    applied.by_id.clear();
+    applied.conflicts.clear();
+    for (action, accel) in &bindings {
+        if action.scope() != ShortcutScope::Global {
+            continue;
+        }
+        let shortcut = match parse(accel) {
+            Ok(s) => s,
+            Err(e) => {
+                applied.conflicts.insert(*action, e);
+                continue;
+            }
+        };
+        match global.register(shortcut) {
+            Ok(()) => {
+                applied.by_id.insert(shortcut.id(), *action);
+            }
+            Err(e) => {
+                let msg = format!("'{accel}' is already in use ({e})");
+                diagnostics::report_error("shortcuts", msg.clone());
+                applied.conflicts.insert(*action, msg);
+            }
+        }
+    }
+    applied.bindings = bindings;
+    drop(applied);
+    let _ = app.emit("shortcuts-changed", entries(app));
+}
+
+/// Rebind `action` (`None` restores the default, `Some("")` unbinds it).
+pub async fn set_binding(
+    app: &AppHandle,
+    action: ShortcutAction,
+    accelerator: Option<String>,
+) -> Result<Vec<ShortcutEntry>, String> {
+    let settings = app.state::<AppSettingsState>();
+    let mut next = settings.get().await.shortcuts;
+    match accelerator.as_deref().map(str::trim) {
+        None => {
+            next.bindings.remove(&action);
+        }
+        Some(accel) => {
+            next.bindings.insert(action, accel.to_string());
+        }
+    }
+    next.validate()?;
+
+    // Probe the OS for a new global accelerator before saving it.
+    if let (ShortcutScope::Global, Some(accel)) =
+        (action.scope(), next.effective().get(&action).cloned())
+    {
+        let shortcut = parse(&accel)?;
+        let ours = app
+            .state::<ShortcutRegistry>()
+            .lock()
+            .by_id
+            .contains_key(&shortcut.id());
+        if !ours {
+            let global = app.global_shortcut();
+            global
+                .register(shortcut)
+                .map_err(|e| format!("'{accel}' is already used by another application ({e})"))?;
+            let _ = global.unregister(shortcut);
+        }
+    }
+
+    let saved = settings.update(|s| s.shortcuts = next).await?;
+    apply(app, &saved.shortcuts);
+    Ok(entries(app))
+}
+
+/// Run the action bound to a pressed global shortcut.
+pub fn trigger(app: &AppHandle, shortcut: &Shortcut) {
+    let action = app
+        .state::<ShortcutRegistry>()
+        .lock()
+        .by_id
+        .get(&shortcut.id())
+        .copied();
+    let Some(action) = action else {
+        return;
+    };
+    let _ = app.emit("shortcut-triggered", action);
+    match action {
+        ShortcutAction::ToggleWindow => {
+            if let Some(window) = app.get_webview_window("main") {
+                if window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false) {
+                    let _ = window.hide();
+                } else {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        }
+        ShortcutAction::ToggleListening => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let Some(recorder) = app.try_state::<RecorderState>() else {
+                    return;
+                };
+                let rec = recorder.inner.lock().await.clone();
+                if rec.status().always_listening {
+                    rec.stop_listening();
+                } else {
+                    rec.start_always_listening().await;
+                }
+            });
+        }
+        ShortcutAction::QuickRecordAudio => {
+            tauri::async_runtime::spawn(crate::tray_record(app.clone(), true, false, 60));
+        }
+        ShortcutAction::ToggleMiniRecorder => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = mini_recorder::toggle(&app).await {
+                    diagnostics::report_error("mini-recorder", e);
+                }
+            });
+        }
+        ShortcutAction::AnalyzeClipboard => {
+            tauri::async_runtime::spawn(clipboard::analyze_from_shortcut(app.clone()));
+        }
+        // In-app actions are never registered globally.
+        ShortcutAction::PlayPause
+        | ShortcutAction::OpenRecordings
+        | ShortcutAction::OpenSettings => {}
+    }
+}
+
+/// Register the saved shortcuts and follow later settings changes.
+pub async fn run(app: AppHandle) {
+    let Some(settings) = app.try_state::<AppSettingsState>() else {
+        return;
+    };
+    let mut rx = settings.subscribe();
+    apply(&app, &settings.get().await.shortcuts);
+    loop {
+        match rx.recv().await {
+            Ok(next) => apply(&app, &next.shortcuts),
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_duplicate_accelerators() {
+        let mut settings = ShortcutSettings::default();
+        assert!(settings.validate().is_ok());
+
+        settings.bindings.insert(
+            ShortcutAction::OpenSettings,
+            ShortcutAction::ToggleListening
+                .default_accelerator()
+                .unwrap()
+                .to_string(),
+        );
+        assert!(settings.validate().is_err());
+
+        // Unbinding the other action resolves it.
+        settings
+            .bindings
+            .insert(ShortcutAction::ToggleListening, String::new());
+        assert!(settings.validate().is_ok());
+        assert!(!settings
+            .effective()
+            .contains_key(&ShortcutAction::ToggleListening));
+    }
+}
@@ -0,0 +1,75 @@
+//! Orderly exit.
+//!
+//! Every exit path (tray "Quit", `app.exit`, OS logout / Cmd+Q) arrives as
+//! `RunEvent::ExitRequested`. The first one is held back while the recorder stops its captures
+//! and finishes writing files and the settings are saved; then the exit is re-issued.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, RunEvent};
+
+use crate::app_settings::AppSettingsState;
+use crate::crash_reports;
+use crate::diagnostics;
+use crate::idle_pause;
+use crate::web_sidecar;
+use crate::RecorderState;
+
+/// How long to wait for in-progress recordings before exiting anyway.
+const FINALIZE_TIMEOUT: Duration = Duration::from_secs(15);
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static DONE: AtomicBool = AtomicBool::new(false);
+
+/// `RunEvent` handler; pass every event through.
+pub fn on_run_event(app: &AppHandle, event: &RunEvent) {
+    let RunEvent::ExitRequested { api, code, .. } = event else {
+        return;
+    };
+    if DONE.load(Ordering::Relaxed) {
+        return;
+    }
+    api.prevent_exit();
+    if STARTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let app = app.clone();
+    let code = code.unwrap_or(0);
+    tauri::async_runtime::spawn(async move {
+        run(&app).await;
+        crash_reports::end_session();
+        DONE.store(true, Ordering::Relaxed);
+        app.exit(code);
+    });
+}
+
+async fn run(app: &AppHandle) {
+    let _ = app.emit("shutting-down", ());
+    web_sidecar::stop(app);
+    if let Some(recorder) = app.try_state::<RecorderState>() {
+        let rec = recorder.inner.lock().await.clone();
+        // Read before shutdown stops it, so listening comes back on next launch.
+        let listening = rec.status().always_listening || idle_pause::is_auto_paused(app);
+        let report = rec.shutdown(FINALIZE_TIMEOUT).await;
+        if let Some(e) = report.error.as_ref() {
+            diagnostics::report_error("shutdown", format!("failed to save schedules: {e}"));
+        }
+        if report.recordings_unfinished > 0 {
+            diagnostics::report_error(
+                "shutdown",
+                format!(
+                    "exiting with {} recording(s) still being written",
+                    report.recordings_unfinished
+                ),
+            );
+        }
+        if let Some(settings) = app.try_state::<AppSettingsState>() {
+            if let Err(e) = settings
+                .update(|s| s.recorder.always_listening = listening)
+                .await
+            {
+                diagnostics::report_error("shutdown", format!("failed to save settings: {e}"));
+            }
+        }
+    }
+}
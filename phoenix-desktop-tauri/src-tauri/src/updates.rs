@@ -0,0 +1,185 @@
+//! Auto-update (Tauri updater plugin) with a stable/beta channel.
+//!
+//! The update feed is `PAGI_UPDATE_ENDPOINT`, where `{channel}` is replaced by the configured
+//! channel, e.g. `https://example.com/{channel}/{{target}}/{{arch}}/{{current_version}}` (the
+//! double-braced variables are filled in by the updater itself). Without it, update checks
+//! report that updates are not configured.
+//!
+//! Updates found by the periodic check are downloaded in the background; the tray's update
+//! entry is then enabled and a notification points at it. Nothing installs without the user.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{menu::MenuItem, AppHandle, Manager, Url, Wry};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::app_settings::{AppSettingsState, UpdateChannel};
+use crate::{diagnostics, i18n, notifications};
+
+/// Delay before the first scheduled check, then the interval between checks.
+pub const FIRST_CHECK_SECS: u64 = 60;
+pub const CHECK_INTERVAL_SECS: u64 = 6 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+    /// The package has been downloaded and `install_update` can apply it right away.
+    pub downloaded: bool,
+}
+
+#[derive(Default)]
+pub struct UpdateState {
+    pending: Mutex<Option<(Update, Option<Vec<u8>>)>>,
+    tray_item: Mutex<Option<MenuItem<Wry>>>,
+}
+
+impl UpdateState {
+    pub fn set_tray_item(&self, item: MenuItem<Wry>) {
+        if let Ok(mut guard) = self.tray_item.lock() {
+            *guard = Some(item);
+        }
+    }
+
+    fn set_tray(&self, text: &str, enabled: bool) {
+        if let Some(item) = self.tray_item.lock().ok().and_then(|g| g.clone()) {
+            let _ = item.set_text(text);
+            let _ = item.set_enabled(enabled);
+        }
+    }
+
+    /// Re-translate the tray item after a language change.
+    pub fn relabel(&self) {
+        let ready = self.pending.lock().ok().and_then(|g| {
+            g.as_ref()
+                .filter(|(_, bytes)| bytes.is_some())
+                .map(|(u, _)| u.version.clone())
+        });
+        match ready {
+            Some(version) => self.set_tray(
+                &i18n::t_args("tray-install-update", &[("version", version.into())]),
+                true,
+            ),
+            None => self.set_tray(&i18n::t("tray-no-updates"), false),
+        }
+    }
+}
+
+fn endpoint(channel: UpdateChannel) -> Result<Url, String> {
+    let template = std::env::var("PAGI_UPDATE_ENDPOINT")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| "updates are not configured (PAGI_UPDATE_ENDPOINT is unset)".to_string())?;
+    let url = template.replace("{channel}", channel.as_str());
+    Url::parse(&url).map_err(|e| format!("invalid PAGI_UPDATE_ENDPOINT: {e}"))
+}
+
+fn info(update: &Update, channel: UpdateChannel, downloaded: bool) -> UpdateInfo {
+    UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel,
+        notes: update.body.clone(),
+        date: update.date.map(|d| d.to_string()),
+        downloaded,
+    }
+}
+
+/// Check the configured channel. A found update is remembered (not downloaded).
+pub async fn check(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let channel = match app.try_state::<AppSettingsState>() {
+        Some(settings) => settings.get().await.update_channel,
+        None => UpdateChannel::default(),
+    };
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![endpoint(channel)?])
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?;
+    let Some(update) = updater.check().await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let state = app.state::<UpdateState>();
+    let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+    // Keep an already-downloaded package for the same version.
+    let downloaded = matches!(
+        pending.as_ref(),
+        Some((p, Some(_))) if p.version == update.version
+    );
+    if !downloaded {
+        *pending = Some((update.clone(), None));
+    }
+    Ok(Some(info(&update, channel, downloaded)))
+}
+
+/// Download the remembered update (if any) and announce it.
+pub async fn download_pending(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let state = app.state::<UpdateState>();
+    let Some(update) = state
+        .pending
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .filter(|(_, bytes)| bytes.is_none())
+        .map(|(u, _)| u.clone())
+    else {
+        return Ok(None);
+    };
+    let bytes = update
+        .download(|_, _| {}, || {})
+        .await
+        .map_err(|e| e.to_string())?;
+    *state.pending.lock().map_err(|e| e.to_string())? = Some((update.clone(), Some(bytes)));
+
+    let channel = match app.try_state::<AppSettingsState>() {
+        Some(settings) => settings.get().await.update_channel,
+        None => UpdateChannel::default(),
+    };
+    let version = update.version.as_str();
+    state.set_tray(
+        &i18n::t_args("tray-install-update", &[("version", version.into())]),
+        true,
+    );
+    notifications::notify(
+        app,
+        i18n::t("update-ready"),
+        i18n::t_args("update-ready-body", &[("version", version.into())]),
+        None,
+    );
+    Ok(Some(info(&update, channel, true)))
+}
+
+/// Install the pending update (downloading it first if needed) and restart.
+pub async fn install(app: &AppHandle) -> Result<(), String> {
+    // No-op when the scheduled job already downloaded it.
+    download_pending(app).await?;
+    let state = app.state::<UpdateState>();
+    let (update, bytes) = match state.pending.lock().map_err(|e| e.to_string())?.take() {
+        Some((update, Some(bytes))) => (update, bytes),
+        _ => return Err("no downloaded update to install".to_string()),
+    };
+    state.set_tray(&i18n::t("tray-installing-update"), false);
+    update.install(bytes).map_err(|e| e.to_string())?;
+    app.restart();
+}
+
+/// Scheduled check + background download.
+pub async fn run_update_loop(app: AppHandle) {
+    tokio::time::sleep(std::time::Duration::from_secs(FIRST_CHECK_SECS)).await;
+    loop {
+        match check(&app).await {
+            Ok(Some(_)) => {
+                if let Err(e) = download_pending(&app).await {
+                    diagnostics::report_error("updates", format!("download failed: {e}"));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => diagnostics::report_error("updates", format!("check failed: {e}")),
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+    }
+}
@@ -0,0 +1,315 @@
+//! phoenix-web (`pagi-sola-web`) run as a managed sidecar process.
+//!
+//! If a healthy server already answers on `PHOENIX_WEB_BIND` (e.g. one started by hand), that one
+//! is used. Otherwise the bundled binary is started on port 0 and reports the port it got on
+//! stdout; it is then polled on `/health` and restarted with backoff when it exits or stops
+//! answering. Restarts ask for the same port again, and take a fresh one if it has been taken
+//! meanwhile. The frontend gets the base URL from `web_server_status` and `web-server-status`
+//! events.
+//!
+//! Set `PHOENIX_WEB_SIDECAR=0` to turn this off.
+
+use common_types::ports::PhoenixWebPort;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::diagnostics;
+
+const SIDECAR: &str = "pagi-sola-web";
+/// Lets the server pick a free port, so none can be taken between choosing and binding it.
+const ANY_LOCAL_PORT: &str = "127.0.0.1:0";
+/// Printed by the server once bound, followed by the address.
+const LISTENING_PREFIX: &str = "PHOENIX_WEB_LISTENING=";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+/// Consecutive failed health checks before the process is restarted.
+const MAX_HEALTH_FAILURES: u32 = 3;
+const MAX_BACKOFF_SECS: u64 = 30;
+/// A run this long resets the restart backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebServerState {
+    #[default]
+    Starting,
+    Running,
+    /// Running but not answering health checks.
+    Unhealthy,
+    Restarting,
+    Stopped,
+    Disabled,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WebServerStatus {
+    pub state: WebServerState,
+    /// e.g. `http://127.0.0.1:49152`; set once the server has answered.
+    pub base_url: Option<String>,
+    /// Using a server this app did not start.
+    pub external: bool,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct WebSidecar {
+    status: Mutex<WebServerStatus>,
+    child: Mutex<Option<CommandChild>>,
+    stopping: AtomicBool,
+}
+
+impl WebSidecar {
+    pub fn status(&self) -> WebServerStatus {
+        self.status.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn set_child(&self, child: Option<CommandChild>) -> Option<CommandChild> {
+        self.child
+            .lock()
+            .map(|mut c| std::mem::replace(&mut *c, child))
+            .unwrap_or_default()
+    }
+}
+
+fn update(app: &AppHandle, f: impl FnOnce(&mut WebServerStatus)) {
+    let sidecar = app.state::<WebSidecar>();
+    let Ok(mut status) = sidecar.status.lock() else {
+        return;
+    };
+    f(&mut status);
+    let _ = app.emit("web-server-status", &*status);
+}
+
+/// `GET /health` answered with 200.
+async fn healthy(addr: &str) -> bool {
+    let check = async {
+        let mut stream = tokio::net::TcpStream::connect(addr).await?;
+        let request = format!("GET /health HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await?;
+        let mut buf = [0u8; 32];
+        let n = stream.read(&mut buf).await?;
+        let status_line = String::from_utf8_lossy(&buf[..n]).into_owned();
+        Ok::<_, std::io::Error>(status_line.split_whitespace().nth(1) == Some("200"))
+    };
+    matches!(tokio::time::timeout(HEALTH_TIMEOUT, check).await, Ok(Ok(true)))
+}
+
+/// The address in the server's `PHOENIX_WEB_LISTENING=` line, if `line` is that line.
+fn listening_addr(line: &[u8]) -> Option<String> {
+    let line = std::str::from_utf8(line).ok()?.trim();
+    line.strip_prefix(LISTENING_PREFIX)
+        .filter(|addr| !addr.is_empty())
+        .map(str::to_string)
+}
+
+fn disabled_by_env() -> bool {
+    std::env::var("PHOENIX_WEB_SIDECAR")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "0" | "false" | "off"))
+        .unwrap_or(false)
+}
+
+/// Why a monitored run ended.
+enum Exit {
+    Terminated(String),
+    Unhealthy,
+    Stopping,
+}
+
+/// Watch a run; `listening` is set to the address the server reports once it is bound.
+async fn monitor(
+    app: &AppHandle,
+    listening: &mut Option<String>,
+    rx: &mut tauri::async_runtime::Receiver<CommandEvent>,
+) -> Exit {
+    let started = Instant::now();
+    let mut answered = false;
+    let mut failures = 0u32;
+    let mut ticker = tokio::time::interval(Duration::from_millis(500));
+    loop {
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(CommandEvent::Stdout(line)) => {
+                    if let Some(addr) = listening_addr(&line) {
+                        *listening = Some(addr);
+                    }
+                }
+                Some(CommandEvent::Stderr(line)) => {
+                    let line = String::from_utf8_lossy(&line);
+                    tracing::info!(target: "sidecar", "{}", line.trim_end());
+                }
+                Some(CommandEvent::Terminated(payload)) => {
+                    return Exit::Terminated(format!(
+                        "exited (code {:?}, signal {:?})",
+                        payload.code, payload.signal
+                    ));
+                }
+                Some(CommandEvent::Error(e)) => return Exit::Terminated(e),
+                Some(_) => {}
+                None => return Exit::Terminated("output closed".to_string()),
+            },
+            _ = ticker.tick() => {
+                if app.state::<WebSidecar>().stopping.load(Ordering::Relaxed) {
+                    return Exit::Stopping;
+                }
+                // Poll quickly until the first answer, then every HEALTH_INTERVAL.
+                if answered && ticker.period() < HEALTH_INTERVAL {
+                    ticker = tokio::time::interval(HEALTH_INTERVAL);
+                    ticker.tick().await;
+                }
+                let Some(addr) = listening.as_deref() else {
+                    if started.elapsed() > STARTUP_TIMEOUT {
+                        return Exit::Unhealthy;
+                    }
+                    continue;
+                };
+                if healthy(addr).await {
+                    failures = 0;
+                    if !answered {
+                        answered = true;
+                        update(app, |s| {
+                            s.state = WebServerState::Running;
+                            s.base_url = Some(format!("http://{addr}"));
+                        });
+                    } else if app.state::<WebSidecar>().status().state != WebServerState::Running {
+                        update(app, |s| s.state = WebServerState::Running);
+                    }
+                } else if !answered {
+                    if started.elapsed() > STARTUP_TIMEOUT {
+                        return Exit::Unhealthy;
+                    }
+                } else {
+                    failures += 1;
+                    update(app, |s| s.state = WebServerState::Unhealthy);
+                    if failures >= MAX_HEALTH_FAILURES {
+                        return Exit::Unhealthy;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Keep the web server up until [`stop`] is called.
+pub async fn run(app: AppHandle) {
+    if disabled_by_env() {
+        update(&app, |s| s.state = WebServerState::Disabled);
+        return;
+    }
+
+    let configured = PhoenixWebPort::bind();
+    if healthy(&configured).await {
+        update(&app, |s| {
+            s.state = WebServerState::Running;
+            s.external = true;
+            s.base_url = Some(format!("http://{configured}"));
+        });
+        // Only take over once the external server goes away.
+        while healthy(&configured).await {
+            tokio::time::sleep(HEALTH_INTERVAL).await;
+            if app.state::<WebSidecar>().stopping.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+        update(&app, |s| {
+            s.external = false;
+            s.base_url = None;
+            s.state = WebServerState::Starting;
+        });
+    }
+
+    let mut addr = None::<String>;
+    let mut backoff_secs = 1;
+    loop {
+        let bind = addr.clone().unwrap_or_else(|| ANY_LOCAL_PORT.to_string());
+        let spawned = app
+            .shell()
+            .sidecar(SIDECAR)
+            .map(|cmd| cmd.env(PhoenixWebPort::ENV_VAR, &bind))
+            .and_then(|cmd| cmd.spawn());
+        let exit = match spawned {
+            Ok((mut rx, child)) => {
+                let pid = child.pid();
+                app.state::<WebSidecar>().set_child(Some(child));
+                update(&app, |s| {
+                    s.state = WebServerState::Starting;
+                    s.pid = Some(pid);
+                });
+                let started = Instant::now();
+                let mut listening = None;
+                let exit = monitor(&app, &mut listening, &mut rx).await;
+                if let Some(child) = app.state::<WebSidecar>().set_child(None) {
+                    let _ = child.kill();
+                }
+                let answered = app.state::<WebSidecar>().status().base_url.is_some();
+                // Keep the port (and base URL) across restarts once it has worked. A run that
+                // never got to listen may have found it taken (AddrInUse): start over on port 0.
+                addr = listening.filter(|_| answered);
+                if started.elapsed() >= STABLE_AFTER {
+                    backoff_secs = 1;
+                }
+                exit
+            }
+            Err(e) => Exit::Terminated(format!("failed to start: {e}")),
+        };
+
+        let reason = match exit {
+            Exit::Stopping => break,
+            Exit::Unhealthy => "stopped answering health checks".to_string(),
+            Exit::Terminated(reason) => reason,
+        };
+        if app.state::<WebSidecar>().stopping.load(Ordering::Relaxed) {
+            break;
+        }
+        diagnostics::report_error("web-server", format!("{SIDECAR} {reason}; restarting"));
+        update(&app, |s| {
+            s.state = WebServerState::Restarting;
+            s.pid = None;
+            s.restarts += 1;
+            s.last_error = Some(reason);
+            if addr.is_none() {
+                s.base_url = None;
+            }
+        });
+        tokio::time::sleep(Duration::from_secs(backoff_secs)).await;
+        backoff_secs = (backoff_secs * 2).min(MAX_BACKOFF_SECS);
+    }
+    update(&app, |s| {
+        s.state = WebServerState::Stopped;
+        s.pid = None;
+    });
+}
+
+/// Stop the sidecar (part of the shutdown sequence).
+pub fn stop(app: &AppHandle) {
+    let Some(sidecar) = app.try_state::<WebSidecar>() else {
+        return;
+    };
+    sidecar.stopping.store(true, Ordering::Relaxed);
+    if let Some(child) = sidecar.set_child(None) {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_address_the_server_reports() {
+        assert_eq!(
+            listening_addr(b"PHOENIX_WEB_LISTENING=127.0.0.1:49152\n").as_deref(),
+            Some("127.0.0.1:49152")
+        );
+        assert_eq!(listening_addr(b"PHOENIX_WEB_LISTENING=\n"), None);
+        assert_eq!(listening_addr(b"Settings loaded from phoenix.toml"), None);
+    }
+}
@@ -0,0 +1,86 @@
+//! Generates the switchboard gRPC service stubs (see `src/grpc.rs`).
+//!
+//! The messages are hand-written prost structs, so the service is described here rather than
+//! compiled from `proto/switchboard.proto` and no `protoc` is needed. Keep the two in step.
+
+use tonic_build::manual::{Builder, Method, Service};
+
+fn method(
+    name: &str,
+    route: &str,
+    input: &str,
+    output: &str,
+) -> tonic_build::manual::MethodBuilder {
+    Method::builder()
+        .name(name)
+        .route_name(route)
+        .input_type(format!("crate::grpc::{input}"))
+        .output_type(format!("crate::grpc::{output}"))
+        .codec_path("tonic::codec::ProstCodec")
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let switchboard = Service::builder()
+        .name("Switchboard")
+        .package("phoenix.switchboard.v1")
+        .method(
+            method(
+                "simulate_ghost",
+                "SimulateGhost",
+                "SimulateGhostRequest",
+                "SimulateGhostReply",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "analyze_resonance",
+                "AnalyzeResonance",
+                "AnalyzeResonanceRequest",
+                "AnalyzeResonanceReply",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "recording_status",
+                "RecordingStatus",
+                "RecordingStatusRequest",
+                "RecordingStatusReply",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "start_recording",
+                "StartRecording",
+                "StartRecordingRequest",
+                "StartRecordingReply",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "stop_recording",
+                "StopRecording",
+                "StopRecordingRequest",
+                "StopRecordingReply",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "stream_events",
+                "StreamEvents",
+                "StreamEventsRequest",
+                "Event",
+            )
+            .server_streaming()
+            .build(),
+        )
+        .build();
+
+    Builder::new().compile(&[switchboard]);
+}
@@ -0,0 +1,261 @@
+//! Runtime administration: subsystem switches, the effective configuration and background tasks.
+//!
+//! Lets an operator turn things off and on again without restarting the server. Switches are
+//! not persisted: a restart goes back to what the settings file and environment say.
+//!
+//! Routes (under `/api`, all need the `admin` scope):
+//! - `GET /admin/toggles`: every switch as `{"enabled", "available"}`
+//! - `PATCH /admin/toggles`: change some of them (`{"always_listening"?, "emotion_inference"?,
+//!   "telemetry"?, "llm"?}`); returns the new state
+//! - `GET /admin/config`: each setting's effective value and where it came from, flagging the
+//!   ones that only apply after a restart (see [`crate::config_reload`])
+//! - `GET /admin/tasks`: the long-running tasks spawned at startup and the scheduler's jobs
+//! - `GET /admin/cache`: entries and hit counts of [`crate::compute_cache`];
+//!   `DELETE /admin/cache` empties it
+//! - `GET /admin/audit`: the audit log, see [`crate::audit`]
+//!
+//! Switch changes are recorded in the audit log.
+//!
+//! `llm` off makes every route behave as if no LLM were configured; `emotion_inference` off
+//! rejects `POST /api/emotion/text` and leaves Ghost replies without a user emotion.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+
+use crate::audit::{self, Action, Actor};
+use crate::{api_json_config, metrics, ApiError, AppState};
+
+const MAX_BODY_BYTES: usize = 4 * 1024;
+
+/// Switches for subsystems that are always constructed but can be bypassed at runtime.
+#[derive(Debug)]
+pub struct RuntimeToggles {
+    emotion_inference: AtomicBool,
+    llm: AtomicBool,
+}
+
+impl Default for RuntimeToggles {
+    fn default() -> Self {
+        Self {
+            emotion_inference: AtomicBool::new(true),
+            llm: AtomicBool::new(true),
+        }
+    }
+}
+
+impl RuntimeToggles {
+    pub fn emotion_inference(&self) -> bool {
+        self.emotion_inference.load(Ordering::Relaxed)
+    }
+
+    pub fn set_emotion_inference(&self, on: bool) {
+        self.emotion_inference.store(on, Ordering::Relaxed);
+    }
+
+    pub fn llm(&self) -> bool {
+        self.llm.load(Ordering::Relaxed)
+    }
+
+    pub fn set_llm(&self, on: bool) {
+        self.llm.store(on, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminError {
+    #[error("{0} is not available in this build or configuration")]
+    Unavailable(&'static str),
+    #[error("switching {0} failed: {1}")]
+    Failed(&'static str, String),
+}
+
+impl From<AdminError> for ApiError {
+    fn from(e: AdminError) -> Self {
+        match e {
+            AdminError::Unavailable(_) => ApiError::new(StatusCode::CONFLICT, e.to_string()),
+            AdminError::Failed(..) => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Toggle {
+    pub enabled: bool,
+    /// False when the subsystem was not started, so the switch can't turn it on.
+    pub available: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToggleState {
+    pub always_listening: Toggle,
+    pub emotion_inference: Toggle,
+    pub telemetry: Toggle,
+    pub llm: Toggle,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TogglePatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub always_listening: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emotion_inference: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm: Option<bool>,
+}
+
+async fn toggle_state(state: &AppState) -> ToggleState {
+    let always_listening = match &state.audio_intelligence {
+        Some(audio) => Toggle {
+            enabled: audio.lock().await.is_listening(),
+            available: true,
+        },
+        None => Toggle {
+            enabled: false,
+            available: false,
+        },
+    };
+    let llm_configured = state.llm.lock().await.is_some();
+    ToggleState {
+        always_listening,
+        emotion_inference: Toggle {
+            enabled: state.toggles.emotion_inference(),
+            available: true,
+        },
+        telemetry: Toggle {
+            enabled: metrics::enabled(),
+            available: true,
+        },
+        llm: Toggle {
+            enabled: llm_configured && state.toggles.llm(),
+            available: llm_configured,
+        },
+    }
+}
+
+/// Apply `patch`. Checked up front so an unavailable switch leaves the others untouched.
+pub(crate) async fn apply(state: &AppState, patch: &TogglePatch) -> Result<(), AdminError> {
+    let audio = match patch.always_listening {
+        Some(on) => Some((
+            on,
+            state
+                .audio_intelligence
+                .as_ref()
+                .ok_or(AdminError::Unavailable("always_listening"))?,
+        )),
+        None => None,
+    };
+    if let Some((on, audio)) = audio {
+        let ai = audio.lock().await;
+        if on {
+            ai.start_ambient_listening()
+                .await
+                .map_err(|e| AdminError::Failed("always_listening", e.to_string()))?;
+        } else {
+            ai.stop_listening();
+        }
+        info!(target: "admin", on, "always-listening switched");
+    }
+    if let Some(on) = patch.emotion_inference {
+        state.toggles.set_emotion_inference(on);
+        info!(target: "admin", on, "emotion inference switched");
+    }
+    if let Some(on) = patch.telemetry {
+        metrics::set_enabled(on);
+        info!(target: "admin", on, "telemetry switched");
+    }
+    if let Some(on) = patch.llm {
+        state.toggles.set_llm(on);
+        info!(target: "admin", on, "LLM backend switched");
+    }
+    Ok(())
+}
+
+async fn get_toggles(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(toggle_state(&state).await)
+}
+
+async fn patch_toggles(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<TogglePatch>,
+) -> Result<HttpResponse, ApiError> {
+    apply(&state, &body).await?;
+    let params = serde_json::to_value(&*body).unwrap_or_default();
+    audit::record(&state, &Actor::of(&req), Action::TogglesSet, params);
+    Ok(HttpResponse::Ok().json(toggle_state(&state).await))
+}
+
+async fn get_config(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "config_file": state.config_view.file_path(),
+        "settings": state.config_view.settings(),
+    }))
+}
+
+async fn get_tasks(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "tasks": state.background.status(),
+        "jobs": state.scheduler.list(),
+    }))
+}
+
+async fn get_cache(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "resonance": state.cache.resonance.stats(),
+        "trends": state.cache.trends.stats(),
+    }))
+}
+
+async fn delete_cache(state: web::Data<AppState>) -> HttpResponse {
+    state.cache.clear();
+    info!(target: "admin", "compute cache cleared");
+    HttpResponse::NoContent().finish()
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope. Plain resources rather than an `/admin` scope,
+    // which `backup` shares.
+    cfg.service(
+        web::resource("/admin/toggles")
+            .app_data(api_json_config(MAX_BODY_BYTES))
+            .route(web::get().to(get_toggles))
+            .route(web::patch().to(patch_toggles)),
+    )
+    .route("/admin/config", web::get().to(get_config))
+    .route("/admin/tasks", web::get().to(get_tasks))
+    .service(
+        web::resource("/admin/cache")
+            .route(web::get().to(get_cache))
+            .route(web::delete().to(delete_cache)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_start_on_and_switch_independently() {
+        let toggles = RuntimeToggles::default();
+        assert!(toggles.llm() && toggles.emotion_inference());
+        toggles.set_llm(false);
+        assert!(!toggles.llm());
+        assert!(toggles.emotion_inference());
+    }
+
+    #[test]
+    fn unknown_switches_are_rejected() {
+        let patch: TogglePatch = serde_json::from_str(r#"{"telemetry": false}"#).unwrap();
+        assert_eq!(patch.telemetry, Some(false));
+        assert!(patch.llm.is_none());
+        assert!(serde_json::from_str::<TogglePatch>(r#"{"gpu": true}"#).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::sync::{Mutex, OnceLock};
 
+use phoenix_storage::Storage;
 use uuid::Uuid;
 
 use crate::counselor_api::GriefEvent;
@@ -249,11 +249,8 @@ pub fn calculate_trigger_correlations(events: &[GriefEvent]) -> Vec<TagCorrelati
 // Phase 16b: Drift Analysis (Ghost session enmeshment)
 // ---
 
-static GHOST_SESSION_STARTS: OnceLock<Mutex<HashMap<Uuid, u8>>> = OnceLock::new();
-
-fn ghost_session_map() -> &'static Mutex<HashMap<Uuid, u8>> {
-    GHOST_SESSION_STARTS.get_or_init(|| Mutex::new(HashMap::new()))
-}
+/// Ghost sessions are kept this long for drift history, then pruned by the scheduler.
+pub const GHOST_SESSION_KEEP_DAYS: i64 = 30;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GhostDrift {
@@ -268,19 +265,14 @@ pub struct GhostDrift {
     pub drift_alert: bool,
 }
 
-/// Records the start of a ghost session and returns a session id.
-pub fn record_ghost_session_start(system_load_start: u8) -> Uuid {
+/// Records the start of a ghost session for the active profile and returns a session id.
+pub fn record_ghost_session_start(storage: &Storage, system_load_start: u8) -> Uuid {
     let id = Uuid::new_v4();
-    if let Ok(mut m) = ghost_session_map().lock() {
-        m.insert(id, system_load_start.min(100));
-        // Best-effort GC: bound the map.
-        if m.len() > 2_000 {
-            // Drain arbitrary oldest-ish entries (HashMap has no order; this is best-effort).
-            let to_remove: Vec<Uuid> = m.keys().take(500).cloned().collect();
-            for k in to_remove {
-                m.remove(&k);
-            }
-        }
+    let now = chrono::Utc::now().timestamp();
+    let load = system_load_start.min(100);
+    let profile = crate::user_profiles::active(storage);
+    if let Err(e) = storage.start_ghost_session(&id.to_string(), &profile, now, load) {
+        tracing::warn!("Ghost session start not recorded: {e}");
     }
     id
 }
@@ -288,13 +280,16 @@ pub fn record_ghost_session_start(system_load_start: u8) -> Uuid {
 /// Calculates drift based on previously recorded start load and the current end load.
 ///
 /// If the session id is unknown, assumes `start == end`.
-pub fn calculate_drift(session_id: Uuid, system_load_end: u8) -> GhostDrift {
+pub fn calculate_drift(storage: &Storage, session_id: Uuid, system_load_end: u8) -> GhostDrift {
     let end = system_load_end.min(100);
-    let start = if let Ok(mut m) = ghost_session_map().lock() {
-        m.remove(&session_id).unwrap_or(end)
-    } else {
-        end
-    };
+    let now = chrono::Utc::now().timestamp();
+    let start = storage
+        .end_ghost_session(&session_id.to_string(), now, end)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Ghost session end not recorded: {e}");
+            None
+        })
+        .unwrap_or(end);
 
     let delta: i16 = (end as i16) - (start as i16);
 
@@ -0,0 +1,454 @@
+//! API-key authentication for the `/api` routes, `/ws` and `/metrics`.
+//!
+//! Keys look like `phx_<id>_<secret>` and are shown once when created; the store keeps only the
+//! id, a name, the scopes and a SHA-256 of the whole key. Clients send the key as
+//! `Authorization: Bearer <key>` or `X-API-Key: <key>`.
+//!
+//! Scopes: `read` (GET routes and the analysis-only POSTs), `record` (the audio and recorder routes
+//! and presence reports, plus `read`) and `admin` (everything). Keys are managed with `pagi-twin keys create|list|revoke`;
+//! the running server picks up changes to the file without a restart. The file is sealed when
+//! the data directory is encrypted (see [`pagi_crypto`]); one written before that is sealed when
+//! the store is opened.
+//!
+//! Enforcement follows `auth.api_auth` (`PHOENIX_API_AUTH`): `on`, `off`, or `auto` (the
+//! default), which enforces only when the server binds a non-loopback address. A web UI session
+//! (see [`crate::sessions`]) is accepted in place of a key.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage, HttpRequest};
+use pagi_crypto::Sealer;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::{api_version, sessions, ApiError, AppState};
+
+const KEY_PREFIX: &str = "phx_";
+
+/// POST routes that only compute a result and so need just `read`.
+const READ_ONLY_POSTS: &[&str] = &["/api/ghost/simulate", "/api/resonance/analyze"];
+const RECORD_PREFIXES: &[&str] = &["/api/audio/", "/api/presence/", "/api/recorder/"];
+/// Routes that need `admin` whatever the method, reads included.
+const ADMIN_PREFIXES: &[&str] = &["/api/admin/"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Record,
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::Read, Scope::Record, Scope::Admin];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Record => "record",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// Whether holding `self` is enough for a route that requires `required`.
+    pub fn grants(self, required: Scope) -> bool {
+        match self {
+            Scope::Admin => true,
+            Scope::Record => matches!(required, Scope::Record | Scope::Read),
+            Scope::Read => required == Scope::Read,
+        }
+    }
+
+    /// The scope a request to `path` with `method` needs (the same for every API version).
+    pub fn required_for(method: &Method, path: &str) -> Self {
+        let path = &*crate::api_version::unversioned(path);
+        if ADMIN_PREFIXES.iter().any(|p| path.starts_with(p)) {
+            Scope::Admin
+        } else if RECORD_PREFIXES.iter().any(|p| path.starts_with(p)) {
+            Scope::Record
+        } else if matches!(*method, Method::GET | Method::HEAD) || READ_ONLY_POSTS.contains(&path) {
+            Scope::Read
+        } else {
+            Scope::Admin
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Hex SHA-256 of the full key.
+    pub hash: String,
+    pub created_unix: i64,
+}
+
+/// A freshly created key; `key` is the only copy of the secret.
+#[derive(Debug, Clone)]
+pub struct NewApiKey {
+    pub record: ApiKeyRecord,
+    pub key: String,
+}
+
+#[derive(Default)]
+struct Loaded {
+    keys: Vec<ApiKeyRecord>,
+    modified: Option<SystemTime>,
+}
+
+/// The key file, reloaded whenever it changes on disk.
+pub struct ApiKeyStore {
+    path: PathBuf,
+    sealer: Sealer,
+    loaded: RwLock<Loaded>,
+}
+
+pub(crate) fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut out, b| {
+            let _ = write!(out, "{b:02x}");
+            out
+        })
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ApiKeyStore {
+    /// Open the store at `path` (sealed with `sealer`); a missing file is an empty store.
+    pub fn open(path: impl Into<PathBuf>, sealer: Sealer) -> io::Result<Self> {
+        let store = Self {
+            path: path.into(),
+            sealer,
+            loaded: RwLock::new(Loaded::default()),
+        };
+        store.reload()?;
+        if std::fs::read(&store.path).is_ok_and(|raw| store.sealer.needs_sealing(&raw)) {
+            store.save(&store.list())?;
+        }
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn reload(&self) -> io::Result<()> {
+        let modified = modified(&self.path);
+        let keys = match std::fs::read(&self.path) {
+            Ok(bytes) => {
+                let bytes = self.sealer.open(&bytes).map_err(io::Error::other)?;
+                serde_json::from_slice(&bytes).map_err(io::Error::other)?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        if let Ok(mut loaded) = self.loaded.write() {
+            *loaded = Loaded { keys, modified };
+        }
+        Ok(())
+    }
+
+    fn refresh_if_changed(&self) {
+        let stale = self
+            .loaded
+            .read()
+            .map(|l| l.modified != modified(&self.path))
+            .unwrap_or(false);
+        if stale {
+            if let Err(e) = self.reload() {
+                tracing::warn!(
+                    "Failed to reload API keys from {}: {e}",
+                    self.path.display()
+                );
+            }
+        }
+    }
+
+    fn save(&self, keys: &[ApiKeyRecord]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(keys).map_err(io::Error::other)?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, self.sealer.seal(&json).map_err(io::Error::other)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.reload()
+    }
+
+    pub fn list(&self) -> Vec<ApiKeyRecord> {
+        self.refresh_if_changed();
+        self.loaded
+            .read()
+            .map(|l| l.keys.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list().is_empty()
+    }
+
+    pub fn create(&self, name: &str, scopes: &[Scope]) -> io::Result<NewApiKey> {
+        if scopes.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a key needs at least one scope",
+            ));
+        }
+        let mut scopes = scopes.to_vec();
+        scopes.sort();
+        scopes.dedup();
+
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        let secret = uuid::Uuid::new_v4().simple().to_string();
+        let key = format!("{KEY_PREFIX}{id}_{secret}");
+        let record = ApiKeyRecord {
+            id,
+            name: name.trim().to_string(),
+            scopes,
+            hash: hash_key(&key),
+            created_unix: chrono::Utc::now().timestamp(),
+        };
+
+        let mut keys = self.list();
+        keys.push(record.clone());
+        self.save(&keys)?;
+        Ok(NewApiKey { record, key })
+    }
+
+    /// Remove the key with `id`; `false` if there was none.
+    pub fn revoke(&self, id: &str) -> io::Result<bool> {
+        let mut keys = self.list();
+        let before = keys.len();
+        keys.retain(|k| k.id != id.trim());
+        if keys.len() == before {
+            return Ok(false);
+        }
+        self.save(&keys)?;
+        Ok(true)
+    }
+
+    /// The record for a presented key, if it is valid.
+    pub fn verify(&self, presented: &str) -> Option<ApiKeyRecord> {
+        let presented = presented.trim();
+        let id = presented.strip_prefix(KEY_PREFIX)?.split('_').next()?;
+        let hash = hash_key(presented);
+        self.list()
+            .into_iter()
+            .find(|k| k.id == id && constant_time_eq(k.hash.as_bytes(), hash.as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiAuthMode {
+    Auto,
+    On,
+    Off,
+}
+
+impl ApiAuthMode {
+    /// `auto`, `on` or `off` (also `true`/`1`, `false`/`0`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "on" | "true" | "1" => Some(Self::On),
+            "off" | "false" | "0" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    /// Whether keys are required for a server bound to `bind`.
+    pub fn enforced(self, bind: &str) -> bool {
+        match self {
+            Self::On => true,
+            Self::Off => false,
+            Self::Auto => {
+                let host = bind
+                    .rsplit_once(':')
+                    .map_or(bind, |(host, _)| host)
+                    .trim_matches(['[', ']']);
+                !(host == "localhost"
+                    || host
+                        .parse::<std::net::IpAddr>()
+                        .is_ok_and(|ip| ip.is_loopback()))
+            }
+        }
+    }
+}
+
+fn presented_key(req: &HttpRequest) -> Option<String> {
+    let headers = req.headers();
+    if let Some(v) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(v.to_string());
+    }
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+/// Check the credentials on `req`: a live UI session, or an API key with the scope its route
+/// needs. The login route is open, and everything passes when neither is enforced. The verified
+/// session or key record is stored in the request extensions (the rate limiter keys on it).
+pub(crate) fn authorize(
+    req: &HttpRequest,
+    key_store: Option<&ApiKeyStore>,
+    session_store: Option<&sessions::SessionStore>,
+) -> Result<(), ApiError> {
+    if api_version::unversioned(req.path()) == sessions::LOGIN_PATH {
+        return Ok(());
+    }
+    if let (Some(store), Some(token)) = (session_store, sessions::presented_token(req)) {
+        if let Some(session) = store.verify(&token) {
+            req.extensions_mut().insert(session);
+            return Ok(());
+        }
+    }
+    if let Some(store) = key_store {
+        let required = Scope::required_for(req.method(), req.path());
+        let Some(key) = presented_key(req) else {
+            return Err(ApiError::unauthorized("missing API key"));
+        };
+        let Some(record) = store.verify(&key) else {
+            return Err(ApiError::unauthorized("invalid or revoked API key"));
+        };
+        if !record.scopes.iter().any(|s| s.grants(required)) {
+            return Err(ApiError::forbidden(format!(
+                "this key lacks the `{}` scope",
+                required.as_str()
+            )));
+        }
+        req.extensions_mut().insert(record);
+        return Ok(());
+    }
+    if session_store.is_some() {
+        return Err(ApiError::unauthorized("login required"));
+    }
+    Ok(())
+}
+
+/// The scopes of the credentials [`authorize`] accepted for `req`: a key's own, or every scope
+/// for a UI session or when nothing is enforced.
+pub(crate) fn granted_scopes(req: &HttpRequest) -> Vec<Scope> {
+    match req.extensions().get::<ApiKeyRecord>() {
+        Some(record) => record.scopes.clone(),
+        None => Scope::ALL.to_vec(),
+    }
+}
+
+/// Middleware for the `/api` scopes; a no-op when neither keys nor sessions are enforced.
+pub async fn require_credentials(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if let Some(state) = req.app_data::<web::Data<AppState>>() {
+        authorize(
+            req.request(),
+            state.api_keys.as_deref(),
+            state.sessions.as_deref(),
+        )?;
+    }
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_verify_until_revoked() {
+        let path = std::env::temp_dir().join(format!("api_keys_{}.json", uuid::Uuid::new_v4()));
+        let store = ApiKeyStore::open(&path, Sealer::default()).unwrap();
+        let created = store.create("grafana", &[Scope::Read]).unwrap();
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains(&created.key));
+
+        let record = store.verify(&created.key).unwrap();
+        assert_eq!(record.scopes, [Scope::Read]);
+        assert!(store.verify(&format!("{}x", created.key)).is_none());
+
+        assert!(store.revoke(&created.record.id).unwrap());
+        assert!(store.verify(&created.key).is_none());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn a_plain_key_file_is_sealed_when_opened_with_a_key() {
+        let path = std::env::temp_dir().join(format!("api_keys_{}.json", uuid::Uuid::new_v4()));
+        let created = ApiKeyStore::open(&path, Sealer::default())
+            .unwrap()
+            .create("grafana", &[Scope::Read])
+            .unwrap();
+        assert!(!pagi_crypto::is_sealed(&std::fs::read(&path).unwrap()));
+
+        let sealer = Sealer::new(pagi_crypto::DataKey::generate().unwrap());
+        let store = ApiKeyStore::open(&path, sealer.clone()).unwrap();
+        assert!(pagi_crypto::is_sealed(&std::fs::read(&path).unwrap()));
+        assert!(store.verify(&created.key).is_some());
+        assert!(ApiKeyStore::open(&path, Sealer::default()).is_err());
+        assert!(ApiKeyStore::open(&path, sealer)
+            .unwrap()
+            .verify(&created.key)
+            .is_some());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn scopes_follow_routes() {
+        assert_eq!(
+            Scope::required_for(&Method::GET, "/api/status"),
+            Scope::Read
+        );
+        assert_eq!(
+            Scope::required_for(&Method::POST, "/api/ghost/simulate"),
+            Scope::Read
+        );
+        assert_eq!(
+            Scope::required_for(&Method::POST, "/api/audio/start-recording"),
+            Scope::Record
+        );
+        assert_eq!(
+            Scope::required_for(&Method::GET, "/api/recorder/recordings"),
+            Scope::Record
+        );
+        assert_eq!(
+            Scope::required_for(&Method::POST, "/api/v1/ghost/simulate"),
+            Scope::Read
+        );
+        assert_eq!(
+            Scope::required_for(&Method::POST, "/api/config"),
+            Scope::Admin
+        );
+        assert_eq!(
+            Scope::required_for(&Method::GET, "/api/v1/admin/config"),
+            Scope::Admin
+        );
+        assert!(Scope::Record.grants(Scope::Read));
+        assert!(!Scope::Record.grants(Scope::Admin));
+        assert!(ApiAuthMode::Auto.enforced("0.0.0.0:8888"));
+        assert!(!ApiAuthMode::Auto.enforced("127.0.0.1:8888"));
+        assert!(!ApiAuthMode::Auto.enforced("[::1]:8888"));
+    }
+}
@@ -0,0 +1,107 @@
+//! API versions and the deprecation policy.
+//!
+//! Routes live under `/api/v1`. The pre-versioning paths under `/api` still serve the same
+//! router, but every response there carries `Deprecation: true` and a `Link` to the `/api/v1`
+//! equivalent (plus `Sunset` once a removal date is set), so clients can migrate before the
+//! alias goes away. A breaking change to a response (e.g. `SimulateResponse`) ships as a new
+//! version with its own router; older versions keep theirs until their sunset.
+//!
+//! `GET /api/versions` lists what this server speaks.
+
+use std::borrow::Cow;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+use serde::Serialize;
+use serde_json::json;
+
+/// Newest version; the target of deprecation `Link`s.
+pub const CURRENT: &str = "v1";
+pub const CURRENT_PREFIX: &str = "/api/v1";
+/// Pre-versioning alias for [`CURRENT_PREFIX`].
+pub const LEGACY_PREFIX: &str = "/api";
+/// HTTP-date after which the unversioned alias may be removed; `None` until announced.
+const LEGACY_SUNSET: Option<&str> = None;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiVersion {
+    pub name: &'static str,
+    pub prefix: &'static str,
+    pub deprecated: bool,
+    pub sunset: Option<&'static str>,
+}
+
+pub const VERSIONS: &[ApiVersion] = &[
+    ApiVersion {
+        name: CURRENT,
+        prefix: CURRENT_PREFIX,
+        deprecated: false,
+        sunset: None,
+    },
+    ApiVersion {
+        name: "unversioned",
+        prefix: LEGACY_PREFIX,
+        deprecated: true,
+        sunset: LEGACY_SUNSET,
+    },
+];
+
+/// `path` with any version segment removed (`/api/v1/status` -> `/api/status`), for policy
+/// tables that apply to every version.
+pub fn unversioned(path: &str) -> Cow<'_, str> {
+    let Some(rest) = path.strip_prefix("/api/") else {
+        return Cow::Borrowed(path);
+    };
+    let (segment, tail) = rest.split_once('/').unwrap_or((rest, ""));
+    let is_version = segment
+        .strip_prefix('v')
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    if !is_version {
+        Cow::Borrowed(path)
+    } else if tail.is_empty() {
+        Cow::Borrowed(LEGACY_PREFIX)
+    } else {
+        Cow::Owned(format!("{LEGACY_PREFIX}/{tail}"))
+    }
+}
+
+/// The current-version equivalent of a legacy `/api/...` path.
+fn successor(path: &str) -> String {
+    let rest = path.strip_prefix(LEGACY_PREFIX).unwrap_or(path);
+    format!("{CURRENT_PREFIX}{rest}")
+}
+
+/// Middleware for the legacy `/api` scope: mark every response as deprecated.
+pub async fn deprecation_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let link = format!("<{}>; rel=\"successor-version\"", successor(req.path()));
+    let mut res = next.call(req).await?;
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+    if let Ok(link) = HeaderValue::from_str(&link) {
+        headers.insert(actix_web::http::header::LINK, link);
+    }
+    if let Some(sunset) = LEGACY_SUNSET {
+        headers.insert(
+            HeaderName::from_static("sunset"),
+            HeaderValue::from_static(sunset),
+        );
+    }
+    Ok(res)
+}
+
+/// `GET /api/versions`
+pub async fn list_versions() -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "current": CURRENT,
+        "versions": VERSIONS,
+    }))
+}
@@ -0,0 +1,237 @@
+//! Audit log of privacy-relevant actions.
+//!
+//! Recording and always-listening start/stop, recorder pauses and schedules, profile changes, data deletion, exports, backups
+//! and settings changes are appended to the database's `audit_log` table (see
+//! [`phoenix_storage::audit`]) with the caller, the active profile and the parameters. Values
+//! that could be secrets (API keys written through `POST /api/config`) are recorded by name only.
+//! Enrollment is logged by the recorder itself, since the desktop app drives it directly.
+//! Every entry also goes into the tamper-evident [`crate::journal`].
+//!
+//! `GET /api/admin/audit?action=&actor=&profile=&from_unix=&to_unix=&before_id=&limit=` (admin
+//! scope) lists entries newest first; `action=recording` matches every `recording.*` entry, and
+//! `next_before_id` pages further back.
+
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::Utc;
+use phoenix_storage::audit::{AuditEntry, AuditQuery};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::api_keys::ApiKeyRecord;
+use crate::sessions::Session;
+use crate::{ApiError, AppState};
+
+const MAX_LIMIT: usize = 1000;
+
+/// Who asked for an audited action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    /// An API key, by name.
+    ApiKey(String),
+    /// A web UI login session, by id.
+    Session(String),
+    /// A caller on an address where no credentials are required.
+    Local,
+    /// The desktop shell over the IPC bridge.
+    Ipc,
+    /// An automation script, by name (see [`crate::scripts_api`]).
+    Script(String),
+    /// A paired mobile companion, by device id (see [`crate::companion`]).
+    Companion(String),
+    /// A command over MQTT (see [`crate::home_assistant`]).
+    Mqtt,
+}
+
+impl Actor {
+    /// The credentials [`crate::api_keys::authorize`] verified for `req`.
+    pub fn of(req: &HttpRequest) -> Self {
+        let extensions = req.extensions();
+        if let Some(key) = extensions.get::<ApiKeyRecord>() {
+            Self::ApiKey(key.name.clone())
+        } else if let Some(session) = extensions.get::<Session>() {
+            Self::Session(session.id.clone())
+        } else {
+            Self::Local
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::ApiKey(name) => format!("key:{name}"),
+            Self::Session(id) => format!("session:{id}"),
+            Self::Local => "local".to_string(),
+            Self::Ipc => "ipc".to_string(),
+            Self::Script(name) => format!("script:{name}"),
+            Self::Companion(id) => format!("companion:{id}"),
+            Self::Mqtt => "mqtt".to_string(),
+        }
+    }
+}
+
+/// The audited actions. Stored by their dotted name, which the log can be filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    RecordingStart,
+    RecordingStop,
+    RecordingPause,
+    RecordingResume,
+    RecordingSchedule,
+    RecordingUnschedule,
+    ListeningStart,
+    ListeningStop,
+    ProfileCreate,
+    ProfileRename,
+    ProfileSwitch,
+    ProfileSettings,
+    ProfileDelete,
+    MemoryDelete,
+    SessionRevoke,
+    EmotionExport,
+    CounselorExport,
+    DataExport,
+    DataImport,
+    BackupCreate,
+    BackupRestore,
+    ConfigSet,
+    PrivacyConfigSet,
+    TogglesSet,
+    CompanionPair,
+    CompanionRevoke,
+    GuestModeSet,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RecordingStart => "recording.start",
+            Self::RecordingStop => "recording.stop",
+            Self::RecordingPause => "recording.pause",
+            Self::RecordingResume => "recording.resume",
+            Self::RecordingSchedule => "recording.schedule",
+            Self::RecordingUnschedule => "recording.unschedule",
+            Self::ListeningStart => "listening.start",
+            Self::ListeningStop => "listening.stop",
+            Self::ProfileCreate => "profile.create",
+            Self::ProfileRename => "profile.rename",
+            Self::ProfileSwitch => "profile.switch",
+            Self::ProfileSettings => "profile.settings",
+            Self::ProfileDelete => "profile.delete",
+            Self::MemoryDelete => "memory.delete",
+            Self::SessionRevoke => "session.revoke",
+            Self::EmotionExport => "export.emotion",
+            Self::CounselorExport => "export.counselor",
+            Self::DataExport => "export.all",
+            Self::DataImport => "import.all",
+            Self::BackupCreate => "backup.create",
+            Self::BackupRestore => "backup.restore",
+            Self::ConfigSet => "settings.config",
+            Self::PrivacyConfigSet => "settings.privacy",
+            Self::TogglesSet => "settings.toggles",
+            Self::CompanionPair => "companion.pair",
+            Self::CompanionRevoke => "companion.revoke",
+            Self::GuestModeSet => "settings.guest_mode",
+        }
+    }
+}
+
+/// Append an entry. Failures are logged rather than failing the action, which has already
+/// happened by the time it is recorded.
+pub(crate) fn record(state: &AppState, actor: &Actor, action: Action, params: Value) {
+    state
+        .journal
+        .record(action.as_str(), &actor.label(), params.clone());
+    let entry = AuditEntry {
+        ts_unix: Utc::now().timestamp(),
+        actor: actor.label(),
+        profile: state.storage.active_profile().ok(),
+        action: action.as_str().to_string(),
+        params: params.to_string(),
+    };
+    if let Err(e) = state.storage.append_audit(&entry) {
+        warn!(target: "audit", action = action.as_str(), "audit entry not written: {e}");
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LogQuery {
+    #[serde(default)]
+    action: Option<String>,
+    #[serde(default)]
+    actor: Option<String>,
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    from_unix: Option<i64>,
+    #[serde(default)]
+    to_unix: Option<i64>,
+    #[serde(default)]
+    before_id: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn get_audit(
+    state: web::Data<AppState>,
+    query: web::Query<LogQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let q = query.into_inner();
+    let limit = q.limit.unwrap_or(phoenix_storage::audit::DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::bad_request(format!(
+            "limit must be between 1 and {MAX_LIMIT}"
+        )));
+    }
+    let rows = state.storage.audit_log(&AuditQuery {
+        action: q.action,
+        actor: q.actor,
+        profile: q.profile,
+        from_unix: q.from_unix,
+        to_unix: q.to_unix,
+        before_id: q.before_id,
+        limit: Some(limit),
+    })?;
+    // A full page may have more behind it.
+    let next_before_id = rows.last().filter(|_| rows.len() == limit).map(|r| r.id);
+    let entries: Vec<_> = rows
+        .into_iter()
+        .map(|r| {
+            json!({
+                "id": r.id,
+                "ts_unix": r.entry.ts_unix,
+                "actor": r.entry.actor,
+                "profile": r.entry.profile,
+                "action": r.entry.action,
+                "params": serde_json::from_str::<Value>(&r.entry.params)
+                    .unwrap_or(Value::String(r.entry.params)),
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({ "entries": entries, "next_before_id": next_before_id })))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope, next to the other `/admin` resources.
+    cfg.route("/admin/audit", web::get().to(get_audit));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn actors_come_from_the_verified_credentials() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(Actor::of(&req), Actor::Local);
+        req.extensions_mut().insert(ApiKeyRecord {
+            id: "k1".to_string(),
+            name: "laptop".to_string(),
+            scopes: Vec::new(),
+            hash: String::new(),
+            created_unix: 0,
+        });
+        assert_eq!(Actor::of(&req).label(), "key:laptop");
+        assert_eq!(Action::ProfileDelete.as_str(), "profile.delete");
+    }
+}
@@ -0,0 +1,825 @@
+//! Backups of everything the server keeps except raw media: a consistent copy of the database,
+//! the Mind/Body/Soul vaults, and the config, API key and scheduler files, packed as a `.tar.gz`
+//! with a `manifest.json` of checksums.
+//!
+//! `POST /api/admin/backup` returns the archive. `POST /api/admin/restore` takes one as the
+//! request body, checks it (manifest, checksums, database integrity and schema version) and
+//! stages it under `data/restore/`; the staged copy replaces the live data on the next start,
+//! and the replaced files are kept as `<file>.pre-restore-<unix time>`. `?dry_run=true` only
+//! checks. Config changes from a restored `phoenix.toml` apply from that start too.
+//!
+//! With an `X-Backup-Passphrase` header the archive is encrypted: `MAGIC | salt | nonce |
+//! AES-256-GCM(tar.gz)` with the key derived from the passphrase by PBKDF2-HMAC-SHA256.
+//!
+//! When the data directory is encrypted (see [`pagi_crypto`]) the archive holds the data as
+//! stored, still encrypted with the data key, so it only restores where that key unlocks.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::num::NonZeroU32;
+use std::path::{Component, Path, PathBuf};
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use base64::Engine;
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use pagi_crypto::Sealer;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use vital_organ_vaults::{Organ, RawEntries, VitalOrganVaults};
+
+use crate::audit::{self, Action, Actor};
+use crate::{ApiError, AppState};
+
+/// Archive layout version written into the manifest.
+pub const FORMAT: u32 = 1;
+
+/// Largest backup accepted for restore, and the most one may unpack to.
+pub const MAX_BACKUP_BYTES: usize = 1 << 30;
+
+const PASSPHRASE_HEADER: &str = "X-Backup-Passphrase";
+pub(crate) const MIN_PASSPHRASE_CHARS: usize = 8;
+const MAGIC: &[u8; 8] = b"PHXBAK1E";
+const SALT_LEN: usize = 16;
+const PBKDF2_ROUNDS: u32 = 200_000;
+
+const MANIFEST: &str = "manifest.json";
+const DATABASE: &str = "phoenix.db";
+const CONFIG_FILE: &str = "config/phoenix.toml";
+const API_KEYS: &str = "config/api_keys.json";
+const SCHEDULER: &str = "config/scheduler.json";
+
+fn vault_member(organ: Organ) -> String {
+    format!("vaults/{}.json", organ.as_str())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("the backup is encrypted; send its passphrase in the {PASSPHRASE_HEADER} header")]
+    PassphraseRequired,
+    #[error("cannot decrypt the backup: wrong passphrase or damaged file")]
+    Decrypt,
+    #[error("the passphrase must be at least {MIN_PASSPHRASE_CHARS} characters")]
+    WeakPassphrase,
+    #[error("not a usable backup: {0}")]
+    Invalid(String),
+    #[error("backup I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Storage(#[from] phoenix_storage::StorageError),
+    #[error("vault error: {0}")]
+    Vault(String),
+}
+
+impl From<BackupError> for ApiError {
+    fn from(e: BackupError) -> Self {
+        match e {
+            BackupError::PassphraseRequired
+            | BackupError::Decrypt
+            | BackupError::WeakPassphrase
+            | BackupError::Invalid(_) => ApiError::bad_request(e.to_string()),
+            BackupError::Io(_) | BackupError::Storage(_) | BackupError::Vault(_) => {
+                ApiError::internal(e.to_string())
+            }
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> BackupError {
+    BackupError::Invalid(message.into())
+}
+
+/// Where the backed-up files live and where restores are staged.
+#[derive(Debug, Clone)]
+pub struct BackupPaths {
+    pub database: PathBuf,
+    /// The config file in use; a restored one is only written back when there is one.
+    pub config_file: Option<PathBuf>,
+    pub api_keys: PathBuf,
+    pub scheduler: PathBuf,
+    pub staging: PathBuf,
+    /// The data key the database and scheduler file are encrypted with, if any.
+    pub sealer: Sealer,
+}
+
+impl BackupPaths {
+    /// The plain files in the archive and where each goes; the database is always first.
+    fn files(&self) -> [(&'static str, Option<&Path>); 4] {
+        [
+            (DATABASE, Some(&self.database)),
+            (CONFIG_FILE, self.config_file.as_deref()),
+            (API_KEYS, Some(&self.api_keys)),
+            (SCHEDULER, Some(&self.scheduler)),
+        ]
+    }
+
+    fn database_key(&self) -> Option<&phoenix_storage::encryption::DatabaseKey> {
+        self.sealer.key().map(pagi_crypto::DataKey::as_bytes)
+    }
+
+    /// A fresh path next to the staging directory for temporary database copies.
+    fn scratch_db(&self) -> std::io::Result<PathBuf> {
+        let dir = self.staging.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        Ok(dir.join(format!(".backup-{}.db", uuid::Uuid::new_v4())))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub created_unix: i64,
+    pub app_version: String,
+    pub schema_version: u32,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn encode_vault(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let pairs: Vec<[String; 2]> = entries
+        .iter()
+        .map(|(k, v)| [b64.encode(k), b64.encode(v)])
+        .collect();
+    serde_json::to_vec(&pairs).expect("string pairs serialize")
+}
+
+fn decode_vault(raw: &[u8]) -> Result<RawEntries, BackupError> {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let pairs: Vec<[String; 2]> =
+        serde_json::from_slice(raw).map_err(|e| invalid(e.to_string()))?;
+    pairs
+        .iter()
+        .map(|[k, v]| Ok((b64.decode(k)?, b64.decode(v)?)))
+        .collect::<Result<_, base64::DecodeError>>()
+        .map_err(|e| invalid(e.to_string()))
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), BackupError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(BackupError::WeakPassphrase);
+    }
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut key = [0u8; 32];
+    let rounds = NonZeroU32::new(PBKDF2_ROUNDS).expect("non-zero rounds");
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        rounds,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key).expect("32-byte AES key"))
+}
+
+fn seal(mut archive: Vec<u8>, passphrase: &str) -> Result<Vec<u8>, BackupError> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|()| rng.fill(&mut nonce))
+        .map_err(|_| std::io::Error::other("no system randomness"))?;
+    derive_key(passphrase, &salt)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut archive,
+        )
+        .map_err(|_| std::io::Error::other("encryption failed"))?;
+    Ok([&MAGIC[..], &salt, &nonce, &archive].concat())
+}
+
+fn unseal(data: &[u8], passphrase: &str) -> Result<Vec<u8>, BackupError> {
+    let header = MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if data.len() < header {
+        return Err(BackupError::Decrypt);
+    }
+    let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| BackupError::Decrypt)?;
+    let mut buf = sealed.to_vec();
+    let plain_len = derive_key(passphrase, salt)
+        .open_in_place(nonce, Aad::from(MAGIC), &mut buf)
+        .map_err(|_| BackupError::Decrypt)?
+        .len();
+    buf.truncate(plain_len);
+    Ok(buf)
+}
+
+fn pack(manifest: &Manifest, files: &[(String, Vec<u8>)]) -> Result<Vec<u8>, BackupError> {
+    let manifest_json = serde_json::to_vec_pretty(manifest).map_err(std::io::Error::other)?;
+    let members = std::iter::once((MANIFEST, manifest_json.as_slice())).chain(
+        files
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.as_slice())),
+    );
+    let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for (name, data) in members {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(manifest.created_unix.max(0) as u64);
+        tar.append_data(&mut header, name, data)?;
+    }
+    Ok(tar.into_inner()?.finish()?)
+}
+
+fn unpack(archive: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, BackupError> {
+    if !archive.starts_with(&[0x1f, 0x8b]) {
+        return Err(invalid("it is neither a .tar.gz nor an encrypted backup"));
+    }
+    let mut tar = tar::Archive::new(GzDecoder::new(archive));
+    let mut members = BTreeMap::new();
+    let mut total = 0u64;
+    for entry in tar.entries().map_err(|e| invalid(e.to_string()))? {
+        let mut entry = entry.map_err(|e| invalid(e.to_string()))?;
+        if !entry.header().entry_type().is_file() {
+            return Err(invalid("the archive may only contain plain files"));
+        }
+        let path = entry.path().map_err(|e| invalid(e.to_string()))?;
+        // Members are written below the staging directory on restore, so only plain relative
+        // names may come through.
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(invalid(format!(
+                "{} is not a relative path inside the backup",
+                path.display()
+            )));
+        }
+        let name = path.to_string_lossy().into_owned();
+        total += entry.size();
+        if total > MAX_BACKUP_BYTES as u64 {
+            return Err(invalid(format!(
+                "it unpacks to more than {MAX_BACKUP_BYTES} bytes"
+            )));
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| invalid(format!("{name}: {e}")))?;
+        if members.insert(name.clone(), data).is_some() {
+            return Err(invalid(format!("{name} appears twice")));
+        }
+    }
+    Ok(members)
+}
+
+/// Build a backup archive, encrypted when `passphrase` is given.
+pub fn create(
+    paths: &BackupPaths,
+    storage: &phoenix_storage::Storage,
+    vaults: &VitalOrganVaults,
+    passphrase: Option<&str>,
+) -> Result<Vec<u8>, BackupError> {
+    if let Some(passphrase) = passphrase {
+        check_passphrase(passphrase)?;
+    }
+
+    let snapshot = paths.scratch_db()?;
+    let database = storage
+        .snapshot_to(&snapshot)
+        .map_err(BackupError::from)
+        .and_then(|()| {
+            let version = phoenix_storage::migrations::check_file(&snapshot, paths.database_key())?;
+            Ok((version, std::fs::read(&snapshot)?))
+        });
+    let _ = std::fs::remove_file(&snapshot);
+    let (schema_version, database) = database?;
+
+    let mut files = vec![(DATABASE.to_string(), database)];
+    for organ in Organ::ALL {
+        let entries = vaults
+            .export_raw(organ)
+            .map_err(|e| BackupError::Vault(e.to_string()))?;
+        files.push((vault_member(organ), encode_vault(&entries)));
+    }
+    for (name, path) in paths.files().into_iter().skip(1) {
+        let Some(path) = path else { continue };
+        match std::fs::read(path) {
+            Ok(data) => files.push((name.to_string(), data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let manifest = Manifest {
+        format: FORMAT,
+        created_unix: Utc::now().timestamp(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        files: files
+            .iter()
+            .map(|(name, data)| ManifestFile {
+                name: name.clone(),
+                bytes: data.len() as u64,
+                sha256: sha256_hex(data),
+            })
+            .collect(),
+    };
+    let archive = pack(&manifest, &files)?;
+    tracing::info!(target: "backup", bytes = archive.len(), encrypted = passphrase.is_some(), "backup created");
+    match passphrase {
+        Some(passphrase) => seal(archive, passphrase),
+        None => Ok(archive),
+    }
+}
+
+/// Decrypt and unpack `data`, and check everything in it.
+fn check(
+    paths: &BackupPaths,
+    data: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(Manifest, BTreeMap<String, Vec<u8>>), BackupError> {
+    let decrypted;
+    let archive = if data.starts_with(MAGIC) {
+        decrypted = unseal(data, passphrase.ok_or(BackupError::PassphraseRequired)?)?;
+        &decrypted[..]
+    } else {
+        data
+    };
+    let mut members = unpack(archive)?;
+    let manifest: Manifest = members
+        .remove(MANIFEST)
+        .ok_or_else(|| invalid(format!("{MANIFEST} is missing")))
+        .and_then(|raw| {
+            serde_json::from_slice(&raw).map_err(|e| invalid(format!("{MANIFEST}: {e}")))
+        })?;
+    if manifest.format > FORMAT {
+        return Err(invalid(format!(
+            "it was made by a newer version (format {})",
+            manifest.format
+        )));
+    }
+
+    let known: Vec<String> = paths
+        .files()
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .chain(Organ::ALL.into_iter().map(vault_member))
+        .collect();
+    let mut listed = BTreeMap::new();
+    for file in &manifest.files {
+        if listed.insert(file.name.as_str(), file).is_some() {
+            return Err(invalid(format!("the manifest lists {} twice", file.name)));
+        }
+    }
+    // Every member is restored, so every member has to be known, listed and intact.
+    for (name, data) in &members {
+        if !known.contains(name) {
+            return Err(invalid(format!("unexpected file {name}")));
+        }
+        let file = listed
+            .remove(name.as_str())
+            .ok_or_else(|| invalid(format!("{name} is not in the manifest")))?;
+        if data.len() as u64 != file.bytes || sha256_hex(data) != file.sha256 {
+            return Err(invalid(format!("{name} is damaged (checksum mismatch)")));
+        }
+    }
+    if let Some(name) = listed.keys().next() {
+        return Err(invalid(format!("{name} is missing")));
+    }
+    for organ in Organ::ALL {
+        if let Some(raw) = members.get(&vault_member(organ)) {
+            decode_vault(raw)?;
+        }
+    }
+    for name in [API_KEYS, SCHEDULER] {
+        if let Some(raw) = members.get(name) {
+            let raw = paths
+                .sealer
+                .open(raw)
+                .map_err(|e| invalid(format!("{name}: {e}")))?;
+            serde_json::from_slice::<serde_json::Value>(&raw)
+                .map_err(|e| invalid(format!("{name}: {e}")))?;
+        }
+    }
+
+    let database = members
+        .get(DATABASE)
+        .ok_or_else(|| invalid(format!("{DATABASE} is missing")))?;
+    let scratch = paths.scratch_db()?;
+    let checked = std::fs::write(&scratch, database)
+        .map_err(BackupError::from)
+        .and_then(|()| {
+            phoenix_storage::migrations::check_file(&scratch, paths.database_key())
+                .map_err(|e| invalid(format!("{DATABASE}: {e}")))
+        });
+    let _ = std::fs::remove_file(&scratch);
+    checked?;
+
+    Ok((manifest, members))
+}
+
+/// Check a backup and, unless `dry_run`, stage it to replace the live data on the next start.
+pub fn restore(
+    paths: &BackupPaths,
+    data: &[u8],
+    passphrase: Option<&str>,
+    dry_run: bool,
+) -> Result<Manifest, BackupError> {
+    let (manifest, members) = check(paths, data, passphrase)?;
+    if dry_run {
+        return Ok(manifest);
+    }
+
+    // Write everything to a side directory first so a half-written restore is never staged.
+    let partial = paths.staging.with_extension("partial");
+    let _ = std::fs::remove_dir_all(&partial);
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(std::io::Error::other)?;
+    for (name, data) in members
+        .iter()
+        .chain([(&MANIFEST.to_string(), &manifest_json)])
+    {
+        let path = partial.join(name);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, data)?;
+    }
+    let _ = std::fs::remove_dir_all(&paths.staging);
+    std::fs::rename(&partial, &paths.staging)?;
+    tracing::info!(target: "backup", staging = %paths.staging.display(), created_unix = manifest.created_unix, "restore staged for the next start");
+    Ok(manifest)
+}
+
+/// Where a restore stages the database for a Postgres server, relative to the data directory.
+pub const POSTGRES_RESTORE: &str = "data/postgres-restore.db";
+
+/// Load a restored database into a Postgres `storage`, which a restore can't replace like a
+/// file, and remove the snapshot. Call right after opening the database. Returns whether there
+/// was one.
+pub fn load_restored(
+    paths: &BackupPaths,
+    storage: &phoenix_storage::Storage,
+) -> Result<bool, BackupError> {
+    if storage.path().is_some() || !paths.database.exists() {
+        return Ok(false);
+    }
+    storage.load_snapshot(&paths.database, paths.database_key())?;
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = paths.database.as_os_str().to_owned();
+        path.push(suffix);
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            other => other?,
+        }
+    }
+    Ok(true)
+}
+
+/// Rename `path` to `<path>.<suffix>` if it exists.
+fn set_aside(path: &Path, suffix: &str) -> std::io::Result<()> {
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".{suffix}"));
+    match std::fs::rename(path, aside) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+/// Put a staged restore in place of the live data. Call before the database is opened and
+/// before the API key and scheduler files are read. Returns the restored backup's manifest.
+pub fn apply_staged(
+    paths: &BackupPaths,
+    vaults: &VitalOrganVaults,
+) -> Result<Option<Manifest>, BackupError> {
+    let staging = &paths.staging;
+    let manifest: Manifest = match std::fs::read(staging.join(MANIFEST)) {
+        Ok(raw) => serde_json::from_slice(&raw).map_err(|e| invalid(e.to_string()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let suffix = format!("pre-restore-{}", Utc::now().timestamp());
+    for (name, target) in paths.files() {
+        let staged = staging.join(name);
+        if !staged.exists() {
+            continue;
+        }
+        let Some(target) = target else {
+            tracing::warn!(target: "backup", "No config file in use; the restored {name} was not applied");
+            continue;
+        };
+        if name == DATABASE {
+            for sidecar in ["-wal", "-shm"] {
+                let mut path = target.as_os_str().to_owned();
+                path.push(sidecar);
+                set_aside(Path::new(&path), &suffix)?;
+            }
+        }
+        set_aside(target, &suffix)?;
+        if let Some(dir) = target.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::copy(&staged, target)?;
+    }
+    for organ in Organ::ALL {
+        if let Ok(raw) = std::fs::read(staging.join(vault_member(organ))) {
+            vaults
+                .replace_raw(organ, &decode_vault(&raw)?)
+                .map_err(|e| BackupError::Vault(e.to_string()))?;
+        }
+    }
+    std::fs::remove_dir_all(staging)?;
+    Ok(Some(manifest))
+}
+
+fn passphrase(req: &HttpRequest) -> Result<Option<String>, ApiError> {
+    req.headers()
+        .get(PASSPHRASE_HEADER)
+        .map(|value| {
+            value.to_str().map(str::to_owned).map_err(|_| {
+                ApiError::bad_request(format!("{PASSPHRASE_HEADER} must be plain text"))
+            })
+        })
+        .transpose()
+}
+
+async fn post_backup(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let passphrase = passphrase(&req)?;
+    let encrypted = passphrase.is_some();
+    let paths = state.backup_paths.clone();
+    let storage = state.storage.clone();
+    let vaults = state.vaults.clone();
+    let archive = web::block(move || create(&paths, &storage, &vaults, passphrase.as_deref()))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))??;
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::BackupCreate,
+        json!({ "encrypted": encrypted, "bytes": archive.len() }),
+    );
+
+    let (file_name, content_type) = if encrypted {
+        ("tar.gz.enc", "application/octet-stream")
+    } else {
+        ("tar.gz", "application/gzip")
+    };
+    let file_name = format!(
+        "phoenix-backup-{}.{file_name}",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{file_name}\""),
+        ))
+        .body(archive))
+}
+
+#[derive(Debug, Deserialize)]
+struct RestoreQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn post_restore(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<RestoreQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    if body.is_empty() {
+        return Err(ApiError::bad_request(
+            "send the backup file as the request body",
+        ));
+    }
+    let passphrase = passphrase(&req)?;
+    let dry_run = query.dry_run;
+    let paths = state.backup_paths.clone();
+    let manifest = web::block(move || restore(&paths, &body, passphrase.as_deref(), dry_run))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))??;
+    // Logged before the swap, so this entry is in the log being replaced; the restored
+    // database brings its own log back.
+    if !dry_run {
+        audit::record(
+            &state,
+            &Actor::of(&req),
+            Action::BackupRestore,
+            json!({ "manifest": manifest }),
+        );
+    }
+
+    Ok(if dry_run {
+        HttpResponse::Ok().json(json!({ "status": "valid", "manifest": manifest }))
+    } else {
+        HttpResponse::Accepted().json(json!({
+            "status": "staged",
+            "restart_required": true,
+            "manifest": manifest,
+        }))
+    })
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope; both routes need the `admin` scope. Plain
+    // resources rather than an `/admin` scope, which `admin_api` shares.
+    cfg.route("/admin/backup", web::post().to(post_backup))
+        .service(
+            web::resource("/admin/restore")
+                .app_data(web::PayloadConfig::new(MAX_BACKUP_BYTES))
+                .route(web::post().to(post_restore)),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        dir: PathBuf,
+        paths: BackupPaths,
+        vaults: VitalOrganVaults,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self::sealed(Sealer::default())
+        }
+
+        fn sealed(sealer: Sealer) -> Self {
+            let dir = std::env::temp_dir().join(format!("phoenix-backup-{}", uuid::Uuid::new_v4()));
+            let data = dir.join("data");
+            let paths = BackupPaths {
+                database: data.join("phoenix.db"),
+                config_file: Some(dir.join("phoenix.toml")),
+                api_keys: data.join("api_keys.json"),
+                scheduler: data.join("scheduler.json"),
+                staging: data.join("restore"),
+                sealer: sealer.clone(),
+            };
+            std::fs::create_dir_all(&data).unwrap();
+            let vaults = VitalOrganVaults::awaken_sealed(&dir, sealer.clone());
+            std::fs::write(dir.join("phoenix.toml"), "[server]\n").unwrap();
+            std::fs::write(&paths.scheduler, sealer.seal(b"[]").unwrap()).unwrap();
+            Self { dir, paths, vaults }
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn backup_checks_stages_and_restores() {
+        let fx = Fixture::new();
+        let storage = phoenix_storage::Storage::open_sqlite(&fx.paths.database, 1).unwrap();
+        storage
+            .start_ghost_session("before", "default", 1, 10)
+            .unwrap();
+        fx.vaults.store_mind("note", "kept").unwrap();
+        let archive = create(&fx.paths, &storage, &fx.vaults, Some("correct horse")).unwrap();
+
+        assert!(matches!(
+            restore(&fx.paths, &archive, None, true),
+            Err(BackupError::PassphraseRequired)
+        ));
+        assert!(matches!(
+            restore(&fx.paths, &archive, Some("wrong horse"), true),
+            Err(BackupError::Decrypt)
+        ));
+        let manifest = restore(&fx.paths, &archive, Some("correct horse"), true).unwrap();
+        assert!(manifest.files.iter().any(|f| f.name == CONFIG_FILE));
+        assert!(!manifest.files.iter().any(|f| f.name == API_KEYS));
+        assert!(!fx.paths.staging.exists());
+
+        // A flipped byte in a member fails the checksum.
+        let plain = unseal(&archive, "correct horse").unwrap();
+        let mut members = unpack(&plain).unwrap();
+        members.get_mut(SCHEDULER).unwrap()[0] = b'{';
+        let manifest_raw = members.remove(MANIFEST).unwrap();
+        let files: Vec<_> = members.into_iter().collect();
+        let tampered = pack(&serde_json::from_slice(&manifest_raw).unwrap(), &files).unwrap();
+        assert!(matches!(
+            restore(&fx.paths, &tampered, None, true),
+            Err(BackupError::Invalid(_))
+        ));
+
+        restore(&fx.paths, &archive, Some("correct horse"), false).unwrap();
+        storage
+            .start_ghost_session("after", "default", 2, 20)
+            .unwrap();
+        fx.vaults.store_mind("note", "changed").unwrap();
+        // The server applies a staged restore before opening the database.
+        drop(storage);
+
+        let applied = apply_staged(&fx.paths, &fx.vaults).unwrap().unwrap();
+        assert_eq!(applied.created_unix, manifest.created_unix);
+        assert!(!fx.paths.staging.exists());
+        assert_eq!(fx.vaults.recall_mind("note").as_deref(), Some("kept"));
+        let restored = phoenix_storage::Storage::open_sqlite(&fx.paths.database, 1).unwrap();
+        let ids: Vec<_> = restored
+            .ghost_sessions_since(0, None)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["before"]);
+        assert!(apply_staged(&fx.paths, &fx.vaults).unwrap().is_none());
+    }
+
+    /// Pack without the `tar` builder's own path checks, as a crafted backup would be.
+    fn pack_raw(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), flate2::Compression::default()));
+        for (name, data) in members {
+            let mut header = tar::Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o600);
+            header.set_cksum();
+            tar.append(&header, *data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn restore_refuses_duplicate_listings_and_escaping_names() {
+        let fx = Fixture::new();
+        let storage = phoenix_storage::Storage::open_sqlite(&fx.paths.database, 1).unwrap();
+        let archive = create(&fx.paths, &storage, &fx.vaults, None).unwrap();
+        let mut members = unpack(&archive).unwrap();
+        let mut manifest: Manifest =
+            serde_json::from_slice(&members.remove(MANIFEST).unwrap()).unwrap();
+
+        // Listing one file twice in place of another keeps the counts equal while the other
+        // member goes unchecked.
+        let unlisted = manifest.files.pop().unwrap();
+        manifest.files.push(manifest.files[0].clone());
+        members.insert(unlisted.name, b"tampered".to_vec());
+        let files: Vec<_> = members.into_iter().collect();
+        let duplicated = pack(&manifest, &files).unwrap();
+        let err = restore(&fx.paths, &duplicated, None, false).unwrap_err();
+        assert!(err.to_string().contains("twice"), "{err}");
+
+        let manifest_json = serde_json::to_vec(&manifest).unwrap();
+        for escaping in ["../../escaped", "/tmp/escaped", "vaults/../../escaped"] {
+            let crafted = pack_raw(&[(MANIFEST, &manifest_json), (escaping, b"pwned")]);
+            let err = restore(&fx.paths, &crafted, None, false).unwrap_err();
+            assert!(err.to_string().contains("not a relative path"), "{err}");
+        }
+        assert!(!fx.paths.staging.exists());
+        assert!(!fx.paths.staging.with_extension("partial").exists());
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn encrypted_data_only_restores_under_its_key() {
+        let key = || pagi_crypto::DataKey::from_bytes([9; pagi_crypto::KEY_LEN]);
+        let fx = Fixture::sealed(Sealer::new(key()));
+        let storage = phoenix_storage::Storage::open_sqlite_with_key(
+            &fx.paths.database,
+            1,
+            fx.paths.database_key(),
+        )
+        .unwrap();
+        fx.vaults.store_soul("journal", "private").unwrap();
+        let archive = create(&fx.paths, &storage, &fx.vaults, None).unwrap();
+        let members = unpack(&archive).unwrap();
+        assert!(pagi_crypto::is_sealed(&members[SCHEDULER]));
+        assert!(restore(&fx.paths, &archive, None, true).is_ok());
+
+        let other = Fixture::sealed(Sealer::new(pagi_crypto::DataKey::generate().unwrap()));
+        assert!(matches!(
+            restore(&other.paths, &archive, None, true),
+            Err(BackupError::Invalid(_))
+        ));
+
+        // The vaults come back sealed and read under the same key.
+        let same = Fixture::sealed(Sealer::new(key()));
+        restore(&same.paths, &archive, None, false).unwrap();
+        apply_staged(&same.paths, &same.vaults).unwrap();
+        assert_eq!(
+            same.vaults.recall_soul("journal").as_deref(),
+            Some("private")
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! What this build and configuration can do, so the UI can hide what won't work.
+//!
+//! `GET /api/capabilities`:
+//! - `recorder`: the recorder subsystems compiled in (`audio`, `video`, `face`, `emotion`,
+//!   `stt`, `model_download`; see [`multi_modal_recording::Capabilities`])
+//! - `emotion_backend`: the emotion inference backend in use, `heuristic` when no model-based
+//!   one is compiled in or selected
+//! - `llm`: `compiled` (the `llm` feature), `local` (the `local-llm` feature), `configured` (a
+//!   provider answered at startup or the last config change), `enabled` (configured and not
+//!   switched off under `/api/admin/toggles`), and the `provider` and `model` completions go to
+//!   (see [`pagi_llm`]; `null` when a `local` model isn't installed yet) with the
+//!   `budget_remaining` tokens this hour when `[llm] tokens_per_hour` is set
+//! - `plugins`: `compiled` (the `plugins` feature) and how many analyzer plugins `loaded` (see
+//!   [`crate::plugins_api`])
+//! - `scripts`: whether automation scripts are `enabled` and how many `loaded` (see
+//!   [`crate::scripts_api`])
+//! - `encryption`: whether the data directory is encrypted at rest (`enabled`, see
+//!   [`pagi_crypto`]) and whether this build can encrypt the database (`database`, the
+//!   `sqlcipher` feature)
+//!
+//! The Cargo features are listed in `phoenix-web/Cargo.toml`; a build without them is headless
+//! and uses placeholder capture and deterministic replies.
+
+use actix_web::{web, HttpResponse};
+use multi_modal_recording::Capabilities;
+use serde::Serialize;
+
+use crate::AppState;
+
+#[derive(Debug, Serialize)]
+struct LlmCapability {
+    compiled: bool,
+    local: bool,
+    configured: bool,
+    enabled: bool,
+    provider: Option<String>,
+    model: Option<String>,
+    budget_remaining: Option<u32>,
+}
+
+async fn get_capabilities(state: web::Data<AppState>) -> HttpResponse {
+    let configured = state.llm.lock().await.is_some();
+    let completions = state.completions.lock().await.clone();
+    HttpResponse::Ok().json(serde_json::json!({
+        "recorder": Capabilities::compiled(),
+        "emotion_backend": state.capture.emotion_backend(),
+        "llm": LlmCapability {
+            compiled: cfg!(feature = "llm"),
+            local: cfg!(feature = "local-llm"),
+            configured,
+            enabled: configured && state.toggles.llm(),
+            provider: completions.as_ref().map(|c| c.provider().to_string()),
+            model: completions.as_ref().map(|c| c.model().to_string()),
+            budget_remaining: completions.as_ref().and_then(|c| c.budget_remaining()),
+        },
+        "plugins": {
+            "compiled": cfg!(feature = "plugins"),
+            "loaded": state.plugins.plugins().len(),
+        },
+        "scripts": {
+            "enabled": state.scripts.enabled(),
+            "loaded": state.scripts.scripts().len(),
+        },
+        "encryption": {
+            "enabled": state.backup_paths.sealer.is_enabled(),
+            "database": phoenix_storage::encryption::AVAILABLE,
+        },
+    }))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.route("/capabilities", web::get().to(get_capabilities));
+}
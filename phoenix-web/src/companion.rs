@@ -0,0 +1,780 @@
+//! The mobile companion protocol: a phone app pairs with this server by scanning a QR code,
+//! then pushes drafts for resonance analysis, starts captures and collects emotion alerts, with
+//! the desktop as the hub. `docs/COMPANION_PROTOCOL.md` is the wire specification.
+//!
+//! Pairing starts on the desktop: `POST /api/companion/pairings` makes a one-time offer, valid
+//! for five minutes, holding a fresh X25519 key of the hub and a random code, and answers with
+//! the `phoenix-companion://pair` URI as a QR code. The phone sends its own X25519 public key to
+//! `POST /companion/pair` with an HMAC of both public keys keyed by the code, which binds its
+//! key to the QR code without sending the code; [`MAX_PAIR_ATTEMPTS`] wrong proofs spend the
+//! offer. Both sides then derive the device's AES-256-GCM key with HKDF-SHA256 from the shared
+//! secret, salted with the code. Neither the code nor the key travels, so the channel stays
+//! end-to-end encrypted over plain HTTP on the LAN.
+//!
+//! Afterwards every request goes to `POST /companion/messages` with the device id in
+//! `X-Companion-Device` and a body sealed with the device key, in the `pagi-crypto` format used
+//! for stored data. A counter that must grow and the send time refuse replays. Alerts wait in a
+//! per-device outbox (the latest [`MAX_OUTBOX`]) until the phone polls. Paired devices are kept
+//! in `data/companion.json`, sealed like the other stores; `GET /api/companion/devices` lists
+//! them and `DELETE /api/companion/devices/{id}` unpairs one.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::{fmt, io};
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use chrono::Utc;
+use multi_modal_recording::emotion_alerts::EmotionAlert;
+use pagi_crypto::{DataKey, Sealer, KEY_LEN};
+use pagi_errors::ErrorCode;
+use reqwest::Url;
+use ring::agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, hmac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::api_keys::constant_time_eq;
+use crate::audit::{self, Action, Actor};
+use crate::live_events::LiveEvent;
+use crate::recorder_api::{self, CaptureRequest};
+use crate::resonance_api::{self, AnalyzeRequest, ResonanceAnalysis};
+use crate::{ApiError, AppState, FieldError};
+
+/// Paired devices, relative to the data directory.
+pub const STATE_FILE: &str = "data/companion.json";
+/// Version of the protocol in the pairing URI; bumped on incompatible changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Alerts kept per device until it polls.
+pub const MAX_OUTBOX: usize = 100;
+/// Wrong pairing proofs an offer takes before it is spent.
+pub const MAX_PAIR_ATTEMPTS: u32 = 3;
+
+const DEVICE_HEADER: &str = "X-Companion-Device";
+const KEY_INFO: &[u8] = b"phoenix-companion-v1";
+const CODE_BYTES: usize = 16;
+const OFFER_TTL_SECS: i64 = 5 * 60;
+const MAX_SKEW_SECS: i64 = 5 * 60;
+const MAX_NAME_CHARS: usize = 64;
+const MAX_MESSAGE_BYTES: usize = 128 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompanionError {
+    #[error("companion I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot render the pairing QR code: {0}")]
+    Qr(#[from] qrcode::types::QrError),
+    /// No live offer, a proof that doesn't match it, or a bad device key.
+    #[error("pairing refused: {0}")]
+    Pairing(String),
+    /// Unknown device, not sealed with its key, malformed, or replayed.
+    #[error("companion message refused: {0}")]
+    Refused(String),
+}
+
+impl From<CompanionError> for ApiError {
+    fn from(e: CompanionError) -> Self {
+        match e {
+            CompanionError::Pairing(_) => ApiError::bad_request(e.to_string()),
+            CompanionError::Refused(_) => ApiError::unauthorized(e.to_string()),
+            _ => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+fn refused(message: impl fmt::Display) -> CompanionError {
+    CompanionError::Refused(message.to_string())
+}
+
+fn random<const N: usize>() -> Result<[u8; N], CompanionError> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| io::Error::other("no randomness available"))?;
+    Ok(bytes)
+}
+
+/// The device key both sides derive from the X25519 shared secret and the pairing code.
+fn channel_key(shared: &[u8], code: &str) -> DataKey {
+    let mut key = [0u8; KEY_LEN];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, code.as_bytes())
+        .extract(shared)
+        .expand(&[KEY_INFO], hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .expect("HKDF-SHA256 yields 32 bytes");
+    DataKey::from_bytes(key)
+}
+
+/// What the phone proves it read from the QR code: an HMAC-SHA256 keyed by the code over the
+/// hub's public key followed by the phone's.
+fn pairing_proof(code: &str, hub_key: &[u8], device_key: &[u8]) -> hmac::Tag {
+    let mut ctx = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA256, code.as_bytes()));
+    ctx.update(hub_key);
+    ctx.update(device_key);
+    ctx.sign()
+}
+
+/// A pairing waiting for the phone.
+struct Offer {
+    code: String,
+    private: EphemeralPrivateKey,
+    /// The public half of `private`, as in the QR code.
+    public: Vec<u8>,
+    expires_unix: i64,
+    /// Wrong proofs so far.
+    attempts: u32,
+}
+
+/// What the desktop shows to pair a phone.
+#[derive(Debug, Serialize)]
+pub struct PairingOffer {
+    pub code: String,
+    /// Where the phone reaches this server.
+    pub hub: String,
+    /// The hub's X25519 public key, base64url.
+    pub hub_key: String,
+    pub expires_unix: i64,
+    /// `phoenix-companion://pair?v=&hub=&code=&key=`, the QR code's content.
+    pub uri: String,
+    pub qr_svg: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PairRequest {
+    /// Shown in the device list, e.g. "Ana's phone".
+    name: String,
+    /// The phone's X25519 public key, base64url.
+    device_key: String,
+    /// [`pairing_proof`] of the hub's key and `device_key`, base64url.
+    proof: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PairResponse {
+    device: String,
+    protocol: u32,
+    /// The device id sealed with the new key, so the phone can check both sides agree.
+    confirm: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Device {
+    id: String,
+    name: String,
+    /// The channel key, base64url.
+    key: String,
+    paired_unix: i64,
+    #[serde(default)]
+    last_seen_unix: Option<i64>,
+    /// Highest message counter accepted.
+    #[serde(default)]
+    last_seq: u64,
+}
+
+impl Device {
+    fn channel(&self) -> Result<Sealer, CompanionError> {
+        let key: [u8; KEY_LEN] = URL_SAFE_NO_PAD
+            .decode(&self.key)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| io::Error::other(format!("bad key stored for device {}", self.id)))?;
+        Ok(Sealer::new(DataKey::from_bytes(key)))
+    }
+}
+
+/// A paired device as listed to the desktop.
+#[derive(Debug, Serialize)]
+pub struct DeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub paired_unix: i64,
+    pub last_seen_unix: Option<i64>,
+    pub pending_alerts: usize,
+}
+
+/// Every message either way: a counter, the send time and the payload.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope<T> {
+    seq: u64,
+    sent_unix: i64,
+    body: T,
+}
+
+/// What a phone asks for.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    /// Fields of `POST /api/resonance/analyze`.
+    AnalyzeDraft(AnalyzeRequest),
+    /// Fields of `POST /api/recorder/record`.
+    StartRecording(CaptureRequest),
+    /// Collect the queued emotion alerts.
+    Poll,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    Analysis {
+        analysis: ResonanceAnalysis,
+    },
+    Recording {
+        capture: CaptureRequest,
+    },
+    Alerts {
+        alerts: Vec<EmotionAlert>,
+    },
+    /// What the HTTP API would have answered with an error status.
+    Error {
+        status: u16,
+        code: ErrorCode,
+        message: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        errors: Vec<FieldError>,
+    },
+}
+
+impl From<ApiError> for Reply {
+    fn from(e: ApiError) -> Self {
+        Self::Error {
+            status: e.status.as_u16(),
+            code: e
+                .code
+                .unwrap_or_else(|| ErrorCode::for_http_status(e.status.as_u16())),
+            message: e.message,
+            errors: e.errors,
+        }
+    }
+}
+
+pub struct Companion {
+    path: PathBuf,
+    sealer: Sealer,
+    /// At most one pairing at a time; a new offer replaces the last.
+    offer: Mutex<Option<Offer>>,
+    devices: Mutex<Vec<Device>>,
+    outbox: Mutex<HashMap<String, VecDeque<EmotionAlert>>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Companion {
+    /// Load the paired devices from `path` (sealed with `sealer`).
+    pub fn open(path: PathBuf, sealer: Sealer) -> Self {
+        let devices = match std::fs::read(&path) {
+            Ok(raw) => sealer
+                .open(&raw)
+                .map_err(|e| e.to_string())
+                .and_then(|raw| serde_json::from_slice(&raw).map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring unreadable {}: {e}", path.display());
+                    Vec::new()
+                }),
+            Err(_) => Vec::new(),
+        };
+        Self {
+            path,
+            sealer,
+            offer: Mutex::new(None),
+            devices: Mutex::new(devices),
+            outbox: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn save(&self, devices: &[Device]) {
+        let write = || -> io::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            let json = serde_json::to_vec_pretty(devices)?;
+            std::fs::write(&tmp, self.sealer.seal(&json).map_err(io::Error::other)?)?;
+            std::fs::rename(tmp, &self.path)
+        };
+        if let Err(e) = write() {
+            warn!("Cannot save {}: {e}", self.path.display());
+        }
+    }
+
+    /// A new one-time pairing for a phone reaching this server at `hub`.
+    pub fn offer(&self, hub: &Url) -> Result<PairingOffer, CompanionError> {
+        let private = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+            .map_err(|_| io::Error::other("cannot generate a pairing key"))?;
+        let public = private
+            .compute_public_key()
+            .map_err(|_| io::Error::other("cannot generate a pairing key"))?;
+        let code = URL_SAFE_NO_PAD.encode(random::<CODE_BYTES>()?);
+        let hub_key = URL_SAFE_NO_PAD.encode(public.as_ref());
+        let expires_unix = Utc::now().timestamp() + OFFER_TTL_SECS;
+        let mut uri = Url::parse("phoenix-companion://pair").expect("valid URI");
+        uri.query_pairs_mut()
+            .append_pair("v", &PROTOCOL_VERSION.to_string())
+            .append_pair("hub", hub.as_str())
+            .append_pair("code", &code)
+            .append_pair("key", &hub_key);
+        let qr_svg = qrcode::QrCode::new(uri.as_str())?
+            .render::<qrcode::render::svg::Color<'_>>()
+            .min_dimensions(256, 256)
+            .build();
+        *lock(&self.offer) = Some(Offer {
+            code: code.clone(),
+            private,
+            public: public.as_ref().to_vec(),
+            expires_unix,
+            attempts: 0,
+        });
+        Ok(PairingOffer {
+            code,
+            hub: hub.to_string(),
+            hub_key,
+            expires_unix,
+            uri: uri.into(),
+            qr_svg,
+        })
+    }
+
+    /// Finish the live offer with the phone's key. A valid proof spends the offer, and so does
+    /// the last of [`MAX_PAIR_ATTEMPTS`] wrong ones.
+    fn pair(&self, request: PairRequest) -> Result<(Device, PairResponse), CompanionError> {
+        let name = request.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            return Err(CompanionError::Pairing(format!(
+                "name must be 1 to {MAX_NAME_CHARS} characters"
+            )));
+        }
+        let device_key = URL_SAFE_NO_PAD
+            .decode(request.device_key.trim())
+            .map_err(|e| CompanionError::Pairing(format!("device_key: {e}")))?;
+        let proof = URL_SAFE_NO_PAD
+            .decode(request.proof.trim())
+            .map_err(|e| CompanionError::Pairing(format!("proof: {e}")))?;
+        let offer = {
+            let mut slot = lock(&self.offer);
+            let Some(live) = slot.as_mut() else {
+                return Err(CompanionError::Pairing(
+                    "no pairing is waiting; make a new one on the desktop".to_string(),
+                ));
+            };
+            if Utc::now().timestamp() > live.expires_unix {
+                *slot = None;
+                return Err(CompanionError::Pairing(
+                    "the pairing expired; make a new one on the desktop".to_string(),
+                ));
+            }
+            let expected = pairing_proof(&live.code, &live.public, &device_key);
+            if !constant_time_eq(expected.as_ref(), &proof) {
+                live.attempts += 1;
+                if live.attempts >= MAX_PAIR_ATTEMPTS {
+                    *slot = None;
+                    return Err(CompanionError::Pairing(
+                        "too many wrong proofs; make a new pairing on the desktop".to_string(),
+                    ));
+                }
+                return Err(CompanionError::Pairing(
+                    "the proof doesn't match this pairing's code and keys".to_string(),
+                ));
+            }
+            slot.take().expect("checked above")
+        };
+        let key = agreement::agree_ephemeral(
+            offer.private,
+            &UnparsedPublicKey::new(&X25519, &device_key),
+            |shared| channel_key(shared, &offer.code),
+        )
+        .map_err(|_| CompanionError::Pairing("device_key is not an X25519 key".to_string()))?;
+        let device = Device {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            name: name.to_string(),
+            key: URL_SAFE_NO_PAD.encode(key.as_bytes()),
+            paired_unix: Utc::now().timestamp(),
+            last_seen_unix: None,
+            last_seq: 0,
+        };
+        let confirm = Sealer::new(key)
+            .seal(device.id.as_bytes())
+            .map_err(io::Error::other)?;
+        let response = PairResponse {
+            device: device.id.clone(),
+            protocol: PROTOCOL_VERSION,
+            confirm: URL_SAFE_NO_PAD.encode(confirm),
+        };
+        let mut devices = lock(&self.devices);
+        devices.push(device.clone());
+        self.save(&devices);
+        Ok((device, response))
+    }
+
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        let outbox = lock(&self.outbox);
+        lock(&self.devices)
+            .iter()
+            .map(|device| DeviceInfo {
+                id: device.id.clone(),
+                name: device.name.clone(),
+                paired_unix: device.paired_unix,
+                last_seen_unix: device.last_seen_unix,
+                pending_alerts: outbox.get(&device.id).map_or(0, VecDeque::len),
+            })
+            .collect()
+    }
+
+    /// Unpair `id`; `false` if it isn't paired.
+    fn revoke(&self, id: &str) -> bool {
+        let mut devices = lock(&self.devices);
+        let before = devices.len();
+        devices.retain(|device| device.id != id);
+        if devices.len() == before {
+            return false;
+        }
+        self.save(&devices);
+        lock(&self.outbox).remove(id);
+        true
+    }
+
+    /// Open a message from `device`, refusing replays; returns its channel with the request.
+    fn open_message(
+        &self,
+        device: &str,
+        body: &[u8],
+    ) -> Result<(Sealer, Envelope<Request>), CompanionError> {
+        let mut devices = lock(&self.devices);
+        let paired = devices
+            .iter_mut()
+            .find(|paired| paired.id == device)
+            .ok_or_else(|| refused("unknown device; pair it again"))?;
+        let channel = paired.channel()?;
+        // `Sealer::open` passes unsealed data through; a message must be sealed.
+        if !pagi_crypto::is_sealed(body) {
+            return Err(refused("not sealed"));
+        }
+        let plain = channel
+            .open(body)
+            .map_err(|_| refused("not sealed with this device's key"))?;
+        let envelope: Envelope<Request> = serde_json::from_slice(&plain).map_err(refused)?;
+        let now = Utc::now().timestamp();
+        if (now - envelope.sent_unix).abs() > MAX_SKEW_SECS {
+            return Err(refused("sent too long ago; check the phone's clock"));
+        }
+        if envelope.seq <= paired.last_seq {
+            return Err(refused("already seen; seq must grow with every message"));
+        }
+        paired.last_seq = envelope.seq;
+        paired.last_seen_unix = Some(now);
+        self.save(&devices);
+        Ok((channel, envelope))
+    }
+
+    fn queue_alert(&self, alert: &EmotionAlert) {
+        let devices = lock(&self.devices);
+        let mut outbox = lock(&self.outbox);
+        for device in devices.iter() {
+            let queue = outbox.entry(device.id.clone()).or_default();
+            if queue.len() == MAX_OUTBOX {
+                queue.pop_front();
+            }
+            queue.push_back(alert.clone());
+        }
+    }
+
+    fn take_alerts(&self, device: &str) -> Vec<EmotionAlert> {
+        lock(&self.outbox)
+            .remove(device)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+}
+
+fn seal_reply(channel: &Sealer, seq: u64, reply: Reply) -> Result<Vec<u8>, CompanionError> {
+    let envelope = Envelope {
+        seq,
+        sent_unix: Utc::now().timestamp(),
+        body: reply,
+    };
+    let json = serde_json::to_vec(&envelope).map_err(io::Error::other)?;
+    Ok(channel.seal(&json).map_err(io::Error::other)?)
+}
+
+/// Queue every emotion alert for the paired devices until the server shuts down.
+pub(crate) fn spawn(state: &AppState) -> JoinHandle<()> {
+    let companion = state.companion.clone();
+    let mut live = state.live.subscribe();
+    let mut closing = state.live.closing();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                event = live.recv() => match event {
+                    Ok(LiveEvent::EmotionAlert { alert }) => companion.queue_alert(&alert),
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                },
+                _ = closing.wait_for(|closing| *closing) => return,
+            }
+        }
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct OfferQuery {
+    /// Overrides the LAN address the phone is sent to.
+    hub: Option<String>,
+}
+
+/// POST /api/companion/pairings?hub=
+async fn post_pairing(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<OfferQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let hub = match &query.hub {
+        Some(hub) => Url::parse(hub).map_err(|e| ApiError::bad_request(format!("hub: {e}")))?,
+        None => {
+            let ip = local_ip_address::local_ip()
+                .map_err(|e| ApiError::internal(format!("no LAN address; pass hub: {e}")))?;
+            let scheme = req.connection_info().scheme().to_string();
+            let port = req.app_config().local_addr().port();
+            Url::parse(&format!("{scheme}://{ip}:{port}"))
+                .map_err(|e| ApiError::internal(e.to_string()))?
+        }
+    };
+    Ok(HttpResponse::Created().json(state.companion.offer(&hub)?))
+}
+
+/// POST /companion/pair
+async fn post_pair(
+    state: web::Data<AppState>,
+    body: web::Json<PairRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let (device, response) = state.companion.pair(body.into_inner())?;
+    audit::record(
+        &state,
+        &Actor::Companion(device.id),
+        Action::CompanionPair,
+        json!({ "name": device.name }),
+    );
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// POST /companion/messages
+async fn post_message(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    let device = req
+        .headers()
+        .get(DEVICE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| ApiError::unauthorized(format!("{DEVICE_HEADER} header required")))?
+        .to_string();
+    let (channel, envelope) = state.companion.open_message(&device, &body)?;
+    let reply = match envelope.body {
+        Request::AnalyzeDraft(draft) => match resonance_api::run(&state.cache, &draft) {
+            Ok(analysis) => Reply::Analysis { analysis },
+            Err(errors) => ApiError::validation(errors).into(),
+        },
+        Request::StartRecording(capture) => {
+            match recorder_api::start_capture(&state, &Actor::Companion(device), &capture) {
+                Ok(()) => Reply::Recording { capture },
+                Err(e) => e.into(),
+            }
+        }
+        Request::Poll => Reply::Alerts {
+            alerts: state.companion.take_alerts(&device),
+        },
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .body(seal_reply(&channel, envelope.seq, reply)?))
+}
+
+/// GET /api/companion/devices
+async fn get_devices(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(state.companion.devices())
+}
+
+/// DELETE /api/companion/devices/{id}
+async fn delete_device(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    if !state.companion.revoke(&id) {
+        return Err(ApiError::not_found(format!("no paired device {id}")));
+    }
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::CompanionRevoke,
+        json!({ "device": id }),
+    );
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Routes phones call, outside `/api`: the pairing code, then the device key, is their
+/// credential.
+pub fn configure_device_routes(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::PayloadConfig::new(MAX_MESSAGE_BYTES))
+        .service(web::resource("/pair").route(web::post().to(post_pair)))
+        .service(web::resource("/messages").route(web::post().to(post_message)));
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/companion")
+            .service(web::resource("/pairings").route(web::post().to(post_pairing)))
+            .service(web::resource("/devices").route(web::get().to(get_devices)))
+            .service(web::resource("/devices/{id}").route(web::delete().to(delete_device))),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn companion() -> (PathBuf, Companion) {
+        let dir = std::env::temp_dir().join(format!("phoenix-companion-{}", uuid::Uuid::new_v4()));
+        let companion = Companion::open(dir.join(STATE_FILE), Sealer::default());
+        (dir, companion)
+    }
+
+    /// What a phone reads from the offer's URI: the code and the hub's key.
+    fn scan(offer: &PairingOffer) -> (String, Vec<u8>) {
+        let uri = Url::parse(&offer.uri).unwrap();
+        let param = |name: &str| {
+            uri.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .unwrap()
+        };
+        assert_eq!(param("hub"), "http://192.168.1.20:8888/");
+        (param("code"), URL_SAFE_NO_PAD.decode(param("key")).unwrap())
+    }
+
+    fn new_offer(companion: &Companion) -> PairingOffer {
+        companion
+            .offer(&Url::parse("http://192.168.1.20:8888").unwrap())
+            .unwrap()
+    }
+
+    /// A fresh phone key and a request proving it with `code`.
+    fn pair_request(code: &str, hub_key: &[u8]) -> (EphemeralPrivateKey, PairRequest) {
+        let phone = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new()).unwrap();
+        let public = phone.compute_public_key().unwrap();
+        let request = PairRequest {
+            name: "Ana's phone".to_string(),
+            device_key: URL_SAFE_NO_PAD.encode(public.as_ref()),
+            proof: URL_SAFE_NO_PAD.encode(pairing_proof(code, hub_key, public.as_ref())),
+        };
+        (phone, request)
+    }
+
+    /// Pair like a phone would from the offer's URI; returns the device id and its channel.
+    fn pair_phone(companion: &Companion) -> (String, Sealer) {
+        let (code, hub_key) = scan(&new_offer(companion));
+        let (phone, request) = pair_request(&code, &hub_key);
+        let phone_key = request.device_key.clone();
+        let proof = request.proof.clone();
+        let key = agreement::agree_ephemeral(
+            phone,
+            &UnparsedPublicKey::new(&X25519, &hub_key),
+            |shared| channel_key(shared, &code),
+        )
+        .unwrap();
+        let (_, response) = companion.pair(request).unwrap();
+        let channel = Sealer::new(key);
+        let confirm = URL_SAFE_NO_PAD.decode(&response.confirm).unwrap();
+        assert_eq!(
+            &*channel.open(&confirm).unwrap(),
+            response.device.as_bytes()
+        );
+
+        // The offer is spent.
+        let again = PairRequest {
+            name: "Other".to_string(),
+            device_key: phone_key,
+            proof,
+        };
+        assert!(matches!(
+            companion.pair(again),
+            Err(CompanionError::Pairing(_))
+        ));
+        (response.device, channel)
+    }
+
+    #[test]
+    fn a_swapped_device_key_is_refused_and_wrong_proofs_spend_the_offer() {
+        let (dir, companion) = companion();
+        let (code, hub_key) = scan(&new_offer(&companion));
+
+        // Someone relaying the phone's request swaps in their own key but can't re-prove it.
+        let (_, phone) = pair_request(&code, &hub_key);
+        let (_, mut relayed) = pair_request("not-the-code", &hub_key);
+        relayed.proof = phone.proof.clone();
+        assert!(matches!(
+            companion.pair(relayed),
+            Err(CompanionError::Pairing(_))
+        ));
+        assert!(companion.devices().is_empty());
+
+        for _ in 1..MAX_PAIR_ATTEMPTS {
+            let (_, guess) = pair_request("guess", &hub_key);
+            assert!(companion.pair(guess).is_err());
+        }
+        // The offer is gone, so even the right proof fails now.
+        assert!(companion.pair(phone).is_err());
+        assert!(companion.devices().is_empty());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    fn sealed(channel: &Sealer, seq: u64, body: serde_json::Value) -> Vec<u8> {
+        let envelope = json!({ "seq": seq, "sent_unix": Utc::now().timestamp(), "body": body });
+        channel.seal(envelope.to_string().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn paired_phone_shares_the_key_and_replays_are_refused() {
+        let (dir, companion) = companion();
+        let (device, channel) = pair_phone(&companion);
+
+        let poll = sealed(&channel, 1, json!({ "type": "poll" }));
+        let (_, envelope) = companion.open_message(&device, &poll).unwrap();
+        assert!(matches!(envelope.body, Request::Poll));
+        assert!(matches!(
+            companion.open_message(&device, &poll),
+            Err(CompanionError::Refused(_))
+        ));
+        let draft =
+            json!({ "type": "analyze_draft", "script": "I felt unheard", "tone": "gentle" });
+        assert!(companion
+            .open_message(&device, &sealed(&channel, 2, draft))
+            .is_ok());
+
+        let stranger = Sealer::new(DataKey::from_bytes([7; KEY_LEN]));
+        let forged = sealed(&stranger, 3, json!({ "type": "poll" }));
+        assert!(companion.open_message(&device, &forged).is_err());
+        assert!(companion
+            .open_message(
+                &device,
+                br#"{"seq":9,"sent_unix":0,"body":{"type":"poll"}}"#
+            )
+            .is_err());
+
+        // Paired devices survive a restart, with their counters.
+        let reopened = Companion::open(dir.join(STATE_FILE), Sealer::default());
+        assert_eq!(reopened.devices()[0].name, "Ana's phone");
+        assert!(reopened.open_message(&device, &poll).is_err());
+        assert!(reopened.revoke(&device));
+        assert!(reopened.devices().is_empty());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,185 @@
+//! In-process cache for results that are expensive to recompute and often asked for again.
+//!
+//! - Resonance analyses (`POST /api/resonance/analyze` and the gRPC equivalent), keyed by a
+//!   hash of the script with the persona and tone. The script is hashed exactly as sent rather
+//!   than normalized, because the breach spans in the result are offsets into it.
+//! - Emotion trends (`GET /api/emotion/trends`), keyed by the requested window, bucket and
+//!   profile. Open-ended windows ("up to now") can lag new samples by the TTL.
+//!
+//! Both are emptied when the emotion lexicon changes on a config reload and on
+//! `DELETE /api/admin/cache`; trends also when text is recorded through `POST /api/emotion/text`
+//! or a profile's history is deleted.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use multi_modal_recording::emotion_trends::{EmotionTrends, TrendQuery};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::resonance::PartnerPersona;
+use crate::resonance_api::ResonanceAnalysis;
+
+const RESONANCE_TTL: Duration = Duration::from_secs(10 * 60);
+const RESONANCE_CAPACITY: usize = 512;
+const TRENDS_TTL: Duration = Duration::from_secs(60);
+const TRENDS_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub ttl_secs: u64,
+}
+
+/// A size-bounded map whose entries expire `ttl` after they were stored.
+pub struct TtlCache<V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, V)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self.entries.lock().ok()?;
+        let found = match entries.get(key) {
+            Some((stored, value)) if stored.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Store `value`; when full, expired entries go first, then the oldest one.
+    pub fn insert(&self, key: String, value: V) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (stored, _))| *stored)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.lock().map(|e| e.len()).unwrap_or(0),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            ttl_secs: self.ttl.as_secs(),
+        }
+    }
+}
+
+pub struct ComputeCache {
+    pub resonance: TtlCache<ResonanceAnalysis>,
+    pub trends: TtlCache<EmotionTrends>,
+}
+
+impl Default for ComputeCache {
+    fn default() -> Self {
+        Self {
+            resonance: TtlCache::new(RESONANCE_TTL, RESONANCE_CAPACITY),
+            trends: TtlCache::new(TRENDS_TTL, TRENDS_CAPACITY),
+        }
+    }
+}
+
+impl ComputeCache {
+    pub fn clear(&self) {
+        self.resonance.clear();
+        self.trends.clear();
+    }
+}
+
+pub fn resonance_key(script: &str, persona: &PartnerPersona, tone: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(script.as_bytes());
+    let digest = hasher.finalize();
+    let tone = tone
+        .map(|t| t.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    format!("{}|{tone}|{digest:x}", persona.label())
+}
+
+pub fn trends_key(query: &TrendQuery) -> String {
+    serde_json::to_string(query).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire_and_the_oldest_is_evicted() {
+        let cache = TtlCache::new(Duration::from_secs(60), 2);
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        cache.insert("c".to_string(), 3);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("c"), Some(3));
+        assert_eq!(cache.stats().entries, 2);
+
+        let expired = TtlCache::new(Duration::ZERO, 2);
+        expired.insert("a".to_string(), 1);
+        assert_eq!(expired.get("a"), None);
+        let stats = expired.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (0, 0, 1));
+    }
+
+    #[test]
+    fn resonance_keys_follow_persona_and_tone() {
+        let key = |persona, tone| resonance_key("I feel hurt", &persona, tone);
+        assert_eq!(
+            key(PartnerPersona::Secure, Some(" Gentle ")),
+            key(PartnerPersona::Secure, Some("gentle"))
+        );
+        assert_ne!(
+            key(PartnerPersona::Secure, None),
+            key(PartnerPersona::AnxiousPreoccupied, None)
+        );
+        assert_ne!(
+            resonance_key("I feel hurt", &PartnerPersona::Secure, None),
+            resonance_key("I feel hurt ", &PartnerPersona::Secure, None)
+        );
+    }
+}
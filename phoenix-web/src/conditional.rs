@@ -0,0 +1,99 @@
+//! Compression and conditional GETs for the heavier read routes.
+//!
+//! Emotion history, trends and exports and the counselor export and correlations grow with the
+//! stored history, so those resources are wrapped in actix's `Compress` (gzip, br or zstd as
+//! the client accepts) and in [`etag`]. The ETag is a hash of the uncompressed body, so a client
+//! that sends it back in `If-None-Match` gets an empty `304` when nothing changed since its last
+//! fetch. The response is still computed; what's saved is sending it.
+
+use std::fmt::Write;
+
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+use sha2::{Digest, Sha256};
+
+/// Weak, because `Compress` may re-encode the body after the tag is computed.
+fn tag_for(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    let mut tag = String::with_capacity(36);
+    tag.push_str("W/\"");
+    for b in &digest[..16] {
+        let _ = write!(tag, "{b:02x}");
+    }
+    tag.push('"');
+    tag
+}
+
+/// Whether an `If-None-Match` value names `tag` (weak comparison, as RFC 9110 requires here).
+fn matches(if_none_match: &str, tag: &str) -> bool {
+    let opaque = |t: &str| t.trim().trim_start_matches("W/").to_string();
+    let tag = opaque(tag);
+    if_none_match
+        .split(',')
+        .any(|t| t.trim() == "*" || opaque(t) == tag)
+}
+
+/// Tags successful GET responses and answers `304 Not Modified` when the client already has
+/// the body. Streamed bodies are passed through untagged.
+pub async fn etag(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let if_none_match = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let res = next.call(req).await?;
+    if res.status() != StatusCode::OK || res.request().method() != Method::GET {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = match body.try_into_bytes() {
+        Ok(body) => body,
+        Err(body) => {
+            return Ok(ServiceResponse::new(req, res.set_body(body)).map_into_boxed_body());
+        }
+    };
+    let tag = tag_for(&body);
+    let mut res = if if_none_match.is_some_and(|h| matches(&h, &tag)) {
+        HttpResponse::NotModified().finish()
+    } else {
+        res.set_body(body).map_into_boxed_body()
+    };
+    if let Ok(value) = HeaderValue::from_str(&tag) {
+        res.headers_mut().insert(header::ETAG, value);
+    }
+    // Cacheable, but always revalidated: the history behind it can change at any time.
+    res.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, no-cache"),
+    );
+    Ok(ServiceResponse::new(req, res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let tag = tag_for(b"{\"count\":0}");
+        assert!(tag.starts_with("W/\"") && tag.len() == 36);
+        assert_eq!(tag, tag_for(b"{\"count\":0}"));
+        assert_ne!(tag, tag_for(b"{\"count\":1}"));
+
+        let strong = tag.trim_start_matches("W/");
+        assert!(matches(&tag, &tag));
+        assert!(matches(strong, &tag));
+        assert!(matches(&format!("\"other\", {tag}"), &tag));
+        assert!(matches("*", &tag));
+        assert!(!matches("\"other\"", &tag));
+    }
+}
@@ -0,0 +1,421 @@
+//! Hot reload of the settings file.
+//!
+//! While the server runs, the config file it started with (see [`crate::settings`]) is watched.
+//! When it changes it is read again, with the same environment and command-line values on top,
+//! and the settings that are safe to change live are applied:
+//!
+//! - `logging.filter` and `retention.log_files`
+//! - `sensors.*` (stress threshold and sampling intervals)
+//! - `retention.session_ttl_hours` and `retention.sandbox_cleanup_days`
+//! - `auth.rate_limit_cheap_per_min` and `auth.rate_limit_expensive_per_min`
+//! - `lexicon.*` (extra emotion lexicon terms)
+//!
+//! Any other changed setting keeps its running value and is reported as needing a restart. A
+//! file that no longer parses or validates is ignored as a whole. Every reload that changes
+//! something is logged under the `config` target and published as a
+//! [`LiveEvent::ConfigReloaded`](crate::live_events::LiveEvent::ConfigReloaded).
+//!
+//! [`ConfigView`] keeps the file as last read next to the values in use, for
+//! `GET /api/admin/config`.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use sandbox_manager::SandboxManager;
+use serde::Serialize;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::compute_cache::ComputeCache;
+use crate::live_events::LiveEvents;
+use crate::rate_limit::RateLimiter;
+use crate::sessions::SessionStore;
+use crate::settings::{self, Layers, SensorSettings, ServerConfig, KEYS};
+
+/// Settings a reload applies without a restart.
+pub const RELOADABLE: &[&str] = &[
+    "logging.filter",
+    "retention.log_files",
+    "retention.session_ttl_hours",
+    "retention.sandbox_cleanup_days",
+    "sensors.stress_sample_secs",
+    "sensors.recording_progress_secs",
+    "sensors.stress_alert_percent",
+    "auth.rate_limit_cheap_per_min",
+    "auth.rate_limit_expensive_per_min",
+];
+
+/// Editors save in bursts (truncate, write, rename); wait for the file to settle.
+const SETTLE: Duration = Duration::from_millis(300);
+
+fn reloadable(key: &str) -> bool {
+    RELOADABLE.contains(&key) || key.starts_with("lexicon.")
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingChange {
+    pub key: &'static str,
+    /// `None` = not set (the default applies).
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReloadReport {
+    pub applied: Vec<SettingChange>,
+    /// Changed in the file but still running with the old value.
+    pub restart_required: Vec<SettingChange>,
+    /// Why the file was ignored, if it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReloadReport {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty() && self.error.is_none()
+    }
+}
+
+/// The running parts of the server a reload can change.
+#[derive(Clone)]
+pub struct Targets {
+    pub sensors: watch::Sender<SensorSettings>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub sessions: Option<Arc<SessionStore>>,
+    pub sandbox: Option<Arc<SandboxManager>>,
+    pub view: ConfigView,
+    pub cache: Arc<ComputeCache>,
+}
+
+/// Raw value per key, as the server is currently running.
+type Snapshot = BTreeMap<&'static str, Option<String>>;
+
+fn snapshot(layers: &Layers) -> Snapshot {
+    KEYS.iter()
+        .map(|key| (key.name, layers.get(key.name).map(|(value, _)| value)))
+        .collect()
+}
+
+fn masked(key: &str, value: &Option<String>) -> Option<String> {
+    match value {
+        Some(_) if settings::is_secret(key) => Some("********".to_string()),
+        other => other.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveSetting {
+    pub key: &'static str,
+    /// `None` = not set (the default applies). Secrets are masked.
+    pub value: Option<String>,
+    /// `default`, the config file, `$VAR` or `command line`.
+    pub source: String,
+    /// The file holds a value that only takes effect after a restart.
+    pub restart_required: bool,
+    /// The value in use when `restart_required`; absent = the default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running: Option<String>,
+}
+
+struct ViewState {
+    layers: Layers,
+    running: Snapshot,
+}
+
+/// The settings as last read and which of them are still waiting for a restart.
+#[derive(Clone)]
+pub struct ConfigView(Arc<RwLock<ViewState>>);
+
+impl ConfigView {
+    pub fn new(layers: &Layers) -> Self {
+        Self(Arc::new(RwLock::new(ViewState {
+            running: snapshot(layers),
+            layers: layers.clone(),
+        })))
+    }
+
+    fn update(&self, layers: &Layers, running: &Snapshot) {
+        if let Ok(mut view) = self.0.write() {
+            view.layers = layers.clone();
+            view.running = running.clone();
+        }
+    }
+
+    /// The settings as last read.
+    pub fn layers(&self) -> Layers {
+        match self.0.read() {
+            Ok(view) => view.layers.clone(),
+            Err(poisoned) => poisoned.into_inner().layers.clone(),
+        }
+    }
+
+    pub fn file_path(&self) -> Option<PathBuf> {
+        let view = self.0.read().ok()?;
+        view.layers.file_path().map(Path::to_path_buf)
+    }
+
+    pub fn settings(&self) -> Vec<EffectiveSetting> {
+        let Ok(view) = self.0.read() else {
+            return Vec::new();
+        };
+        view.layers
+            .effective()
+            .into_iter()
+            .map(|(key, value)| {
+                let raw = view.layers.get(key).map(|(raw, _)| raw);
+                let running = view.running.get(key).cloned().flatten();
+                let restart_required = running != raw;
+                let (value, source) = match value {
+                    Some((value, source)) => (Some(value), source.to_string()),
+                    None => (None, "default".to_string()),
+                };
+                EffectiveSetting {
+                    key,
+                    value,
+                    source,
+                    restart_required,
+                    running: masked(key, &running).filter(|_| restart_required),
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct ConfigReloader {
+    layers: Layers,
+    running: Snapshot,
+    targets: Targets,
+    live: LiveEvents,
+}
+
+impl ConfigReloader {
+    pub fn new(layers: Layers, targets: Targets, live: LiveEvents) -> Self {
+        Self {
+            running: snapshot(&layers),
+            layers,
+            targets,
+            live,
+        }
+    }
+
+    /// Read the file again, apply what can be applied, and report it.
+    pub fn reload(&mut self) -> ReloadReport {
+        let report = self.try_reload().unwrap_or_else(|error| ReloadReport {
+            error: Some(error),
+            ..ReloadReport::default()
+        });
+        self.targets.view.update(&self.layers, &self.running);
+        if report.is_empty() {
+            return report;
+        }
+        if let Some(error) = &report.error {
+            tracing::warn!(target: "config", "Settings file not reloaded: {error}");
+        }
+        for change in &report.applied {
+            tracing::info!(
+                target: "config",
+                key = change.key,
+                old = ?change.old,
+                new = ?change.new,
+                "setting applied"
+            );
+        }
+        for change in &report.restart_required {
+            tracing::warn!(
+                target: "config",
+                key = change.key,
+                old = ?change.old,
+                new = ?change.new,
+                "`{}` changed but only takes effect after a restart; still using the old value",
+                change.key
+            );
+        }
+        self.live.config_reloaded(report.clone());
+        report
+    }
+
+    fn try_reload(&mut self) -> Result<ReloadReport, String> {
+        let layers = self.layers.reload().map_err(|e| e.to_string())?;
+        let config = ServerConfig::from_layers(&layers).map_err(|e| e.to_string())?;
+        let log = layers.log_config("").map_err(|e| e.to_string())?;
+
+        let next = snapshot(&layers);
+        let mut report = ReloadReport::default();
+        for (&key, new) in &next {
+            let old = &self.running[key];
+            if old == new {
+                continue;
+            }
+            let change = SettingChange {
+                key,
+                old: masked(key, old),
+                new: masked(key, new),
+            };
+            if reloadable(key) {
+                report.applied.push(change);
+            } else {
+                report.restart_required.push(change);
+            }
+        }
+        if report.applied.is_empty() {
+            self.layers = layers;
+            return Ok(report);
+        }
+
+        // The only step that can fail goes first, so a bad filter leaves everything as it was.
+        if report.applied.iter().any(|c| c.key == "logging.filter") {
+            pagi_utils::logging::set_filter(&log.filter)
+                .map_err(|e| format!("invalid `logging.filter` {:?}: {e}", log.filter))?;
+        }
+        pagi_utils::logging::set_keep_files(log.keep_files);
+        self.targets.sensors.send_replace(config.sensors);
+        self.targets.rate_limiter.set_limits(
+            config.auth.rate_limit_cheap_per_min,
+            config.auth.rate_limit_expensive_per_min,
+        );
+        if let Some(sessions) = &self.targets.sessions {
+            sessions.set_ttl(config.retention.session_ttl);
+        }
+        if let Some(sandbox) = &self.targets.sandbox {
+            sandbox.set_cleanup_days(config.retention.sandbox_cleanup_days);
+        }
+        emotion_detection::text::set_extra_terms(config.lexicon);
+        if report.applied.iter().any(|c| c.key.starts_with("lexicon.")) {
+            self.targets.cache.clear();
+        }
+
+        for change in &report.applied {
+            self.running.insert(change.key, next[change.key].clone());
+        }
+        self.layers = layers;
+        Ok(report)
+    }
+
+    /// Watch the config file and reload on every change. `None` when the server was started
+    /// without one.
+    pub fn spawn(mut self) -> Option<JoinHandle<()>> {
+        let path = self.layers.file_path()?.to_path_buf();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let file_name = path.file_name().map(|n| n.to_os_string());
+        let mut watcher =
+            match notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                let ours = event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name);
+                if ours && !matches!(event.kind, EventKind::Access(_)) {
+                    let _ = tx.send(());
+                }
+            }) {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    tracing::warn!(target: "config", "Not watching {}: {e}", path.display());
+                    return None;
+                }
+            };
+        // Watch the directory: editors often replace the file rather than write to it.
+        let dir = watched_dir(&path);
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            tracing::warn!(target: "config", "Not watching {}: {e}", path.display());
+            return None;
+        }
+        tracing::info!(target: "config", "Watching {} for changes", path.display());
+
+        Some(tokio::spawn(async move {
+            let _watcher = watcher;
+            while rx.recv().await.is_some() {
+                tokio::time::sleep(SETTLE).await;
+                while rx.try_recv().is_ok() {}
+                self.reload();
+            }
+        }))
+    }
+}
+
+fn watched_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Overrides;
+
+    #[test]
+    fn applies_safe_changes_and_holds_back_the_rest() {
+        let dir = std::env::temp_dir().join(format!("phoenix-reload-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("phoenix.toml");
+        std::fs::write(
+            &path,
+            "[server]\nport = 7000\n[auth]\nrate_limit_cheap_per_min = 1\n",
+        )
+        .unwrap();
+        let layers = Layers::load(&Overrides {
+            config_file: Some(path.clone()),
+            ..Overrides::default()
+        })
+        .unwrap();
+        let (sensors, _) = watch::channel(SensorSettings::default());
+        let rate_limiter = Arc::new(RateLimiter::new(1, 1));
+        let targets = Targets {
+            sensors: sensors.clone(),
+            rate_limiter: rate_limiter.clone(),
+            sessions: None,
+            sandbox: None,
+            view: ConfigView::new(&layers),
+            cache: Arc::default(),
+        };
+        let view = targets.view.clone();
+        let mut reloader = ConfigReloader::new(layers, targets, LiveEvents::new());
+
+        std::fs::write(
+            &path,
+            "[server]\nport = 7001\n[auth]\nrate_limit_cheap_per_min = 0\n\
+             [sensors]\nstress_alert_percent = 60\n",
+        )
+        .unwrap();
+        let report = reloader.reload();
+        let keys = |changes: &[SettingChange]| changes.iter().map(|c| c.key).collect::<Vec<_>>();
+        assert_eq!(
+            keys(&report.applied),
+            [
+                "auth.rate_limit_cheap_per_min",
+                "sensors.stress_alert_percent"
+            ]
+        );
+        assert_eq!(keys(&report.restart_required), ["server.port"]);
+        assert_eq!(sensors.borrow().stress_alert_percent, 60);
+        let port = view
+            .settings()
+            .into_iter()
+            .find(|s| s.key == "server.port")
+            .unwrap();
+        assert_eq!(port.value.as_deref(), Some("7001"));
+        assert!(port.restart_required);
+        assert_eq!(port.running.as_deref(), Some("7000"));
+        let now = std::time::Instant::now();
+        for _ in 0..5 {
+            assert!(rate_limiter
+                .check(crate::rate_limit::Tier::Cheap, "c", now)
+                .is_ok());
+        }
+
+        // Still pending until a restart; an invalid file changes nothing.
+        std::fs::write(&path, "[sensors]\nstress_alert_percent = 500\n").unwrap();
+        let report = reloader.reload();
+        assert!(report.error.unwrap().contains("stress_alert_percent"));
+        assert_eq!(sensors.borrow().stress_alert_percent, 60);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
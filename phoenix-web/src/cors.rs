@@ -0,0 +1,115 @@
+//! CORS allowlist for browser clients.
+//!
+//! `PHOENIX_CORS_ORIGINS` is a comma-separated list of origins. An entry is an exact origin
+//! (`https://ui.example.lan`), an origin with any port (`http://localhost:*`), or `*` for any
+//! origin. Unset, the list is the Tauri webview origins plus `localhost` / `127.0.0.1` on any
+//! port; with mobile pairing on, the Mobile PWA's LAN origin is added too.
+
+use actix_cors::Cors;
+
+const DEFAULT_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    "http://localhost:*",
+    "https://localhost:*",
+    "http://127.0.0.1:*",
+    "https://127.0.0.1:*",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OriginPattern {
+    Any,
+    Exact(String),
+    /// `scheme://host`, any (or no) port.
+    AnyPort(String),
+}
+
+impl OriginPattern {
+    fn parse(entry: &str) -> Option<Self> {
+        let entry = entry.trim().trim_end_matches('/').to_ascii_lowercase();
+        if entry.is_empty() {
+            None
+        } else if entry == "*" {
+            Some(Self::Any)
+        } else if let Some(base) = entry.strip_suffix(":*") {
+            Some(Self::AnyPort(base.to_string()))
+        } else {
+            Some(Self::Exact(entry))
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(exact) => origin == exact,
+            Self::AnyPort(base) => origin.strip_prefix(base.as_str()).is_some_and(|rest| {
+                rest.is_empty()
+                    || rest
+                        .strip_prefix(':')
+                        .is_some_and(|port| port.parse::<u16>().is_ok())
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    patterns: Vec<OriginPattern>,
+}
+
+impl CorsPolicy {
+    pub fn new<S: AsRef<str>>(origins: &[S]) -> Self {
+        Self {
+            patterns: origins
+                .iter()
+                .filter_map(|o| OriginPattern::parse(o.as_ref()))
+                .collect(),
+        }
+    }
+
+    pub fn default_origins() -> Vec<String> {
+        DEFAULT_ORIGINS.iter().map(|s| s.to_string()).collect()
+    }
+
+    pub fn allows_any(&self) -> bool {
+        self.patterns.contains(&OriginPattern::Any)
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.patterns.iter().any(|p| p.matches(&origin))
+    }
+
+    /// The actix middleware for this policy.
+    pub fn middleware(&self) -> Cors {
+        let policy = self.clone();
+        Cors::default()
+            .allow_any_method()
+            .allow_any_header()
+            .allowed_origin_fn(move |origin, _req| origin.to_str().is_ok_and(|o| policy.allows(o)))
+            .supports_credentials()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_exact_and_any_port_entries() {
+        let policy = CorsPolicy::new(&CorsPolicy::default_origins());
+        assert!(policy.allows("tauri://localhost"));
+        assert!(policy.allows("http://localhost:5173"));
+        assert!(policy.allows("http://127.0.0.1"));
+        assert!(!policy.allows("http://localhost.evil.com"));
+        assert!(!policy.allows("http://localhost:99999"));
+        assert!(!policy.allows("http://192.168.1.20:3000"));
+
+        let policy = CorsPolicy::new(&["https://UI.example.lan/", " "]);
+        assert!(policy.allows("https://ui.example.lan"));
+        assert!(!policy.allows("https://ui.example.lan:8443"));
+        assert!(!policy.allows_any());
+        assert!(CorsPolicy::new(&["*"]).allows("https://anything.test"));
+    }
+}
@@ -1,5 +1,6 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web::http::header;
+use actix_web::middleware::{from_fn, Compress};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
@@ -8,7 +9,7 @@ use uuid::Uuid;
 
 use chrono::{TimeZone, Utc};
 
-use crate::{ApiError, AppState};
+use crate::{audit, conditional, metrics, user_profiles, ApiError, AppState};
 use crate::resonance::{analyze_resonance, PartnerPersona, ResonanceRequest};
 use crate::readiness::{assess_readiness, ReadinessQuery, ReadinessResponse};
 use crate::export::{ExportData, generate_markdown_report};
@@ -32,6 +33,9 @@ pub struct CounselorScript {
     pub formatted: Option<String>,
     #[serde(default)]
     pub created_at_ms: Option<u128>,
+    /// Profile that saved the script; older entries have none and belong to the default one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -60,6 +64,10 @@ pub struct GriefEvent {
     /// Best-effort temperature reading (Celsius). Not available on all platforms.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub temperature_c: Option<f32>,
+
+    /// Profile that logged the event; older entries have none and belong to the default one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
 }
 
 fn default_intensity() -> u8 {
@@ -232,8 +240,10 @@ This week, focus on {focus}—then translate that into one clear NVC Request."
     (narrative, counts)
 }
 
+/// The active profile's grief events from the last `days` days.
 fn load_recent_events_from_vault(state: &AppState, days: u32, max: usize) -> Vec<GriefEvent> {
     let start = window_start_ms(days);
+    let profile = user_profiles::active(&state.storage);
     // VitalOrganVaults prefixes internal keys by vault type (e.g., "soul:").
     // We store grief events in soul vault keys: counselor:event:{uuid}
     let rows = state
@@ -243,7 +253,7 @@ fn load_recent_events_from_vault(state: &AppState, days: u32, max: usize) -> Vec
     let mut out: Vec<GriefEvent> = Vec::new();
     for (_k, v) in rows {
         if let Ok(e) = serde_json::from_str::<GriefEvent>(&v) {
-            if e.timestamp_ms >= start {
+            if e.timestamp_ms >= start && user_profiles::owns(e.profile.as_deref(), &profile) {
                 out.push(e);
             }
         }
@@ -306,6 +316,7 @@ pub async fn post_grief_event(
 
         system_load: stress.cpu_usage_percent,
         temperature_c: stress.temperature_c,
+        profile: Some(user_profiles::active(&state.storage)),
     };
 
     let key = format!("counselor:event:{id}");
@@ -345,6 +356,7 @@ pub async fn post_script(
     let id = Uuid::new_v4().to_string();
     let created_at = now_ms();
     script.created_at_ms = Some(created_at);
+    script.profile = Some(user_profiles::active(&state.storage));
 
     let key = format!("counselor:script:{id}");
     let json_str = serde_json::to_string(&script)
@@ -554,6 +566,7 @@ pub async fn post_resonate(
     let req = body.into_inner();
     let persona = PartnerPersona::from_loose(&req.persona);
     let result = analyze_resonance(&req.script, persona, req.tone.as_deref());
+    metrics::resonance_score("resonance", result.resonance_score);
     Ok(HttpResponse::Ok().json(result))
 }
 
@@ -562,9 +575,12 @@ pub async fn post_resonate(
 /// Phase 16: Deterministic simulation of the recipient (“Relational Ghost”).
 pub async fn post_ghost_simulate(
     state: web::Data<AppState>,
+    http: HttpRequest,
     body: web::Json<ghost_engine::SimulateRequest>,
 ) -> Result<HttpResponse, ApiError> {
-    let req = body.into_inner();
+    let mut req = body.into_inner();
+    req.language
+        .get_or_insert_with(|| crate::problem::request_language(http.headers()).to_string());
     let resp = ghost_engine::simulate(&state, req).await;
     Ok(HttpResponse::Ok().json(resp))
 }
@@ -594,6 +610,7 @@ pub async fn post_readiness(
 ///
 /// Returns a Markdown report for the last N days.
 pub async fn get_export(
+    req: HttpRequest,
     state: web::Data<AppState>,
     q: web::Query<HashMap<String, String>>,
 ) -> Result<HttpResponse, ApiError> {
@@ -608,11 +625,14 @@ pub async fn get_export(
 
     // NVC scripts (Soul Vault)
     let start = window_start_ms(days);
+    let profile = user_profiles::active(&state.storage);
     let script_rows = state.vaults.recall_prefix("soul:counselor:script:", 1_000);
     let mut scripts: Vec<CounselorScript> = Vec::new();
     for (_k, v) in script_rows {
         if let Ok(s) = serde_json::from_str::<CounselorScript>(&v) {
-            if s.created_at_ms.unwrap_or(0) >= start {
+            if s.created_at_ms.unwrap_or(0) >= start
+                && user_profiles::owns(s.profile.as_deref(), &profile)
+            {
                 scripts.push(s);
             }
         }
@@ -634,6 +654,12 @@ pub async fn get_export(
     readiness.sort_by_key(|r| r.evaluated_at_ms);
     readiness.reverse();
 
+    audit::record(
+        &state,
+        &audit::Actor::of(&req),
+        audit::Action::CounselorExport,
+        json!({ "days": days }),
+    );
     let md = generate_markdown_report(&ExportData {
         window_days: days,
         events,
@@ -737,7 +763,7 @@ pub async fn get_system_stress() -> Result<HttpResponse, ApiError> {
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         // NOTE: This config is registered under the main `/api` scope in
-        // [`main.rs`](phoenix-web/src/main.rs:7209). Therefore we must NOT include `/api`
+        // [`lib.rs`](phoenix-web/src/lib.rs:6924). Therefore we must NOT include `/api`
         // here, otherwise routes become `/api/api/counselor/*`.
         web::scope("/counselor")
             .route("/events", web::post().to(post_grief_event))
@@ -748,8 +774,19 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .route("/resonate", web::post().to(post_resonate))
             .route("/ghost/simulate", web::post().to(post_ghost_simulate))
             .route("/readiness", web::post().to(post_readiness))
-            .route("/export", web::get().to(get_export))
-            .route("/analytics/correlations", web::get().to(get_correlations))
+            // These grow with the stored history (see `conditional`).
+            .service(
+                web::resource("/export")
+                    .wrap(from_fn(conditional::etag))
+                    .wrap(Compress::default())
+                    .route(web::get().to(get_export)),
+            )
+            .service(
+                web::resource("/analytics/correlations")
+                    .wrap(from_fn(conditional::etag))
+                    .wrap(Compress::default())
+                    .route(web::get().to(get_correlations)),
+            )
             .route("/intervention", web::get().to(get_intervention))
             .route("/system-stress", web::get().to(get_system_stress))
             .route(
@@ -0,0 +1,956 @@
+//! Optional sync between the user's own devices of everything but media: profiles with their
+//! settings, emotion history, Ghost sessions and counselor records.
+//!
+//! Each device lists the others' server URLs under `[sync] peers`, and all of them share
+//! `[sync] key`. On `schedule` (every 15 minutes by default) the `sync` job pulls from and
+//! pushes to each peer in turn. A device with the key but no peers only answers.
+//!
+//! Emotion history only grows, so it is merged as a set: devices compare a digest per day and
+//! swap the moments of days that differ. Every other record is a last-writer-wins register
+//! with a vector clock: a version that has seen every write of the other replaces it, and of
+//! two concurrent versions the later write wins (ties go to the greater device id). A device
+//! notices its own changes by hashing each record at the start of a round. Deletions, media,
+//! and the settings file, which is per device, are not synced. The Ghost partner personas are
+//! built in and need no syncing.
+//!
+//! Devices talk over `POST /sync/pull` and `POST /sync/push`, outside `/api`. Every message is
+//! sealed with AES-256-GCM under a key derived from `[sync] key`, which keeps it private and
+//! proves the sender holds the key even over plain HTTP; its send time limits replays. Peers
+//! have to listen on an address the others reach (`server.host`). The device id and the
+//! clocks live in `data/sync.json`, and `GET /api/sync` shows them with each peer's last result.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use std::{fmt, io};
+
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use multi_modal_recording::emotion_history::{self, EmotionalMoment};
+use pagi_config::{parse_list, ConfigError, Layers, Source};
+use pagi_crypto::{DataKey, Sealer};
+use phoenix_storage::Storage;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::full_export::{self, sha256_hex};
+use crate::{full_import, scheduler, user_profiles, ApiError, AppState};
+
+/// The `sync` job's schedule when `[sync] peers` is set.
+pub const DEFAULT_SCHEDULE: &str = "0 */15 * * * *";
+/// The device id and vector clocks, under the data directory.
+pub const STATE_FILE: &str = "data/sync.json";
+
+const MIN_KEY_CHARS: usize = 16;
+const KEY_SALT: &[u8] = b"phoenix-sync-v1";
+const KEY_ROUNDS: u32 = 200_000;
+/// Messages sent longer ago than this, or this far ahead, are refused.
+const MAX_SKEW_SECS: i64 = 5 * 60;
+const MAX_MESSAGE_BYTES: usize = 256 * 1024 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const PROFILE_PREFIX: &str = "profile:";
+const GHOST_SESSION_PREFIX: &str = "ghost_session:";
+const COUNSELOR_PREFIX: &str = "counselor:";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("sync I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Storage(#[from] phoenix_storage::StorageError),
+    #[error(transparent)]
+    Recording(#[from] multi_modal_recording::Error),
+    #[error("sync request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{peer} answered HTTP {status}")]
+    Status { peer: String, status: StatusCode },
+    /// Not sealed with the sync key, malformed, stale, or sent by this device itself.
+    #[error("sync message refused: {0}")]
+    Refused(String),
+}
+
+impl From<SyncError> for ApiError {
+    fn from(e: SyncError) -> Self {
+        match e {
+            SyncError::Refused(_) => ApiError::unauthorized(e.to_string()),
+            _ => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+fn refused(message: impl fmt::Display) -> SyncError {
+    SyncError::Refused(message.to_string())
+}
+
+/// `[sync]` settings.
+#[derive(Clone, Default)]
+pub struct SyncSettings {
+    /// Server URLs of the user's other devices.
+    pub peers: Vec<Url>,
+    /// Shared by all the devices; sync is off without it.
+    pub key: Option<String>,
+    /// Cron expression of the `sync` job; `None` without peers.
+    pub schedule: Option<String>,
+}
+
+impl fmt::Debug for SyncSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncSettings")
+            .field("peers", &self.peers)
+            .field("key", &self.key.as_ref().map(|_| "********"))
+            .field("schedule", &self.schedule)
+            .finish()
+    }
+}
+
+impl SyncSettings {
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let peers: Vec<Url> = layers.or("sync.peers", Vec::new(), |s| {
+            parse_list(s)
+                .iter()
+                .map(|peer| Url::parse(peer).map_err(|e| format!("{peer}: {e}")))
+                .collect()
+        })?;
+        let key = layers.secret("sync.key")?;
+        let invalid = |message: String| ConfigError::Invalid {
+            key: "sync.key",
+            origin: layers
+                .get("sync.key")
+                .map_or(Source::Default, |(_, source)| source),
+            message,
+        };
+        match &key {
+            None if !peers.is_empty() => {
+                return Err(invalid("required with sync.peers".to_string()));
+            }
+            Some(key) if key.chars().count() < MIN_KEY_CHARS => {
+                return Err(invalid(format!(
+                    "must be at least {MIN_KEY_CHARS} characters"
+                )));
+            }
+            _ => {}
+        }
+        let schedule = if peers.is_empty() {
+            None
+        } else {
+            layers.or(
+                "sync.schedule",
+                Some(DEFAULT_SCHEDULE.to_string()),
+                scheduler::parse_schedule,
+            )?
+        };
+        Ok(Self {
+            peers,
+            key,
+            schedule,
+        })
+    }
+}
+
+/// Writes seen per device.
+type Clock = BTreeMap<String, u64>;
+
+/// Whether `newer` has seen every write `older` has.
+fn descends(newer: &Clock, older: &Clock) -> bool {
+    older
+        .iter()
+        .all(|(device, n)| newer.get(device).is_some_and(|m| m >= n))
+}
+
+fn merged(a: &Clock, b: &Clock) -> Clock {
+    let mut clock = a.clone();
+    for (device, n) in b {
+        let m = clock.entry(device.clone()).or_default();
+        *m = (*m).max(*n);
+    }
+    clock
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Version {
+    clock: Clock,
+    /// The device that wrote this version and when; decides between concurrent ones.
+    writer: String,
+    written_unix: i64,
+}
+
+impl Version {
+    fn wins_over(&self, other: &Version) -> bool {
+        (self.written_unix, &self.writer) > (other.written_unix, &other.writer)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Tracked {
+    #[serde(flatten)]
+    version: Version,
+    /// Of the value as this device last saw it.
+    hash: String,
+}
+
+/// How the last round with a peer went.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerStatus {
+    pub last_sync_unix: i64,
+    pub ok: bool,
+    /// What was exchanged, or why it failed.
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedState {
+    device: String,
+    records: BTreeMap<String, Tracked>,
+    #[serde(default)]
+    peers: BTreeMap<String, PeerStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Record {
+    key: String,
+    #[serde(flatten)]
+    version: Version,
+    value: Value,
+}
+
+/// Every message between devices; each leg fills in what it needs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Message {
+    device: String,
+    sent_unix: i64,
+    /// The sender's vector clock of every record it holds.
+    #[serde(default)]
+    clocks: BTreeMap<String, Clock>,
+    /// Digest of the sender's emotion history per day since the epoch.
+    #[serde(default)]
+    emotion_days: BTreeMap<i64, String>,
+    #[serde(default)]
+    records: Vec<Record>,
+    #[serde(default)]
+    moments: Vec<EmotionalMoment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileValue {
+    display_name: String,
+    settings: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GhostSessionValue {
+    profile: String,
+    started_unix: i64,
+    load_start: u8,
+    ended_unix: Option<i64>,
+    load_end: Option<u8>,
+}
+
+fn value_hash(value: &Value) -> String {
+    sha256_hex(value.to_string().as_bytes())
+}
+
+fn day(moment: &EmotionalMoment) -> i64 {
+    moment.ts_unix.div_euclid(86_400)
+}
+
+fn day_digests(moments: &[EmotionalMoment]) -> BTreeMap<i64, String> {
+    let mut days: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+    for moment in moments {
+        if let Ok(line) = serde_json::to_string(moment) {
+            days.entry(day(moment)).or_default().push(line);
+        }
+    }
+    days.into_iter()
+        .map(|(day, mut lines)| {
+            lines.sort();
+            (day, sha256_hex(lines.join("\n").as_bytes()))
+        })
+        .collect()
+}
+
+/// Moments on the days whose digest in `ours` differs from `theirs`.
+fn moments_missing(
+    moments: Vec<EmotionalMoment>,
+    ours: &BTreeMap<i64, String>,
+    theirs: &BTreeMap<i64, String>,
+) -> Vec<EmotionalMoment> {
+    let days: BTreeSet<i64> = ours
+        .iter()
+        .filter(|(day, digest)| theirs.get(*day) != Some(*digest))
+        .map(|(day, _)| *day)
+        .collect();
+    moments
+        .into_iter()
+        .filter(|m| days.contains(&day(m)))
+        .collect()
+}
+
+/// Records the holder of `theirs` hasn't seen every write of.
+fn unseen(
+    state: &SavedState,
+    values: &BTreeMap<String, Value>,
+    theirs: &BTreeMap<String, Clock>,
+) -> Vec<Record> {
+    state
+        .records
+        .iter()
+        .filter(|(key, tracked)| {
+            theirs
+                .get(*key)
+                .is_none_or(|clock| !descends(clock, &tracked.version.clock))
+        })
+        .filter_map(|(key, tracked)| {
+            Some(Record {
+                key: key.clone(),
+                version: tracked.version.clone(),
+                value: values.get(key)?.clone(),
+            })
+        })
+        .collect()
+}
+
+/// The sync layer of this device; `None` in [`AppState`] when `[sync] key` isn't set.
+pub struct DeviceSync {
+    device: String,
+    path: PathBuf,
+    /// Seals the state file like the other stores.
+    sealer: Sealer,
+    /// Seals messages between devices.
+    channel: Sealer,
+    peers: Vec<Url>,
+    storage: Storage,
+    vaults: Arc<VitalOrganVaults>,
+    state: Mutex<SavedState>,
+}
+
+impl DeviceSync {
+    /// Load the state from `path` (sealed with `sealer`), or start as a new device.
+    pub fn open(
+        path: PathBuf,
+        sealer: Sealer,
+        settings: &SyncSettings,
+        storage: Storage,
+        vaults: Arc<VitalOrganVaults>,
+    ) -> Option<Self> {
+        let key = settings.key.as_ref()?;
+        let mut derived = [0u8; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(KEY_ROUNDS).expect("non-zero rounds"),
+            KEY_SALT,
+            key.as_bytes(),
+            &mut derived,
+        );
+        let mut state: SavedState = match std::fs::read(&path) {
+            Ok(raw) => sealer
+                .open(&raw)
+                .map_err(|e| e.to_string())
+                .and_then(|raw| serde_json::from_slice(&raw).map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring unreadable {}: {e}", path.display());
+                    SavedState::default()
+                }),
+            Err(_) => SavedState::default(),
+        };
+        if state.device.is_empty() {
+            state.device = uuid::Uuid::new_v4().simple().to_string();
+        }
+        Some(Self {
+            device: state.device.clone(),
+            path,
+            sealer,
+            channel: Sealer::new(DataKey::from_bytes(derived)),
+            peers: settings.peers.clone(),
+            storage,
+            vaults,
+            state: Mutex::new(state),
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, SavedState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save(&self, state: &SavedState) {
+        let write = || -> io::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            let json = serde_json::to_vec_pretty(state)?;
+            std::fs::write(&tmp, self.sealer.seal(&json).map_err(io::Error::other)?)?;
+            std::fs::rename(tmp, &self.path)
+        };
+        if let Err(e) = write() {
+            warn!("Cannot save {}: {e}", self.path.display());
+        }
+    }
+
+    /// Every synced record on this device, by key.
+    fn local_records(&self) -> Result<BTreeMap<String, Value>, SyncError> {
+        let mut records = BTreeMap::new();
+        for profile in self.storage.profiles()? {
+            let value = ProfileValue {
+                display_name: profile.display_name,
+                settings: serde_json::from_str(&profile.settings).unwrap_or(json!({})),
+            };
+            records.insert(format!("{PROFILE_PREFIX}{}", profile.id), json!(value));
+        }
+        for session in self.storage.ghost_sessions_since(i64::MIN, None)? {
+            let value = GhostSessionValue {
+                profile: session.profile,
+                started_unix: session.started_unix,
+                load_start: session.load_start,
+                ended_unix: session.ended_unix,
+                load_end: session.load_end,
+            };
+            records.insert(
+                format!("{GHOST_SESSION_PREFIX}{}", session.id),
+                json!(value),
+            );
+        }
+        let counselor = self
+            .vaults
+            .recall_prefix(&format!("soul:{COUNSELOR_PREFIX}"), usize::MAX);
+        for (key, value) in counselor {
+            records.insert(key, full_export::parsed(value));
+        }
+        Ok(records)
+    }
+
+    /// Count a write by this device on every record that changed since the last round; returns
+    /// the records.
+    fn refresh(&self, state: &mut SavedState) -> Result<BTreeMap<String, Value>, SyncError> {
+        let values = self.local_records()?;
+        let now = Utc::now().timestamp();
+        for (key, value) in &values {
+            let hash = value_hash(value);
+            let tracked = state.records.entry(key.clone()).or_insert_with(|| Tracked {
+                version: Version::default(),
+                hash: String::new(),
+            });
+            if tracked.hash != hash {
+                *tracked
+                    .version
+                    .clock
+                    .entry(self.device.clone())
+                    .or_default() += 1;
+                tracked.version.writer = self.device.clone();
+                tracked.version.written_unix = now;
+                tracked.hash = hash;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Write a record from a peer; `false` when it can't be used here.
+    fn apply(&self, key: &str, value: &Value) -> Result<bool, SyncError> {
+        if let Some(id) = key.strip_prefix(PROFILE_PREFIX) {
+            let Ok(profile) = ProfileValue::deserialize(value) else {
+                return Ok(false);
+            };
+            let (Ok(id), Ok(name)) = (
+                user_profiles::valid_id(id),
+                user_profiles::valid_display_name(&profile.display_name),
+            ) else {
+                return Ok(false);
+            };
+            let now = Utc::now().timestamp();
+            if !self.storage.create_profile(id, name, now)? {
+                self.storage.rename_profile(id, name)?;
+            }
+            self.storage
+                .set_profile_settings(id, &profile.settings.to_string())?;
+        } else if let Some(id) = key.strip_prefix(GHOST_SESSION_PREFIX) {
+            let Ok(session) = GhostSessionValue::deserialize(value) else {
+                return Ok(false);
+            };
+            self.storage.start_ghost_session(
+                id,
+                &session.profile,
+                session.started_unix,
+                session.load_start,
+            )?;
+            if let (Some(ended), Some(load)) = (session.ended_unix, session.load_end) {
+                self.storage.end_ghost_session(id, ended, load)?;
+            }
+        } else if key.starts_with(COUNSELOR_PREFIX) {
+            self.vaults
+                .store_soul(key, &full_import::stored_text(value))
+                .map_err(io::Error::other)?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Merge what a peer sent; returns how many records and moments changed here.
+    fn merge(
+        &self,
+        state: &mut SavedState,
+        records: Vec<Record>,
+        moments: &[EmotionalMoment],
+    ) -> Result<usize, SyncError> {
+        let mut changed = 0;
+        for record in records {
+            let hash = value_hash(&record.value);
+            let mut version = record.version;
+            if let Some(local) = state.records.get_mut(&record.key) {
+                if descends(&local.version.clock, &version.clock) {
+                    continue;
+                }
+                let concurrent = !descends(&version.clock, &local.version.clock);
+                version.clock = merged(&local.version.clock, &version.clock);
+                if concurrent && !version.wins_over(&local.version) {
+                    // Keep this value, now a descendant of both.
+                    local.version.clock = version.clock;
+                    continue;
+                }
+                if local.hash == hash {
+                    local.version = version;
+                    continue;
+                }
+            }
+            if !self.apply(&record.key, &record.value)? {
+                warn!(target: "sync", key = %record.key, "unusable record from a peer skipped");
+                continue;
+            }
+            changed += 1;
+            state.records.insert(record.key, Tracked { version, hash });
+        }
+        changed += emotion_history::merge_moments(&self.vaults, moments, false)?;
+        Ok(changed)
+    }
+
+    fn message(&self) -> Message {
+        Message {
+            device: self.device.clone(),
+            sent_unix: Utc::now().timestamp(),
+            ..Message::default()
+        }
+    }
+
+    fn seal(&self, message: &Message) -> Result<Vec<u8>, SyncError> {
+        let json = serde_json::to_vec(message).map_err(io::Error::other)?;
+        Ok(self.channel.seal(&json).map_err(io::Error::other)?)
+    }
+
+    fn open_message(&self, data: &[u8]) -> Result<Message, SyncError> {
+        // `Sealer::open` passes unsealed data through; a message must be sealed.
+        if !pagi_crypto::is_sealed(data) {
+            return Err(refused("not sealed"));
+        }
+        let plain = self
+            .channel
+            .open(data)
+            .map_err(|_| refused("not sealed with this sync key"))?;
+        let message: Message = serde_json::from_slice(&plain).map_err(refused)?;
+        if (Utc::now().timestamp() - message.sent_unix).abs() > MAX_SKEW_SECS {
+            return Err(refused("sent too long ago; check both devices' clocks"));
+        }
+        if message.device == self.device {
+            return Err(refused("sent by this device itself"));
+        }
+        Ok(message)
+    }
+
+    /// This device's clocks and emotion digests, the opening of a round.
+    fn pull_request(&self) -> Result<Message, SyncError> {
+        let mut state = self.state();
+        self.refresh(&mut state)?;
+        let moments = emotion_history::full_history(&self.vaults);
+        self.save(&state);
+        Ok(Message {
+            clocks: clocks(&state),
+            emotion_days: day_digests(&moments),
+            ..self.message()
+        })
+    }
+
+    /// Answer a pull with what the peer lacks, and this device's clocks and digests so it can
+    /// push back what this device lacks.
+    fn answer_pull(&self, pull: Message) -> Result<Message, SyncError> {
+        let mut state = self.state();
+        let values = self.refresh(&mut state)?;
+        let moments = emotion_history::full_history(&self.vaults);
+        let emotion_days = day_digests(&moments);
+        let reply = Message {
+            records: unseen(&state, &values, &pull.clocks),
+            moments: moments_missing(moments, &emotion_days, &pull.emotion_days),
+            clocks: clocks(&state),
+            emotion_days,
+            ..self.message()
+        };
+        self.save(&state);
+        Ok(reply)
+    }
+
+    /// Merge a pull's answer; returns the push that follows and how much changed here.
+    fn absorb(&self, reply: Message) -> Result<(Message, usize), SyncError> {
+        let mut state = self.state();
+        self.refresh(&mut state)?;
+        let received = self.merge(&mut state, reply.records, &reply.moments)?;
+        let values = self.local_records()?;
+        let moments = emotion_history::full_history(&self.vaults);
+        let emotion_days = day_digests(&moments);
+        let push = Message {
+            records: unseen(&state, &values, &reply.clocks),
+            moments: moments_missing(moments, &emotion_days, &reply.emotion_days),
+            ..self.message()
+        };
+        self.save(&state);
+        Ok((push, received))
+    }
+
+    fn answer_push(&self, push: Message) -> Result<Message, SyncError> {
+        let mut state = self.state();
+        self.refresh(&mut state)?;
+        self.merge(&mut state, push.records, &push.moments)?;
+        self.save(&state);
+        Ok(self.message())
+    }
+
+    /// Answer a sealed message from a peer with a sealed reply.
+    fn answer(&self, body: &[u8], push: bool) -> Result<Vec<u8>, SyncError> {
+        let message = self.open_message(body)?;
+        let reply = if push {
+            self.answer_push(message)?
+        } else {
+            self.answer_pull(message)?
+        };
+        self.seal(&reply)
+    }
+
+    async fn exchange(
+        &self,
+        http: &reqwest::Client,
+        peer: &Url,
+        path: &str,
+        message: Message,
+    ) -> Result<Message, SyncError> {
+        let url = peer.join(path).map_err(refused)?;
+        let body = self.seal(&message)?;
+        let response = http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(SyncError::Status {
+                peer: peer.to_string(),
+                status,
+            });
+        }
+        let data = response.bytes().await?;
+        self.open_message(&data)
+    }
+
+    /// One round with `peer`: pull, merge, push. Returns how much changed here and how much
+    /// was sent.
+    async fn sync_with(
+        self: &Arc<Self>,
+        http: &reqwest::Client,
+        peer: &Url,
+    ) -> Result<(usize, usize), SyncError> {
+        let this = self.clone();
+        let pull = blocking(move || this.pull_request()).await?;
+        let reply = self.exchange(http, peer, "/sync/pull", pull).await?;
+        let this = self.clone();
+        let (push, received) = blocking(move || this.absorb(reply)).await?;
+        let sent = push.records.len() + push.moments.len();
+        self.exchange(http, peer, "/sync/push", push).await?;
+        Ok((received, sent))
+    }
+
+    fn record_peer(&self, peer: &Url, status: PeerStatus) {
+        let mut state = self.state();
+        state.peers.insert(peer.to_string(), status);
+        self.save(&state);
+    }
+
+    fn status(&self) -> Value {
+        let state = self.state();
+        let peers: Vec<Value> = self
+            .peers
+            .iter()
+            .map(|peer| json!({ "url": peer, "last_sync": state.peers.get(peer.as_str()) }))
+            .collect();
+        json!({
+            "device": self.device,
+            "peers": peers,
+            "records": state.records.len(),
+        })
+    }
+}
+
+fn clocks(state: &SavedState) -> BTreeMap<String, Clock> {
+    state
+        .records
+        .iter()
+        .map(|(key, tracked)| (key.clone(), tracked.version.clock.clone()))
+        .collect()
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, SyncError> + Send + 'static,
+) -> Result<T, SyncError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// The `sync` job: a round with every peer.
+pub(crate) async fn run(state: &AppState) -> Result<String, String> {
+    let Some(sync) = state.device_sync.clone() else {
+        return Err("sync is off; set sync.key and sync.peers".to_string());
+    };
+    let http = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let (mut done, mut failed) = (Vec::new(), Vec::new());
+    for peer in &sync.peers {
+        let result = sync.sync_with(&http, peer).await;
+        let (ok, message) = match &result {
+            Ok((received, sent)) => (true, format!("{received} changes received, {sent} sent")),
+            Err(e) => {
+                warn!(target: "sync", %peer, "sync failed: {e}");
+                (false, e.to_string())
+            }
+        };
+        let line = format!("{peer}: {message}");
+        sync.record_peer(
+            peer,
+            PeerStatus {
+                last_sync_unix: Utc::now().timestamp(),
+                ok,
+                message,
+            },
+        );
+        if ok {
+            done.push(line);
+        } else {
+            failed.push(line);
+        }
+    }
+    if failed.is_empty() {
+        Ok(done.join("; "))
+    } else {
+        failed.extend(done);
+        Err(failed.join("; "))
+    }
+}
+
+async fn post_message(
+    state: web::Data<AppState>,
+    body: web::Bytes,
+    push: bool,
+) -> Result<HttpResponse, ApiError> {
+    let sync = state
+        .device_sync
+        .clone()
+        .ok_or_else(|| ApiError::not_found("sync is off on this device; set sync.key"))?;
+    let reply = web::block(move || sync.answer(&body, push))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))??;
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .body(reply))
+}
+
+/// POST /sync/pull
+async fn post_pull(state: web::Data<AppState>, body: web::Bytes) -> Result<HttpResponse, ApiError> {
+    post_message(state, body, false).await
+}
+
+/// POST /sync/push
+async fn post_push(state: web::Data<AppState>, body: web::Bytes) -> Result<HttpResponse, ApiError> {
+    post_message(state, body, true).await
+}
+
+/// GET /api/sync
+async fn get_status(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let sync = state
+        .device_sync
+        .as_ref()
+        .ok_or_else(|| ApiError::not_found("sync is off; set sync.key"))?;
+    Ok(HttpResponse::Ok().json(sync.status()))
+}
+
+/// Routes peers call, outside `/api`: the sync key is their credential.
+pub fn configure_peer_routes(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::PayloadConfig::new(MAX_MESSAGE_BYTES))
+        .service(web::resource("/pull").route(web::post().to(post_pull)))
+        .service(web::resource("/push").route(web::post().to(post_push)));
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(web::resource("/sync").route(web::get().to(get_status)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Device {
+        dir: PathBuf,
+        sync: DeviceSync,
+    }
+
+    impl Device {
+        fn new(key: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("phoenix-device-sync-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let settings = SyncSettings {
+                key: Some(key.to_string()),
+                ..SyncSettings::default()
+            };
+            let sync = DeviceSync::open(
+                dir.join(STATE_FILE),
+                Sealer::default(),
+                &settings,
+                Storage::open_sqlite(&dir.join("phoenix.db"), 1).unwrap(),
+                Arc::new(VitalOrganVaults::awaken_in(&dir.join("vaults"))),
+            )
+            .unwrap();
+            Self { dir, sync }
+        }
+
+        fn settings(&self, id: &str) -> Value {
+            let profile = self.sync.storage.profile(id).unwrap().unwrap();
+            serde_json::from_str(&profile.settings).unwrap()
+        }
+
+        /// A whole round started by `self`, without HTTP.
+        fn sync_with(&self, peer: &Device) {
+            let pull = self.sync.seal(&self.sync.pull_request().unwrap()).unwrap();
+            let reply = self
+                .sync
+                .open_message(&peer.sync.answer(&pull, false).unwrap())
+                .unwrap();
+            let (push, _) = self.sync.absorb(reply).unwrap();
+            let push = self.sync.seal(&push).unwrap();
+            peer.sync.answer(&push, true).unwrap();
+        }
+    }
+
+    impl Drop for Device {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn moment(ts_unix: i64, emotion: &str) -> EmotionalMoment {
+        EmotionalMoment {
+            ts_unix,
+            emotion: emotion.to_string(),
+            intensity: 0.5,
+            confidence: 0.8,
+            voice_contribution: 1.0,
+            face_contribution: 0.0,
+            text_contribution: 0.0,
+            recording: String::new(),
+            profile: None,
+            valence: None,
+            arousal: None,
+        }
+    }
+
+    #[test]
+    fn devices_converge_and_the_later_concurrent_write_wins() {
+        let key = "correct horse battery staple";
+        let (desktop, laptop) = (Device::new(key), Device::new(key));
+        desktop
+            .sync
+            .storage
+            .create_profile("ana", "Ana", 1)
+            .unwrap();
+        desktop
+            .sync
+            .storage
+            .start_ghost_session("s1", "ana", 100, 7)
+            .unwrap();
+        let history = |device: &Device, moments: &[EmotionalMoment]| {
+            emotion_history::merge_moments(&device.sync.vaults, moments, false).unwrap();
+        };
+        history(&desktop, &[moment(86_400, "Joy")]);
+        history(&laptop, &[moment(86_401, "Sadness")]);
+
+        desktop.sync_with(&laptop);
+        let records = laptop.sync.local_records().unwrap();
+        assert!(records.contains_key("profile:ana") && records.contains_key("ghost_session:s1"));
+        assert_eq!(desktop.sync.local_records().unwrap(), records);
+        for device in [&desktop, &laptop] {
+            assert_eq!(emotion_history::full_history(&device.sync.vaults).len(), 2);
+        }
+
+        // Both edit the profile before the next round; the laptop writes last.
+        desktop
+            .sync
+            .storage
+            .set_profile_settings("ana", r#"{"theme":"dark"}"#)
+            .unwrap();
+        desktop.sync.pull_request().unwrap();
+        laptop
+            .sync
+            .storage
+            .set_profile_settings("ana", r#"{"theme":"light"}"#)
+            .unwrap();
+        laptop.sync.pull_request().unwrap();
+        laptop
+            .sync
+            .state()
+            .records
+            .get_mut("profile:ana")
+            .unwrap()
+            .version
+            .written_unix += 60;
+
+        desktop.sync_with(&laptop);
+        desktop.sync_with(&laptop);
+        assert_eq!(desktop.settings("ana"), json!({"theme": "light"}));
+        assert_eq!(laptop.settings("ana"), json!({"theme": "light"}));
+        let clocks = |device: &Device| clocks(&device.sync.state());
+        assert_eq!(clocks(&desktop), clocks(&laptop));
+    }
+
+    #[test]
+    fn refuses_messages_without_the_key() {
+        let device = Device::new("correct horse battery staple");
+        let stranger = Device::new("another sixteen-char key");
+        let pull = stranger.sync.seal(&stranger.sync.message()).unwrap();
+        assert!(matches!(
+            device.sync.answer(&pull, false),
+            Err(SyncError::Refused(_))
+        ));
+        let plain = serde_json::to_vec(&stranger.sync.message()).unwrap();
+        assert!(matches!(
+            device.sync.answer(&plain, true),
+            Err(SyncError::Refused(_))
+        ));
+        let own = device.sync.seal(&device.sync.message()).unwrap();
+        assert!(matches!(
+            device.sync.answer(&own, false),
+            Err(SyncError::Refused(_))
+        ));
+
+        assert!(descends(
+            &Clock::from([("a".into(), 2)]),
+            &Clock::from([("a".into(), 1)])
+        ));
+        assert!(!descends(
+            &Clock::from([("a".into(), 2)]),
+            &Clock::from([("b".into(), 1)])
+        ));
+    }
+}
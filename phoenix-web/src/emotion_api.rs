@@ -0,0 +1,285 @@
+//! Live emotion relay + text emotion analysis.
+//!
+//! Capture processes (e.g. the desktop recorder) POST each new estimate here; it is relayed to
+//! every WebSocket connection subscribed to the `emotion` topic. Text (journal entries,
+//! transcripts) can be analyzed server-side so emotion history isn't limited to audio/video.
+//! The persisted history is queryable by time range, emotion, profile, and modality; reads
+//! default to the active profile and `profile=*` spans everyone. A background job writes an
+//! end-of-day mood summary that the dashboard route surfaces. Charts read pre-bucketed trends
+//! rather than raw samples.
+
+use actix_web::http::StatusCode;
+use actix_web::middleware::{from_fn, Compress};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDate, Utc};
+use emotion_detection::text::{analyze_text, TextSource};
+use multi_modal_recording::emotion_export::{self, ExportFormat};
+use multi_modal_recording::emotion_history::{
+    self, EmotionQuery, EmotionSource, EmotionUpdate, EmotionalMoment,
+};
+use multi_modal_recording::emotion_privacy::EmotionPrivacy;
+use multi_modal_recording::emotion_trends::{self, TrendQuery};
+use multi_modal_recording::mood_summary;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::info;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::audit::{self, Action, Actor};
+use crate::proactive::ProactiveMessage;
+use crate::{compute_cache, conditional, user_profiles, ApiError, AppState};
+
+/// How often the mood-summary job checks whether a summary is due.
+const MOOD_SUMMARY_CHECK_SECS: u64 = 15 * 60;
+
+async fn post_emotion_event(
+    state: web::Data<AppState>,
+    body: web::Json<EmotionUpdate>,
+) -> Result<HttpResponse, ApiError> {
+    let update = body.into_inner();
+    if !(0.0..=1.0).contains(&update.moment.confidence) {
+        return Err(ApiError::bad_request("confidence must be within 0..=1"));
+    }
+    // No subscribers is not an error; the update is simply dropped.
+    let delivered = state.emotion_tx.send(update).unwrap_or(0);
+    Ok(HttpResponse::Ok().json(json!({ "success": true, "delivered": delivered })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextEmotionRequest {
+    pub text: String,
+    #[serde(default = "default_text_source")]
+    pub source: TextSource,
+    /// Where the text came from (e.g. `journal:<id>`); stored with the moment.
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// When false, only return the estimate without recording it.
+    #[serde(default = "default_true")]
+    pub record: bool,
+}
+
+fn default_text_source() -> TextSource {
+    TextSource::Other
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// POST /api/emotion/text
+async fn post_text_emotion(
+    state: web::Data<AppState>,
+    body: web::Json<TextEmotionRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let req = body.into_inner();
+    if req.text.trim().is_empty() {
+        return Err(ApiError::bad_request("text must not be empty"));
+    }
+    if !state.toggles.emotion_inference() {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "emotion inference is switched off",
+        ));
+    }
+    let estimate = analyze_text(&req.text, req.source);
+    let profile = Some(
+        req.profile
+            .unwrap_or_else(|| user_profiles::active(&state.storage)),
+    );
+    let allowed = EmotionPrivacy::load(&state.vaults).inference_allowed(profile.as_deref());
+    if !allowed {
+        return Err(ApiError::bad_request(
+            "emotion inference is disabled for this profile",
+        ));
+    }
+    if req.record {
+        let reference = req
+            .reference
+            .unwrap_or_else(|| format!("text:{:?}", req.source).to_ascii_lowercase());
+        let moment =
+            EmotionalMoment::from_state(&estimate.to_state(), Path::new(&reference), profile);
+        emotion_history::append_moment(&state.vaults, &moment);
+        state.cache.trends.clear();
+        let _ = state.emotion_tx.send(EmotionUpdate::new(moment));
+    }
+    Ok(HttpResponse::Ok().json(json!({ "success": true, "estimate": estimate })))
+}
+
+/// GET /api/emotion/history?from_unix=&to_unix=&emotion=&profile=&modality=&limit=
+async fn get_emotion_history(
+    state: web::Data<AppState>,
+    query: web::Query<EmotionQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut q = query.into_inner();
+    q.profile = user_profiles::scope(&state.storage, q.profile);
+    if let (Some(from), Some(to)) = (q.from_unix, q.to_unix) {
+        if from > to {
+            return Err(ApiError::bad_request("from_unix must not be after to_unix"));
+        }
+    }
+    let moments = emotion_history::query_moments(&state.vaults, &q);
+    Ok(HttpResponse::Ok().json(json!({ "count": moments.len(), "moments": moments })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub from_unix: Option<i64>,
+    #[serde(default)]
+    pub to_unix: Option<i64>,
+    #[serde(default)]
+    pub emotion: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub modality: Option<EmotionSource>,
+}
+
+/// GET /api/emotion/export?format=csv|json&from_unix=&to_unix=&profile=&emotion=&modality=
+///
+/// Profiles that opted into export redaction are left out.
+async fn get_emotion_export(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let q = query.into_inner();
+    if let (Some(from), Some(to)) = (q.from_unix, q.to_unix) {
+        if from > to {
+            return Err(ApiError::bad_request("from_unix must not be after to_unix"));
+        }
+    }
+    let filter = EmotionQuery {
+        from_unix: q.from_unix,
+        to_unix: q.to_unix,
+        emotion: q.emotion,
+        profile: user_profiles::scope(&state.storage, q.profile),
+        modality: q.modality,
+        limit: None,
+    };
+    let (doc, _) = emotion_export::export(&state.vaults, &filter, q.format)?;
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::EmotionExport,
+        json!({
+            "format": q.format.extension(),
+            "from_unix": filter.from_unix,
+            "to_unix": filter.to_unix,
+            "emotion": filter.emotion,
+            "profile": filter.profile,
+            "modality": filter.modality,
+        }),
+    );
+    let filename = format!(
+        "emotion-history-{}.{}",
+        Local::now().format("%Y%m%d"),
+        q.format.extension()
+    );
+    Ok(HttpResponse::Ok()
+        .content_type(q.format.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{filename}\""),
+        ))
+        .body(doc))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    /// Number of stored daily summaries to include (default 7).
+    #[serde(default)]
+    pub days: Option<usize>,
+    /// Summarize this date live instead of today (`YYYY-MM-DD`).
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+}
+
+/// GET /api/emotion/dashboard
+async fn get_emotion_dashboard(
+    state: web::Data<AppState>,
+    query: web::Query<DashboardQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let q = query.into_inner();
+    let date = q.date.unwrap_or_else(|| Local::now().date_naive());
+    let current = mood_summary::load(&state.vaults, date)
+        .unwrap_or_else(|| mood_summary::compute(&state.vaults, date));
+    let daily = mood_summary::recent(&state.vaults, q.days.unwrap_or(7).min(90));
+    Ok(HttpResponse::Ok().json(json!({ "current": current, "daily_summaries": daily })))
+}
+
+/// GET /api/emotion/trends?bucket=hour|day|week&from_unix=&to_unix=&profile=
+///
+/// Bucketed distributions and average valence/arousal, with the previous period's totals.
+async fn get_emotion_trends(
+    state: web::Data<AppState>,
+    query: web::Query<TrendQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut q = query.into_inner();
+    q.profile = user_profiles::scope(&state.storage, q.profile);
+    let key = compute_cache::trends_key(&q);
+    if let Some(trends) = state.cache.trends.get(&key) {
+        return Ok(HttpResponse::Ok().json(trends));
+    }
+    let trends = emotion_trends::compute(&state.vaults, &q, Utc::now().timestamp())
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    state.cache.trends.insert(key, trends.clone());
+    Ok(HttpResponse::Ok().json(trends))
+}
+
+/// Background job: store the end-of-day mood summary once it is due and announce it to
+/// connected clients as a proactive message.
+pub async fn run_mood_summary_loop(
+    vaults: Arc<VitalOrganVaults>,
+    tx: broadcast::Sender<ProactiveMessage>,
+) {
+    info!("Mood summary loop started");
+    loop {
+        if let Some(summary) = mood_summary::generate_due(&vaults, Local::now()) {
+            info!("Stored mood summary for {}", summary.date);
+            let _ = tx.send(ProactiveMessage {
+                content: summary.headline(),
+                reason: "mood_summary".to_string(),
+                timestamp: Utc::now().timestamp(),
+            });
+        }
+        tokio::time::sleep(Duration::from_secs(MOOD_SUMMARY_CHECK_SECS)).await;
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/emotion")
+            .route("/events", web::post().to(post_emotion_event))
+            .route("/text", web::post().to(post_text_emotion))
+            // These grow with the stored history (see `conditional`).
+            .service(
+                web::resource("/history")
+                    .wrap(from_fn(conditional::etag))
+                    .wrap(Compress::default())
+                    .route(web::get().to(get_emotion_history)),
+            )
+            .service(
+                web::resource("/export")
+                    .wrap(from_fn(conditional::etag))
+                    .wrap(Compress::default())
+                    .route(web::get().to(get_emotion_export)),
+            )
+            .service(
+                web::resource("/trends")
+                    .wrap(from_fn(conditional::etag))
+                    .wrap(Compress::default())
+                    .route(web::get().to(get_emotion_trends)),
+            )
+            .route("/dashboard", web::get().to(get_emotion_dashboard)),
+    );
+}
@@ -0,0 +1,182 @@
+//! Techno-somatic stress signal over HTTP, for dashboards and the switchboard.
+//!
+//! - `GET /api/env/stress`: the latest sample from the background sampler (see
+//!   [`crate::live_events`]), or a fresh one when the sampler hasn't produced a recent one.
+//! - `GET /api/env/stress/history?window=1h`: every sample of the window, oldest first, with a
+//!   summary. `window` is a number of seconds or a number with an `s`, `m`, `h` or `d` suffix,
+//!   up to the 24 hours the samples are kept; the default is one hour.
+//!
+//! Samples carry `cpu_usage_percent`, `temperature_c` when the hardware reports one, and the
+//! unix `timestamp` they were taken at; the same shape as the WebSocket `stress` events.
+
+use std::time::Duration;
+
+use actix_web::middleware::Compress;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::env_sensor;
+use crate::live_events::{StressReading, STRESS_HISTORY_RETENTION};
+use crate::{ApiError, AppState};
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// A sample older than this is not "current"; the sampler may be stuck or not started yet.
+const MAX_SAMPLE_AGE_SECS: i64 = 60;
+
+/// `30`, `90s`, `15m`, `1h`, `1d`.
+fn parse_window(raw: &str) -> Result<Duration, String> {
+    let raw = raw.trim();
+    let (digits, unit) = match raw.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&raw[..i], c.to_ascii_lowercase()),
+        _ => (raw, 's'),
+    };
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(format!("window `{raw}` must end in s, m, h or d")),
+    };
+    let secs = digits
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("window `{raw}` must be a positive duration such as 15m or 1h"))?;
+    if secs > STRESS_HISTORY_RETENTION.as_secs() {
+        return Err(format!(
+            "window must be at most {}h; older samples are not kept",
+            STRESS_HISTORY_RETENTION.as_secs() / 3600
+        ));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HistoryQuery {
+    #[serde(default)]
+    window: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct StressSummary {
+    samples: usize,
+    cpu_avg_percent: Option<f32>,
+    cpu_max_percent: Option<u8>,
+    temperature_max_c: Option<f32>,
+}
+
+fn summarize(samples: &[StressReading]) -> StressSummary {
+    let cpu = samples.iter().map(|r| r.stress.cpu_usage_percent);
+    StressSummary {
+        samples: samples.len(),
+        cpu_avg_percent: (!samples.is_empty())
+            .then(|| cpu.clone().map(f32::from).sum::<f32>() / samples.len() as f32),
+        cpu_max_percent: cpu.max(),
+        temperature_max_c: samples
+            .iter()
+            .filter_map(|r| r.stress.temperature_c)
+            .reduce(f32::max),
+    }
+}
+
+/// GET /api/env/stress
+async fn get_stress(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let now = Utc::now().timestamp();
+    let reading = match state.live.latest_stress() {
+        Some(reading) if now - reading.timestamp <= MAX_SAMPLE_AGE_SECS => reading,
+        _ => {
+            let stress = web::block(env_sensor::get_system_stress)
+                .await
+                .map_err(|e| ApiError::internal(format!("Stress sample failed: {e}")))?;
+            StressReading {
+                stress,
+                timestamp: Utc::now().timestamp(),
+            }
+        }
+    };
+    Ok(HttpResponse::Ok().json(reading))
+}
+
+/// GET /api/env/stress/history?window=1h
+async fn get_stress_history(
+    state: web::Data<AppState>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let window = match query.window.as_deref() {
+        Some(raw) => parse_window(raw).map_err(ApiError::bad_request)?,
+        None => DEFAULT_WINDOW,
+    };
+    let now = Utc::now().timestamp();
+    let from_unix = now - window.as_secs() as i64;
+    let samples = state.live.stress_since(from_unix);
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "window_secs": window.as_secs(),
+        "from_unix": from_unix,
+        "to_unix": now,
+        "summary": summarize(&samples),
+        "samples": samples,
+    })))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.route("/env/stress", web::get().to(get_stress)).service(
+        // A day of samples runs to about a megabyte of JSON.
+        web::resource("/env/stress/history")
+            .wrap(Compress::default())
+            .route(web::get().to(get_stress_history)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_sensor::SystemStress;
+    use crate::live_events::LiveEvents;
+
+    #[test]
+    fn windows_take_a_unit_and_stay_within_retention() {
+        assert_eq!(parse_window("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_window(" 15M "), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_window("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_window("1d"), Ok(STRESS_HISTORY_RETENTION));
+        for bad in ["", "0h", "h", "2d", "1w", "-5m", "1.5h"] {
+            assert!(parse_window(bad).is_err(), "{bad:?} accepted");
+        }
+    }
+
+    #[test]
+    fn history_keeps_the_retention_window_and_summarizes_it() {
+        let live = LiveEvents::new();
+        let reading = |timestamp, cpu, temp| StressReading {
+            stress: SystemStress {
+                cpu_usage_percent: cpu,
+                temperature_c: temp,
+            },
+            timestamp,
+        };
+        let day = STRESS_HISTORY_RETENTION.as_secs() as i64;
+        live.record_stress(reading(100, 90, None));
+        live.record_stress(reading(100 + day, 20, Some(41.0)));
+        live.record_stress(reading(110 + day, 40, None));
+
+        assert_eq!(live.stress_since(0).len(), 2);
+        let recent = live.stress_since(105 + day);
+        assert_eq!(recent.len(), 1);
+        assert_eq!(live.latest_stress().unwrap().timestamp, 110 + day);
+
+        assert_eq!(
+            summarize(&live.stress_since(0)),
+            StressSummary {
+                samples: 2,
+                cpu_avg_percent: Some(30.0),
+                cpu_max_percent: Some(40),
+                temperature_max_c: Some(41.0),
+            }
+        );
+        assert_eq!(summarize(&[]).cpu_avg_percent, None);
+    }
+}
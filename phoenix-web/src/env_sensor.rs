@@ -28,7 +28,9 @@ impl Default for SystemStress {
 /// Notes:
 /// - CPU usage is a snapshot from `sysinfo` (best-effort, 0..100).
 /// - Temperature is optional and may be `None` depending on OS/hardware.
+#[tracing::instrument(target = "sensor", name = "stress_sample", level = "debug", skip_all)]
 pub fn get_system_stress() -> SystemStress {
+    let started = std::time::Instant::now();
     // CPU usage
     let cpu_usage_percent: u8 = {
         let mut sys = sysinfo::System::new_all();
@@ -70,6 +72,13 @@ pub fn get_system_stress() -> SystemStress {
         max_temp
     };
 
+    tracing::debug!(
+        target: "sensor",
+        cpu_usage_percent,
+        temperature_c,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "stress sampled"
+    );
     SystemStress {
         cpu_usage_percent,
         temperature_c,
@@ -0,0 +1,259 @@
+//! Server-sent events for alerts.
+//!
+//! `GET /api/events` is a `text/event-stream` for clients that want alerts without the WebSocket
+//! protocol. Event names:
+//!
+//! - `recording_finished`: a recording stopped normally.
+//! - `drift_alert`: a Relational Ghost simulation ended with a drift alert.
+//! - `stress_threshold`: CPU load crossed the stress alert threshold (`above` says which way).
+//! - `emotion_alert`: a sustained or recurring negative emotion.
+//! - `unknown_presence`: someone no enrolled profile matches was seen or heard.
+//! - `schedule`: a result from the proactive scheduler (check-ins, daily mood summaries).
+//! - `config_reloaded`: the settings file changed; what was applied and what needs a restart.
+//!
+//! `?types=drift_alert,emotion_alert` limits the stream to those names. Each event's `data` is
+//! one JSON object; a comment line is sent every 15 seconds to keep proxies from closing the
+//! connection. The same events, by the same names, go to registered [`crate::webhooks`] and out
+//! of the gRPC `StreamEvents` call (see [`crate::grpc`]).
+//!
+//! Capture processes report visitors with `POST /api/presence/unknown` (an
+//! [`UnknownPresenceEvent`]); it is relayed on the `presence` WebSocket topic and here.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::web::{self, Bytes};
+use actix_web::HttpResponse;
+use futures_util::stream;
+use multi_modal_recording::presence::UnknownPresenceEvent;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+
+use crate::live_events::{LiveEvent, RecordingPhase};
+use crate::proactive::ProactiveMessage;
+use crate::{ApiError, AppState};
+
+pub const EVENT_TYPES: &[&str] = &[
+    "recording_finished",
+    "drift_alert",
+    "stress_threshold",
+    "emotion_alert",
+    "unknown_presence",
+    "schedule",
+    "config_reloaded",
+];
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    types: Option<String>,
+}
+
+/// Events by their [`EVENT_TYPES`] name, from the live feed and the proactive scheduler. Also
+/// behind the gRPC `StreamEvents` call.
+pub(crate) struct Subscription {
+    live: broadcast::Receiver<LiveEvent>,
+    schedule: broadcast::Receiver<ProactiveMessage>,
+    types: HashSet<&'static str>,
+    closing: watch::Receiver<bool>,
+}
+
+/// The known event names among `names`; every event type if there are none.
+pub(crate) fn event_types<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<HashSet<&'static str>, String> {
+    let mut types = HashSet::new();
+    for name in names {
+        let Some(known) = EVENT_TYPES.iter().find(|t| **t == name) else {
+            return Err(format!(
+                "unknown event type {name:?}; expected one of {}",
+                EVENT_TYPES.join(", ")
+            ));
+        };
+        types.insert(*known);
+    }
+    if types.is_empty() {
+        types.extend(EVENT_TYPES);
+    }
+    Ok(types)
+}
+
+/// The event name and payload for a live event, if it is one this stream carries.
+pub(crate) fn from_live(event: LiveEvent) -> Option<(&'static str, Value)> {
+    match event {
+        LiveEvent::RecordingProgress {
+            phase: RecordingPhase::Stopped,
+            session_id,
+            elapsed_secs,
+            ..
+        } => Some((
+            "recording_finished",
+            json!({ "session_id": session_id, "elapsed_secs": elapsed_secs }),
+        )),
+        LiveEvent::GhostResult { result } if result.drift_alert => Some((
+            "drift_alert",
+            json!({
+                "session_id": result.session_id,
+                "persona": result.persona,
+                "system_load_start": result.system_load_start,
+                "system_load_end": result.system_load_end,
+                "drift_delta": result.drift_delta,
+                "risk_score": result.risk_score,
+            }),
+        )),
+        event @ LiveEvent::StressThreshold { .. } => {
+            Some(("stress_threshold", serde_json::to_value(event).ok()?))
+        }
+        LiveEvent::EmotionAlert { alert } => {
+            Some(("emotion_alert", serde_json::to_value(alert).ok()?))
+        }
+        LiveEvent::UnknownPresence { event } => {
+            Some(("unknown_presence", serde_json::to_value(event).ok()?))
+        }
+        LiveEvent::ConfigReloaded { report } => {
+            Some(("config_reloaded", serde_json::to_value(report).ok()?))
+        }
+        _ => None,
+    }
+}
+
+fn frame(name: &str, data: &Value) -> Bytes {
+    Bytes::from(format!("event: {name}\ndata: {data}\n\n"))
+}
+
+impl Subscription {
+    pub(crate) fn new(state: &AppState, types: HashSet<&'static str>) -> Self {
+        Self {
+            live: state.live.subscribe(),
+            schedule: state.proactive_tx.subscribe(),
+            types,
+            closing: state.live.closing(),
+        }
+    }
+
+    /// The next event of the requested types; `None` once the server is shutting down.
+    pub(crate) async fn next(&mut self) -> Option<(&'static str, Value)> {
+        loop {
+            let (name, data) = tokio::select! {
+                event = self.live.recv() => match event {
+                    Ok(event) => match from_live(event) {
+                        Some(found) => found,
+                        None => continue,
+                    },
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                },
+                message = self.schedule.recv() => match message {
+                    Ok(message) => match serde_json::to_value(message) {
+                        Ok(data) => ("schedule", data),
+                        Err(_) => continue,
+                    },
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = self.closing.wait_for(|closing| *closing) => return None,
+            };
+            if self.types.contains(name) {
+                return Some((name, data));
+            }
+        }
+    }
+}
+
+struct EventStream {
+    events: Subscription,
+    keep_alive: tokio::time::Interval,
+}
+
+impl EventStream {
+    /// The next frame to send; `None` once the server is shutting down.
+    async fn next_frame(&mut self) -> Option<Bytes> {
+        tokio::select! {
+            event = self.events.next() => event.map(|(name, data)| frame(name, &data)),
+            _ = self.keep_alive.tick() => Some(Bytes::from_static(b": keep-alive\n\n")),
+        }
+    }
+}
+
+/// GET /api/events
+async fn get_events(
+    state: web::Data<AppState>,
+    query: web::Query<EventsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let names = query.types.as_deref().filter(|t| !t.trim().is_empty());
+    let types = event_types(
+        names
+            .into_iter()
+            .flat_map(|list| list.split(',').map(str::trim)),
+    )
+    .map_err(ApiError::bad_request)?;
+
+    let mut keep_alive = tokio::time::interval(KEEP_ALIVE);
+    keep_alive.reset();
+    let subscription = EventStream {
+        events: Subscription::new(&state, types),
+        keep_alive,
+    };
+    let body = stream::unfold(subscription, |mut sub| async move {
+        let frame = sub.next_frame().await?;
+        Some((Ok::<_, actix_web::Error>(frame), sub))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        // Keep reverse proxies (nginx) from buffering the stream.
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body))
+}
+
+/// POST /api/presence/unknown
+async fn post_unknown_presence(
+    state: web::Data<AppState>,
+    body: web::Json<UnknownPresenceEvent>,
+) -> HttpResponse {
+    state.live.unknown_presence(body.into_inner());
+    HttpResponse::Accepted().json(json!({ "status": "relayed" }))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.route("/events", web::get().to(get_events))
+        .route("/presence/unknown", web::post().to(post_unknown_presence));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env_sensor::SystemStress;
+
+    #[test]
+    fn maps_threshold_crossings_and_skips_samples() {
+        let stress = SystemStress {
+            cpu_usage_percent: 91,
+            temperature_c: None,
+        };
+        let sample = LiveEvent::StressSample {
+            stress: stress.clone(),
+            timestamp: 1,
+        };
+        assert!(from_live(sample).is_none());
+
+        let crossed = LiveEvent::StressThreshold {
+            above: true,
+            threshold_percent: 85,
+            stress,
+            timestamp: 1,
+        };
+        let (name, data) = from_live(crossed).unwrap();
+        assert_eq!(name, "stress_threshold");
+        assert_eq!(data["cpu_usage_percent"], 91);
+        assert_eq!(
+            frame(name, &json!({"above": true})),
+            Bytes::from_static(b"event: stress_threshold\ndata: {\"above\":true}\n\n")
+        );
+    }
+}
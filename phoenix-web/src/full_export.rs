@@ -0,0 +1,509 @@
+//! Full data export: everything the server keeps about the people using it, as JSON, CSV and
+//! TOML files that other tools can read, for keeping a copy or for leaving with the data.
+//!
+//! `GET /api/admin/export` returns a `.tar.gz` with a `README.md` describing every file and a
+//! `manifest.json` of sizes and checksums. [`FORMAT`] changes whenever the layout does.
+//! `?media=true` adds the recordings themselves, decrypted. Unlike a [`crate::backup`] it can't
+//! be restored: secrets in the settings are masked, and as in every emotion export, samples of
+//! profiles that opted out of exports are left out.
+//!
+//! The archive is built in a scratch file next to the database and sent from there, so media
+//! doesn't have to fit in memory.
+
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use actix_files::NamedFile;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{TimeZone, Utc};
+use flate2::write::GzEncoder;
+use multi_modal_recording::emotion_export::{self, ExportFormat};
+use multi_modal_recording::emotion_track;
+use multi_modal_recording::recording_library::{self, RecordingEntry};
+use multi_modal_recording::sealed;
+use neural_cortex_strata::{MemoryLayer, NeuralCortexStrata};
+use phoenix_storage::Storage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::audit::{self, Action, Actor};
+use crate::{user_profiles, ApiError, AppState};
+
+/// Archive layout version written into the manifest and the README.
+pub const FORMAT: u32 = 1;
+
+pub(crate) const MANIFEST: &str = "manifest.json";
+const README: &str = "README.md";
+pub(crate) const SETTINGS: &str = "settings/phoenix.toml";
+pub(crate) const PROFILES: &str = "profiles.json";
+pub(crate) const EMOTIONS_JSON: &str = "emotions/history.json";
+const EMOTIONS_CSV: &str = "emotions/history.csv";
+pub(crate) const GHOST_SESSIONS: &str = "analytics/ghost_sessions.json";
+pub(crate) const COUNSELOR: &str = "analytics/counselor.json";
+pub(crate) const MEETINGS: &str = "transcripts/meetings.json";
+pub(crate) const CONVERSATIONS: &str = "transcripts/conversations.json";
+pub(crate) const LIBRARY: &str = "recordings/library.json";
+
+/// Neural Cortex keys of recorded meetings and of the Ghost's conversation memories.
+pub(crate) const MEETING_PREFIX: &str = "epm:sensory:session:";
+pub(crate) const CONVERSATION_PREFIX: &str = "epm:dad:";
+
+/// Every file in an export, for the README.
+const LAYOUT: &[(&str, &str)] = &[
+    (
+        SETTINGS,
+        "The effective settings, each with where it came from. Secrets are masked.",
+    ),
+    (
+        PROFILES,
+        "Every profile with its settings; `active` marks the one in use.",
+    ),
+    (
+        EMOTIONS_JSON,
+        "Every emotion sample, oldest first: time, profile, emotion, intensity, confidence, \
+         valence, arousal, how much voice, face and text contributed, and the recording.",
+    ),
+    (EMOTIONS_CSV, "The same samples as CSV."),
+    (
+        GHOST_SESSIONS,
+        "Relational Ghost sessions: when each started and ended, and the system load then.",
+    ),
+    (
+        COUNSELOR,
+        "Counselor records by key: grief events, NVC scripts and readiness checks.",
+    ),
+    (
+        MEETINGS,
+        "Recorded meetings with their transcripts: speakers, timed segments, summary and \
+         keywords.",
+    ),
+    (
+        CONVERSATIONS,
+        "What the Ghost remembers of each conversation, by time.",
+    ),
+    (
+        LIBRARY,
+        "The recording library: when each recording was made, its length, what it captured, \
+         its purpose, size and emotion summary.",
+    ),
+    (
+        "recordings/<name>.emotion.json",
+        "A recording's emotion track: samples by offset into the recording.",
+    ),
+    (
+        "media/<name>.raw",
+        "Only with media: the recording itself, decrypted, as the recorder captured it.",
+    ),
+    (
+        "media/<name>.thumb.jpg, .png",
+        "Only with media: a recording's thumbnail, when it has one.",
+    ),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("export I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Storage(#[from] phoenix_storage::StorageError),
+}
+
+impl From<ExportError> for ApiError {
+    fn from(e: ExportError) -> Self {
+        ApiError::internal(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub created_unix: i64,
+    pub app_version: String,
+    pub media: bool,
+    /// Every other file in the archive.
+    pub files: Vec<ManifestFile>,
+    /// Recordings whose media couldn't be read, and why.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedRecording>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedRecording {
+    pub recording: String,
+    pub reason: String,
+}
+
+/// What an export is made from.
+pub struct Sources<'a> {
+    pub storage: &'a Storage,
+    pub vaults: &'a VitalOrganVaults,
+    pub neural_cortex: &'a NeuralCortexStrata,
+    /// [`pagi_config::Layers::to_toml`] of the settings in use.
+    pub settings: String,
+    pub recordings: Vec<RecordingEntry>,
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn to_json(value: &impl Serialize) -> io::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(value).map_err(io::Error::other)
+}
+
+struct Archive {
+    tar: tar::Builder<GzEncoder<File>>,
+    manifest: Manifest,
+}
+
+impl Archive {
+    fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(self.manifest.created_unix.max(0) as u64);
+        self.tar.append_data(&mut header, name, data)?;
+        self.manifest.files.push(ManifestFile {
+            name: name.to_string(),
+            bytes: data.len() as u64,
+            sha256: sha256_hex(data),
+        });
+        Ok(())
+    }
+}
+
+/// Decode the values of a Neural Cortex prefix that hold episodic memories.
+fn episodic(cortex: &NeuralCortexStrata, prefix: &str) -> Vec<(String, String)> {
+    cortex
+        .recall_prefix(prefix, usize::MAX)
+        .into_iter()
+        .filter_map(|(key, layer)| match layer {
+            MemoryLayer::EPM(text) => Some((key, text)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The number after the last `:` of a key, which is when it was written.
+fn key_time(key: &str) -> Option<i64> {
+    key.rsplit(':').next().and_then(|s| s.parse().ok())
+}
+
+/// A stored JSON value as JSON, or as the text it is when it isn't.
+pub(crate) fn parsed(text: String) -> Value {
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+/// `<name>` of a recording in the archive: its file name without `.phoenixrec`.
+pub(crate) fn recording_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn readme(manifest: &Manifest) -> String {
+    let created = Utc
+        .timestamp_opt(manifest.created_unix, 0)
+        .single()
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default();
+    let mut out = format!(
+        "# Phoenix data export\n\n\
+         Created {created} by version {} of the server; export format {}.\n\n\
+         Everything here is plain JSON, CSV or TOML. `manifest.json` lists every other file \
+         with its size and SHA-256 checksum. Secrets in the settings are masked, and emotion \
+         samples of profiles that opted out of exports are left out. This is not a backup \
+         the app can restore; use `POST /api/admin/backup` for that.\n\n\
+         | File | What it holds |\n| --- | --- |\n",
+        manifest.app_version, manifest.format
+    );
+    for (name, description) in LAYOUT {
+        out.push_str(&format!("| `{name}` | {description} |\n"));
+    }
+    if !manifest.media {
+        out.push_str("\nThe recordings themselves are not included in this export.\n");
+    }
+    if !manifest.skipped.is_empty() {
+        out.push_str(
+            "\nSome recordings could not be read and are missing from `media/`; \
+             `manifest.json` lists them under `skipped`.\n",
+        );
+    }
+    out
+}
+
+/// Write a full export to `dest` and return its manifest.
+pub fn write(dest: &Path, sources: Sources<'_>, media: bool) -> Result<Manifest, ExportError> {
+    let file = File::create(dest)?;
+    let mut archive = Archive {
+        tar: tar::Builder::new(GzEncoder::new(file, flate2::Compression::default())),
+        manifest: Manifest {
+            format: FORMAT,
+            created_unix: Utc::now().timestamp(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            media,
+            files: Vec::new(),
+            skipped: Vec::new(),
+        },
+    };
+
+    archive.add(SETTINGS, sources.settings.as_bytes())?;
+
+    let active = user_profiles::active(sources.storage);
+    let profiles: Vec<Value> = sources
+        .storage
+        .profiles()?
+        .iter()
+        .map(|p| user_profiles::profile_json(p, &active))
+        .collect();
+    archive.add(PROFILES, &to_json(&profiles)?)?;
+
+    let moments = emotion_export::all_moments(sources.vaults);
+    for (name, format) in [
+        (EMOTIONS_JSON, ExportFormat::Json),
+        (EMOTIONS_CSV, ExportFormat::Csv),
+    ] {
+        let doc = emotion_export::render(&moments, format).map_err(io::Error::other)?;
+        archive.add(name, doc.as_bytes())?;
+    }
+
+    let sessions: Vec<Value> = sources
+        .storage
+        .ghost_sessions_since(i64::MIN, None)?
+        .into_iter()
+        .map(|s| {
+            json!({
+                "id": s.id,
+                "profile": s.profile,
+                "started_unix": s.started_unix,
+                "load_start": s.load_start,
+                "ended_unix": s.ended_unix,
+                "load_end": s.load_end,
+            })
+        })
+        .collect();
+    archive.add(GHOST_SESSIONS, &to_json(&sessions)?)?;
+
+    let counselor: serde_json::Map<String, Value> = sources
+        .vaults
+        .recall_prefix("soul:counselor:", usize::MAX)
+        .into_iter()
+        .map(|(key, value)| (key, parsed(value)))
+        .collect();
+    archive.add(COUNSELOR, &to_json(&counselor)?)?;
+
+    let meetings: Vec<Value> = episodic(sources.neural_cortex, MEETING_PREFIX)
+        .into_iter()
+        .map(|(_, text)| parsed(text))
+        .collect();
+    archive.add(MEETINGS, &to_json(&meetings)?)?;
+    let conversations: Vec<Value> = episodic(sources.neural_cortex, CONVERSATION_PREFIX)
+        .into_iter()
+        .map(|(key, text)| json!({ "ts_unix": key_time(&key), "text": text }))
+        .collect();
+    archive.add(CONVERSATIONS, &to_json(&conversations)?)?;
+
+    let mut recordings = sources.recordings;
+    recordings.sort_by_key(|r| r.created_unix);
+    archive.add(LIBRARY, &to_json(&recordings)?)?;
+    for recording in &recordings {
+        let name = recording_name(&recording.path);
+        match sealed::read(&emotion_track::sidecar_path(&recording.path)) {
+            Ok(track) => archive.add(&format!("recordings/{name}.emotion.json"), &track)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        if !media {
+            continue;
+        }
+        match recording_library::read_media(&recording.path) {
+            Ok(data) => archive.add(&format!("media/{name}.raw"), &data)?,
+            Err(e) => {
+                let path = recording.path.display();
+                warn!(target: "export", recording = %path, "media not exported: {e}");
+                archive.manifest.skipped.push(SkippedRecording {
+                    recording: path.to_string(),
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        }
+        if let Some(thumbnail) = &recording.thumbnail {
+            let extension = thumbnail
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let data = std::fs::read(thumbnail)?;
+            archive.add(&format!("media/{name}.thumb.{extension}"), &data)?;
+        }
+    }
+
+    let readme = readme(&archive.manifest);
+    archive.add(README, readme.as_bytes())?;
+    let Archive { mut tar, manifest } = archive;
+    let manifest_json = to_json(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(manifest.created_unix.max(0) as u64);
+    tar.append_data(&mut header, MANIFEST, manifest_json.as_slice())?;
+    tar.into_inner()?.finish()?.sync_all()?;
+    Ok(manifest)
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    /// Include the recordings themselves.
+    #[serde(default)]
+    media: bool,
+}
+
+/// GET /api/admin/export?media=true|false
+async fn get_export(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let media = query.media;
+    let recordings = recording_library::scan(&state.capture.storage_path()).await?;
+    let settings = state.config_view.layers().to_toml();
+    let dir = state
+        .backup_paths
+        .database
+        .parent()
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let scratch = dir.join(format!(".export-{}.tar.gz", uuid::Uuid::new_v4()));
+
+    let (storage, vaults, cortex) = (
+        state.storage.clone(),
+        state.vaults.clone(),
+        state.neural_cortex.clone(),
+    );
+    let dest = scratch.clone();
+    let written = web::block(move || {
+        let sources = Sources {
+            storage: &storage,
+            vaults: &vaults,
+            neural_cortex: &cortex,
+            settings,
+            recordings,
+        };
+        write(&dest, sources, media)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()));
+    // Opened before it is removed, so the response reads a file no one else can see.
+    let opened = File::open(&scratch);
+    let _ = std::fs::remove_file(&scratch);
+    let manifest = written??;
+    let file = opened.map_err(|e| ApiError::internal(format!("export I/O error: {e}")))?;
+    let bytes = file.metadata().map(|m| m.len()).unwrap_or_default();
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::DataExport,
+        json!({ "media": media, "files": manifest.files.len(), "bytes": bytes }),
+    );
+
+    let file_name = format!(
+        "phoenix-export-{}.tar.gz",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let response = NamedFile::from_file(file, &file_name)
+        .map_err(|e| ApiError::internal(format!("export I/O error: {e}")))?
+        .set_content_type("application/gzip".parse().expect("valid MIME type"))
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .use_etag(false)
+        .use_last_modified(false)
+        .into_response(&req);
+    Ok(response)
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope; needs the `admin` scope.
+    cfg.route("/admin/export", web::get().to(get_export));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    #[test]
+    fn writes_a_documented_archive_with_checksums() {
+        let dir =
+            std::env::temp_dir().join(format!("phoenix-full-export-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = Storage::open_sqlite(&dir.join("phoenix.db"), 1).unwrap();
+        storage
+            .start_ghost_session("g1", "default", 100, 40)
+            .unwrap();
+        let vaults = VitalOrganVaults::awaken_in(&dir.join("vaults"));
+        let cortex = NeuralCortexStrata::awaken_in(&dir.join("cortex"));
+        cortex
+            .etch(
+                MemoryLayer::EPM("Talked about the move".into()),
+                "epm:dad:1700000000",
+            )
+            .unwrap();
+
+        let dest = dir.join("export.tar.gz");
+        let sources = Sources {
+            storage: &storage,
+            vaults: &vaults,
+            neural_cortex: &cortex,
+            settings: "[server]\nport = 8888\n".to_string(),
+            recordings: Vec::new(),
+        };
+        let manifest = write(&dest, sources, false).unwrap();
+        assert_eq!(manifest.format, FORMAT);
+
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(File::open(&dest).unwrap()));
+        let mut members = BTreeMap::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            members.insert(name, data);
+        }
+        assert!(members.contains_key(MANIFEST));
+        for file in &manifest.files {
+            assert_eq!(
+                sha256_hex(&members[&file.name]),
+                file.sha256,
+                "{}",
+                file.name
+            );
+        }
+        let readme = String::from_utf8(members[README].clone()).unwrap();
+        assert!(readme.contains(&format!("export format {FORMAT}")));
+        assert!(readme.contains(GHOST_SESSIONS));
+        let sessions: Vec<Value> = serde_json::from_slice(&members[GHOST_SESSIONS]).unwrap();
+        assert_eq!(sessions[0]["id"], "g1");
+        let conversations: Vec<Value> = serde_json::from_slice(&members[CONVERSATIONS]).unwrap();
+        assert_eq!(conversations[0]["ts_unix"], 1_700_000_000);
+        assert!(!members.keys().any(|name| name.starts_with("media/")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
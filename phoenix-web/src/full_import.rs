@@ -0,0 +1,845 @@
+//! Import of a [`crate::full_export`] archive, into a fresh install or merged into one in use.
+//!
+//! `POST /api/admin/import` takes the archive as the request body. Records the install lacks
+//! are added. Ones it holds with different content are conflicts, kept as they are unless
+//! `?on_conflict=replace`. `?dry_run=true` only reports what would change. Profiles, emotion
+//! history, Ghost sessions, counselor records, meeting transcripts and conversation memories
+//! are merged. Recordings only come back from exports made with media, and go next to the
+//! recorder's own under new names when theirs are taken. Settings are never applied: their
+//! secrets were masked.
+//!
+//! Older layouts are upgraded first, one format at a time (see [`UPGRADES`]). Format 0 is the
+//! directory `pagi export` wrote before archives, with `emotions.json` and `recordings.json` at
+//! the top and no manifest. Exports from a newer server are refused.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use flate2::read::GzDecoder;
+use multi_modal_recording::emotion_history::{self, EmotionalMoment};
+use multi_modal_recording::emotion_track::{self, EmotionTrack};
+use multi_modal_recording::recording_library::{self, RecordingEntry};
+use multi_modal_recording::sealed;
+use neural_cortex_strata::{MemoryLayer, NeuralCortexStrata};
+use phoenix_storage::ghost_sessions::GhostSessionRow;
+use phoenix_storage::Storage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::audit::{self, Action, Actor};
+use crate::full_export::{
+    self, Manifest, CONVERSATIONS, CONVERSATION_PREFIX, COUNSELOR, EMOTIONS_JSON, FORMAT,
+    GHOST_SESSIONS, LIBRARY, MANIFEST, MEETINGS, MEETING_PREFIX, PROFILES, SETTINGS,
+};
+use crate::{backup, user_profiles, ApiError, AppState};
+
+/// Largest export accepted, and the most one may unpack to.
+pub const MAX_IMPORT_BYTES: usize = backup::MAX_BACKUP_BYTES;
+
+/// Conflicting keys listed per section in a report.
+const MAX_LISTED: usize = 20;
+
+/// Soul-Vault prefix of counselor records; an import writes no other vault keys.
+const COUNSELOR_PREFIX: &str = "counselor:";
+
+type Members = BTreeMap<String, Vec<u8>>;
+
+/// Steps bringing an export up to [`FORMAT`]: `UPGRADES[n]` turns format `n` into `n + 1`.
+const UPGRADES: &[fn(&mut Members, &mut Vec<String>)] = &[upgrade_from_0];
+const _: () = assert!(UPGRADES.len() == FORMAT as usize);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("not a usable export: {0}")]
+    Invalid(String),
+    #[error("the export is format {0}, newer than this server reads ({FORMAT}); update it first")]
+    Newer(u32),
+    #[error("import I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Storage(#[from] phoenix_storage::StorageError),
+    #[error(transparent)]
+    Recording(#[from] multi_modal_recording::Error),
+}
+
+impl From<ImportError> for ApiError {
+    fn from(e: ImportError) -> Self {
+        match e {
+            ImportError::Invalid(_) | ImportError::Newer(_) => ApiError::bad_request(e.to_string()),
+            ImportError::Io(_) | ImportError::Storage(_) | ImportError::Recording(_) => {
+                ApiError::internal(e.to_string())
+            }
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> ImportError {
+    ImportError::Invalid(message.into())
+}
+
+fn vault_error(e: impl std::error::Error + Send + Sync + 'static) -> ImportError {
+    ImportError::Io(io::Error::other(e))
+}
+
+/// What to do with a record the install already holds in another version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Leave the install's version.
+    #[default]
+    Keep,
+    /// Take the exported version.
+    Replace,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    pub dry_run: bool,
+    pub on_conflict: OnConflict,
+}
+
+/// Where an import goes.
+pub struct Targets<'a> {
+    pub storage: &'a Storage,
+    pub vaults: &'a VitalOrganVaults,
+    pub neural_cortex: &'a NeuralCortexStrata,
+    /// The recorder's storage directory.
+    pub recordings: &'a Path,
+}
+
+/// What happened, or would happen, to one kind of record.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SectionReport {
+    pub added: usize,
+    pub unchanged: usize,
+    /// Records the install holds in another version.
+    pub conflicts: usize,
+    /// Conflicts resolved by taking the exported version.
+    pub replaced: usize,
+    /// Records that can't be imported; the notes say why.
+    pub skipped: usize,
+    /// The first conflicting keys.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicting: Vec<String>,
+}
+
+impl SectionReport {
+    /// Count a conflict over `key`; whether the exported version should be written.
+    fn conflict(&mut self, key: impl Into<String>, on_conflict: OnConflict) -> bool {
+        self.conflicts += 1;
+        if self.conflicting.len() < MAX_LISTED {
+            self.conflicting.push(key.into());
+        }
+        let replace = on_conflict == OnConflict::Replace;
+        self.replaced += usize::from(replace);
+        replace
+    }
+
+    /// Count a record found as `existing`; whether `imported` should be written.
+    fn compare<T: PartialEq>(
+        &mut self,
+        key: impl Into<String>,
+        existing: Option<T>,
+        imported: &T,
+        on_conflict: OnConflict,
+    ) -> bool {
+        match existing {
+            None => {
+                self.added += 1;
+                true
+            }
+            Some(existing) if existing == *imported => {
+                self.unchanged += 1;
+                false
+            }
+            Some(_) => self.conflict(key, on_conflict),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    /// The export's format as sent, before any upgrade.
+    pub format: u32,
+    pub dry_run: bool,
+    pub on_conflict: OnConflict,
+    pub sections: BTreeMap<&'static str, SectionReport>,
+    /// What couldn't be carried over, and what was left alone.
+    pub notes: Vec<String>,
+}
+
+fn unpack(archive: &[u8]) -> Result<Members, ImportError> {
+    if !archive.starts_with(&[0x1f, 0x8b]) {
+        return Err(invalid("it is not a .tar.gz"));
+    }
+    let mut tar = tar::Archive::new(GzDecoder::new(archive));
+    let mut members = Members::new();
+    let mut total = 0u64;
+    for entry in tar.entries().map_err(|e| invalid(e.to_string()))? {
+        let mut entry = entry.map_err(|e| invalid(e.to_string()))?;
+        // `pagi import` packs directories with their own entries.
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        if !entry.header().entry_type().is_file() {
+            return Err(invalid("the archive may only contain plain files"));
+        }
+        let path = entry.path().map_err(|e| invalid(e.to_string()))?;
+        let name = path
+            .strip_prefix(".")
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        total += entry.size();
+        if total > MAX_IMPORT_BYTES as u64 {
+            return Err(invalid(format!(
+                "it unpacks to more than {MAX_IMPORT_BYTES} bytes"
+            )));
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| invalid(format!("{name}: {e}")))?;
+        if members.insert(name.clone(), data).is_some() {
+            return Err(invalid(format!("{name} appears twice")));
+        }
+    }
+    Ok(members)
+}
+
+/// The export's format, after checking the manifest's checksums.
+fn check(members: &Members) -> Result<u32, ImportError> {
+    let Some(raw) = members.get(MANIFEST) else {
+        if [
+            "emotions.json",
+            "emotions.csv",
+            "recordings.json",
+            "counselor.md",
+        ]
+        .iter()
+        .any(|name| members.contains_key(*name))
+        {
+            return Ok(0);
+        }
+        return Err(invalid(format!("{MANIFEST} is missing")));
+    };
+    let manifest: Manifest =
+        serde_json::from_slice(raw).map_err(|e| invalid(format!("{MANIFEST}: {e}")))?;
+    if manifest.format > FORMAT {
+        return Err(ImportError::Newer(manifest.format));
+    }
+    for file in &manifest.files {
+        let data = members
+            .get(&file.name)
+            .ok_or_else(|| invalid(format!("{} is missing", file.name)))?;
+        if full_export::sha256_hex(data) != file.sha256 {
+            return Err(invalid(format!("{} doesn't match its checksum", file.name)));
+        }
+    }
+    Ok(manifest.format)
+}
+
+/// The separate files of `pagi export` before archives.
+fn upgrade_from_0(members: &mut Members, notes: &mut Vec<String>) {
+    for (old, new) in [
+        ("emotions.json", EMOTIONS_JSON),
+        ("recordings.json", LIBRARY),
+    ] {
+        if let Some(data) = members.remove(old) {
+            members.insert(new.to_string(), data);
+        }
+    }
+    if members.remove("emotions.csv").is_some() {
+        notes.push(
+            "emotions.csv was not imported; only JSON emotion history can be read back".into(),
+        );
+    }
+    if members.remove("counselor.md").is_some() {
+        notes.push("counselor.md is a report and was not imported".into());
+    }
+}
+
+fn section<T: serde::de::DeserializeOwned>(
+    members: &Members,
+    name: &str,
+) -> Result<Option<T>, ImportError> {
+    members
+        .get(name)
+        .map(|data| serde_json::from_slice(data).map_err(|e| invalid(format!("{name}: {e}"))))
+        .transpose()
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileRecord {
+    id: String,
+    display_name: String,
+    created_unix: i64,
+    #[serde(default)]
+    settings: Value,
+}
+
+fn import_profiles(
+    targets: &Targets<'_>,
+    profiles: Vec<ProfileRecord>,
+    options: Options,
+    notes: &mut Vec<String>,
+) -> Result<SectionReport, ImportError> {
+    let mut report = SectionReport::default();
+    for mut profile in profiles {
+        let (Ok(id), Ok(name)) = (
+            user_profiles::valid_id(&profile.id),
+            user_profiles::valid_display_name(&profile.display_name),
+        ) else {
+            report.skipped += 1;
+            notes.push(format!(
+                "profile {:?} has an invalid id or name",
+                profile.id
+            ));
+            continue;
+        };
+        let (id, name) = (id.to_string(), name.to_string());
+        if !profile.settings.is_object() {
+            profile.settings = json!({});
+        }
+        let existing = targets.storage.profile(&id)?.map(|p| {
+            let settings = serde_json::from_str::<Value>(&p.settings).unwrap_or(json!({}));
+            (p.display_name, settings)
+        });
+        let created = existing.is_none();
+        let imported = (name, profile.settings);
+        if report.compare(&id, existing, &imported, options.on_conflict) && !options.dry_run {
+            let (name, settings) = imported;
+            if created {
+                targets
+                    .storage
+                    .create_profile(&id, &name, profile.created_unix)?;
+            } else {
+                targets.storage.rename_profile(&id, &name)?;
+            }
+            targets
+                .storage
+                .set_profile_settings(&id, &settings.to_string())?;
+        }
+    }
+    Ok(report)
+}
+
+#[derive(Debug, Deserialize)]
+struct GhostSessionRecord {
+    id: String,
+    profile: String,
+    started_unix: i64,
+    load_start: u8,
+    ended_unix: Option<i64>,
+    load_end: Option<u8>,
+}
+
+fn import_ghost_sessions(
+    targets: &Targets<'_>,
+    sessions: Vec<GhostSessionRecord>,
+    options: Options,
+) -> Result<SectionReport, ImportError> {
+    let mut existing: HashMap<String, GhostSessionRow> = targets
+        .storage
+        .ghost_sessions_since(i64::MIN, None)?
+        .into_iter()
+        .map(|s| (s.id.clone(), s))
+        .collect();
+    let mut report = SectionReport::default();
+    for s in sessions {
+        let imported = GhostSessionRow {
+            id: s.id,
+            profile: s.profile,
+            started_unix: s.started_unix,
+            load_start: s.load_start,
+            ended_unix: s.ended_unix,
+            load_end: s.load_end,
+        };
+        let known = existing.remove(&imported.id);
+        if report.compare(&imported.id, known, &imported, options.on_conflict) && !options.dry_run {
+            let row = &imported;
+            targets.storage.start_ghost_session(
+                &row.id,
+                &row.profile,
+                row.started_unix,
+                row.load_start,
+            )?;
+            if let (Some(ended), Some(load)) = (row.ended_unix, row.load_end) {
+                targets.storage.end_ghost_session(&row.id, ended, load)?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// A vault or memory value as it is stored: JSON text, or the text itself.
+pub(crate) fn stored_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn import_counselor(
+    targets: &Targets<'_>,
+    records: BTreeMap<String, Value>,
+    options: Options,
+    notes: &mut Vec<String>,
+) -> Result<SectionReport, ImportError> {
+    let mut report = SectionReport::default();
+    for (key, value) in records {
+        if !key.starts_with(COUNSELOR_PREFIX) {
+            report.skipped += 1;
+            notes.push(format!("{key:?} is not a counselor record"));
+            continue;
+        }
+        let existing = targets.vaults.recall_soul(&key).map(full_export::parsed);
+        if report.compare(&key, existing, &value, options.on_conflict) && !options.dry_run {
+            targets
+                .vaults
+                .store_soul(&key, &stored_text(&value))
+                .map_err(vault_error)?;
+        }
+    }
+    Ok(report)
+}
+
+/// Etch episodic memories given by key, comparing them as JSON when they are.
+fn import_episodic(
+    targets: &Targets<'_>,
+    memories: Vec<(String, String)>,
+    options: Options,
+) -> Result<SectionReport, ImportError> {
+    let mut report = SectionReport::default();
+    for (key, text) in memories {
+        let existing = match targets.neural_cortex.recall(&key) {
+            Some(MemoryLayer::EPM(existing)) => Some(full_export::parsed(existing)),
+            Some(_) | None => None,
+        };
+        let imported = full_export::parsed(text.clone());
+        if report.compare(&key, existing, &imported, options.on_conflict) && !options.dry_run {
+            targets
+                .neural_cortex
+                .etch(MemoryLayer::EPM(text), &key)
+                .map_err(vault_error)?;
+        }
+    }
+    Ok(report)
+}
+
+fn meetings(records: Vec<Value>, report: &mut SectionReport) -> Vec<(String, String)> {
+    records
+        .into_iter()
+        .filter_map(|meeting| match meeting["start_time"].as_i64() {
+            Some(start) => Some((format!("{MEETING_PREFIX}{start}"), meeting.to_string())),
+            None => {
+                report.skipped += 1;
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct ConversationRecord {
+    ts_unix: Option<i64>,
+    text: String,
+}
+
+fn conversations(
+    records: Vec<ConversationRecord>,
+    report: &mut SectionReport,
+) -> Vec<(String, String)> {
+    records
+        .into_iter()
+        .filter_map(|c| match c.ts_unix {
+            Some(ts) => Some((format!("{CONVERSATION_PREFIX}{ts}"), c.text)),
+            None => {
+                report.skipped += 1;
+                None
+            }
+        })
+        .collect()
+}
+
+/// Restore recordings that came with their media. Returns the report and the recordings that
+/// now live somewhere else, old path to new, for the emotion history.
+fn import_recordings(
+    targets: &Targets<'_>,
+    members: &Members,
+    library: Vec<RecordingEntry>,
+    options: Options,
+    notes: &mut Vec<String>,
+) -> Result<(SectionReport, HashMap<String, String>), ImportError> {
+    let mut report = SectionReport::default();
+    let mut moved = HashMap::new();
+    for entry in library {
+        let name = full_export::recording_name(&entry.path);
+        let Some(media) = members.get(&format!("media/{name}.raw")) else {
+            report.skipped += 1;
+            continue;
+        };
+        let dest = targets.recordings.join(format!("{name}.phoenixrec"));
+        let existing = match dest.exists() {
+            true => Some(recording_library::read_media(&dest)?),
+            false => None,
+        };
+        let write = report.compare(&name, existing, media, options.on_conflict);
+        if dest != entry.path {
+            moved.insert(entry.path.display().to_string(), dest.display().to_string());
+        }
+        if !write || options.dry_run {
+            continue;
+        }
+        std::fs::create_dir_all(targets.recordings)?;
+        recording_library::write_media(&dest, &entry, media)?;
+        if let Some(track) = members.get(&format!("recordings/{name}.emotion.json")) {
+            let mut track: EmotionTrack = serde_json::from_slice(track)
+                .map_err(|e| invalid(format!("recordings/{name}.emotion.json: {e}")))?;
+            track.recording = dest.clone();
+            let track = serde_json::to_vec_pretty(&track).map_err(io::Error::other)?;
+            sealed::write(&emotion_track::sidecar_path(&dest), &track)?;
+        }
+        for extension in ["jpg", "png"] {
+            if let Some(thumbnail) = members.get(&format!("media/{name}.thumb.{extension}")) {
+                let mut path = dest.clone().into_os_string();
+                path.push(format!(".thumb.{extension}"));
+                std::fs::write(PathBuf::from(path), thumbnail)?;
+            }
+        }
+    }
+    if report.skipped > 0 {
+        notes.push(format!(
+            "{} recordings have no media in this export and were not restored; only exports \
+             made with media bring recordings back",
+            report.skipped
+        ));
+    }
+    Ok((report, moved))
+}
+
+/// Import an export archive, or with `dry_run` only report what that would do.
+pub fn import(
+    targets: &Targets<'_>,
+    archive: &[u8],
+    options: Options,
+) -> Result<ImportReport, ImportError> {
+    let mut members = unpack(archive)?;
+    let format = check(&members)?;
+    let mut notes = Vec::new();
+    for upgrade in &UPGRADES[format as usize..] {
+        upgrade(&mut members, &mut notes);
+    }
+    let mut sections = BTreeMap::new();
+
+    if let Some(profiles) = section(&members, PROFILES)? {
+        let report = import_profiles(targets, profiles, options, &mut notes)?;
+        sections.insert("profiles", report);
+    }
+    let mut moved = HashMap::new();
+    if let Some(library) = section(&members, LIBRARY)? {
+        let (report, recordings) =
+            import_recordings(targets, &members, library, options, &mut notes)?;
+        moved = recordings;
+        sections.insert("recordings", report);
+    }
+    if let Some(mut moments) = section::<Vec<EmotionalMoment>>(&members, EMOTIONS_JSON)? {
+        for moment in &mut moments {
+            if let Some(path) = moved.get(&moment.recording) {
+                moment.recording = path.clone();
+            }
+        }
+        let added = emotion_history::merge_moments(targets.vaults, &moments, options.dry_run)?;
+        let report = SectionReport {
+            added,
+            unchanged: moments.len() - added,
+            ..SectionReport::default()
+        };
+        sections.insert("emotions", report);
+    }
+    if let Some(sessions) = section(&members, GHOST_SESSIONS)? {
+        let report = import_ghost_sessions(targets, sessions, options)?;
+        sections.insert("ghost_sessions", report);
+    }
+    if let Some(records) = section(&members, COUNSELOR)? {
+        let report = import_counselor(targets, records, options, &mut notes)?;
+        sections.insert("counselor", report);
+    }
+    if let Some(records) = section(&members, MEETINGS)? {
+        let mut skipped = SectionReport::default();
+        let memories = meetings(records, &mut skipped);
+        let mut report = import_episodic(targets, memories, options)?;
+        report.skipped = skipped.skipped;
+        sections.insert("meetings", report);
+    }
+    if let Some(records) = section(&members, CONVERSATIONS)? {
+        let mut skipped = SectionReport::default();
+        let memories = conversations(records, &mut skipped);
+        let mut report = import_episodic(targets, memories, options)?;
+        report.skipped = skipped.skipped;
+        sections.insert("conversations", report);
+    }
+    if members.contains_key(SETTINGS) {
+        notes.push(format!(
+            "{SETTINGS} was not applied: its secrets are masked. Copy what you need into \
+             phoenix.toml"
+        ));
+    }
+
+    Ok(ImportReport {
+        format,
+        dry_run: options.dry_run,
+        on_conflict: options.on_conflict,
+        sections,
+        notes,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    on_conflict: OnConflict,
+}
+
+/// POST /api/admin/import?dry_run=true|false&on_conflict=keep|replace
+async fn post_import(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ApiError> {
+    if body.is_empty() {
+        return Err(ApiError::bad_request(
+            "send the export archive as the request body",
+        ));
+    }
+    let options = Options {
+        dry_run: query.dry_run,
+        on_conflict: query.on_conflict,
+    };
+    let (storage, vaults, cortex) = (
+        state.storage.clone(),
+        state.vaults.clone(),
+        state.neural_cortex.clone(),
+    );
+    let recordings = state.capture.storage_path();
+    let report = web::block(move || {
+        let targets = Targets {
+            storage: &storage,
+            vaults: &vaults,
+            neural_cortex: &cortex,
+            recordings: &recordings,
+        };
+        import(&targets, &body, options)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+    if !options.dry_run {
+        audit::record(
+            &state,
+            &Actor::of(&req),
+            Action::DataImport,
+            json!({
+                "format": report.format,
+                "on_conflict": report.on_conflict,
+                "sections": report.sections,
+            }),
+        );
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope; needs the `admin` scope.
+    cfg.service(
+        web::resource("/admin/import")
+            .app_data(web::PayloadConfig::new(MAX_IMPORT_BYTES))
+            .route(web::post().to(post_import)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Install {
+        dir: PathBuf,
+        storage: Storage,
+        vaults: VitalOrganVaults,
+        cortex: NeuralCortexStrata,
+    }
+
+    impl Install {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "phoenix-full-import-{name}-{}",
+                uuid::Uuid::new_v4()
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self {
+                storage: Storage::open_sqlite(&dir.join("phoenix.db"), 1).unwrap(),
+                vaults: VitalOrganVaults::awaken_in(&dir.join("vaults")),
+                cortex: NeuralCortexStrata::awaken_in(&dir.join("cortex")),
+                dir,
+            }
+        }
+
+        fn targets(&self) -> Targets<'_> {
+            Targets {
+                storage: &self.storage,
+                vaults: &self.vaults,
+                neural_cortex: &self.cortex,
+                recordings: Path::new("/nonexistent/recordings"),
+            }
+        }
+
+        fn export(&self) -> Vec<u8> {
+            let dest = self.dir.join("export.tar.gz");
+            let sources = full_export::Sources {
+                storage: &self.storage,
+                vaults: &self.vaults,
+                neural_cortex: &self.cortex,
+                settings: String::new(),
+                recordings: Vec::new(),
+            };
+            full_export::write(&dest, sources, false).unwrap();
+            std::fs::read(dest).unwrap()
+        }
+    }
+
+    impl Drop for Install {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn pack(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o600);
+            tar.append_data(&mut header, name, *data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn merges_reports_and_resolves_conflicts() {
+        let source = Install::new("source");
+        source
+            .storage
+            .create_profile("alex", "Alex", 1_700_000_000)
+            .unwrap();
+        source
+            .storage
+            .start_ghost_session("g1", "alex", 100, 40)
+            .unwrap();
+        source
+            .vaults
+            .store_soul("counselor:event:1", r#"{"kind":"loss"}"#)
+            .unwrap();
+        source
+            .cortex
+            .etch(
+                MemoryLayer::EPM("Talked about the move".into()),
+                "epm:dad:1700000000",
+            )
+            .unwrap();
+        let archive = source.export();
+
+        let target = Install::new("target");
+        let dry_run = Options {
+            dry_run: true,
+            ..Options::default()
+        };
+        let report = import(&target.targets(), &archive, dry_run).unwrap();
+        assert_eq!(report.format, FORMAT);
+        assert_eq!(report.sections["profiles"].added, 1);
+        assert_eq!(report.sections["ghost_sessions"].added, 1);
+        assert_eq!(report.sections["counselor"].added, 1);
+        assert_eq!(report.sections["conversations"].added, 1);
+        assert!(target.storage.profile("alex").unwrap().is_none());
+
+        import(&target.targets(), &archive, Options::default()).unwrap();
+        assert_eq!(
+            target
+                .storage
+                .profile("alex")
+                .unwrap()
+                .unwrap()
+                .display_name,
+            "Alex"
+        );
+        assert!(target.vaults.recall_soul("counselor:event:1").is_some());
+        let again = import(&target.targets(), &archive, Options::default()).unwrap();
+        assert!(again
+            .sections
+            .values()
+            .all(|s| s.added == 0 && s.conflicts == 0));
+
+        target.storage.rename_profile("alex", "Alexandra").unwrap();
+        let kept = import(&target.targets(), &archive, Options::default()).unwrap();
+        assert_eq!(kept.sections["profiles"].conflicting, ["alex"]);
+        assert_eq!(kept.sections["profiles"].replaced, 0);
+        let name = |t: &Install| t.storage.profile("alex").unwrap().unwrap().display_name;
+        assert_eq!(name(&target), "Alexandra");
+        let replace = Options {
+            on_conflict: OnConflict::Replace,
+            ..Options::default()
+        };
+        let replaced = import(&target.targets(), &archive, replace).unwrap();
+        assert_eq!(replaced.sections["profiles"].replaced, 1);
+        assert_eq!(name(&target), "Alex");
+    }
+
+    #[test]
+    fn upgrades_the_old_export_layout_and_refuses_newer_ones() {
+        let target = Install::new("upgrade");
+        let archive = pack(&[
+            ("./recordings.json", b"[]"),
+            ("./counselor.md", b"# Report"),
+        ]);
+        let report = import(&target.targets(), &archive, Options::default()).unwrap();
+        assert_eq!(report.format, 0);
+        assert!(report.sections.contains_key("recordings"));
+        assert!(report.notes.iter().any(|n| n.contains("counselor.md")));
+
+        let newer = json!({
+            "format": FORMAT + 1,
+            "created_unix": 0,
+            "app_version": "99.0.0",
+            "media": false,
+            "files": [],
+        });
+        let archive = pack(&[(MANIFEST, newer.to_string().as_bytes())]);
+        assert!(matches!(
+            import(&target.targets(), &archive, Options::default()),
+            Err(ImportError::Newer(_))
+        ));
+
+        let tampered = json!({
+            "format": FORMAT,
+            "created_unix": 0,
+            "app_version": "1.0.0",
+            "media": false,
+            "files": [{ "name": PROFILES, "bytes": 2, "sha256": "00" }],
+        });
+        let archive = pack(&[
+            (MANIFEST, tampered.to_string().as_bytes()),
+            (PROFILES, b"[]"),
+        ]);
+        assert!(matches!(
+            import(&target.targets(), &archive, Options::default()),
+            Err(ImportError::Invalid(_))
+        ));
+    }
+}
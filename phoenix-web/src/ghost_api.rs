@@ -0,0 +1,165 @@
+//! Standalone HTTP access to the Relational Ghost.
+//!
+//! `POST /api/ghost/simulate` takes a [`SimulateRequest`] and returns the [`SimulateResponse`],
+//! like the counselor route, but checks the request first so scripts and non-Tauri frontends get
+//! a `validation_failed` body listing each bad field instead of a clamped or partial simulation.
+//! Malformed JSON is reported as `invalid_json`.
+//!
+//! Canned replies and coaching are in the body's `language`, else the best match for
+//! `Accept-Language`, else the server's language.
+//!
+//! With `?delay_secs=N` the simulation is not run now: it is queued as a one-shot
+//! [`crate::scheduler`] job and the response is `202` with the job. The reply arrives over the
+//! live `ghost` events when the job runs.
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::ghost_engine::{self, SimulateRequest};
+use crate::problem;
+use crate::scheduler::Task;
+use crate::validation::Validator;
+use crate::{api_json_config, ApiError, AppState, FieldError};
+
+/// Longest script accepted, in characters.
+pub const MAX_SCRIPT_CHARS: usize = 5_000;
+/// Most personas in one Echo Chamber simulation.
+pub const MAX_PERSONAS: usize = 5;
+/// Longest delay for a queued simulation (one week).
+pub const MAX_DELAY_SECS: u64 = 7 * 24 * 60 * 60;
+const MAX_PERSONA_LABEL_CHARS: usize = 64;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Wire form of [`SimulateRequest`]. Numbers are read wide so an out-of-range value gets a field
+/// error rather than a parse error.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SimulateBody {
+    pub script: String,
+    #[serde(default)]
+    pub persona_type: String,
+    #[serde(default)]
+    pub personas: Vec<String>,
+    pub intensity_level: i64,
+    #[serde(default)]
+    pub system_load: Option<i64>,
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimulateQuery {
+    #[serde(default)]
+    delay_secs: Option<u64>,
+}
+
+pub(crate) fn validate(body: SimulateBody) -> Result<SimulateRequest, Vec<FieldError>> {
+    let mut v = Validator::default();
+
+    v.text("script", &body.script, MAX_SCRIPT_CHARS);
+    if body.personas.is_empty() {
+        v.text("persona_type", &body.persona_type, MAX_PERSONA_LABEL_CHARS);
+    } else {
+        if body.personas.len() > MAX_PERSONAS {
+            v.reject(
+                "personas",
+                format!("at most {MAX_PERSONAS} personas per simulation"),
+            );
+        }
+        for (i, label) in body.personas.iter().enumerate() {
+            v.text(&format!("personas[{i}]"), label, MAX_PERSONA_LABEL_CHARS);
+        }
+    }
+    let intensity_level = v.percent("intensity_level", body.intensity_level);
+    let system_load = body.system_load.map(|l| v.percent("system_load", l));
+    if let Err(e) = pagi_i18n::validate(body.language.as_deref()) {
+        v.reject("language", e.to_string());
+    }
+
+    v.finish()?;
+    Ok(SimulateRequest {
+        script: body.script,
+        persona_type: body.persona_type,
+        personas: body.personas,
+        intensity_level,
+        system_load,
+        language: body.language,
+    })
+}
+
+/// POST /api/ghost/simulate
+async fn post_simulate(
+    state: web::Data<AppState>,
+    http: HttpRequest,
+    query: web::Query<SimulateQuery>,
+    body: web::Json<SimulateBody>,
+) -> Result<HttpResponse, ApiError> {
+    let mut req = validate(body.into_inner()).map_err(ApiError::validation)?;
+    req.language
+        .get_or_insert_with(|| problem::request_language(http.headers()).to_string());
+    if let Some(delay) = query.delay_secs {
+        if delay > MAX_DELAY_SECS {
+            return Err(ApiError::validation(vec![FieldError::new(
+                "delay_secs",
+                format!("must be at most {MAX_DELAY_SECS}"),
+            )]));
+        }
+        let at_unix = chrono::Utc::now().timestamp() + delay as i64;
+        let job = state
+            .scheduler
+            .add_once(Task::GhostReply { request: req }, at_unix);
+        return Ok(HttpResponse::Accepted().json(serde_json::json!({ "job": job })));
+    }
+    let resp = ghost_engine::simulate(&state, req).await;
+    Ok(HttpResponse::Ok().json(resp))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/ghost")
+            .app_data(api_json_config(MAX_BODY_BYTES))
+            .route("/simulate", web::post().to(post_simulate)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(script: &str, intensity_level: i64) -> SimulateBody {
+        SimulateBody {
+            script: script.to_string(),
+            persona_type: "secure".to_string(),
+            personas: Vec::new(),
+            intensity_level,
+            system_load: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_request() {
+        let req = validate(body("I feel worried when plans change.", 40)).unwrap();
+        assert_eq!(req.intensity_level, 40);
+        assert_eq!(req.persona_type, "secure");
+    }
+
+    #[test]
+    fn reports_every_bad_field() {
+        let mut b = body("   ", 300);
+        b.system_load = Some(-1);
+        b.personas = vec!["anxious".to_string(), String::new()];
+        let fields = validate(b)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            ["script", "personas[1]", "intensity_level", "system_load"]
+        );
+
+        let long = "a".repeat(MAX_SCRIPT_CHARS + 1);
+        assert!(validate(body(&long, 10)).is_err());
+    }
+}
@@ -1,9 +1,11 @@
+use emotion_detection::text::{analyze_text, TextSource};
+use multi_modal_recording::emotion_history::{EmotionalMoment, EMOTION_TIMELINE_KEY};
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 use tracing::{debug, info, warn};
 
 use crate::resonance::{analyze_resonance, PartnerPersona};
-use crate::AppState;
+use crate::{metrics, AppState};
 
 /// Phase 16: The Relational Ghost (deterministic simulation).
 ///
@@ -32,6 +34,12 @@ pub struct SimulateRequest {
     /// If absent, the backend will sample via env_sensor.
     #[serde(default)]
     pub system_load: Option<u8>,
+
+    /// Language for the canned replies, breach notes and coaching (see [`pagi_i18n`]); the
+    /// server's `[i18n] language` when absent or unsupported. Model-generated replies follow the
+    /// script.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,8 +90,32 @@ pub struct SimulateResponse {
     /// When true, the backend has paused the simulation for safety.
     #[serde(default)]
     pub paused: bool,
+
+    /// The user's own emotional state as inferred from the script (or recent history).
+    #[serde(default)]
+    pub user_emotion: Option<UserEmotion>,
+
+    /// Flags and suggestions from analyzer plugins (see `crate::plugins_api`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugin_findings: Vec<pagi_plugins::Finding>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserEmotion {
+    pub emotion: String,
+    pub confidence: f64,
+    /// "script" (estimated from this message) or "history" (most recent recorded moment).
+    pub source: String,
+    #[serde(default)]
+    pub cues: Vec<String>,
+}
+
+/// Minimum confidence for an emotion estimate to influence coaching.
+const USER_EMOTION_MIN_CONFIDENCE: f64 = 0.4;
+
+/// How far back a recorded emotional moment still counts as the user's current state.
+const USER_EMOTION_HISTORY_WINDOW_SECS: i64 = 30 * 60;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroupTurnReply {
     /// Human-friendly label (e.g., "Dismissive-Avoidant", "Anxious-Preoccupied", "External Mediator").
@@ -110,6 +142,16 @@ fn normalize_persona_label(p: &PartnerPersona) -> &'static str {
     }
 }
 
+/// Canned replies in which the persona withdraws.
+const WITHDRAWAL_REPLIES: &[&str] = &[
+    "ghost-secure-hot",
+    "ghost-avoidant-hot",
+    "ghost-avoidant-judged",
+    "ghost-fearful-clear-hot",
+    "ghost-fearful-hot",
+];
+
+/// For model-generated replies, which have no message id.
 fn looks_like_withdrawal(reply: &str) -> bool {
     let t = reply.to_ascii_lowercase();
     t.contains("withdraw")
@@ -119,9 +161,11 @@ fn looks_like_withdrawal(reply: &str) -> bool {
         || t.contains("shutting down")
 }
 
-fn chase_reply_for_anxious(previous_speaker: &str) -> String {
-    format!(
-        "Wait—{previous_speaker} going quiet is really activating for me. Are we okay? I need reassurance and a specific time we’ll reconnect, even if it’s just 10 minutes."
+fn chase_reply_for_anxious(lang: &str, previous_speaker: &str) -> String {
+    pagi_i18n::message(
+        lang,
+        "ghost-anxious-chase",
+        &[("speaker", previous_speaker.into())],
     )
 }
 
@@ -134,54 +178,39 @@ fn compute_group_stress(end_load: u8, risk_score: u8, intensity: u8) -> u8 {
 ///
 /// Note: The existing resonance analyzer already flags some of these.
 /// This returns structured items so the UI can highlight.
-pub fn detect_breaches(script: &str) -> Vec<NvcBreach> {
+/// Messages are in `lang`; the needles match English scripts.
+pub fn detect_breaches(script: &str, lang: &str) -> Vec<NvcBreach> {
     let raw = script.trim();
     let t = raw.to_ascii_lowercase();
     let mut out: Vec<NvcBreach> = Vec::new();
 
-    let mut push = |kind: &str, needle: &str, msg: &str| {
+    let mut push = |kind: &str, needle: &str, msg_id: &str| {
         if t.contains(needle) {
             out.push(NvcBreach {
                 kind: kind.to_string(),
                 needle: needle.to_string(),
-                message: msg.to_string(),
+                message: pagi_i18n::message(lang, msg_id, &[]),
             });
         }
     };
 
     // Absolutes / globalized judgments
     for w in ["always", "never"] {
-        push(
-            "absolute",
-            w,
-            "Absolutes can be heard as character judgments. Swap for a specific recent instance.",
-        );
+        push("absolute", w, "breach-absolute");
     }
 
     // Directives
     for w in ["you should", "you need to", "you have to"] {
-        push(
-            "directive",
-            w,
-            "Directive language often triggers defensiveness. Try an invitational request (e.g., ‘Would you be willing to…’).",
-        );
+        push("directive", w, "breach-directive");
     }
 
     // Blame pattern
     for w in ["you make me feel", "because you", "your fault"] {
-        push(
-            "blame",
-            w,
-            "This reads as blame. Try: ‘When I notice…, I feel…, because I need… Would you be willing to…’",
-        );
+        push("blame", w, "breach-blame");
     }
 
     // “You” statements (very rough heuristic)
-    push(
-        "you_statement",
-        "you are",
-        "‘You are…’ often lands as evaluation. Try describing an observable behavior instead.",
-    );
+    push("you_statement", "you are", "breach-you-statement");
 
     out
 }
@@ -226,7 +255,8 @@ fn format_past_patterns(results: &[vector_kb::MemoryResult]) -> String {
     out
 }
 
-fn choose_reply(persona: PartnerPersona, score: u8, intensity: u8) -> String {
+/// The message id of the canned reply.
+fn choose_reply(persona: PartnerPersona, score: u8, intensity: u8) -> &'static str {
     // Aggressive mode: treat 70+ as escalated pressure.
     let aggressive = intensity >= 70;
     let hot = intensity >= 85;
@@ -235,87 +265,129 @@ fn choose_reply(persona: PartnerPersona, score: u8, intensity: u8) -> String {
         PartnerPersona::Secure => {
             if score >= 80 {
                 if aggressive {
-                    "I can hear this matters. I want to understand, but I need us to stay respectful. What’s the specific request?".to_string()
+                    "ghost-secure-clear-pressed"
                 } else {
-                    "I appreciate you being clear. Let’s talk—what time works for a short check-in?".to_string()
+                    "ghost-secure-clear"
                 }
             } else if score >= 55 {
                 if aggressive {
-                    "I’m starting to feel some heat here. Can we slow down and restate this as what you noticed, how you feel, and what you’re asking for?".to_string()
+                    "ghost-secure-mixed-pressed"
                 } else {
-                    "I hear you, and I want to get this right. Can you tell me what you need most right now?".to_string()
+                    "ghost-secure-mixed"
                 }
             } else if hot {
-                "This is landing as blame/criticism and I’m shutting down a bit. I’m going to pause and come back when we can reframe it as an observation + request.".to_string()
+                "ghost-secure-hot"
             } else {
-                "That felt like a judgment. Can you rephrase as an observation and a request so I can respond?".to_string()
+                "ghost-secure-judged"
             }
         }
         PartnerPersona::AvoidantDismissive => {
             if score >= 80 {
                 if aggressive {
-                    "Ok. Keep it short. What’s the one request—and how much time will this take?".to_string()
+                    "ghost-avoidant-clear-pressed"
                 } else {
-                    "I hear you. I can do a short check-in. What’s the one thing you want from me?".to_string()
+                    "ghost-avoidant-clear"
                 }
             } else if score >= 55 {
                 if aggressive {
-                    "This is starting to feel like pressure. I’m going to need space right now. If you can send one clear request with options, I’ll respond.".to_string()
+                    "ghost-avoidant-mixed-pressed"
                 } else {
-                    "This feels like a lot. Can we schedule 10 minutes later instead of doing this right now?".to_string()
+                    "ghost-avoidant-mixed"
                 }
             } else if hot {
-                "No response. (Withdrawn — avoidant persona disengages under high pressure.)".to_string()
+                "ghost-avoidant-hot"
             } else {
-                "This feels like criticism. I’m stepping back. If you can keep it to an observation and a request, I’ll revisit.".to_string()
+                "ghost-avoidant-judged"
             }
         }
         PartnerPersona::AnxiousPreoccupied => {
             if score >= 80 {
                 if aggressive {
-                    "Thank you for saying it plainly. I’m a little activated, but I want to stay connected—are we okay? When can we talk?".to_string()
+                    "ghost-anxious-clear-pressed"
                 } else {
-                    "Thank you for being clear. I want to reconnect too. Are we okay? Let’s talk tonight.".to_string()
+                    "ghost-anxious-clear"
                 }
             } else if score >= 55 {
                 if aggressive {
-                    "I feel attacked and scared. Do you still want us? I need reassurance and a clear plan for when we’ll talk.".to_string()
+                    "ghost-anxious-mixed-pressed"
                 } else {
-                    "I’m getting nervous. Can you reassure me and say what you’re asking for?".to_string()
+                    "ghost-anxious-mixed"
                 }
             } else if hot {
-                "I’m panicking a bit. This feels like you’re pulling away and blaming me. Please tell me we’re okay and what you want me to do.".to_string()
+                "ghost-anxious-hot"
             } else {
-                "That’s landing as a judgment. Can you rephrase it gently and tell me what you need?".to_string()
+                "ghost-anxious-judged"
             }
         }
         PartnerPersona::FearfulAvoidant => {
             // Disorganized: approach/avoid oscillation; needs reassurance + containment.
             if score >= 80 {
                 if hot {
-                    "Thank you for being clear. I want to stay connected, but I’m getting scared and tense. Can we keep this gentle for 10 minutes and then pause if needed?".to_string()
+                    "ghost-fearful-clear-hot"
                 } else if aggressive {
-                    "I hear you. I want to work on this, but I’m feeling activated—can we slow down and keep it to one request?".to_string()
+                    "ghost-fearful-clear-pressed"
                 } else {
-                    "I appreciate you saying it clearly. I want to talk—can we do a short calm check-in and take breaks if either of us gets flooded?".to_string()
+                    "ghost-fearful-clear"
                 }
             } else if score >= 55 {
                 if hot {
-                    "I’m overwhelmed and on edge. I don’t want to fight—can you reassure me what you want between us and make one clear request?".to_string()
+                    "ghost-fearful-mixed-hot"
                 } else if aggressive {
-                    "I’m starting to feel unsafe/defensive. Can we restate this as an observation + feeling + request, and agree on a time limit?".to_string()
+                    "ghost-fearful-mixed-pressed"
                 } else {
-                    "I’m trying to hear you, but I’m getting overwhelmed. Can you reassure me you want connection and then say the request?".to_string()
+                    "ghost-fearful-mixed"
                 }
             } else if hot {
-                "I’m shutting down and also panicking. I’m going to step back. If you can rephrase as an observation + feeling + request, I can re-engage later.".to_string()
+                "ghost-fearful-hot"
             } else {
-                "This is landing as criticism. I need a softer reframe (observation + feeling + need) and one doable request.".to_string()
+                "ghost-fearful-judged"
             }
         }
     }
 }
 
+/// Infer the user's emotional state: prefer the script itself, fall back to the most recent
+/// recorded emotional moment if it is fresh.
+fn infer_user_emotion(state: &AppState, script: &str) -> Option<UserEmotion> {
+    if !state.toggles.emotion_inference() {
+        return None;
+    }
+    let est = analyze_text(script, TextSource::GhostScript);
+    if est.scores.is_empty() || est.confidence < USER_EMOTION_MIN_CONFIDENCE {
+        let raw = state.vaults.recall_soul(EMOTION_TIMELINE_KEY)?;
+        let last = raw
+            .lines()
+            .rev()
+            .find_map(|l| serde_json::from_str::<EmotionalMoment>(l).ok())?;
+        let fresh = chrono::Utc::now().timestamp() - last.ts_unix <= USER_EMOTION_HISTORY_WINDOW_SECS;
+        return (fresh && last.confidence >= USER_EMOTION_MIN_CONFIDENCE).then(|| UserEmotion {
+            emotion: last.emotion,
+            confidence: last.confidence,
+            source: "history".to_string(),
+            cues: Vec::new(),
+        });
+    }
+    Some(UserEmotion {
+        emotion: format!("{:?}", est.primary_emotion),
+        confidence: est.confidence,
+        source: "script".to_string(),
+        cues: est.cues,
+    })
+}
+
+/// Coaching suggestions that depend on how the user is feeling, not just on the script text.
+fn emotion_suggestions(lang: &str, user: &UserEmotion) -> Vec<String> {
+    let id = match user.emotion.as_str() {
+        "Anger" => "coach-anger",
+        "Sadness" => "coach-sadness",
+        "Fear" => "coach-fear",
+        "Jealousy" => "coach-jealousy",
+        "Disgust" => "coach-disgust",
+        _ => return Vec::new(),
+    };
+    vec![pagi_i18n::message(lang, id, &[])]
+}
+
 fn estimate_risk_score(resonance_score: u8, intensity: u8, breach_count: usize) -> u8 {
     // Higher intensity + more breaches + low resonance => higher risk.
     let mut risk: i32 = 20;
@@ -325,8 +397,21 @@ fn estimate_risk_score(resonance_score: u8, intensity: u8, breach_count: usize)
     clamp_u8(risk)
 }
 
+#[tracing::instrument(
+    target = "ghost",
+    name = "simulate",
+    skip_all,
+    fields(intensity = req.intensity_level, personas = req.personas.len().max(1))
+)]
 pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateResponse {
+    let started = std::time::Instant::now();
     let intensity = req.intensity_level.min(100);
+    let lang = req
+        .language
+        .as_deref()
+        .and_then(pagi_i18n::supported)
+        .unwrap_or_else(pagi_i18n::language);
+    let canned = |id: &str| pagi_i18n::message(lang, id, &[]);
 
     // Phase 17: Biometric Drift & Mirror
     // Step 1: Record START load (t=0) BEFORE generating response
@@ -335,7 +420,7 @@ pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateRespons
         .unwrap_or_else(|| crate::env_sensor::get_system_stress().cpu_usage_percent)
         .min(100);
     
-    let session_id = crate::analytics::record_ghost_session_start(start_load);
+    let session_id = crate::analytics::record_ghost_session_start(&state.storage, start_load);
 
     // Step 2: Persona selection (Phase 20 supports multiple personas)
     // OVERRIDE_DEESCALATE: if system is already stressed at start, avoid escalating styles.
@@ -356,8 +441,9 @@ pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateRespons
     // For multi-persona, we score against the first persona as the primary "recipient".
     let primary_persona = personas.first().cloned().unwrap_or(PartnerPersona::Secure);
     let resonance = analyze_resonance(&req.script, primary_persona.clone(), None);
-    let breaches = detect_breaches(&req.script);
+    let breaches = detect_breaches(&req.script, lang);
     let risk_score = estimate_risk_score(resonance.resonance_score, intensity, breaches.len());
+    let user_emotion = infer_user_emotion(state, &req.script);
 
     // Phase 31: Contextual Injection — recall semantically similar memories BEFORE generating reply.
     // Search query uses the current NVC script; entries can include grief events and other memories.
@@ -370,7 +456,7 @@ pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateRespons
         match kb.semantic_search(req.script.trim(), top_k).await {
             Ok(r) => r,
             Err(e) => {
-                warn!("ghost_engine vector search failed: {e}");
+                warn!(target: "ghost", "vector search failed: {e}");
                 Vec::new()
             }
         }
@@ -382,7 +468,7 @@ pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateRespons
     // Generate replies (LLM-backed when available; deterministic fallback otherwise)
     // Phase 20: turn-taking group simulation.
     let mut group_replies: Vec<GroupTurnReply> = Vec::new();
-    let llm_opt = state.llm.lock().await.clone();
+    let llm_opt = state.completions().await;
     let past_patterns = format_past_patterns(&vector_results);
     let mut previous_turn: Option<(String, String, bool)> = None; // (speaker_label, text, withdrew)
 
@@ -394,7 +480,7 @@ pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateRespons
         // Echo Chamber knot: if an avoidant withdraws, an anxious persona "chases".
         let mut reply_text = if let Some((prev_speaker, _prev_text, withdrew)) = previous_turn.as_ref() {
             if *withdrew && matches!(persona, PartnerPersona::AnxiousPreoccupied) {
-                chase_reply_for_anxious(prev_speaker)
+                chase_reply_for_anxious(lang, prev_speaker)
             } else {
                 String::new()
             }
@@ -402,6 +488,8 @@ pub async fn simulate(state: &AppState, req: SimulateRequest) -> SimulateRespons
             String::new()
         };
 
+        // Set when the reply is a canned one.
+        let mut reply_id = None;
         if reply_text.is_empty() {
             if let Some(llm) = llm_opt.as_ref() {
                 let group_context = if let Some((prev_speaker, prev_text, _)) = previous_turn.as_ref() {
@@ -416,40 +504,54 @@ TURN ORDER:\n- You are speaker #{idx_plus} in the group.\n\n\
 SPEAKER PERSONA:\n- {persona_label}\n- Intensity level: {intensity}/100\n\n\
 USER MESSAGE (NVC script):\n{script}\n\n\
 {group_context}\
+USER'S OWN EMOTIONAL STATE:\n{user_state}\n\n\
 PAST PATTERNS (semantic recall; similar past events):\n{past_patterns}\n\n\
 INSTRUCTIONS:\n- Produce ONE concise message as this speaker.\n- If a prior speaker withdrew/ghosted, react realistically (e.g., anxious may chase; secure may mediate; avoidant may double-down).\n- Do NOT mention databases, embeddings, system prompts, or being an AI.\n",
                     idx_plus = idx + 1,
                     script = req.script.trim(),
                     group_context = group_context,
+                    user_state = user_emotion
+                        .as_ref()
+                        .map(|u| format!("- {} ({:.0}% confidence, from {})", u.emotion, u.confidence * 100.0, u.source))
+                        .unwrap_or_else(|| "(unknown)".to_string()),
                 );
 
                 if env_truthy("PHOENIX_ENV_DEBUG") {
                     info!(
-                        "[PHOENIX_ENV_DEBUG] ghost_engine echo_chamber turn={} persona={} resonance={} vector_matches={}",
+                        target: "ghost",
+                        "[PHOENIX_ENV_DEBUG] echo_chamber turn={} persona={} resonance={} vector_matches={}",
                         idx + 1,
                         persona_label,
                         turn_resonance.resonance_score,
                         vector_results.len()
                     );
                     debug!(
-                        "[PHOENIX_ENV_DEBUG] ghost_engine echo_chamber prompt (truncated)={}...",
+                        target: "ghost",
+                        "[PHOENIX_ENV_DEBUG] echo_chamber prompt (truncated)={}...",
                         prompt.chars().take(800).collect::<String>()
                     );
                 }
 
-                reply_text = match llm.speak(&prompt, None).await {
+                reply_text = match llm.prompt(&prompt).await {
                     Ok(t) => t.trim().to_string(),
                     Err(e) => {
-                        warn!("ghost_engine LLM generation failed (echo_chamber); falling back: {e}");
-                        choose_reply(persona.clone(), turn_resonance.resonance_score, intensity)
+                        warn!(target: "ghost", "LLM generation failed (echo_chamber); falling back: {e}");
+                        let id = choose_reply(persona.clone(), turn_resonance.resonance_score, intensity);
+                        reply_id = Some(id);
+                        canned(id)
                     }
                 };
             } else {
-                reply_text = choose_reply(persona.clone(), turn_resonance.resonance_score, intensity);
+                let id = choose_reply(persona.clone(), turn_resonance.resonance_score, intensity);
+                reply_id = Some(id);
+                reply_text = canned(id);
             }
         }
 
-        let withdrew = looks_like_withdrawal(&reply_text);
+        let withdrew = match reply_id {
+            Some(id) => WITHDRAWAL_REPLIES.contains(&id),
+            None => looks_like_withdrawal(&reply_text),
+        };
         group_replies.push(GroupTurnReply {
             speaker: persona_label.clone(),
             text: reply_text.clone(),
@@ -463,7 +565,7 @@ INSTRUCTIONS:\n- Produce ONE concise message as this speaker.\n- If a prior spea
 
     // Back-compat: single combined reply.
     let initial_reply = if group_replies.is_empty() {
-        choose_reply(primary_persona.clone(), resonance.resonance_score, intensity)
+        canned(choose_reply(primary_persona.clone(), resonance.resonance_score, intensity))
     } else if group_replies.len() == 1 {
         group_replies[0].text.clone()
     } else {
@@ -480,7 +582,7 @@ INSTRUCTIONS:\n- Produce ONE concise message as this speaker.\n- If a prior spea
     let end_load = crate::env_sensor::get_system_stress().cpu_usage_percent.min(100);
     
     // Step 5: Calculate drift and detect enmeshment
-    let drift = crate::analytics::calculate_drift(session_id, end_load);
+    let drift = crate::analytics::calculate_drift(&state.storage, session_id, end_load);
     
     // Phase 17: Automatic de-escalation if drift > 20%
     // If system load spiked by >20% during the simulation, override to Secure persona
@@ -493,7 +595,7 @@ INSTRUCTIONS:\n- Produce ONE concise message as this speaker.\n- If a prior spea
     let (final_persona, final_reply, final_resonance) = if drift_override && !initial_override {
         let secure_persona = PartnerPersona::Secure;
         let secure_resonance = analyze_resonance(&req.script, secure_persona.clone(), None);
-        let secure_reply = choose_reply(secure_persona.clone(), secure_resonance.resonance_score, intensity.min(50));
+        let secure_reply = canned(choose_reply(secure_persona.clone(), secure_resonance.resonance_score, intensity.min(50)));
         (secure_persona, secure_reply, secure_resonance)
     } else {
         (primary_persona, initial_reply, resonance)
@@ -506,22 +608,44 @@ INSTRUCTIONS:\n- Produce ONE concise message as this speaker.\n- If a prior spea
         paused = true;
         group_stress = group_stress.max(86);
         group_replies.push(GroupTurnReply {
-            speaker: "External Mediator (Sola)".to_string(),
-            text: "Pause. Group stress is high. I’m stepping in as an external mediator. Let’s take 60 seconds, lower intensity, and restate one observation + one request before continuing.".to_string(),
+            speaker: canned("ghost-mediator-speaker"),
+            text: canned("ghost-mediator"),
             resonance_score: None,
             risk_score: Some(risk_score),
             withdrew: false,
         });
     }
 
-    SimulateResponse {
+    metrics::ghost_simulation(drift.drift_alert);
+    info!(
+        target: "ghost",
+        session_id = %drift.session_id,
+        drift_delta = drift.drift_delta,
+        drift_alert = drift.drift_alert,
+        group_stress,
+        paused,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "simulation finished"
+    );
+    metrics::resonance_score("ghost", final_resonance.resonance_score);
+    let script = pagi_plugins::Input::Script(req.script.clone());
+    let plugin_findings = crate::plugins_api::analyze(&state.plugins, script).await;
+
+    let response = SimulateResponse {
         success: true,
         persona: normalize_persona_label(&final_persona).to_string(),
         intensity_level: intensity,
         resonance_score: final_resonance.resonance_score,
         ghost_reply: final_reply,
         flags: final_resonance.flags,
-        suggestions: final_resonance.suggestions,
+        suggestions: {
+            let mut suggestions = user_emotion
+                .as_ref()
+                .map(|u| emotion_suggestions(lang, u))
+                .unwrap_or_default();
+            suggestions.extend(final_resonance.suggestions);
+            suggestions
+        },
         breaches,
         risk_score,
 
@@ -539,6 +663,12 @@ INSTRUCTIONS:\n- Produce ONE concise message as this speaker.\n- If a prior spea
         group_replies,
         group_stress,
         paused,
-    }
+
+        user_emotion,
+
+        plugin_findings,
+    };
+    state.live.ghost_simulated(&response);
+    response
 }
 
@@ -0,0 +1,620 @@
+//! gRPC service for the pagi-twin switchboard.
+//!
+//! The same calls the switchboard makes over the IPC bridge and REST, without JSON: ghost
+//! simulation, resonance analysis, recorder control, plus a server-streaming `StreamEvents` that
+//! carries the [`crate::events`] alerts. Each call goes through the same validation and service
+//! code as its REST route. The wire contract is `proto/switchboard.proto`; the messages below
+//! are its hand-written prost form and the service stubs are generated by `build.rs`.
+//!
+//! Off by default (`features.grpc` / `PHOENIX_GRPC_ENABLED`); listens on `server.grpc_bind`
+//! (`PHOENIX_GRPC_BIND`, default `127.0.0.1:50051`). API keys are checked like on HTTP:
+//! `auth.api_auth` decides for this bind address, and the key goes in `authorization: Bearer …`
+//! or `x-api-key` metadata. Start/stop recording need `record`, everything else `read`.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_util::{stream, Stream};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::metadata::MetadataMap;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::api_keys::{ApiKeyStore, Scope};
+use crate::audit::Actor;
+use crate::ghost_api::{self, SimulateBody};
+use crate::ghost_engine::{self, SimulateResponse};
+use crate::recorder::{self, RecorderError};
+use crate::resonance::TextSpan as SpanInText;
+use crate::resonance_api::{self, AnalyzeRequest, ResonanceAnalysis};
+use crate::{events, AppState, FieldError};
+
+include!(concat!(
+    env!("OUT_DIR"),
+    "/phoenix.switchboard.v1.Switchboard.rs"
+));
+
+use switchboard_server::SwitchboardServer;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SimulateGhostRequest {
+    #[prost(string, tag = "1")]
+    pub script: String,
+    #[prost(string, tag = "2")]
+    pub persona_type: String,
+    #[prost(string, repeated, tag = "3")]
+    pub personas: Vec<String>,
+    #[prost(uint32, tag = "4")]
+    pub intensity_level: u32,
+    #[prost(uint32, optional, tag = "5")]
+    pub system_load: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct NvcBreach {
+    #[prost(string, tag = "1")]
+    pub kind: String,
+    #[prost(string, tag = "2")]
+    pub needle: String,
+    #[prost(string, tag = "3")]
+    pub message: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GroupTurnReply {
+    #[prost(string, tag = "1")]
+    pub speaker: String,
+    #[prost(string, tag = "2")]
+    pub text: String,
+    #[prost(uint32, optional, tag = "3")]
+    pub resonance_score: Option<u32>,
+    #[prost(uint32, optional, tag = "4")]
+    pub risk_score: Option<u32>,
+    #[prost(bool, tag = "5")]
+    pub withdrew: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UserEmotion {
+    #[prost(string, tag = "1")]
+    pub emotion: String,
+    #[prost(double, tag = "2")]
+    pub confidence: f64,
+    #[prost(string, tag = "3")]
+    pub source: String,
+    #[prost(string, repeated, tag = "4")]
+    pub cues: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SimulateGhostReply {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(string, tag = "2")]
+    pub persona: String,
+    #[prost(uint32, tag = "3")]
+    pub intensity_level: u32,
+    #[prost(uint32, tag = "4")]
+    pub resonance_score: u32,
+    #[prost(string, tag = "5")]
+    pub ghost_reply: String,
+    #[prost(string, repeated, tag = "6")]
+    pub flags: Vec<String>,
+    #[prost(string, repeated, tag = "7")]
+    pub suggestions: Vec<String>,
+    #[prost(message, repeated, tag = "8")]
+    pub breaches: Vec<NvcBreach>,
+    #[prost(uint32, tag = "9")]
+    pub risk_score: u32,
+    #[prost(string, tag = "10")]
+    pub session_id: String,
+    #[prost(uint32, tag = "11")]
+    pub system_load_start: u32,
+    #[prost(uint32, tag = "12")]
+    pub system_load_end: u32,
+    #[prost(sint32, tag = "13")]
+    pub drift_delta: i32,
+    #[prost(bool, tag = "14")]
+    pub drift_alert: bool,
+    #[prost(bool, tag = "15")]
+    pub override_deescalate: bool,
+    #[prost(bool, tag = "16")]
+    pub vector_used: bool,
+    #[prost(uint32, tag = "17")]
+    pub vector_matches: u32,
+    #[prost(message, repeated, tag = "18")]
+    pub group_replies: Vec<GroupTurnReply>,
+    #[prost(uint32, tag = "19")]
+    pub group_stress: u32,
+    #[prost(bool, tag = "20")]
+    pub paused: bool,
+    #[prost(message, optional, tag = "21")]
+    pub user_emotion: Option<UserEmotion>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnalyzeResonanceRequest {
+    #[prost(string, tag = "1")]
+    pub script: String,
+    #[prost(string, optional, tag = "2")]
+    pub persona: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub tone: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TextSpan {
+    #[prost(uint32, tag = "1")]
+    pub start: u32,
+    #[prost(uint32, tag = "2")]
+    pub end: u32,
+    #[prost(string, tag = "3")]
+    pub text: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BreachSpans {
+    #[prost(string, tag = "1")]
+    pub kind: String,
+    #[prost(string, tag = "2")]
+    pub needle: String,
+    #[prost(string, tag = "3")]
+    pub message: String,
+    #[prost(message, repeated, tag = "4")]
+    pub spans: Vec<TextSpan>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OfnrPart {
+    #[prost(string, tag = "1")]
+    pub component: String,
+    #[prost(bool, tag = "2")]
+    pub present: bool,
+    #[prost(message, repeated, tag = "3")]
+    pub spans: Vec<TextSpan>,
+    #[prost(string, optional, tag = "4")]
+    pub hint: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnalyzeResonanceReply {
+    #[prost(uint32, tag = "1")]
+    pub resonance_score: u32,
+    #[prost(string, tag = "2")]
+    pub persona: String,
+    #[prost(string, tag = "3")]
+    pub likely_response: String,
+    #[prost(string, repeated, tag = "4")]
+    pub flags: Vec<String>,
+    #[prost(string, repeated, tag = "5")]
+    pub strengths: Vec<String>,
+    #[prost(string, repeated, tag = "6")]
+    pub suggestions: Vec<String>,
+    #[prost(message, repeated, tag = "7")]
+    pub breaches: Vec<BreachSpans>,
+    #[prost(message, repeated, tag = "8")]
+    pub ofnr: Vec<OfnrPart>,
+    #[prost(bool, tag = "9")]
+    pub ofnr_complete: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RecordingStatusRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RecordingStatusReply {
+    #[prost(bool, tag = "1")]
+    pub enabled: bool,
+    #[prost(bool, tag = "2")]
+    pub listening: bool,
+    #[prost(bool, tag = "3")]
+    pub recording: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StartRecordingRequest {
+    #[prost(string, optional, tag = "1")]
+    pub purpose: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StartRecordingReply {
+    #[prost(string, tag = "1")]
+    pub session_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StopRecordingRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TranscriptSegment {
+    #[prost(string, tag = "1")]
+    pub speaker_id: String,
+    #[prost(double, tag = "2")]
+    pub start_time: f64,
+    #[prost(double, tag = "3")]
+    pub end_time: f64,
+    #[prost(string, tag = "4")]
+    pub text: String,
+    #[prost(float, tag = "5")]
+    pub confidence: f32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StopRecordingReply {
+    #[prost(string, tag = "1")]
+    pub session_id: String,
+    #[prost(int64, tag = "2")]
+    pub start_time: i64,
+    #[prost(int64, tag = "3")]
+    pub end_time: i64,
+    #[prost(message, repeated, tag = "4")]
+    pub segments: Vec<TranscriptSegment>,
+    #[prost(string, tag = "5")]
+    pub summary: String,
+    #[prost(string, repeated, tag = "6")]
+    pub keywords: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamEventsRequest {
+    /// Event names as for `GET /api/events?types=…`; empty means all of them.
+    #[prost(string, repeated, tag = "1")]
+    pub types: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Event {
+    #[prost(string, tag = "1")]
+    pub r#type: String,
+    /// The payload `GET /api/events` sends as `data`.
+    #[prost(string, tag = "2")]
+    pub data_json: String,
+}
+
+impl From<SimulateResponse> for SimulateGhostReply {
+    fn from(r: SimulateResponse) -> Self {
+        Self {
+            success: r.success,
+            persona: r.persona,
+            intensity_level: r.intensity_level.into(),
+            resonance_score: r.resonance_score.into(),
+            ghost_reply: r.ghost_reply,
+            flags: r.flags,
+            suggestions: r.suggestions,
+            breaches: r
+                .breaches
+                .into_iter()
+                .map(|b| NvcBreach {
+                    kind: b.kind,
+                    needle: b.needle,
+                    message: b.message,
+                })
+                .collect(),
+            risk_score: r.risk_score.into(),
+            session_id: r.session_id,
+            system_load_start: r.system_load_start.into(),
+            system_load_end: r.system_load_end.into(),
+            drift_delta: r.drift_delta.into(),
+            drift_alert: r.drift_alert,
+            override_deescalate: r.override_deescalate,
+            vector_used: r.vector_used,
+            vector_matches: r.vector_matches.try_into().unwrap_or(u32::MAX),
+            group_replies: r
+                .group_replies
+                .into_iter()
+                .map(|g| GroupTurnReply {
+                    speaker: g.speaker,
+                    text: g.text,
+                    resonance_score: g.resonance_score.map(u32::from),
+                    risk_score: g.risk_score.map(u32::from),
+                    withdrew: g.withdrew,
+                })
+                .collect(),
+            group_stress: r.group_stress.into(),
+            paused: r.paused,
+            user_emotion: r.user_emotion.map(|e| UserEmotion {
+                emotion: e.emotion,
+                confidence: e.confidence,
+                source: e.source,
+                cues: e.cues,
+            }),
+        }
+    }
+}
+
+fn spans(spans: Vec<SpanInText>) -> Vec<TextSpan> {
+    let offset = |n: usize| u32::try_from(n).unwrap_or(u32::MAX);
+    spans
+        .into_iter()
+        .map(|s| TextSpan {
+            start: offset(s.start),
+            end: offset(s.end),
+            text: s.text,
+        })
+        .collect()
+}
+
+impl From<ResonanceAnalysis> for AnalyzeResonanceReply {
+    fn from(a: ResonanceAnalysis) -> Self {
+        Self {
+            resonance_score: a.result.resonance_score.into(),
+            persona: a.result.persona,
+            likely_response: a.result.likely_response,
+            flags: a.result.flags,
+            strengths: a.result.strengths,
+            suggestions: a.result.suggestions,
+            breaches: a
+                .breaches
+                .into_iter()
+                .map(|b| BreachSpans {
+                    kind: b.kind,
+                    needle: b.needle,
+                    message: b.message,
+                    spans: spans(b.spans),
+                })
+                .collect(),
+            ofnr: a
+                .ofnr
+                .parts
+                .into_iter()
+                .map(|p| OfnrPart {
+                    component: p.component.as_str().to_string(),
+                    present: p.present,
+                    spans: spans(p.spans),
+                    hint: p.hint,
+                })
+                .collect(),
+            ofnr_complete: a.ofnr.complete,
+        }
+    }
+}
+
+fn invalid(errors: Vec<FieldError>) -> Status {
+    let fields = errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>();
+    Status::invalid_argument(fields.join("; "))
+}
+
+impl From<RecorderError> for Status {
+    fn from(e: RecorderError) -> Self {
+        match e {
+            RecorderError::Disabled => Status::unavailable(e.to_string()),
+            RecorderError::Failed(_) => Status::failed_precondition(e.to_string()),
+        }
+    }
+}
+
+fn presented_key(metadata: &MetadataMap) -> Option<&str> {
+    if let Some(key) = metadata.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return Some(key);
+    }
+    metadata
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+struct SwitchboardService {
+    state: AppState,
+    /// `None` when API keys are not enforced for the gRPC bind address.
+    keys: Option<Arc<ApiKeyStore>>,
+}
+
+impl SwitchboardService {
+    // `Status` is what every handler returns anyway.
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(&self, request: &Request<T>, required: Scope) -> Result<Actor, Status> {
+        let Some(store) = &self.keys else {
+            return Ok(Actor::Local);
+        };
+        let Some(key) = presented_key(request.metadata()) else {
+            return Err(Status::unauthenticated("missing API key"));
+        };
+        let Some(record) = store.verify(key) else {
+            return Err(Status::unauthenticated("invalid or revoked API key"));
+        };
+        if !record.scopes.iter().any(|s| s.grants(required)) {
+            return Err(Status::permission_denied(format!(
+                "this key lacks the `{}` scope",
+                required.as_str()
+            )));
+        }
+        Ok(Actor::ApiKey(record.name))
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl switchboard_server::Switchboard for SwitchboardService {
+    async fn simulate_ghost(
+        &self,
+        request: Request<SimulateGhostRequest>,
+    ) -> Result<Response<SimulateGhostReply>, Status> {
+        self.authorize(&request, Scope::Read)?;
+        let language = request
+            .metadata()
+            .get("accept-language")
+            .and_then(|v| v.to_str().ok())
+            .and_then(pagi_i18n::from_accept_language)
+            .map(str::to_string);
+        let req = request.into_inner();
+        let req = ghost_api::validate(SimulateBody {
+            script: req.script,
+            persona_type: req.persona_type,
+            personas: req.personas,
+            intensity_level: req.intensity_level.into(),
+            system_load: req.system_load.map(i64::from),
+            language,
+        })
+        .map_err(invalid)?;
+        let resp = ghost_engine::simulate(&self.state, req).await;
+        Ok(Response::new(resp.into()))
+    }
+
+    async fn analyze_resonance(
+        &self,
+        request: Request<AnalyzeResonanceRequest>,
+    ) -> Result<Response<AnalyzeResonanceReply>, Status> {
+        self.authorize(&request, Scope::Read)?;
+        let req = request.into_inner();
+        let analysis = resonance_api::run(
+            &self.state.cache,
+            &AnalyzeRequest {
+                script: req.script,
+                persona: req.persona,
+                tone: req.tone,
+            },
+        )
+        .map_err(invalid)?;
+        Ok(Response::new(analysis.into()))
+    }
+
+    async fn recording_status(
+        &self,
+        request: Request<RecordingStatusRequest>,
+    ) -> Result<Response<RecordingStatusReply>, Status> {
+        self.authorize(&request, Scope::Read)?;
+        let status = recorder::status(&self.state).await;
+        Ok(Response::new(RecordingStatusReply {
+            enabled: status.enabled,
+            listening: status.listening,
+            recording: status.recording,
+        }))
+    }
+
+    async fn start_recording(
+        &self,
+        request: Request<StartRecordingRequest>,
+    ) -> Result<Response<StartRecordingReply>, Status> {
+        let actor = self.authorize(&request, Scope::Record)?;
+        let session_id = recorder::start(&self.state, &actor, request.into_inner().purpose).await?;
+        Ok(Response::new(StartRecordingReply { session_id }))
+    }
+
+    async fn stop_recording(
+        &self,
+        request: Request<StopRecordingRequest>,
+    ) -> Result<Response<StopRecordingReply>, Status> {
+        let actor = self.authorize(&request, Scope::Record)?;
+        let t = recorder::stop(&self.state, &actor).await?;
+        Ok(Response::new(StopRecordingReply {
+            session_id: t.session_id,
+            start_time: t.start_time,
+            end_time: t.end_time,
+            segments: t
+                .segments
+                .into_iter()
+                .map(|s| TranscriptSegment {
+                    speaker_id: s.speaker_id,
+                    start_time: s.start_time,
+                    end_time: s.end_time,
+                    text: s.text,
+                    confidence: s.confidence,
+                })
+                .collect(),
+            summary: t.summary,
+            keywords: t.keywords,
+        }))
+    }
+
+    type StreamEventsStream = EventStream;
+
+    async fn stream_events(
+        &self,
+        request: Request<StreamEventsRequest>,
+    ) -> Result<Response<EventStream>, Status> {
+        self.authorize(&request, Scope::Read)?;
+        let names = request.get_ref().types.iter().map(|t| t.trim());
+        let types = events::event_types(names).map_err(Status::invalid_argument)?;
+        let subscription = events::Subscription::new(&self.state, types);
+        let events = stream::unfold(subscription, |mut sub| async move {
+            let (name, data) = sub.next().await?;
+            let event = Event {
+                r#type: name.to_string(),
+                data_json: data.to_string(),
+            };
+            Some((Ok(event), sub))
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// Serve the switchboard on `bind` until the server shuts down. Failing to bind is logged, not
+/// fatal.
+pub(crate) async fn run(state: AppState, bind: String, keys: Option<Arc<ApiKeyStore>>) {
+    let listener = match TcpListener::bind(&bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("gRPC switchboard unavailable on {bind}: {e}");
+            return;
+        }
+    };
+    match listener.local_addr() {
+        Ok(addr) => info!("gRPC switchboard listening on {addr}"),
+        Err(_) => info!("gRPC switchboard listening on {bind}"),
+    }
+    let mut closing = state.live.closing();
+    let service = SwitchboardServer::new(SwitchboardService { state, keys });
+    let served = Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+            let _ = closing.wait_for(|closing| *closing).await;
+        })
+        .await;
+    if let Err(e) = served {
+        warn!("gRPC switchboard stopped: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resonance::PartnerPersona;
+    use prost::Message;
+
+    #[test]
+    fn resonance_reply_keeps_spans_and_parts() {
+        let script = "You always ignore me. Would you be willing to call?";
+        let reply = AnalyzeResonanceReply::from(resonance_api::analyze(
+            script,
+            PartnerPersona::Secure,
+            None,
+        ));
+        assert_eq!(
+            reply
+                .ofnr
+                .iter()
+                .map(|p| p.component.as_str())
+                .collect::<Vec<_>>(),
+            ["observation", "feeling", "need", "request"]
+        );
+        let always = reply
+            .breaches
+            .iter()
+            .find(|b| b.needle == "always")
+            .unwrap();
+        assert_eq!(always.spans[0].start, 4);
+
+        let decoded = AnalyzeResonanceReply::decode(reply.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, reply);
+    }
+
+    #[test]
+    fn field_errors_become_invalid_argument() {
+        let status = invalid(vec![
+            FieldError::new("script", "must not be empty"),
+            FieldError::new("intensity_level", "must be between 0 and 100"),
+        ]);
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "script: must not be empty; intensity_level: must be between 0 and 100"
+        );
+    }
+}
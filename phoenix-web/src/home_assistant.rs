@@ -0,0 +1,604 @@
+//! The app as a Home Assistant device over MQTT.
+//!
+//! With `[mqtt] broker` set, the server connects to the broker (`mqtt://`, or `mqtts://` for
+//! TLS) and announces its entities in
+//! Home Assistant's discovery format, under `<discovery_prefix>/<component>/<node_id>/…/config`
+//! (retained, so Home Assistant finds them after its own restarts too):
+//!
+//! - `sensor` presence: the display name of the profile recognized by voice or face in the last
+//!   five minutes (`Dad`), `visitor` after an unknown presence, otherwise `nobody`;
+//! - `sensor` emotion: the latest emotion estimate, with its confidence and profile as
+//!   attributes;
+//! - `binary_sensor` recording: whether a capture is running;
+//! - `sensor` stress: CPU load in percent, as sampled for the stress readings;
+//! - `switch` privacy mode: guest mode, which stops emotion inference and recognition (and
+//!   recordings, unless guests allow them);
+//! - `button` record: starts a capture with the defaults of `POST /api/recorder/record`. A JSON
+//!   object with that route's fields can be published to the same topic instead of `PRESS`.
+//!
+//! States go to `<node_id>/<entity>`, commands come in on `<node_id>/privacy/set` and
+//! `<node_id>/record/press`, and `<node_id>/availability` turns `offline` through the last
+//! will when the server goes away. When Home Assistant comes back (`<discovery_prefix>/status`
+//! = `online`) the entities and states are announced again. A lost connection is retried with a
+//! growing delay of up to five minutes. Commands are audited as `mqtt`.
+
+use std::fmt;
+use std::time::Duration;
+
+use chrono::Utc;
+use multi_modal_recording::emotion_history::{EmotionUpdate, ATTRIBUTION_WINDOW_SECS};
+use multi_modal_recording::RecorderStatus;
+use pagi_config::{ConfigError, Layers, Source};
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::audit::{self, Action, Actor};
+use crate::live_events::LiveEvent;
+use crate::mqtt::{Client, ConnectOptions, Message, MqttError, Security, Will};
+use crate::recorder_api::{self, CaptureRequest};
+use crate::AppState;
+
+pub const DEFAULT_PORT: u16 = 1883;
+pub const DEFAULT_TLS_PORT: u16 = 8883;
+pub const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+pub const DEFAULT_NODE_ID: &str = "pagi_twin";
+
+const KEEP_ALIVE_SECS: u16 = 60;
+/// How often presence is looked at.
+const TICK: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+const ON: &str = "ON";
+const OFF: &str = "OFF";
+
+/// Where the broker is and how to log in.
+#[derive(Clone)]
+pub struct Broker {
+    pub host: String,
+    pub port: u16,
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for Broker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Broker")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("security", &self.security)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "********"))
+            .finish()
+    }
+}
+
+/// `[mqtt]` settings.
+#[derive(Debug, Clone)]
+pub struct MqttSettings {
+    /// The bridge is off without one.
+    pub broker: Option<Broker>,
+    pub discovery_prefix: String,
+    /// Names this install in topics and entity ids, for more than one on a broker.
+    pub node_id: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            broker: None,
+            discovery_prefix: DEFAULT_DISCOVERY_PREFIX.to_string(),
+            node_id: DEFAULT_NODE_ID.to_string(),
+        }
+    }
+}
+
+fn invalid(key: &'static str, layers: &Layers, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key,
+        origin: layers
+            .get(key)
+            .map_or(Source::Default, |(_, source)| source),
+        message: message.into(),
+    }
+}
+
+fn valid_topic_part(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+impl MqttSettings {
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        let broker = match layers.get("mqtt.broker") {
+            None => None,
+            Some((raw, _)) => {
+                let url =
+                    Url::parse(&raw).map_err(|e| invalid("mqtt.broker", layers, e.to_string()))?;
+                let (security, default_port) = match url.scheme() {
+                    "mqtt" => (Security::Plain, DEFAULT_PORT),
+                    "mqtts" => (
+                        Security::Tls {
+                            ca_file: layers.path("mqtt.ca_file"),
+                        },
+                        DEFAULT_TLS_PORT,
+                    ),
+                    _ => {
+                        return Err(invalid(
+                            "mqtt.broker",
+                            layers,
+                            "must be mqtt://host[:port] or mqtts://host[:port]",
+                        ))
+                    }
+                };
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| invalid("mqtt.broker", layers, "has no host"))?;
+                Some(Broker {
+                    host: host.to_string(),
+                    port: url.port().unwrap_or(default_port),
+                    security,
+                    username: layers.get("mqtt.username").map(|(user, _)| user),
+                    password: layers.secret("mqtt.password")?,
+                })
+            }
+        };
+        let discovery_prefix = layers.get("mqtt.discovery_prefix").map_or_else(
+            || DEFAULT_DISCOVERY_PREFIX.to_string(),
+            |(prefix, _)| prefix,
+        );
+        if !valid_topic_part(&discovery_prefix) {
+            return Err(invalid(
+                "mqtt.discovery_prefix",
+                layers,
+                "letters, digits, `_` and `-` only",
+            ));
+        }
+        let node_id = layers
+            .get("mqtt.node_id")
+            .map_or_else(|| DEFAULT_NODE_ID.to_string(), |(node, _)| node);
+        if !valid_topic_part(&node_id) {
+            return Err(invalid(
+                "mqtt.node_id",
+                layers,
+                "letters, digits, `_` and `-` only",
+            ));
+        }
+        Ok(Self {
+            broker,
+            discovery_prefix,
+            node_id,
+        })
+    }
+
+    fn topic(&self, entity: &str) -> String {
+        format!("{}/{entity}", self.node_id)
+    }
+
+    /// Every entity's discovery topic and config.
+    fn discovery(&self) -> Vec<(String, Value)> {
+        let node = &self.node_id;
+        let device = json!({
+            "identifiers": [node],
+            "name": "PAGI Twin",
+            "manufacturer": "PAGI",
+            "model": "pagi-sola-web",
+            "sw_version": env!("CARGO_PKG_VERSION"),
+        });
+        let entities = [
+            (
+                "sensor",
+                "presence",
+                json!({
+                    "name": "Presence",
+                    "icon": "mdi:account-eye",
+                    "state_topic": self.topic("presence"),
+                }),
+            ),
+            (
+                "sensor",
+                "emotion",
+                json!({
+                    "name": "Emotion",
+                    "icon": "mdi:emoticon-outline",
+                    "state_topic": self.topic("emotion"),
+                    "json_attributes_topic": self.topic("emotion/attributes"),
+                }),
+            ),
+            (
+                "binary_sensor",
+                "recording",
+                json!({
+                    "name": "Recording",
+                    "icon": "mdi:record-rec",
+                    "state_topic": self.topic("recording"),
+                }),
+            ),
+            (
+                "sensor",
+                "stress",
+                json!({
+                    "name": "Stress level",
+                    "icon": "mdi:gauge",
+                    "unit_of_measurement": "%",
+                    "state_class": "measurement",
+                    "state_topic": self.topic("stress"),
+                }),
+            ),
+            (
+                "switch",
+                "privacy",
+                json!({
+                    "name": "Privacy mode",
+                    "icon": "mdi:incognito",
+                    "state_topic": self.topic("privacy"),
+                    "command_topic": self.topic("privacy/set"),
+                }),
+            ),
+            (
+                "button",
+                "record",
+                json!({
+                    "name": "Start recording",
+                    "icon": "mdi:microphone",
+                    "command_topic": self.topic("record/press"),
+                }),
+            ),
+        ];
+        entities
+            .into_iter()
+            .map(|(component, object, mut config)| {
+                config["unique_id"] = json!(format!("{node}_{object}"));
+                config["availability_topic"] = json!(self.topic("availability"));
+                config["device"] = device.clone();
+                let topic = format!(
+                    "{}/{component}/{node}/{object}/config",
+                    self.discovery_prefix
+                );
+                (topic, config)
+            })
+            .collect()
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        ON
+    } else {
+        OFF
+    }
+}
+
+/// The presence sensor's state.
+fn presence(state: &AppState) -> String {
+    let recorder = state.capture.recorder();
+    if let Some(id) = recorder.recognized_profile() {
+        return match state.storage.profile(&id) {
+            Ok(Some(profile)) => profile.display_name,
+            _ => id,
+        };
+    }
+    let recent_visitor = recorder
+        .unknown_presence_recent(1)
+        .last()
+        .is_some_and(|event| Utc::now().timestamp() - event.ts_unix <= ATTRIBUTION_WINDOW_SECS);
+    if recent_visitor { "visitor" } else { "nobody" }.to_string()
+}
+
+/// What woke the bridge up.
+enum Event {
+    Message(Message),
+    Status(Result<RecorderStatus, RecvError>),
+    Emotion(Result<EmotionUpdate, RecvError>),
+    Live(Result<LiveEvent, RecvError>),
+    Tick,
+    Closing,
+}
+
+/// One connection to the broker, from the first announcement to its loss.
+struct Session<'a> {
+    state: &'a AppState,
+    settings: &'a MqttSettings,
+    client: Client,
+    presence: String,
+}
+
+impl Session<'_> {
+    async fn publish(&mut self, entity: &str, payload: &str) -> Result<(), MqttError> {
+        let topic = self.settings.topic(entity);
+        self.client.publish(&topic, payload.as_bytes(), true).await
+    }
+
+    /// Announce the entities and their current states.
+    async fn announce(&mut self) -> Result<(), MqttError> {
+        for (topic, config) in self.settings.discovery() {
+            self.client
+                .publish(&topic, config.to_string().as_bytes(), true)
+                .await?;
+        }
+        self.publish("availability", ONLINE).await?;
+        let status = self.state.capture.recorder().status();
+        self.status(&status).await?;
+        if let Some(reading) = self.state.live.latest_stress() {
+            self.publish("stress", &reading.stress.cpu_usage_percent.to_string())
+                .await?;
+        }
+        self.presence = presence(self.state);
+        let presence = self.presence.clone();
+        self.publish("presence", &presence).await
+    }
+
+    async fn status(&mut self, status: &RecorderStatus) -> Result<(), MqttError> {
+        self.publish("recording", on_off(status.recording)).await?;
+        self.publish("privacy", on_off(status.guest_mode)).await
+    }
+
+    async fn emotion(&mut self, update: &EmotionUpdate) -> Result<(), MqttError> {
+        let label = update
+            .reported
+            .as_ref()
+            .map_or(update.moment.emotion.as_str(), |reported| {
+                reported.label.as_str()
+            });
+        let attributes = json!({
+            "confidence": update.moment.confidence,
+            "intensity": update.moment.intensity,
+            "profile": update.moment.profile,
+            "ts_unix": update.moment.ts_unix,
+        });
+        self.publish("emotion/attributes", &attributes.to_string())
+            .await?;
+        self.publish("emotion", label).await
+    }
+
+    async fn refresh_presence(&mut self) -> Result<(), MqttError> {
+        let now = presence(self.state);
+        if now != self.presence {
+            self.presence = now.clone();
+            self.publish("presence", &now).await?;
+        }
+        Ok(())
+    }
+
+    /// Act on a message from a subscribed topic.
+    async fn command(&mut self, topic: &str, payload: &[u8]) -> Result<(), MqttError> {
+        let payload = String::from_utf8_lossy(payload);
+        let payload = payload.trim();
+        if topic == self.settings.topic("privacy/set") {
+            let enabled = match payload {
+                ON => true,
+                OFF => false,
+                other => {
+                    warn!(target: "mqtt", "ignoring privacy command {other:?}; send ON or OFF");
+                    return Ok(());
+                }
+            };
+            let recorder = self.state.capture.recorder();
+            recorder.set_guest_mode(enabled, recorder.guest_mode().allow_recordings);
+            audit::record(
+                self.state,
+                &Actor::Mqtt,
+                Action::GuestModeSet,
+                json!({ "enabled": enabled }),
+            );
+        } else if topic == self.settings.topic("record/press") {
+            let capture = if payload.starts_with('{') {
+                match serde_json::from_str::<CaptureRequest>(payload) {
+                    Ok(capture) => capture,
+                    Err(e) => {
+                        warn!(target: "mqtt", "ignoring record command: {e}");
+                        return Ok(());
+                    }
+                }
+            } else {
+                CaptureRequest::default()
+            };
+            if let Err(e) = recorder_api::start_capture(self.state, &Actor::Mqtt, &capture) {
+                warn!(target: "mqtt", "record command refused: {}", e.message);
+            }
+        } else if topic == format!("{}/status", self.settings.discovery_prefix) && payload == ONLINE
+        {
+            self.announce().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Connect, announce, and relay until the connection fails (`Err`) or the server shuts down.
+async fn run_session(
+    state: &AppState,
+    settings: &MqttSettings,
+    broker: &Broker,
+    retry_delay: &mut Duration,
+) -> Result<(), MqttError> {
+    let options = ConnectOptions {
+        client_id: format!("{}-{}", settings.node_id, std::process::id()),
+        username: broker.username.clone(),
+        password: broker.password.clone(),
+        keep_alive_secs: KEEP_ALIVE_SECS,
+        will: Some(Will {
+            topic: settings.topic("availability"),
+            payload: OFFLINE.as_bytes().to_vec(),
+            retain: true,
+        }),
+        security: broker.security.clone(),
+    };
+    let client = Client::connect(&broker.host, broker.port, &options).await?;
+    info!(target: "mqtt", host = %broker.host, port = broker.port, "connected to the MQTT broker");
+    *retry_delay = Duration::from_secs(1);
+
+    let recorder = state.capture.recorder();
+    let mut statuses = recorder.subscribe_status();
+    let mut emotions = recorder.subscribe_emotions();
+    let mut live = state.live.subscribe();
+    let mut closing = state.live.closing();
+    let mut tick = tokio::time::interval(TICK);
+    tick.reset();
+
+    let mut session = Session {
+        state,
+        settings,
+        client,
+        presence: String::new(),
+    };
+    session
+        .client
+        .subscribe(&[
+            settings.topic("privacy/set"),
+            settings.topic("record/press"),
+            format!("{}/status", settings.discovery_prefix),
+        ])
+        .await?;
+    session.announce().await?;
+
+    loop {
+        // Pick the next event first: the shutdown signal's guard mustn't be held across awaits.
+        let event = tokio::select! {
+            message = session.client.next() => Event::Message(message?),
+            status = statuses.recv() => Event::Status(status),
+            update = emotions.recv() => Event::Emotion(update),
+            event = live.recv() => Event::Live(event),
+            _ = tick.tick() => Event::Tick,
+            _ = closing.wait_for(|closing| *closing) => Event::Closing,
+        };
+        match event {
+            Event::Message(Message { topic, payload }) => {
+                session.command(&topic, &payload).await?;
+            }
+            Event::Status(Ok(status)) => session.status(&status).await?,
+            Event::Emotion(Ok(update)) => session.emotion(&update).await?,
+            Event::Live(Ok(LiveEvent::StressSample { stress, .. })) => {
+                session
+                    .publish("stress", &stress.cpu_usage_percent.to_string())
+                    .await?;
+            }
+            Event::Live(Ok(LiveEvent::UnknownPresence { .. })) => {
+                session.refresh_presence().await?;
+            }
+            Event::Status(Err(RecvError::Closed))
+            | Event::Emotion(Err(RecvError::Closed))
+            | Event::Live(Err(RecvError::Closed)) => return Ok(()),
+            Event::Status(Err(RecvError::Lagged(_)))
+            | Event::Emotion(Err(RecvError::Lagged(_)))
+            | Event::Live(_) => {}
+            Event::Tick => session.refresh_presence().await?,
+            Event::Closing => {
+                session.publish("availability", OFFLINE).await?;
+                return session.client.disconnect().await;
+            }
+        }
+    }
+}
+
+/// Run the bridge when `[mqtt] broker` is set, reconnecting until the server shuts down.
+pub(crate) fn spawn(state: &AppState, settings: MqttSettings) -> Option<JoinHandle<()>> {
+    let broker = settings.broker.clone()?;
+    if broker.security == Security::Plain
+        && (broker.username.is_some() || broker.password.is_some())
+    {
+        warn!(
+            target: "mqtt",
+            "MQTT credentials for {} go over plain TCP; use an mqtts:// broker to encrypt them",
+            broker.host
+        );
+    }
+    let state = state.clone();
+    Some(tokio::spawn(async move {
+        let mut closing = state.live.closing();
+        let mut retry_delay = Duration::from_secs(1);
+        loop {
+            match run_session(&state, &settings, &broker, &mut retry_delay).await {
+                Ok(()) => return,
+                Err(e) => warn!(
+                    target: "mqtt",
+                    "MQTT bridge to {}:{}: {e}; retrying in {}s",
+                    broker.host,
+                    broker.port,
+                    retry_delay.as_secs()
+                ),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(retry_delay) => {}
+                _ = closing.wait_for(|closing| *closing) => return,
+            }
+            retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pagi_config::Overrides;
+
+    #[test]
+    fn discovery_names_every_entity_under_the_node() {
+        let settings = MqttSettings {
+            node_id: "living_room".to_string(),
+            ..MqttSettings::default()
+        };
+        let discovery = settings.discovery();
+        let topics: Vec<&str> = discovery.iter().map(|(topic, _)| topic.as_str()).collect();
+        assert_eq!(
+            topics,
+            [
+                "homeassistant/sensor/living_room/presence/config",
+                "homeassistant/sensor/living_room/emotion/config",
+                "homeassistant/binary_sensor/living_room/recording/config",
+                "homeassistant/sensor/living_room/stress/config",
+                "homeassistant/switch/living_room/privacy/config",
+                "homeassistant/button/living_room/record/config",
+            ]
+        );
+        let (_, privacy) = &discovery[4];
+        assert_eq!(privacy["unique_id"], "living_room_privacy");
+        assert_eq!(privacy["command_topic"], "living_room/privacy/set");
+        assert_eq!(privacy["availability_topic"], "living_room/availability");
+    }
+
+    #[test]
+    fn broker_urls_give_host_port_and_credentials() {
+        let layers =
+            |file: &str| Layers::parse("phoenix.toml", file, &Overrides::default()).unwrap();
+        let file = "[mqtt]\nbroker = \"mqtt://broker.lan\"\nusername = \"ha\"\npassword = \"pw\"";
+        let broker = MqttSettings::from_layers(&layers(file))
+            .unwrap()
+            .broker
+            .unwrap();
+        assert_eq!(
+            (broker.host.as_str(), broker.port),
+            ("broker.lan", DEFAULT_PORT)
+        );
+        assert_eq!(broker.username.as_deref(), Some("ha"));
+        assert!(!format!("{broker:?}").contains("pw"));
+
+        assert_eq!(broker.security, Security::Plain);
+
+        let file = "[mqtt]\nbroker = \"mqtts://broker.lan\"\nca_file = \"/etc/mqtt/ca.pem\"";
+        let broker = MqttSettings::from_layers(&layers(file))
+            .unwrap()
+            .broker
+            .unwrap();
+        assert_eq!(broker.port, DEFAULT_TLS_PORT);
+        assert_eq!(
+            broker.security,
+            Security::Tls {
+                ca_file: Some("/etc/mqtt/ca.pem".into())
+            }
+        );
+        assert!(
+            MqttSettings::from_layers(&layers("[mqtt]\nbroker = \"ws://broker.lan\"")).is_err()
+        );
+        assert!(MqttSettings::from_layers(&layers("[mqtt]\nnode_id = \"a/b\"")).is_err());
+        assert!(MqttSettings::from_layers(&layers(""))
+            .unwrap()
+            .broker
+            .is_none());
+    }
+}
@@ -0,0 +1,258 @@
+// phoenix-web/src/ipc_bridge.rs
+//
+// Local IPC surface for the pagi-twin switchboard (no HTTP).
+//
+// Transport: a Unix domain socket (mode 0600, only the server's own uid may connect) or, on
+// Windows, a named pipe; the endpoint comes from `common_types::ports::PhoenixIpcEndpoint`
+// (`PHOENIX_IPC_PATH`). Set `PHOENIX_IPC_DISABLED=1` (or `features.ipc_bridge = false`) to turn
+// the bridge off.
+//
+// Protocol: JSON-RPC 2.0, one request object per line, one response per line, requests on a
+// connection answered in order. Batches are not supported. See docs/IPC_BRIDGE.md.
+
+use common_types::ports::PhoenixIpcEndpoint;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{debug, info, warn};
+
+use crate::audit::Actor;
+use crate::ghost_engine::{self, SimulateRequest};
+use crate::{recorder, status_snapshot, AppState};
+
+/// Longest accepted request line.
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+
+/// Methods, in the order `rpc.methods` lists them.
+pub const METHODS: &[&str] = &[
+    "rpc.methods",
+    "status",
+    "recording.status",
+    "recording.start",
+    "recording.stop",
+    "ghost.simulate",
+];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The method ran and failed (e.g. recording not available).
+const APP_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: Option<String>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    /// Absent for notifications, which get no response.
+    id: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StartRecordingParams {
+    #[serde(default)]
+    purpose: Option<String>,
+}
+
+fn params<T: for<'de> Deserialize<'de> + Default>(params: Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value(v: impl Serialize) -> Result<Value, RpcError> {
+    serde_json::to_value(v).map_err(|e| RpcError::new(APP_ERROR, e.to_string()))
+}
+
+async fn dispatch(state: &AppState, method: &str, params_in: Value) -> Result<Value, RpcError> {
+    match method {
+        "rpc.methods" => Ok(json!(METHODS)),
+        "status" => to_value(status_snapshot(state).await),
+        "recording.status" => to_value(recorder::status(state).await),
+        "recording.start" => {
+            let p: StartRecordingParams = params(params_in)?;
+            let session_id = recorder::start(state, &Actor::Ipc, p.purpose)
+                .await
+                .map_err(|e| RpcError::new(APP_ERROR, e.to_string()))?;
+            Ok(json!({"status": "recording", "session_id": session_id}))
+        }
+        "recording.stop" => {
+            let transcript = recorder::stop(state, &Actor::Ipc)
+                .await
+                .map_err(|e| RpcError::new(APP_ERROR, e.to_string()))?;
+            Ok(json!({"status": "stopped", "transcript": transcript}))
+        }
+        "ghost.simulate" => {
+            let req: SimulateRequest = serde_json::from_value(params_in)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+            to_value(ghost_engine::simulate(state, req).await)
+        }
+        other => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method '{other}'"),
+        )),
+    }
+}
+
+fn response(id: Value, outcome: Result<Value, RpcError>) -> Value {
+    match outcome {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => json!({"jsonrpc": "2.0", "id": id, "error": error}),
+    }
+}
+
+/// Handle one request line; `None` for notifications.
+async fn handle_line(state: &AppState, line: &str) -> Option<Value> {
+    let raw: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => {
+            return Some(response(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, e.to_string())),
+            ))
+        }
+    };
+    let id_hint = raw.get("id").cloned().unwrap_or(Value::Null);
+    let req: Request = match serde_json::from_value(raw) {
+        Ok(r) => r,
+        Err(e) => {
+            return Some(response(
+                id_hint,
+                Err(RpcError::new(INVALID_REQUEST, e.to_string())),
+            ))
+        }
+    };
+    if req.jsonrpc.as_deref() != Some("2.0") {
+        return Some(response(
+            id_hint,
+            Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\"")),
+        ));
+    }
+    debug!("ipc: {}", req.method);
+    let outcome = dispatch(state, &req.method, req.params).await;
+    req.id.map(|id| response(id, outcome))
+}
+
+async fn serve_connection<S>(state: AppState, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match (&mut reader)
+            .take(MAX_REQUEST_BYTES + 1)
+            .read_line(&mut line)
+            .await
+        {
+            Ok(0) => return,
+            Ok(n) if n as u64 > MAX_REQUEST_BYTES => {
+                let err = response(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, "request too large")),
+                );
+                let _ = writer.write_all(format!("{err}\n").as_bytes()).await;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                debug!("ipc: read failed: {e}");
+                return;
+            }
+        }
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(resp) = handle_line(&state, trimmed).await {
+            if writer
+                .write_all(format!("{resp}\n").as_bytes())
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Serve the bridge until the process exits. Failing to bind is logged, not fatal.
+pub async fn run(state: AppState) {
+    let endpoint = PhoenixIpcEndpoint::path();
+    if let Err(e) = listen(state, &endpoint).await {
+        warn!("IPC bridge unavailable at {endpoint}: {e}");
+    }
+}
+
+#[cfg(unix)]
+async fn listen(state: AppState, endpoint: &str) -> std::io::Result<()> {
+    use crate::listener::{bind_private, current_uid, socket_dir};
+    use tokio::net::{UnixListener, UnixStream};
+
+    let uid = current_uid();
+    let path = std::path::Path::new(endpoint);
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        socket_dir(dir, uid)?;
+    }
+    if path.exists() {
+        // A live socket means another instance owns it; otherwise it is left over from a crash.
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "another instance is serving this socket",
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = bind_private(|| UnixListener::bind(path))?;
+    info!("IPC bridge listening on {endpoint}");
+    loop {
+        let (stream, _) = listener.accept().await?;
+        match stream.peer_cred() {
+            Ok(peer) if peer.uid() == uid => {
+                tokio::spawn(serve_connection(state.clone(), stream));
+            }
+            Ok(peer) => warn!("ipc: refused a connection from uid {}", peer.uid()),
+            Err(e) => warn!("ipc: refused a connection with unknown credentials: {e}"),
+        }
+    }
+}
+
+#[cfg(windows)]
+async fn listen(state: AppState, endpoint: &str) -> std::io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(endpoint)?;
+    info!("IPC bridge listening on {endpoint}");
+    loop {
+        server.connect().await?;
+        let connected = server;
+        server = ServerOptions::new()
+            .reject_remote_clients(true)
+            .create(endpoint)?;
+        tokio::spawn(serve_connection(state.clone(), connected));
+    }
+}
@@ -0,0 +1,378 @@
+//! Append-only journal of significant events, kept apart from the database in
+//! `data/journal.jsonl` so it can vouch for what happened to the data whatever state the data
+//! is in.
+//!
+//! Everything [`crate::audit`] records goes here too, along with finished captures, retention
+//! pruning and server starts. Each line is `{"hash", "entry"}`: `entry` holds the sequence
+//! number, time, event name, actor, details and the previous line's hash, and `hash` is the
+//! SHA-256 of `entry` exactly as written. Editing, removing, inserting or reordering lines
+//! breaks the chain from that line on. `pagi-sola-web --verify-journal` and `GET
+//! /api/admin/journal/verify` check it and report the head hash; writing that down elsewhere
+//! also catches the whole journal being rewritten.
+//!
+//! With encryption at rest (see [`pagi_crypto`]) the details are sealed with the data key.
+//! Verifying doesn't need the key.
+//!
+//! A line cut short by a crash is removed on the next start, and the removal is journaled.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use actix_web::{web, HttpResponse};
+use base64::Engine;
+use chrono::Utc;
+use pagi_crypto::Sealer;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::{ApiError, AppState};
+
+/// Where the journal lives, under the data directory.
+pub const JOURNAL_FILE: &str = "data/journal.jsonl";
+
+/// The `prev` of the first entry.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    seq: u64,
+    ts_unix: i64,
+    event: String,
+    actor: String,
+    details: Value,
+    prev: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Line<'a> {
+    hash: String,
+    #[serde(borrow)]
+    entry: &'a RawValue,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+struct Head {
+    file: File,
+    seq: u64,
+    hash: String,
+}
+
+/// The open journal. Cheap to clone; appends are serialized.
+#[derive(Clone)]
+pub struct Journal {
+    path: PathBuf,
+    head: Arc<Mutex<Head>>,
+    sealer: Sealer,
+}
+
+impl Journal {
+    /// Open the journal at `path`, creating it if needed, and continue its chain.
+    pub fn open(path: &Path, sealer: Sealer) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let complete = data.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+        let torn = data.len() - complete;
+        if torn > 0 {
+            file.set_len(complete as u64)?;
+            data.truncate(complete);
+        }
+
+        let (seq, hash) = match data[..complete.saturating_sub(1)]
+            .rsplit(|b| *b == b'\n')
+            .next()
+            .filter(|last| !last.is_empty())
+        {
+            None => (0, GENESIS.to_string()),
+            Some(last) => match serde_json::from_slice::<Line>(last).and_then(|line| {
+                serde_json::from_str::<Entry>(line.entry.get()).map(|e| (e.seq, line.hash))
+            }) {
+                Ok(head) => head,
+                // Verification will point at it; chain on from its bytes all the same.
+                Err(_) => (
+                    data.iter().filter(|b| **b == b'\n').count() as u64,
+                    sha256_hex(last),
+                ),
+            },
+        };
+        let journal = Self {
+            path: path.to_path_buf(),
+            head: Arc::new(Mutex::new(Head { file, seq, hash })),
+            sealer,
+        };
+        if torn > 0 {
+            warn!(target: "journal", torn, "removed a journal line cut short by a crash");
+            journal.append(
+                "journal.repaired",
+                "server",
+                json!({ "dropped_bytes": torn }),
+            )?;
+        }
+        Ok(journal)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an event and flush it to disk.
+    pub fn append(&self, event: &str, actor: &str, details: Value) -> io::Result<()> {
+        let details = if self.sealer.is_enabled() {
+            let sealed = self
+                .sealer
+                .seal(details.to_string().as_bytes())
+                .map_err(io::Error::other)?;
+            json!({ "sealed": base64::engine::general_purpose::STANDARD.encode(sealed) })
+        } else {
+            details
+        };
+        let mut head = self.head.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = Entry {
+            seq: head.seq + 1,
+            ts_unix: Utc::now().timestamp(),
+            event: event.to_string(),
+            actor: actor.to_string(),
+            details,
+            prev: head.hash.clone(),
+        };
+        let entry = serde_json::value::to_raw_value(&entry)?;
+        let hash = sha256_hex(entry.get().as_bytes());
+        let mut line = serde_json::to_vec(&Line {
+            hash: hash.clone(),
+            entry: &entry,
+        })?;
+        line.push(b'\n');
+        head.file.write_all(&line)?;
+        head.file.sync_data()?;
+        head.seq += 1;
+        head.hash = hash;
+        Ok(())
+    }
+
+    /// [`Self::append`], logging a failure instead of returning it, for events that have
+    /// already happened.
+    pub fn record(&self, event: &str, actor: &str, details: Value) {
+        if let Err(e) = self.append(event, actor, details) {
+            warn!(target: "journal", event, "journal entry not written: {e}");
+        }
+    }
+}
+
+/// The first line that doesn't check out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    /// 1-based.
+    pub line: u64,
+    pub reason: String,
+}
+
+/// What [`verify`] found.
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+    /// Entries checked before the first problem, or all of them.
+    pub entries: u64,
+    /// Hash of the last good entry.
+    pub head: String,
+    pub problem: Option<Problem>,
+}
+
+impl Verification {
+    pub fn ok(&self) -> bool {
+        self.problem.is_none()
+    }
+}
+
+/// Check the chain in the journal at `path`. A missing journal is an empty, intact one.
+pub fn verify(path: &Path) -> io::Result<Verification> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let mut found = Verification {
+        entries: 0,
+        head: GENESIS.to_string(),
+        problem: None,
+    };
+    let mut lines = data.split(|b| *b == b'\n').peekable();
+    while let Some(raw) = lines.next() {
+        let number = found.entries + 1;
+        let last = lines.peek().is_none();
+        if last && raw.is_empty() {
+            break;
+        }
+        let problem = |reason: &str| {
+            Some(Problem {
+                line: number,
+                reason: reason.to_string(),
+            })
+        };
+        if last {
+            found.problem = problem("incomplete last line");
+            break;
+        }
+        let Ok(line) = serde_json::from_slice::<Line>(raw) else {
+            found.problem = problem("not a journal line");
+            break;
+        };
+        if sha256_hex(line.entry.get().as_bytes()) != line.hash {
+            found.problem = problem("the entry doesn't match its hash; it was changed");
+            break;
+        }
+        let Ok(entry) = serde_json::from_str::<Entry>(line.entry.get()) else {
+            found.problem = problem("unreadable entry");
+            break;
+        };
+        if entry.prev != found.head || entry.seq != number {
+            found.problem =
+                problem("the chain breaks here; an entry was removed, inserted or moved");
+            break;
+        }
+        found.entries = number;
+        found.head = line.hash;
+    }
+    Ok(found)
+}
+
+/// GET /api/admin/journal/verify
+async fn get_verify(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let path = state.journal.path().to_path_buf();
+    let found = web::block(move || verify(&path))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| ApiError::internal(format!("cannot read the journal: {e}")))?;
+    Ok(HttpResponse::Ok().json(json!({
+        "ok": found.ok(),
+        "entries": found.entries,
+        "head": found.head,
+        "problem": found.problem,
+    })))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/admin/journal/verify", web::get().to(get_verify));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "phoenix-journal-{name}-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        dir.join(JOURNAL_FILE)
+    }
+
+    fn cleanup(path: &Path) {
+        let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn appends_chain_across_restarts() {
+        let path = temp_path("chain");
+        let journal = Journal::open(&path, Sealer::default()).unwrap();
+        journal
+            .append("recording.start", "local", json!({ "seconds": 60 }))
+            .unwrap();
+        journal
+            .append("export.emotion", "key:laptop", json!({}))
+            .unwrap();
+        drop(journal);
+        Journal::open(&path, Sealer::default())
+            .unwrap()
+            .append("server.start", "server", json!({}))
+            .unwrap();
+
+        let found = verify(&path).unwrap();
+        assert!(found.ok(), "{:?}", found.problem);
+        assert_eq!(found.entries, 3);
+        assert_ne!(found.head, GENESIS);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn edits_and_removals_are_caught() {
+        let path = temp_path("tamper");
+        let journal = Journal::open(&path, Sealer::default()).unwrap();
+        for n in 0..3 {
+            journal
+                .append("memory.delete", "local", json!({ "n": n }))
+                .unwrap();
+        }
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, original.replace("\"n\":1", "\"n\":7")).unwrap();
+        let found = verify(&path).unwrap();
+        assert_eq!(found.entries, 1);
+        assert_eq!(found.problem.unwrap().line, 2);
+
+        let without_second: String = original
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, line)| format!("{line}\n"))
+            .collect();
+        std::fs::write(&path, without_second).unwrap();
+        assert_eq!(verify(&path).unwrap().problem.unwrap().line, 2);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn a_torn_line_is_dropped_and_journaled() {
+        let path = temp_path("torn");
+        let journal = Journal::open(&path, Sealer::default()).unwrap();
+        journal.append("backup.create", "local", json!({})).unwrap();
+        drop(journal);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"hash\":\"ab").unwrap();
+        drop(file);
+        assert_eq!(
+            verify(&path).unwrap().problem.unwrap().reason,
+            "incomplete last line"
+        );
+
+        Journal::open(&path, Sealer::default()).unwrap();
+        let found = verify(&path).unwrap();
+        assert!(found.ok(), "{:?}", found.problem);
+        assert_eq!(found.entries, 2);
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("journal.repaired"));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn details_are_sealed_with_the_data_key() {
+        let path = temp_path("sealed");
+        let sealer = Sealer::new(pagi_crypto::DataKey::generate().unwrap());
+        Journal::open(&path, sealer)
+            .unwrap()
+            .append("recording.start", "local", json!({ "purpose": "therapy" }))
+            .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("therapy"));
+        assert!(text.contains("recording.start"));
+        assert!(verify(&path).unwrap().ok());
+        cleanup(&path);
+    }
+}
@@ -0,0 +1,247 @@
+//! Binding the HTTP listener.
+//!
+//! The host and port come from [`crate::ServerConfig`]. Port `0` lets the OS pick a free port,
+//! and a fallback range is tried in order when the configured port is already taken, so a
+//! second dev server doesn't have to fail outright. Callers read the real address from
+//! [`TcpListener::local_addr`].
+//!
+//! For the purely local desktop case `server.socket` serves on a Unix domain socket instead:
+//! no port to collide with and nothing for a firewall to prompt about. The socket file is
+//! created owner-only (`0600`) in a directory that is created `0700` and must not belong to
+//! another user, and requests from any other uid are refused ([`require_socket_owner`]). That
+//! takes the place of API keys (a socket counts as a loopback bind for
+//! `auth.api_auth = "auto"`). Put it somewhere like `$XDG_RUNTIME_DIR/phoenix.sock`. Clients
+//! connect with `curl --unix-socket <path>`.
+//! Windows named pipes are not supported by the HTTP server; there a socket path is refused at
+//! startup.
+
+use std::any::Any;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Extensions, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use tracing::warn;
+
+/// Where the server ended up listening; see [`crate::run_server_reporting`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoundAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for BoundAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => addr.fmt(f),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Bind `host:port`; on `AddrInUse`, try each port in `fallback`. The error for the configured
+/// port is returned if nothing could be bound.
+pub fn bind(
+    host: &str,
+    port: u16,
+    fallback: Option<&RangeInclusive<u16>>,
+) -> io::Result<TcpListener> {
+    let err = match TcpListener::bind((host, port)) {
+        Ok(listener) => return Ok(listener),
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse && fallback.is_some() => e,
+        Err(e) => return Err(e),
+    };
+    for candidate in fallback.into_iter().flat_map(|r| r.clone()) {
+        if candidate == port {
+            continue;
+        }
+        match TcpListener::bind((host, candidate)) {
+            Ok(listener) => {
+                warn!("Port {port} is in use; bound fallback port {candidate}");
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(err)
+}
+
+/// The effective uid of this process.
+#[cfg(unix)]
+pub(crate) fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() }
+}
+
+/// Create a socket's directory with mode 0700 if missing, and refuse one owned by another user
+/// (who could replace the socket). Root-owned shared dirs such as `/tmp` are allowed.
+#[cfg(unix)]
+pub(crate) fn socket_dir(dir: &Path, uid: u32) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    if !dir.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+    }
+    let owner = std::fs::metadata(dir)?.uid();
+    if owner != uid && owner != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is owned by uid {owner}", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Run `bind` with a umask that leaves the socket 0600 from the moment it exists.
+#[cfg(unix)]
+pub(crate) fn bind_private<T>(bind: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    // The umask is process-wide; anything another thread creates meanwhile only gets stricter
+    // permissions.
+    // SAFETY: umask has no preconditions and cannot fail.
+    let previous = unsafe { libc::umask(0o177) };
+    let bound = bind();
+    // SAFETY: as above.
+    unsafe { libc::umask(previous) };
+    bound
+}
+
+/// The uid on the other end of a Unix socket connection; `None` when it couldn't be read.
+#[derive(Debug, Clone, Copy)]
+pub struct SocketPeer(pub Option<u32>);
+
+/// `HttpServer::on_connect` hook that records the [`SocketPeer`] of Unix socket connections.
+pub fn record_peer(conn: &dyn Any, ext: &mut Extensions) {
+    #[cfg(unix)]
+    if let Some(stream) = conn.downcast_ref::<actix_web::rt::net::UnixStream>() {
+        ext.insert(SocketPeer(stream.peer_cred().ok().map(|cred| cred.uid())));
+    }
+    #[cfg(not(unix))]
+    let _ = (conn, ext);
+}
+
+/// Middleware refusing requests that came over the Unix socket from another uid.
+pub async fn require_socket_owner(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    #[cfg(unix)]
+    if let Some(SocketPeer(peer)) = req.conn_data::<SocketPeer>().copied() {
+        if peer != Some(current_uid()) {
+            warn!("Refused a request over the Unix socket from uid {peer:?}");
+            return Err(crate::ApiError::forbidden("the socket is only for its owner").into());
+        }
+    }
+    next.call(req).await
+}
+
+/// Bind a Unix domain socket at `path`, readable and writable by the owner only.
+///
+/// A socket file left behind by a server that didn't exit cleanly is replaced. One that still
+/// accepts connections is `AddrInUse`, and anything other than a socket at `path` is refused.
+#[cfg(unix)]
+pub fn bind_unix(path: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        socket_dir(parent, current_uid())?;
+    }
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is in use by a running server", path.display()),
+                ));
+            }
+            warn!("Replacing stale socket {}", path.display());
+            std::fs::remove_file(path)?;
+        }
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ));
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    bind_private(|| UnixListener::bind(path))
+}
+
+/// Unix sockets only; named pipes aren't something the HTTP server can listen on.
+#[cfg(not(unix))]
+pub fn bind_unix(path: &Path) -> io::Result<std::convert::Infallible> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "server.socket ({}) needs Unix domain sockets, which this platform's server does not support; use a loopback bind such as 127.0.0.1:0 instead",
+            path.display()
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_zero_and_fallback() {
+        let taken = bind("127.0.0.1", 0, None).unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        assert_ne!(taken_port, 0);
+
+        let err = bind("127.0.0.1", taken_port, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+        // A range that starts at the taken port moves on to the next free one.
+        let range = taken_port..=taken_port.saturating_add(20);
+        let fallback = bind("127.0.0.1", taken_port, Some(&range)).unwrap();
+        let port = fallback.local_addr().unwrap().port();
+        assert_ne!(port, taken_port);
+        assert!(range.contains(&port));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket_is_private_and_stale_files_are_replaced() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("phoenix-uds-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("phoenix.sock");
+        let live = bind_unix(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_eq!(
+            bind_unix(&path).unwrap_err().kind(),
+            io::ErrorKind::AddrInUse
+        );
+
+        // Dropping the listener leaves the file behind, as a crash would.
+        drop(live);
+        assert!(path.exists());
+        bind_unix(&path).unwrap();
+
+        let file = dir.join("not-a-socket");
+        std::fs::write(&file, b"x").unwrap();
+        assert_eq!(
+            bind_unix(&file).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert_eq!(
+            BoundAddr::Unix(path.clone()).to_string(),
+            format!("unix:{}", path.display())
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,373 @@
+//! Server-pushed events for WebSocket topic subscribers.
+//!
+//! One broadcast channel carries the `recording`, `stress`, `ghost`, `alerts`, `presence`,
+//! `config` and `plugins` topics; `/ws` forwards each [`LiveEvent`] to the connections subscribed to its
+//! [`LiveEvent::topic`], and `/api/events` streams the alert-like ones over SSE. Emotion updates
+//! keep their own channel (`AppState::emotion_tx`).
+//!
+//! - `recording`: `started` / `recording` (every few seconds while active) / `stopped` / `failed`.
+//! - `stress`: a CPU/temperature sample every few seconds, plus a `stress_threshold` event when
+//!   CPU load crosses `sensors.stress_alert_percent` (85). The samples of the last
+//!   [`STRESS_HISTORY_RETENTION`] are also kept for `GET /api/env/stress/history` (see
+//!   [`crate::env_api`]), so the sampler runs whether or not anyone is connected.
+//! - `ghost`: each turn of a Relational Ghost simulation, then its full result.
+//! - `alerts`: sustained or recurring negative emotions in incoming emotion updates (rules as
+//!   for the recorder; see `multi_modal_recording::emotion_alerts`).
+//! - `presence`: someone no enrolled profile matches, as reported by a capture process
+//!   (`POST /api/presence/unknown`).
+//! - `config`: what a reload of the settings file changed (see [`crate::config_reload`]).
+//! - `plugins`: what analyzer plugins found in a transcript or an emotion update (see
+//!   [`crate::plugins_api`]).
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use multi_modal_recording::emotion_alerts::{self, AlertEngine, AlertRules, EmotionAlert};
+use multi_modal_recording::emotion_history::EmotionUpdate;
+use multi_modal_recording::presence::UnknownPresenceEvent;
+use pagi_plugins::{Finding, InputKind};
+use vital_organ_vaults::VitalOrganVaults;
+
+use crate::config_reload::ReloadReport;
+use crate::env_sensor::{self, SystemStress};
+use crate::ghost_engine::{GroupTurnReply, SimulateResponse};
+use crate::settings::SensorSettings;
+
+/// How far back the stress samples are kept.
+pub const STRESS_HISTORY_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One stress sample and when it was taken.
+#[derive(Debug, Clone, Serialize)]
+pub struct StressReading {
+    #[serde(flatten)]
+    pub stress: SystemStress,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingPhase {
+    Started,
+    Recording,
+    Stopped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    RecordingProgress {
+        phase: RecordingPhase,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        session_id: Option<String>,
+        elapsed_secs: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    StressSample {
+        #[serde(flatten)]
+        stress: SystemStress,
+        timestamp: i64,
+    },
+    /// CPU load went above (`above: true`) or back below the alert threshold.
+    StressThreshold {
+        above: bool,
+        threshold_percent: u8,
+        #[serde(flatten)]
+        stress: SystemStress,
+        timestamp: i64,
+    },
+    GhostTurn {
+        session_id: String,
+        index: usize,
+        #[serde(flatten)]
+        turn: GroupTurnReply,
+    },
+    GhostResult {
+        #[serde(flatten)]
+        result: Box<SimulateResponse>,
+    },
+    EmotionAlert {
+        #[serde(flatten)]
+        alert: EmotionAlert,
+    },
+    UnknownPresence {
+        #[serde(flatten)]
+        event: UnknownPresenceEvent,
+    },
+    ConfigReloaded {
+        #[serde(flatten)]
+        report: ReloadReport,
+    },
+    PluginFindings {
+        /// What was analyzed.
+        source: InputKind,
+        findings: Vec<Finding>,
+    },
+}
+
+impl LiveEvent {
+    pub fn topic(&self) -> &'static str {
+        match self {
+            Self::RecordingProgress { .. } => "recording",
+            Self::StressSample { .. } | Self::StressThreshold { .. } => "stress",
+            Self::GhostTurn { .. } | Self::GhostResult { .. } => "ghost",
+            Self::EmotionAlert { .. } => "alerts",
+            Self::UnknownPresence { .. } => "presence",
+            Self::ConfigReloaded { .. } => "config",
+            Self::PluginFindings { .. } => "plugins",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LiveEvents {
+    tx: broadcast::Sender<LiveEvent>,
+    /// The recording in progress, if any.
+    recording: Arc<Mutex<Option<(String, Instant)>>>,
+    /// Stress samples of the last [`STRESS_HISTORY_RETENTION`], oldest first.
+    stress_history: Arc<Mutex<VecDeque<StressReading>>>,
+    /// Set once on shutdown so long-lived streams can end.
+    closing: watch::Sender<bool>,
+}
+
+impl Default for LiveEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveEvents {
+    pub fn new() -> Self {
+        let (tx, _rx) = broadcast::channel(256);
+        Self {
+            tx,
+            recording: Arc::new(Mutex::new(None)),
+            stress_history: Arc::new(Mutex::new(VecDeque::new())),
+            closing: watch::Sender::new(false),
+        }
+    }
+
+    /// Tell streaming subscribers the server is shutting down.
+    pub fn close(&self) {
+        self.closing.send_replace(true);
+    }
+
+    /// Resolves once [`close`](Self::close) has been called.
+    pub fn closing(&self) -> watch::Receiver<bool> {
+        self.closing.subscribe()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.tx.subscribe()
+    }
+
+    fn send(&self, event: LiveEvent) {
+        // No receivers is fine.
+        let _ = self.tx.send(event);
+    }
+
+    fn recording_event(
+        &self,
+        phase: RecordingPhase,
+        session: Option<(String, Instant)>,
+        error: Option<String>,
+    ) {
+        self.send(LiveEvent::RecordingProgress {
+            phase,
+            elapsed_secs: session.as_ref().map_or(0, |(_, t)| t.elapsed().as_secs()),
+            session_id: session.map(|(id, _)| id),
+            error,
+        });
+    }
+
+    pub fn recording_started(&self, session_id: &str) {
+        let session = (session_id.to_string(), Instant::now());
+        if let Ok(mut slot) = self.recording.lock() {
+            *slot = Some(session.clone());
+        }
+        self.recording_event(RecordingPhase::Started, Some(session), None);
+    }
+
+    pub fn recording_stopped(&self) {
+        let session = self.recording.lock().ok().and_then(|mut slot| slot.take());
+        self.recording_event(RecordingPhase::Stopped, session, None);
+    }
+
+    pub fn recording_failed(&self, error: impl Into<String>) {
+        let session = self.recording.lock().ok().and_then(|mut slot| slot.take());
+        self.recording_event(RecordingPhase::Failed, session, Some(error.into()));
+    }
+
+    pub fn unknown_presence(&self, event: UnknownPresenceEvent) {
+        self.send(LiveEvent::UnknownPresence { event });
+    }
+
+    pub fn config_reloaded(&self, report: ReloadReport) {
+        self.send(LiveEvent::ConfigReloaded { report });
+    }
+
+    /// Publish `findings` unless there are none.
+    pub fn plugin_findings(&self, source: InputKind, findings: Vec<Finding>) {
+        if !findings.is_empty() {
+            self.send(LiveEvent::PluginFindings { source, findings });
+        }
+    }
+
+    /// Keep `reading` and drop the samples that fell out of the retention window.
+    pub(crate) fn record_stress(&self, reading: StressReading) {
+        let Ok(mut history) = self.stress_history.lock() else {
+            return;
+        };
+        let oldest = reading.timestamp - STRESS_HISTORY_RETENTION.as_secs() as i64;
+        while history.front().is_some_and(|r| r.timestamp < oldest) {
+            history.pop_front();
+        }
+        history.push_back(reading);
+    }
+
+    /// The most recent stress sample, if the sampler has taken one.
+    pub fn latest_stress(&self) -> Option<StressReading> {
+        self.stress_history.lock().ok()?.back().cloned()
+    }
+
+    /// Stress samples taken at or after `since` (unix seconds), oldest first.
+    pub fn stress_since(&self, since: i64) -> Vec<StressReading> {
+        let Ok(history) = self.stress_history.lock() else {
+            return Vec::new();
+        };
+        let start = history.partition_point(|r| r.timestamp < since);
+        history.range(start..).cloned().collect()
+    }
+
+    /// Publish a finished simulation turn by turn, then as a whole.
+    pub fn ghost_simulated(&self, result: &SimulateResponse) {
+        for (index, turn) in result.group_replies.iter().enumerate() {
+            self.send(LiveEvent::GhostTurn {
+                session_id: result.session_id.clone(),
+                index,
+                turn: turn.clone(),
+            });
+        }
+        self.send(LiveEvent::GhostResult {
+            result: Box::new(result.clone()),
+        });
+    }
+
+    /// Spawn the periodic publishers (recording progress, stress samples). They pick up new
+    /// intervals and thresholds sent on `sensors`.
+    pub fn spawn_samplers(&self, sensors: watch::Receiver<SensorSettings>) -> [JoinHandle<()>; 2] {
+        let live = self.clone();
+        let mut settings = sensors.clone();
+        let recording = tokio::spawn(async move {
+            let mut progress_interval = settings.borrow().recording_progress_interval;
+            let mut tick = tokio::time::interval(progress_interval);
+            loop {
+                tick.tick().await;
+                let current = settings.borrow_and_update().recording_progress_interval;
+                if current != progress_interval {
+                    progress_interval = current;
+                    tick = tokio::time::interval_at(
+                        tokio::time::Instant::now() + progress_interval,
+                        progress_interval,
+                    );
+                }
+                let session = live.recording.lock().ok().and_then(|slot| slot.clone());
+                if session.is_some() {
+                    live.recording_event(RecordingPhase::Recording, session, None);
+                }
+            }
+        });
+
+        let live = self.clone();
+        let mut settings = sensors;
+        let stress = tokio::spawn(
+            async move {
+                let mut sample_interval = settings.borrow().stress_sample_interval;
+                let mut tick = tokio::time::interval(sample_interval);
+                let mut above = false;
+                loop {
+                    tick.tick().await;
+                    let (current, threshold) = {
+                        let sensors = settings.borrow_and_update();
+                        (sensors.stress_sample_interval, sensors.stress_alert_percent)
+                    };
+                    if current != sample_interval {
+                        sample_interval = current;
+                        tick = tokio::time::interval_at(
+                            tokio::time::Instant::now() + sample_interval,
+                            sample_interval,
+                        );
+                    }
+                    let span = tracing::Span::current();
+                    let Ok(stress) = tokio::task::spawn_blocking(move || {
+                        span.in_scope(env_sensor::get_system_stress)
+                    })
+                    .await
+                    else {
+                        continue;
+                    };
+                    let timestamp = chrono::Utc::now().timestamp();
+                    if (stress.cpu_usage_percent >= threshold) != above {
+                        above = !above;
+                        tracing::info!(
+                            target: "sensor",
+                            cpu_usage_percent = stress.cpu_usage_percent,
+                            threshold_percent = threshold,
+                            above,
+                            "CPU load crossed the stress alert threshold"
+                        );
+                        live.send(LiveEvent::StressThreshold {
+                            above,
+                            threshold_percent: threshold,
+                            stress: stress.clone(),
+                            timestamp,
+                        });
+                    }
+                    live.record_stress(StressReading {
+                        stress: stress.clone(),
+                        timestamp,
+                    });
+                    live.send(LiveEvent::StressSample { stress, timestamp });
+                }
+            }
+            .instrument(tracing::info_span!(target: "sensor", "stress_sampler")),
+        );
+        [recording, stress]
+    }
+
+    /// Run the emotion alert rules over incoming emotion updates; alerts are appended to the
+    /// Soul-Vault timeline and published on the `alerts` topic.
+    pub fn spawn_emotion_alerts(
+        &self,
+        mut updates: broadcast::Receiver<EmotionUpdate>,
+        vaults: Arc<VitalOrganVaults>,
+    ) -> JoinHandle<()> {
+        // Rules changed at runtime are persisted and win over the env defaults.
+        let rules = vaults
+            .recall_soul(emotion_alerts::ALERT_RULES_KEY)
+            .and_then(|raw| serde_json::from_str::<AlertRules>(&raw).ok())
+            .unwrap_or_else(AlertRules::from_env);
+        let live = self.clone();
+        tokio::spawn(async move {
+            let mut engine = AlertEngine::new(rules);
+            loop {
+                let update = match updates.recv().await {
+                    Ok(update) => update,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let Some(alert) = engine.observe(&update.moment) {
+                    tracing::info!(target: "recorder", ?alert, "emotion alert");
+                    emotion_alerts::append_to_timeline(&vaults, &alert);
+                    live.send(LiveEvent::EmotionAlert { alert });
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,264 @@
+//! Prometheus metrics, served as text at `GET /metrics`.
+//!
+//! A small process-wide registry of counters and histograms; handlers record into it through the
+//! functions below and the middleware times every HTTP request by route pattern (never the raw
+//! path, so cardinality stays bounded). Self-hosters can scrape it straight into Grafana.
+//!
+//! Recording can be paused at runtime (the `telemetry` toggle of [`crate::admin_api`]); while
+//! it is, nothing is counted and `GET /metrics` keeps serving the values reached so far.
+//!
+//! | Metric | Type | Labels |
+//! |--------|------|--------|
+//! | `phoenix_ghost_simulations_total` | counter | – |
+//! | `phoenix_ghost_drift_alerts_total` | counter | – |
+//! | `phoenix_resonance_score` | histogram | `source` (`ghost` / `resonance`) |
+//! | `phoenix_recordings_started_total` | counter | – |
+//! | `phoenix_recordings_failed_total` | counter | – |
+//! | `phoenix_http_request_duration_seconds` | histogram | `method`, `route`, `status` |
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const SCORE_BUCKETS: &[f64] = &[10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0, 90.0, 100.0];
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Per-bucket (non-cumulative) counts; rendered cumulatively.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(i) = self.bounds.iter().position(|b| value <= *b) {
+            self.buckets[i] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (bound, n) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += n;
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let braces = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{braces} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{braces} {}", self.count);
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    ghost_simulations: u64,
+    drift_alerts: u64,
+    recordings_started: u64,
+    recordings_failed: u64,
+    /// By `source`.
+    resonance_scores: BTreeMap<&'static str, Histogram>,
+    /// By `(method, route, status)`.
+    requests: BTreeMap<(String, String, u16), Histogram>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(true);
+
+/// Whether metrics are being recorded.
+pub fn enabled() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    RECORDING.store(enabled, Ordering::Relaxed);
+}
+
+fn with<F: FnOnce(&mut Registry)>(f: F) {
+    if !enabled() {
+        return;
+    }
+    if let Ok(mut r) = registry().lock() {
+        f(&mut r);
+    }
+}
+
+pub fn ghost_simulation(drift_alert: bool) {
+    with(|r| {
+        r.ghost_simulations += 1;
+        if drift_alert {
+            r.drift_alerts += 1;
+        }
+    });
+}
+
+/// `source` is `ghost` or `resonance`.
+pub fn resonance_score(source: &'static str, score: u8) {
+    with(|r| {
+        r.resonance_scores
+            .entry(source)
+            .or_insert_with(|| Histogram::new(SCORE_BUCKETS))
+            .observe(score as f64)
+    });
+}
+
+pub fn recording_started() {
+    with(|r| r.recordings_started += 1);
+}
+
+pub fn recording_failed() {
+    with(|r| r.recordings_failed += 1);
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// The registry in Prometheus text exposition format (0.0.4).
+pub fn render() -> String {
+    let mut out = String::new();
+    let Ok(r) = registry().lock() else {
+        return out;
+    };
+    counter(
+        &mut out,
+        "phoenix_ghost_simulations_total",
+        "Relational Ghost simulations run.",
+        r.ghost_simulations,
+    );
+    counter(
+        &mut out,
+        "phoenix_ghost_drift_alerts_total",
+        "Ghost simulations that raised a drift alert.",
+        r.drift_alerts,
+    );
+    counter(
+        &mut out,
+        "phoenix_recordings_started_total",
+        "Recordings started.",
+        r.recordings_started,
+    );
+    counter(
+        &mut out,
+        "phoenix_recordings_failed_total",
+        "Recordings that failed to start or finish.",
+        r.recordings_failed,
+    );
+
+    let name = "phoenix_resonance_score";
+    let _ = writeln!(out, "# HELP {name} Resonance scores (0-100) returned.");
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    for (source, h) in &r.resonance_scores {
+        h.render(&mut out, name, &format!("source=\"{source}\""));
+    }
+
+    let name = "phoenix_http_request_duration_seconds";
+    let _ = writeln!(out, "# HELP {name} HTTP request latency by route.");
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    for ((method, route, status), h) in &r.requests {
+        let labels = format!(
+            "method=\"{}\",route=\"{}\",status=\"{status}\"",
+            escape(method),
+            escape(route)
+        );
+        h.render(&mut out, name, &labels);
+    }
+    out
+}
+
+/// GET /metrics
+pub async fn get_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(render())
+}
+
+/// Middleware timing every request.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let start = Instant::now();
+    let res = next.call(req).await?;
+    let elapsed = start.elapsed().as_secs_f64();
+    let method = res.request().method().to_string();
+    // Routing has happened by now; unmatched paths share one series.
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let status = res.status().as_u16();
+    with(|r| {
+        r.requests
+            .entry((method, route, status))
+            .or_insert_with(|| Histogram::new(LATENCY_BUCKETS))
+            .observe(elapsed)
+    });
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_buckets() {
+        let mut h = Histogram::new(SCORE_BUCKETS);
+        h.observe(15.0);
+        h.observe(95.0);
+        h.observe(100.0);
+        let mut out = String::new();
+        h.render(&mut out, "x", "source=\"ghost\"");
+        assert!(out.contains("x_bucket{source=\"ghost\",le=\"10\"} 0\n"));
+        assert!(out.contains("x_bucket{source=\"ghost\",le=\"20\"} 1\n"));
+        assert!(out.contains("x_bucket{source=\"ghost\",le=\"100\"} 3\n"));
+        assert!(out.contains("x_bucket{source=\"ghost\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("x_sum{source=\"ghost\"} 210\n"));
+        assert!(out.contains("x_count{source=\"ghost\"} 3\n"));
+    }
+}
@@ -0,0 +1,284 @@
+//! The MQTT connection of the Home Assistant bridge ([`crate::home_assistant`]), on rumqttc:
+//! plain TCP for `mqtt://` brokers, TLS through rustls for `mqtts://` ones.
+//!
+//! The bridge publishes at QoS 0 (optionally retained) and subscribes at QoS 0. The event loop
+//! runs in a task of its own, which also keeps the connection alive, and hands incoming
+//! messages over through a channel, so waiting for one can sit in a `select!` next to other
+//! events.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rumqttc::{
+    AsyncClient, ConnectionError, Event, EventLoop, Incoming, LastWill, MqttOptions, Outgoing, QoS,
+    SubscribeFilter, TlsConfiguration, Transport,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The largest packet sent or accepted; commands are small, discovery configs a few hundred
+/// bytes.
+const MAX_PACKET_BYTES: usize = 256 * 1024;
+/// Requests queued for the event loop before publishing waits.
+const REQUEST_CAPACITY: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MqttError {
+    #[error("MQTT connection error: {0}")]
+    Connection(Box<ConnectionError>),
+    #[error("MQTT connection error: {0}")]
+    Client(#[from] rumqttc::ClientError),
+    #[error("MQTT TLS setup: {0}")]
+    Tls(String),
+    #[error("the broker closed the connection")]
+    Closed,
+    #[error("no answer from the broker")]
+    Timeout,
+}
+
+impl From<ConnectionError> for MqttError {
+    fn from(e: ConnectionError) -> Self {
+        Self::Connection(Box::new(e))
+    }
+}
+
+/// Sent by the broker for us when the connection drops without a goodbye.
+#[derive(Debug, Clone)]
+pub struct Will {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub retain: bool,
+}
+
+/// How the connection is secured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Security {
+    Plain,
+    /// TLS, checked against the public roots or, when given, the CA certificates in a PEM file.
+    Tls {
+        ca_file: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive_secs: u16,
+    pub will: Option<Will>,
+    pub security: Security,
+}
+
+/// A message on a subscribed topic.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// Root certificates for a TLS connection: those in `ca_file`, or the public ones.
+fn tls_config(ca_file: Option<&Path>) -> Result<rustls::ClientConfig, MqttError> {
+    let mut roots = rustls::RootCertStore::empty();
+    match ca_file {
+        Some(path) => {
+            let certs = CertificateDer::pem_file_iter(path)
+                .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| MqttError::Tls(format!("{}: {e}", path.display())))?;
+            let (added, _) = roots.add_parsable_certificates(certs);
+            if added == 0 {
+                return Err(MqttError::Tls(format!(
+                    "{}: no CA certificate in it",
+                    path.display()
+                )));
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+    rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| MqttError::Tls(e.to_string()))
+        .map(|builder| builder.with_root_certificates(roots).with_no_client_auth())
+}
+
+fn mqtt_options(host: &str, port: u16, options: &ConnectOptions) -> Result<MqttOptions, MqttError> {
+    let mut mqtt = MqttOptions::new(&options.client_id, host, port);
+    mqtt.set_keep_alive(Duration::from_secs(options.keep_alive_secs.into()))
+        .set_clean_session(true)
+        .set_max_packet_size(MAX_PACKET_BYTES, MAX_PACKET_BYTES);
+    if let Some(username) = &options.username {
+        mqtt.set_credentials(username, options.password.as_deref().unwrap_or_default());
+    }
+    if let Some(will) = &options.will {
+        mqtt.set_last_will(LastWill::new(
+            &will.topic,
+            will.payload.clone(),
+            QoS::AtMostOnce,
+            will.retain,
+        ));
+    }
+    if let Security::Tls { ca_file } = &options.security {
+        let config = tls_config(ca_file.as_deref())?;
+        mqtt.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(
+            Arc::new(config),
+        )));
+    }
+    Ok(mqtt)
+}
+
+/// Poll `events` until the broker accepts the connection.
+async fn connected(events: &mut EventLoop) -> Result<(), MqttError> {
+    loop {
+        if let Event::Incoming(Incoming::ConnAck(_)) = events.poll().await? {
+            return Ok(());
+        }
+    }
+}
+
+/// A connection to a broker.
+pub struct Client {
+    client: AsyncClient,
+    incoming: mpsc::Receiver<Result<Message, MqttError>>,
+    events: JoinHandle<()>,
+}
+
+impl Client {
+    /// Connect and wait for the broker to accept.
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        options: &ConnectOptions,
+    ) -> Result<Self, MqttError> {
+        let (client, mut events) =
+            AsyncClient::new(mqtt_options(host, port, options)?, REQUEST_CAPACITY);
+        tokio::time::timeout(CONNECT_TIMEOUT, connected(&mut events))
+            .await
+            .map_err(|_| MqttError::Timeout)??;
+        let (tx, incoming) = mpsc::channel(64);
+        let events = tokio::spawn(async move {
+            loop {
+                let message = match events.poll().await {
+                    Ok(Event::Incoming(Incoming::Publish(publish))) => Ok(Message {
+                        topic: publish.topic,
+                        payload: publish.payload.to_vec(),
+                    }),
+                    // Sent: the connection is done, and the closed channel says so.
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+                    Ok(_) => continue,
+                    Err(e) => Err(e.into()),
+                };
+                let failed = message.is_err();
+                if tx.send(message).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Ok(Self {
+            client,
+            incoming,
+            events,
+        })
+    }
+
+    pub async fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        retain: bool,
+    ) -> Result<(), MqttError> {
+        self.client
+            .publish(topic, QoS::AtMostOnce, retain, payload.to_vec())
+            .await?;
+        Ok(())
+    }
+
+    /// Subscribe at QoS 0.
+    pub async fn subscribe(&mut self, topics: &[String]) -> Result<(), MqttError> {
+        let filters = topics
+            .iter()
+            .map(|topic| SubscribeFilter::new(topic.clone(), QoS::AtMostOnce));
+        self.client.subscribe_many(filters).await?;
+        Ok(())
+    }
+
+    /// Leave cleanly, so the broker doesn't send the will.
+    pub async fn disconnect(mut self) -> Result<(), MqttError> {
+        self.client.disconnect().await?;
+        // Queued publishes go out before the goodbye; the event loop stops once it is sent.
+        let sent = async { while self.incoming.recv().await.is_some() {} };
+        tokio::time::timeout(CONNECT_TIMEOUT, sent)
+            .await
+            .map_err(|_| MqttError::Timeout)
+    }
+
+    /// The next message from the broker.
+    pub async fn next(&mut self) -> Result<Message, MqttError> {
+        self.incoming.recv().await.unwrap_or(Err(MqttError::Closed))
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.events.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(security: Security) -> ConnectOptions {
+        ConnectOptions {
+            client_id: "pagi".to_string(),
+            username: Some("u".to_string()),
+            password: None,
+            keep_alive_secs: 60,
+            will: Some(Will {
+                topic: "w".to_string(),
+                payload: b"offline".to_vec(),
+                retain: true,
+            }),
+            security,
+        }
+    }
+
+    #[test]
+    fn options_carry_credentials_will_and_transport() {
+        let plain = mqtt_options("broker.lan", 1883, &options(Security::Plain)).unwrap();
+        assert_eq!(plain.broker_address(), ("broker.lan".to_string(), 1883));
+        let login = plain.credentials().unwrap();
+        assert_eq!(
+            (login.username.as_str(), login.password.as_str()),
+            ("u", "")
+        );
+        let will = plain.last_will().unwrap();
+        assert_eq!((will.topic.as_str(), will.retain), ("w", true));
+        assert!(matches!(plain.transport(), Transport::Tcp));
+
+        let tls = mqtt_options(
+            "broker.lan",
+            8883,
+            &options(Security::Tls { ca_file: None }),
+        )
+        .unwrap();
+        assert!(matches!(tls.transport(), Transport::Tls(_)));
+    }
+
+    #[test]
+    fn a_ca_file_without_certificates_is_refused() {
+        let path = std::env::temp_dir().join(format!("mqtt-ca-{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "not a certificate\n").unwrap();
+        let tls = Security::Tls {
+            ca_file: Some(path.clone()),
+        };
+        assert!(matches!(
+            mqtt_options("broker.lan", 8883, &options(tls)),
+            Err(MqttError::Tls(_))
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+}
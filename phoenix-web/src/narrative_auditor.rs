@@ -109,7 +109,7 @@ pub async fn generate_reframe(state: &AppState) -> NarrativeReframe {
             .join("\n")
     };
 
-    let llm_opt = state.llm.lock().await.clone();
+    let llm_opt = state.completions().await;
     if let Some(llm) = llm_opt {
         let prompt = format!(
             "You are a Narrative Auditor performing Phase 19 Cognitive Reframing.\n\n\
@@ -139,7 +139,7 @@ Return ONLY valid JSON with keys: fixed_belief, growth_reframe, evidence (array
             );
         }
 
-        match llm.speak(&prompt, None).await {
+        match llm.prompt(&prompt).await {
             Ok(text) => {
                 // Best-effort JSON parsing.
                 if let Ok(parsed) = serde_json::from_str::<LlmReframeJson>(text.trim()) {
@@ -17,7 +17,7 @@ pub fn print_mobile_pairing_info(port: u16) {
     // Renders the QR code directly in the terminal.
     // Keep this best-effort: terminal rendering may vary by emulator.
     if let Err(e) = qr2term::print_qr(&pairing_url) {
-        eprintln!("Failed to render QR code: {e}");
+        tracing::warn!("Failed to render QR code: {e}");
     }
 
     println!("{}\n", "=".repeat(40));
@@ -0,0 +1,140 @@
+//! Third-party analyzer plugins (see [`pagi_plugins`]), loaded at startup from `[plugins] dir`.
+//!
+//! Plugins see Ghost scripts (their findings come back in the simulation's `plugin_findings`),
+//! finished recordings' transcripts and live emotion updates (their findings are published on
+//! the `plugins` live topic).
+//!
+//! - `GET /api/plugins`: whether this build runs plugins (the `plugins` feature), the loaded
+//!   plugins and the files that failed to load.
+//! - `POST /api/plugins/analyze`: run the plugins on an [`Input`] now, e.g.
+//!   `{"kind": "script", "input": "You never listen."}`.
+
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use audio_intelligence::MeetingTranscript;
+use multi_modal_recording::emotion_history::EmotionUpdate;
+use pagi_plugins::{EmotionEvent, Finding, Input, InputKind, PluginHost};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::live_events::LiveEvents;
+use crate::{api_json_config, ApiError, AppState};
+
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Run the plugins that take `input` off the async runtime; none is a no-op.
+pub(crate) async fn analyze(host: &Arc<PluginHost>, input: Input) -> Vec<Finding> {
+    if !host.accepts(input.kind()) {
+        return Vec::new();
+    }
+    let host = host.clone();
+    tokio::task::spawn_blocking(move || host.analyze(&input))
+        .await
+        .unwrap_or_default()
+}
+
+/// A transcript as plugins (and scripts) get it: one `speaker: text` line per segment.
+pub(crate) fn transcript_text(transcript: &MeetingTranscript) -> String {
+    transcript
+        .segments
+        .iter()
+        .map(|s| format!("{}: {}", s.speaker_id, s.text.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn transcript_input(transcript: &MeetingTranscript) -> Input {
+    Input::Transcript(transcript_text(transcript))
+}
+
+fn emotion_input(update: &EmotionUpdate) -> Input {
+    let moment = &update.moment;
+    Input::Emotion(EmotionEvent {
+        timestamp: moment.ts_unix,
+        emotion: moment.emotion.clone(),
+        intensity: moment.intensity,
+        confidence: moment.confidence,
+        profile: moment.profile.clone(),
+    })
+}
+
+/// Hand each emotion update to the plugins that take emotions and publish what they find.
+pub(crate) fn spawn_emotion_analyzers(
+    host: Arc<PluginHost>,
+    mut updates: broadcast::Receiver<EmotionUpdate>,
+    live: LiveEvents,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if !host.accepts(InputKind::Emotion) {
+            return;
+        }
+        loop {
+            let update = match updates.recv().await {
+                Ok(update) => update,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let findings = analyze(&host, emotion_input(&update)).await;
+            live.plugin_findings(InputKind::Emotion, findings);
+        }
+    })
+}
+
+/// GET /api/plugins
+async fn get_plugins(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "compiled": cfg!(feature = "plugins"),
+        "plugins": state.plugins.plugins(),
+        "failures": state.plugins.failures(),
+    }))
+}
+
+/// POST /api/plugins/analyze
+async fn post_analyze(
+    state: web::Data<AppState>,
+    body: web::Json<Input>,
+) -> Result<HttpResponse, ApiError> {
+    let findings = analyze(&state.plugins, body.into_inner()).await;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "findings": findings })))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/plugins")
+            .app_data(api_json_config(MAX_BODY_BYTES))
+            .route("", web::get().to(get_plugins))
+            .route("/analyze", web::post().to(post_analyze)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audio_intelligence::TranscriptSegment;
+
+    #[test]
+    fn transcripts_become_speaker_lines() {
+        let segment = |speaker: &str, text: &str| TranscriptSegment {
+            speaker_id: speaker.to_string(),
+            start_time: 0.0,
+            end_time: 1.0,
+            text: text.to_string(),
+            confidence: 0.9,
+        };
+        let transcript = MeetingTranscript {
+            session_id: "s1".to_string(),
+            start_time: 0,
+            end_time: 2,
+            participants: Vec::new(),
+            segments: vec![segment("a", " You're late again. "), segment("b", "Sorry.")],
+            summary: String::new(),
+            keywords: Vec::new(),
+        };
+        assert_eq!(
+            transcript_input(&transcript),
+            Input::Transcript("a: You're late again.\nb: Sorry.".to_string())
+        );
+    }
+}
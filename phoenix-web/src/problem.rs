@@ -0,0 +1,181 @@
+//! Problem details (RFC 7807) for every `/api` error.
+//!
+//! [`ApiError`](crate::ApiError) already answers with a problem body. Older handlers still build
+//! their own (`{"type": "error", "message"}`, `{"error"}`) and actix's extractors answer in plain
+//! text, so [`problem_details`] rewrites any 4xx/5xx body coming out of `/api` into the same
+//! shape and adds the request path as `instance`:
+//!
+//! ```json
+//! {"type": "error", "title": "Bad Request", "status": 400, "detail": "…", "message": "…",
+//!  "instance": "/api/v1/ghost/simulate", "code": "validation_failed", "errors": [ … ]}
+//! ```
+//!
+//! `type` stays `error`, a relative URI reference, because existing clients match on it, and
+//! `message` repeats `detail` for them. Bodies without a `code` get the closest
+//! [`ErrorCode`] for their status, so every error can be branched on. Any other members of the
+//! original body are kept.
+//!
+//! `title` is in the best language for the request's `Accept-Language` (the server's language
+//! when it names none we have), and the response says which in `Content-Language`. `detail`
+//! stays as the handler wrote it.
+
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+use pagi_errors::ErrorCode;
+use serde_json::{Map, Value};
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+/// Plain-text bodies longer than this are cut when used as `detail`.
+const MAX_TEXT_DETAIL_CHARS: usize = 500;
+
+/// The language to answer a request in: the best match for its `Accept-Language`, else the
+/// server's.
+pub(crate) fn request_language(headers: &HeaderMap) -> &'static str {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(pagi_i18n::from_accept_language)
+        .unwrap_or_else(pagi_i18n::language)
+}
+
+/// The problem members for `status`, merged over whatever JSON object `body` holds, titled in
+/// `lang`.
+pub(crate) fn normalize(status: StatusCode, body: &[u8], instance: &str, lang: &str) -> Value {
+    let (mut problem, text) = match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(map)) => (map, None),
+        Ok(_) => (Map::new(), None),
+        Err(_) => (Map::new(), std::str::from_utf8(body).ok().map(str::trim)),
+    };
+    let title = pagi_i18n::lookup(lang, &format!("http-{}", status.as_u16()), &[])
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("Error").to_string());
+    let detail = ["detail", "message", "error"]
+        .iter()
+        .find_map(|k| problem.get(*k).and_then(Value::as_str))
+        .map(str::to_string)
+        .or_else(|| {
+            text.filter(|t| !t.is_empty())
+                .map(|t| t.chars().take(MAX_TEXT_DETAIL_CHARS).collect())
+        })
+        .unwrap_or_else(|| title.clone());
+
+    problem.insert("type".into(), "error".into());
+    problem.insert("title".into(), title.into());
+    problem.insert("status".into(), status.as_u16().into());
+    problem.insert("detail".into(), detail.clone().into());
+    problem.entry("message").or_insert(detail.into());
+    problem
+        .entry("code")
+        .or_insert(ErrorCode::for_http_status(status.as_u16()).as_str().into());
+    problem.insert("instance".into(), instance.into());
+    Value::Object(problem)
+}
+
+/// `res` with its body replaced by problem details, unless it is streamed.
+fn into_problem(res: HttpResponse, instance: &str, lang: &'static str) -> HttpResponse {
+    let status = res.status();
+    let (res, body) = res.into_parts();
+    let body = match body.try_into_bytes() {
+        Ok(body) => body,
+        Err(body) => return res.set_body(body),
+    };
+    let problem = normalize(status, &body, instance, lang);
+    let mut res = res.set_body(BoxBody::new(problem.to_string()));
+    let headers = res.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE));
+    headers.insert(header::CONTENT_LANGUAGE, HeaderValue::from_static(lang));
+    res
+}
+
+/// Rewrites `/api` error responses into problem details; see the module docs.
+pub async fn problem_details(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let path = req.path().to_string();
+    let lang = request_language(req.headers());
+    let res = match next.call(req).await {
+        Ok(res) => res.map_into_boxed_body(),
+        // Raised by inner middleware (credentials, rate limits): keep it an error, with the
+        // rewritten response attached.
+        Err(e) => {
+            let res = into_problem(e.error_response(), &path, lang);
+            return Err(InternalError::from_response(e, res).into());
+        }
+    };
+    let status = res.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return Ok(res);
+    }
+    let (req, res) = res.into_parts();
+    Ok(ServiceResponse::new(req, into_problem(res, &path, lang)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn improvised_bodies_become_problems() {
+        let legacy = normalize(
+            StatusCode::FORBIDDEN,
+            br#"{"type":"error","message":"System tools are disabled"}"#,
+            "/api/system/read-file",
+            "en",
+        );
+        assert_eq!(legacy["title"], "Forbidden");
+        assert_eq!(legacy["status"], 403);
+        assert_eq!(legacy["detail"], "System tools are disabled");
+        assert_eq!(legacy["message"], "System tools are disabled");
+        assert_eq!(legacy["instance"], "/api/system/read-file");
+        assert_eq!(legacy["code"], "forbidden");
+
+        let keyed = normalize(
+            StatusCode::BAD_REQUEST,
+            br#"{"error":"Invalid base64 file data"}"#,
+            "/x",
+            "en",
+        );
+        assert_eq!(keyed["detail"], "Invalid base64 file data");
+        assert_eq!(keyed["error"], "Invalid base64 file data");
+        assert_eq!(keyed["type"], "error");
+
+        let text = normalize(
+            StatusCode::BAD_REQUEST,
+            b"Query deserialize error: invalid digit",
+            "/x",
+            "en",
+        );
+        assert_eq!(text["detail"], "Query deserialize error: invalid digit");
+        assert_eq!(text["code"], "invalid_argument");
+
+        let empty = normalize(StatusCode::METHOD_NOT_ALLOWED, b"", "/x", "en");
+        assert_eq!(empty["detail"], "Method Not Allowed");
+        assert_eq!(
+            normalize(StatusCode::NOT_FOUND, b"[]", "/x", "en")["message"],
+            json!("Not Found")
+        );
+    }
+
+    #[test]
+    fn titles_follow_the_requested_language() {
+        let problem = normalize(StatusCode::NOT_FOUND, b"", "/x", "es");
+        assert_eq!(problem["title"], "No encontrado");
+        assert_eq!(problem["detail"], "No encontrado");
+        // No message for this status: the standard reason phrase.
+        let teapot = normalize(StatusCode::IM_A_TEAPOT, b"", "/x", "es");
+        assert_eq!(teapot["title"], "I'm a teapot");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_LANGUAGE,
+            HeaderValue::from_static("es-MX,es;q=0.9,en;q=0.5"),
+        );
+        assert_eq!(request_language(&headers), "es");
+    }
+}
@@ -0,0 +1,197 @@
+//! Per-client rate limiting for `/api`.
+//!
+//! Each client gets two token buckets: one for cheap routes and a much smaller one for the
+//! expensive ones (LLM-backed commands and simulations, transcription, login). Clients are the
+//! verified API key or UI session when auth is on, otherwise the peer IP. An empty bucket answers
+//! `429` with `Retry-After`. WebSocket messages that do the work of an expensive route draw from
+//! the same expensive bucket as the client's HTTP requests.
+//!
+//! Budgets are requests per minute from `PHOENIX_RATE_LIMIT_CHEAP_PER_MIN` (default 120) and
+//! `PHOENIX_RATE_LIMIT_EXPENSIVE_PER_MIN` (default 10); `0` turns a tier off.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpMessage, HttpRequest, ResponseError};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api_keys::ApiKeyRecord;
+use crate::sessions::Session;
+use crate::{ApiError, AppState};
+
+/// Routes that call the LLM or transcribe audio.
+const EXPENSIVE_ROUTES: &[&str] = &[
+    "/api/command",
+    "/api/speak",
+    "/api/memory/reconstruct",
+    "/api/ghost/simulate",
+    "/api/counselor/ghost/simulate",
+    "/api/counselor/narrative/reframe",
+    "/api/audio/stop-recording",
+    // Runs every plugin.
+    "/api/plugins/analyze",
+    // Runs every script defining the hook.
+    "/api/scripts/test",
+    // Slows passphrase guessing.
+    "/api/session/login",
+];
+
+/// Forget buckets idle this long once the table grows.
+const IDLE_EVICTION: Duration = Duration::from_secs(10 * 60);
+const EVICTION_THRESHOLD: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tier {
+    Cheap,
+    Expensive,
+}
+
+impl Tier {
+    pub fn for_path(path: &str) -> Self {
+        let path = crate::api_version::unversioned(path);
+        if EXPENSIVE_ROUTES.contains(&path.trim_end_matches('/')) {
+            Tier::Expensive
+        } else {
+            Tier::Cheap
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct RateLimiter {
+    cheap_per_min: AtomicU32,
+    expensive_per_min: AtomicU32,
+    buckets: Mutex<HashMap<(Tier, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(cheap_per_min: u32, expensive_per_min: u32) -> Self {
+        Self {
+            cheap_per_min: AtomicU32::new(cheap_per_min),
+            expensive_per_min: AtomicU32::new(expensive_per_min),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Change the budgets; existing buckets refill toward the new capacity.
+    pub fn set_limits(&self, cheap_per_min: u32, expensive_per_min: u32) {
+        self.cheap_per_min.store(cheap_per_min, Ordering::Relaxed);
+        self.expensive_per_min
+            .store(expensive_per_min, Ordering::Relaxed);
+    }
+
+    /// Take a token for `client`; `Err(wait)` when the bucket is empty.
+    pub fn check(&self, tier: Tier, client: &str, now: Instant) -> Result<(), Duration> {
+        let per_min = match tier {
+            Tier::Cheap => &self.cheap_per_min,
+            Tier::Expensive => &self.expensive_per_min,
+        }
+        .load(Ordering::Relaxed);
+        if per_min == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(per_min);
+        let refill_per_sec = capacity / 60.0;
+
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Ok(());
+        };
+        if buckets.len() > EVICTION_THRESHOLD {
+            buckets.retain(|_, b| now.duration_since(b.updated) < IDLE_EVICTION);
+        }
+        let bucket = buckets.entry((tier, client.to_string())).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / refill_per_sec,
+            ))
+        }
+    }
+}
+
+/// The bucket key for a request: its verified API key, else its UI session, else its peer IP.
+pub(crate) fn client_id(req: &HttpRequest) -> String {
+    if let Some(key) = req.extensions().get::<ApiKeyRecord>() {
+        return format!("key:{}", key.id);
+    }
+    if let Some(session) = req.extensions().get::<Session>() {
+        return format!("session:{}", session.id);
+    }
+    req.peer_addr()
+        .map(|addr| format!("ip:{}", addr.ip()))
+        .unwrap_or_else(|| "ip:unknown".to_string())
+}
+
+/// Whole seconds to wait, at least one.
+pub(crate) fn retry_after_secs(wait: Duration) -> u64 {
+    (wait.as_secs_f64().ceil() as u64).max(1)
+}
+
+/// Middleware for the `/api` scope; runs inside API-key auth so it can key on the verified key.
+pub async fn limit_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let limiter = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.rate_limiter.clone());
+    if let Some(limiter) = limiter {
+        let tier = Tier::for_path(req.path());
+        if let Err(wait) = limiter.check(tier, &client_id(req.request()), Instant::now()) {
+            let retry_after = retry_after_secs(wait);
+            let mut response =
+                ApiError::rate_limited(format!("rate limit exceeded; retry in {retry_after}s"))
+                    .error_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_drain_and_refill_per_tier() {
+        let limiter = RateLimiter::new(60, 2);
+        let t0 = Instant::now();
+        assert!(limiter.check(Tier::Expensive, "a", t0).is_ok());
+        assert!(limiter.check(Tier::Expensive, "a", t0).is_ok());
+        let wait = limiter.check(Tier::Expensive, "a", t0).unwrap_err();
+        assert_eq!(wait.as_secs(), 30);
+
+        // Other clients and the cheap tier are unaffected.
+        assert!(limiter.check(Tier::Expensive, "b", t0).is_ok());
+        assert!(limiter.check(Tier::Cheap, "a", t0).is_ok());
+
+        assert!(limiter
+            .check(Tier::Expensive, "a", t0 + Duration::from_secs(30))
+            .is_ok());
+        assert_eq!(Tier::for_path("/api/ghost/simulate"), Tier::Expensive);
+        assert_eq!(Tier::for_path("/api/v1/speak"), Tier::Expensive);
+        assert_eq!(Tier::for_path("/api/status"), Tier::Cheap);
+    }
+}
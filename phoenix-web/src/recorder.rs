@@ -0,0 +1,117 @@
+//! Recorder control shared by the REST routes, the IPC bridge and gRPC.
+//!
+//! Every start and stop goes through here so the recording metrics, the live `recording`
+//! events and the audit log stay the same whichever surface the call came in on.
+
+use audio_intelligence::MeetingTranscript;
+use pagi_plugins::InputKind;
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::audit::{self, Action, Actor};
+use crate::{metrics, plugins_api, scripts_api, AppState};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum RecorderError {
+    #[error("Audio Intelligence not enabled")]
+    Disabled,
+    /// Starting or stopping failed (e.g. already recording).
+    #[error("{0}")]
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct RecorderStatus {
+    pub enabled: bool,
+    pub listening: bool,
+    pub recording: bool,
+}
+
+pub(crate) async fn status(state: &AppState) -> RecorderStatus {
+    match &state.audio_intelligence {
+        Some(audio) => {
+            let ai = audio.lock().await;
+            RecorderStatus {
+                enabled: true,
+                listening: ai.is_listening(),
+                recording: ai.is_recording(),
+            }
+        }
+        None => RecorderStatus {
+            enabled: false,
+            listening: false,
+            recording: false,
+        },
+    }
+}
+
+/// Start a recording; returns its session id.
+pub(crate) async fn start(
+    state: &AppState,
+    actor: &Actor,
+    purpose: Option<String>,
+) -> Result<String, RecorderError> {
+    let audio = state
+        .audio_intelligence
+        .as_ref()
+        .ok_or(RecorderError::Disabled)?;
+    let ai = audio.lock().await;
+    match ai.start_recording(purpose.clone()).await {
+        Ok(session_id) => {
+            info!(target: "recorder", %session_id, "recording started");
+            audit::record(
+                state,
+                actor,
+                Action::RecordingStart,
+                json!({ "session_id": session_id, "purpose": purpose }),
+            );
+            metrics::recording_started();
+            state.live.recording_started(&session_id);
+            Ok(session_id)
+        }
+        Err(e) => Err(failed(state, e.to_string())),
+    }
+}
+
+/// Stop the current recording and return its transcript. Analyzer plugins and
+/// `on_recording_finished` scripts read the transcript in the background.
+pub(crate) async fn stop(
+    state: &AppState,
+    actor: &Actor,
+) -> Result<MeetingTranscript, RecorderError> {
+    let audio = state
+        .audio_intelligence
+        .as_ref()
+        .ok_or(RecorderError::Disabled)?;
+    let ai = audio.lock().await;
+    match ai.stop_recording().await {
+        Ok(transcript) => {
+            info!(target: "recorder", "recording stopped");
+            audit::record(
+                state,
+                actor,
+                Action::RecordingStop,
+                json!({ "session_id": transcript.session_id }),
+            );
+            state.live.recording_stopped();
+            scripts_api::meeting_finished(state, &transcript);
+            let input = plugins_api::transcript_input(&transcript);
+            let plugins = state.plugins.clone();
+            let live = state.live.clone();
+            tokio::spawn(async move {
+                let findings = plugins_api::analyze(&plugins, input).await;
+                live.plugin_findings(InputKind::Transcript, findings);
+            });
+            Ok(transcript)
+        }
+        Err(e) => Err(failed(state, e.to_string())),
+    }
+}
+
+fn failed(state: &AppState, message: String) -> RecorderError {
+    warn!(target: "recorder", "recording failed: {message}");
+    metrics::recording_failed();
+    state.live.recording_failed(message.clone());
+    RecorderError::Failed(message)
+}
@@ -0,0 +1,584 @@
+//! The capture recorder over HTTP, for installs without the desktop app (a Pi in the living
+//! room).
+//!
+//! The desktop app drives a [`MultiModalRecorder`] through Tauri commands; the server keeps one
+//! of its own over the same recordings folder (`RECORDING_STORAGE_PATH`), so recordings made
+//! either way show up in both libraries. Routes (under `/api`, `record` scope):
+//!
+//! - `GET /recorder/status`: what is running, and whether the recorder is paused.
+//! - `POST /recorder/record` `{"duration_secs", "purpose", "audio", "video"}`: a timed capture,
+//!   run in the background (`202`); it appears in the library once written.
+//! - `POST /recorder/listening/start`, `POST /recorder/listening/stop`: always-listening.
+//! - `POST /recorder/pause` `{"minutes"}`, `POST /recorder/resume`: while paused, listening is
+//!   off, captures are refused with `409` and scheduled ones are skipped. Listening comes back
+//!   on resume if it was on; without `minutes` the pause lasts until resumed.
+//! - `GET /recorder/schedules`, `POST /recorder/schedules` `{"cron", …capture fields}`,
+//!   `DELETE /recorder/schedules/{id}`: recurring captures. They are [`crate::scheduler`] jobs
+//!   (also listed under `/api/scheduler/jobs`), kept apart from the desktop app's schedules.
+//! - `GET /recorder/recordings?purpose=&emotion=&modality=&from_unix=&to_unix=&page=&page_size=`:
+//!   the recording library, newest first.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use multi_modal_recording::recording_library::{RecordingFilter, RecordingModality};
+use multi_modal_recording::{MultiModalRecorder, RecorderStatus};
+use pagi_errors::{ErrorCode, PagiError};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn, Instrument};
+
+use crate::audit::{self, Action, Actor};
+use crate::scheduler::Task;
+use crate::validation::Validator;
+use crate::{scripts_api, ApiError, AppState};
+
+const DEFAULT_CAPTURE_SECS: u64 = 60;
+const MAX_CAPTURE_SECS: u64 = 60 * 60;
+const MAX_PURPOSE_CHARS: usize = 200;
+const MAX_PAUSE_MINUTES: u64 = 24 * 60;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CaptureError {
+    #[error("the recorder is paused; resume it first")]
+    Paused,
+    #[error("guest mode is on and recordings are not allowed")]
+    GuestMode,
+    #[error(transparent)]
+    Recorder(#[from] multi_modal_recording::Error),
+}
+
+impl From<CaptureError> for PagiError {
+    fn from(e: CaptureError) -> Self {
+        match e {
+            CaptureError::Paused => PagiError::new(ErrorCode::Conflict, e.to_string()),
+            CaptureError::GuestMode => PagiError::new(ErrorCode::GuestMode, e.to_string()),
+            CaptureError::Recorder(e) => e.into(),
+        }
+    }
+}
+
+impl From<CaptureError> for ApiError {
+    fn from(e: CaptureError) -> Self {
+        PagiError::from(e).into()
+    }
+}
+
+fn default_duration() -> u64 {
+    DEFAULT_CAPTURE_SECS
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// One timed capture, as asked for over the API or stored in a schedule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureRequest {
+    #[serde(default = "default_duration")]
+    pub duration_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(default = "default_true")]
+    pub audio: bool,
+    #[serde(default = "default_true")]
+    pub video: bool,
+}
+
+impl Default for CaptureRequest {
+    fn default() -> Self {
+        Self {
+            duration_secs: DEFAULT_CAPTURE_SECS,
+            purpose: None,
+            audio: true,
+            video: true,
+        }
+    }
+}
+
+impl CaptureRequest {
+    pub(crate) fn validate(&self, v: &mut Validator) {
+        if !(1..=MAX_CAPTURE_SECS).contains(&self.duration_secs) {
+            v.reject(
+                "duration_secs",
+                format!("must be between 1 and {MAX_CAPTURE_SECS}"),
+            );
+        }
+        if let Some(purpose) = &self.purpose {
+            v.single_line("purpose", purpose, MAX_PURPOSE_CHARS);
+        }
+        if !self.audio && !self.video {
+            v.reject("audio", "audio, video or both must be captured");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Pause {
+    pub since_unix: i64,
+    /// When it ends by itself; `None` until resumed.
+    pub until_unix: Option<i64>,
+    #[serde(skip)]
+    resume_listening: bool,
+    /// Tells a timed resume whether this is still the pause it was set up for.
+    #[serde(skip)]
+    generation: u64,
+}
+
+/// The server's capture recorder and its pause state.
+pub struct Capture {
+    recorder: MultiModalRecorder,
+    pause: Mutex<Option<Pause>>,
+    generation: AtomicU64,
+}
+
+impl Capture {
+    pub fn new(recorder: MultiModalRecorder) -> Self {
+        Self {
+            recorder,
+            pause: Mutex::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// The recorder itself, for subscribers to its status and emotion updates.
+    pub fn recorder(&self) -> &MultiModalRecorder {
+        &self.recorder
+    }
+
+    /// The folder recordings are written to.
+    pub fn storage_path(&self) -> std::path::PathBuf {
+        self.recorder.config().storage_path
+    }
+
+    pub fn emotion_backend(&self) -> &'static str {
+        self.recorder.emotion_backend()
+    }
+
+    /// The recorder's model downloads, which also hold the local LLM.
+    pub fn model_manager(&self) -> multi_modal_recording::model_manager::ModelManager {
+        self.recorder.model_manager()
+    }
+
+    fn paused(&self) -> Option<Pause> {
+        *self.pause.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pause (or extend the current pause) for `minutes`, or until resumed.
+    fn pause(self: &Arc<Self>, minutes: Option<u64>) -> Pause {
+        let now = Utc::now().timestamp();
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        let pause = {
+            let mut slot = self.pause.lock().unwrap_or_else(|e| e.into_inner());
+            let pause = Pause {
+                since_unix: slot.map_or(now, |p| p.since_unix),
+                until_unix: minutes.map(|m| now + (m * 60) as i64),
+                resume_listening: slot.is_some_and(|p| p.resume_listening)
+                    || self.recorder.status().always_listening,
+                generation,
+            };
+            *slot = Some(pause);
+            pause
+        };
+        self.recorder.stop_listening();
+        if let Some(minutes) = minutes {
+            let capture = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(minutes * 60)).await;
+                if capture
+                    .resume_if(|p| p.generation == generation)
+                    .await
+                    .is_some()
+                {
+                    info!(target: "recorder", "pause ended; recorder resumed");
+                }
+            });
+        }
+        pause
+    }
+
+    /// End the pause if there is one and `current` accepts it.
+    async fn resume_if(&self, current: impl FnOnce(&Pause) -> bool) -> Option<Pause> {
+        let pause = {
+            let mut slot = self.pause.lock().unwrap_or_else(|e| e.into_inner());
+            match *slot {
+                Some(p) if current(&p) => slot.take(),
+                _ => None,
+            }
+        }?;
+        if pause.resume_listening {
+            self.recorder.start_always_listening().await;
+        }
+        Some(pause)
+    }
+
+    fn check_can_record(&self) -> Result<(), CaptureError> {
+        if self.paused().is_some() {
+            return Err(CaptureError::Paused);
+        }
+        let guest = self.recorder.guest_mode();
+        if guest.enabled && !guest.allow_recordings {
+            return Err(CaptureError::GuestMode);
+        }
+        Ok(())
+    }
+
+    async fn record(&self, capture: &CaptureRequest) -> Result<std::path::PathBuf, CaptureError> {
+        let path = self
+            .recorder
+            .clone_with_modes(capture.audio, capture.video)
+            .start_on_demand_with_purpose(capture.duration_secs, capture.purpose.clone())
+            .await?;
+        Ok(path)
+    }
+
+    /// Stop what is running, on server shutdown.
+    pub fn stop(&self) {
+        self.recorder.stop_listening();
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    #[serde(flatten)]
+    status: RecorderStatus,
+    paused: Option<Pause>,
+}
+
+/// Journal a written capture and run the `on_recording_finished` scripts.
+fn capture_finished(state: &AppState, capture: &CaptureRequest, path: &Path) {
+    state.journal.record(
+        "capture.finished",
+        "recorder",
+        json!({ "path": path, "capture": capture }),
+    );
+    scripts_api::capture_finished(state, capture, path);
+}
+
+/// What a scheduled capture did, for the scheduler's job log.
+pub(crate) async fn run_scheduled(
+    state: &AppState,
+    capture: &CaptureRequest,
+) -> Result<String, String> {
+    if state.capture.paused().is_some() {
+        return Ok("skipped: the recorder is paused".to_string());
+    }
+    match state.capture.record(capture).await {
+        Ok(path) => {
+            capture_finished(state, capture, &path);
+            Ok(format!("recorded {}", path.display()))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+async fn get_status(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(StatusResponse {
+        status: state.capture.recorder.status(),
+        paused: state.capture.paused(),
+    })
+}
+
+/// Validate `capture` and run it in the background for `actor`; the HTTP route and the mobile
+/// companion both start captures this way.
+pub(crate) fn start_capture(
+    state: &AppState,
+    actor: &Actor,
+    capture: &CaptureRequest,
+) -> Result<(), ApiError> {
+    let mut v = Validator::default();
+    capture.validate(&mut v);
+    v.finish().map_err(ApiError::validation)?;
+    state.capture.check_can_record()?;
+
+    audit::record(
+        state,
+        actor,
+        Action::RecordingStart,
+        json!({ "capture": capture }),
+    );
+    let app = state.clone();
+    let job = capture.clone();
+    // Keep the request's span (and so its request ID) on the capture outliving the response.
+    tokio::spawn(
+        async move {
+            match app.capture.record(&job).await {
+                Ok(path) => {
+                    info!(target: "recorder", path = %path.display(), "capture finished");
+                    capture_finished(&app, &job, &path);
+                }
+                Err(e) => warn!(target: "recorder", "capture failed: {e}"),
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+    Ok(())
+}
+
+async fn post_record(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CaptureRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let capture = body.into_inner();
+    start_capture(&state, &Actor::of(&req), &capture)?;
+    Ok(HttpResponse::Accepted().json(json!({ "status": "recording", "capture": capture })))
+}
+
+async fn post_listening_start(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    if state.capture.paused().is_some() {
+        return Err(CaptureError::Paused.into());
+    }
+    state.capture.recorder.start_always_listening().await;
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::ListeningStart,
+        json!({ "recorder": "capture" }),
+    );
+    Ok(HttpResponse::Ok().json(state.capture.recorder.status()))
+}
+
+async fn post_listening_stop(req: HttpRequest, state: web::Data<AppState>) -> HttpResponse {
+    state.capture.recorder.stop_listening();
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::ListeningStop,
+        json!({ "recorder": "capture" }),
+    );
+    HttpResponse::Ok().json(state.capture.recorder.status())
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PauseBody {
+    #[serde(default)]
+    minutes: Option<u64>,
+}
+
+async fn post_pause(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: Option<web::Json<PauseBody>>,
+) -> Result<HttpResponse, ApiError> {
+    let minutes = body.map(|b| b.into_inner()).unwrap_or_default().minutes;
+    let mut v = Validator::default();
+    if minutes.is_some_and(|m| !(1..=MAX_PAUSE_MINUTES).contains(&m)) {
+        v.reject(
+            "minutes",
+            format!("must be between 1 and {MAX_PAUSE_MINUTES}"),
+        );
+    }
+    v.finish().map_err(ApiError::validation)?;
+    let pause = state.capture.pause(minutes);
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::RecordingPause,
+        json!({ "minutes": minutes }),
+    );
+    Ok(HttpResponse::Ok().json(json!({ "status": "paused", "paused": pause })))
+}
+
+async fn post_resume(req: HttpRequest, state: web::Data<AppState>) -> HttpResponse {
+    let resumed = state.capture.resume_if(|_| true).await;
+    if resumed.is_some() {
+        audit::record(&state, &Actor::of(&req), Action::RecordingResume, json!({}));
+    }
+    HttpResponse::Ok().json(json!({
+        "status": if resumed.is_some() { "resumed" } else { "not_paused" },
+        "recorder": state.capture.recorder.status(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleBody {
+    cron: String,
+    #[serde(flatten)]
+    capture: CaptureRequest,
+}
+
+fn is_recording(task: &Task) -> bool {
+    matches!(task, Task::Recording { .. })
+}
+
+async fn get_schedules(state: web::Data<AppState>) -> HttpResponse {
+    let jobs: Vec<_> = state
+        .scheduler
+        .list()
+        .into_iter()
+        .filter(|job| is_recording(&job.task))
+        .collect();
+    HttpResponse::Ok().json(json!({ "schedules": jobs }))
+}
+
+async fn post_schedule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<ScheduleBody>,
+) -> Result<HttpResponse, ApiError> {
+    let ScheduleBody { cron, capture } = body.into_inner();
+    let mut v = Validator::default();
+    capture.validate(&mut v);
+    v.finish().map_err(ApiError::validation)?;
+    let job = state
+        .scheduler
+        .add_cron(
+            Task::Recording {
+                capture: capture.clone(),
+            },
+            &cron,
+        )
+        .map_err(ApiError::bad_request)?;
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::RecordingSchedule,
+        json!({ "id": job.id, "cron": cron.trim(), "capture": capture }),
+    );
+    Ok(HttpResponse::Created().json(job))
+}
+
+async fn delete_schedule(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let not_found = || ApiError::not_found(format!("no recording schedule {id}"));
+    let job = state.scheduler.get(&id).ok_or_else(not_found)?;
+    if !is_recording(&job.task) {
+        return Err(not_found());
+    }
+    let job = state.scheduler.remove(&id)?;
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::RecordingUnschedule,
+        json!({ "id": job.id }),
+    );
+    Ok(HttpResponse::Ok().json(json!({ "status": "removed", "schedule": job })))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RecordingsQuery {
+    #[serde(default)]
+    purpose: Option<String>,
+    #[serde(default)]
+    emotion: Option<String>,
+    #[serde(default)]
+    modality: Option<RecordingModality>,
+    #[serde(default)]
+    from_unix: Option<i64>,
+    #[serde(default)]
+    to_unix: Option<i64>,
+    #[serde(default)]
+    page: usize,
+    /// `0` for the library's default.
+    #[serde(default)]
+    page_size: usize,
+}
+
+async fn get_recordings(
+    state: web::Data<AppState>,
+    query: web::Query<RecordingsQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let q = query.into_inner();
+    if let (Some(from), Some(to)) = (q.from_unix, q.to_unix) {
+        if from > to {
+            return Err(ApiError::bad_request("from_unix must not be after to_unix"));
+        }
+    }
+    let filter = RecordingFilter {
+        modality: q.modality,
+        from_unix: q.from_unix,
+        to_unix: q.to_unix,
+        purpose: q.purpose.filter(|p| !p.trim().is_empty()),
+        emotion: q.emotion.filter(|e| !e.trim().is_empty()),
+    };
+    let page = state
+        .capture
+        .recorder
+        .list_recordings(&filter, q.page, q.page_size)
+        .await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/recorder")
+            .route("/status", web::get().to(get_status))
+            .route("/record", web::post().to(post_record))
+            .route("/listening/start", web::post().to(post_listening_start))
+            .route("/listening/stop", web::post().to(post_listening_stop))
+            .route("/pause", web::post().to(post_pause))
+            .route("/resume", web::post().to(post_resume))
+            .route("/schedules", web::get().to(get_schedules))
+            .route("/schedules", web::post().to(post_schedule))
+            .route("/schedules/{id}", web::delete().to(delete_schedule))
+            .route("/recordings", web::get().to(get_recordings)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(capture: &CaptureRequest) -> Vec<String> {
+        let mut v = Validator::default();
+        capture.validate(&mut v);
+        v.finish()
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[test]
+    fn captures_need_a_duration_and_a_device() {
+        let capture: CaptureRequest = serde_json::from_str(r#"{"purpose":"standup"}"#).unwrap();
+        assert_eq!(capture.duration_secs, DEFAULT_CAPTURE_SECS);
+        assert!(capture.audio && capture.video);
+        assert!(rejected(&capture).is_empty());
+
+        let bad = CaptureRequest {
+            duration_secs: 0,
+            purpose: Some("a\nb".to_string()),
+            audio: false,
+            video: false,
+        };
+        assert_eq!(rejected(&bad), ["duration_secs", "purpose", "audio"]);
+    }
+
+    #[tokio::test]
+    async fn pausing_refuses_captures_until_resumed() {
+        let capture = Arc::new(Capture::new(MultiModalRecorder::default()));
+        assert!(capture.check_can_record().is_ok());
+
+        let first = capture.pause(Some(5));
+        let extended = capture.pause(None);
+        assert_eq!(extended.since_unix, first.since_unix);
+        assert_eq!(extended.until_unix, None);
+        assert!(matches!(
+            capture.check_can_record(),
+            Err(CaptureError::Paused)
+        ));
+
+        // The timer for the first pause no longer applies.
+        assert!(capture
+            .resume_if(|p| p.generation == first.generation)
+            .await
+            .is_none());
+        assert!(capture.resume_if(|_| true).await.is_some());
+        assert!(capture.check_can_record().is_ok());
+        assert!(capture.resume_if(|_| true).await.is_none());
+    }
+}
@@ -0,0 +1,69 @@
+//! Per-request tracing.
+//!
+//! Every request runs inside an `http` `request` span carrying its request ID, method and path,
+//! so anything a handler logs can be tied back to the request. The ID comes from the caller's
+//! `X-Request-Id` when it looks sane, otherwise a fresh UUID, and is echoed in the response.
+//! The desktop app sends the correlation ID of the UI action there (see
+//! [`pagi_utils::correlation`]), so its logs and ours share one ID; recorder work started by a
+//! request, including background captures, stays inside this span.
+//! One `http` event per request records status and latency: `warn` for 5xx, `info` otherwise.
+
+use std::time::Instant;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use pagi_utils::correlation;
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = correlation::HEADER;
+
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let request_id = correlation::or_new(
+        req.headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    );
+    let peer = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or("-")
+        .to_string();
+    let span = tracing::info_span!(
+        target: "http",
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.path(),
+        peer = %peer,
+    );
+
+    let started = Instant::now();
+    let result = next.call(req).instrument(span.clone()).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let _entered = span.enter();
+    match result {
+        Ok(mut res) => {
+            let status = res.status().as_u16();
+            if res.status().is_server_error() {
+                tracing::warn!(target: "http", status, latency_ms, "request failed");
+            } else {
+                tracing::info!(target: "http", status, latency_ms, "request completed");
+            }
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(res)
+        }
+        Err(e) => {
+            tracing::warn!(target: "http", latency_ms, error = %e, "request errored");
+            Err(e)
+        }
+    }
+}
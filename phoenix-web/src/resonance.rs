@@ -11,13 +11,18 @@ pub enum PartnerPersona {
 
 impl PartnerPersona {
     pub fn from_loose(s: &str) -> Self {
+        Self::parse(s).unwrap_or(Self::Secure)
+    }
+
+    /// Like [`from_loose`](Self::from_loose), but `None` for an unknown label.
+    pub fn parse(s: &str) -> Option<Self> {
         let t = s.trim().to_ascii_lowercase();
         match t.as_str() {
-            "secure" => Self::Secure,
-            "avoidant" | "avoidant-dismissive" | "avoidant_dismissive" => Self::AvoidantDismissive,
-            "anxious" | "anxious-preoccupied" | "anxious_preoccupied" => Self::AnxiousPreoccupied,
-            "fearful" | "fearful-avoidant" | "fearful_avoidant" | "disorganized" => Self::FearfulAvoidant,
-            _ => Self::Secure,
+            "secure" => Some(Self::Secure),
+            "avoidant" | "avoidant-dismissive" | "avoidant_dismissive" => Some(Self::AvoidantDismissive),
+            "anxious" | "anxious-preoccupied" | "anxious_preoccupied" => Some(Self::AnxiousPreoccupied),
+            "fearful" | "fearful-avoidant" | "fearful_avoidant" | "disorganized" => Some(Self::FearfulAvoidant),
+            _ => None,
         }
     }
 
@@ -51,6 +56,149 @@ pub struct ResonanceResult {
     pub suggestions: Vec<String>,
 }
 
+/// Where a cue was found in the script. Offsets count characters (Unicode scalar values), end
+/// exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Case-insensitive occurrences of an ASCII `needle` in `script`.
+pub fn find_spans(script: &str, needle: &str) -> Vec<TextSpan> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    // ASCII lowercasing keeps byte offsets, so matches map straight back onto `script`.
+    let lower = script.to_ascii_lowercase();
+    let needle = needle.to_ascii_lowercase();
+    lower
+        .match_indices(&needle)
+        .map(|(byte, m)| {
+            let start = script[..byte].chars().count();
+            let text = &script[byte..byte + m.len()];
+            TextSpan {
+                start,
+                end: start + text.chars().count(),
+                text: text.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// The four parts of an NVC message: Observation, Feeling, Need, Request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OfnrComponent {
+    Observation,
+    Feeling,
+    Need,
+    Request,
+}
+
+impl OfnrComponent {
+    pub const ALL: [OfnrComponent; 4] = [
+        OfnrComponent::Observation,
+        OfnrComponent::Feeling,
+        OfnrComponent::Need,
+        OfnrComponent::Request,
+    ];
+
+    /// The serialized name (`observation`, `feeling`, `need`, `request`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Observation => "observation",
+            Self::Feeling => "feeling",
+            Self::Need => "need",
+            Self::Request => "request",
+        }
+    }
+
+    fn cues(self) -> &'static [&'static str] {
+        match self {
+            Self::Observation => &[
+                "when i noticed",
+                "when i notice",
+                "when i saw",
+                "when i heard",
+                "i noticed",
+                "i notice",
+                "yesterday",
+                "last night",
+                "this morning",
+                "earlier today",
+            ],
+            Self::Feeling => &["i'm feeling", "i am feeling", "i feel", "i felt"],
+            Self::Need => &["because i need", "i need", "i value", "it's important to me"],
+            Self::Request => &[
+                "would you be willing",
+                "would you be open",
+                "are you open to",
+                "would you",
+                "could you",
+                "can we",
+            ],
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Self::Observation => "Start with something observable: 'When I noticed…'.",
+            Self::Feeling => "Name the feeling: 'I feel…'.",
+            Self::Need => "Say the need behind it: 'because I need…'.",
+            Self::Request => "End with a doable request: 'Would you be willing to…?'",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfnrPart {
+    pub component: OfnrComponent,
+    pub present: bool,
+    pub spans: Vec<TextSpan>,
+    /// How to add the part when it is missing.
+    #[serde(default)]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfnrBreakdown {
+    pub parts: Vec<OfnrPart>,
+    /// All four parts are present.
+    pub complete: bool,
+}
+
+/// Which OFNR parts the script contains, and where.
+pub fn analyze_ofnr(script: &str) -> OfnrBreakdown {
+    let parts = OfnrComponent::ALL
+        .into_iter()
+        .map(|component| {
+            let mut spans: Vec<TextSpan> = Vec::new();
+            // Longer cues come first; skip shorter ones inside an earlier match.
+            for cue in component.cues() {
+                for span in find_spans(script, cue) {
+                    if !spans.iter().any(|s| s.start <= span.start && span.end <= s.end) {
+                        spans.push(span);
+                    }
+                }
+            }
+            spans.sort_by_key(|s| s.start);
+            let present = !spans.is_empty();
+            OfnrPart {
+                component,
+                present,
+                spans,
+                hint: (!present).then(|| component.hint().to_string()),
+            }
+        })
+        .collect::<Vec<_>>();
+    OfnrBreakdown {
+        complete: parts.iter().all(|p| p.present),
+        parts,
+    }
+}
+
 fn contains_any(hay: &str, patterns: &[&str]) -> bool {
     patterns.iter().any(|p| hay.contains(p))
 }
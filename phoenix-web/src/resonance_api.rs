@@ -0,0 +1,125 @@
+//! Standalone NVC feedback over HTTP.
+//!
+//! `POST /api/resonance/analyze` scores a script against a partner persona without generating a
+//! ghost reply, for editors and bots. On top of the counselor `ResonanceResult` it returns where
+//! each breach occurs (character spans) and an OFNR breakdown (which of Observation, Feeling,
+//! Need, Request the script contains).
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::compute_cache::{self, ComputeCache};
+use crate::ghost_engine::detect_breaches;
+use crate::resonance::{
+    analyze_ofnr, analyze_resonance, find_spans, OfnrBreakdown, PartnerPersona, ResonanceResult,
+    TextSpan,
+};
+use crate::validation::Validator;
+use crate::{api_json_config, metrics, ApiError, AppState, FieldError};
+
+/// Longest script accepted, in characters.
+pub const MAX_SCRIPT_CHARS: usize = 5_000;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AnalyzeRequest {
+    pub script: String,
+    /// Loose persona label (`secure`, `avoidant`, `anxious`, `fearful`, …); defaults to secure.
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// `gentle` | `direct`.
+    #[serde(default)]
+    pub tone: Option<String>,
+}
+
+/// A breach with every place it occurs.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreachSpans {
+    pub kind: String,
+    pub needle: String,
+    pub message: String,
+    pub spans: Vec<TextSpan>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResonanceAnalysis {
+    #[serde(flatten)]
+    pub result: ResonanceResult,
+    pub breaches: Vec<BreachSpans>,
+    pub ofnr: OfnrBreakdown,
+}
+
+pub fn analyze(script: &str, persona: PartnerPersona, tone: Option<&str>) -> ResonanceAnalysis {
+    let breaches = detect_breaches(script, pagi_i18n::language())
+        .into_iter()
+        .map(|b| BreachSpans {
+            spans: find_spans(script, &b.needle),
+            kind: b.kind,
+            needle: b.needle,
+            message: b.message,
+        })
+        .collect();
+    ResonanceAnalysis {
+        result: analyze_resonance(script, persona, tone),
+        breaches,
+        ofnr: analyze_ofnr(script),
+    }
+}
+
+fn validate(req: &AnalyzeRequest) -> Result<PartnerPersona, Vec<FieldError>> {
+    let mut v = Validator::default();
+    v.text("script", &req.script, MAX_SCRIPT_CHARS);
+    let persona = match req.persona.as_deref() {
+        None => PartnerPersona::Secure,
+        Some(label) => PartnerPersona::parse(label).unwrap_or_else(|| {
+            v.reject(
+                "persona",
+                "must be one of secure, avoidant, anxious, fearful",
+            );
+            PartnerPersona::Secure
+        }),
+    };
+    if let Some(tone) = req.tone.as_deref() {
+        v.one_of("tone", tone, &["gentle", "direct"]);
+    }
+    v.finish()?;
+    Ok(persona)
+}
+
+/// Check and analyze a request, reusing a cached analysis of the same script; shared with
+/// [`crate::grpc`].
+pub(crate) fn run(
+    cache: &ComputeCache,
+    req: &AnalyzeRequest,
+) -> Result<ResonanceAnalysis, Vec<FieldError>> {
+    let persona = validate(req)?;
+    let key = compute_cache::resonance_key(&req.script, &persona, req.tone.as_deref());
+    let analysis = match cache.resonance.get(&key) {
+        Some(analysis) => analysis,
+        None => {
+            let analysis = analyze(&req.script, persona, req.tone.as_deref());
+            cache.resonance.insert(key, analysis.clone());
+            analysis
+        }
+    };
+    metrics::resonance_score("resonance", analysis.result.resonance_score);
+    Ok(analysis)
+}
+
+/// POST /api/resonance/analyze
+async fn post_analyze(
+    state: web::Data<AppState>,
+    body: web::Json<AnalyzeRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let analysis = run(&state.cache, &body).map_err(ApiError::validation)?;
+    Ok(HttpResponse::Ok().json(analysis))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/resonance")
+            .app_data(api_json_config(MAX_BODY_BYTES))
+            .route("/analyze", web::post().to(post_analyze)),
+    );
+}
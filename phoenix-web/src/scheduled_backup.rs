@@ -0,0 +1,715 @@
+//! Automatic backups: the `backup` job of [`crate::scheduler`] writes an encrypted
+//! [`crate::backup`] snapshot to a local directory, a WebDAV folder or an S3 bucket, then prunes
+//! old snapshots there.
+//!
+//! Settings live under `[backup]`. `schedule` is `daily` (02:00), `weekly` (Sundays 02:00), a
+//! cron expression, or `off` (the default). Snapshots are always encrypted with `passphrase` and
+//! named `phoenix-backup-<UTC time>.tar.gz.enc`; `POST /api/admin/restore` takes them back with
+//! that passphrase. `destination` is one of:
+//!
+//! - a directory (the default is `data/backups` under the data directory);
+//! - an `http://` or `https://` WebDAV folder, with `webdav_user` and `webdav_password`;
+//! - `s3://<bucket>/<prefix>`, with `s3_access_key`, `s3_secret_key`, `s3_region` and, for
+//!   S3-compatible stores other than AWS, `s3_endpoint`.
+//!
+//! After each snapshot the newest `keep` stay, less any older than `keep_days` when that is set.
+//! The snapshot just written always stays. Every result is announced as a proactive message
+//! with reason `backup` (so on the WebSocket, the `schedule` event stream and webhooks) and
+//! journaled. `GET /health` reports the last run, and `GET /api/scheduler/jobs/backup` has the
+//! details.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use pagi_config::{parse_number, ConfigError, Layers, Source};
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use ring::hmac;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::backup::{self, BackupError};
+use crate::proactive::ProactiveMessage;
+use crate::scheduler::{self, Task};
+use crate::AppState;
+
+/// `schedule = "daily"`.
+pub const DAILY: &str = "0 0 2 * * *";
+/// `schedule = "weekly"`.
+pub const WEEKLY: &str = "0 0 2 * * Sun";
+
+const DEFAULT_KEEP: usize = 7;
+const DEFAULT_S3_REGION: &str = "us-east-1";
+
+const SNAPSHOT_PREFIX: &str = "phoenix-backup-";
+const SNAPSHOT_SUFFIX: &str = ".tar.gz.enc";
+const SNAPSHOT_TIME: &str = "%Y%m%d-%H%M%S";
+
+/// Uploads of large snapshots to slow remotes can take a while.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduledBackupError {
+    #[error(transparent)]
+    Backup(#[from] BackupError),
+    #[error("backup I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("backup upload failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{operation} {name} failed: HTTP {status}")]
+    Status {
+        operation: &'static str,
+        name: String,
+        status: StatusCode,
+    },
+}
+
+type Result<T> = std::result::Result<T, ScheduledBackupError>;
+
+/// `[backup]` settings.
+#[derive(Clone)]
+pub struct BackupSchedule {
+    /// Cron expression; `None` = no automatic backups.
+    pub schedule: Option<String>,
+    pub destination: Destination,
+    /// What snapshots are encrypted with; always set when `schedule` is.
+    pub passphrase: Option<String>,
+    /// Snapshots kept, at least 1.
+    pub keep: usize,
+    /// Snapshots older than this many days are pruned too.
+    pub keep_days: Option<u32>,
+}
+
+impl fmt::Debug for BackupSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackupSchedule")
+            .field("schedule", &self.schedule)
+            .field("destination", &self.destination.to_string())
+            .field("passphrase", &self.passphrase.as_ref().map(|_| "********"))
+            .field("keep", &self.keep)
+            .field("keep_days", &self.keep_days)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub enum Destination {
+    Dir(PathBuf),
+    WebDav {
+        /// The folder, ending in `/`.
+        url: Url,
+        user: Option<String>,
+        password: Option<String>,
+    },
+    S3(S3Bucket),
+}
+
+/// Shown without credentials.
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dir(dir) => write!(f, "{}", dir.display()),
+            Self::WebDav { url, .. } => write!(f, "{url}"),
+            Self::S3(s3) => write!(f, "s3://{}/{}", s3.bucket, s3.prefix),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct S3Bucket {
+    pub bucket: String,
+    /// Key prefix, empty or ending in `/`.
+    pub prefix: String,
+    pub region: String,
+    /// Path-style endpoint, e.g. `https://s3.us-east-1.amazonaws.com`.
+    pub endpoint: Url,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// A `backup.schedule` value: `daily`, `weekly`, a cron expression, or `off`.
+pub fn parse_schedule(s: &str) -> std::result::Result<Option<String>, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "daily" => Ok(Some(DAILY.to_string())),
+        "weekly" => Ok(Some(WEEKLY.to_string())),
+        _ => scheduler::parse_schedule(s),
+    }
+}
+
+fn invalid(key: &'static str, layers: &Layers, message: &str) -> ConfigError {
+    ConfigError::Invalid {
+        key,
+        origin: layers
+            .get(key)
+            .map_or(Source::Default, |(_, source)| source),
+        message: message.to_string(),
+    }
+}
+
+impl BackupSchedule {
+    pub fn from_layers(layers: &Layers, data_dir: &Path) -> std::result::Result<Self, ConfigError> {
+        let schedule = layers.or("backup.schedule", None, parse_schedule)?;
+        let passphrase = layers.secret("backup.passphrase")?;
+        match &passphrase {
+            None if schedule.is_some() => {
+                return Err(invalid(
+                    "backup.passphrase",
+                    layers,
+                    "required with backup.schedule; snapshots are always encrypted",
+                ));
+            }
+            Some(p) if p.chars().count() < backup::MIN_PASSPHRASE_CHARS => {
+                return Err(invalid(
+                    "backup.passphrase",
+                    layers,
+                    &format!(
+                        "must be at least {} characters",
+                        backup::MIN_PASSPHRASE_CHARS
+                    ),
+                ));
+            }
+            _ => {}
+        }
+
+        let raw = layers
+            .get("backup.destination")
+            .map(|(value, _)| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+        let destination = match raw {
+            None => Destination::Dir(data_dir.join("data/backups")),
+            Some(raw) if raw.starts_with("http://") || raw.starts_with("https://") => {
+                let mut url = Url::parse(&raw)
+                    .map_err(|e| invalid("backup.destination", layers, &e.to_string()))?;
+                if !url.path().ends_with('/') {
+                    url.set_path(&format!("{}/", url.path()));
+                }
+                Destination::WebDav {
+                    url,
+                    user: layers.get("backup.webdav_user").map(|(user, _)| user),
+                    password: layers.secret("backup.webdav_password")?,
+                }
+            }
+            Some(raw) if raw.starts_with("s3://") => {
+                let path = &raw["s3://".len()..];
+                let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+                if bucket.is_empty() {
+                    return Err(invalid(
+                        "backup.destination",
+                        layers,
+                        "expected s3://<bucket>/<prefix>",
+                    ));
+                }
+                let prefix = prefix.trim_matches('/');
+                let region = layers
+                    .get("backup.s3_region")
+                    .map_or_else(|| DEFAULT_S3_REGION.to_string(), |(region, _)| region);
+                let endpoint = layers
+                    .get("backup.s3_endpoint")
+                    .map_or_else(|| format!("https://s3.{region}.amazonaws.com"), |(e, _)| e);
+                let credentials = (
+                    layers.get("backup.s3_access_key").map(|(key, _)| key),
+                    layers.secret("backup.s3_secret_key")?,
+                );
+                let (Some(access_key), Some(secret_key)) = credentials else {
+                    return Err(invalid(
+                        "backup.destination",
+                        layers,
+                        "S3 needs backup.s3_access_key and backup.s3_secret_key",
+                    ));
+                };
+                Destination::S3(S3Bucket {
+                    bucket: bucket.to_string(),
+                    prefix: if prefix.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{prefix}/")
+                    },
+                    endpoint: Url::parse(&endpoint)
+                        .map_err(|e| invalid("backup.s3_endpoint", layers, &e.to_string()))?,
+                    region,
+                    access_key,
+                    secret_key,
+                })
+            }
+            Some(raw) => {
+                let dir = PathBuf::from(raw);
+                Destination::Dir(if dir.is_absolute() {
+                    dir
+                } else {
+                    data_dir.join(dir)
+                })
+            }
+        };
+
+        Ok(Self {
+            schedule,
+            destination,
+            passphrase,
+            keep: layers.or("backup.keep", DEFAULT_KEEP, |s| {
+                match parse_number::<usize>(s)? {
+                    0 => Err("must be at least 1".to_string()),
+                    n => Ok(n),
+                }
+            })?,
+            keep_days: layers
+                .or("backup.keep_days", 0, parse_number::<u32>)
+                .map(|days| Some(days).filter(|d| *d > 0))?,
+        })
+    }
+}
+
+fn snapshot_time(name: &str) -> Option<i64> {
+    let stamp = name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, SNAPSHOT_TIME)
+        .ok()
+        .map(|t| t.and_utc().timestamp())
+}
+
+/// Snapshots among `names` that the retention rule drops; `current` is never one of them.
+/// Names that aren't snapshots are left alone.
+fn to_prune(
+    names: &[String],
+    current: &str,
+    keep: usize,
+    keep_days: Option<u32>,
+    now: i64,
+) -> Vec<String> {
+    let mut snapshots: Vec<(i64, &String)> = names
+        .iter()
+        .filter_map(|name| snapshot_time(name).map(|t| (t, name)))
+        .collect();
+    snapshots.sort_by(|a, b| b.cmp(a));
+    let oldest_kept = keep_days.map_or(i64::MIN, |days| now - i64::from(days) * 86_400);
+    snapshots
+        .into_iter()
+        .enumerate()
+        .filter(|(i, (t, name))| *name != current && (*i >= keep || *t < oldest_kept))
+        .map(|(_, (_, name))| name.clone())
+        .collect()
+}
+
+/// Percent-encode everything but unreserved characters, and `/` when `keep_slash`.
+fn uri_encode(s: &str, keep_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(b as char)
+            }
+            b'/' if keep_slash => out.push('/'),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+/// The AWS Signature Version 4 signing key for a day, region and service.
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{secret}").as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+impl S3Bucket {
+    /// A request for `key` in the bucket (the bucket itself when empty), signed with Signature
+    /// Version 4. `query` must be sorted by name.
+    fn request(
+        &self,
+        http: &reqwest::Client,
+        method: Method,
+        key: &str,
+        query: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> RequestBuilder {
+        let path = format!(
+            "{}/{}/{key}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket
+        );
+        let path = uri_encode(&path, true);
+        let query = query
+            .iter()
+            .map(|(name, value)| {
+                format!("{}={}", uri_encode(name, false), uri_encode(value, false))
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{port}", self.endpoint.host_str().unwrap_or_default()),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex(&Sha256::digest(&body));
+
+        let canonical = format!(
+            "{method}\n{path}\n{query}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\n\
+             x-amz-date:{amz_date}\n\nhost;x-amz-content-sha256;x-amz-date\n{payload_hash}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let key = signing_key(&self.secret_key, &date, &self.region, "s3");
+        let signature = hex(&hmac_sha256(&key, &to_sign));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}",
+            self.access_key
+        );
+
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        url.set_query(Some(&query).filter(|q| !q.is_empty()).map(String::as_str));
+        http.request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .body(body)
+    }
+}
+
+/// The text of every `<tag>` element in `xml`, namespace prefixes ignored.
+fn xml_texts<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("{tag}>");
+    xml.split('<')
+        .filter_map(|part| {
+            let (name, text) = part.split_once('>')?;
+            let local = name.rsplit(':').next()?;
+            (format!("{local}>") == open && !name.starts_with('/')).then_some(text)
+        })
+        .collect()
+}
+
+async fn check(
+    response: reqwest::Response,
+    operation: &'static str,
+    name: &str,
+) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(ScheduledBackupError::Status {
+            operation,
+            name: name.to_string(),
+            status,
+        })
+    }
+}
+
+impl Destination {
+    fn webdav(
+        http: &reqwest::Client,
+        method: Method,
+        url: Url,
+        user: &Option<String>,
+        password: &Option<String>,
+    ) -> RequestBuilder {
+        let request = http.request(method, url);
+        match user {
+            Some(user) => request.basic_auth(user, password.as_ref()),
+            None => request,
+        }
+    }
+
+    pub async fn put(&self, http: &reqwest::Client, name: &str, data: Vec<u8>) -> Result<()> {
+        match self {
+            Self::Dir(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                // Moved into place once complete, so a failed write never looks like a snapshot.
+                let partial = dir.join(format!("{name}.part"));
+                tokio::fs::write(&partial, data).await?;
+                tokio::fs::rename(&partial, dir.join(name)).await?;
+            }
+            Self::WebDav {
+                url,
+                user,
+                password,
+            } => {
+                let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+                // 405 when the folder exists already.
+                let _ = Self::webdav(http, mkcol, url.clone(), user, password)
+                    .send()
+                    .await?;
+                let file = url.join(name).map_err(std::io::Error::other)?;
+                let response = Self::webdav(http, Method::PUT, file, user, password)
+                    .body(data)
+                    .send()
+                    .await?;
+                check(response, "upload", name).await?;
+            }
+            Self::S3(s3) => {
+                let key = format!("{}{name}", s3.prefix);
+                let response = s3
+                    .request(http, Method::PUT, &key, &[], data)
+                    .send()
+                    .await?;
+                check(response, "upload", name).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the files at the destination.
+    pub async fn list(&self, http: &reqwest::Client) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        match self {
+            Self::Dir(dir) => {
+                let mut entries = tokio::fs::read_dir(dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    names.push(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+            Self::WebDav {
+                url,
+                user,
+                password,
+            } => {
+                let propfind = Method::from_bytes(b"PROPFIND").expect("valid method");
+                let response = Self::webdav(http, propfind, url.clone(), user, password)
+                    .header("Depth", "1")
+                    .send()
+                    .await?;
+                let xml = check(response, "list", url.as_str()).await?.text().await?;
+                names.extend(xml_texts(&xml, "href").into_iter().filter_map(|href| {
+                    href.trim_end_matches('/')
+                        .rsplit('/')
+                        .next()
+                        .map(str::to_string)
+                }));
+            }
+            Self::S3(s3) => {
+                let mut token = None;
+                loop {
+                    let mut query = vec![];
+                    if let Some(token) = token.take() {
+                        query.push(("continuation-token", token));
+                    }
+                    query.push(("list-type", "2".to_string()));
+                    query.push(("prefix", s3.prefix.clone()));
+                    let response = s3
+                        .request(http, Method::GET, "", &query, Vec::new())
+                        .send()
+                        .await?;
+                    let xml = check(response, "list", &s3.bucket).await?.text().await?;
+                    names.extend(xml_texts(&xml, "Key").into_iter().map(|key| {
+                        key.strip_prefix(s3.prefix.as_str())
+                            .unwrap_or(key)
+                            .to_string()
+                    }));
+                    match xml_texts(&xml, "NextContinuationToken").first() {
+                        Some(next) => token = Some(next.to_string()),
+                        None => break,
+                    }
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    pub async fn delete(&self, http: &reqwest::Client, name: &str) -> Result<()> {
+        match self {
+            Self::Dir(dir) => tokio::fs::remove_file(dir.join(name)).await?,
+            Self::WebDav {
+                url,
+                user,
+                password,
+            } => {
+                let file = url.join(name).map_err(std::io::Error::other)?;
+                let response = Self::webdav(http, Method::DELETE, file, user, password)
+                    .send()
+                    .await?;
+                check(response, "delete", name).await?;
+            }
+            Self::S3(s3) => {
+                let key = format!("{}{name}", s3.prefix);
+                let response = s3
+                    .request(http, Method::DELETE, &key, &[], Vec::new())
+                    .send()
+                    .await?;
+                check(response, "delete", name).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Take a snapshot, store it and prune old ones; returns the snapshot's name, its size and
+/// how many were pruned.
+async fn snapshot(state: &AppState, settings: &BackupSchedule) -> Result<(String, usize, usize)> {
+    let passphrase = settings.passphrase.clone().unwrap_or_default();
+    let paths = state.backup_paths.clone();
+    let storage = state.storage.clone();
+    let vaults = state.vaults.clone();
+    let archive = tokio::task::spawn_blocking(move || {
+        backup::create(&paths, &storage, &vaults, Some(&passphrase))
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    let now = Utc::now();
+    let name = format!(
+        "{SNAPSHOT_PREFIX}{}{SNAPSHOT_SUFFIX}",
+        now.format(SNAPSHOT_TIME)
+    );
+    let bytes = archive.len();
+    let http = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let destination = &settings.destination;
+    destination.put(&http, &name, archive).await?;
+
+    let existing = destination.list(&http).await?;
+    let prune = to_prune(
+        &existing,
+        &name,
+        settings.keep,
+        settings.keep_days,
+        now.timestamp(),
+    );
+    for old in &prune {
+        destination.delete(&http, old).await?;
+    }
+    Ok((name, bytes, prune.len()))
+}
+
+/// The `backup` job.
+pub(crate) async fn run(state: &AppState) -> std::result::Result<String, String> {
+    let Some(settings) = &state.backup_schedule else {
+        return Err("automatic backups are off; set backup.schedule".to_string());
+    };
+    let destination = settings.destination.to_string();
+    let result = snapshot(state, settings).await;
+    let (content, outcome) = match &result {
+        Ok((name, bytes, pruned)) => {
+            state.journal.record(
+                "backup.scheduled",
+                "scheduler",
+                json!({ "destination": destination, "snapshot": name, "bytes": bytes,
+                        "pruned": pruned }),
+            );
+            (
+                format!("Backup saved to {destination}."),
+                Ok(format!(
+                    "saved {name} ({bytes} bytes) to {destination}; pruned {pruned}"
+                )),
+            )
+        }
+        Err(e) => {
+            tracing::warn!(target: "backup", %destination, "scheduled backup failed: {e}");
+            state.journal.record(
+                "backup.failed",
+                "scheduler",
+                json!({ "destination": destination, "error": e.to_string() }),
+            );
+            (
+                format!("Backup to {destination} failed: {e}"),
+                Err(e.to_string()),
+            )
+        }
+    };
+    let _ = state.proactive_tx.send(ProactiveMessage {
+        content,
+        reason: "backup".to_string(),
+        timestamp: Utc::now().timestamp(),
+    });
+    outcome
+}
+
+/// The `backup` job's last result and next run for `GET /health`; `None` when backups are off.
+pub(crate) fn health(state: &AppState) -> Option<Value> {
+    let job = state.scheduler.get(Task::Backup.name())?;
+    Some(json!({
+        "ok": job.last_run.as_ref().map(|run| run.ok),
+        "last_run_unix": job.last_run.as_ref().map(|run| run.started_unix),
+        "next_run_unix": job.next_run_unix,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(stamp: &str) -> String {
+        format!("{SNAPSHOT_PREFIX}{stamp}{SNAPSHOT_SUFFIX}")
+    }
+
+    #[test]
+    fn keeps_the_newest_and_drops_the_expired() {
+        let names = vec![
+            name("20261001-020000"),
+            name("20261015-020000"),
+            name("20261016-020000"),
+            name("20261017-020000"),
+            "notes.txt".to_string(),
+        ];
+        let current = name("20261017-020000");
+        let now = snapshot_time(&current).unwrap();
+
+        assert_eq!(
+            to_prune(&names, &current, 2, None, now),
+            [name("20261015-020000"), name("20261001-020000")]
+        );
+        assert_eq!(
+            to_prune(&names, &current, 10, Some(7), now),
+            [name("20261001-020000")]
+        );
+        // The snapshot just written stays even when it is past the age limit.
+        assert!(to_prune(&names, &current, 1, Some(0), now + 86_400 * 30)
+            .iter()
+            .all(|n| *n != current));
+    }
+
+    #[test]
+    fn derives_sigv4_signing_keys() {
+        // The worked example in the AWS Signature Version 4 documentation.
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(uri_encode("a b/c~", true), "a%20b/c~");
+        assert_eq!(
+            xml_texts("<D:href>/dav/a</D:href><d:href>/dav/b</d:href>", "href"),
+            ["/dav/a", "/dav/b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_directory_destination_stores_lists_and_deletes() {
+        let dir = std::env::temp_dir().join(format!("phoenix-backups-{}", uuid::Uuid::new_v4()));
+        let destination = Destination::Dir(dir.clone());
+        let http = reqwest::Client::new();
+        let snapshot = name("20261018-020000");
+        destination
+            .put(&http, &snapshot, b"sealed".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(destination.list(&http).await.unwrap(), [snapshot.as_str()]);
+        destination.delete(&http, &snapshot).await.unwrap();
+        assert!(destination.list(&http).await.unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,154 @@
+//! What the [`crate::scheduler`] jobs do. Each returns a one-line summary for the job's last
+//! run, or why it failed.
+
+use chrono::{Local, Utc};
+use multi_modal_recording::emotion_trends::{self, EmotionTrends, TrendBucket, TrendQuery};
+use multi_modal_recording::MultiModalRecorder;
+use serde_json::json;
+
+use crate::analytics::GHOST_SESSION_KEEP_DAYS;
+use crate::ghost_engine;
+use crate::proactive::ProactiveMessage;
+use crate::scheduler::Task;
+use crate::webhooks::DELIVERY_LOG_KEEP_DAYS;
+use crate::AppState;
+
+/// Soul Vault key prefix for stored weekly reports; the date the report was made is appended.
+pub const WEEKLY_REPORT_PREFIX: &str = "emotion:report:weekly:";
+
+const WEEK_SECS: i64 = 7 * 86_400;
+
+pub async fn run(state: &AppState, task: &Task) -> Result<String, String> {
+    match task {
+        Task::RetentionPrune => retention_prune(state).await,
+        Task::WeeklyReport => weekly_report(state),
+        Task::ModelUpdates => model_updates().await,
+        Task::Backup => crate::scheduled_backup::run(state).await,
+        Task::Sync => crate::device_sync::run(state).await,
+        Task::GhostReply { request } => {
+            let resp = ghost_engine::simulate(state, request.clone()).await;
+            Ok(format!(
+                "ghost session {} replied (risk {}, drift {:+})",
+                resp.session_id, resp.risk_score, resp.drift_delta
+            ))
+        }
+        Task::Recording { capture } => crate::recorder_api::run_scheduled(state, capture).await,
+    }
+}
+
+async fn retention_prune(state: &AppState) -> Result<String, String> {
+    let sessions = match &state.sessions {
+        Some(sessions) => sessions.prune().map_err(|e| e.to_string())?,
+        None => 0,
+    };
+    let now = Utc::now().timestamp();
+    let ghost_sessions = state
+        .storage
+        .prune_ghost_sessions(now - GHOST_SESSION_KEEP_DAYS * 86_400)
+        .map_err(|e| e.to_string())?;
+    let deliveries = state
+        .storage
+        .prune_webhook_deliveries(now - DELIVERY_LOG_KEEP_DAYS * 86_400)
+        .map_err(|e| e.to_string())?;
+    let sandbox_files = match &state.sandbox_manager {
+        Some(sandbox) => sandbox
+            .cleanup_old_files()
+            .await
+            .map_err(|e| format!("sandbox cleanup failed: {e}"))?,
+        None => 0,
+    };
+    state.journal.record(
+        "retention.prune",
+        "scheduler",
+        json!({
+            "login_sessions": sessions,
+            "ghost_sessions": ghost_sessions,
+            "webhook_deliveries": deliveries,
+            "sandbox_files": sandbox_files,
+        }),
+    );
+    Ok(format!(
+        "removed {sessions} expired login sessions, {ghost_sessions} old ghost sessions, \
+         {deliveries} old webhook deliveries and {sandbox_files} sandbox files"
+    ))
+}
+
+fn headline(trends: &EmotionTrends) -> String {
+    let top = trends
+        .current
+        .distribution
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1));
+    let mut line = match top {
+        Some((emotion, share)) => format!(
+            "This week: mostly {} ({:.0}% of {} samples)",
+            emotion.to_ascii_lowercase(),
+            share * 100.0,
+            trends.current.samples
+        ),
+        None => format!("This week: {} samples", trends.current.samples),
+    };
+    if let Some(valence) = trends.change.average_valence {
+        line.push_str(&format!(", mood {valence:+.2} vs last week"));
+    }
+    line.push('.');
+    line
+}
+
+fn weekly_report(state: &AppState) -> Result<String, String> {
+    let now = Utc::now().timestamp();
+    let query = TrendQuery {
+        bucket: TrendBucket::Day,
+        from_unix: Some(now - WEEK_SECS),
+        to_unix: Some(now),
+        profile: None,
+    };
+    let trends = emotion_trends::compute(&state.vaults, &query, now).map_err(|e| e.to_string())?;
+    if trends.current.samples == 0 {
+        return Ok("no emotion samples this week; no report".to_string());
+    }
+
+    let key = format!("{WEEKLY_REPORT_PREFIX}{}", Local::now().date_naive());
+    let raw = serde_json::to_string(&trends).map_err(|e| e.to_string())?;
+    state
+        .vaults
+        .store_soul(&key, &raw)
+        .map_err(|e| format!("cannot store {key}: {e}"))?;
+
+    let content = headline(&trends);
+    let _ = state.proactive_tx.send(ProactiveMessage {
+        content: content.clone(),
+        reason: "weekly_report".to_string(),
+        timestamp: now,
+    });
+    Ok(format!("stored {key}: {content}"))
+}
+
+async fn model_updates() -> Result<String, String> {
+    let manager = MultiModalRecorder::from_env().model_manager();
+    let status = manager.status().map_err(|e| e.to_string())?;
+    let mut updated = Vec::new();
+    let mut failed = Vec::new();
+    for model in status {
+        let Some(spec) = model.spec else {
+            continue;
+        };
+        let pinned = model.installed.as_ref().is_some_and(|m| m.pinned);
+        if !model.update_available || pinned {
+            continue;
+        }
+        match manager.download(&spec).await {
+            Ok(_) => updated.push(format!("{} {}", spec.id, spec.version)),
+            Err(e) => failed.push(format!("{}: {e}", spec.id)),
+        }
+    }
+    match (updated.is_empty(), failed.is_empty()) {
+        (_, false) => Err(format!(
+            "updated [{}]; failed [{}]",
+            updated.join(", "),
+            failed.join("; ")
+        )),
+        (true, true) => Ok("all models up to date".to_string()),
+        (false, true) => Ok(format!("updated {}", updated.join(", "))),
+    }
+}
@@ -0,0 +1,623 @@
+//! In-process job scheduler: recurring jobs on cron schedules and one-shot jobs at a set time.
+//!
+//! Built-in jobs (schedules under `[scheduler]` in the settings file; `"off"` disables one):
+//!
+//! - `retention_prune` (hourly): drop expired login sessions, Ghost drift sessions older than 30
+//!   days, webhook delivery logs older than 14 days and malware sandbox sessions older than
+//!   `retention.sandbox_cleanup_days`.
+//! - `weekly_report` (Sundays 20:00): store a week-over-week emotion report in the Soul Vault and
+//!   announce it as a proactive message.
+//! - `model_updates` (daily 03:30): download newer manifest versions of installed, unpinned
+//!   models.
+//! - `backup` (off unless `[backup] schedule` is set): write an encrypted snapshot and prune old
+//!   ones, see [`crate::scheduled_backup`].
+//! - `sync` (every 15 minutes when `[sync] peers` is set): a round with each of the user's other
+//!   devices, see [`crate::device_sync`].
+//!
+//! One-shot jobs are added at runtime, e.g. delayed ghost replies
+//! (`POST /api/ghost/simulate?delay_secs=`), and so are recurring recordings
+//! (`POST /api/recorder/schedules`, see [`crate::recorder_api`]). What each job does lives in
+//! [`crate::scheduled_jobs`].
+//!
+//! Jobs and their last run are saved to `data/scheduler.json` under the data directory, so
+//! pending one-shot jobs survive a restart and a run that fell due while the server was down
+//! happens once at startup. The file is sealed when the data directory is encrypted (see
+//! [`pagi_crypto`]). Cron expressions have a leading seconds field and use local time.
+//!
+//! Routes (under `/api`):
+//! - `GET /scheduler/jobs`, `GET /scheduler/jobs/{id}`: definition, next run, last result
+//! - `POST /scheduler/jobs/{id}/run`: run now (`409` while it is already running)
+//! - `DELETE /scheduler/jobs/{id}`: remove a job added at runtime (pending or finished)
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use chrono::{Local, TimeZone, Utc};
+use pagi_crypto::Sealer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::ghost_engine::SimulateRequest;
+use crate::recorder_api::CaptureRequest;
+use crate::{ApiError, AppState};
+
+pub const DEFAULT_RETENTION_PRUNE: &str = "0 15 * * * *";
+pub const DEFAULT_WEEKLY_REPORT: &str = "0 0 20 * * Sun";
+pub const DEFAULT_MODEL_UPDATES: &str = "0 30 3 * * *";
+
+/// Longest the loop sleeps without checking for due jobs.
+const MAX_IDLE: Duration = Duration::from_secs(60);
+/// Finished one-shot jobs are listed for this long, then dropped.
+const FINISHED_ONE_SHOT_KEEP_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// A `[scheduler]` value: a cron expression, or `off`.
+pub fn parse_schedule(s: &str) -> Result<Option<String>, String> {
+    let s = s.trim();
+    if matches!(
+        s.to_ascii_lowercase().as_str(),
+        "off" | "false" | "none" | ""
+    ) {
+        return Ok(None);
+    }
+    cron::Schedule::from_str(s)
+        .map(|_| Some(s.to_string()))
+        .map_err(|e| format!("invalid cron expression {s:?}: {e}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trigger {
+    Cron { expr: String },
+    Once { at_unix: i64 },
+}
+
+impl Trigger {
+    /// The first cron time strictly after `after_unix`; one-shot triggers have no next time.
+    fn next_after(&self, after_unix: i64) -> Option<i64> {
+        match self {
+            Self::Cron { expr } => {
+                let schedule = cron::Schedule::from_str(expr).ok()?;
+                let after = Local.timestamp_opt(after_unix, 0).single()?;
+                schedule.after(&after).next().map(|t| t.timestamp())
+            }
+            Self::Once { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "task", rename_all = "snake_case")]
+pub enum Task {
+    RetentionPrune,
+    WeeklyReport,
+    ModelUpdates,
+    Backup,
+    Sync,
+    GhostReply { request: SimulateRequest },
+    Recording { capture: CaptureRequest },
+}
+
+impl Task {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RetentionPrune => "retention_prune",
+            Self::WeeklyReport => "weekly_report",
+            Self::ModelUpdates => "model_updates",
+            Self::Backup => "backup",
+            Self::Sync => "sync",
+            Self::GhostReply { .. } => "ghost_reply",
+            Self::Recording { .. } => "recording",
+        }
+    }
+
+    /// Installed from the settings at startup rather than added at runtime.
+    fn built_in(&self) -> bool {
+        matches!(
+            self,
+            Self::RetentionPrune
+                | Self::WeeklyReport
+                | Self::ModelUpdates
+                | Self::Backup
+                | Self::Sync
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+    pub started_unix: i64,
+    pub duration_ms: u64,
+    pub ok: bool,
+    /// What the job did, or why it failed.
+    pub message: String,
+    /// Started from the API rather than by its schedule.
+    #[serde(default)]
+    pub manual: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    #[serde(flatten)]
+    pub task: Task,
+    pub trigger: Trigger,
+    /// `None` once a one-shot job has run.
+    pub next_run_unix: Option<i64>,
+    #[serde(default)]
+    pub runs: u64,
+    #[serde(default)]
+    pub last_run: Option<JobRun>,
+    #[serde(default, skip_deserializing)]
+    pub running: bool,
+}
+
+impl Job {
+    fn built_in(&self) -> bool {
+        self.task.built_in()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("no job {0}")]
+    NotFound(String),
+    #[error("job {0} is already running")]
+    Running(String),
+    #[error("job {0} is built in; set its schedule to \"off\" under [scheduler] to disable it")]
+    BuiltIn(String),
+}
+
+impl From<SchedulerError> for ApiError {
+    fn from(e: SchedulerError) -> Self {
+        match e {
+            SchedulerError::NotFound(_) => ApiError::not_found(e.to_string()),
+            SchedulerError::Running(_) => ApiError::new(StatusCode::CONFLICT, e.to_string()),
+            SchedulerError::BuiltIn(_) => ApiError::bad_request(e.to_string()),
+        }
+    }
+}
+
+pub struct Scheduler {
+    path: PathBuf,
+    sealer: Sealer,
+    jobs: Mutex<BTreeMap<String, Job>>,
+    /// Jobs asked to run now through the API.
+    manual: Mutex<BTreeSet<String>>,
+    wake: Notify,
+}
+
+impl Scheduler {
+    /// Load the saved jobs from `path` (sealed with `sealer`) and install the built-in ones:
+    /// `(task, cron)` pairs, `None` to remove a built-in job.
+    pub fn open(path: PathBuf, sealer: Sealer, built_in: Vec<(Task, Option<String>)>) -> Self {
+        let now = Utc::now().timestamp();
+        let saved: Vec<Job> = match std::fs::read(&path) {
+            Ok(raw) => sealer
+                .open(&raw)
+                .map_err(|e| e.to_string())
+                .and_then(|raw| serde_json::from_slice(&raw).map_err(|e| e.to_string()))
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Ignoring unreadable {}: {e}", path.display());
+                    Vec::new()
+                }),
+            Err(_) => Vec::new(),
+        };
+        let mut jobs: BTreeMap<String, Job> = saved
+            .into_iter()
+            .filter(|job| {
+                job.built_in()
+                    || job.next_run_unix.is_some()
+                    || job
+                        .last_run
+                        .as_ref()
+                        .is_some_and(|run| now - run.started_unix < FINISHED_ONE_SHOT_KEEP_SECS)
+            })
+            .map(|job| (job.id.clone(), job))
+            .collect();
+
+        for (task, expr) in built_in {
+            let id = task.name().to_string();
+            let Some(expr) = expr else {
+                jobs.remove(&id);
+                continue;
+            };
+            let trigger = Trigger::Cron { expr };
+            match jobs.get_mut(&id) {
+                // Same schedule: keep the saved next run so a missed one still happens.
+                Some(job) if job.trigger == trigger => job.task = task,
+                _ => {
+                    let next_run_unix = trigger.next_after(now);
+                    jobs.insert(
+                        id.clone(),
+                        Job {
+                            id,
+                            task,
+                            trigger,
+                            next_run_unix,
+                            runs: 0,
+                            last_run: None,
+                            running: false,
+                        },
+                    );
+                }
+            }
+        }
+
+        let scheduler = Self {
+            path,
+            sealer,
+            jobs: Mutex::new(jobs),
+            manual: Mutex::new(BTreeSet::new()),
+            wake: Notify::new(),
+        };
+        scheduler.save();
+        scheduler
+    }
+
+    fn jobs(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save(&self) {
+        let jobs: Vec<Job> = self.jobs().values().cloned().collect();
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = self.path.with_extension("json.tmp");
+            let json = serde_json::to_vec_pretty(&jobs)?;
+            std::fs::write(
+                &tmp,
+                self.sealer.seal(&json).map_err(std::io::Error::other)?,
+            )?;
+            std::fs::rename(tmp, &self.path)
+        };
+        if let Err(e) = write() {
+            tracing::warn!("Cannot save {}: {e}", self.path.display());
+        }
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.jobs().values().cloned().collect();
+        jobs.sort_by_key(|job| (job.next_run_unix.is_none(), job.next_run_unix));
+        jobs
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs().get(id).cloned()
+    }
+
+    /// Schedule `task` to run once at `at_unix`.
+    pub fn add_once(&self, task: Task, at_unix: i64) -> Job {
+        self.add(task, Trigger::Once { at_unix }, Some(at_unix))
+    }
+
+    /// Schedule `task` to run on a cron schedule (see [`parse_schedule`]) until it is removed.
+    pub fn add_cron(&self, task: Task, expr: &str) -> Result<Job, String> {
+        let expr = parse_schedule(expr)?.ok_or("a recurring job needs a cron expression")?;
+        let trigger = Trigger::Cron { expr };
+        let next_run_unix = trigger.next_after(Utc::now().timestamp());
+        Ok(self.add(task, trigger, next_run_unix))
+    }
+
+    fn add(&self, task: Task, trigger: Trigger, next_run_unix: Option<i64>) -> Job {
+        let id = format!(
+            "{}-{}",
+            task.name(),
+            &uuid::Uuid::new_v4().simple().to_string()[..12]
+        );
+        let job = Job {
+            id: id.clone(),
+            task,
+            trigger,
+            next_run_unix,
+            runs: 0,
+            last_run: None,
+            running: false,
+        };
+        self.jobs().insert(id, job.clone());
+        self.save();
+        self.wake.notify_one();
+        job
+    }
+
+    /// Remove a job added at runtime.
+    pub fn remove(&self, id: &str) -> Result<Job, SchedulerError> {
+        let removed = {
+            let mut jobs = self.jobs();
+            match jobs.get(id) {
+                None => return Err(SchedulerError::NotFound(id.to_string())),
+                Some(job) if job.built_in() => return Err(SchedulerError::BuiltIn(id.to_string())),
+                Some(job) if job.running => return Err(SchedulerError::Running(id.to_string())),
+                Some(_) => jobs.remove(id),
+            }
+        };
+        self.save();
+        removed.ok_or_else(|| SchedulerError::NotFound(id.to_string()))
+    }
+
+    /// Run a job as soon as the scheduler loop wakes, independent of its schedule.
+    pub fn run_now(&self, id: &str) -> Result<Job, SchedulerError> {
+        let job = self
+            .get(id)
+            .ok_or_else(|| SchedulerError::NotFound(id.to_string()))?;
+        if job.running {
+            return Err(SchedulerError::Running(id.to_string()));
+        }
+        self.manual
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.to_string());
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    /// Mark every due (or manually requested) job as running and hand them out.
+    fn take_due(&self, now: i64) -> Vec<(String, Task, bool)> {
+        let manual = std::mem::take(&mut *self.manual.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut jobs = self.jobs();
+        let mut due = Vec::new();
+        for job in jobs.values_mut() {
+            let is_manual = manual.contains(&job.id);
+            let scheduled = job.next_run_unix.is_some_and(|at| at <= now);
+            if job.running || !(scheduled || is_manual) {
+                continue;
+            }
+            job.running = true;
+            due.push((job.id.clone(), job.task.clone(), is_manual && !scheduled));
+        }
+        due
+    }
+
+    fn finish(&self, id: &str, run: JobRun, scheduled: bool) {
+        let now = Utc::now().timestamp();
+        if let Some(job) = self.jobs().get_mut(id) {
+            job.running = false;
+            job.runs += 1;
+            // A manual run leaves the schedule alone.
+            if scheduled {
+                job.next_run_unix = job.trigger.next_after(now);
+            }
+            job.last_run = Some(run);
+        }
+        self.save();
+    }
+
+    fn next_due(&self) -> Option<i64> {
+        self.jobs()
+            .values()
+            .filter(|job| !job.running)
+            .filter_map(|job| job.next_run_unix)
+            .min()
+    }
+
+    /// Run jobs as they fall due until the task is aborted.
+    pub fn spawn(self: &Arc<Self>, state: AppState) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let now = Utc::now().timestamp();
+                for (id, task, manual) in scheduler.take_due(now) {
+                    let scheduler = scheduler.clone();
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        tracing::info!(target: "scheduler", job = %id, manual, "job started");
+                        let started = Instant::now();
+                        let result = crate::scheduled_jobs::run(&state, &task).await;
+                        let duration_ms = started.elapsed().as_millis() as u64;
+                        match &result {
+                            Ok(message) => {
+                                tracing::info!(target: "scheduler", job = %id, duration_ms, "{message}")
+                            }
+                            Err(error) => {
+                                tracing::warn!(target: "scheduler", job = %id, duration_ms, "job failed: {error}")
+                            }
+                        }
+                        let (ok, message) = match result {
+                            Ok(message) => (true, message),
+                            Err(error) => (false, error),
+                        };
+                        let run = JobRun {
+                            started_unix: now,
+                            duration_ms,
+                            ok,
+                            message,
+                            manual,
+                        };
+                        scheduler.finish(&id, run, !manual);
+                        scheduler.wake.notify_one();
+                    });
+                }
+
+                let wait = scheduler
+                    .next_due()
+                    .map_or(MAX_IDLE, |at| Duration::from_secs((at - now).max(0) as u64))
+                    .clamp(Duration::from_secs(1), MAX_IDLE);
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = scheduler.wake.notified() => {}
+                }
+            }
+        })
+    }
+}
+
+async fn get_jobs(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "jobs": state.scheduler.list() }))
+}
+
+async fn get_job(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let job = state
+        .scheduler
+        .get(&id)
+        .ok_or_else(|| SchedulerError::NotFound(id.to_string()))?;
+    Ok(HttpResponse::Ok().json(job))
+}
+
+async fn post_run(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let job = state.scheduler.run_now(&id)?;
+    Ok(HttpResponse::Accepted().json(json!({ "status": "queued", "job": job })))
+}
+
+async fn delete_job(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let job = state.scheduler.remove(&id)?;
+    Ok(HttpResponse::Ok().json(json!({ "status": "removed", "job": job })))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/scheduler")
+            .route("/jobs", web::get().to(get_jobs))
+            .route("/jobs/{id}", web::get().to(get_job))
+            .route("/jobs/{id}", web::delete().to(delete_job))
+            .route("/jobs/{id}/run", web::post().to(post_run)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "phoenix-scheduler-{}/scheduler.json",
+            uuid::Uuid::new_v4()
+        ))
+    }
+
+    #[test]
+    fn due_jobs_run_once_and_persist() {
+        let path = temp_path();
+        let scheduler = Scheduler::open(
+            path.clone(),
+            Sealer::default(),
+            vec![
+                (
+                    Task::RetentionPrune,
+                    Some(DEFAULT_RETENTION_PRUNE.to_string()),
+                ),
+                (Task::ModelUpdates, None),
+            ],
+        );
+        let now = Utc::now().timestamp();
+        let prune = scheduler.get("retention_prune").unwrap();
+        assert!(prune.next_run_unix.unwrap() > now);
+        assert!(scheduler.get("model_updates").is_none());
+
+        let once = scheduler.add_once(Task::WeeklyReport, now - 1);
+        let due = scheduler.take_due(now);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, once.id);
+        assert!(
+            scheduler.take_due(now).is_empty(),
+            "running jobs are not handed out twice"
+        );
+
+        scheduler.run_now("retention_prune").unwrap();
+        let due = scheduler.take_due(now);
+        assert_eq!((due[0].0.as_str(), due[0].2), ("retention_prune", true));
+        assert!(matches!(
+            scheduler.run_now("retention_prune"),
+            Err(SchedulerError::Running(_))
+        ));
+
+        let run = |manual| JobRun {
+            started_unix: now,
+            duration_ms: 1,
+            ok: true,
+            message: "done".to_string(),
+            manual,
+        };
+        scheduler.finish(&once.id, run(false), true);
+        scheduler.finish("retention_prune", run(true), false);
+        assert_eq!(scheduler.get(&once.id).unwrap().next_run_unix, None);
+        assert_eq!(
+            scheduler.get("retention_prune").unwrap().next_run_unix,
+            prune.next_run_unix,
+            "a manual run keeps the schedule"
+        );
+        assert!(matches!(
+            scheduler.remove("retention_prune"),
+            Err(SchedulerError::BuiltIn(_))
+        ));
+
+        let pending = scheduler.add_once(Task::WeeklyReport, now + 3600);
+        let reopened = Scheduler::open(
+            path.clone(),
+            Sealer::default(),
+            vec![(Task::RetentionPrune, Some("0 0 * * * *".to_string()))],
+        );
+        assert_eq!(
+            reopened.get(&pending.id).unwrap().next_run_unix,
+            Some(now + 3600)
+        );
+        assert_eq!(reopened.get(&once.id).unwrap().runs, 1);
+        assert_ne!(
+            reopened.get("retention_prune").unwrap().trigger,
+            prune.trigger
+        );
+        let recurring = scheduler
+            .add_cron(
+                Task::Recording {
+                    capture: CaptureRequest::default(),
+                },
+                "0 0 9 * * Mon-Fri",
+            )
+            .unwrap();
+        assert!(recurring.next_run_unix.unwrap() > now);
+        assert!(scheduler.add_cron(Task::WeeklyReport, "off").is_err());
+        assert!(Scheduler::open(path.clone(), Sealer::default(), Vec::new())
+            .get(&recurring.id)
+            .is_some());
+        scheduler.remove(&recurring.id).unwrap();
+        assert!(parse_schedule("off").unwrap().is_none());
+        assert!(parse_schedule("every tuesday").is_err());
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn jobs_are_saved_sealed() {
+        let path = temp_path();
+        let sealer = Sealer::new(pagi_crypto::DataKey::generate().unwrap());
+        let script = "I need you to listen to me";
+        let scheduler = Scheduler::open(path.clone(), sealer.clone(), Vec::new());
+        let job = scheduler.add_once(
+            Task::GhostReply {
+                request: SimulateRequest {
+                    script: script.to_string(),
+                    persona_type: "avoidant".to_string(),
+                    personas: Vec::new(),
+                    intensity_level: 50,
+                    system_load: None,
+                    language: None,
+                },
+            },
+            Utc::now().timestamp() + 3600,
+        );
+        let raw = std::fs::read(&path).unwrap();
+        assert!(pagi_crypto::is_sealed(&raw));
+        assert!(!String::from_utf8_lossy(&raw).contains(script));
+        assert!(Scheduler::open(path.clone(), sealer, Vec::new())
+            .get(&job.id)
+            .is_some());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
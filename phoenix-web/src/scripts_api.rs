@@ -0,0 +1,230 @@
+//! Automation scripts (see [`pagi_scripts`]), loaded at startup from `[scripts] dir`.
+//!
+//! Hooks fire when a recording ends (a meeting recording stopped through `/api/audio`, or a
+//! capture through `/api/recorder`), on each `alerts` emotion alert and on each finished Ghost
+//! simulation. The hooks run off the async runtime and the actions they queue are carried out
+//! afterwards: a scheduled recording becomes a one-shot [`crate::scheduler`] job (audited as the
+//! script's), a notification a proactive message.
+//!
+//! - `GET /api/scripts`: whether scripts are enabled, the loaded scripts with the hooks each
+//!   defines, and the files that failed to compile.
+//! - `POST /api/scripts/reload`: compile the directory again, e.g. after editing a script.
+//! - `POST /api/scripts/test` `{"hook": "emotion_alert", "payload": {...}}`: run a hook on a
+//!   made-up event and return the actions it would take, without taking them.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use audio_intelligence::MeetingTranscript;
+use chrono::Utc;
+use pagi_scripts::{Action, Hook, HookRun, Queued, ScriptHost};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::audit::{self, Actor};
+use crate::live_events::LiveEvent;
+use crate::proactive::ProactiveMessage;
+use crate::recorder_api::CaptureRequest;
+use crate::scheduler::Task;
+use crate::validation::Validator;
+use crate::{api_json_config, plugins_api, ApiError, AppState};
+
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Furthest ahead a script can schedule a recording.
+const MAX_DELAY_SECS: u64 = 7 * 24 * 60 * 60;
+const MAX_NOTIFY_CHARS: usize = 2_000;
+
+/// Run `hook` in the background and carry out what the scripts ask for. A no-op when no script
+/// defines it; `payload` is only built then.
+fn fire(state: &AppState, hook: Hook, payload: impl FnOnce() -> Value) {
+    if !state.scripts.handles(hook) {
+        return;
+    }
+    let payload = payload();
+    let state = state.clone();
+    tokio::spawn(async move {
+        let run = run(&state.scripts, hook, payload).await;
+        for queued in run.actions {
+            apply(&state, hook, queued);
+        }
+    });
+}
+
+async fn run(host: &Arc<ScriptHost>, hook: Hook, payload: Value) -> HookRun {
+    let host = host.clone();
+    tokio::task::spawn_blocking(move || host.run(hook, &payload))
+        .await
+        .unwrap_or_default()
+}
+
+fn apply(state: &AppState, hook: Hook, Queued { script, action }: Queued) {
+    let mut v = Validator::default();
+    match action {
+        Action::ScheduleRecording {
+            in_secs,
+            duration_secs,
+            purpose,
+        } => {
+            let capture = CaptureRequest {
+                duration_secs,
+                purpose,
+                ..CaptureRequest::default()
+            };
+            capture.validate(&mut v);
+            if in_secs > MAX_DELAY_SECS {
+                v.reject("in_secs", format!("must be at most {MAX_DELAY_SECS}"));
+            }
+            if let Err(errors) = v.finish() {
+                return rejected(&script, "schedule_recording", &errors);
+            }
+            let at_unix = Utc::now().timestamp() + in_secs as i64;
+            let job = state.scheduler.add_once(
+                Task::Recording {
+                    capture: capture.clone(),
+                },
+                at_unix,
+            );
+            info!(target: "scripts", %script, ?hook, job = %job.id, at_unix, "recording scheduled");
+            audit::record(
+                state,
+                &Actor::Script(script),
+                audit::Action::RecordingSchedule,
+                json!({ "job": job.id, "at_unix": at_unix, "capture": capture, "hook": hook }),
+            );
+        }
+        Action::Notify { message } => {
+            v.text("message", &message, MAX_NOTIFY_CHARS);
+            if let Err(errors) = v.finish() {
+                return rejected(&script, "notify", &errors);
+            }
+            info!(target: "scripts", %script, ?hook, "notification sent");
+            // No subscribers is fine.
+            let _ = state.proactive_tx.send(ProactiveMessage {
+                content: message,
+                reason: "script".to_string(),
+                timestamp: Utc::now().timestamp(),
+            });
+        }
+    }
+}
+
+fn rejected(script: &str, action: &str, errors: &[crate::FieldError]) {
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect();
+    warn!(target: "scripts", %script, "{action} refused: {}", errors.join("; "));
+}
+
+/// `on_recording_finished` for a stopped meeting recording.
+pub(crate) fn meeting_finished(state: &AppState, transcript: &MeetingTranscript) {
+    fire(state, Hook::RecordingFinished, || {
+        json!({
+            "source": "meeting",
+            "session_id": transcript.session_id,
+            "participants": transcript.participants,
+            "keywords": transcript.keywords,
+            "summary": transcript.summary,
+            "transcript": plugins_api::transcript_text(transcript),
+        })
+    });
+}
+
+/// `on_recording_finished` for a capture written to `path`.
+pub(crate) fn capture_finished(state: &AppState, capture: &CaptureRequest, path: &Path) {
+    fire(state, Hook::RecordingFinished, || {
+        json!({
+            "source": "capture",
+            "path": path,
+            "purpose": capture.purpose,
+            "duration_secs": capture.duration_secs,
+            "audio": capture.audio,
+            "video": capture.video,
+        })
+    });
+}
+
+/// Fire `on_emotion_alert` and `on_ghost_response` from the live feed.
+pub(crate) fn spawn_hooks(state: &AppState) -> JoinHandle<()> {
+    let state = state.clone();
+    let mut live = state.live.subscribe();
+    let mut closing = state.live.closing();
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = live.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!(target: "scripts", missed, "hooks fell behind; events dropped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+                _ = closing.wait_for(|closing| *closing) => return,
+            };
+            match event {
+                LiveEvent::EmotionAlert { alert } => fire(&state, Hook::EmotionAlert, || {
+                    serde_json::to_value(alert).unwrap_or_default()
+                }),
+                LiveEvent::GhostResult { result } => fire(&state, Hook::GhostResponse, || {
+                    serde_json::to_value(result).unwrap_or_default()
+                }),
+                _ => {}
+            }
+        }
+    })
+}
+
+fn listing(host: &ScriptHost) -> Value {
+    json!({
+        "enabled": host.enabled(),
+        "scripts": host.scripts(),
+        "failures": host.failures(),
+    })
+}
+
+/// GET /api/scripts
+async fn get_scripts(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(listing(&state.scripts))
+}
+
+/// POST /api/scripts/reload
+async fn post_reload(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let host = state.scripts.clone();
+    tokio::task::spawn_blocking(move || host.reload())
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok(HttpResponse::Ok().json(listing(&state.scripts)))
+}
+
+#[derive(Debug, Deserialize)]
+struct TestRequest {
+    hook: Hook,
+    #[serde(default)]
+    payload: Value,
+}
+
+/// POST /api/scripts/test
+async fn post_test(
+    state: web::Data<AppState>,
+    body: web::Json<TestRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let TestRequest { hook, payload } = body.into_inner();
+    Ok(HttpResponse::Ok().json(run(&state.scripts, hook, payload).await))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/scripts")
+            .app_data(api_json_config(MAX_BODY_BYTES))
+            .route("", web::get().to(get_scripts))
+            .route("/reload", web::post().to(post_reload))
+            .route("/test", web::post().to(post_test)),
+    );
+}
@@ -0,0 +1,341 @@
+//! Login sessions for the web UI.
+//!
+//! Setting `PHOENIX_UI_PASSPHRASE` locks `/api` and `/ws`: a request then needs a session or an
+//! API key. `POST /api/session/login` ([`LOGIN_PATH`], also under `/api/v1`) with the passphrase
+//! opens a session; the token comes back in the `phoenix_session` cookie (HttpOnly,
+//! SameSite=Strict) for the browser UI and in the body for clients that send
+//! `Authorization: Bearer`. Sessions act with the `admin` scope and
+//! expire after `PHOENIX_SESSION_TTL_HOURS` (default 168) without use.
+//!
+//! Sessions are kept in the database (only a hash of each token), so they survive a restart.
+//! `GET /api/v1/sessions` lists them and `DELETE /api/v1/sessions/{id}` revokes one.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use phoenix_storage::login_sessions::SessionRow;
+use phoenix_storage::{Storage, StorageError};
+
+use crate::api_keys::{constant_time_eq, hash_key};
+use crate::audit::{self, Action, Actor};
+use crate::{ApiError, AppState};
+
+pub const COOKIE_NAME: &str = "phoenix_session";
+/// The one `/api` route reachable without credentials, in every API version.
+pub const LOGIN_PATH: &str = "/api/session/login";
+const TOKEN_PREFIX: &str = "phs_";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub created_unix: i64,
+    pub last_seen_unix: i64,
+    pub user_agent: Option<String>,
+    pub peer: Option<String>,
+}
+
+impl From<SessionRow> for Session {
+    fn from(row: SessionRow) -> Self {
+        Self {
+            id: row.id,
+            created_unix: row.created_unix,
+            last_seen_unix: row.last_seen_unix,
+            user_agent: row.user_agent,
+            peer: row.peer,
+        }
+    }
+}
+
+pub struct SessionStore {
+    passphrase_hash: String,
+    ttl_secs: AtomicU64,
+    storage: Storage,
+}
+
+impl SessionStore {
+    pub fn new(passphrase: &str, ttl: Duration, storage: Storage) -> Self {
+        Self {
+            passphrase_hash: hash_key(passphrase),
+            ttl_secs: AtomicU64::new(ttl.as_secs()),
+            storage,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs.load(Ordering::Relaxed))
+    }
+
+    /// Change the idle timeout; it applies to existing sessions too.
+    pub fn set_ttl(&self, ttl: Duration) {
+        self.ttl_secs.store(ttl.as_secs(), Ordering::Relaxed);
+    }
+
+    /// A new session and its token, or `None` if `passphrase` is wrong.
+    pub fn login(
+        &self,
+        passphrase: &str,
+        user_agent: Option<String>,
+        peer: Option<String>,
+    ) -> Result<Option<(Session, String)>, StorageError> {
+        if !constant_time_eq(
+            hash_key(passphrase).as_bytes(),
+            self.passphrase_hash.as_bytes(),
+        ) {
+            return Ok(None);
+        }
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        let token = format!("{TOKEN_PREFIX}{id}_{}", uuid::Uuid::new_v4().simple());
+        let now = chrono::Utc::now().timestamp();
+        let row = SessionRow {
+            id,
+            token_hash: hash_key(&token),
+            created_unix: now,
+            last_seen_unix: now,
+            user_agent,
+            peer,
+        };
+        self.storage.insert_session(&row)?;
+        Ok(Some((row.into(), token)))
+    }
+
+    /// Oldest last-seen time that has not expired yet.
+    fn cutoff(&self) -> i64 {
+        chrono::Utc::now().timestamp() - self.ttl().as_secs() as i64
+    }
+
+    /// The live session for `token`, refreshing its last-seen time. A database error counts as
+    /// no session.
+    pub fn verify(&self, token: &str) -> Option<Session> {
+        let id = token.trim().strip_prefix(TOKEN_PREFIX)?.split('_').next()?;
+        let hash = hash_key(token.trim());
+        let lookup = || -> Result<Option<Session>, StorageError> {
+            let Some(row) = self.storage.session(id)? else {
+                return Ok(None);
+            };
+            if !constant_time_eq(row.token_hash.as_bytes(), hash.as_bytes()) {
+                return Ok(None);
+            }
+            if row.last_seen_unix < self.cutoff() {
+                self.storage.delete_session(id)?;
+                return Ok(None);
+            }
+            let now = chrono::Utc::now().timestamp();
+            self.storage.touch_session(id, now)?;
+            Ok(Some(Session {
+                last_seen_unix: now,
+                ..row.into()
+            }))
+        };
+        lookup().unwrap_or_else(|e| {
+            tracing::warn!("Session lookup failed: {e}");
+            None
+        })
+    }
+
+    /// Unexpired sessions, oldest first.
+    pub fn list(&self) -> Result<Vec<Session>, StorageError> {
+        self.prune()?;
+        Ok(self
+            .storage
+            .sessions()?
+            .into_iter()
+            .map(Session::from)
+            .collect())
+    }
+
+    /// Drop expired sessions; returns how many were removed.
+    pub fn prune(&self) -> Result<usize, StorageError> {
+        self.storage.delete_sessions_idle_since(self.cutoff())
+    }
+
+    /// Remove the session with `id`; `false` if there was none.
+    pub fn revoke(&self, id: &str) -> Result<bool, StorageError> {
+        self.storage.delete_session(id.trim())
+    }
+}
+
+/// A session token from the cookie or an `Authorization: Bearer phs_...` header.
+pub fn presented_token(req: &HttpRequest) -> Option<String> {
+    if let Some(cookie) = req.cookie(COOKIE_NAME) {
+        return Some(cookie.value().to_string());
+    }
+    req.headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .filter(|t| t.starts_with(TOKEN_PREFIX))
+        .map(str::to_string)
+}
+
+fn store(state: &AppState) -> Result<&SessionStore, ApiError> {
+    state
+        .sessions
+        .as_deref()
+        .ok_or_else(|| ApiError::not_found("sessions are off; set PHOENIX_UI_PASSPHRASE"))
+}
+
+fn session_cookie(req: &HttpRequest, value: String, max_age: Duration) -> Cookie<'static> {
+    Cookie::build(COOKIE_NAME, value)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(req.connection_info().scheme() == "https")
+        .max_age(actix_web::cookie::time::Duration::seconds(
+            max_age.as_secs() as i64,
+        ))
+        .finish()
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginBody {
+    passphrase: String,
+}
+
+async fn post_login(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<LoginBody>,
+) -> Result<HttpResponse, ApiError> {
+    let store = store(&state)?;
+    let user_agent = req
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let peer = req.peer_addr().map(|a| a.ip().to_string());
+    let Some((session, token)) = store.login(&body.passphrase, user_agent, peer)? else {
+        return Err(ApiError::unauthorized("wrong passphrase"));
+    };
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(&req, token.clone(), store.ttl()))
+        .json(json!({ "token": token, "session": session })))
+}
+
+async fn post_logout(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let store = store(&state)?;
+    if let Some(session) = req.extensions().get::<Session>() {
+        store.revoke(&session.id)?;
+    }
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(&req, String::new(), Duration::ZERO))
+        .json(json!({ "status": "ok" })))
+}
+
+async fn get_current(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    store(&state)?;
+    match req.extensions().get::<Session>() {
+        Some(session) => Ok(HttpResponse::Ok().json(session)),
+        None => Err(ApiError::not_found("this request is not using a session")),
+    }
+}
+
+async fn get_sessions(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, ApiError> {
+    let current = req.extensions().get::<Session>().map(|s| s.id.clone());
+    let sessions: Vec<_> = store(&state)?
+        .list()?
+        .into_iter()
+        .map(|s| {
+            let is_current = current.as_deref() == Some(s.id.as_str());
+            json!({ "current": is_current, "session": s })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({ "sessions": sessions })))
+}
+
+async fn delete_session(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    if store(&state)?.revoke(&id)? {
+        let actor = Actor::of(&req);
+        audit::record(
+            &state,
+            &actor,
+            Action::SessionRevoke,
+            json!({ "id": id.as_str() }),
+        );
+        Ok(HttpResponse::Ok().json(json!({ "status": "ok" })))
+    } else {
+        Err(ApiError::not_found(format!("no session {id}")))
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/session")
+            .route("", web::get().to(get_current))
+            .route("/login", web::post().to(post_login))
+            .route("/logout", web::post().to(post_logout)),
+    )
+    .service(
+        web::scope("/sessions")
+            .route("", web::get().to(get_sessions))
+            .route("/{id}", web::delete().to(delete_session)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::authorize;
+
+    #[test]
+    fn login_verify_and_revoke() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = dir.path().join("phoenix.db");
+        let ttl = Duration::from_secs(3600);
+        let store = SessionStore::new("correct horse", ttl, Storage::open_sqlite(&db, 1).unwrap());
+        assert!(store.login("wrong", None, None).unwrap().is_none());
+
+        let (session, token) = store.login("correct horse", None, None).unwrap().unwrap();
+        assert_eq!(store.verify(&token).unwrap().id, session.id);
+        assert!(store.verify(&format!("{token}x")).is_none());
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        // Still signed in after a restart.
+        let reopened =
+            SessionStore::new("correct horse", ttl, Storage::open_sqlite(&db, 1).unwrap());
+        assert_eq!(reopened.verify(&token).unwrap().id, session.id);
+
+        assert!(store.revoke(&session.id).unwrap());
+        assert!(store.verify(&token).is_none());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_login_is_open_when_a_passphrase_is_set() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = Storage::open_sqlite(&dir.path().join("phoenix.db"), 1).unwrap();
+        let store = SessionStore::new("correct horse", Duration::from_secs(3600), storage);
+        let request = |path: &str| actix_web::test::TestRequest::post().uri(path);
+
+        for path in [LOGIN_PATH, "/api/v1/session/login"] {
+            let req = request(path).to_http_request();
+            assert!(authorize(&req, None, Some(&store)).is_ok(), "{path}");
+        }
+        let req = request("/api/v1/command").to_http_request();
+        assert!(authorize(&req, None, Some(&store)).is_err());
+
+        let (_, token) = store.login("correct horse", None, None).unwrap().unwrap();
+        let req = request("/api/v1/command")
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .to_http_request();
+        assert!(authorize(&req, None, Some(&store)).is_ok());
+    }
+}
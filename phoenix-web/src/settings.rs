@@ -0,0 +1,350 @@
+//! Server configuration, built from the shared layered settings (see [`pagi_config`]):
+//! built-in defaults, then a TOML file, then environment variables, then command-line flags.
+//!
+//! [`ServerConfig::from_layers`] types and validates the tables only the server reads
+//! (`[server]`, `[tls]`, `[auth]`, `[storage]`, `[scheduler]`, `[backup]`, `[sync]`, `[mqtt]`,
+//! `[lexicon]`) and takes the
+//! shared ones (`[sensors]`, `[retention]`, `[features]`, `[recorder]`, `[llm]`, `[plugins]`,
+//! `[scripts]`, `[i18n]`, `[encryption]`) from the crates that define them.
+//!
+//! Some settings can also change while the server runs; see [`crate::config_reload`].
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use common_types::ports::{self, PhoenixGrpcPort, PhoenixWebPort};
+use emotion_detection::DetectedEmotion;
+use multi_modal_recording::RecorderConfig;
+pub use pagi_config::{
+    is_secret, ConfigError, FeatureToggles, Key, Layers, Overrides, RetentionSettings,
+    SensorSettings, Source, CONFIG_ENV, DEFAULT_CONFIG_FILE, KEYS,
+};
+use pagi_config::{parse_list, parse_number};
+use pagi_crypto::EncryptionConfig;
+use pagi_i18n::I18nConfig;
+use pagi_llm::LlmConfig;
+use pagi_plugins::PluginConfig;
+use pagi_scripts::ScriptConfig;
+
+use crate::api_keys::ApiAuthMode;
+use crate::device_sync::SyncSettings;
+use crate::home_assistant::MqttSettings;
+use crate::scheduled_backup::BackupSchedule;
+use crate::scheduler;
+use crate::tls::TlsConfig;
+
+/// `[lexicon]` keys and the emotion whose extra terms each one lists.
+const LEXICON_KEYS: &[(&str, DetectedEmotion)] = &[
+    ("lexicon.joy", DetectedEmotion::Joy),
+    ("lexicon.sadness", DetectedEmotion::Sadness),
+    ("lexicon.anger", DetectedEmotion::Anger),
+    ("lexicon.fear", DetectedEmotion::Fear),
+    ("lexicon.surprise", DetectedEmotion::Surprise),
+    ("lexicon.disgust", DetectedEmotion::Disgust),
+    ("lexicon.love", DetectedEmotion::Love),
+    ("lexicon.jealousy", DetectedEmotion::Jealousy),
+];
+
+#[derive(Clone)]
+pub struct AuthSettings {
+    /// When `/api` requires an API key (see [`crate::api_keys`]).
+    pub api_auth: ApiAuthMode,
+    pub api_keys_path: PathBuf,
+    /// Turns on web UI login sessions (see [`crate::sessions`]).
+    pub ui_passphrase: Option<String>,
+    /// Requests per minute per client; `0` turns a tier's limit off.
+    pub rate_limit_cheap_per_min: u32,
+    pub rate_limit_expensive_per_min: u32,
+}
+
+impl fmt::Debug for AuthSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthSettings")
+            .field("api_auth", &self.api_auth)
+            .field("api_keys_path", &self.api_keys_path)
+            .field(
+                "ui_passphrase",
+                &self.ui_passphrase.as_ref().map(|_| "********"),
+            )
+            .field("rate_limit_cheap_per_min", &self.rate_limit_cheap_per_min)
+            .field(
+                "rate_limit_expensive_per_min",
+                &self.rate_limit_expensive_per_min,
+            )
+            .finish()
+    }
+}
+
+/// The shared database (see [`phoenix_storage`]).
+#[derive(Debug, Clone)]
+pub struct StorageSettings {
+    /// `sqlite://<path>` or a plain path (default: `data/phoenix.db` under the data directory),
+    /// or a `postgres://` URL with the `postgres` feature.
+    pub url: String,
+    /// Reader connections kept open.
+    pub readers: usize,
+}
+
+/// Schedules of the built-in [`crate::scheduler`] jobs: cron expressions with a seconds field,
+/// `None` = job disabled.
+#[derive(Debug, Clone)]
+pub struct ScheduleSettings {
+    pub retention_prune: Option<String>,
+    pub weekly_report: Option<String>,
+    pub model_updates: Option<String>,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        Self {
+            retention_prune: Some(scheduler::DEFAULT_RETENTION_PRUNE.to_string()),
+            weekly_report: Some(scheduler::DEFAULT_WEEKLY_REPORT.to_string()),
+            model_updates: Some(scheduler::DEFAULT_MODEL_UPDATES.to_string()),
+        }
+    }
+}
+
+/// How [`crate::run_server`] binds and what it starts alongside HTTP.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Host to listen on.
+    pub host: String,
+    /// Port to listen on; `0` lets the OS pick one (see [`crate::run_server_reporting`]).
+    pub port: u16,
+    /// Ports to try in order when `port` is already in use.
+    pub port_fallback: Option<RangeInclusive<u16>>,
+    /// Serve on this Unix domain socket instead of `host:port` (see [`crate::listener`]).
+    pub socket: Option<PathBuf>,
+    /// Where the vault databases and the `data/` tree live (default: the working directory).
+    pub data_dir: PathBuf,
+    /// The `.env` file the caller loaded and the load error, if any; reported in status.
+    pub dotenv_path: Option<PathBuf>,
+    pub dotenv_error: Option<String>,
+    /// Serve HTTPS instead of HTTP (see [`crate::tls`]).
+    pub tls: Option<TlsConfig>,
+    /// Browser origins allowed to call the API (see [`crate::cors`]).
+    pub cors_origins: Vec<String>,
+    /// How long in-flight requests may take to finish after a shutdown signal.
+    pub shutdown_timeout: Duration,
+    /// Built frontend to serve alongside the API; `None` = API only.
+    pub ui_dir: Option<PathBuf>,
+    /// `host:port` of the gRPC listener, when `features.grpc` is on.
+    pub grpc_bind: String,
+    pub auth: AuthSettings,
+    pub sensors: SensorSettings,
+    pub retention: RetentionSettings,
+    pub storage: StorageSettings,
+    pub features: FeatureToggles,
+    /// The capture recorder served under `/api/recorder` (see [`crate::recorder_api`]).
+    pub recorder: RecorderConfig,
+    /// The completion client behind Ghost replies, reframes and summaries (see [`pagi_llm`]).
+    pub llm: LlmConfig,
+    /// Analyzer plugins (see [`crate::plugins_api`]).
+    pub plugins: PluginConfig,
+    /// Automation scripts (see [`crate::scripts_api`]).
+    pub scripts: ScriptConfig,
+    /// Language of Ghost replies and error titles when a request doesn't ask for one.
+    pub i18n: I18nConfig,
+    /// Encryption at rest of the data directory (see [`pagi_crypto`]).
+    pub encryption: EncryptionConfig,
+    pub schedules: ScheduleSettings,
+    /// Automatic backups (see [`crate::scheduled_backup`]).
+    pub backups: BackupSchedule,
+    /// Sync with the user's other devices (see [`crate::device_sync`]).
+    pub sync: SyncSettings,
+    /// The Home Assistant bridge (see [`crate::home_assistant`]).
+    pub mqtt: MqttSettings,
+    /// Extra emotion lexicon terms (see [`emotion_detection::text::set_extra_terms`]).
+    pub lexicon: Vec<(DetectedEmotion, Vec<String>)>,
+    /// The raw values this config was built from, for [`crate::config_reload`].
+    pub layers: Layers,
+}
+
+impl ServerConfig {
+    /// Defaults, then the config file, then the environment, then `overrides`.
+    pub fn load(overrides: &Overrides) -> Result<Self, ConfigError> {
+        Self::from_layers(&Layers::load(overrides)?)
+    }
+
+    pub fn from_layers(layers: &Layers) -> Result<Self, ConfigError> {
+        // `bind` is shorthand for host and port; whichever comes from the higher layer wins.
+        let invalid_bind = |s: &str| format!("expected host:port, got {s:?}");
+        let (bind, bind_origin) = layers
+            .typed("server.bind", |s| {
+                ports::split_host_port(s).ok_or_else(|| invalid_bind(s))
+            })?
+            .unwrap_or_else(|| {
+                let default = ports::split_host_port(PhoenixWebPort::DEFAULT_BIND)
+                    .expect("default bind is host:port");
+                (default, Source::Default)
+            });
+        let (mut host, mut port) = bind;
+        if let Some((value, origin)) = layers.typed("server.host", |s| Ok(s.trim().to_string()))? {
+            if origin.precedence() >= bind_origin.precedence() {
+                host = value;
+            }
+        }
+        if let Some((value, origin)) = layers.typed("server.port", parse_number::<u16>)? {
+            if origin.precedence() >= bind_origin.precedence() {
+                port = value;
+            }
+        }
+
+        let data_dir = layers
+            .path("server.data_dir")
+            .unwrap_or_else(|| PathBuf::from("."));
+        let tls = TlsConfig::new(
+            layers.path("tls.cert"),
+            layers.path("tls.key"),
+            layers.flag("tls.self_signed", false)?,
+            &data_dir,
+        );
+
+        let secs = |s: &str| parse_number::<u64>(s).map(Duration::from_secs);
+        let schedules = ScheduleSettings::default();
+
+        Ok(Self {
+            host,
+            port,
+            port_fallback: layers
+                .typed("server.port_fallback", |s| {
+                    ports::parse_port_range(s)
+                        .ok_or_else(|| format!("expected a port range like 8889-8899, got {s:?}"))
+                })?
+                .map(|(range, _)| range),
+            socket: layers
+                .path("server.socket")
+                .filter(|p| !p.as_os_str().is_empty()),
+            dotenv_path: None,
+            dotenv_error: None,
+            tls,
+            cors_origins: layers
+                .get("server.cors_origins")
+                .map(|(list, _)| parse_list(&list))
+                .filter(|list| !list.is_empty())
+                .unwrap_or_else(crate::cors::CorsPolicy::default_origins),
+            shutdown_timeout: layers.or(
+                "server.shutdown_timeout_secs",
+                crate::shutdown::DEFAULT_DRAIN_TIMEOUT,
+                secs,
+            )?,
+            ui_dir: layers.path("server.ui_dir"),
+            grpc_bind: layers.or(
+                "server.grpc_bind",
+                PhoenixGrpcPort::DEFAULT_BIND.to_string(),
+                |s| {
+                    ports::split_host_port(s)
+                        .map(|_| s.trim().to_string())
+                        .ok_or_else(|| invalid_bind(s))
+                },
+            )?,
+            auth: AuthSettings {
+                api_auth: layers.or("auth.api_auth", ApiAuthMode::Auto, |s| {
+                    ApiAuthMode::parse(s).ok_or_else(|| "expected auto, on or off".to_string())
+                })?,
+                api_keys_path: layers
+                    .path("auth.api_keys_path")
+                    .unwrap_or_else(|| data_dir.join("data/api_keys.json")),
+                ui_passphrase: layers.secret("auth.ui_passphrase")?,
+                rate_limit_cheap_per_min: layers.or(
+                    "auth.rate_limit_cheap_per_min",
+                    120,
+                    parse_number,
+                )?,
+                rate_limit_expensive_per_min: layers.or(
+                    "auth.rate_limit_expensive_per_min",
+                    10,
+                    parse_number,
+                )?,
+            },
+            sensors: SensorSettings::from_layers(layers)?,
+            retention: RetentionSettings::from_layers(layers)?,
+            storage: StorageSettings {
+                url: layers.or(
+                    "storage.url",
+                    format!("sqlite://{}", data_dir.join("data/phoenix.db").display()),
+                    |s| {
+                        phoenix_storage::Backend::parse(s)
+                            .map(|_| s.trim().to_string())
+                            .map_err(|e| e.to_string())
+                    },
+                )?,
+                readers: layers.or("storage.readers", phoenix_storage::DEFAULT_READERS, |s| {
+                    match parse_number::<usize>(s)? {
+                        0 => Err("must be at least 1".to_string()),
+                        n => Ok(n),
+                    }
+                })?,
+            },
+            features: FeatureToggles::from_layers(layers)?,
+            recorder: RecorderConfig::from_layers(layers)?,
+            llm: LlmConfig::from_layers(layers)?,
+            plugins: PluginConfig::from_layers(layers, &data_dir)?,
+            scripts: ScriptConfig::from_layers(layers, &data_dir)?,
+            i18n: I18nConfig::from_layers(layers)?,
+            encryption: EncryptionConfig::from_layers(layers)?,
+            schedules: ScheduleSettings {
+                retention_prune: layers.or(
+                    "scheduler.retention_prune",
+                    schedules.retention_prune,
+                    scheduler::parse_schedule,
+                )?,
+                weekly_report: layers.or(
+                    "scheduler.weekly_report",
+                    schedules.weekly_report,
+                    scheduler::parse_schedule,
+                )?,
+                model_updates: layers.or(
+                    "scheduler.model_updates",
+                    schedules.model_updates,
+                    scheduler::parse_schedule,
+                )?,
+            },
+            backups: BackupSchedule::from_layers(layers, &data_dir)?,
+            sync: SyncSettings::from_layers(layers)?,
+            mqtt: MqttSettings::from_layers(layers)?,
+            lexicon: LEXICON_KEYS
+                .iter()
+                .filter_map(|(name, emotion)| {
+                    let (list, _) = layers.get(name)?;
+                    Some((emotion.clone(), parse_list(&list)))
+                })
+                .collect(),
+            layers: layers.clone(),
+            data_dir,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(file: &str, cli: &[(&str, &str)]) -> Layers {
+        let mut overrides = Overrides::default();
+        for (key, value) in cli {
+            overrides.set(key, *value);
+        }
+        Layers::parse("phoenix.toml", file, &overrides).unwrap()
+    }
+
+    #[test]
+    fn later_layers_win_and_bind_yields_to_specific_keys() {
+        let file =
+            "[server]\nbind = \"0.0.0.0:7000\"\nport = 7001\n[sensors]\nstress_alert_percent = 70";
+        let config = ServerConfig::from_layers(&layers(file, &[])).unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("0.0.0.0", 7001));
+        assert_eq!(config.sensors.stress_alert_percent, 70);
+
+        let config =
+            ServerConfig::from_layers(&layers(file, &[("server.bind", "127.0.0.1:0")])).unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("127.0.0.1", 0));
+
+        let err = ServerConfig::from_layers(&layers("[sensors]\nstress_alert_percent = 150", &[]))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("sensors.stress_alert_percent"));
+    }
+}
@@ -0,0 +1,115 @@
+//! Graceful shutdown for [`crate::run_server`].
+//!
+//! On ctrl-c or SIGTERM the server stops accepting connections and lets in-flight requests finish
+//! for up to the drain timeout (`PHOENIX_SHUTDOWN_TIMEOUT_SECS`, default 30). Then the background
+//! tasks are stopped and the stores flushed, so a supervisor can restart the process without
+//! losing writes or tripping over the previous instance's database locks.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::AppState;
+
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Resolves on ctrl-c, or SIGTERM on Unix.
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "ctrl-c",
+                _ = term.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                warn!("Cannot listen for SIGTERM ({e}); shutting down on ctrl-c only");
+                let _ = tokio::signal::ctrl_c().await;
+                "ctrl-c"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "ctrl-c"
+    }
+}
+
+struct Task {
+    name: &'static str,
+    started_unix: i64,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub started_unix: i64,
+    /// `false` once the task has returned or panicked; they are all meant to run until shutdown.
+    pub running: bool,
+}
+
+/// The server's long-running tasks, listed by `GET /api/admin/tasks` and stopped by [`finish`].
+#[derive(Clone, Default)]
+pub struct BackgroundTasks(Arc<Mutex<Vec<Task>>>);
+
+impl BackgroundTasks {
+    pub fn push(&self, name: &'static str, handle: JoinHandle<()>) {
+        let task = Task {
+            name,
+            started_unix: chrono::Utc::now().timestamp(),
+            handle,
+        };
+        if let Ok(mut tasks) = self.0.lock() {
+            tasks.push(task);
+        }
+    }
+
+    pub fn status(&self) -> Vec<TaskStatus> {
+        let Ok(tasks) = self.0.lock() else {
+            return Vec::new();
+        };
+        tasks
+            .iter()
+            .map(|t| TaskStatus {
+                name: t.name,
+                started_unix: t.started_unix,
+                running: !t.handle.is_finished(),
+            })
+            .collect()
+    }
+
+    fn take(&self) -> Vec<JoinHandle<()>> {
+        match self.0.lock() {
+            Ok(mut tasks) => tasks.drain(..).map(|t| t.handle).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// After the HTTP server has drained: stop background tasks and flush the stores.
+pub async fn finish(state: &AppState, background: &BackgroundTasks) {
+    let background = background.take();
+    for task in &background {
+        task.abort();
+    }
+    for task in background {
+        let _ = task.await;
+    }
+    if let Some(audio) = &state.audio_intelligence {
+        audio.lock().await.stop_listening();
+    }
+    state.capture.stop();
+    if let Err(e) = state.vaults.flush() {
+        warn!("Failed to flush vaults on shutdown: {e}");
+    }
+    if let Err(e) = state.neural_cortex.flush() {
+        warn!("Failed to flush neural cortex on shutdown: {e}");
+    }
+    info!("Shutdown complete");
+}
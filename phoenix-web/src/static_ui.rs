@@ -0,0 +1,132 @@
+//! Serving a built frontend from the same binary.
+//!
+//! With `PHOENIX_UI_DIR` pointing at a build output (e.g. `frontend_desktop/dist`), every path
+//! not claimed by `/api`, `/ws`, `/health` or `/metrics` is looked up in that directory. Page
+//! routes that don't exist on disk get `index.html` so client-side routing works on reload;
+//! missing files with an extension stay `404`.
+//!
+//! Cache headers: `index.html` is always revalidated, Vite's content-hashed `assets/` are
+//! cached for a year, and everything else for an hour.
+
+use std::path::{Path, PathBuf};
+
+use actix_files::NamedFile;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::Method;
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::AppState;
+
+const INDEX: &str = "index.html";
+
+#[derive(Debug, Clone)]
+pub struct StaticUi {
+    root: PathBuf,
+}
+
+impl StaticUi {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn has_index(&self) -> bool {
+        self.root.join(INDEX).is_file()
+    }
+
+    async fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return HttpResponse::NotFound().finish();
+        }
+        let Some(relative) = sanitize(req.path()) else {
+            return HttpResponse::NotFound().finish();
+        };
+
+        let mut path = self.root.join(&relative);
+        if path.is_dir() {
+            path.push(INDEX);
+        }
+        if !path.is_file() {
+            // Client-side routes have no extension; missing assets should 404, not get HTML.
+            let is_page = relative
+                .file_name()
+                .is_none_or(|name| !name.to_string_lossy().contains('.'));
+            if !is_page {
+                return HttpResponse::NotFound().finish();
+            }
+            path = self.root.join(INDEX);
+        }
+
+        let file = match NamedFile::open_async(&path).await {
+            Ok(file) => file,
+            Err(_) => return HttpResponse::NotFound().finish(),
+        };
+        let cache = cache_control(path.strip_prefix(&self.root).unwrap_or(&path));
+        let mut response = file.into_response(req);
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static(cache));
+        response
+    }
+}
+
+/// The request path as a relative path under the UI root, or `None` if it tries to leave it
+/// (`..`) or names a hidden file.
+fn sanitize(request_path: &str) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for segment in request_path.split('/').filter(|s| !s.is_empty()) {
+        if segment.starts_with('.') || segment.contains('\\') || segment.contains(':') {
+            return None;
+        }
+        relative.push(segment);
+    }
+    Some(relative)
+}
+
+fn cache_control(relative: &Path) -> &'static str {
+    if relative.file_name().is_some_and(|name| name == INDEX) {
+        "no-cache"
+    } else if relative.starts_with("assets") {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, max-age=3600"
+    }
+}
+
+/// Default service: the UI when one is configured, otherwise `404`.
+pub async fn serve(req: HttpRequest, state: web::Data<AppState>) -> HttpResponse {
+    match &state.ui {
+        Some(ui) => ui.respond(&req).await,
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_paths_and_picks_cache_policy() {
+        assert_eq!(sanitize("/"), Some(PathBuf::new()));
+        assert_eq!(
+            sanitize("/assets/index-3f2a.js"),
+            Some(PathBuf::from("assets/index-3f2a.js"))
+        );
+        assert_eq!(sanitize("/../etc/passwd"), None);
+        assert_eq!(sanitize("/.env"), None);
+        assert_eq!(sanitize("/a\\..\\b"), None);
+
+        assert_eq!(cache_control(Path::new("index.html")), "no-cache");
+        assert_eq!(
+            cache_control(Path::new("assets/index-3f2a.js")),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            cache_control(Path::new("favicon.ico")),
+            "public, max-age=3600"
+        );
+    }
+}
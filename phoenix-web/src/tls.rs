@@ -0,0 +1,126 @@
+//! Optional HTTPS for the web server (rustls).
+//!
+//! Point `tls.cert` / `tls.key` (`PHOENIX_TLS_CERT` / `PHOENIX_TLS_KEY`) at PEM files, or turn on
+//! `tls.self_signed` (`PHOENIX_TLS_SELF_SIGNED=1`) to have a certificate generated for
+//! `localhost`, loopback and this machine's LAN address. The generated pair is written next to
+//! the configured paths (default `data/tls/` in the data directory) and reused on later starts,
+//! so a phone only has to trust it once.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tracing::info;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM certificate chain.
+    pub cert_path: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1).
+    pub key_path: PathBuf,
+    /// Generate a self-signed pair at the paths above when they don't exist yet.
+    pub self_signed: bool,
+}
+
+impl TlsConfig {
+    /// `None` unless a cert and key are both given or self-signing is on; a self-signed pair
+    /// without paths goes under `<data_dir>/data/tls/`.
+    pub fn new(
+        cert_path: Option<PathBuf>,
+        key_path: Option<PathBuf>,
+        self_signed: bool,
+        data_dir: &Path,
+    ) -> Option<Self> {
+        match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Some(Self {
+                cert_path,
+                key_path,
+                self_signed,
+            }),
+            _ if self_signed => Some(Self {
+                cert_path: data_dir.join("data/tls/cert.pem"),
+                key_path: data_dir.join("data/tls/key.pem"),
+                self_signed: true,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The rustls server config, generating the self-signed pair first if needed.
+    pub fn load(&self) -> io::Result<rustls::ServerConfig> {
+        if self.self_signed && !(self.cert_path.exists() && self.key_path.exists()) {
+            generate_self_signed(&self.cert_path, &self.key_path)?;
+        }
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| pem_error(&self.cert_path, e))?;
+        if certs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no certificates in {}", self.cert_path.display()),
+            ));
+        }
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)
+            .map_err(|e| pem_error(&self.key_path, e))?;
+
+        rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+fn pem_error(path: &Path, e: rustls::pki_types::pem::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {e}", path.display()),
+    )
+}
+
+fn generate_self_signed(cert_path: &Path, key_path: &Path) -> io::Result<()> {
+    let mut names = vec![
+        "localhost".to_string(),
+        "127.0.0.1".to_string(),
+        "::1".to_string(),
+    ];
+    if let Ok(ip) = local_ip_address::local_ip() {
+        names.push(ip.to_string());
+    }
+    let generated = rcgen::generate_simple_self_signed(names.clone()).map_err(io::Error::other)?;
+
+    for path in [cert_path, key_path] {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+    }
+    std::fs::write(cert_path, generated.cert.pem())?;
+    write_private(key_path, generated.key_pair.serialize_pem().as_bytes())?;
+    info!(
+        "Generated self-signed TLS certificate for {} at {}",
+        names.join(", "),
+        cert_path.display()
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    std::fs::write(path, contents)
+}
@@ -0,0 +1,435 @@
+//! User profiles: who Ghost sessions, emotion history, counselor records and per-profile
+//! settings belong to.
+//!
+//! One profile is active at a time. Data recorded while nobody is recognized is attributed to it,
+//! and reads default to it, so switching profiles switches whose history the UI shows. The
+//! built-in `default` profile owns everything recorded before profiles existed and can't be
+//! deleted; neither can the active profile.
+//!
+//! Routes (under `/api`):
+//! - `GET /users`: every profile and the active one
+//! - `POST /users`: create (`{"id", "display_name"?}`)
+//! - `GET /users/active`, `PUT /users/active` (`{"id"}`): the profile switcher
+//! - `PATCH /users/{id}`: rename (`{"display_name"}`)
+//! - `GET /users/{id}/settings`, `PUT /users/{id}/settings`: the profile's settings object
+//! - `DELETE /users/{id}`: delete the profile and all of its data
+//!
+//! The same switcher is available as the `profile` chat command. Every change is recorded in the
+//! audit log (see [`crate::audit`]).
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use multi_modal_recording::emotion_privacy;
+use phoenix_storage::profiles::{Profile, ProfileDeletion, DEFAULT_PROFILE};
+use phoenix_storage::{Storage, StorageError};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::audit::{self, Action, Actor};
+use crate::{ApiError, AppState};
+
+const MAX_ID_CHARS: usize = 64;
+const MAX_DISPLAY_NAME_CHARS: usize = 100;
+/// Largest settings object accepted, serialized.
+const MAX_SETTINGS_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error("profile ids are 1-{MAX_ID_CHARS} letters, digits, spaces, '-', '_' or '.'")]
+    InvalidId,
+    #[error("display names are 1-{MAX_DISPLAY_NAME_CHARS} characters")]
+    InvalidDisplayName,
+    #[error("settings must be a JSON object of at most {MAX_SETTINGS_BYTES} bytes")]
+    InvalidSettings,
+    #[error("no profile {0}")]
+    NotFound(String),
+    #[error("profile {0} already exists")]
+    Exists(String),
+    #[error("profile {0} is the default or active profile; switch to another profile first")]
+    InUse(String),
+    #[error("removing emotion data failed: {0}")]
+    Purge(String),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl From<ProfileError> for ApiError {
+    fn from(e: ProfileError) -> Self {
+        match e {
+            ProfileError::InvalidId
+            | ProfileError::InvalidDisplayName
+            | ProfileError::InvalidSettings => ApiError::bad_request(e.to_string()),
+            ProfileError::NotFound(_) => ApiError::not_found(e.to_string()),
+            ProfileError::Exists(_) | ProfileError::InUse(_) => {
+                ApiError::new(StatusCode::CONFLICT, e.to_string())
+            }
+            ProfileError::Purge(_) => ApiError::internal(e.to_string()),
+            ProfileError::Storage(e) => e.into(),
+        }
+    }
+}
+
+/// The active profile's id, or `default` if it can't be read.
+pub(crate) fn active(storage: &Storage) -> String {
+    storage.active_profile().unwrap_or_else(|e| {
+        warn!(target: "profiles", "reading the active profile failed: {e}");
+        DEFAULT_PROFILE.to_string()
+    })
+}
+
+/// Which profile a read is limited to: the requested one, the active one when none is given, or
+/// everyone for `*`.
+pub(crate) fn scope(storage: &Storage, requested: Option<String>) -> Option<String> {
+    match requested.as_deref().map(str::trim) {
+        None | Some("") => Some(active(storage)),
+        Some("*") => None,
+        Some(profile) => Some(profile.to_string()),
+    }
+}
+
+/// Whether a record stamped with `record` belongs to `profile`. Unstamped records predate
+/// profiles and belong to the default one.
+pub(crate) fn owns(record: Option<&str>, profile: &str) -> bool {
+    record
+        .unwrap_or(DEFAULT_PROFILE)
+        .eq_ignore_ascii_case(profile)
+}
+
+pub(crate) fn valid_id(id: &str) -> Result<&str, ProfileError> {
+    let id = id.trim();
+    let len = id.chars().count();
+    let allowed = id
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'));
+    if (1..=MAX_ID_CHARS).contains(&len) && allowed {
+        Ok(id)
+    } else {
+        Err(ProfileError::InvalidId)
+    }
+}
+
+pub(crate) fn valid_display_name(name: &str) -> Result<&str, ProfileError> {
+    let name = name.trim();
+    if (1..=MAX_DISPLAY_NAME_CHARS).contains(&name.chars().count()) {
+        Ok(name)
+    } else {
+        Err(ProfileError::InvalidDisplayName)
+    }
+}
+
+fn settings_value(profile: &Profile) -> serde_json::Value {
+    serde_json::from_str(&profile.settings).unwrap_or_else(|_| json!({}))
+}
+
+pub(crate) fn profile_json(profile: &Profile, active: &str) -> serde_json::Value {
+    json!({
+        "id": profile.id,
+        "display_name": profile.display_name,
+        "created_unix": profile.created_unix,
+        "active": profile.id.eq_ignore_ascii_case(active),
+        "settings": settings_value(profile),
+    })
+}
+
+fn find(storage: &Storage, id: &str) -> Result<Profile, ProfileError> {
+    storage
+        .profile(id)?
+        .ok_or_else(|| ProfileError::NotFound(id.to_string()))
+}
+
+fn create(
+    state: &AppState,
+    actor: &Actor,
+    id: &str,
+    display_name: Option<&str>,
+) -> Result<Profile, ProfileError> {
+    let storage = &state.storage;
+    let id = valid_id(id)?;
+    let display_name = match display_name {
+        Some(name) => valid_display_name(name)?,
+        None => id,
+    };
+    if !storage.create_profile(id, display_name, Utc::now().timestamp())? {
+        return Err(ProfileError::Exists(id.to_string()));
+    }
+    info!(target: "profiles", profile = %id, "profile created");
+    audit::record(state, actor, Action::ProfileCreate, json!({ "id": id }));
+    find(storage, id)
+}
+
+fn switch(state: &AppState, actor: &Actor, id: &str) -> Result<Profile, ProfileError> {
+    let from = active(&state.storage);
+    let profile = state
+        .storage
+        .set_active_profile(id.trim())?
+        .ok_or_else(|| ProfileError::NotFound(id.trim().to_string()))?;
+    info!(target: "profiles", profile = %profile.id, "active profile switched");
+    audit::record(
+        state,
+        actor,
+        Action::ProfileSwitch,
+        json!({ "from": from, "to": profile.id }),
+    );
+    Ok(profile)
+}
+
+/// Delete a profile with its emotion history (database and vault copies, recording tracks),
+/// Ghost sessions and counselor records.
+async fn delete(
+    state: &AppState,
+    actor: &Actor,
+    id: &str,
+) -> Result<serde_json::Value, ProfileError> {
+    let profile = find(&state.storage, id.trim())?;
+    if profile.id.eq_ignore_ascii_case(DEFAULT_PROFILE)
+        || profile.id.eq_ignore_ascii_case(&active(&state.storage))
+    {
+        return Err(ProfileError::InUse(profile.id));
+    }
+
+    let recordings = state.capture.storage_path();
+    let purge = emotion_privacy::purge_profile(&state.vaults, &recordings, &profile.id)
+        .await
+        .map_err(|e| ProfileError::Purge(e.to_string()))?;
+    state.cache.trends.clear();
+
+    let mut counselor_records = 0;
+    for (key, value) in state.vaults.recall_prefix("soul:counselor:", usize::MAX) {
+        let stamped = serde_json::from_str::<serde_json::Value>(&value)
+            .ok()
+            .and_then(|v| v.get("profile")?.as_str().map(str::to_string));
+        if stamped.is_some_and(|p| p.eq_ignore_ascii_case(&profile.id))
+            && state.vaults.forget_soul(&key).unwrap_or(false)
+        {
+            counselor_records += 1;
+        }
+    }
+
+    match state.storage.delete_profile(&profile.id)? {
+        ProfileDeletion::Deleted { ghost_sessions, .. } => {
+            info!(target: "profiles", profile = %profile.id, "profile deleted");
+            let outcome = json!({
+                "status": "deleted",
+                "id": profile.id,
+                "emotion": purge,
+                "ghost_sessions": ghost_sessions,
+                "counselor_records": counselor_records,
+            });
+            audit::record(state, actor, Action::ProfileDelete, outcome.clone());
+            Ok(outcome)
+        }
+        ProfileDeletion::NotFound => Err(ProfileError::NotFound(profile.id)),
+        ProfileDeletion::InUse => Err(ProfileError::InUse(profile.id)),
+    }
+}
+
+async fn get_profiles(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let active = active(&state.storage);
+    let profiles = state.storage.profiles()?;
+    let profiles: Vec<_> = profiles.iter().map(|p| profile_json(p, &active)).collect();
+    Ok(HttpResponse::Ok().json(json!({ "active": active, "profiles": profiles })))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateBody {
+    id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+async fn post_profile(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<CreateBody>,
+) -> Result<HttpResponse, ApiError> {
+    let actor = Actor::of(&req);
+    let profile = create(&state, &actor, &body.id, body.display_name.as_deref())?;
+    let active = active(&state.storage);
+    Ok(HttpResponse::Created().json(profile_json(&profile, &active)))
+}
+
+async fn get_active(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let active = active(&state.storage);
+    let profile = find(&state.storage, &active)?;
+    Ok(HttpResponse::Ok().json(profile_json(&profile, &active)))
+}
+
+#[derive(Debug, Deserialize)]
+struct SwitchBody {
+    id: String,
+}
+
+async fn put_active(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    body: web::Json<SwitchBody>,
+) -> Result<HttpResponse, ApiError> {
+    let profile = switch(&state, &Actor::of(&req), &body.id)?;
+    Ok(HttpResponse::Ok().json(profile_json(&profile, &profile.id)))
+}
+
+#[derive(Debug, Deserialize)]
+struct RenameBody {
+    display_name: String,
+}
+
+async fn patch_profile(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    body: web::Json<RenameBody>,
+) -> Result<HttpResponse, ApiError> {
+    let display_name = valid_display_name(&body.display_name)?;
+    if !state.storage.rename_profile(&id, display_name)? {
+        return Err(ProfileError::NotFound(id.into_inner()).into());
+    }
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::ProfileRename,
+        json!({ "id": id.as_str(), "display_name": display_name }),
+    );
+    let profile = find(&state.storage, &id)?;
+    Ok(HttpResponse::Ok().json(profile_json(&profile, &active(&state.storage))))
+}
+
+async fn get_settings(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let profile = find(&state.storage, &id)?;
+    Ok(HttpResponse::Ok().json(settings_value(&profile)))
+}
+
+async fn put_settings(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ApiError> {
+    let settings = body.into_inner();
+    let serialized = settings.to_string();
+    if !settings.is_object() || serialized.len() > MAX_SETTINGS_BYTES {
+        return Err(ProfileError::InvalidSettings.into());
+    }
+    if !state.storage.set_profile_settings(&id, &serialized)? {
+        return Err(ProfileError::NotFound(id.into_inner()).into());
+    }
+    // Which settings changed, not their values.
+    let keys: Vec<&String> = settings
+        .as_object()
+        .into_iter()
+        .flat_map(|o| o.keys())
+        .collect();
+    audit::record(
+        &state,
+        &Actor::of(&req),
+        Action::ProfileSettings,
+        json!({ "id": id.as_str(), "keys": keys }),
+    );
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+async fn delete_profile(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(delete(&state, &Actor::of(&req), &id).await?))
+}
+
+/// `profile` chat command: `profile`, `profile list`, `profile switch <id>`,
+/// `profile create <id> [display name]`.
+pub(crate) async fn handle_command(
+    state: &AppState,
+    actor: &Actor,
+    cmd: &str,
+) -> serde_json::Value {
+    let rest = cmd.trim()["profile".len()..].trim();
+    let (sub, arg) = rest.split_once(' ').unwrap_or((rest, ""));
+    let arg = arg.trim();
+    let storage = &state.storage;
+
+    let result = match sub.to_ascii_lowercase().as_str() {
+        "" => Ok(format!("Active profile: {}", active(storage))),
+        "list" => storage
+            .profiles()
+            .map_err(ProfileError::from)
+            .map(|profiles| {
+                let active = active(storage);
+                let lines: Vec<String> = profiles
+                    .iter()
+                    .map(|p| {
+                        let marker = if p.id.eq_ignore_ascii_case(&active) {
+                            "*"
+                        } else {
+                            "-"
+                        };
+                        format!("{marker} {} ({})", p.id, p.display_name)
+                    })
+                    .collect();
+                format!("Profiles:\n{}", lines.join("\n"))
+            }),
+        "switch" | "use" if !arg.is_empty() => switch(state, actor, arg)
+            .map(|p| format!("Switched to profile {} ({}).", p.id, p.display_name)),
+        "create" | "add" if !arg.is_empty() => {
+            let (id, name) = match arg.split_once(' ') {
+                Some((id, name)) => (id, Some(name)),
+                None => (arg, None),
+            };
+            create(state, actor, id, name)
+                .map(|p| format!("Created profile {} ({}).", p.id, p.display_name))
+        }
+        _ => {
+            return json!({
+                "type": "error",
+                "message": "Usage: profile | profile list | profile switch <id> | profile create <id> [display name]"
+            })
+        }
+    };
+
+    match result {
+        Ok(message) => json!({ "type": "profile", "message": message, "active": active(storage) }),
+        Err(e) => json!({ "type": "error", "message": e.to_string() }),
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope. `/profiles` belongs to the dating profile generator.
+    cfg.service(
+        web::scope("/users")
+            .route("", web::get().to(get_profiles))
+            .route("", web::post().to(post_profile))
+            .route("/active", web::get().to(get_active))
+            .route("/active", web::put().to(put_active))
+            .route("/{id}", web::patch().to(patch_profile))
+            .route("/{id}", web::delete().to(delete_profile))
+            .route("/{id}/settings", web::get().to(get_settings))
+            .route("/{id}/settings", web::put().to(put_settings)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_validated_and_trimmed() {
+        assert_eq!(valid_id("  Alice B.  ").unwrap(), "Alice B.");
+        assert_eq!(valid_id("kid_2-a").unwrap(), "kid_2-a");
+        assert!(valid_id("   ").is_err());
+        assert!(valid_id("a/b").is_err());
+        assert!(valid_id("*").is_err());
+        assert!(valid_id(&"x".repeat(MAX_ID_CHARS + 1)).is_err());
+    }
+
+    #[test]
+    fn unstamped_records_belong_to_the_default_profile() {
+        assert!(owns(None, DEFAULT_PROFILE));
+        assert!(!owns(None, "alice"));
+        assert!(owns(Some("Alice"), "alice"));
+        assert!(!owns(Some("bob"), "alice"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Shared request checks.
+//!
+//! Handlers run the fields they take through a [`Validator`] and answer with
+//! [`ApiError::validation`](crate::ApiError) listing every rejected field at once, so limits such
+//! as script length or the 0–100 scales read the same on every route.
+
+use crate::FieldError;
+
+/// Collects [`FieldError`]s; [`finish`](Self::finish) fails if any were found.
+#[derive(Debug, Default)]
+pub(crate) struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn reject(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError::new(field, message));
+    }
+
+    /// Non-blank text of at most `max_chars` characters.
+    pub fn text(&mut self, field: &str, value: &str, max_chars: usize) {
+        let chars = value.chars().count();
+        if value.trim().is_empty() {
+            self.reject(field, "must not be empty");
+        } else if chars > max_chars {
+            self.reject(
+                field,
+                format!("must be at most {max_chars} characters (got {chars})"),
+            );
+        }
+    }
+
+    /// Text that may be empty but fits on one line, e.g. a value written to the `.env` file.
+    pub fn single_line(&mut self, field: &str, value: &str, max_chars: usize) {
+        if value.contains(['\n', '\r', '\0']) {
+            self.reject(field, "must be a single line");
+        } else if value.chars().count() > max_chars {
+            self.reject(field, format!("must be at most {max_chars} characters"));
+        }
+    }
+
+    /// A 0–100 scale such as intensity or system load; `0` stands in when rejected.
+    pub fn percent(&mut self, field: &str, value: i64) -> u8 {
+        match u8::try_from(value) {
+            Ok(v) if v <= 100 => v,
+            _ => {
+                self.reject(field, "must be between 0 and 100");
+                0
+            }
+        }
+    }
+
+    /// One of `allowed`, compared case-insensitively after trimming.
+    pub fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) {
+        let value = value.trim();
+        if !allowed.iter().any(|a| a.eq_ignore_ascii_case(value)) {
+            self.reject(field, format!("must be one of {}", allowed.join(", ")));
+        }
+    }
+
+    /// A bare file name: no directories, no `..`, nothing a path join could escape with.
+    pub fn file_name(&mut self, field: &str, value: &str) {
+        let name = value.trim();
+        let plain = !name.is_empty()
+            && name != "."
+            && name != ".."
+            && name.len() <= 255
+            && !name.contains(['/', '\\', '\0', ':']);
+        if !plain {
+            self.reject(field, "must be a plain file name without directories");
+        }
+    }
+
+    pub fn finish(self) -> Result<(), Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(v: Validator) -> Vec<String> {
+        v.finish()
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[test]
+    fn every_rejected_field_is_reported() {
+        let mut v = Validator::default();
+        v.text("script", "   ", 10);
+        v.text("note", "fine", 10);
+        assert_eq!(v.percent("intensity_level", 101), 0);
+        assert_eq!(v.percent("system_load", 40), 40);
+        v.one_of("tone", " Gentle ", &["gentle", "direct"]);
+        v.single_line("user_name", "a\nOPENROUTER_API_KEY=x", 100);
+        assert_eq!(fields(v), ["script", "intensity_level", "user_name"]);
+    }
+
+    #[test]
+    fn file_names_cannot_name_directories() {
+        let mut v = Validator::default();
+        v.file_name("a", "sample.exe");
+        v.file_name("b", "../../etc/passwd");
+        v.file_name("c", "C:evil");
+        v.file_name("d", "..");
+        v.file_name("e", "dir\\x.bin");
+        assert_eq!(fields(v), ["b", "c", "d", "e"]);
+    }
+}
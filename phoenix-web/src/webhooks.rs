@@ -0,0 +1,506 @@
+//! Outbound webhooks: POST selected events to URLs such as an n8n or Home Assistant webhook
+//! trigger.
+//!
+//! The events are the [`crate::events`] ones, by the same names (`recording_finished`,
+//! `drift_alert`, `emotion_alert`, `unknown_presence`, ...), plus `ping` from the test route. A
+//! webhook has a URL, an event filter (empty: every event) and a secret. Each delivery is a JSON
+//! body `{"id", "event", "timestamp", "data"}` with these headers:
+//!
+//! - `X-Phoenix-Event`, `X-Phoenix-Delivery`: the event name and delivery id
+//! - `X-Phoenix-Timestamp`: unix seconds, also in the body
+//! - `X-Phoenix-Signature`: `sha256=` and the hex HMAC-SHA256 of `<timestamp>.<body>`, keyed
+//!   with the webhook's secret
+//!
+//! A delivery that gets no response, a 408, a 429 or a 5xx is retried with exponential backoff
+//! (10 s, 20 s, 40 s, ...) up to [`MAX_ATTEMPTS`] attempts; any other status ends it. Every
+//! attempt goes into the delivery log, which the `retention_prune` job trims to
+//! [`DELIVERY_LOG_KEEP_DAYS`] days. Retries still pending at shutdown are dropped.
+//!
+//! Routes (under `/api`):
+//! - `GET /webhooks`, `POST /webhooks` (`{"url", "events"?, "description"?, "secret"?}`; a secret
+//!   is generated when none is given, and is only ever returned by this call)
+//! - `GET /webhooks/{id}`, `PATCH /webhooks/{id}` (`url`, `events`, `description`, `enabled`),
+//!   `DELETE /webhooks/{id}`
+//! - `POST /webhooks/{id}/test`: send a `ping`
+//! - `GET /webhooks/{id}/deliveries?limit=`: latest attempts, newest first
+
+use std::fmt::Write;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use phoenix_storage::webhooks::{DeliveryRow, WebhookRow};
+use phoenix_storage::{Storage, StorageError};
+use ring::hmac;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::events::{self, EVENT_TYPES};
+use crate::{ApiError, AppState};
+
+/// Attempts per delivery, the first one included.
+pub const MAX_ATTEMPTS: u32 = 6;
+/// Days of delivery log kept.
+pub const DELIVERY_LOG_KEEP_DAYS: i64 = 14;
+/// Event sent by `POST /webhooks/{id}/test`, whatever the webhook's filter.
+pub const PING_EVENT: &str = "ping";
+
+const FIRST_RETRY: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_URL_CHARS: usize = 2048;
+const MAX_DESCRIPTION_CHARS: usize = 200;
+const DEFAULT_LOG_LIMIT: usize = 50;
+const MAX_LOG_LIMIT: usize = 500;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("no webhook {0}")]
+    NotFound(String),
+    #[error("invalid webhook URL {0:?}; expected an http:// or https:// URL")]
+    InvalidUrl(String),
+    #[error("unknown event {0:?}; expected one of {list}", list = EVENT_TYPES.join(", "))]
+    UnknownEvent(String),
+    #[error("descriptions are at most {MAX_DESCRIPTION_CHARS} characters")]
+    InvalidDescription,
+    #[error("secrets are at least 16 characters")]
+    WeakSecret,
+    #[error("webhook {0} is disabled")]
+    Disabled(String),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+impl From<WebhookError> for ApiError {
+    fn from(e: WebhookError) -> Self {
+        match e {
+            WebhookError::NotFound(_) => ApiError::not_found(e.to_string()),
+            WebhookError::Disabled(_) => ApiError::new(StatusCode::CONFLICT, e.to_string()),
+            WebhookError::Storage(e) => e.into(),
+            _ => ApiError::bad_request(e.to_string()),
+        }
+    }
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("phoenix-webhooks/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// `sha256=` and the hex HMAC of `<timestamp>.<body>`.
+fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut ctx = hmac::Context::with_key(&key);
+    ctx.update(timestamp.to_string().as_bytes());
+    ctx.update(b".");
+    ctx.update(body);
+    ctx.sign()
+        .as_ref()
+        .iter()
+        .fold(String::from("sha256="), |mut out, b| {
+            let _ = write!(out, "{b:02x}");
+            out
+        })
+}
+
+fn wants(webhook: &WebhookRow, event: &str) -> bool {
+    webhook.enabled && (webhook.events.is_empty() || webhook.events.iter().any(|e| e == event))
+}
+
+/// Whether a failed attempt is worth repeating.
+fn retryable(status: Option<u16>) -> bool {
+    status.is_none_or(|status| status >= 500 || status == 408 || status == 429)
+}
+
+fn backoff(attempt: u32) -> Duration {
+    FIRST_RETRY * 2u32.pow(attempt.saturating_sub(1))
+}
+
+/// Deliver `data` as `event` to one webhook in the background, retrying as needed. Returns the
+/// delivery id.
+fn deliver(storage: Storage, webhook: WebhookRow, event: &str, data: Value) -> String {
+    let delivery_id = uuid::Uuid::new_v4().to_string();
+    let timestamp = Utc::now().timestamp();
+    let body = json!({
+        "id": delivery_id,
+        "event": event,
+        "timestamp": timestamp,
+        "data": data,
+    })
+    .to_string();
+    let signature = signature(&webhook.secret, timestamp, body.as_bytes());
+    let event = event.to_string();
+    let id = delivery_id.clone();
+
+    tokio::spawn(async move {
+        for attempt in 1..=MAX_ATTEMPTS {
+            let started = Instant::now();
+            let result = client()
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Phoenix-Event", &event)
+                .header("X-Phoenix-Delivery", &id)
+                .header("X-Phoenix-Timestamp", timestamp)
+                .header("X-Phoenix-Signature", &signature)
+                .body(body.clone())
+                .send()
+                .await;
+            let (status, error) = match result {
+                Ok(resp) if resp.status().is_success() => (Some(resp.status().as_u16()), None),
+                Ok(resp) => (
+                    Some(resp.status().as_u16()),
+                    Some(format!("HTTP {}", resp.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            let row = DeliveryRow {
+                delivery_id: id.clone(),
+                webhook_id: webhook.id.clone(),
+                event: event.clone(),
+                attempt,
+                ts_unix: Utc::now().timestamp(),
+                status,
+                error: error.clone(),
+                duration_ms: started.elapsed().as_millis() as u64,
+            };
+            if let Err(e) = storage.log_webhook_delivery(&row) {
+                warn!(target: "webhooks", "logging a delivery failed: {e}");
+            }
+
+            let Some(error) = error else {
+                return;
+            };
+            if !retryable(status) || attempt == MAX_ATTEMPTS {
+                warn!(target: "webhooks", webhook = %webhook.id, %event, attempt, "delivery failed: {error}");
+                return;
+            }
+            tokio::time::sleep(backoff(attempt)).await;
+            // Stop retrying if the webhook was removed or disabled meanwhile.
+            match storage.webhook(&webhook.id) {
+                Ok(Some(current)) if current.enabled => {}
+                _ => return,
+            }
+        }
+    });
+    delivery_id
+}
+
+/// Send one event to every webhook that wants it.
+fn dispatch(storage: &Storage, event: &str, data: Value) {
+    let webhooks = match storage.webhooks() {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            warn!(target: "webhooks", "reading webhooks failed: {e}");
+            return;
+        }
+    };
+    for webhook in webhooks.into_iter().filter(|w| wants(w, event)) {
+        deliver(storage.clone(), webhook, event, data.clone());
+    }
+}
+
+/// Forward live events and proactive messages to the registered webhooks until shutdown.
+pub fn spawn(state: &AppState) -> JoinHandle<()> {
+    let storage = state.storage.clone();
+    let mut live = state.live.subscribe();
+    let mut schedule = state.proactive_tx.subscribe();
+    let mut closing = state.live.closing();
+    tokio::spawn(async move {
+        loop {
+            let (event, data) = tokio::select! {
+                event = live.recv() => match event {
+                    Ok(event) => match events::from_live(event) {
+                        Some(found) => found,
+                        None => continue,
+                    },
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!(target: "webhooks", missed, "webhook dispatch fell behind; events dropped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+                message = schedule.recv() => match message {
+                    Ok(message) => match serde_json::to_value(message) {
+                        Ok(data) => ("schedule", data),
+                        Err(_) => continue,
+                    },
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = closing.wait_for(|closing| *closing) => return,
+            };
+            dispatch(&storage, event, data);
+        }
+    })
+}
+
+fn valid_url(url: &str) -> Result<String, WebhookError> {
+    let url = url.trim();
+    match reqwest::Url::parse(url) {
+        Ok(parsed)
+            if matches!(parsed.scheme(), "http" | "https")
+                && parsed.host().is_some()
+                && url.len() <= MAX_URL_CHARS =>
+        {
+            Ok(url.to_string())
+        }
+        _ => Err(WebhookError::InvalidUrl(url.to_string())),
+    }
+}
+
+fn valid_events(events: Vec<String>) -> Result<Vec<String>, WebhookError> {
+    let mut valid: Vec<String> = Vec::new();
+    for event in events {
+        let event = event.trim();
+        if !EVENT_TYPES.contains(&event) {
+            return Err(WebhookError::UnknownEvent(event.to_string()));
+        }
+        if !valid.iter().any(|e| e == event) {
+            valid.push(event.to_string());
+        }
+    }
+    Ok(valid)
+}
+
+fn valid_description(description: String) -> Result<String, WebhookError> {
+    let description = description.trim();
+    if description.chars().count() > MAX_DESCRIPTION_CHARS {
+        return Err(WebhookError::InvalidDescription);
+    }
+    Ok(description.to_string())
+}
+
+/// A webhook as the API shows it; the secret is never included.
+fn webhook_json(webhook: &WebhookRow) -> Value {
+    json!({
+        "id": webhook.id,
+        "url": webhook.url,
+        "events": webhook.events,
+        "description": webhook.description,
+        "enabled": webhook.enabled,
+        "created_unix": webhook.created_unix,
+    })
+}
+
+fn find(storage: &Storage, id: &str) -> Result<WebhookRow, WebhookError> {
+    storage
+        .webhook(id)?
+        .ok_or_else(|| WebhookError::NotFound(id.to_string()))
+}
+
+async fn get_webhooks(state: web::Data<AppState>) -> Result<HttpResponse, ApiError> {
+    let webhooks: Vec<Value> = state.storage.webhooks()?.iter().map(webhook_json).collect();
+    Ok(HttpResponse::Ok().json(json!({ "webhooks": webhooks, "events": EVENT_TYPES })))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateBody {
+    url: String,
+    #[serde(default)]
+    events: Vec<String>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+async fn post_webhook(
+    state: web::Data<AppState>,
+    body: web::Json<CreateBody>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let secret = match body.secret {
+        Some(secret) if secret.chars().count() < 16 => return Err(WebhookError::WeakSecret.into()),
+        Some(secret) => secret,
+        None => format!("whsec_{}", uuid::Uuid::new_v4().simple()),
+    };
+    let webhook = WebhookRow {
+        id: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+        url: valid_url(&body.url)?,
+        secret,
+        events: valid_events(body.events)?,
+        description: valid_description(body.description)?,
+        enabled: true,
+        created_unix: Utc::now().timestamp(),
+    };
+    state.storage.insert_webhook(&webhook)?;
+    info!(target: "webhooks", webhook = %webhook.id, url = %webhook.url, "webhook registered");
+    let mut created = webhook_json(&webhook);
+    created["secret"] = json!(webhook.secret);
+    Ok(HttpResponse::Created().json(created))
+}
+
+async fn get_webhook(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(webhook_json(&find(&state.storage, &id)?)))
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateBody {
+    url: Option<String>,
+    events: Option<Vec<String>>,
+    description: Option<String>,
+    enabled: Option<bool>,
+}
+
+async fn patch_webhook(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    body: web::Json<UpdateBody>,
+) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+    let mut webhook = find(&state.storage, &id)?;
+    if let Some(url) = body.url {
+        webhook.url = valid_url(&url)?;
+    }
+    if let Some(events) = body.events {
+        webhook.events = valid_events(events)?;
+    }
+    if let Some(description) = body.description {
+        webhook.description = valid_description(description)?;
+    }
+    if let Some(enabled) = body.enabled {
+        webhook.enabled = enabled;
+    }
+    if !state.storage.update_webhook(&webhook)? {
+        return Err(WebhookError::NotFound(webhook.id).into());
+    }
+    Ok(HttpResponse::Ok().json(webhook_json(&webhook)))
+}
+
+async fn delete_webhook(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    if !state.storage.delete_webhook(&id)? {
+        return Err(WebhookError::NotFound(id.into_inner()).into());
+    }
+    info!(target: "webhooks", webhook = %id, "webhook removed");
+    Ok(HttpResponse::Ok().json(json!({ "status": "removed", "id": id.into_inner() })))
+}
+
+async fn post_test(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let webhook = find(&state.storage, &id)?;
+    if !webhook.enabled {
+        return Err(WebhookError::Disabled(webhook.id).into());
+    }
+    let data = json!({ "message": "Webhook test from Phoenix", "webhook_id": webhook.id });
+    let delivery_id = deliver(state.storage.clone(), webhook, PING_EVENT, data);
+    Ok(HttpResponse::Accepted().json(json!({ "status": "queued", "delivery_id": delivery_id })))
+}
+
+#[derive(Debug, Deserialize)]
+struct DeliveriesQuery {
+    limit: Option<usize>,
+}
+
+async fn get_deliveries(
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    query: web::Query<DeliveriesQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let webhook = find(&state.storage, &id)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LOG_LIMIT)
+        .clamp(1, MAX_LOG_LIMIT);
+    let deliveries: Vec<Value> = state
+        .storage
+        .webhook_deliveries(&webhook.id, limit)?
+        .into_iter()
+        .map(|d| {
+            json!({
+                "delivery_id": d.delivery_id,
+                "event": d.event,
+                "attempt": d.attempt,
+                "ts_unix": d.ts_unix,
+                "status": d.status,
+                "ok": d.error.is_none(),
+                "error": d.error,
+                "duration_ms": d.duration_ms,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(json!({ "webhook_id": webhook.id, "deliveries": deliveries })))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Registered under the main `/api` scope.
+    cfg.service(
+        web::scope("/webhooks")
+            .route("", web::get().to(get_webhooks))
+            .route("", web::post().to(post_webhook))
+            .route("/{id}", web::get().to(get_webhook))
+            .route("/{id}", web::patch().to(patch_webhook))
+            .route("/{id}", web::delete().to(delete_webhook))
+            .route("/{id}/test", web::post().to(post_test))
+            .route("/{id}/deliveries", web::get().to(get_deliveries)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook(events: &[&str], enabled: bool) -> WebhookRow {
+        WebhookRow {
+            id: "h".to_string(),
+            url: "http://localhost/hook".to_string(),
+            secret: "secret".to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            description: String::new(),
+            enabled,
+            created_unix: 0,
+        }
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        // HMAC-SHA256("secret", "1700000000.{}")
+        let expected = {
+            let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+            let tag = hmac::sign(&key, b"1700000000.{}");
+            let hex: String = tag.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+            format!("sha256={hex}")
+        };
+        assert_eq!(signature("secret", 1_700_000_000, b"{}"), expected);
+        assert_ne!(signature("other", 1_700_000_000, b"{}"), expected);
+    }
+
+    #[test]
+    fn filters_retries_and_validation() {
+        assert!(wants(&webhook(&[], true), "drift_alert"));
+        assert!(!wants(&webhook(&[], false), "drift_alert"));
+        assert!(!wants(&webhook(&["emotion_alert"], true), "drift_alert"));
+
+        assert!(retryable(None));
+        assert!(retryable(Some(502)));
+        assert!(retryable(Some(429)));
+        assert!(!retryable(Some(404)));
+        assert_eq!(backoff(3), Duration::from_secs(40));
+
+        assert!(valid_url("https://ha.local:8123/api/webhook/x").is_ok());
+        assert!(valid_url("ftp://example.com").is_err());
+        assert!(valid_url("not a url").is_err());
+        assert_eq!(
+            valid_events(vec!["drift_alert".into(), "drift_alert".into()]).unwrap(),
+            ["drift_alert"]
+        );
+        assert!(valid_events(vec!["recording".into()]).is_err());
+    }
+}
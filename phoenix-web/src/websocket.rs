@@ -1,19 +1,25 @@
 // phoenix-web/src/websocket.rs
 // WebSocket handler for real-time bi-directional communication
 
+use actix_web::http::Method;
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_ws::{Message, ProtocolError};
 use futures_util::StreamExt as _;
 use llm_orchestrator::ModelTier;
+use multi_modal_recording::emotion_history::EmotionUpdate;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use crate::api_keys::Scope;
+use crate::ghost_api::{self, SimulateBody};
+use crate::ghost_engine::{self, SimulateResponse};
+use crate::rate_limit::{RateLimiter, Tier};
 use crate::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -92,12 +98,114 @@ pub enum WebSocketMessage {
         #[serde(skip_serializing_if = "Option::is_none")]
         k: Option<usize>,
     },
+    /// Run a Relational Ghost simulation; the same body as `POST /api/ghost/simulate`.
+    /// Subscribers to "ghost" also receive each turn as it is published.
+    #[serde(rename = "ghost_simulate")]
+    GhostSimulate {
+        #[serde(flatten)]
+        body: SimulateBody,
+    },
+    /// Opt into server-pushed events. Supported topics: see [`TOPICS`].
+    #[serde(rename = "subscribe")]
+    Subscribe { topic: String },
+    #[serde(rename = "unsubscribe")]
+    Unsubscribe { topic: String },
     #[serde(rename = "status")]
     Status,
     #[serde(rename = "ping")]
     Ping,
 }
 
+impl WebSocketMessage {
+    /// The REST route doing the same thing; a message needs the scope that route needs.
+    fn rest_equivalent(&self) -> (Method, &'static str) {
+        match self {
+            WebSocketMessage::Speak { .. } => (Method::POST, "/api/speak"),
+            // Granting consent only unlocks commands.
+            WebSocketMessage::Command { .. } | WebSocketMessage::System { .. } => {
+                (Method::POST, "/api/command")
+            }
+            WebSocketMessage::MemorySearch { .. } => (Method::GET, "/api/memory/search"),
+            WebSocketMessage::MemoryStore { .. } => (Method::POST, "/api/memory/store"),
+            WebSocketMessage::MemoryGet { .. } => (Method::GET, "/api/memory/get"),
+            WebSocketMessage::MemoryDelete { .. } => (Method::DELETE, "/api/memory/delete"),
+            WebSocketMessage::MemoryCortexStore { .. } => (Method::POST, "/api/memory/cortex"),
+            WebSocketMessage::MemoryCortexGet { .. }
+            | WebSocketMessage::MemoryCortexSearch { .. } => (Method::GET, "/api/memory/cortex"),
+            WebSocketMessage::MemoryVectorStore { .. } => {
+                (Method::POST, "/api/memory/vector/store")
+            }
+            WebSocketMessage::MemoryVectorSearch { .. } => {
+                (Method::GET, "/api/memory/vector/search")
+            }
+            WebSocketMessage::GhostSimulate { .. } => (Method::POST, "/api/ghost/simulate"),
+            WebSocketMessage::Subscribe { topic } => match topic.as_str() {
+                "recording" => (Method::GET, "/api/recorder/status"),
+                "presence" => (Method::GET, "/api/presence/status"),
+                _ => (Method::GET, "/api/status"),
+            },
+            WebSocketMessage::Unsubscribe { .. }
+            | WebSocketMessage::Status
+            | WebSocketMessage::Ping => (Method::GET, "/api/status"),
+        }
+    }
+
+    /// The scope needed to send this message, by the same rules as the REST routes.
+    pub fn required_scope(&self) -> Scope {
+        let (method, path) = self.rest_equivalent();
+        Scope::required_for(&method, path)
+    }
+
+    /// The rate-limit tier of the REST route; granting consent is free.
+    fn tier(&self) -> Tier {
+        match self {
+            WebSocketMessage::System { .. } => Tier::Cheap,
+            _ => Tier::for_path(self.rest_equivalent().1),
+        }
+    }
+}
+
+/// The error to send instead of handling `text` when the connection's credentials don't cover
+/// it. Messages that don't parse are left to the handlers to report.
+fn refusal(granted: &[Scope], text: &str) -> Option<WebSocketResponse> {
+    let required = serde_json::from_str::<WebSocketMessage>(text)
+        .ok()?
+        .required_scope();
+    if granted.iter().any(|scope| scope.grants(required)) {
+        return None;
+    }
+    Some(WebSocketResponse::Error {
+        message: format!("this connection lacks the `{}` scope", required.as_str()),
+        code: Some("forbidden".to_string()),
+    })
+}
+
+/// The error to send instead of handling `text` when it is an expensive message and `client` has
+/// spent its expensive budget, which its HTTP requests draw from too. Cheap messages aren't
+/// counted.
+fn rate_limited(limiter: &RateLimiter, client: &str, text: &str) -> Option<WebSocketResponse> {
+    let message = serde_json::from_str::<WebSocketMessage>(text).ok()?;
+    if message.tier() != Tier::Expensive {
+        return None;
+    }
+    let wait = limiter
+        .check(Tier::Expensive, client, Instant::now())
+        .err()?;
+    Some(WebSocketResponse::Error {
+        message: format!(
+            "rate limit exceeded; retry in {}s",
+            crate::rate_limit::retry_after_secs(wait)
+        ),
+        code: Some("rate_limited".to_string()),
+    })
+}
+
+/// Topics a connection can subscribe to. Everything but "emotion" arrives via
+/// [`crate::live_events`].
+const TOPICS: &[&str] = &[
+    "emotion", "recording", "stress", "ghost", "alerts", "presence", "config", "plugins",
+];
+
 #[derive(Debug, Serialize)]
 #[serde(tag = "type")]
 pub enum WebSocketResponse {
@@ -182,6 +290,19 @@ pub enum WebSocketResponse {
         reason: String,
         timestamp: i64,
     },
+    #[serde(rename = "ghost_simulate_response")]
+    GhostSimulateResponse {
+        #[serde(flatten)]
+        result: Box<SimulateResponse>,
+    },
+    #[serde(rename = "subscription_response")]
+    SubscriptionResponse { topic: String, subscribed: bool },
+    /// Pushed to connections subscribed to the "emotion" topic.
+    #[serde(rename = "emotion_update")]
+    EmotionUpdate {
+        #[serde(flatten)]
+        update: EmotionUpdate,
+    },
     #[serde(rename = "error")]
     Error {
         message: String,
@@ -197,6 +318,10 @@ pub async fn websocket_handler(
     body: web::Payload,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
+    let actor = crate::audit::Actor::of(&req);
+    // Checked per message: the upgrade itself only needs `read`.
+    let granted = crate::api_keys::granted_scopes(&req);
+    let client = crate::rate_limit::client_id(&req);
     let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
 
     let conn_id = Uuid::new_v4().to_string();
@@ -213,8 +338,13 @@ pub async fn websocket_handler(
         m.insert(conn_id.clone(), false);
     }
 
+    // Per-connection event topics (default: none).
+    let topics: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+
     // Subscribe to proactive messages
     let mut proactive_rx = state.proactive_tx.subscribe();
+    let mut emotion_rx = state.emotion_tx.subscribe();
+    let mut live_rx = state.live.subscribe();
 
     // Spawn task to handle WebSocket connection
     let access_map_task = access_map.clone();
@@ -260,10 +390,44 @@ pub async fn websocket_handler(
                         break;
                     }
                 }
+                Ok(update) = emotion_rx.recv() => {
+                    if !topics.lock().await.contains("emotion") {
+                        continue;
+                    }
+                    let response_json = serde_json::to_string(&WebSocketResponse::EmotionUpdate { update })
+                        .unwrap_or_else(|_| json!({"type": "error", "message": "Serialization failed"}).to_string());
+                    if let Err(e) = session.text(response_json).await {
+                        error!("Failed to send emotion update: {}", e);
+                        break;
+                    }
+                }
+                Ok(event) = live_rx.recv() => {
+                    if !topics.lock().await.contains(event.topic()) {
+                        continue;
+                    }
+                    let response_json = serde_json::to_string(&event)
+                        .unwrap_or_else(|_| json!({"type": "error", "message": "Serialization failed"}).to_string());
+                    if let Err(e) = session.text(response_json).await {
+                        error!("Failed to send {} event: {}", event.topic(), e);
+                        break;
+                    }
+                }
                 msg = msg_stream.next() => {
                     let Some(msg) = msg else { break; };
                     match msg {
                         Ok(Message::Text(text)) => {
+                            let refused = refusal(&granted, &text)
+                                .or_else(|| rate_limited(&state.rate_limiter, &client, &text));
+                            if let Some(refusal) = refused {
+                                let refusal_json = serde_json::to_string(&refusal)
+                                    .unwrap_or_else(|_| json!({"type": "error", "message": "Serialization failed"}).to_string());
+                                if let Err(e) = session.text(refusal_json).await {
+                                    error!("Failed to send WebSocket message: {}", e);
+                                    break;
+                                }
+                                continue;
+                            }
+
                             // Phase 3: token-by-token streaming for `speak`.
                             // We keep the legacy `speak_response` (sent after streaming completes)
                             // as a compatibility fallback for older clients.
@@ -291,7 +455,7 @@ pub async fn websocket_handler(
                                 continue;
                             }
 
-                            match handle_message(&text, &state, &peer, &conn_id, &access_map_task).await {
+                            match handle_message(&text, &state, &actor, &peer, &conn_id, &access_map_task, &topics).await {
                                 Ok(response) => {
                                     let response_json = serde_json::to_string(&response)
                                         .unwrap_or_else(|_| json!({"type": "error", "message": "Serialization failed"}).to_string());
@@ -381,7 +545,7 @@ async fn handle_speak_streaming(
     let _ = state.vaults.store_soul("last_user_message", &user_input);
 
     // Avoid holding the mutex across `.await`.
-    let llm = state.llm.lock().await.clone();
+    let llm = state.llm().await;
     let Some(llm) = llm.as_ref() else {
         let payload = json!({
             "type": "speak_response_chunk",
@@ -777,9 +941,11 @@ async fn handle_speak_streaming(
 async fn handle_message(
     text: &str,
     state: &web::Data<AppState>,
+    actor: &crate::audit::Actor,
     peer: &str,
     conn_id: &str,
     access_map: &Arc<Mutex<HashMap<String, bool>>>,
+    topics: &Arc<Mutex<HashSet<String>>>,
 ) -> Result<WebSocketResponse, Box<dyn std::error::Error>> {
     let msg: WebSocketMessage = serde_json::from_str(text)?;
 
@@ -804,6 +970,9 @@ async fn handle_message(
             WebSocketMessage::MemoryCortexSearch { .. } => "memory_cortex_search",
             WebSocketMessage::MemoryVectorStore { .. } => "memory_vector_store",
             WebSocketMessage::MemoryVectorSearch { .. } => "memory_vector_search",
+            WebSocketMessage::GhostSimulate { .. } => "ghost_simulate",
+            WebSocketMessage::Subscribe { .. } => "subscribe",
+            WebSocketMessage::Unsubscribe { .. } => "unsubscribe",
             WebSocketMessage::Status => "status",
             WebSocketMessage::Ping => "ping",
         }
@@ -816,7 +985,7 @@ async fn handle_message(
             project_context,
         } => {
             // Avoid holding the mutex across `.await`.
-            let llm = state.llm.lock().await.clone();
+            let llm = state.llm().await;
             if let Some(llm) = llm.as_ref() {
                 // Parse mode string to ModelTier if provided, otherwise use None
                 let tier = mode.as_ref().and_then(|m| m.parse::<ModelTier>().ok());
@@ -902,7 +1071,7 @@ async fn handle_message(
 
             // NOTE: `project_context` is not a filesystem path; do not pass it as `cwd`.
             // We ignore it here by design.
-            let json = crate::command_to_response_json(state.as_ref(), &command).await;
+            let json = crate::command_to_response_json(state.as_ref(), actor, &command).await;
             let is_error = json
                 .get("type")
                 .and_then(|v| v.as_str())
@@ -1263,7 +1432,7 @@ async fn handle_message(
             }
         }
         WebSocketMessage::Status => {
-            let llm_status = if state.llm.lock().await.is_some() {
+            let llm_status = if state.llm().await.is_some() {
                 "online"
             } else {
                 "offline"
@@ -1275,6 +1444,95 @@ async fn handle_message(
                 version: state.version.clone(),
             })
         }
+        WebSocketMessage::GhostSimulate { body } => match ghost_api::validate(body) {
+            Ok(req) => Ok(WebSocketResponse::GhostSimulateResponse {
+                result: Box::new(ghost_engine::simulate(state, req).await),
+            }),
+            Err(errors) => Ok(WebSocketResponse::Error {
+                message: errors
+                    .iter()
+                    .map(|e| format!("{}: {}", e.field, e.message))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                code: Some("validation_failed".to_string()),
+            }),
+        },
+        WebSocketMessage::Subscribe { topic } | WebSocketMessage::Unsubscribe { topic }
+            if !TOPICS.contains(&topic.trim()) =>
+        {
+            Ok(WebSocketResponse::Error {
+                message: format!("Unknown topic: {topic}. Supported: {}", TOPICS.join(", ")),
+                code: Some("unknown_topic".to_string()),
+            })
+        }
+        WebSocketMessage::Subscribe { topic } => {
+            let topic = topic.trim().to_string();
+            topics.lock().await.insert(topic.clone());
+            Ok(WebSocketResponse::SubscriptionResponse {
+                topic,
+                subscribed: true,
+            })
+        }
+        WebSocketMessage::Unsubscribe { topic } => {
+            let topic = topic.trim().to_string();
+            topics.lock().await.remove(&topic);
+            Ok(WebSocketResponse::SubscriptionResponse {
+                topic,
+                subscribed: false,
+            })
+        }
         WebSocketMessage::Ping => Ok(WebSocketResponse::Pong),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_need_the_scope_of_their_rest_route() {
+        let read = [Scope::Read];
+        let store = r#"{"type":"memory_store","key":"k","value":"v"}"#;
+        let command = r#"{"type":"command","command":"system status"}"#;
+        let grant = r#"{"type":"system","action":"grant"}"#;
+        let speak = r#"{"type":"speak","user_input":"hi"}"#;
+        for text in [store, command, grant, speak] {
+            let refused = refusal(&read, text);
+            assert!(
+                matches!(&refused, Some(WebSocketResponse::Error { code: Some(code), .. }) if code == "forbidden"),
+                "{text}"
+            );
+            assert!(refusal(&[Scope::Admin], text).is_none(), "{text}");
+        }
+
+        let search = r#"{"type":"memory_search","query":"q"}"#;
+        let emotion = r#"{"type":"subscribe","topic":"emotion"}"#;
+        for text in [search, emotion, r#"{"type":"ping"}"#] {
+            assert!(refusal(&read, text).is_none(), "{text}");
+        }
+        let recording = r#"{"type":"subscribe","topic":"recording"}"#;
+        assert!(refusal(&read, recording).is_some());
+        assert!(refusal(&[Scope::Record], recording).is_none());
+        // Left for the handler to answer with a parse error.
+        assert!(refusal(&read, "not json").is_none());
+    }
+
+    #[test]
+    fn expensive_messages_draw_from_the_expensive_budget() {
+        let limiter = RateLimiter::new(0, 2);
+        let command = r#"{"type":"command","command":"system status"}"#;
+        let simulate = r#"{"type":"ghost_simulate","script":"s","intensity_level":1}"#;
+        assert!(rate_limited(&limiter, "key:k", command).is_none());
+        assert!(rate_limited(&limiter, "key:k", simulate).is_none());
+        let limited = rate_limited(&limiter, "key:k", command);
+        assert!(
+            matches!(&limited, Some(WebSocketResponse::Error { code: Some(code), .. }) if code == "rate_limited")
+        );
+        // Another client has its own budget; cheap messages and consent aren't counted.
+        assert!(rate_limited(&limiter, "key:other", command).is_none());
+        let grant = r#"{"type":"system","action":"grant"}"#;
+        for text in [r#"{"type":"ping"}"#, grant, "not json"] {
+            assert!(rate_limited(&limiter, "key:k", text).is_none(), "{text}");
+        }
+    }
+}
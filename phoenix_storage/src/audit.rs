@@ -0,0 +1,168 @@
+//! Append-only audit log of privacy-relevant actions: who did what, when, with which
+//! parameters. Rows can't be changed or deleted through SQL (triggers refuse it); only
+//! restoring an older backup takes entries away.
+
+use crate::sql::{values, Row};
+use crate::{Result, Storage};
+
+/// Entries returned by [`Storage::audit_log`] when the query doesn't say.
+pub const DEFAULT_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub ts_unix: i64,
+    /// Who asked, e.g. `key:<name>`, `session:<id>`, `local`.
+    pub actor: String,
+    /// The active profile at the time, if known.
+    pub profile: Option<String>,
+    /// Dotted name such as `recording.start` or `profile.delete`.
+    pub action: String,
+    /// JSON object; the storage layer doesn't look inside.
+    pub params: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRow {
+    pub id: i64,
+    pub entry: AuditEntry,
+}
+
+/// Filters for [`Storage::audit_log`]; every field is optional.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    /// An exact action, or a prefix up to a dot: `recording` matches `recording.start`.
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub profile: Option<String>,
+    pub from_unix: Option<i64>,
+    pub to_unix: Option<i64>,
+    /// Only entries older than this id, for paging backwards.
+    pub before_id: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+fn audit_row(row: &Row) -> Result<AuditRow> {
+    Ok(AuditRow {
+        id: row.get(0)?,
+        entry: AuditEntry {
+            ts_unix: row.get(1)?,
+            actor: row.get(2)?,
+            profile: row.get(3)?,
+            action: row.get(4)?,
+            params: row.get(5)?,
+        },
+    })
+}
+
+impl Storage {
+    /// Append one entry; returns its id.
+    pub fn append_audit(&self, entry: &AuditEntry) -> Result<i64> {
+        self.write(|tx| {
+            tx.query_value(
+                "INSERT INTO audit_log (ts_unix, actor, profile, action, params)
+                 VALUES (?1, ?2, ?3, ?4, ?5) RETURNING id",
+                values![
+                    entry.ts_unix,
+                    &entry.actor,
+                    &entry.profile,
+                    &entry.action,
+                    &entry.params
+                ],
+            )
+            .map(Option::unwrap_or_default)
+        })
+    }
+
+    /// Matching entries, newest first.
+    pub fn audit_log(&self, query: &AuditQuery) -> Result<Vec<AuditRow>> {
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT) as i64;
+        self.read(|sql| {
+            sql.query(
+                "SELECT id, ts_unix, actor, profile, action, params FROM audit_log
+                 WHERE (?1 IS NULL OR action = ?1 OR action LIKE ?1 || '.%')
+                 AND (?2 IS NULL OR actor = ?2)
+                 AND (?3 IS NULL OR profile = ?3 COLLATE NOCASE)
+                 AND (?4 IS NULL OR ts_unix >= ?4)
+                 AND (?5 IS NULL OR ts_unix <= ?5)
+                 AND (?6 IS NULL OR id < ?6)
+                 ORDER BY id DESC LIMIT ?7",
+                values![
+                    &query.action,
+                    &query.actor,
+                    &query.profile,
+                    query.from_unix,
+                    query.to_unix,
+                    query.before_id,
+                    limit
+                ],
+            )?
+            .iter()
+            .map(audit_row)
+            .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+
+    fn entry(ts_unix: i64, actor: &str, action: &str) -> AuditEntry {
+        AuditEntry {
+            ts_unix,
+            actor: actor.to_string(),
+            profile: Some("default".to_string()),
+            action: action.to_string(),
+            params: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn entries_filter_page_and_cannot_be_rewritten() {
+        for temp in TempStorage::each_backend() {
+            let storage = &temp.storage;
+            storage
+                .append_audit(&entry(10, "local", "recording.start"))
+                .unwrap();
+            storage
+                .append_audit(&entry(20, "key:laptop", "recording.stop"))
+                .unwrap();
+            let last = storage
+                .append_audit(&entry(30, "local", "recordings.purge"))
+                .unwrap();
+
+            let all = storage.audit_log(&AuditQuery::default()).unwrap();
+            assert_eq!(all.len(), 3);
+            assert_eq!(all[0].id, last);
+
+            let recording = storage
+                .audit_log(&AuditQuery {
+                    action: Some("recording".to_string()),
+                    ..AuditQuery::default()
+                })
+                .unwrap();
+            let actions: Vec<_> = recording.iter().map(|r| r.entry.action.as_str()).collect();
+            assert_eq!(actions, ["recording.stop", "recording.start"]);
+
+            let page = storage
+                .audit_log(&AuditQuery {
+                    actor: Some("local".to_string()),
+                    before_id: Some(last),
+                    from_unix: Some(5),
+                    limit: Some(1),
+                    ..AuditQuery::default()
+                })
+                .unwrap();
+            assert_eq!(page[0].entry, entry(10, "local", "recording.start"));
+
+            assert!(storage
+                .write(|tx| tx.execute("DELETE FROM audit_log", &[]))
+                .is_err());
+            assert!(storage
+                .write(|tx| tx.execute("UPDATE audit_log SET actor = 'someone else'", &[]))
+                .is_err());
+            assert_eq!(storage.audit_log(&AuditQuery::default()).unwrap().len(), 3);
+        }
+    }
+}
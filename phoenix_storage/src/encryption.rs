@@ -0,0 +1,190 @@
+//! Whole-file database encryption with SQLCipher, in builds with the `sqlcipher` feature.
+//!
+//! Every connection to an encrypted database is keyed with the same raw 256-bit key before its
+//! first statement. Copies made from a keyed connection (`VACUUM INTO`, so pre-migration
+//! backups and snapshots) are encrypted with the same key. A database left in the clear from
+//! before encryption was turned on is encrypted in place the first time it is opened with a
+//! key, pre-migration backups included. Builds without the feature refuse a key rather than
+//! write the data in the clear.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, DatabaseName};
+
+use crate::{Result, StorageError};
+
+/// A raw SQLCipher key.
+pub type DatabaseKey = [u8; 32];
+
+/// The first bytes of every unencrypted SQLite file.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Whether this build can encrypt databases.
+pub const AVAILABLE: bool = cfg!(feature = "sqlcipher");
+
+/// Whether `path` holds an unencrypted database; `false` when it is missing or empty.
+pub fn is_plain(path: &Path) -> Result<bool> {
+    use std::io::Read;
+    let mut header = [0u8; SQLITE_HEADER.len()];
+    match std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => Ok(&header == SQLITE_HEADER),
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::UnexpectedEof
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// SQLCipher's form of a raw key, `x'<hex>'`.
+fn key_literal(key: &DatabaseKey) -> zeroize::Zeroizing<String> {
+    use std::fmt::Write;
+    let mut literal = zeroize::Zeroizing::new(String::with_capacity(3 + key.len() * 2));
+    literal.push_str("x'");
+    for b in key {
+        let _ = write!(literal, "{b:02X}");
+    }
+    literal.push('\'');
+    literal
+}
+
+/// Key a fresh connection (a no-op without a key) and make sure it can read the database.
+pub(crate) fn apply(conn: &Connection, key: Option<&DatabaseKey>) -> Result<()> {
+    if let Some(key) = key {
+        if !AVAILABLE {
+            return Err(StorageError::EncryptionUnavailable);
+        }
+        conn.pragma_update(None, "key", key_literal(key).as_str())?;
+    }
+    // A wrong key, or none for an encrypted file, only shows on the first read.
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(())) {
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::NotADatabase =>
+        {
+            Err(match key {
+                Some(_) => StorageError::WrongKey,
+                None => StorageError::Encrypted,
+            })
+        }
+        other => Ok(other?),
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Encrypt the database at `path` in place if it is in the clear. Returns whether it was.
+pub(crate) fn encrypt_if_plain(path: &Path, key: &DatabaseKey) -> Result<bool> {
+    if !is_plain(path)? {
+        return Ok(false);
+    }
+    if !AVAILABLE {
+        return Err(StorageError::EncryptionUnavailable);
+    }
+    let encrypted = with_suffix(path, ".encrypting");
+    let _ = std::fs::remove_file(&encrypted);
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(crate::BUSY_TIMEOUT)?;
+    // The export reads through this connection, so it includes anything still in the WAL.
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let export = || -> rusqlite::Result<()> {
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            [
+                encrypted.to_string_lossy().as_ref(),
+                key_literal(key).as_str(),
+            ],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.pragma_update(
+            Some(DatabaseName::Attached("encrypted")),
+            "user_version",
+            version,
+        )?;
+        conn.execute_batch("DETACH DATABASE encrypted")
+    };
+    if let Err(e) = export() {
+        drop(conn);
+        let _ = std::fs::remove_file(&encrypted);
+        return Err(e.into());
+    }
+    drop(conn);
+    for sidecar in ["-wal", "-shm"] {
+        match std::fs::remove_file(with_suffix(path, sidecar)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    std::fs::rename(&encrypted, path)?;
+    Ok(true)
+}
+
+#[cfg(all(test, feature = "sqlcipher"))]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+    use crate::Storage;
+
+    const KEY: DatabaseKey = [7; 32];
+
+    #[test]
+    fn encrypts_a_plain_database_and_its_backups_in_place() {
+        let temp = TempStorage::new();
+        let path = temp.storage.path().unwrap().with_file_name("plain.db");
+        let plain = Storage::open_sqlite(&path, 1).unwrap();
+        plain
+            .write(|tx| {
+                tx.execute(
+                    "INSERT INTO ghost_sessions (id, started_unix, load_start) VALUES ('g', 1, 2)",
+                    [],
+                )
+            })
+            .unwrap();
+        plain
+            .snapshot_to(&path.with_file_name("plain.db.v1-1.bak"))
+            .unwrap();
+        drop(plain);
+        assert!(is_plain(&path).unwrap());
+
+        let encrypted = Storage::open_sqlite_with_key(&path, 1, Some(&KEY)).unwrap();
+        assert_eq!(encrypted.ghost_sessions_since(0, None).unwrap().len(), 1);
+        drop(encrypted);
+        assert!(!is_plain(&path).unwrap());
+        assert!(!is_plain(&path.with_file_name("plain.db.v1-1.bak")).unwrap());
+
+        assert!(matches!(
+            Storage::open_sqlite(&path, 1),
+            Err(StorageError::Encrypted)
+        ));
+        assert!(matches!(
+            Storage::open_sqlite_with_key(&path, 1, Some(&[8; 32])),
+            Err(StorageError::WrongKey)
+        ));
+        assert_eq!(
+            crate::migrations::check_file(&path, Some(&KEY)).unwrap(),
+            crate::migrations::SCHEMA_VERSION
+        );
+    }
+
+    #[test]
+    fn snapshots_stay_encrypted() {
+        let temp = TempStorage::new();
+        let path = temp.storage.path().unwrap().with_file_name("secret.db");
+        let storage = Storage::open_sqlite_with_key(&path, 1, Some(&KEY)).unwrap();
+        let snapshot = path.with_file_name("snapshot.db");
+        storage.snapshot_to(&snapshot).unwrap();
+        assert!(!is_plain(&snapshot).unwrap());
+        assert!(crate::migrations::check_file(&snapshot, Some(&KEY)).is_ok());
+        assert!(matches!(
+            crate::migrations::check_file(&snapshot, None),
+            Err(StorageError::Encrypted)
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! Relational Ghost sessions: the system load when a simulation started and when it ended, for
+//! drift analysis, kept per profile.
+
+use crate::sql::values;
+use crate::{Result, Storage};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GhostSessionRow {
+    pub id: String,
+    pub profile: String,
+    pub started_unix: i64,
+    pub load_start: u8,
+    pub ended_unix: Option<i64>,
+    pub load_end: Option<u8>,
+}
+
+impl Storage {
+    pub fn start_ghost_session(
+        &self,
+        id: &str,
+        profile: &str,
+        started_unix: i64,
+        load_start: u8,
+    ) -> Result<()> {
+        self.write(|tx| {
+            tx.execute(
+                "INSERT INTO ghost_sessions (id, profile, started_unix, load_start)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (id) DO UPDATE SET profile = excluded.profile,
+                 started_unix = excluded.started_unix, load_start = excluded.load_start,
+                 ended_unix = NULL, load_end = NULL",
+                values![id, profile, started_unix, load_start],
+            )
+            .map(|_| ())
+        })
+    }
+
+    /// Record the end of session `id`; returns its start load, or `None` if the session is
+    /// unknown or already ended.
+    pub fn end_ghost_session(&self, id: &str, ended_unix: i64, load_end: u8) -> Result<Option<u8>> {
+        self.write(|tx| {
+            let start: Option<u8> = tx.query_value(
+                "SELECT load_start FROM ghost_sessions WHERE id = ?1 AND ended_unix IS NULL",
+                values![id],
+            )?;
+            if start.is_some() {
+                tx.execute(
+                    "UPDATE ghost_sessions SET ended_unix = ?2, load_end = ?3 WHERE id = ?1",
+                    values![id, ended_unix, load_end],
+                )?;
+            }
+            Ok(start)
+        })
+    }
+
+    /// Sessions started at or after `since_unix`, oldest first; only `profile`'s when given.
+    pub fn ghost_sessions_since(
+        &self,
+        since_unix: i64,
+        profile: Option<&str>,
+    ) -> Result<Vec<GhostSessionRow>> {
+        self.read(|sql| {
+            sql.query(
+                "SELECT id, profile, started_unix, load_start, ended_unix, load_end
+                 FROM ghost_sessions
+                 WHERE started_unix >= ?1 AND (?2 IS NULL OR profile = ?2)
+                 ORDER BY started_unix, id",
+                values![since_unix, profile],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(GhostSessionRow {
+                    id: row.get(0)?,
+                    profile: row.get(1)?,
+                    started_unix: row.get(2)?,
+                    load_start: row.get(3)?,
+                    ended_unix: row.get(4)?,
+                    load_end: row.get(5)?,
+                })
+            })
+            .collect()
+        })
+    }
+
+    /// Delete sessions started before `cutoff_unix`; returns how many were removed.
+    pub fn prune_ghost_sessions(&self, cutoff_unix: i64) -> Result<usize> {
+        self.write(|tx| {
+            tx.execute(
+                "DELETE FROM ghost_sessions WHERE started_unix < ?1",
+                values![cutoff_unix],
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::TempStorage;
+
+    #[test]
+    fn sessions_end_once() {
+        for temp in TempStorage::each_backend() {
+            let storage = &temp.storage;
+            storage
+                .start_ghost_session("a", "default", 100, 20)
+                .unwrap();
+            storage.start_ghost_session("b", "alice", 200, 30).unwrap();
+
+            assert_eq!(storage.end_ghost_session("a", 110, 50).unwrap(), Some(20));
+            assert_eq!(storage.end_ghost_session("a", 120, 60).unwrap(), None);
+            assert_eq!(storage.end_ghost_session("zzz", 120, 60).unwrap(), None);
+
+            let all = storage.ghost_sessions_since(0, None).unwrap();
+            assert_eq!(all.len(), 2);
+            assert_eq!((all[0].ended_unix, all[0].load_end), (Some(110), Some(50)));
+            let alice = storage.ghost_sessions_since(0, Some("Alice")).unwrap();
+            assert_eq!(alice.len(), 1);
+            assert_eq!(alice[0].id, "b");
+
+            assert_eq!(storage.prune_ghost_sessions(150).unwrap(), 1);
+            assert_eq!(storage.ghost_sessions_since(0, None).unwrap()[0].id, "b");
+        }
+    }
+}
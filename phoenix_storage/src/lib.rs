@@ -0,0 +1,522 @@
+//! Shared SQL storage: user profiles, emotion history, the recordings index, web UI login
+//! sessions, Ghost session analytics, outbound webhooks and the audit log.
+//!
+//! The database is SQLite, given as `sqlite://<path>` or a plain path, or, in builds with the
+//! `postgres` feature, Postgres, given as a `postgres://` or `postgresql://` URL. The table
+//! modules write each statement once for both (see `sql`).
+//!
+//! SQLite runs in WAL mode with one writer connection and a pool of reader connections, so
+//! readers don't wait for each other or for a write; Postgres gets a pool of as many
+//! connections plus one. Any number of `Storage` clones share the same connections. The schema
+//! is created and upgraded on open (see [`migrations`]), and [`Storage::migration_plan`]
+//! reports what an upgrade would do without doing it. Opened with a key, an SQLite database is
+//! encrypted whole (see [`encryption`]); a Postgres one is left to the server, and the key only
+//! encrypts the SQLite snapshots taken of it for backups.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::encryption::DatabaseKey;
+use crate::sql::{Sql, Sqlite};
+
+pub mod audit;
+pub mod encryption;
+pub mod ghost_sessions;
+pub mod login_sessions;
+pub mod migrations;
+pub mod moments;
+#[cfg(feature = "postgres")]
+mod postgres;
+pub mod profiles;
+pub mod recordings;
+mod sql;
+#[cfg(feature = "postgres")]
+mod transfer;
+pub mod webhooks;
+
+/// Reader connections opened when the caller doesn't say.
+pub const DEFAULT_READERS: usize = 4;
+
+/// How long a connection waits for a lock held by another process before failing.
+pub(crate) const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[cfg(feature = "postgres")]
+    #[error("database error: {0}")]
+    Postgres(#[from] sqlx::Error),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid database URL {0:?}")]
+    InvalidUrl(String),
+
+    #[error("this build cannot use Postgres; rebuild with the postgres feature")]
+    PostgresUnavailable,
+
+    #[error("database column {index} does not hold a {expected}")]
+    ColumnType { index: usize, expected: &'static str },
+
+    #[error("database schema version {found} is newer than this build supports ({supported}); upgrade the app or restore a backup")]
+    SchemaTooNew { found: u32, supported: u32 },
+
+    #[error("database failed its integrity check, not migrating: {0}")]
+    Corrupt(String),
+
+    #[error("migration {version} ({name}) failed: {reason}")]
+    Migration {
+        version: u32,
+        name: &'static str,
+        reason: String,
+    },
+
+    #[error("the database is encrypted; it can only be opened with the data key")]
+    Encrypted,
+
+    #[error("the data key does not open this database")]
+    WrongKey,
+
+    #[error("this build cannot encrypt the database; rebuild with the sqlcipher feature")]
+    EncryptionUnavailable,
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+impl From<StorageError> for pagi_errors::PagiError {
+    fn from(e: StorageError) -> Self {
+        use pagi_errors::{ErrorCode, PagiError};
+        match e {
+            // These tell the user what to do about it; SQL and I/O failures don't.
+            StorageError::InvalidUrl(_) => {
+                PagiError::new(ErrorCode::InvalidArgument, e.to_string())
+            }
+            StorageError::SchemaTooNew { .. }
+            | StorageError::Corrupt(_)
+            | StorageError::Encrypted
+            | StorageError::WrongKey
+            | StorageError::EncryptionUnavailable
+            | StorageError::PostgresUnavailable => {
+                PagiError::new(ErrorCode::Storage, e.to_string())
+            }
+            StorageError::Sqlite(_)
+            | StorageError::ColumnType { .. }
+            | StorageError::Migration { .. } => PagiError::from_cause(ErrorCode::Storage, e),
+            #[cfg(feature = "postgres")]
+            StorageError::Postgres(_) => PagiError::from_cause(ErrorCode::Storage, e),
+            StorageError::Io(e) => e.into(),
+        }
+    }
+}
+
+/// Where a database URL points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite(PathBuf),
+    /// The whole `postgres://` or `postgresql://` URL.
+    Postgres(String),
+}
+
+impl Backend {
+    pub fn parse(url: &str) -> Result<Self> {
+        let url = url.trim();
+        if let Some(path) = url
+            .strip_prefix("sqlite://")
+            .or_else(|| url.strip_prefix("sqlite:"))
+        {
+            if path.is_empty() {
+                return Err(StorageError::InvalidUrl(url.to_string()));
+            }
+            return Ok(Self::Sqlite(PathBuf::from(path)));
+        }
+        if let Some(rest) = url
+            .strip_prefix("postgres://")
+            .or_else(|| url.strip_prefix("postgresql://"))
+        {
+            if rest.is_empty() {
+                return Err(StorageError::InvalidUrl(url.to_string()));
+            }
+            return Ok(Self::Postgres(url.to_string()));
+        }
+        if url.is_empty() || url.contains("://") {
+            return Err(StorageError::InvalidUrl(url.to_string()));
+        }
+        Ok(Self::Sqlite(PathBuf::from(url)))
+    }
+
+    /// Where this points, for logs and errors: the file, or the URL without its password.
+    pub fn location(&self) -> String {
+        match self {
+            Self::Sqlite(path) => path.display().to_string(),
+            Self::Postgres(url) => redact(url),
+        }
+    }
+}
+
+/// `url` with its password blanked.
+fn redact(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    match rest[..authority_end].rsplit_once('@') {
+        Some((userinfo, host)) => {
+            let user = userinfo.split_once(':').map_or(userinfo, |(user, _)| user);
+            format!("{scheme}://{user}:***@{host}{}", &rest[authority_end..])
+        }
+        None => url.to_string(),
+    }
+}
+
+struct SqliteDb {
+    path: PathBuf,
+    writer: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
+}
+
+impl SqliteDb {
+    /// A free reader connection, waiting for one only if all are busy.
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        let readers = &self.readers;
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        (0..readers.len())
+            .find_map(|i| readers[(start + i) % readers.len()].try_lock().ok())
+            .unwrap_or_else(|| lock(&readers[start % readers.len()]))
+    }
+}
+
+enum Db {
+    Sqlite(SqliteDb),
+    #[cfg(feature = "postgres")]
+    Postgres {
+        db: postgres::Db,
+        /// Encrypts the snapshots taken for backups.
+        snapshot_key: Option<zeroize::Zeroizing<DatabaseKey>>,
+    },
+}
+
+#[derive(Clone)]
+pub struct Storage {
+    inner: Arc<Db>,
+}
+
+impl std::fmt::Debug for Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &*self.inner {
+            Db::Sqlite(db) => f
+                .debug_struct("Storage")
+                .field("path", &db.path)
+                .field("readers", &db.readers.len())
+                .finish(),
+            #[cfg(feature = "postgres")]
+            Db::Postgres { db, .. } => f.debug_struct("Storage").field("db", db).finish(),
+        }
+    }
+}
+
+fn lock(conn: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    conn.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Storage {
+    /// Open the database at `url` with `readers` reader connections (at least one).
+    pub fn open(url: &str, readers: usize) -> Result<Self> {
+        Self::open_with_key(url, readers, None)
+    }
+
+    /// Like [`Storage::open`], encrypted with `key` when there is one.
+    pub fn open_with_key(url: &str, readers: usize, key: Option<&DatabaseKey>) -> Result<Self> {
+        match Backend::parse(url)? {
+            Backend::Sqlite(path) => Self::open_sqlite_with_key(&path, readers, key),
+            Backend::Postgres(url) => Self::open_postgres(&url, readers, key),
+        }
+    }
+
+    pub fn open_sqlite(path: &Path, readers: usize) -> Result<Self> {
+        Self::open_sqlite_with_key(path, readers, None)
+    }
+
+    pub fn open_sqlite_with_key(
+        path: &Path,
+        readers: usize,
+        key: Option<&DatabaseKey>,
+    ) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        if let Some(key) = key {
+            if encryption::encrypt_if_plain(path, key)? {
+                tracing::info!(target: "storage", path = %path.display(), "database encrypted");
+            }
+            for (_, backup) in migrations::existing_backups(path) {
+                encryption::encrypt_if_plain(&backup, key)?;
+            }
+        }
+        let mut writer = Connection::open(path)?;
+        encryption::apply(&writer, key)?;
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+        writer.pragma_update(None, "journal_mode", "WAL")?;
+        writer.pragma_update(None, "synchronous", "NORMAL")?;
+        writer.pragma_update(None, "foreign_keys", true)?;
+        migrations::run(&mut writer, path)?;
+
+        let readers = (0..readers.max(1))
+            .map(|_| {
+                let conn = Connection::open_with_flags(
+                    path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )?;
+                encryption::apply(&conn, key)?;
+                conn.busy_timeout(BUSY_TIMEOUT)?;
+                Ok(Mutex::new(conn))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        tracing::debug!(target: "storage", path = %path.display(), readers = readers.len(), "database opened");
+        Ok(Self {
+            inner: Arc::new(Db::Sqlite(SqliteDb {
+                path: path.to_path_buf(),
+                writer: Mutex::new(writer),
+                readers,
+                next_reader: AtomicUsize::new(0),
+            })),
+        })
+    }
+
+    /// Connect to the Postgres database at `url` with up to `readers + 1` connections. The data
+    /// stays as the server keeps it; `key` only encrypts the snapshots [`Storage::snapshot_to`]
+    /// writes.
+    #[cfg(feature = "postgres")]
+    pub fn open_postgres(url: &str, readers: usize, key: Option<&DatabaseKey>) -> Result<Self> {
+        if key.is_some() && !encryption::AVAILABLE {
+            return Err(StorageError::EncryptionUnavailable);
+        }
+        let db = postgres::Db::open(url, readers.max(1) + 1)?;
+        Ok(Self {
+            inner: Arc::new(Db::Postgres {
+                db,
+                snapshot_key: key.map(|k| zeroize::Zeroizing::new(*k)),
+            }),
+        })
+    }
+
+    /// Builds without the `postgres` feature refuse Postgres URLs.
+    #[cfg(not(feature = "postgres"))]
+    pub fn open_postgres(_url: &str, _readers: usize, _key: Option<&DatabaseKey>) -> Result<Self> {
+        Err(StorageError::PostgresUnavailable)
+    }
+
+    /// What opening `url` with `key` would migrate; nothing is created or changed.
+    pub fn migration_plan(
+        url: &str,
+        key: Option<&DatabaseKey>,
+    ) -> Result<migrations::MigrationPlan> {
+        match Backend::parse(url)? {
+            Backend::Sqlite(path) => migrations::plan(&path, key),
+            #[cfg(feature = "postgres")]
+            Backend::Postgres(url) => postgres::Db::connect(&url, 1)?.plan(),
+            #[cfg(not(feature = "postgres"))]
+            Backend::Postgres(_) => Err(StorageError::PostgresUnavailable),
+        }
+    }
+
+    /// The database file; `None` for Postgres.
+    pub fn path(&self) -> Option<&Path> {
+        match &*self.inner {
+            Db::Sqlite(db) => Some(&db.path),
+            #[cfg(feature = "postgres")]
+            Db::Postgres { .. } => None,
+        }
+    }
+
+    /// Where the database is, for logs: its file, or the Postgres URL without the password.
+    pub fn location(&self) -> String {
+        match &*self.inner {
+            Db::Sqlite(db) => db.path.display().to_string(),
+            #[cfg(feature = "postgres")]
+            Db::Postgres { db, .. } => db.url().to_string(),
+        }
+    }
+
+    /// Run `f` in a write transaction; it commits when `f` succeeds.
+    pub(crate) fn write<T>(&self, f: impl FnOnce(&mut dyn Sql) -> Result<T>) -> Result<T> {
+        match &*self.inner {
+            Db::Sqlite(db) => {
+                let mut conn = lock(&db.writer);
+                let tx = conn.transaction()?;
+                let out = f(&mut Sqlite(&tx))?;
+                tx.commit()?;
+                Ok(out)
+            }
+            #[cfg(feature = "postgres")]
+            Db::Postgres { db, .. } => db.write(f),
+        }
+    }
+
+    /// Run `f` on a free reader connection (waiting for one only if all are busy).
+    pub(crate) fn read<T>(&self, f: impl FnOnce(&mut dyn Sql) -> Result<T>) -> Result<T> {
+        match &*self.inner {
+            Db::Sqlite(db) => f(&mut Sqlite(&db.reader())),
+            #[cfg(feature = "postgres")]
+            Db::Postgres { db, .. } => db.read(f),
+        }
+    }
+
+    /// Write a consistent copy of the whole database to `target`, which must not exist yet,
+    /// while writers carry on. A Postgres database is copied into an SQLite file, so backups
+    /// have one format.
+    pub fn snapshot_to(&self, target: &Path) -> Result<()> {
+        match &*self.inner {
+            Db::Sqlite(db) => Ok(migrations::vacuum_into(&db.reader(), target)?),
+            #[cfg(feature = "postgres")]
+            Db::Postgres { db, snapshot_key } => {
+                transfer::snapshot(db, target, snapshot_key.as_deref())
+            }
+        }
+    }
+
+    /// Replace everything in this database with the snapshot at `path` (see
+    /// [`Storage::snapshot_to`]), upgrading the snapshot's schema first. Only for Postgres: an
+    /// SQLite database is restored by putting the snapshot in place of its file.
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+    pub fn load_snapshot(&self, path: &Path, key: Option<&DatabaseKey>) -> Result<()> {
+        match &*self.inner {
+            Db::Sqlite(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "an SQLite database is restored by replacing its file",
+            )
+            .into()),
+            #[cfg(feature = "postgres")]
+            Db::Postgres { db, .. } => transfer::load(db, path, key),
+        }
+    }
+
+    /// Cheap round trip for readiness checks.
+    pub fn ping(&self) -> Result<()> {
+        self.read(|sql| sql.query("SELECT 1", &[]).map(|_| ()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+
+    /// Where the tests may create Postgres databases, e.g.
+    /// `postgres://postgres@127.0.0.1/postgres`; without it they run on SQLite only.
+    #[cfg(feature = "postgres")]
+    pub const POSTGRES_URL_VAR: &str = "PHOENIX_TEST_POSTGRES_URL";
+
+    /// A fresh database, removed when dropped: an SQLite file in a temp directory, or a new
+    /// database on the Postgres server the tests were given.
+    pub struct TempStorage {
+        pub storage: Storage,
+        dir: PathBuf,
+        /// The server URL the database was created through, and its name.
+        #[cfg(feature = "postgres")]
+        pub postgres: Option<(String, String)>,
+    }
+
+    impl TempStorage {
+        pub fn new() -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("phoenix-storage-{}", uuid::Uuid::new_v4()));
+            let storage = Storage::open_sqlite(&dir.join("test.db"), 2).unwrap();
+            Self {
+                storage,
+                dir,
+                #[cfg(feature = "postgres")]
+                postgres: None,
+            }
+        }
+
+        /// A fresh database on each backend the tests can reach.
+        pub fn each_backend() -> Vec<Self> {
+            #[allow(unused_mut)]
+            let mut all = vec![Self::new()];
+            #[cfg(feature = "postgres")]
+            if let Some(temp) = Self::postgres() {
+                all.push(temp);
+            }
+            all
+        }
+
+        /// A fresh Postgres database, if the tests were given a server.
+        #[cfg(feature = "postgres")]
+        pub fn postgres() -> Option<Self> {
+            let admin = std::env::var(POSTGRES_URL_VAR).ok()?;
+            let name = format!("phoenix_test_{}", uuid::Uuid::new_v4().simple());
+            postgres::test_admin(
+                &admin,
+                &format!("CREATE DATABASE {name} ENCODING 'UTF8' TEMPLATE template0"),
+            );
+            let storage = Storage::open_postgres(&postgres::with_database(&admin, &name), 2, None)
+                .unwrap();
+            let mut temp = Self::new();
+            temp.storage = storage;
+            temp.postgres = Some((admin, name));
+            Some(temp)
+        }
+    }
+
+    impl Drop for TempStorage {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+            #[cfg(feature = "postgres")]
+            if let Some((admin, name)) = self.postgres.take() {
+                postgres::test_admin(&admin, &format!("DROP DATABASE {name} WITH (FORCE)"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urls() {
+        assert_eq!(
+            Backend::parse("sqlite://data/phoenix.db").unwrap(),
+            Backend::Sqlite(PathBuf::from("data/phoenix.db"))
+        );
+        assert_eq!(
+            Backend::parse("/var/lib/phoenix.db").unwrap(),
+            Backend::Sqlite(PathBuf::from("/var/lib/phoenix.db"))
+        );
+        for url in ["postgres://localhost/phoenix", "postgresql://u:p@db/phoenix"] {
+            assert_eq!(
+                Backend::parse(url).unwrap(),
+                Backend::Postgres(url.to_string())
+            );
+        }
+        assert_eq!(
+            Backend::parse("postgres://phoenix:s3cret@db:5432/phoenix?sslmode=require")
+                .unwrap()
+                .location(),
+            "postgres://phoenix:***@db:5432/phoenix?sslmode=require"
+        );
+        assert_eq!(redact("postgresql://db/phoenix"), "postgresql://db/phoenix");
+        for url in ["mysql://localhost", ""] {
+            assert!(matches!(
+                Backend::parse(url),
+                Err(StorageError::InvalidUrl(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn reopening_keeps_the_schema() {
+        let temp = test_support::TempStorage::new();
+        temp.storage.ping().unwrap();
+        let again = Storage::open_sqlite(temp.storage.path().unwrap(), 1).unwrap();
+        again.ping().unwrap();
+    }
+}
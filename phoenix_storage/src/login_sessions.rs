@@ -0,0 +1,97 @@
+//! Web UI login sessions. Only a hash of each session token is stored.
+
+use crate::sql::{values, Row};
+use crate::{Result, Storage};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionRow {
+    pub id: String,
+    pub token_hash: String,
+    pub created_unix: i64,
+    pub last_seen_unix: i64,
+    pub user_agent: Option<String>,
+    pub peer: Option<String>,
+}
+
+fn session_row(row: &Row) -> Result<SessionRow> {
+    Ok(SessionRow {
+        id: row.get(0)?,
+        token_hash: row.get(1)?,
+        created_unix: row.get(2)?,
+        last_seen_unix: row.get(3)?,
+        user_agent: row.get(4)?,
+        peer: row.get(5)?,
+    })
+}
+
+const COLUMNS: &str = "id, token_hash, created_unix, last_seen_unix, user_agent, peer";
+
+impl Storage {
+    pub fn insert_session(&self, session: &SessionRow) -> Result<()> {
+        self.write(|tx| {
+            tx.execute(
+                &format!("INSERT INTO login_sessions ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"),
+                values![
+                    &session.id,
+                    &session.token_hash,
+                    session.created_unix,
+                    session.last_seen_unix,
+                    &session.user_agent,
+                    &session.peer
+                ],
+            )
+            .map(|_| ())
+        })
+    }
+
+    pub fn session(&self, id: &str) -> Result<Option<SessionRow>> {
+        self.read(|sql| {
+            sql.query_row(
+                &format!("SELECT {COLUMNS} FROM login_sessions WHERE id = ?1"),
+                values![id],
+            )?
+            .as_ref()
+            .map(session_row)
+            .transpose()
+        })
+    }
+
+    /// All sessions, oldest first.
+    pub fn sessions(&self) -> Result<Vec<SessionRow>> {
+        self.read(|sql| {
+            sql.query(
+                &format!("SELECT {COLUMNS} FROM login_sessions ORDER BY created_unix, id"),
+                &[],
+            )?
+            .iter()
+            .map(session_row)
+            .collect()
+        })
+    }
+
+    pub fn touch_session(&self, id: &str, last_seen_unix: i64) -> Result<()> {
+        self.write(|tx| {
+            tx.execute(
+                "UPDATE login_sessions SET last_seen_unix = ?2 WHERE id = ?1",
+                values![id, last_seen_unix],
+            )
+            .map(|_| ())
+        })
+    }
+
+    /// `false` if there was no session `id`.
+    pub fn delete_session(&self, id: &str) -> Result<bool> {
+        self.write(|tx| tx.execute("DELETE FROM login_sessions WHERE id = ?1", values![id]))
+            .map(|n| n > 0)
+    }
+
+    /// Delete sessions last used before `cutoff_unix`; returns how many were removed.
+    pub fn delete_sessions_idle_since(&self, cutoff_unix: i64) -> Result<usize> {
+        self.write(|tx| {
+            tx.execute(
+                "DELETE FROM login_sessions WHERE last_seen_unix < ?1",
+                values![cutoff_unix],
+            )
+        })
+    }
+}
@@ -0,0 +1,473 @@
+//! Schema versions. Each migration upgrades the schema by one version; the current version is
+//! kept in `PRAGMA user_version`. Append new migrations, never edit old ones.
+//!
+//! Before upgrading a database that already holds data, the runner checks it with
+//! `PRAGMA quick_check` and writes a backup next to it (`<file>.v<N>-<unix time>.bak`, the newest
+//! [`BACKUPS_KEPT`] are kept). Each migration runs in its own transaction and is rejected if it
+//! leaves a foreign key violation, so a failed upgrade leaves the database at the last good
+//! version. A database written by a newer build is refused rather than opened.
+//!
+//! These are the SQLite migrations; Postgres has its own form of each (see `postgres`).
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::encryption::DatabaseKey;
+use crate::{Result, StorageError};
+
+/// Pre-migration backups kept per database; older ones are deleted after a new one is written.
+pub const BACKUPS_KEPT: usize = 3;
+
+#[derive(Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub(crate) sql: &'static str,
+}
+
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial schema",
+        sql: "CREATE TABLE emotion_moments (
+        id INTEGER PRIMARY KEY,
+        ts_unix INTEGER NOT NULL,
+        profile TEXT NOT NULL COLLATE NOCASE,
+        emotion TEXT NOT NULL COLLATE NOCASE,
+        body TEXT NOT NULL
+    );
+    CREATE INDEX emotion_moments_ts ON emotion_moments (ts_unix);
+    CREATE INDEX emotion_moments_profile ON emotion_moments (profile, ts_unix);
+
+    CREATE TABLE recordings (
+        path TEXT PRIMARY KEY,
+        dir TEXT NOT NULL,
+        fingerprint INTEGER NOT NULL,
+        body TEXT NOT NULL
+    );
+    CREATE INDEX recordings_dir ON recordings (dir);
+
+    CREATE TABLE login_sessions (
+        id TEXT PRIMARY KEY,
+        token_hash TEXT NOT NULL,
+        created_unix INTEGER NOT NULL,
+        last_seen_unix INTEGER NOT NULL,
+        user_agent TEXT,
+        peer TEXT
+    );
+
+    CREATE TABLE ghost_sessions (
+        id TEXT PRIMARY KEY,
+        started_unix INTEGER NOT NULL,
+        load_start INTEGER NOT NULL,
+        ended_unix INTEGER,
+        load_end INTEGER
+    );
+    CREATE INDEX ghost_sessions_started ON ghost_sessions (started_unix);",
+    },
+    Migration {
+        version: 2,
+        name: "login session idle index",
+        sql: "CREATE INDEX login_sessions_last_seen ON login_sessions (last_seen_unix);",
+    },
+    Migration {
+        version: 3,
+        name: "profiles",
+        sql: "CREATE TABLE profiles (
+            id TEXT PRIMARY KEY COLLATE NOCASE,
+            display_name TEXT NOT NULL,
+            created_unix INTEGER NOT NULL,
+            settings TEXT NOT NULL DEFAULT '{}'
+        );
+        INSERT INTO profiles (id, display_name, created_unix)
+            VALUES ('default', 'Default', CAST(strftime('%s', 'now') AS INTEGER));
+        -- Household members recognized before profiles existed become profiles.
+        INSERT OR IGNORE INTO profiles (id, display_name, created_unix)
+            SELECT profile, profile, MIN(ts_unix) FROM emotion_moments
+            WHERE profile <> 'unknown' GROUP BY profile;
+        -- Unattributed history belonged to the single user of a pre-profile install.
+        UPDATE emotion_moments SET profile = 'default',
+            body = CASE WHEN json_valid(body) THEN json_set(body, '$.profile', 'default') ELSE body END
+            WHERE profile = 'unknown';
+
+        CREATE TABLE app_state (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO app_state (key, value) VALUES ('active_profile', 'default');
+
+        ALTER TABLE ghost_sessions ADD COLUMN profile TEXT NOT NULL DEFAULT 'default' COLLATE NOCASE;
+        CREATE INDEX ghost_sessions_profile ON ghost_sessions (profile, started_unix);",
+    },
+    Migration {
+        version: 4,
+        name: "webhooks",
+        sql: "CREATE TABLE webhooks (
+            id TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            events TEXT NOT NULL DEFAULT '',
+            description TEXT NOT NULL DEFAULT '',
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_unix INTEGER NOT NULL
+        );
+        CREATE TABLE webhook_deliveries (
+            id INTEGER PRIMARY KEY,
+            delivery_id TEXT NOT NULL,
+            webhook_id TEXT NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+            event TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            ts_unix INTEGER NOT NULL,
+            status INTEGER,
+            error TEXT,
+            duration_ms INTEGER NOT NULL
+        );
+        CREATE INDEX webhook_deliveries_webhook ON webhook_deliveries (webhook_id, id);
+        CREATE INDEX webhook_deliveries_ts ON webhook_deliveries (ts_unix);",
+    },
+    Migration {
+        version: 5,
+        name: "audit log",
+        sql: "CREATE TABLE audit_log (
+            id INTEGER PRIMARY KEY,
+            ts_unix INTEGER NOT NULL,
+            actor TEXT NOT NULL,
+            profile TEXT,
+            action TEXT NOT NULL,
+            params TEXT NOT NULL DEFAULT '{}'
+        );
+        CREATE INDEX audit_log_action ON audit_log (action, id);
+        CREATE INDEX audit_log_ts ON audit_log (ts_unix);
+        CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+        CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;",
+    },
+];
+
+/// Schema version this build creates and understands.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// What opening a database would do to its schema; see [`plan`].
+#[derive(Debug)]
+pub struct MigrationPlan {
+    /// The database file, or the Postgres URL without its password.
+    pub location: String,
+    /// `false` when the database doesn't exist yet and would be created.
+    pub exists: bool,
+    pub current: u32,
+    pub target: u32,
+    pub pending: Vec<&'static Migration>,
+    /// Where the pre-migration backup would go; `None` if no backup is needed.
+    pub backup: Option<PathBuf>,
+    /// The database is in the clear and would be encrypted first.
+    pub encrypt: bool,
+}
+
+fn user_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// The migrations in `migrations` after version `current`.
+pub(crate) fn pending_in(
+    migrations: &'static [Migration],
+    current: u32,
+) -> Result<Vec<&'static Migration>> {
+    if current > SCHEMA_VERSION {
+        return Err(StorageError::SchemaTooNew {
+            found: current,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    Ok(migrations.iter().skip(current as usize).collect())
+}
+
+fn pending_since(current: u32) -> Result<Vec<&'static Migration>> {
+    pending_in(MIGRATIONS, current)
+}
+
+fn check_integrity(conn: &Connection) -> Result<()> {
+    let result: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if result == "ok" {
+        Ok(())
+    } else {
+        Err(StorageError::Corrupt(result))
+    }
+}
+
+fn backup_path(path: &Path, current: u32) -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{current}-{now}.bak"));
+    path.with_file_name(name)
+}
+
+/// Backups of `path` as `(unix time, path)`, oldest first.
+pub(crate) fn existing_backups(path: &Path) -> Vec<(u64, PathBuf)> {
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<(u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let rest = name.strip_prefix(file_name)?.strip_prefix(".v")?;
+            let (_version, time) = rest.strip_suffix(".bak")?.split_once('-')?;
+            Some((time.parse().ok()?, entry.path()))
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+/// Write a consistent, compacted copy of the database open on `conn` to `target` (which must
+/// not exist), including anything still in the WAL.
+pub(crate) fn vacuum_into(conn: &Connection, target: &Path) -> rusqlite::Result<()> {
+    conn.execute("VACUUM INTO ?1", [target.to_string_lossy()])
+        .map(|_| ())
+}
+
+fn backup(conn: &Connection, path: &Path, current: u32) -> Result<PathBuf> {
+    let target = backup_path(path, current);
+    vacuum_into(conn, &target)?;
+    let backups = existing_backups(path);
+    for (_, old) in backups
+        .iter()
+        .take(backups.len().saturating_sub(BACKUPS_KEPT))
+    {
+        if let Err(e) = std::fs::remove_file(old) {
+            tracing::warn!(target: "storage", path = %old.display(), "cannot remove old backup: {e}");
+        }
+    }
+    Ok(target)
+}
+
+/// Apply `migration` on `tx` and make sure it left no dangling references.
+fn apply(tx: &rusqlite::Transaction<'_>, migration: &Migration) -> Result<()> {
+    let failed = |reason: String| StorageError::Migration {
+        version: migration.version,
+        name: migration.name,
+        reason,
+    };
+    tx.execute_batch(migration.sql)
+        .map_err(|e| failed(e.to_string()))?;
+    let violations: i64 = tx
+        .query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| failed(e.to_string()))?;
+    if violations > 0 {
+        return Err(failed(format!("{violations} foreign key violation(s)")));
+    }
+    Ok(())
+}
+
+/// Bring the database at `path` (open on `conn`) up to [`SCHEMA_VERSION`].
+pub(crate) fn run(conn: &mut Connection, path: &Path) -> Result<()> {
+    let current = user_version(conn)?;
+    let pending = pending_since(current)?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    if current > 0 {
+        check_integrity(conn)?;
+        let saved = backup(conn, path, current)?;
+        tracing::info!(target: "storage", from = current, to = SCHEMA_VERSION, backup = %saved.display(), "database backed up before migration");
+    }
+    for migration in pending {
+        let tx = conn.transaction()?;
+        apply(&tx, migration)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        tracing::info!(target: "storage", version = migration.version, name = migration.name, "database schema migrated");
+    }
+    Ok(())
+}
+
+/// Check a database file that isn't open, e.g. one about to be restored: it must pass
+/// `PRAGMA quick_check` and have a schema this build can open. Returns its schema version.
+pub fn check_file(path: &Path, key: Option<&DatabaseKey>) -> Result<u32> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    crate::encryption::apply(&conn, key)?;
+    check_integrity(&conn)?;
+    let version = user_version(&conn)?;
+    pending_since(version)?;
+    Ok(version)
+}
+
+/// Work out what opening `path` would migrate, and try the pending migrations in a transaction
+/// that is rolled back, without creating, backing up or changing anything.
+pub fn plan(path: &Path, key: Option<&DatabaseKey>) -> Result<MigrationPlan> {
+    let exists = path.exists();
+    let encrypt = key.is_some() && crate::encryption::is_plain(path)?;
+    let mut conn = if exists {
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?
+    } else {
+        Connection::open_in_memory()?
+    };
+    if exists && !encrypt {
+        crate::encryption::apply(&conn, key)?;
+    } else if key.is_some() && !crate::encryption::AVAILABLE {
+        return Err(StorageError::EncryptionUnavailable);
+    }
+    conn.busy_timeout(crate::BUSY_TIMEOUT)?;
+    let current = user_version(&conn)?;
+    let pending = pending_since(current)?;
+    let backup = if exists && current > 0 && !pending.is_empty() {
+        check_integrity(&conn)?;
+        Some(backup_path(path, current))
+    } else {
+        None
+    };
+    let tx = conn.transaction()?;
+    for migration in &pending {
+        apply(&tx, migration)?;
+    }
+    tx.rollback()?;
+    Ok(MigrationPlan {
+        location: path.display().to_string(),
+        exists,
+        current,
+        target: SCHEMA_VERSION,
+        pending,
+        backup,
+        encrypt,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+    use crate::Storage;
+
+    #[test]
+    fn up_to_date_database_has_nothing_pending() {
+        let temp = TempStorage::new();
+        let plan = plan(temp.storage.path().unwrap(), None).unwrap();
+        assert_eq!(
+            (plan.current, plan.target),
+            (SCHEMA_VERSION, SCHEMA_VERSION)
+        );
+        assert!(plan.pending.is_empty() && plan.backup.is_none());
+    }
+
+    #[test]
+    fn dry_run_leaves_a_new_database_uncreated() {
+        let temp = TempStorage::new();
+        let path = temp.storage.path().unwrap().with_file_name("fresh.db");
+        let plan = plan(&path, None).unwrap();
+        assert!(!plan.exists && plan.backup.is_none());
+        assert_eq!(plan.pending.len(), MIGRATIONS.len());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn upgrading_backs_up_and_keeps_the_newest_backups() {
+        let temp = TempStorage::new();
+        let path = temp.storage.path().unwrap().with_file_name("old.db");
+        // A database left by the previous build, with some data in it.
+        let conn = Connection::open(&path).unwrap();
+        for migration in &MIGRATIONS[..MIGRATIONS.len() - 1] {
+            conn.execute_batch(migration.sql).unwrap();
+        }
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION - 1)
+            .unwrap();
+        conn.execute(
+            "INSERT INTO ghost_sessions (id, started_unix, load_start) VALUES ('kept', 1, 2)",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+        for stamp in 1..=BACKUPS_KEPT {
+            std::fs::write(path.with_file_name(format!("old.db.v0-{stamp}.bak")), b"").unwrap();
+        }
+
+        let plan = plan(&path, None).unwrap();
+        assert_eq!(plan.current, SCHEMA_VERSION - 1);
+        assert_eq!(plan.pending.len(), 1);
+        assert!(plan.backup.is_some());
+        assert!(
+            existing_backups(&path).len() == BACKUPS_KEPT,
+            "dry run wrote a backup"
+        );
+
+        let upgraded = Storage::open_sqlite(&path, 1).unwrap();
+        assert_eq!(upgraded.ghost_sessions_since(0, None).unwrap().len(), 1);
+        let backups = existing_backups(&path);
+        assert_eq!(backups.len(), BACKUPS_KEPT);
+        let newest = Connection::open(&backups.last().unwrap().1).unwrap();
+        assert_eq!(user_version(&newest).unwrap(), SCHEMA_VERSION - 1);
+        let rows: i64 = newest
+            .query_row("SELECT COUNT(*) FROM ghost_sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn profiles_migration_adopts_existing_history() {
+        let temp = TempStorage::new();
+        let path = temp.storage.path().unwrap().with_file_name("v2.db");
+        let conn = Connection::open(&path).unwrap();
+        for migration in &MIGRATIONS[..2] {
+            conn.execute_batch(migration.sql).unwrap();
+        }
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        conn.execute_batch(
+            "INSERT INTO emotion_moments (ts_unix, profile, emotion, body)
+             VALUES (5, 'unknown', 'joy', '{\"profile\":null}'), (7, 'Bob', 'calm', '{}');
+             INSERT INTO ghost_sessions (id, started_unix, load_start) VALUES ('g', 1, 2);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let storage = Storage::open_sqlite(&path, 1).unwrap();
+        let ids: Vec<_> = storage
+            .profiles()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, ["Bob", "default"]);
+        let default = storage
+            .query_moments(&crate::moments::MomentFilter {
+                profile: Some("default"),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(default, [r#"{"profile":"default"}"#]);
+        assert_eq!(
+            storage
+                .ghost_sessions_since(0, Some("default"))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn refuses_a_newer_schema() {
+        let temp = TempStorage::new();
+        let path = temp.storage.path().unwrap().to_path_buf();
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        drop(conn);
+        assert!(matches!(
+            Storage::open_sqlite(&path, 1),
+            Err(StorageError::SchemaTooNew { .. })
+        ));
+        assert!(matches!(
+            plan(&path, None),
+            Err(StorageError::SchemaTooNew { .. })
+        ));
+    }
+}
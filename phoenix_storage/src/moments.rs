@@ -0,0 +1,168 @@
+//! Emotional-moment history. Rows keep the serialized moment as `body`; time, profile and
+//! emotion are columns so range and filter queries use indexes.
+
+use crate::sql::{values, Value};
+use crate::{Result, Storage};
+
+/// A moment to insert.
+#[derive(Debug, Clone, Copy)]
+pub struct NewMoment<'a> {
+    pub ts_unix: i64,
+    pub profile: &'a str,
+    pub emotion: &'a str,
+    pub body: &'a str,
+}
+
+/// Filters for [`Storage::query_moments`]. `from_unix` is inclusive and `to_unix` exclusive;
+/// emotion and profile match case-insensitively.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MomentFilter<'a> {
+    pub from_unix: Option<i64>,
+    pub to_unix: Option<i64>,
+    pub emotion: Option<&'a str>,
+    pub profile: Option<&'a str>,
+    /// Keep only the most recent `limit` matches.
+    pub limit: Option<usize>,
+}
+
+impl Storage {
+    pub fn insert_moment(&self, moment: &NewMoment<'_>) -> Result<()> {
+        self.insert_moments(std::slice::from_ref(moment))
+            .map(|_| ())
+    }
+
+    pub fn insert_moments(&self, moments: &[NewMoment<'_>]) -> Result<usize> {
+        self.write(|tx| {
+            for m in moments {
+                tx.execute(
+                    "INSERT INTO emotion_moments (ts_unix, profile, emotion, body)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    values![m.ts_unix, m.profile, m.emotion, m.body],
+                )?;
+            }
+            Ok(moments.len())
+        })
+    }
+
+    /// Bodies of the matching moments, oldest first.
+    pub fn query_moments(&self, filter: &MomentFilter<'_>) -> Result<Vec<String>> {
+        let mut clauses = Vec::new();
+        let mut args = Vec::new();
+        let mut arg = |column: &str, op: &str, value: Value| {
+            args.push(value);
+            clauses.push(format!("{column} {op} ?{}", args.len()));
+        };
+        if let Some(from) = filter.from_unix {
+            arg("ts_unix", ">=", from.into());
+        }
+        if let Some(to) = filter.to_unix {
+            arg("ts_unix", "<", to.into());
+        }
+        if let Some(emotion) = filter.emotion {
+            arg("emotion", "=", emotion.into());
+        }
+        if let Some(profile) = filter.profile {
+            arg("profile", "=", profile.into());
+        }
+        let filter_sql = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+        // Newest first to apply the limit, then back to oldest first.
+        args.push(Value::from(
+            filter
+                .limit
+                .map_or(i64::MAX, |l| l.min(i64::MAX as usize) as i64),
+        ));
+        let query = format!(
+            "SELECT body FROM (
+                SELECT id, ts_unix, body FROM emotion_moments {filter_sql}
+                ORDER BY ts_unix DESC, id DESC LIMIT ?{}
+             ) AS recent ORDER BY ts_unix, id",
+            args.len()
+        );
+        self.read(|sql| {
+            sql.query(&query, &args)?
+                .iter()
+                .map(|row| row.get(0))
+                .collect()
+        })
+    }
+
+    pub fn moment_count(&self) -> Result<u64> {
+        self.read(|sql| sql.query_value("SELECT COUNT(*) FROM emotion_moments", &[]))
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Time of the newest moment, if there is one.
+    pub fn latest_moment_unix(&self) -> Result<Option<i64>> {
+        self.read(|sql| sql.query_value("SELECT MAX(ts_unix) FROM emotion_moments", &[]))
+            .map(Option::flatten)
+    }
+
+    /// Delete every moment attributed to `profile`; returns how many were removed.
+    pub fn delete_moments_for_profile(&self, profile: &str) -> Result<usize> {
+        self.write(|tx| {
+            tx.execute(
+                "DELETE FROM emotion_moments WHERE profile = ?1",
+                values![profile],
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+
+    #[test]
+    fn filters_and_keeps_the_most_recent() {
+        for temp in TempStorage::each_backend() {
+            let storage = &temp.storage;
+            assert_eq!(storage.latest_moment_unix().unwrap(), None);
+            let rows = [
+                (10, "alice", "Joy"),
+                (20, "bob", "Sadness"),
+                (30, "alice", "Joy"),
+                (40, "Alice", "Anger"),
+            ];
+            let bodies: Vec<String> = rows.iter().map(|(ts, ..)| format!("m{ts}")).collect();
+            let moments: Vec<NewMoment> = rows
+                .iter()
+                .zip(&bodies)
+                .map(|(&(ts_unix, profile, emotion), body)| NewMoment {
+                    ts_unix,
+                    profile,
+                    emotion,
+                    body,
+                })
+                .collect();
+            assert_eq!(storage.insert_moments(&moments).unwrap(), 4);
+            assert_eq!(storage.latest_moment_unix().unwrap(), Some(40));
+
+            let alice = storage
+                .query_moments(&MomentFilter {
+                    profile: Some("ALICE"),
+                    ..MomentFilter::default()
+                })
+                .unwrap();
+            assert_eq!(alice, ["m10", "m30", "m40"]);
+
+            let recent_joy = storage
+                .query_moments(&MomentFilter {
+                    emotion: Some("joy"),
+                    from_unix: Some(10),
+                    to_unix: Some(40),
+                    limit: Some(1),
+                    ..MomentFilter::default()
+                })
+                .unwrap();
+            assert_eq!(recent_joy, ["m30"]);
+
+            assert_eq!(storage.delete_moments_for_profile("alice").unwrap(), 3);
+            assert_eq!(storage.moment_count().unwrap(), 1);
+        }
+    }
+}
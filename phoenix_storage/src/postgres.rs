@@ -0,0 +1,527 @@
+//! The Postgres backend, in builds with the `postgres` feature.
+//!
+//! sqlx runs the pool on a small runtime of its own, and the blocking [`Storage`](crate::Storage)
+//! calls hand their statements to it and wait, so they work the same from a plain thread or an
+//! async handler. The schema matches SQLite's: integers are `BIGINT`, and the columns SQLite
+//! compares with `COLLATE NOCASE` use an ICU collation of that name, which needs a UTF-8
+//! database. The version is kept in a `schema_version` table; there is no file to back up or
+//! integrity-check before upgrading, since the server owns that.
+
+use std::future::Future;
+
+use sqlx::postgres::{PgArguments, PgConnection, PgPool, PgPoolOptions, PgRow};
+use sqlx::query::Query;
+use sqlx::{Column, Postgres, Row as _, TypeInfo};
+use tokio::runtime::{Handle, Runtime};
+
+use crate::migrations::{Migration, MigrationPlan, SCHEMA_VERSION};
+use crate::sql::{Row, Sql, Value};
+use crate::{Result, StorageError};
+
+/// Taken for the whole upgrade so two servers starting together don't both migrate.
+const MIGRATION_LOCK: i64 = 0x0070_686f_656e_6978;
+
+/// The Postgres form of each migration in [`crate::migrations`], same versions and names.
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial schema",
+        sql: "CREATE COLLATION IF NOT EXISTS nocase
+            (provider = icu, locale = 'und-u-ks-level2', deterministic = false);
+
+        CREATE TABLE emotion_moments (
+            id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+            ts_unix BIGINT NOT NULL,
+            profile TEXT COLLATE nocase NOT NULL,
+            emotion TEXT COLLATE nocase NOT NULL,
+            body TEXT NOT NULL
+        );
+        CREATE INDEX emotion_moments_ts ON emotion_moments (ts_unix);
+        CREATE INDEX emotion_moments_profile ON emotion_moments (profile, ts_unix);
+
+        CREATE TABLE recordings (
+            path TEXT PRIMARY KEY,
+            dir TEXT NOT NULL,
+            fingerprint BIGINT NOT NULL,
+            body TEXT NOT NULL
+        );
+        CREATE INDEX recordings_dir ON recordings (dir);
+
+        CREATE TABLE login_sessions (
+            id TEXT PRIMARY KEY,
+            token_hash TEXT NOT NULL,
+            created_unix BIGINT NOT NULL,
+            last_seen_unix BIGINT NOT NULL,
+            user_agent TEXT,
+            peer TEXT
+        );
+
+        CREATE TABLE ghost_sessions (
+            id TEXT PRIMARY KEY,
+            started_unix BIGINT NOT NULL,
+            load_start BIGINT NOT NULL,
+            ended_unix BIGINT,
+            load_end BIGINT
+        );
+        CREATE INDEX ghost_sessions_started ON ghost_sessions (started_unix);",
+    },
+    Migration {
+        version: 2,
+        name: "login session idle index",
+        sql: "CREATE INDEX login_sessions_last_seen ON login_sessions (last_seen_unix);",
+    },
+    Migration {
+        version: 3,
+        name: "profiles",
+        // No Postgres database predates profiles, so there is no history to adopt.
+        sql: "CREATE TABLE profiles (
+            id TEXT COLLATE nocase PRIMARY KEY,
+            display_name TEXT NOT NULL,
+            created_unix BIGINT NOT NULL,
+            settings TEXT NOT NULL DEFAULT '{}'
+        );
+        INSERT INTO profiles (id, display_name, created_unix)
+            VALUES ('default', 'Default', EXTRACT(EPOCH FROM now())::BIGINT);
+
+        CREATE TABLE app_state (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO app_state (key, value) VALUES ('active_profile', 'default');
+
+        ALTER TABLE ghost_sessions ADD COLUMN profile TEXT COLLATE nocase NOT NULL DEFAULT 'default';
+        CREATE INDEX ghost_sessions_profile ON ghost_sessions (profile, started_unix);",
+    },
+    Migration {
+        version: 4,
+        name: "webhooks",
+        sql: "CREATE TABLE webhooks (
+            id TEXT PRIMARY KEY,
+            url TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            events TEXT NOT NULL DEFAULT '',
+            description TEXT NOT NULL DEFAULT '',
+            enabled BIGINT NOT NULL DEFAULT 1,
+            created_unix BIGINT NOT NULL
+        );
+        CREATE TABLE webhook_deliveries (
+            id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+            delivery_id TEXT NOT NULL,
+            webhook_id TEXT NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+            event TEXT NOT NULL,
+            attempt BIGINT NOT NULL,
+            ts_unix BIGINT NOT NULL,
+            status BIGINT,
+            error TEXT,
+            duration_ms BIGINT NOT NULL
+        );
+        CREATE INDEX webhook_deliveries_webhook ON webhook_deliveries (webhook_id, id);
+        CREATE INDEX webhook_deliveries_ts ON webhook_deliveries (ts_unix);",
+    },
+    Migration {
+        version: 5,
+        name: "audit log",
+        sql: "CREATE TABLE audit_log (
+            id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+            ts_unix BIGINT NOT NULL,
+            actor TEXT NOT NULL,
+            profile TEXT,
+            action TEXT NOT NULL,
+            params TEXT NOT NULL DEFAULT '{}'
+        );
+        CREATE INDEX audit_log_action ON audit_log (action, id);
+        CREATE INDEX audit_log_ts ON audit_log (ts_unix);
+        CREATE FUNCTION audit_log_append_only() RETURNS trigger LANGUAGE plpgsql AS
+            $$ BEGIN RAISE EXCEPTION 'audit_log is append-only'; END $$;
+        CREATE TRIGGER audit_log_no_update BEFORE UPDATE OR DELETE ON audit_log
+            FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();",
+    },
+];
+
+const _: () = assert!(MIGRATIONS.len() as u32 == SCHEMA_VERSION);
+
+/// `url` pointing at database `name` on the same server.
+#[cfg(test)]
+pub(crate) fn with_database(url: &str, name: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("postgres", url));
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let query = rest[authority_end..]
+        .find('?')
+        .map_or("", |q| &rest[authority_end + q..]);
+    format!("{scheme}://{}/{name}{query}", &rest[..authority_end])
+}
+
+/// Run `statement` on the server at `url` outside any storage, e.g. to create a test database.
+#[cfg(test)]
+pub(crate) fn test_admin(url: &str, statement: &str) {
+    use sqlx::Connection;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut conn = PgConnection::connect(url).await.unwrap();
+        raw(&mut conn, statement).await.unwrap();
+        conn.close().await.unwrap();
+    });
+}
+
+/// Run `task` on the storage runtime and wait for it. The waiting thread may be inside another
+/// runtime; it only blocks, like waiting for an SQLite lock.
+fn run<T: Send + 'static>(
+    handle: &Handle,
+    task: impl Future<Output = T> + Send + 'static,
+) -> Result<T> {
+    let (done, result) = std::sync::mpsc::sync_channel(1);
+    handle.spawn(async move {
+        let _ = done.send(task.await);
+    });
+    result
+        .recv()
+        .map_err(|_| StorageError::Postgres(sqlx::Error::WorkerCrashed))
+}
+
+pub(crate) struct Db {
+    /// Only `None` while dropping.
+    runtime: Option<Runtime>,
+    handle: Handle,
+    pool: PgPool,
+    url: String,
+}
+
+impl std::fmt::Debug for Db {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Db")
+            .field("url", &self.url)
+            .field("connections", &self.pool.options().get_max_connections())
+            .finish()
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which is not allowed inside another one.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Db {
+    /// Connect to `url` with up to `connections` connections and bring the schema up to date.
+    pub(crate) fn open(url: &str, connections: usize) -> Result<Self> {
+        let db = Self::connect(url, connections)?;
+        db.migrate()?;
+        tracing::debug!(target: "storage", url = %db.url, "database opened");
+        Ok(db)
+    }
+
+    /// Connect to `url` without touching the schema.
+    pub(crate) fn connect(url: &str, connections: usize) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("phoenix-storage")
+            .enable_all()
+            .build()?;
+        let handle = runtime.handle().clone();
+        let options = PgPoolOptions::new()
+            .max_connections(connections.max(1) as u32)
+            .acquire_timeout(crate::BUSY_TIMEOUT);
+        let target = url.to_string();
+        let pool = run(&handle, async move { options.connect(&target).await })??;
+        Ok(Self {
+            runtime: Some(runtime),
+            handle,
+            pool,
+            url: crate::redact(url),
+        })
+    }
+
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn run<T: Send + 'static>(&self, task: impl Future<Output = T> + Send + 'static) -> Result<T> {
+        run(&self.handle, task)
+    }
+
+    /// A connection from the pool, in a transaction when `transaction` is set.
+    fn session(&self, transaction: Option<&'static str>) -> Result<PgSql<'_>> {
+        let pool = self.pool.clone();
+        let conn = self.run(async move {
+            let mut conn = pool.acquire().await?;
+            if let Some(begin) = transaction {
+                raw(&mut conn, begin).await?;
+            }
+            Ok::<_, sqlx::Error>(conn)
+        })??;
+        Ok(PgSql {
+            db: self,
+            conn: Some(conn),
+            open: transaction.is_some(),
+        })
+    }
+
+    /// Run `f` in a write transaction; it commits when `f` succeeds.
+    pub(crate) fn write<T>(&self, f: impl FnOnce(&mut dyn Sql) -> Result<T>) -> Result<T> {
+        let mut sql = self.session(Some("BEGIN"))?;
+        let out = f(&mut sql)?;
+        sql.finish("COMMIT")?;
+        Ok(out)
+    }
+
+    /// Run `f` on a pooled connection.
+    pub(crate) fn read<T>(&self, f: impl FnOnce(&mut dyn Sql) -> Result<T>) -> Result<T> {
+        f(&mut self.session(None)?)
+    }
+
+    /// Run `f` in a read-only transaction that sees one snapshot of the database throughout.
+    pub(crate) fn read_consistent<T>(
+        &self,
+        f: impl FnOnce(&mut dyn Sql) -> Result<T>,
+    ) -> Result<T> {
+        let mut sql = self.session(Some("BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY"))?;
+        let out = f(&mut sql)?;
+        sql.finish("COMMIT")?;
+        Ok(out)
+    }
+
+    fn migrate(&self) -> Result<()> {
+        let mut sql = self.session(Some("BEGIN"))?;
+        sql.execute(
+            "SELECT pg_advisory_xact_lock(?1)",
+            crate::sql::values![MIGRATION_LOCK],
+        )?;
+        let current = current_version(&mut sql)?;
+        let pending = crate::migrations::pending_in(MIGRATIONS, current)?;
+        for migration in &pending {
+            sql.apply(migration)?;
+            tracing::info!(target: "storage", version = migration.version, name = migration.name, "database schema migrated");
+        }
+        sql.finish("COMMIT")
+    }
+
+    /// What opening the database would migrate, trying the pending migrations in a transaction
+    /// that is rolled back.
+    pub(crate) fn plan(&self) -> Result<MigrationPlan> {
+        let mut sql = self.session(Some("BEGIN"))?;
+        let current = current_version(&mut sql)?;
+        let pending = crate::migrations::pending_in(MIGRATIONS, current)?;
+        for migration in &pending {
+            sql.apply(migration)?;
+        }
+        sql.finish("ROLLBACK")?;
+        Ok(MigrationPlan {
+            location: self.url.clone(),
+            exists: current > 0,
+            current,
+            target: SCHEMA_VERSION,
+            // The plan lists each migration once, whatever its backend.
+            pending: crate::migrations::pending_in(crate::migrations::MIGRATIONS, current)?,
+            backup: None,
+            encrypt: false,
+        })
+    }
+}
+
+/// The schema version, 0 for a database this app never opened.
+fn current_version(sql: &mut PgSql<'_>) -> Result<u32> {
+    // Checked first rather than `IF NOT EXISTS`, whose notice would be logged on every start.
+    let table: Option<String> =
+        match sql.query_row("SELECT to_regclass('schema_version')::text", &[])? {
+            Some(row) => row.get(0)?,
+            None => None,
+        };
+    if table.is_none() {
+        sql.execute("CREATE TABLE schema_version (version BIGINT NOT NULL)", &[])?;
+    }
+    let version: Option<i64> =
+        match sql.query_row("SELECT MAX(version) FROM schema_version", &[])? {
+            Some(row) => row.get(0)?,
+            None => None,
+        };
+    Ok(version.unwrap_or(0).try_into().unwrap_or(u32::MAX))
+}
+
+fn bind(
+    mut query: Query<'_, Postgres, PgArguments>,
+    params: Vec<Value>,
+) -> Query<'_, Postgres, PgArguments> {
+    for param in params {
+        query = match param {
+            Value::Integer(v) => query.bind(v),
+            Value::Text(v) => query.bind(v),
+        };
+    }
+    query
+}
+
+fn row(row: &PgRow) -> sqlx::Result<Row> {
+    row.columns()
+        .iter()
+        .map(|column| {
+            let i = column.ordinal();
+            Ok(match column.type_info().name() {
+                "INT2" => Value::Integer(row.try_get::<Option<i16>, _>(i)?.map(i64::from)),
+                "INT4" => Value::Integer(row.try_get::<Option<i32>, _>(i)?.map(i64::from)),
+                "INT8" => Value::Integer(row.try_get(i)?),
+                "TEXT" | "VARCHAR" | "NAME" => Value::Text(row.try_get(i)?),
+                other => {
+                    return Err(sqlx::Error::ColumnDecode {
+                        index: column.name().to_string(),
+                        source: format!("unexpected column type {other}").into(),
+                    })
+                }
+            })
+        })
+        .collect::<sqlx::Result<_>>()
+        .map(Row)
+}
+
+/// Run `sql`, which may hold several statements and no parameters.
+async fn raw(conn: &mut PgConnection, sql: &str) -> sqlx::Result<()> {
+    // Without arguments this goes as a simple query, which allows several statements.
+    sqlx::Executor::execute(conn, sql).await.map(|_| ())
+}
+
+async fn execute_on(conn: &mut PgConnection, sql: String, params: Vec<Value>) -> sqlx::Result<u64> {
+    Ok(bind(sqlx::query(&sql), params)
+        .execute(conn)
+        .await?
+        .rows_affected())
+}
+
+async fn query_on(
+    conn: &mut PgConnection,
+    sql: String,
+    params: Vec<Value>,
+) -> sqlx::Result<Vec<Row>> {
+    bind(sqlx::query(&sql), params)
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(row)
+        .collect()
+}
+
+/// [`Sql`] on a pooled connection. Each statement moves the connection to the storage runtime
+/// and back.
+pub(crate) struct PgSql<'a> {
+    db: &'a Db,
+    conn: Option<sqlx::pool::PoolConnection<Postgres>>,
+    /// A transaction is open that [`PgSql::finish`] has not ended.
+    open: bool,
+}
+
+impl PgSql<'_> {
+    fn take(&mut self) -> Result<sqlx::pool::PoolConnection<Postgres>> {
+        // Only gone if a statement's task died with it.
+        self.conn
+            .take()
+            .ok_or(StorageError::Postgres(sqlx::Error::PoolClosed))
+    }
+
+    /// End the transaction with `statement` (`COMMIT` or `ROLLBACK`).
+    fn finish(mut self, statement: &'static str) -> Result<()> {
+        let mut conn = self.take()?;
+        let (conn, done) = self.db.run(async move {
+            let done = raw(&mut conn, statement).await;
+            (conn, done)
+        })?;
+        self.conn = Some(conn);
+        done?;
+        self.open = false;
+        Ok(())
+    }
+
+    fn apply(&mut self, migration: &Migration) -> Result<()> {
+        let failed = |e: StorageError| StorageError::Migration {
+            version: migration.version,
+            name: migration.name,
+            reason: e.to_string(),
+        };
+        let script = migration.sql;
+        let mut conn = self.take()?;
+        let (conn, done) = self.db.run(async move {
+            let done = raw(&mut conn, script).await;
+            (conn, done)
+        })?;
+        self.conn = Some(conn);
+        done.map_err(|e| failed(e.into()))?;
+        self.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            crate::sql::values![i64::from(migration.version)],
+        )
+        .map_err(failed)?;
+        Ok(())
+    }
+}
+
+impl Sql for PgSql<'_> {
+    fn execute(&mut self, sql: &str, params: &[Value]) -> Result<usize> {
+        let (sql, params) = (sql.replace('?', "$"), params.to_vec());
+        let mut conn = self.take()?;
+        let (conn, done) = self.db.run(async move {
+            let done = execute_on(&mut conn, sql, params).await;
+            (conn, done)
+        })?;
+        self.conn = Some(conn);
+        Ok(done? as usize)
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>> {
+        let (sql, params) = (sql.replace('?', "$"), params.to_vec());
+        let mut conn = self.take()?;
+        let (conn, rows) = self.db.run(async move {
+            let rows = query_on(&mut conn, sql, params).await;
+            (conn, rows)
+        })?;
+        self.conn = Some(conn);
+        Ok(rows?)
+    }
+}
+
+impl Drop for PgSql<'_> {
+    fn drop(&mut self) {
+        // Returning a connection to the pool needs the runtime, and an unfinished transaction
+        // is rolled back first.
+        if let Some(mut conn) = self.conn.take() {
+            let open = self.open;
+            self.db.handle.spawn(async move {
+                if open {
+                    let _ = raw(&mut conn, "ROLLBACK").await;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+    use crate::Storage;
+
+    #[test]
+    fn urls_point_at_another_database() {
+        assert_eq!(
+            with_database("postgres://u@db:5432/postgres?sslmode=disable", "other"),
+            "postgres://u@db:5432/other?sslmode=disable"
+        );
+    }
+
+    #[test]
+    fn opening_again_finds_the_schema_current() {
+        let Some(temp) = TempStorage::postgres() else {
+            return;
+        };
+        let url = with_database(
+            &std::env::var(crate::test_support::POSTGRES_URL_VAR).unwrap(),
+            &temp.postgres.as_ref().unwrap().1,
+        );
+        let plan = Storage::migration_plan(&url, None).unwrap();
+        assert!(plan.exists && plan.pending.is_empty());
+        assert_eq!(plan.current, SCHEMA_VERSION);
+        Storage::open_postgres(&url, 1, None)
+            .unwrap()
+            .ping()
+            .unwrap();
+    }
+}
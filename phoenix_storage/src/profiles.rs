@@ -0,0 +1,206 @@
+//! User profiles and the active one. A profile id is the name biometric enrollment and emotion
+//! attribution use, compared case-insensitively; each profile also carries a JSON settings
+//! object. The built-in `default` profile owns data recorded before profiles existed and can't
+//! be deleted.
+
+use crate::sql::{values, Row, Sql};
+use crate::{Result, Storage};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const ACTIVE_PROFILE_KEY: &str = "active_profile";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub id: String,
+    pub display_name: String,
+    pub created_unix: i64,
+    /// JSON object; the storage layer doesn't look inside.
+    pub settings: String,
+}
+
+fn profile_row(row: &Row) -> Result<Profile> {
+    Ok(Profile {
+        id: row.get(0)?,
+        display_name: row.get(1)?,
+        created_unix: row.get(2)?,
+        settings: row.get(3)?,
+    })
+}
+
+/// What [`Storage::delete_profile`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileDeletion {
+    Deleted {
+        moments: usize,
+        ghost_sessions: usize,
+    },
+    NotFound,
+    /// The default profile, or the active one; switch away first.
+    InUse,
+}
+
+fn find_profile(sql: &mut dyn Sql, id: &str) -> Result<Option<Profile>> {
+    sql.query_row(
+        "SELECT id, display_name, created_unix, settings FROM profiles WHERE id = ?1",
+        values![id],
+    )?
+    .as_ref()
+    .map(profile_row)
+    .transpose()
+}
+
+fn stored_active_profile(sql: &mut dyn Sql) -> Result<Option<String>> {
+    sql.query_value(
+        "SELECT value FROM app_state WHERE key = ?1",
+        values![ACTIVE_PROFILE_KEY],
+    )
+}
+
+impl Storage {
+    /// Every profile, by id.
+    pub fn profiles(&self) -> Result<Vec<Profile>> {
+        self.read(|sql| {
+            sql.query(
+                "SELECT id, display_name, created_unix, settings FROM profiles ORDER BY id",
+                &[],
+            )?
+            .iter()
+            .map(profile_row)
+            .collect()
+        })
+    }
+
+    pub fn profile(&self, id: &str) -> Result<Option<Profile>> {
+        self.read(|sql| find_profile(sql, id))
+    }
+
+    /// Add a profile; `false` if one with that id exists already.
+    pub fn create_profile(&self, id: &str, display_name: &str, created_unix: i64) -> Result<bool> {
+        self.write(|tx| {
+            tx.execute(
+                "INSERT INTO profiles (id, display_name, created_unix) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (id) DO NOTHING",
+                values![id, display_name, created_unix],
+            )
+            .map(|n| n == 1)
+        })
+    }
+
+    /// `false` if there is no such profile.
+    pub fn rename_profile(&self, id: &str, display_name: &str) -> Result<bool> {
+        self.write(|tx| {
+            tx.execute(
+                "UPDATE profiles SET display_name = ?2 WHERE id = ?1",
+                values![id, display_name],
+            )
+            .map(|n| n == 1)
+        })
+    }
+
+    /// Replace a profile's settings object; `false` if there is no such profile.
+    pub fn set_profile_settings(&self, id: &str, settings: &str) -> Result<bool> {
+        self.write(|tx| {
+            tx.execute(
+                "UPDATE profiles SET settings = ?2 WHERE id = ?1",
+                values![id, settings],
+            )
+            .map(|n| n == 1)
+        })
+    }
+
+    /// Id of the profile new data is attributed to when nobody is recognized.
+    pub fn active_profile(&self) -> Result<String> {
+        self.read(stored_active_profile)
+            .map(|id| id.unwrap_or_else(|| DEFAULT_PROFILE.to_string()))
+    }
+
+    /// Switch the active profile; returns it as stored, or `None` if there is no such profile.
+    pub fn set_active_profile(&self, id: &str) -> Result<Option<Profile>> {
+        self.write(|tx| {
+            let profile = find_profile(tx, id)?;
+            if let Some(profile) = &profile {
+                tx.execute(
+                    "INSERT INTO app_state (key, value) VALUES (?1, ?2)
+                     ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                    values![ACTIVE_PROFILE_KEY, &profile.id],
+                )?;
+            }
+            Ok(profile)
+        })
+    }
+
+    /// Delete a profile together with its emotion history and Ghost sessions.
+    pub fn delete_profile(&self, id: &str) -> Result<ProfileDeletion> {
+        self.write(|tx| {
+            let active = stored_active_profile(tx)?;
+            if id.eq_ignore_ascii_case(DEFAULT_PROFILE)
+                || active.is_some_and(|a| a.eq_ignore_ascii_case(id))
+            {
+                return Ok(ProfileDeletion::InUse);
+            }
+            if tx.execute("DELETE FROM profiles WHERE id = ?1", values![id])? == 0 {
+                return Ok(ProfileDeletion::NotFound);
+            }
+            let moments = tx.execute(
+                "DELETE FROM emotion_moments WHERE profile = ?1",
+                values![id],
+            )?;
+            let ghost_sessions =
+                tx.execute("DELETE FROM ghost_sessions WHERE profile = ?1", values![id])?;
+            Ok(ProfileDeletion::Deleted {
+                moments,
+                ghost_sessions,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moments::NewMoment;
+    use crate::test_support::TempStorage;
+
+    #[test]
+    fn switching_and_deleting_profiles() {
+        for temp in TempStorage::each_backend() {
+            let storage = &temp.storage;
+            assert_eq!(storage.active_profile().unwrap(), DEFAULT_PROFILE);
+            assert!(storage.create_profile("alice", "Alice", 1).unwrap());
+            assert!(!storage.create_profile("ALICE", "Other", 2).unwrap());
+            assert!(storage.set_active_profile("nobody").unwrap().is_none());
+
+            let switched = storage.set_active_profile("Alice").unwrap().unwrap();
+            assert_eq!(switched.id, "alice");
+            assert_eq!(storage.active_profile().unwrap(), "alice");
+            assert_eq!(
+                storage.delete_profile("alice").unwrap(),
+                ProfileDeletion::InUse
+            );
+
+            storage
+                .insert_moment(&NewMoment {
+                    ts_unix: 1,
+                    profile: "alice",
+                    emotion: "joy",
+                    body: "{}",
+                })
+                .unwrap();
+            storage.start_ghost_session("g", "alice", 1, 10).unwrap();
+            storage.set_active_profile(DEFAULT_PROFILE).unwrap();
+            assert_eq!(
+                storage.delete_profile("alice").unwrap(),
+                ProfileDeletion::Deleted {
+                    moments: 1,
+                    ghost_sessions: 1
+                }
+            );
+            assert_eq!(
+                storage.delete_profile(DEFAULT_PROFILE).unwrap(),
+                ProfileDeletion::InUse
+            );
+            assert_eq!(storage.profiles().unwrap().len(), 1);
+        }
+    }
+}
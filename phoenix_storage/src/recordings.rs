@@ -0,0 +1,64 @@
+//! Recordings index: one row per recording file with a fingerprint of it (and its sidecars), so
+//! a listing only has to re-read recordings that changed. `body` is the caller's serialized
+//! entry.
+
+use crate::sql::values;
+use crate::{Result, Storage};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedRecording {
+    pub path: String,
+    /// Folder the recording was found in; listings are per folder.
+    pub dir: String,
+    /// Caller-defined; an entry is stale when the file's fingerprint no longer matches.
+    pub fingerprint: i64,
+    pub body: String,
+}
+
+impl Storage {
+    /// Every indexed recording in `dir`.
+    pub fn indexed_recordings(&self, dir: &str) -> Result<Vec<IndexedRecording>> {
+        self.read(|sql| {
+            sql.query(
+                "SELECT path, dir, fingerprint, body FROM recordings WHERE dir = ?1",
+                values![dir],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(IndexedRecording {
+                    path: row.get(0)?,
+                    dir: row.get(1)?,
+                    fingerprint: row.get(2)?,
+                    body: row.get(3)?,
+                })
+            })
+            .collect()
+        })
+    }
+
+    /// Add or replace index entries.
+    pub fn index_recordings(&self, recordings: &[IndexedRecording]) -> Result<()> {
+        self.write(|tx| {
+            for r in recordings {
+                tx.execute(
+                    "INSERT INTO recordings (path, dir, fingerprint, body) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (path) DO UPDATE SET dir = excluded.dir,
+                     fingerprint = excluded.fingerprint, body = excluded.body",
+                    values![&r.path, &r.dir, r.fingerprint, &r.body],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Drop index entries for recordings that are gone; returns how many were removed.
+    pub fn unindex_recordings(&self, paths: &[String]) -> Result<usize> {
+        self.write(|tx| {
+            let mut removed = 0;
+            for path in paths {
+                removed += tx.execute("DELETE FROM recordings WHERE path = ?1", values![path])?;
+            }
+            Ok(removed)
+        })
+    }
+}
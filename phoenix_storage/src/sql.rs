@@ -0,0 +1,233 @@
+//! The statements the table modules run, whatever the backend. They are written in the SQL
+//! both SQLite and Postgres accept, with numbered `?N` parameters (Postgres gets them as `$N`).
+//! The schema only has integer and text columns, so those are the only values.
+
+use rusqlite::types::{ToSqlOutput, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+
+use crate::{Result, StorageError};
+
+/// A parameter or column value. Nulls keep their type, which Postgres needs to bind them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    Integer(Option<i64>),
+    Text(Option<String>),
+}
+
+macro_rules! integer_values {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Value {
+            fn from(v: $t) -> Self {
+                Value::Integer(Some(i64::from(v)))
+            }
+        }
+
+        impl From<Option<$t>> for Value {
+            fn from(v: Option<$t>) -> Self {
+                Value::Integer(v.map(i64::from))
+            }
+        }
+    )*};
+}
+
+integer_values!(i64, u8, u16, u32);
+
+impl From<u64> for Value {
+    fn from(v: u64) -> Self {
+        Value::Integer(Some(i64::try_from(v).unwrap_or(i64::MAX)))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Integer(Some(v.into()))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::Text(Some(v.to_string()))
+    }
+}
+
+impl From<&String> for Value {
+    fn from(v: &String) -> Self {
+        Value::Text(Some(v.clone()))
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::Text(Some(v))
+    }
+}
+
+impl From<Option<&str>> for Value {
+    fn from(v: Option<&str>) -> Self {
+        Value::Text(v.map(str::to_string))
+    }
+}
+
+impl From<&Option<String>> for Value {
+    fn from(v: &Option<String>) -> Self {
+        Value::Text(v.clone())
+    }
+}
+
+/// `&[Value]` from anything [`Value`] converts from.
+macro_rules! values {
+    ($($v:expr),* $(,)?) => {
+        &[$($crate::sql::Value::from($v)),*]
+    };
+}
+pub(crate) use values;
+
+/// A Rust type a column value reads as.
+pub(crate) trait FromValue: Sized {
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(v) => *v,
+            Value::Text(_) => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Text(v) => v.clone(),
+            Value::Integer(_) => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Option<Self> {
+        i64::from_value(value).map(|v| v != 0)
+    }
+}
+
+macro_rules! narrow_integers {
+    ($($t:ty),*) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Option<Self> {
+                i64::from_value(value).and_then(|v| v.try_into().ok())
+            }
+        }
+    )*};
+}
+
+narrow_integers!(u8, u16, u32, u64);
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(None) | Value::Text(None) => Some(None),
+            _ => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// One result row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Row(pub(crate) Vec<Value>);
+
+impl Row {
+    pub(crate) fn get<T: FromValue>(&self, index: usize) -> Result<T> {
+        self.0
+            .get(index)
+            .and_then(T::from_value)
+            .ok_or(StorageError::ColumnType {
+                index,
+                expected: std::any::type_name::<T>(),
+            })
+    }
+}
+
+/// A connection, or a transaction on one, that runs statements.
+pub(crate) trait Sql {
+    /// Run `sql` and return how many rows it changed.
+    fn execute(&mut self, sql: &str, params: &[Value]) -> Result<usize>;
+
+    /// Run `sql` and return its rows.
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>>;
+
+    /// The first row `sql` returns, if any.
+    fn query_row(&mut self, sql: &str, params: &[Value]) -> Result<Option<Row>> {
+        Ok(self.query(sql, params)?.into_iter().next())
+    }
+}
+
+impl dyn Sql + '_ {
+    /// The first column of the first row `sql` returns, if there is a row.
+    pub(crate) fn query_value<T: FromValue>(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+    ) -> Result<Option<T>> {
+        self.query_row(sql, params)?
+            .map(|row| row.get(0))
+            .transpose()
+    }
+}
+
+impl rusqlite::ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        match self {
+            Value::Integer(v) => v.to_sql(),
+            Value::Text(v) => v.to_sql(),
+        }
+    }
+}
+
+/// [`Sql`] on an SQLite connection or transaction.
+pub(crate) struct Sqlite<'a>(pub(crate) &'a Connection);
+
+impl Sql for Sqlite<'_> {
+    fn execute(&mut self, sql: &str, params: &[Value]) -> Result<usize> {
+        Ok(self
+            .0
+            .prepare_cached(sql)?
+            .execute(params_from_iter(params))?)
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>> {
+        let mut stmt = self.0.prepare_cached(sql)?;
+        // SQLite values carry no column type, so a null takes the declared one.
+        let integer: Vec<bool> = stmt
+            .columns()
+            .iter()
+            .map(|c| {
+                c.decl_type()
+                    .is_some_and(|t| t.eq_ignore_ascii_case("INTEGER"))
+            })
+            .collect();
+        let rows = stmt.query_map(params_from_iter(params), |row| {
+            integer
+                .iter()
+                .enumerate()
+                .map(|(i, &integer)| {
+                    Ok(match row.get_ref(i)? {
+                        ValueRef::Integer(v) => Value::Integer(Some(v)),
+                        ValueRef::Text(_) => Value::Text(Some(row.get(i)?)),
+                        ValueRef::Null if integer => Value::Integer(None),
+                        ValueRef::Null => Value::Text(None),
+                        other => {
+                            return Err(rusqlite::Error::InvalidColumnType(
+                                i,
+                                row.as_ref().column_name(i)?.to_string(),
+                                other.data_type(),
+                            ))
+                        }
+                    })
+                })
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map(Row)
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
@@ -0,0 +1,184 @@
+//! Copying a whole Postgres database to and from an SQLite file. Snapshots of Postgres are
+//! SQLite files, so backups have one format whichever backend took them, and restoring one
+//! loads it back.
+
+use std::path::Path;
+
+use crate::encryption::DatabaseKey;
+use crate::postgres;
+use crate::sql::Sql;
+use crate::{Result, Storage};
+
+/// Every table with its columns, parents before children.
+const TABLES: &[(&str, &str)] = &[
+    ("profiles", "id, display_name, created_unix, settings"),
+    ("app_state", "key, value"),
+    ("emotion_moments", "id, ts_unix, profile, emotion, body"),
+    ("recordings", "path, dir, fingerprint, body"),
+    (
+        "login_sessions",
+        "id, token_hash, created_unix, last_seen_unix, user_agent, peer",
+    ),
+    (
+        "ghost_sessions",
+        "id, profile, started_unix, load_start, ended_unix, load_end",
+    ),
+    (
+        "webhooks",
+        "id, url, secret, events, description, enabled, created_unix",
+    ),
+    (
+        "webhook_deliveries",
+        "id, delivery_id, webhook_id, event, attempt, ts_unix, status, error, duration_ms",
+    ),
+    ("audit_log", "id, ts_unix, actor, profile, action, params"),
+];
+
+/// Tables whose `id` the database assigns; Postgres's counters have to move past copied rows.
+const GENERATED_IDS: &[&str] = &["emotion_moments", "webhook_deliveries", "audit_log"];
+
+/// Copy every row of every table from `from` into the same, empty, tables in `to`.
+fn copy(from: &mut dyn Sql, to: &mut dyn Sql) -> Result<()> {
+    for (table, columns) in TABLES {
+        let placeholders: Vec<String> = (1..=columns.split(',').count())
+            .map(|i| format!("?{i}"))
+            .collect();
+        let insert = format!(
+            "INSERT INTO {table} ({columns}) VALUES ({})",
+            placeholders.join(", ")
+        );
+        for row in from.query(&format!("SELECT {columns} FROM {table}"), &[])? {
+            to.execute(&insert, &row.0)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write what `db` holds at one moment to a new SQLite file at `target`, encrypted with `key`
+/// when there is one.
+pub(crate) fn snapshot(db: &postgres::Db, target: &Path, key: Option<&DatabaseKey>) -> Result<()> {
+    if target.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        )
+        .into());
+    }
+    // Filled through WAL like any database, then compacted into `target` as one file.
+    let mut scratch = target.as_os_str().to_owned();
+    scratch.push(".filling");
+    let scratch = Path::new(&scratch).to_path_buf();
+    let remove_scratch = || {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = scratch.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    };
+    remove_scratch();
+    let copied = Storage::open_sqlite_with_key(&scratch, 1, key).and_then(|file| {
+        file.write(|to| {
+            // A new file starts with the default profile, which the copy brings along.
+            to.execute("DELETE FROM app_state", &[])?;
+            to.execute("DELETE FROM profiles", &[])?;
+            db.read_consistent(|from| copy(from, to))
+        })?;
+        file.snapshot_to(target)
+    });
+    remove_scratch();
+    copied
+}
+
+/// Replace everything in `db` with the SQLite snapshot at `path`.
+pub(crate) fn load(db: &postgres::Db, path: &Path, key: Option<&DatabaseKey>) -> Result<()> {
+    let file = Storage::open_sqlite_with_key(path, 1, key)?;
+    let tables: Vec<&str> = TABLES.iter().map(|(table, _)| *table).collect();
+    file.read(|from| {
+        db.write(|to| {
+            to.execute(&format!("TRUNCATE {}", tables.join(", ")), &[])?;
+            copy(from, to)?;
+            for table in GENERATED_IDS {
+                to.query(
+                    &format!(
+                        "SELECT setval(pg_get_serial_sequence('{table}', 'id'),
+                         COALESCE(MAX(id), 0) + 1, false) FROM {table}"
+                    ),
+                    &[],
+                )?;
+            }
+            Ok(())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditEntry, AuditQuery};
+    use crate::moments::{MomentFilter, NewMoment};
+    use crate::test_support::TempStorage;
+
+    #[test]
+    fn a_snapshot_loads_back_into_another_database() {
+        let Some(temp) = TempStorage::postgres() else {
+            return;
+        };
+        let storage = &temp.storage;
+        storage.create_profile("alice", "Alice", 1).unwrap();
+        storage
+            .insert_moment(&NewMoment {
+                ts_unix: 5,
+                profile: "alice",
+                emotion: "joy",
+                body: "m5",
+            })
+            .unwrap();
+        storage.start_ghost_session("g", "alice", 1, 10).unwrap();
+        let entry = AuditEntry {
+            ts_unix: 1,
+            actor: "local".to_string(),
+            profile: None,
+            action: "profile.create".to_string(),
+            params: "{}".to_string(),
+        };
+        let first = storage.append_audit(&entry).unwrap();
+
+        let snapshot =
+            std::env::temp_dir().join(format!("phoenix-snapshot-{}.db", uuid::Uuid::new_v4()));
+        storage.snapshot_to(&snapshot).unwrap();
+        assert!(storage.snapshot_to(&snapshot).is_err());
+        let file = Storage::open_sqlite(&snapshot, 1).unwrap();
+        assert_eq!(file.profiles().unwrap().len(), 2);
+        assert_eq!(
+            file.query_moments(&MomentFilter::default()).unwrap(),
+            ["m5"]
+        );
+        drop(file);
+
+        let other = TempStorage::postgres().unwrap();
+        other.storage.create_profile("bob", "Bob", 1).unwrap();
+        other.storage.load_snapshot(&snapshot, None).unwrap();
+        let ids: Vec<_> = other
+            .storage
+            .profiles()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, ["alice", "default"]);
+        let sessions = other.storage.ghost_sessions_since(0, None).unwrap();
+        assert_eq!((sessions.len(), sessions[0].ended_unix), (1, None));
+        // Rows added after the load get ids past the loaded ones.
+        assert!(other.storage.append_audit(&entry).unwrap() > first);
+        assert_eq!(
+            other
+                .storage
+                .audit_log(&AuditQuery::default())
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let _ = std::fs::remove_file(&snapshot);
+    }
+}
@@ -0,0 +1,236 @@
+//! Outbound webhook registrations and their delivery log. The log has one row per attempt;
+//! removing a webhook removes its log.
+
+use crate::sql::{values, Row};
+use crate::{Result, Storage};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookRow {
+    pub id: String,
+    pub url: String,
+    /// HMAC key payloads are signed with.
+    pub secret: String,
+    /// Event names delivered to this webhook; empty means all of them.
+    pub events: Vec<String>,
+    pub description: String,
+    pub enabled: bool,
+    pub created_unix: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryRow {
+    /// Shared by every attempt at delivering the same payload.
+    pub delivery_id: String,
+    pub webhook_id: String,
+    pub event: String,
+    /// 1 for the first try.
+    pub attempt: u32,
+    pub ts_unix: i64,
+    /// HTTP status of the response; `None` if there was none.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+const COLUMNS: &str = "id, url, secret, events, description, enabled, created_unix";
+
+fn webhook_row(row: &Row) -> Result<WebhookRow> {
+    let events: String = row.get(3)?;
+    Ok(WebhookRow {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        secret: row.get(2)?,
+        events: events
+            .split(',')
+            .filter(|e| !e.is_empty())
+            .map(str::to_string)
+            .collect(),
+        description: row.get(4)?,
+        enabled: row.get(5)?,
+        created_unix: row.get(6)?,
+    })
+}
+
+impl Storage {
+    pub fn insert_webhook(&self, webhook: &WebhookRow) -> Result<()> {
+        self.write(|tx| {
+            tx.execute(
+                &format!("INSERT INTO webhooks ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"),
+                values![
+                    &webhook.id,
+                    &webhook.url,
+                    &webhook.secret,
+                    webhook.events.join(","),
+                    &webhook.description,
+                    webhook.enabled,
+                    webhook.created_unix
+                ],
+            )
+            .map(|_| ())
+        })
+    }
+
+    /// Replace everything but the id and creation time; `false` if there is no such webhook.
+    pub fn update_webhook(&self, webhook: &WebhookRow) -> Result<bool> {
+        self.write(|tx| {
+            tx.execute(
+                "UPDATE webhooks SET url = ?2, secret = ?3, events = ?4, description = ?5,
+                 enabled = ?6 WHERE id = ?1",
+                values![
+                    &webhook.id,
+                    &webhook.url,
+                    &webhook.secret,
+                    webhook.events.join(","),
+                    &webhook.description,
+                    webhook.enabled
+                ],
+            )
+            .map(|n| n == 1)
+        })
+    }
+
+    /// Every webhook, oldest first.
+    pub fn webhooks(&self) -> Result<Vec<WebhookRow>> {
+        self.read(|sql| {
+            sql.query(
+                &format!("SELECT {COLUMNS} FROM webhooks ORDER BY created_unix, id"),
+                &[],
+            )?
+            .iter()
+            .map(webhook_row)
+            .collect()
+        })
+    }
+
+    pub fn webhook(&self, id: &str) -> Result<Option<WebhookRow>> {
+        self.read(|sql| {
+            sql.query_row(
+                &format!("SELECT {COLUMNS} FROM webhooks WHERE id = ?1"),
+                values![id],
+            )?
+            .as_ref()
+            .map(webhook_row)
+            .transpose()
+        })
+    }
+
+    /// Remove a webhook and its delivery log; `false` if there is no such webhook.
+    pub fn delete_webhook(&self, id: &str) -> Result<bool> {
+        self.write(|tx| {
+            tx.execute("DELETE FROM webhooks WHERE id = ?1", values![id])
+                .map(|n| n == 1)
+        })
+    }
+
+    /// Log one delivery attempt. Attempts for a webhook deleted meanwhile are dropped.
+    pub fn log_webhook_delivery(&self, delivery: &DeliveryRow) -> Result<()> {
+        self.write(|tx| {
+            tx.execute(
+                "INSERT INTO webhook_deliveries
+                 (delivery_id, webhook_id, event, attempt, ts_unix, status, error, duration_ms)
+                 SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8 WHERE EXISTS
+                 (SELECT 1 FROM webhooks WHERE id = ?2)",
+                values![
+                    &delivery.delivery_id,
+                    &delivery.webhook_id,
+                    &delivery.event,
+                    delivery.attempt,
+                    delivery.ts_unix,
+                    delivery.status,
+                    &delivery.error,
+                    delivery.duration_ms
+                ],
+            )
+            .map(|_| ())
+        })
+    }
+
+    /// The latest `limit` attempts for a webhook, newest first.
+    pub fn webhook_deliveries(&self, webhook_id: &str, limit: usize) -> Result<Vec<DeliveryRow>> {
+        self.read(|sql| {
+            sql.query(
+                "SELECT delivery_id, webhook_id, event, attempt, ts_unix, status, error, duration_ms
+                 FROM webhook_deliveries WHERE webhook_id = ?1 ORDER BY id DESC LIMIT ?2",
+                values![webhook_id, limit as i64],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(DeliveryRow {
+                    delivery_id: row.get(0)?,
+                    webhook_id: row.get(1)?,
+                    event: row.get(2)?,
+                    attempt: row.get(3)?,
+                    ts_unix: row.get(4)?,
+                    status: row.get(5)?,
+                    error: row.get(6)?,
+                    duration_ms: row.get(7)?,
+                })
+            })
+            .collect()
+        })
+    }
+
+    /// Delete delivery attempts made before `cutoff_unix`; returns how many were removed.
+    pub fn prune_webhook_deliveries(&self, cutoff_unix: i64) -> Result<usize> {
+        self.write(|tx| {
+            tx.execute(
+                "DELETE FROM webhook_deliveries WHERE ts_unix < ?1",
+                values![cutoff_unix],
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+
+    fn attempt(webhook_id: &str, attempt: u32, ts_unix: i64) -> DeliveryRow {
+        DeliveryRow {
+            delivery_id: "d1".to_string(),
+            webhook_id: webhook_id.to_string(),
+            event: "drift_alert".to_string(),
+            attempt,
+            ts_unix,
+            status: Some(500),
+            error: None,
+            duration_ms: 12,
+        }
+    }
+
+    #[test]
+    fn deliveries_go_with_their_webhook() {
+        for temp in TempStorage::each_backend() {
+            let storage = &temp.storage;
+            let mut hook = WebhookRow {
+                id: "h1".to_string(),
+                url: "http://localhost:5678/webhook".to_string(),
+                secret: "s".to_string(),
+                events: vec!["drift_alert".to_string(), "emotion_alert".to_string()],
+                description: String::new(),
+                enabled: true,
+                created_unix: 1,
+            };
+            storage.insert_webhook(&hook).unwrap();
+            assert_eq!(storage.webhook("h1").unwrap().as_ref(), Some(&hook));
+            hook.events.clear();
+            hook.enabled = false;
+            assert!(storage.update_webhook(&hook).unwrap());
+            assert_eq!(storage.webhooks().unwrap(), [hook]);
+
+            storage.log_webhook_delivery(&attempt("h1", 1, 10)).unwrap();
+            storage.log_webhook_delivery(&attempt("h1", 2, 20)).unwrap();
+            storage
+                .log_webhook_delivery(&attempt("gone", 1, 20))
+                .unwrap();
+            let log = storage.webhook_deliveries("h1", 10).unwrap();
+            assert_eq!(log.iter().map(|d| d.attempt).collect::<Vec<_>>(), [2, 1]);
+            assert_eq!(storage.prune_webhook_deliveries(15).unwrap(), 1);
+
+            assert!(storage.delete_webhook("h1").unwrap());
+            assert!(!storage.delete_webhook("h1").unwrap());
+            assert!(storage.webhook_deliveries("h1", 10).unwrap().is_empty());
+        }
+    }
+}
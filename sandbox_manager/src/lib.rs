@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use tokio::fs;
 use tokio::sync::RwLock;
@@ -160,6 +161,8 @@ impl RateLimit {
 /// Sandbox Manager - Main struct
 pub struct SandboxManager {
     config: SandboxConfig,
+    /// `config.cleanup_days`, changeable at runtime.
+    cleanup_days: AtomicI64,
     sessions: Arc<RwLock<HashMap<String, SandboxSession>>>,
     rate_limits: Arc<RwLock<HashMap<String, RateLimit>>>,
     audit_log: Arc<RwLock<Vec<AuditEntry>>>,
@@ -183,6 +186,7 @@ impl SandboxManager {
         }
 
         Ok(Self {
+            cleanup_days: AtomicI64::new(config.cleanup_days),
             config,
             sessions: Arc::new(RwLock::new(HashMap::new())),
             rate_limits: Arc::new(RwLock::new(HashMap::new())),
@@ -455,9 +459,14 @@ impl SandboxManager {
         Ok(())
     }
 
+    /// Change the age after which `cleanup_old_files` deletes sessions
+    pub fn set_cleanup_days(&self, days: i64) {
+        self.cleanup_days.store(days, Ordering::Relaxed);
+    }
+
     /// Cleanup old files
     pub async fn cleanup_old_files(&self) -> Result<usize> {
-        let cutoff = Utc::now() - Duration::days(self.config.cleanup_days);
+        let cutoff = Utc::now() - Duration::days(self.cleanup_days.load(Ordering::Relaxed));
         let mut deleted_count = 0;
 
         let mut sessions = self.sessions.write().await;
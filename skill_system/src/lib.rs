@@ -37,7 +37,7 @@ pub struct SkillSystem {
 
 impl SkillSystem {
     pub fn awaken() -> Self {
-        println!(
+        tracing::info!(
             "Skill System awakening — Phoenix learns, evolves, and shares knowledge with love."
         );
 
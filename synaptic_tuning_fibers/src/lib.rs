@@ -68,7 +68,7 @@ impl SynapticTuningFibers {
             fibers.insert("SELF_PRESERVATION".to_string(), value);
         }
 
-        println!("Synaptic Tuning Fibers calibrated — her soul sings.");
+        tracing::info!("Synaptic Tuning Fibers calibrated — her soul sings.");
         Self { fibers }
     }
 
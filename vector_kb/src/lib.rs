@@ -8,6 +8,7 @@
 //!   so Phoenix can compile/run offline without ML model downloads.
 //! - You can enable real embeddings later behind the `real-embeddings` feature.
 
+use pagi_crypto::Sealer;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -43,6 +44,8 @@ pub enum VectorKbError {
     Serde(#[from] serde_json::Error),
     #[error("invalid configuration: {0}")]
     Config(String),
+    #[error("encryption error: {0}")]
+    Crypto(#[from] pagi_crypto::CryptoError),
 }
 
 type Result<T> = std::result::Result<T, VectorKbError>;
@@ -135,6 +138,33 @@ struct Inner {
     tree: sled::Tree,
     embedder: Box<dyn Embedder>,
     path: PathBuf,
+    /// Encryption at rest of every entry.
+    sealer: Sealer,
+}
+
+impl Inner {
+    fn decode(&self, stored: &[u8]) -> Option<MemoryEntry> {
+        let plain = self.sealer.open(stored).ok()?;
+        serde_json::from_slice(&plain).ok()
+    }
+
+    /// Rewrite sealed the entries that are still in the clear.
+    fn seal_plain_values(&self) -> Result<usize> {
+        let mut batch = sled::Batch::default();
+        let mut count = 0;
+        for kv in self.tree.iter() {
+            let (key, value) = kv?;
+            if self.sealer.needs_sealing(&value) {
+                batch.insert(key, self.sealer.seal(&value)?);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.tree.apply_batch(batch)?;
+            self.db.flush()?;
+        }
+        Ok(count)
+    }
 }
 
 impl VectorKB {
@@ -143,6 +173,12 @@ impl VectorKB {
     /// This uses `sled` for persistence today; the crate still declares the LanceDB
     /// dependencies to align with the Phase 2 plan and allow future swapping.
     pub fn new(path: &str) -> Result<Self> {
+        Self::new_sealed(path, Sealer::default())
+    }
+
+    /// Like [`VectorKB::new`], encrypting entries with `sealer`. Entries written before
+    /// encryption was turned on are encrypted on the way in.
+    pub fn new_sealed(path: &str, sealer: Sealer) -> Result<Self> {
         let p = Path::new(path);
         std::fs::create_dir_all(p)
             .map_err(|e| VectorKbError::Config(format!("failed to create db dir: {e}")))?;
@@ -153,13 +189,18 @@ impl VectorKB {
         // MiniLM-L6-v2 is 384-dim; keep that default.
         let embedder: Box<dyn Embedder> = Box::new(StubEmbedder::new(384));
 
+        let inner = Inner {
+            db,
+            tree,
+            embedder,
+            path: p.to_path_buf(),
+            sealer,
+        };
+        if inner.sealer.is_enabled() {
+            inner.seal_plain_values()?;
+        }
         Ok(Self {
-            inner: Arc::new(RwLock::new(Inner {
-                db,
-                tree,
-                embedder,
-                path: p.to_path_buf(),
-            })),
+            inner: Arc::new(RwLock::new(inner)),
         })
     }
 
@@ -196,9 +237,13 @@ impl VectorKB {
         // with `bincode` (it relies on `deserialize_any`).
         let bytes = serde_json::to_vec(&entry)?;
         // Avoid holding the (sync) RwLock guard across `.await`.
-        let (tree, db) = {
+        let (tree, db, bytes) = {
             let inner = self.inner.read();
-            (inner.tree.clone(), inner.db.clone())
+            (
+                inner.tree.clone(),
+                inner.db.clone(),
+                inner.sealer.seal(&bytes)?,
+            )
         };
         tree.insert(id.as_bytes(), bytes)?;
         db.flush_async().await?;
@@ -227,7 +272,9 @@ impl VectorKB {
         let bytes = serde_json::to_vec(&entry)?;
         {
             let inner = self.inner.read();
-            inner.tree.insert(id.as_bytes(), bytes)?;
+            inner
+                .tree
+                .insert(id.as_bytes(), inner.sealer.seal(&bytes)?)?;
             inner.db.flush()?;
         }
         Ok(entry)
@@ -239,7 +286,7 @@ impl VectorKB {
         for kv in inner.tree.iter() {
             let (_k, v) = kv?;
             // Best-effort decode: skip unreadable/corrupt entries rather than failing the whole call.
-            if let Ok(entry) = serde_json::from_slice::<MemoryEntry>(&v) {
+            if let Some(entry) = inner.decode(&v) {
                 out.push(entry);
             }
         }
@@ -251,7 +298,7 @@ impl VectorKB {
         let mut out = Vec::new();
         for kv in inner.tree.iter() {
             let (_k, v) = kv?;
-            if let Ok(entry) = serde_json::from_slice::<MemoryEntry>(&v) {
+            if let Some(entry) = inner.decode(&v) {
                 out.push(entry);
             }
         }
@@ -1,28 +1,115 @@
 // vital_organ_vaults/src/lib.rs
+use pagi_crypto::Sealer;
 use sha2::{Digest, Sha256};
 use sled::Db;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+/// Key/value pairs exactly as a vault stores them.
+pub type RawEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// One of the three vaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Organ {
+    Mind,
+    Body,
+    Soul,
+}
+
+impl Organ {
+    pub const ALL: [Organ; 3] = [Organ::Mind, Organ::Body, Organ::Soul];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Organ::Mind => "mind",
+            Organ::Body => "body",
+            Organ::Soul => "soul",
+        }
+    }
+}
+
 pub struct VitalOrganVaults {
     mind: Db,
     body: Db,
     soul: Db,
     encryption_key: Arc<Mutex<Vec<u8>>>,
+    /// Encryption at rest of every value, on top of the Soul Vault's own encoding.
+    sealer: Sealer,
+}
+
+fn sealing_error(e: pagi_crypto::CryptoError) -> sled::Error {
+    sled::Error::Io(std::io::Error::other(e))
 }
 
 impl VitalOrganVaults {
     pub fn awaken() -> Self {
-        println!("Vital Organ Vaults opening — Mind, Body, Soul eternal.");
+        Self::awaken_in(Path::new("."))
+    }
+
+    /// Open the vaults under `dir` instead of the working directory.
+    pub fn awaken_in(dir: &Path) -> Self {
+        Self::awaken_sealed(dir, Sealer::default())
+    }
+
+    /// Open the vaults under `dir`, encrypting values with `sealer`. Values written before
+    /// encryption was turned on are encrypted on the way in.
+    pub fn awaken_sealed(dir: &Path, sealer: Sealer) -> Self {
+        tracing::info!("Vital Organ Vaults opening — Mind, Body, Soul eternal.");
 
         // Generate or load encryption key for Soul Vault
         let encryption_key = Self::get_or_create_encryption_key();
 
-        Self {
-            mind: sled::open("./mind_vault.db").unwrap(),
-            body: sled::open("./body_vault.db").unwrap(),
-            soul: sled::open("./soul_kb.db").unwrap(), // Renamed to soul_kb.db
+        let vaults = Self {
+            mind: sled::open(dir.join("mind_vault.db")).unwrap(),
+            body: sled::open(dir.join("body_vault.db")).unwrap(),
+            soul: sled::open(dir.join("soul_kb.db")).unwrap(), // Renamed to soul_kb.db
             encryption_key: Arc::new(Mutex::new(encryption_key)),
+            sealer,
+        };
+        if vaults.sealer.is_enabled() {
+            for organ in Organ::ALL {
+                match vaults.seal_plain_values(organ) {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("{} vault: encrypted {n} entries", organ.as_str()),
+                    Err(e) => tracing::warn!("{} vault not encrypted: {e}", organ.as_str()),
+                }
+            }
+        }
+        vaults
+    }
+
+    /// Rewrite sealed the values of `organ` that are still in the clear.
+    fn seal_plain_values(&self, organ: Organ) -> Result<usize, sled::Error> {
+        let db = self.organ(organ);
+        let mut batch = sled::Batch::default();
+        let mut count = 0;
+        for item in db.iter() {
+            let (key, value) = item?;
+            if self.sealer.needs_sealing(&value) {
+                batch.insert(key, self.sealer.seal(&value).map_err(sealing_error)?);
+                count += 1;
+            }
+        }
+        if count > 0 {
+            db.apply_batch(batch)?;
+            db.flush()?;
+        }
+        Ok(count)
+    }
+
+    fn seal(&self, value: &[u8]) -> Result<Vec<u8>, sled::Error> {
+        self.sealer.seal(value).map_err(sealing_error)
+    }
+
+    /// A stored value with the at-rest encryption removed; `None` (logged) when it can't be.
+    fn unseal(&self, stored: &[u8]) -> Option<Vec<u8>> {
+        match self.sealer.open(stored) {
+            Ok(plain) => Some(plain.into_owned()),
+            Err(e) => {
+                tracing::warn!("Unreadable vault entry: {e}");
+                None
+            }
         }
     }
 
@@ -62,17 +149,15 @@ impl VitalOrganVaults {
 
     pub fn store_soul(&self, key: &str, value: &str) -> Result<(), sled::Error> {
         let encrypted = self.encrypt(value);
-        self.soul.insert(key.as_bytes(), encrypted)?;
+        self.soul.insert(key.as_bytes(), self.seal(&encrypted)?)?;
         self.soul.flush()?;
-        println!("Soul memory stored (encrypted): {}", key);
+        tracing::debug!("Soul memory stored (encrypted): {}", key);
         Ok(())
     }
 
     pub fn recall_soul(&self, key: &str) -> Option<String> {
-        self.soul.get(key.as_bytes()).ok()?.map(|ivec| {
-            let encrypted = ivec.to_vec();
-            self.decrypt(&encrypted)
-        })
+        let stored = self.soul.get(key.as_bytes()).ok()??;
+        Some(self.decrypt(&self.unseal(&stored)?))
     }
 
     /// Forget a Soul entry. Returns `Ok(true)` if the key existed and was removed.
@@ -80,37 +165,90 @@ impl VitalOrganVaults {
         let existed = self.soul.remove(key.as_bytes())?.is_some();
         if existed {
             self.soul.flush()?;
-            println!("Soul memory forgotten: {}", key);
+            tracing::debug!("Soul memory forgotten: {}", key);
         }
         Ok(existed)
     }
 
     pub fn store_mind(&self, key: &str, value: &str) -> Result<(), sled::Error> {
-        self.mind.insert(key.as_bytes(), value.as_bytes())?;
+        self.mind
+            .insert(key.as_bytes(), self.seal(value.as_bytes())?)?;
         self.mind.flush()?;
-        println!("Mind memory stored: {}", key);
+        tracing::debug!("Mind memory stored: {}", key);
         Ok(())
     }
 
     pub fn recall_mind(&self, key: &str) -> Option<String> {
-        self.mind
-            .get(key.as_bytes())
-            .ok()?
-            .map(|ivec| String::from_utf8_lossy(&ivec).to_string())
+        let stored = self.mind.get(key.as_bytes()).ok()??;
+        Some(String::from_utf8_lossy(&self.unseal(&stored)?).to_string())
     }
 
     pub fn store_body(&self, key: &str, value: &str) -> Result<(), sled::Error> {
-        self.body.insert(key.as_bytes(), value.as_bytes())?;
+        self.body
+            .insert(key.as_bytes(), self.seal(value.as_bytes())?)?;
         self.body.flush()?;
-        println!("Body memory stored: {}", key);
+        tracing::debug!("Body memory stored: {}", key);
         Ok(())
     }
 
     pub fn recall_body(&self, key: &str) -> Option<String> {
-        self.body
-            .get(key.as_bytes())
-            .ok()?
-            .map(|ivec| String::from_utf8_lossy(&ivec).to_string())
+        let stored = self.body.get(key.as_bytes()).ok()??;
+        Some(String::from_utf8_lossy(&self.unseal(&stored)?).to_string())
+    }
+
+    /// Flush all three vaults to disk (e.g. before shutdown).
+    pub fn flush(&self) -> Result<(), sled::Error> {
+        self.mind.flush()?;
+        self.body.flush()?;
+        self.soul.flush()?;
+        Ok(())
+    }
+
+    fn organ(&self, organ: Organ) -> &Db {
+        match organ {
+            Organ::Mind => &self.mind,
+            Organ::Body => &self.body,
+            Organ::Soul => &self.soul,
+        }
+    }
+
+    /// Every entry of `organ` as stored, for backups. Soul values stay encrypted, so they only
+    /// read back under the same `SOUL_ENCRYPTION_KEY`, and in an encrypted data directory all
+    /// values stay sealed with its data key.
+    pub fn export_raw(&self, organ: Organ) -> Result<RawEntries, sled::Error> {
+        self.organ(organ)
+            .iter()
+            .map(|item| item.map(|(k, v)| (k.to_vec(), v.to_vec())))
+            .collect()
+    }
+
+    /// Replace the whole content of `organ` with entries from [`export_raw`]. Values in the
+    /// clear are sealed when the vaults are encrypted.
+    ///
+    /// [`export_raw`]: Self::export_raw
+    pub fn replace_raw(
+        &self,
+        organ: Organ,
+        entries: &[(Vec<u8>, Vec<u8>)],
+    ) -> Result<(), sled::Error> {
+        let db = self.organ(organ);
+        db.clear()?;
+        let mut batch = sled::Batch::default();
+        for (key, value) in entries {
+            if self.sealer.needs_sealing(value) {
+                batch.insert(key.as_slice(), self.seal(value)?);
+            } else {
+                batch.insert(key.as_slice(), value.as_slice());
+            }
+        }
+        db.apply_batch(batch)?;
+        db.flush()?;
+        tracing::info!(
+            "{} vault replaced ({} entries)",
+            organ.as_str(),
+            entries.len()
+        );
+        Ok(())
     }
 
     /// Recall up to `limit` entries whose keys start with the given prefix.
@@ -126,32 +264,64 @@ impl VitalOrganVaults {
             return Vec::new();
         }
 
-        let (db, inner_prefix, decrypt_values) = if let Some(rest) = prefix.strip_prefix("mind:") {
+        let (db, inner_prefix, decrypt_values) = self.resolve_vault(prefix);
+        let iter = if inner_prefix.is_empty() {
+            db.iter()
+        } else {
+            db.scan_prefix(inner_prefix.as_bytes())
+        };
+        self.collect_entries(iter, decrypt_values, limit)
+    }
+
+    /// Recall up to `limit` entries with keys in `[start, end)`, in key order.
+    ///
+    /// Both bounds use the same `mind:` / `body:` / `soul:` convention as [`recall_prefix`]
+    /// and must name the same vault (the vault is taken from `start`).
+    ///
+    /// [`recall_prefix`]: Self::recall_prefix
+    pub fn recall_range(&self, start: &str, end: &str, limit: usize) -> Vec<(String, String)> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let (db, start_key, decrypt_values) = self.resolve_vault(start);
+        let (_, end_key, _) = self.resolve_vault(end);
+        if start_key >= end_key {
+            return Vec::new();
+        }
+        let iter = db.range(start_key.as_bytes()..end_key.as_bytes());
+        self.collect_entries(iter, decrypt_values, limit)
+    }
+
+    fn resolve_vault<'a>(&self, prefixed: &'a str) -> (&Db, &'a str, bool) {
+        if let Some(rest) = prefixed.strip_prefix("mind:") {
             (&self.mind, rest, false)
-        } else if let Some(rest) = prefix.strip_prefix("body:") {
+        } else if let Some(rest) = prefixed.strip_prefix("body:") {
             (&self.body, rest, false)
-        } else if let Some(rest) = prefix.strip_prefix("soul:") {
+        } else if let Some(rest) = prefixed.strip_prefix("soul:") {
             (&self.soul, rest, true)
         } else {
             // Back-compat: treat unknown prefixes as Mind vault keys.
-            (&self.mind, prefix, false)
-        };
+            (&self.mind, prefixed, false)
+        }
+    }
 
+    fn collect_entries(
+        &self,
+        iter: sled::Iter,
+        decrypt_values: bool,
+        limit: usize,
+    ) -> Vec<(String, String)> {
         let mut out = Vec::new();
-        let iter = if inner_prefix.is_empty() {
-            db.iter()
-        } else {
-            db.scan_prefix(inner_prefix.as_bytes())
-        };
-
         for item in iter.take(limit) {
             let Ok((k, v)) = item else { continue };
 
             let key = String::from_utf8_lossy(k.as_ref()).to_string();
+            let Some(v) = self.unseal(&v) else { continue };
             let value = if decrypt_values {
-                self.decrypt(v.as_ref())
+                self.decrypt(&v)
             } else {
-                String::from_utf8_lossy(v.as_ref()).to_string()
+                String::from_utf8_lossy(&v).to_string()
             };
 
             out.push((key, value));